@@ -1,9 +1,14 @@
 #![allow(clippy::arithmetic_side_effects)]
 use {
     criterion::{BatchSize, BenchmarkId, Criterion, Throughput, criterion_group, criterion_main},
+    rand::{SeedableRng, seq::SliceRandom},
+    rand_chacha::ChaChaRng,
     solana_account::{AccountSharedData, ReadableAccount},
     solana_accounts_db::{
+        StorageReadIo,
+        accounts_file::AccountsFile,
         append_vec::{self, AppendVec},
+        batched_storage_reader::{BatchedStorageReader, LOAD_BATCH_SIZE},
         utils::create_account_shared_data,
     },
     solana_clock::Slot,
@@ -192,10 +197,92 @@ fn bench_get_account_shared_data(c: &mut Criterion) {
     }
 }
 
+// Loads accounts at random offsets of a storage, as scans of the accounts index do, one by one
+// and in batches. Note that the storage is likely in the page cache, which favors the one by one
+// loads, except for direct IO.
+fn bench_load_accounts(c: &mut Criterion) {
+    let mut group = c.benchmark_group("load_accounts");
+    let temp_dir = tempfile::tempdir().unwrap();
+
+    // distribution of account data sizes to use when creating accounts
+    // 3% of accounts have no data
+    // 75% of accounts are 165 bytes (a token account)
+    // 20% of accounts are 200 bytes (a stake account)
+    // 2% of accounts are 16 kibibytes (larger than the first read of batched loads)
+    let data_sizes = [0, 165, 200, 1 << 14];
+    let weights = [3, 75, 20, 2];
+
+    for accounts_count in &ACCOUNTS_COUNTS[1..] {
+        let accounts_count = *accounts_count;
+        group.throughput(Throughput::Elements(accounts_count as u64));
+
+        let storable_accounts: Vec<_> = utils::accounts(255, &data_sizes, &weights)
+            .take(accounts_count)
+            .collect();
+
+        // create an append vec file
+        let append_vec_path = temp_dir.path().join(format!("append_vec_{accounts_count}"));
+        _ = std::fs::remove_file(&append_vec_path);
+        let file_size = storable_accounts
+            .iter()
+            .map(|(_, account)| AppendVec::calculate_stored_size(account.data().len()))
+            .sum();
+        let append_vec = AppendVec::new(append_vec_path, file_size);
+        let mut offsets = append_vec
+            .append_accounts(&(Slot::MAX, storable_accounts.as_slice()))
+            .unwrap()
+            .offsets;
+        assert_eq!(offsets.len(), accounts_count);
+        append_vec.flush().unwrap();
+        offsets.shuffle(&mut ChaChaRng::seed_from_u64(255));
+        // See `bench_scan_pubkeys()` for why the append vec is reopened and not dropped
+        let accounts_file = ManuallyDrop::new(AccountsFile::AppendVec(
+            AppendVec::new_from_file(append_vec.path(), append_vec.len())
+                .unwrap()
+                .0,
+        ));
+        let AccountsFile::AppendVec(append_vec_file) = &*accounts_file else {
+            unreachable!();
+        };
+
+        group.bench_function(BenchmarkId::new("sync", accounts_count), |b| {
+            b.iter_with_large_drop(|| {
+                offsets
+                    .iter()
+                    .map(|offset| append_vec_file.get_account_shared_data(*offset).unwrap())
+                    .collect::<Vec<_>>()
+            });
+        });
+
+        let to_load: Vec<_> = offsets
+            .iter()
+            .map(|offset| (&*accounts_file, *offset))
+            .collect();
+        for read_io in [StorageReadIo::IoUring, StorageReadIo::IoUringDirect] {
+            let mut reader = BatchedStorageReader::new(read_io).unwrap();
+            group.bench_function(BenchmarkId::new(read_io.as_str(), accounts_count), |b| {
+                b.iter_with_large_drop(|| {
+                    let mut accounts = vec![None; to_load.len()];
+                    for (batch_index, batch) in to_load.chunks(LOAD_BATCH_SIZE).enumerate() {
+                        let start = batch_index * LOAD_BATCH_SIZE;
+                        reader
+                            .load_accounts(batch, |index, account| {
+                                accounts[start + index] = Some(account.unwrap());
+                            })
+                            .unwrap();
+                    }
+                    accounts
+                });
+            });
+        }
+    }
+}
+
 criterion_group!(
     benches,
     bench_write_accounts_file,
     bench_scan_pubkeys,
     bench_get_account_shared_data,
+    bench_load_accounts,
 );
 criterion_main!(benches);
@@ -0,0 +1,714 @@
+#![allow(clippy::arithmetic_side_effects)]
+//! Reports how the account storages of a ledger are used.
+//!
+//! An account version is alive if no storage of a newer slot holds a version of the same pubkey.
+//! This matches what accounts-db keeps once clean has run, so the dead bytes reported here are
+//! what clean and shrink can reclaim.
+use {
+    agave_fs::FileInfo,
+    ahash::HashMap,
+    clap::{App, Arg, crate_description, crate_name, value_t, values_t},
+    rayon::prelude::*,
+    serde::Serialize,
+    solana_accounts_db::{accounts_db::DEFAULT_ACCOUNTS_SHRINK_RATIO, accounts_file::AccountsFile},
+    solana_clock::{DEFAULT_SLOTS_PER_EPOCH, Slot},
+    solana_pubkey::Pubkey,
+    std::{
+        fmt::Display,
+        fs, io,
+        path::{Path, PathBuf},
+        process::exit,
+        str::FromStr,
+    },
+};
+
+/// Default for how many slots the ancient boundary is moved forward, same as accounts-db
+const DEFAULT_ANCIENT_OFFSET: i64 = 100_000;
+
+fn is_parsable<T>(string: String) -> Result<(), String>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    string
+        .parse::<T>()
+        .map(|_| ())
+        .map_err(|err| format!("error parsing '{string}': {err}"))
+}
+
+fn main() {
+    let default_shrink_ratio = DEFAULT_ACCOUNTS_SHRINK_RATIO.to_string();
+    let default_ancient_offset = DEFAULT_ANCIENT_OFFSET.to_string();
+    let matches = App::new(crate_name!())
+        .about(crate_description!())
+        .version(solana_version::version!())
+        .arg(
+            Arg::with_name("ledger")
+                .index(1)
+                .takes_value(true)
+                .value_name("PATH")
+                .required_unless("accounts_path")
+                .help("Ledger path, the storages in <PATH>/accounts/run are inspected"),
+        )
+        .arg(
+            Arg::with_name("accounts_path")
+                .long("accounts-path")
+                .takes_value(true)
+                .value_name("PATH")
+                .multiple(true)
+                .number_of_values(1)
+                .help(
+                    "Account storage directory to inspect instead of the ledger's. May be \
+                     specified multiple times, e.g. to include the ancient storage tier",
+                ),
+        )
+        .arg(
+            Arg::with_name("offset")
+                .long("offset")
+                .takes_value(true)
+                .value_name("SLOT-OFFSET")
+                .validator(is_parsable::<i64>)
+                .default_value(&default_ancient_offset)
+                .help("ancient offset"),
+        )
+        .arg(
+            Arg::with_name("shrink_ratio")
+                .long("shrink-ratio")
+                .takes_value(true)
+                .value_name("RATIO")
+                .validator(is_parsable::<f64>)
+                .default_value(&default_shrink_ratio)
+                .help("Storages with a lower ratio of alive bytes are projected to be shrunk"),
+        )
+        .arg(
+            Arg::with_name("top")
+                .long("top")
+                .takes_value(true)
+                .value_name("NUM")
+                .validator(is_parsable::<usize>)
+                .default_value("20")
+                .help("Number of owners and duplicated pubkeys to report"),
+        )
+        .arg(
+            Arg::with_name("output_format")
+                .long("output")
+                .takes_value(true)
+                .value_name("FORMAT")
+                .possible_values(&["json", "json-compact"])
+                .help("Return information in specified output format"),
+        )
+        .arg(
+            Arg::with_name("verbose")
+                .short("v")
+                .long("verbose")
+                .takes_value(false)
+                .help("Show the report of every storage"),
+        )
+        .get_matches();
+
+    let dirs = values_t!(matches, "accounts_path", PathBuf).unwrap_or_else(|_| {
+        let ledger = value_t!(matches, "ledger", PathBuf).unwrap();
+        vec![ledger.join("accounts").join("run")]
+    });
+    let options = InspectOptions {
+        ancient_offset: value_t!(matches, "offset", i64).unwrap(),
+        shrink_ratio: value_t!(matches, "shrink_ratio", f64).unwrap(),
+        top: value_t!(matches, "top", usize).unwrap(),
+    };
+
+    let report = inspect(&dirs, &options).unwrap_or_else(|err| {
+        eprintln!("Error: {err}");
+        exit(1);
+    });
+    match matches.value_of("output_format") {
+        Some("json") => println!("{}", serde_json::to_string_pretty(&report).unwrap()),
+        Some("json-compact") => println!("{}", serde_json::to_string(&report).unwrap()),
+        _ => print_report(&report, matches.is_present("verbose")),
+    }
+}
+
+struct InspectOptions {
+    /// Moves the ancient boundary, see `AccountsDbConfig::ancient_append_vec_offset`
+    ancient_offset: i64,
+    /// Storages with a lower ratio of alive bytes are shrink candidates
+    shrink_ratio: f64,
+    /// Number of owners and duplicated pubkeys to report
+    top: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct Report {
+    num_storages: usize,
+    num_unreadable_storages: usize,
+    lowest_slot: Slot,
+    highest_slot: Slot,
+    /// Storages of older slots are ancient
+    oldest_non_ancient_slot: Slot,
+    total: StoragesSummary,
+    ancient: StoragesSummary,
+    non_ancient: StoragesSummary,
+    shrink: ShrinkSummary,
+    duplicates: DuplicatesSummary,
+    /// Owners with the most bytes stored
+    top_owners: Vec<OwnerReport>,
+    storages: Vec<StorageReport>,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct StoragesSummary {
+    num_storages: usize,
+    file_size: u64,
+    num_accounts: usize,
+    num_alive_accounts: usize,
+    num_zero_lamport_accounts: usize,
+    alive_bytes: u64,
+    dead_bytes: u64,
+}
+
+impl StoragesSummary {
+    fn add(&mut self, storage: &StorageReport) {
+        self.num_storages += 1;
+        self.file_size += storage.file_size;
+        self.num_accounts += storage.num_accounts;
+        self.num_alive_accounts += storage.num_alive_accounts;
+        self.num_zero_lamport_accounts += storage.num_zero_lamport_accounts;
+        self.alive_bytes += storage.alive_bytes;
+        self.dead_bytes += storage.dead_bytes;
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ShrinkSummary {
+    shrink_ratio: f64,
+    num_candidates: usize,
+    /// Bytes freed on disk by shrinking all candidates
+    projected_savings: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct DuplicatesSummary {
+    num_pubkeys: usize,
+    /// Pubkeys with versions in more than one slot
+    num_duplicated_pubkeys: usize,
+    /// Versions that are not the newest version of their pubkey
+    num_dead_versions: usize,
+    /// Pubkeys with the most versions
+    top_pubkeys: Vec<DuplicatedPubkeyReport>,
+}
+
+#[derive(Debug, Serialize)]
+struct DuplicatedPubkeyReport {
+    pubkey: String,
+    num_versions: usize,
+    newest_slot: Slot,
+}
+
+#[derive(Debug, Serialize)]
+struct OwnerReport {
+    owner: String,
+    num_accounts: usize,
+    num_alive_accounts: usize,
+    bytes: u64,
+    alive_bytes: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct StorageReport {
+    path: PathBuf,
+    slot: Slot,
+    id: usize,
+    format: &'static str,
+    file_size: u64,
+    num_accounts: usize,
+    num_alive_accounts: usize,
+    num_zero_lamport_accounts: usize,
+    num_alive_zero_lamport_accounts: usize,
+    /// Stored size of the alive accounts, i.e. the size of the storage after shrinking
+    alive_bytes: u64,
+    dead_bytes: u64,
+    alive_ratio: f64,
+    is_ancient: bool,
+    is_shrink_candidate: bool,
+    projected_shrink_savings: u64,
+}
+
+/// The versions of a pubkey across all storages
+#[derive(Debug, Default, Clone, Copy)]
+struct Versions {
+    newest_slot: Slot,
+    num_versions: usize,
+}
+
+impl Versions {
+    fn add(&mut self, other: Self) {
+        self.newest_slot = self.newest_slot.max(other.newest_slot);
+        self.num_versions += other.num_versions;
+    }
+}
+
+#[derive(Debug, Default)]
+struct OwnerStats {
+    num_accounts: usize,
+    num_alive_accounts: usize,
+    bytes: u64,
+    alive_bytes: u64,
+}
+
+/// A storage file found in one of the inspected directories
+#[derive(Debug)]
+struct StorageFile {
+    path: PathBuf,
+    slot: Slot,
+    id: usize,
+}
+
+impl StorageFile {
+    /// Opens the storage, read-only
+    fn open(&self) -> Option<(AccountsFile, u64)> {
+        FileInfo::new_from_path(&self.path)
+            .and_then(|file_info| {
+                let file_size = file_info.size;
+                let storage = AccountsFile::new_for_startup(file_info).map_err(io::Error::other)?;
+                // By default, when the storage is dropped, the backing file will be removed.
+                // We do not want to remove the backing file here, so prevent it.
+                storage.disable_remove_on_drop();
+                Ok((storage, file_size))
+            })
+            .inspect_err(|err| {
+                eprintln!(
+                    "failed to open account storage file '{}': {err}",
+                    self.path.display(),
+                )
+            })
+            .ok()
+    }
+}
+
+/// Returns the storage files in `dirs`, sorted by slot
+fn find_storages(dirs: &[PathBuf]) -> Result<Vec<StorageFile>, String> {
+    fn parse_file_name(path: &Path) -> Option<(Slot, usize)> {
+        let (slot, id) = path.file_name()?.to_str()?.split_once('.')?;
+        Some((slot.parse().ok()?, id.parse().ok()?))
+    }
+
+    let mut storages = Vec::new();
+    for dir in dirs {
+        let entries = fs::read_dir(dir)
+            .map_err(|err| format!("failed to read dir '{}': {err}", dir.display()))?;
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if !path.is_file() {
+                continue;
+            }
+            if let Some((slot, id)) = parse_file_name(&path) {
+                storages.push(StorageFile { path, slot, id });
+            }
+        }
+    }
+    storages.sort_unstable_by_key(|storage| (storage.slot, storage.id));
+    Ok(storages)
+}
+
+fn inspect(dirs: &[PathBuf], options: &InspectOptions) -> Result<Report, String> {
+    let storages = find_storages(dirs)?;
+    let (Some(lowest_slot), Some(highest_slot)) = (
+        storages.first().map(|storage| storage.slot),
+        storages.last().map(|storage| storage.slot),
+    ) else {
+        return Err(format!("no account storages found in {dirs:?}"));
+    };
+    // same as `AccountsDb::get_oldest_non_ancient_slot()`, with the highest slot as max root
+    let oldest_non_ancient_slot = highest_slot
+        .saturating_add_signed(options.ancient_offset)
+        .saturating_sub(DEFAULT_SLOTS_PER_EPOCH - 1)
+        .min(highest_slot);
+
+    // Find the newest version of every pubkey first, which decides what is alive
+    let versions = storages
+        .par_iter()
+        .fold(
+            HashMap::<Pubkey, Versions>::default,
+            |mut versions, storage| {
+                if let Some((accounts_file, _)) = storage.open() {
+                    accounts_file
+                        .scan_pubkeys(|pubkey| {
+                            versions.entry(*pubkey).or_default().add(Versions {
+                                newest_slot: storage.slot,
+                                num_versions: 1,
+                            });
+                        })
+                        .unwrap_or_else(|err| {
+                            eprintln!(
+                                "failed to scan accounts in file '{}': {err}",
+                                storage.path.display(),
+                            )
+                        });
+                }
+                versions
+            },
+        )
+        .reduce(HashMap::default, |mut versions, other| {
+            if versions.len() < other.len() {
+                return merge_versions(other, versions);
+            }
+            merge_versions(versions, other)
+        });
+
+    let results: Vec<_> = storages
+        .par_iter()
+        .map(|storage| {
+            let is_ancient = storage.slot < oldest_non_ancient_slot;
+            inspect_storage(storage, &versions, is_ancient, options.shrink_ratio)
+        })
+        .collect();
+
+    let mut total = StoragesSummary::default();
+    let mut ancient = StoragesSummary::default();
+    let mut non_ancient = StoragesSummary::default();
+    let mut num_shrink_candidates = 0;
+    let mut projected_shrink_savings = 0;
+    let mut owners = HashMap::<Pubkey, OwnerStats>::default();
+    let mut storage_reports = Vec::with_capacity(results.len());
+    for (storage, storage_owners) in results.into_iter().flatten() {
+        total.add(&storage);
+        if storage.is_ancient {
+            ancient.add(&storage);
+        } else {
+            non_ancient.add(&storage);
+        }
+        if storage.is_shrink_candidate {
+            num_shrink_candidates += 1;
+            projected_shrink_savings += storage.projected_shrink_savings;
+        }
+        for (owner, stats) in storage_owners {
+            let owner_stats = owners.entry(owner).or_default();
+            owner_stats.num_accounts += stats.num_accounts;
+            owner_stats.num_alive_accounts += stats.num_alive_accounts;
+            owner_stats.bytes += stats.bytes;
+            owner_stats.alive_bytes += stats.alive_bytes;
+        }
+        storage_reports.push(storage);
+    }
+
+    let mut top_owners: Vec<_> = owners.into_iter().collect();
+    top_owners.sort_unstable_by(|(owner_a, a), (owner_b, b)| {
+        b.bytes.cmp(&a.bytes).then(owner_a.cmp(owner_b))
+    });
+    let top_owners = top_owners
+        .into_iter()
+        .take(options.top)
+        .map(|(owner, stats)| OwnerReport {
+            owner: owner.to_string(),
+            num_accounts: stats.num_accounts,
+            num_alive_accounts: stats.num_alive_accounts,
+            bytes: stats.bytes,
+            alive_bytes: stats.alive_bytes,
+        })
+        .collect();
+
+    let mut duplicated: Vec<_> = versions
+        .iter()
+        .filter(|(_, versions)| versions.num_versions > 1)
+        .collect();
+    let num_dead_versions = duplicated
+        .iter()
+        .map(|(_, versions)| versions.num_versions - 1)
+        .sum();
+    let num_duplicated_pubkeys = duplicated.len();
+    duplicated.sort_unstable_by(|(pubkey_a, a), (pubkey_b, b)| {
+        b.num_versions
+            .cmp(&a.num_versions)
+            .then(pubkey_a.cmp(pubkey_b))
+    });
+    let top_pubkeys = duplicated
+        .into_iter()
+        .take(options.top)
+        .map(|(pubkey, versions)| DuplicatedPubkeyReport {
+            pubkey: pubkey.to_string(),
+            num_versions: versions.num_versions,
+            newest_slot: versions.newest_slot,
+        })
+        .collect();
+
+    Ok(Report {
+        num_storages: storages.len(),
+        num_unreadable_storages: storages.len() - storage_reports.len(),
+        lowest_slot,
+        highest_slot,
+        oldest_non_ancient_slot,
+        total,
+        ancient,
+        non_ancient,
+        shrink: ShrinkSummary {
+            shrink_ratio: options.shrink_ratio,
+            num_candidates: num_shrink_candidates,
+            projected_savings: projected_shrink_savings,
+        },
+        duplicates: DuplicatesSummary {
+            num_pubkeys: versions.len(),
+            num_duplicated_pubkeys,
+            num_dead_versions,
+            top_pubkeys,
+        },
+        top_owners,
+        storages: storage_reports,
+    })
+}
+
+/// Merges the smaller map of versions into the larger one
+fn merge_versions(
+    mut versions: HashMap<Pubkey, Versions>,
+    other: HashMap<Pubkey, Versions>,
+) -> HashMap<Pubkey, Versions> {
+    for (pubkey, other) in other {
+        versions.entry(pubkey).or_default().add(other);
+    }
+    versions
+}
+
+/// Inspects the accounts in `storage`, returning its report and the stats of its owners
+fn inspect_storage(
+    storage: &StorageFile,
+    versions: &HashMap<Pubkey, Versions>,
+    is_ancient: bool,
+    shrink_ratio: f64,
+) -> Option<(StorageReport, HashMap<Pubkey, OwnerStats>)> {
+    let (accounts_file, file_size) = storage.open()?;
+    let format = match &accounts_file {
+        AccountsFile::AppendVec(_) => "append-vec",
+        AccountsFile::ColdStorage(_) => "cold-storage",
+    };
+
+    let mut report = StorageReport {
+        path: storage.path.clone(),
+        slot: storage.slot,
+        id: storage.id,
+        format,
+        file_size,
+        num_accounts: 0,
+        num_alive_accounts: 0,
+        num_zero_lamport_accounts: 0,
+        num_alive_zero_lamport_accounts: 0,
+        alive_bytes: 0,
+        dead_bytes: 0,
+        alive_ratio: 0.0,
+        is_ancient,
+        is_shrink_candidate: false,
+        projected_shrink_savings: 0,
+    };
+    let mut owners = HashMap::<Pubkey, OwnerStats>::default();
+    accounts_file
+        .scan_accounts_without_data(|_offset, account| {
+            let stored_size = accounts_file.calculate_stored_size(account.data_len) as u64;
+            let is_alive = versions
+                .get(account.pubkey)
+                .is_some_and(|versions| versions.newest_slot == storage.slot);
+            let is_zero_lamport = account.lamports == 0;
+
+            report.num_accounts += 1;
+            report.num_zero_lamport_accounts += usize::from(is_zero_lamport);
+            let owner_stats = owners.entry(*account.owner).or_default();
+            owner_stats.num_accounts += 1;
+            owner_stats.bytes += stored_size;
+            if is_alive {
+                report.num_alive_accounts += 1;
+                report.num_alive_zero_lamport_accounts += usize::from(is_zero_lamport);
+                report.alive_bytes += stored_size;
+                owner_stats.num_alive_accounts += 1;
+                owner_stats.alive_bytes += stored_size;
+            } else {
+                report.dead_bytes += stored_size;
+            }
+        })
+        .inspect_err(|err| {
+            eprintln!(
+                "failed to scan accounts in file '{}': {err}",
+                storage.path.display(),
+            )
+        })
+        .ok()?;
+
+    let stored_bytes = report.alive_bytes + report.dead_bytes;
+    if stored_bytes > 0 {
+        report.alive_ratio = report.alive_bytes as f64 / stored_bytes as f64;
+        report.is_shrink_candidate = report.dead_bytes > 0 && report.alive_ratio < shrink_ratio;
+    }
+    if report.is_shrink_candidate {
+        // scale by the file size, which is smaller than the stored size for cold storages
+        report.projected_shrink_savings =
+            (file_size as u128 * report.dead_bytes as u128 / stored_bytes as u128) as u64;
+    }
+    Some((report, owners))
+}
+
+fn print_summary(name: &str, summary: &StoragesSummary) {
+    println!(
+        "{name}: storages: {}, file size: {}, accounts: {}, alive accounts: {}, zero lamport \
+         accounts: {}, alive bytes: {}, dead bytes: {}",
+        summary.num_storages,
+        summary.file_size,
+        summary.num_accounts,
+        summary.num_alive_accounts,
+        summary.num_zero_lamport_accounts,
+        summary.alive_bytes,
+        summary.dead_bytes,
+    );
+}
+
+fn print_report(report: &Report, verbose: bool) {
+    if verbose {
+        for storage in &report.storages {
+            println!(
+                "{}: slot: {}, format: {}, file size: {}, accounts: {}, alive: {}, zero lamport: \
+                 {}, alive bytes: {}, dead bytes: {}, alive ratio: {:.3}, ancient: {}, shrink \
+                 savings: {}",
+                storage.path.display(),
+                storage.slot,
+                storage.format,
+                storage.file_size,
+                storage.num_accounts,
+                storage.num_alive_accounts,
+                storage.num_zero_lamport_accounts,
+                storage.alive_bytes,
+                storage.dead_bytes,
+                storage.alive_ratio,
+                storage.is_ancient,
+                storage.projected_shrink_savings,
+            );
+        }
+        println!();
+    }
+
+    println!(
+        "storages: {}, unreadable: {}, slots: {}..={}, oldest non-ancient slot: {}",
+        report.num_storages,
+        report.num_unreadable_storages,
+        report.lowest_slot,
+        report.highest_slot,
+        report.oldest_non_ancient_slot,
+    );
+    print_summary("total", &report.total);
+    print_summary("ancient", &report.ancient);
+    print_summary("non-ancient", &report.non_ancient);
+    println!(
+        "shrink: ratio: {}, candidates: {}, projected savings: {}",
+        report.shrink.shrink_ratio, report.shrink.num_candidates, report.shrink.projected_savings,
+    );
+    println!(
+        "duplicates: pubkeys: {}, duplicated pubkeys: {}, dead versions: {}",
+        report.duplicates.num_pubkeys,
+        report.duplicates.num_duplicated_pubkeys,
+        report.duplicates.num_dead_versions,
+    );
+
+    println!("\ntop owners by bytes:");
+    for owner in &report.top_owners {
+        println!(
+            "  {:44} accounts: {}, alive accounts: {}, bytes: {}, alive bytes: {}",
+            owner.owner,
+            owner.num_accounts,
+            owner.num_alive_accounts,
+            owner.bytes,
+            owner.alive_bytes,
+        );
+    }
+    println!("\ntop duplicated pubkeys:");
+    for pubkey in &report.duplicates.top_pubkeys {
+        println!(
+            "  {:44} versions: {}, newest slot: {}",
+            pubkey.pubkey, pubkey.num_versions, pubkey.newest_slot,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        solana_account::{AccountSharedData, ReadableAccount},
+        solana_accounts_db::{accounts_file::AccountsFileProvider, append_vec::AppendVec},
+        tempfile::TempDir,
+    };
+
+    fn write_storage(dir: &Path, slot: Slot, accounts: &[(Pubkey, AccountSharedData)]) {
+        let file_size = accounts
+            .iter()
+            .map(|(_, account)| AppendVec::calculate_stored_size(account.data().len()))
+            .sum::<usize>();
+        let storage = AccountsFileProvider::AppendVec
+            .new_writable(dir.join(AccountsFile::file_name(slot, 0)), file_size as u64);
+        storage.disable_remove_on_drop();
+        storage.write_accounts(&(slot, accounts)).unwrap().unwrap();
+        storage.flush().unwrap();
+    }
+
+    #[test]
+    fn test_inspect() {
+        let dir = TempDir::new().unwrap();
+        let owner = Pubkey::new_unique();
+        let pubkey_a = Pubkey::new_unique();
+        let pubkey_b = Pubkey::new_unique();
+        let account = |lamports, data_len| AccountSharedData::new(lamports, data_len, &owner);
+        write_storage(
+            dir.path(),
+            1,
+            &[(pubkey_a, account(1, 100)), (pubkey_b, account(1, 10))],
+        );
+        write_storage(
+            dir.path(),
+            DEFAULT_SLOTS_PER_EPOCH + 2,
+            &[(pubkey_a, account(0, 0))],
+        );
+        // files that are not storages are ignored
+        fs::write(dir.path().join("not-a-storage"), b"").unwrap();
+
+        let options = InspectOptions {
+            ancient_offset: 0,
+            shrink_ratio: DEFAULT_ACCOUNTS_SHRINK_RATIO,
+            top: 10,
+        };
+        let report = inspect(&[dir.path().to_path_buf()], &options).unwrap();
+
+        assert_eq!(report.num_storages, 2);
+        assert_eq!(report.num_unreadable_storages, 0);
+        assert_eq!(report.oldest_non_ancient_slot, 3);
+        assert_eq!(report.ancient.num_storages, 1);
+        assert_eq!(report.non_ancient.num_storages, 1);
+
+        let old_storage = &report.storages[0];
+        assert_eq!(old_storage.slot, 1);
+        assert_eq!(old_storage.format, "append-vec");
+        assert!(old_storage.is_ancient);
+        assert_eq!(old_storage.num_accounts, 2);
+        assert_eq!(old_storage.num_alive_accounts, 1);
+        assert_eq!(
+            old_storage.alive_bytes,
+            AppendVec::calculate_stored_size(10) as u64
+        );
+        assert_eq!(
+            old_storage.dead_bytes,
+            AppendVec::calculate_stored_size(100) as u64
+        );
+        assert!(old_storage.is_shrink_candidate);
+        assert_eq!(old_storage.projected_shrink_savings, old_storage.dead_bytes);
+
+        let new_storage = &report.storages[1];
+        assert!(!new_storage.is_ancient);
+        assert_eq!(new_storage.num_alive_accounts, 1);
+        assert_eq!(new_storage.num_alive_zero_lamport_accounts, 1);
+        assert!(!new_storage.is_shrink_candidate);
+
+        assert_eq!(report.total.num_zero_lamport_accounts, 1);
+        assert_eq!(report.shrink.num_candidates, 1);
+        assert_eq!(report.duplicates.num_pubkeys, 2);
+        assert_eq!(report.duplicates.num_duplicated_pubkeys, 1);
+        assert_eq!(report.duplicates.num_dead_versions, 1);
+        assert_eq!(
+            report.duplicates.top_pubkeys[0].pubkey,
+            pubkey_a.to_string()
+        );
+        assert_eq!(report.top_owners.len(), 1);
+        assert_eq!(report.top_owners[0].num_accounts, 3);
+        assert_eq!(report.top_owners[0].num_alive_accounts, 2);
+    }
+}
@@ -339,6 +339,21 @@ impl ShrinkInProgress<'_> {
     pub(crate) fn old_storage(&self) -> &Arc<AccountStorageEntry> {
         &self.old_store
     }
+
+    /// Replaces the new store with `new_store`.
+    /// Only valid before the index refers to any account in the new store.
+    pub(crate) fn replace_new_storage(&mut self, new_store: Arc<AccountStorageEntry>) {
+        assert_eq!(new_store.slot(), self.slot);
+        assert!(
+            self.storage
+                .shrink_in_progress_map
+                .write()
+                .unwrap()
+                .insert(self.slot, Arc::clone(&new_store))
+                .is_some()
+        );
+        self.new_store = new_store;
+    }
 }
 
 /// Wrapper over slice of `Arc<AccountStorageEntry>` that provides an ordered access to storages.
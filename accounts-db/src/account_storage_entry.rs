@@ -5,6 +5,7 @@ use {
         accounts_db::AccountsFileId,
         accounts_file::{AccountsFile, AccountsFileError, AccountsFileProvider},
         obsolete_accounts::ObsoleteAccounts,
+        storage_placement::{StoragePlacement, StorageTier},
     },
     agave_fs::buffered_reader::RequiredLenBufFileRead,
     solana_clock::Slot,
@@ -12,7 +13,7 @@ use {
     std::{
         path::Path,
         sync::{
-            RwLock, RwLockReadGuard,
+            OnceLock, RwLock, RwLockReadGuard,
             atomic::{AtomicUsize, Ordering},
         },
     },
@@ -49,6 +50,10 @@ pub struct AccountStorageEntry {
     /// 2. The account was set to zero lamports and is older than the last
     ///    full snapshot. In this case, slot is set to the snapshot slot
     pub(crate) obsolete_accounts: RwLock<ObsoleteAccounts>,
+
+    /// The tier this storage is placed on. Set when the storage is created or moved, and
+    /// looked up from its path on first use for storages opened at startup.
+    tier: OnceLock<StorageTier>,
 }
 
 impl AccountStorageEntry {
@@ -71,6 +76,7 @@ impl AccountStorageEntry {
             num_alive_bytes: AtomicUsize::new(0),
             tombstone_offsets: RwLock::default(),
             obsolete_accounts: RwLock::default(),
+            tier: OnceLock::new(),
         }
     }
 
@@ -84,9 +90,39 @@ impl AccountStorageEntry {
             accounts,
             tombstone_offsets: RwLock::new(self.tombstone_offsets.read().unwrap().clone()),
             obsolete_accounts: RwLock::new(self.obsolete_accounts.read().unwrap().clone()),
+            tier: self.tier.clone(),
         })
     }
 
+    /// Returns a storage over `accounts`, a copy of this storage's file placed on `tier`.
+    ///
+    /// The new storage carries over the counts, tombstones and obsolete accounts of this
+    /// storage, so it can replace this storage in place. Accounts removed from this storage
+    /// after this call are not carried over.
+    pub(crate) fn with_copied_accounts(&self, accounts: AccountsFile, tier: StorageTier) -> Self {
+        Self {
+            id: self.id,
+            slot: self.slot,
+            num_alive_accounts: AtomicUsize::new(self.count()),
+            num_alive_bytes: AtomicUsize::new(self.alive_bytes()),
+            accounts,
+            tombstone_offsets: RwLock::new(self.tombstone_offsets.read().unwrap().clone()),
+            obsolete_accounts: RwLock::new(self.obsolete_accounts.read().unwrap().clone()),
+            tier: OnceLock::from(tier),
+        }
+    }
+
+    /// Sets the tier this storage is placed on
+    pub(crate) fn with_tier(self, tier: StorageTier) -> Self {
+        self.tier.set(tier).unwrap();
+        self
+    }
+
+    /// Returns the tier this storage is placed on
+    pub(crate) fn tier(&self, placement: &StoragePlacement) -> StorageTier {
+        *self.tier.get_or_init(|| placement.tier_of(self.path()))
+    }
+
     pub fn new_existing(
         slot: Slot,
         id: AccountsFileId,
@@ -101,6 +137,7 @@ impl AccountStorageEntry {
             num_alive_bytes: AtomicUsize::new(0),
             tombstone_offsets: RwLock::default(),
             obsolete_accounts: RwLock::new(obsolete_accounts),
+            tier: OnceLock::new(),
         }
     }
 
@@ -241,7 +278,7 @@ impl AccountStorageEntry {
     }
 
     /// Collect the offsets that should be excluded from scans
-    fn excluded_offsets(&self) -> IntSet<Offset> {
+    pub(crate) fn excluded_offsets(&self) -> IntSet<Offset> {
         let mut offsets: IntSet<_> = self
             .obsolete_accounts_read_lock()
             .filter_obsolete_accounts(None)
@@ -337,6 +374,7 @@ mod tests {
             .accounts
             .write_accounts(&(slot, &accounts[..]))
             .unwrap()
+            .unwrap()
             .offsets;
 
         // Mark account 1 obsolete and record account 3 as a tombstone.
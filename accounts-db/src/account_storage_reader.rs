@@ -1,7 +1,7 @@
 use {
     crate::{
         account_info::Offset, account_storage_entry::AccountStorageEntry,
-        accounts_file::OpenFileForArchive,
+        accounts_file::OpenFileForArchive, cold_storage::AppendVecEncoder,
     },
     agave_fs::{
         buffered_reader::{self, FileBufRead},
@@ -78,9 +78,13 @@ pub enum TombstonesFilter {
 /// The caller is responsible for activating the storage's file on `file_reader`
 /// via `set_file` (typically using a file opened with [`open_storage_files`])
 /// before constructing the reader.
+///
+/// Storages in other formats are re-encoded as AppendVecs while being read, and
+/// `file_reader` is left untouched.
 pub struct AccountStorageReader<'r, R> {
     sorted_excluded_accounts: Vec<(Offset, usize)>,
     reader: &'r mut R,
+    encoder: Option<AppendVecEncoder<'r>>,
     num_alive_bytes: usize,
     num_total_bytes: usize,
 }
@@ -92,7 +96,7 @@ impl<'a, 'r, R: FileBufRead<'a>> AccountStorageReader<'r, R> {
     /// Expects that the caller has already attached the storage's file to
     /// `file_reader` via `set_file`.
     pub fn new(
-        storage: &AccountStorageEntry,
+        storage: &'r AccountStorageEntry,
         snapshot_slot: Option<Slot>,
         tombstones_filter: TombstonesFilter,
         file_reader: &'r mut R,
@@ -128,9 +132,16 @@ impl<'a, 'r, R: FileBufRead<'a>> AccountStorageReader<'r, R> {
         sorted_excluded_accounts
             .sort_unstable_by(|(a_offset, _), (b_offset, _)| b_offset.cmp(a_offset));
 
+        let encoder = storage.accounts.append_vec_encoder(
+            sorted_excluded_accounts
+                .iter()
+                .map(|(offset, _size)| *offset),
+        );
+
         Ok(Self {
             sorted_excluded_accounts,
             reader: file_reader,
+            encoder,
             num_alive_bytes,
             num_total_bytes,
         })
@@ -147,6 +158,10 @@ impl<'a, 'r, R: FileBufRead<'a>> AccountStorageReader<'r, R> {
 
 impl<'a, R: FileBufRead<'a>> Read for AccountStorageReader<'_, R> {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if let Some(encoder) = &mut self.encoder {
+            return encoder.read(buf);
+        }
+
         let mut total_read = 0;
         let buf_len = buf.len();
 
@@ -224,6 +239,7 @@ mod tests {
     }
 
     #[test_case(AccountsFileProvider::AppendVec)]
+    #[test_case(AccountsFileProvider::ColdStorage)]
     fn test_account_storage_reader_no_obsolete_accounts(provider: AccountsFileProvider) {
         let (storage, _temp_dirs) = create_storage_for_storage_reader(0, provider);
 
@@ -236,7 +252,10 @@ mod tests {
             (&Pubkey::new_unique(), &account2),
         ];
 
-        storage.accounts.write_accounts(&(slot, &accounts[..]));
+        storage
+            .accounts
+            .write_accounts(&(slot, &accounts[..]))
+            .unwrap();
 
         let files = open_storage_files(iter::once(&storage), false)
             .collect::<io::Result<Vec<_>>>()
@@ -248,7 +267,10 @@ mod tests {
         )
         .unwrap();
         buf_reader
-            .set_file(files[0].as_ref(), storage.accounts.len() as u64)
+            .set_file(
+                files[0].as_ref(),
+                storage.accounts.archive_read_limit() as u64,
+            )
             .unwrap();
         let reader =
             AccountStorageReader::new(&storage, None, TombstonesFilter::Include, &mut buf_reader)
@@ -276,8 +298,44 @@ mod tests {
         num_obsolete: usize,
         tombstones_filter: TombstonesFilter,
     ) {
-        let (storage, _temp_dirs) =
-            create_storage_for_storage_reader(0, AccountsFileProvider::AppendVec);
+        run_test_account_storage_reader_with_excluded_accounts(
+            total_accounts,
+            num_tombstones,
+            num_obsolete,
+            tombstones_filter,
+            AccountsFileProvider::AppendVec,
+        );
+    }
+
+    /// Cold storages are re-encoded as append vecs, which must come out the same as if the
+    /// accounts had been stored in an append vec to begin with.
+    #[test_case(100, 0, 0, TombstonesFilter::Include)]
+    #[test_case(100, 10, 10, TombstonesFilter::Include)]
+    #[test_case(100, 10, 10, TombstonesFilter::Exclude)]
+    #[test_case(100, 100, 0, TombstonesFilter::Exclude)]
+    fn test_account_storage_reader_with_excluded_accounts_cold_storage(
+        total_accounts: usize,
+        num_tombstones: usize,
+        num_obsolete: usize,
+        tombstones_filter: TombstonesFilter,
+    ) {
+        run_test_account_storage_reader_with_excluded_accounts(
+            total_accounts,
+            num_tombstones,
+            num_obsolete,
+            tombstones_filter,
+            AccountsFileProvider::ColdStorage,
+        );
+    }
+
+    fn run_test_account_storage_reader_with_excluded_accounts(
+        total_accounts: usize,
+        num_tombstones: usize,
+        num_obsolete: usize,
+        tombstones_filter: TombstonesFilter,
+        provider: AccountsFileProvider,
+    ) {
+        let (storage, _temp_dirs) = create_storage_for_storage_reader(0, provider);
 
         let slot = 0;
 
@@ -317,6 +375,7 @@ mod tests {
         let offsets = storage
             .accounts
             .write_accounts(&(slot, &accounts_to_append[..]))
+            .unwrap()
             .map(|stored_accounts_info| stored_accounts_info.offsets)
             .unwrap_or_default();
 
@@ -352,7 +411,10 @@ mod tests {
         )
         .unwrap();
         file_reader
-            .set_file(files[0].as_ref(), storage.accounts.len() as u64)
+            .set_file(
+                files[0].as_ref(),
+                storage.accounts.archive_read_limit() as u64,
+            )
             .unwrap();
         let mut reader =
             AccountStorageReader::new(&storage, None, tombstones_filter, &mut file_reader).unwrap();
@@ -421,7 +483,8 @@ mod tests {
 
         let offsets = storage
             .accounts
-            .write_accounts(&(slot, &accounts_to_append[..]));
+            .write_accounts(&(slot, &accounts_to_append[..]))
+            .unwrap();
 
         // Generate a seed from entropy and log the original seed
         let seed: u64 = rand::random();
@@ -483,7 +546,10 @@ mod tests {
         .unwrap();
         for snapshot_slot in 0..slot_marked_dead {
             file_reader
-                .set_file(files[0].as_ref(), storage.accounts.len() as u64)
+                .set_file(
+                    files[0].as_ref(),
+                    storage.accounts.archive_read_limit() as u64,
+                )
                 .unwrap();
             let mut reader = AccountStorageReader::new(
                 &storage,
@@ -210,6 +210,36 @@ impl Accounts {
         )
     }
 
+    /// same as `load_with_fixed_root` for each of `pubkeys`, except the loads from storage are
+    /// batched
+    pub fn load_many_with_fixed_root(
+        &self,
+        ancestors: &Ancestors,
+        pubkeys: &[Pubkey],
+    ) -> Vec<Option<(AccountSharedData, Slot)>> {
+        self.accounts_db.load_many(
+            ancestors,
+            pubkeys,
+            LoadHint::FixedMaxRoot,
+            PopulateReadCache::True,
+        )
+    }
+
+    /// same as `load_without_fixed_root` for each of `pubkeys`, except the loads from storage are
+    /// batched
+    pub fn load_many_without_fixed_root(
+        &self,
+        ancestors: &Ancestors,
+        pubkeys: &[Pubkey],
+    ) -> Vec<Option<(AccountSharedData, Slot)>> {
+        self.accounts_db.load_many(
+            ancestors,
+            pubkeys,
+            LoadHint::Unspecified,
+            PopulateReadCache::True,
+        )
+    }
+
     /// scans underlying accounts_db for this delta (slot) with a map function
     ///   from LoadedAccount to B
     /// returns only the latest/current version of B for this slot
@@ -19,15 +19,11 @@
 //! commit for each slot entry would be indexed.
 
 mod accounts_db_config;
+mod forked_view;
 mod geyser_plugin_utils;
 pub(crate) mod stats;
 pub(crate) mod tests;
 
-pub use accounts_db_config::{
-    ACCOUNTS_DB_CONFIG_FOR_BENCHMARKS, ACCOUNTS_DB_CONFIG_FOR_TESTING, AccountsDbConfig,
-};
-#[cfg(feature = "dev-context-only-utils")]
-use qualifier_attr::qualifiers;
 use {
     crate::{
         account_info::{AccountInfo, Offset, StorageLocation},
@@ -43,7 +39,7 @@ use {
             StoreAccountsForFlushStats, StoreAccountsForShrinkStats, StoreAccountsForSquashStats,
             StoreAccountsUnfrozenStats, WriteAccountsToCacheStats,
         },
-        accounts_file::AccountsFileProvider,
+        accounts_file::{AccountsFileError, AccountsFileProvider},
         accounts_hash::{AccountLtHash, AccountsLtHash, ZERO_LAMPORT_ACCOUNT_LT_HASH},
         accounts_index::{
             AccountSecondaryIndexes, AccountsIndex, IndexKey, ReclaimsSlotList,
@@ -52,23 +48,27 @@ use {
         },
         accounts_scan::{ScanConfig, ScanError, ScanGuard, ScanResult, ScanTracker},
         accounts_update_notifier_interface::{AccountForGeyser, AccountsUpdateNotifier},
+        accounts_verification_progress::{AccountsVerificationProgress, VerificationBatch},
         active_stats::{ActiveStatItem, ActiveStats},
         ancestors::Ancestors,
         append_vec::{self, AppendVec},
+        batched_storage_reader::{BatchedStorageReader, LOAD_BATCH_SIZE, StorageReadIo},
         contains::Contains,
         is_zero_lamport::IsZeroLamport,
         partitioned_rewards::PartitionedEpochRewardsConfig,
         read_only_accounts_cache::ReadOnlyAccountsCache,
         storable_accounts::{StorableAccounts, StorableAccountsBySlot},
+        storage_placement::{StoragePlacement, StorageTier},
+        storage_scan_cache::StorageScanCache,
         u64_align,
         utils::{self, create_account_shared_data},
+        write_telemetry::{SampledWrite, WriteTelemetry, WriteTelemetryReport},
     },
     agave_fs::buffered_reader::RequiredLenBufFileRead,
     ahash::{HashMapExt as _, HashSetExt as _},
     bv::BitVec,
     dashmap::DashMap,
     log::*,
-    rand::{Rng, rng},
     rayon::{ThreadPool, prelude::*},
     seqlock::SeqLock,
     solana_account::{Account, AccountSharedData, ReadableAccount},
@@ -86,7 +86,7 @@ use {
         borrow::Cow,
         boxed::Box,
         collections::{BTreeSet, HashSet, VecDeque},
-        io, iter, mem,
+        fmt, io, iter, mem,
         num::Saturating,
         ops::RangeBounds,
         path::{Path, PathBuf},
@@ -99,6 +99,17 @@ use {
     },
     tempfile::TempDir,
 };
+pub use {
+    accounts_db_config::{
+        ACCOUNTS_DB_CONFIG_FOR_BENCHMARKS, ACCOUNTS_DB_CONFIG_FOR_TESTING, AccountsDbConfig,
+    },
+    forked_view::ForkedAccountsView,
+};
+#[cfg(feature = "dev-context-only-utils")]
+use {
+    qualifier_attr::qualifiers,
+    rand::{Rng, rng},
+};
 
 // when the accounts write cache exceeds this many bytes, we will flush it
 // this can be specified on the command line, too (--accounts-db-write-cache-limit)
@@ -121,6 +132,10 @@ const SHRINK_COLLECT_CHUNK_SIZE: usize = 50;
 /// candidates for shrinking.
 const SHRINK_INSERT_ANCIENT_THRESHOLD: usize = 10;
 
+/// The most bytes of ancient storages moved onto the ancient tier per ancient pass.
+/// This bounds the extra disk io a single pass can take on.
+const MAX_ANCIENT_TIER_MIGRATION_BYTES_PER_PASS: u64 = 512 * 1024 * 1024;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) enum ScanAccountStorageData {
     /// callback for accounts in storage will not include `data`
@@ -390,6 +405,27 @@ pub struct IndexGenerationInfo {
     pub calculated_capitalization: u64,
 }
 
+/// Called with the number of storages processed so far and the number of storages, as generating
+/// the index at startup progresses
+///
+/// The accounts lt hash that the snapshot is verified against is calculated as the storages are
+/// processed, so this is also the progress of verifying the accounts.
+#[derive(Clone)]
+pub struct IndexGenerationProgressCallback(Arc<dyn Fn(u64, u64) + Send + Sync>);
+
+impl IndexGenerationProgressCallback {
+    pub fn new(callback: impl Fn(u64, u64) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(callback))
+    }
+}
+
+impl fmt::Debug for IndexGenerationProgressCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IndexGenerationProgressCallback")
+            .finish_non_exhaustive()
+    }
+}
+
 /// Accumulator for the values produced while generating the index
 #[derive(Debug)]
 struct IndexGenerationAccumulator {
@@ -417,6 +453,8 @@ struct IndexGenerationAccumulator {
     num_obsolete_accounts_skipped: u64,
     /// The number of zero-lamport pubkeys found in this slot
     num_zero_lamport_pubkeys: u64,
+    /// The number of storages whose accounts were taken from the scan cache
+    num_storages_from_scan_cache: u64,
     slot_arena: IndexGenerationSlotArena,
 }
 impl IndexGenerationAccumulator {
@@ -435,6 +473,7 @@ impl IndexGenerationAccumulator {
             capitalization: 0,
             num_obsolete_accounts_skipped: 0,
             num_zero_lamport_pubkeys: 0,
+            num_storages_from_scan_cache: 0,
             slot_arena: IndexGenerationSlotArena::default(),
         }
     }
@@ -455,6 +494,7 @@ impl IndexGenerationAccumulator {
             .expect("capitalization cannot overflow");
         self.num_obsolete_accounts_skipped += other.num_obsolete_accounts_skipped;
         self.num_zero_lamport_pubkeys += other.num_zero_lamport_pubkeys;
+        self.num_storages_from_scan_cache += other.num_storages_from_scan_cache;
         self.storage_info.append(&mut other.storage_info);
     }
 }
@@ -509,6 +549,7 @@ struct GenerateIndexTimings {
     pub num_slots_removed_as_obsolete: u64,
     pub num_obsolete_accounts_skipped: u64,
     pub num_zero_lamport_pubkeys: u64,
+    pub num_storages_from_scan_cache: u64,
 }
 
 #[derive(Default, Debug, PartialEq, Eq)]
@@ -596,6 +637,11 @@ impl GenerateIndexTimings {
                 self.num_zero_lamport_pubkeys,
                 i64
             ),
+            (
+                "num_storages_from_scan_cache",
+                self.num_storages_from_scan_cache,
+                i64
+            ),
         );
     }
 }
@@ -639,7 +685,7 @@ pub enum LoadHint {
     Unspecified,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum PopulateReadCache {
     /// If the account is found in storage, populate the read cache with the loaded account
     True,
@@ -888,6 +934,13 @@ pub struct AccountsDb {
     /// Set of storage paths to pick from
     pub paths: Vec<PathBuf>,
 
+    /// Which of `paths` new storages are placed in, by tier
+    storage_placement: StoragePlacement,
+
+    /// Held for write while a storage moved to another tier replaces the original, and for read
+    /// while dead accounts are removed from a storage, so no removal is lost in the move
+    storage_move_lock: RwLock<()>,
+
     /// directory for bank hash details files
     bank_hash_details_dir: PathBuf,
 
@@ -899,6 +952,9 @@ pub struct AccountsDb {
     pub thread_pool_foreground: ThreadPool,
     /// Thread pool for background tasks, e.g. AccountsBackgroundService and flush/clean/shrink
     pub thread_pool_background: ThreadPool,
+    /// Number of threads generating the index at startup
+    num_index_generation_threads: usize,
+    index_generation_progress_callback: Option<IndexGenerationProgressCallback>,
 
     pub stats: AccountsStats,
 
@@ -919,6 +975,9 @@ pub struct AccountsDb {
 
     pub scan_tracker: ScanTracker,
 
+    /// The slot of the next forked view of the accounts
+    next_forked_view_slot: AtomicU64,
+
     pub account_indexes: AccountSecondaryIndexes,
 
     /// Set of unique keys per slot which is used
@@ -952,6 +1011,12 @@ pub struct AccountsDb {
     /// storage format to use for new storages
     accounts_file_provider: AccountsFileProvider,
 
+    /// how storages are read when many accounts are loaded at once
+    storage_read_io: StorageReadIo,
+
+    /// sampled counts of the accounts written, if enabled
+    write_telemetry: Option<WriteTelemetry>,
+
     /// index scan filtering for shrinking
     scan_filter_for_shrinking: ScanFilter,
 
@@ -1020,7 +1085,7 @@ impl AccountsDb {
         let accounts_index_config = accounts_db_config.index.unwrap_or_default();
         let accounts_index = AccountsIndex::new(&accounts_index_config, exit);
 
-        let (paths, temp_paths) = if paths.is_empty() {
+        let (mut paths, temp_paths) = if paths.is_empty() {
             // Create a temporary set of accounts directories, used primarily
             // for testing
             let (temp_dirs, temp_paths) = get_temp_accounts_paths(DEFAULT_NUM_DIRS).unwrap();
@@ -1028,6 +1093,15 @@ impl AccountsDb {
         } else {
             (paths, None)
         };
+        // Storages on the ancient tier are storages like any other, so they must be found
+        // wherever the account paths are walked
+        for ancient_path in &accounts_db_config.ancient_storage_paths {
+            if !paths.contains(ancient_path) {
+                paths.push(ancient_path.clone());
+            }
+        }
+        let storage_placement =
+            StoragePlacement::new(&paths, &accounts_db_config.ancient_storage_paths);
 
         let read_cache_size = accounts_db_config.read_cache_limit_bytes.unwrap_or((
             Self::DEFAULT_MAX_READ_ONLY_CACHE_DATA_SIZE_LO,
@@ -1067,6 +1141,8 @@ impl AccountsDb {
         let new = Self {
             accounts_index,
             paths,
+            storage_placement,
+            storage_move_lock: RwLock::default(),
             bank_hash_details_dir: accounts_db_config.bank_hash_details_dir,
             temp_paths,
             skip_initial_hash_calc: accounts_db_config.skip_initial_hash_calc,
@@ -1080,14 +1156,19 @@ impl AccountsDb {
                 .max_ancient_storages
                 .unwrap_or(DEFAULT_MAX_ANCIENT_STORAGES),
             scan_tracker: ScanTracker::default(),
+            next_forked_view_slot: AtomicU64::new(forked_view::FIRST_FORKED_VIEW_SLOT),
             account_indexes: accounts_db_config.account_indexes.unwrap_or_default(),
             shrink_ratio: accounts_db_config.shrink_ratio,
             accounts_update_notifier,
-            read_only_accounts_cache: ReadOnlyAccountsCache::new(
+            read_only_accounts_cache: ReadOnlyAccountsCache::new_with_policy(
                 read_cache_size.0,
                 read_cache_size.1,
                 read_cache_evict_sample_size,
                 read_cache_num_shards,
+                accounts_db_config.read_cache_eviction_policy,
+                accounts_db_config
+                    .read_cache_adaptive_min_bytes
+                    .map(|min_bytes| min_bytes.min(read_cache_size.1)),
             ),
             write_cache_limit_bytes: accounts_db_config.write_cache_limit_bytes,
             partitioned_epoch_rewards_config: accounts_db_config.partitioned_epoch_rewards_config,
@@ -1095,6 +1176,11 @@ impl AccountsDb {
             scan_filter_for_shrinking: accounts_db_config.scan_filter_for_shrinking,
             thread_pool_foreground,
             thread_pool_background,
+            num_index_generation_threads: accounts_db_config
+                .num_index_generation_threads
+                .map_or_else(num_cpus::get, Into::into),
+            index_generation_progress_callback: accounts_db_config
+                .index_generation_progress_callback,
             active_stats: ActiveStats::default(),
             storage: AccountStorage::default(),
             accounts_cache: AccountsCache::default(),
@@ -1116,6 +1202,10 @@ impl AccountsDb {
             is_bank_drop_callback_enabled: AtomicBool::default(),
             latest_full_snapshot_slot_advanced_since_clean: AtomicBool::default(),
             accounts_file_provider: accounts_db_config.accounts_file_provider,
+            storage_read_io: accounts_db_config.storage_read_io,
+            write_telemetry: accounts_db_config
+                .write_telemetry_sample_rate
+                .map(WriteTelemetry::new),
             latest_full_snapshot_slot: SeqLock::new(None),
             last_swept_full_snapshot_slot: AtomicU64::new(0),
             best_ancient_slots_to_shrink: RwLock::default(),
@@ -2220,7 +2310,7 @@ impl AccountsDb {
 
         let mut stats_sub = ShrinkStatsSub::default();
         let mut rewrite_elapsed = Measure::start("rewrite_elapsed");
-        let (shrink_in_progress, time_us) = measure_us!(self.get_store_for_shrink(
+        let (mut shrink_in_progress, time_us) = measure_us!(self.get_store_for_shrink(
             slot,
             Arc::clone(&store),
             total_rewrite_bytes as u64
@@ -2232,16 +2322,42 @@ impl AccountsDb {
         // mutating rooted slots; There should be no writers to them.
         let accounts = [(slot, &shrink_collect.alive_accounts.alive_accounts()[..])];
         let storable_accounts = StorableAccountsBySlot::new(slot, &accounts, self);
-        stats_sub.store_accounts_stats =
-            self.store_accounts_for_shrink(storable_accounts, shrink_in_progress.new_storage());
-
         let tombstone_refs: Vec<_> = shrink_collect.tombstones_to_carry_forward.iter().collect();
         let tombstone_accounts = [(slot, &tombstone_refs[..])];
         let storable_tombstones = StorableAccountsBySlot::new(slot, &tombstone_accounts, self);
-        let (num_tombstones_carried_forward, tombstone_carry_forward_us) = measure_us!(
-            self.store_tombstones(shrink_in_progress.new_storage(), storable_tombstones)
+
+        // The tombstones are written before the index is updated, so that both they and the
+        // alive accounts are written again if the new storage has to be replaced.
+        let store_accounts_stats = &mut stats_sub.store_accounts_stats;
+        store_accounts_stats.num_accounts_stored = storable_accounts.len() as u64;
+        let mut write_accounts_us = 0;
+        let mut tombstone_carry_forward_us = 0;
+        let (infos, tombstone_infos) =
+            self.write_for_shrink(&mut shrink_in_progress, store_accounts_stats, |storage| {
+                let (infos, time_us) =
+                    measure_us!(self.write_accounts_to_storage(slot, storage, &storable_accounts));
+                write_accounts_us += time_us;
+                let infos = infos?;
+                let (tombstone_infos, time_us) = measure_us!(self.write_accounts_to_storage(
+                    slot,
+                    storage,
+                    &storable_tombstones
+                ));
+                tombstone_carry_forward_us += time_us;
+                Ok((infos, tombstone_infos?))
+            });
+        store_accounts_stats.write_accounts_us = write_accounts_us;
+
+        let (_, update_index_us) =
+            measure_us!(self.update_index_for_shrink(&infos, &storable_accounts));
+        store_accounts_stats.update_index_us = update_index_us;
+
+        let (num_tombstones_carried_forward, time_us) = measure_us!(
+            shrink_in_progress
+                .new_storage()
+                .batch_insert_tombstone_offsets(tombstone_infos.iter().map(|info| info.offset()))
         );
-        stats_sub.tombstone_carry_forward_us = Saturating(tombstone_carry_forward_us);
+        stats_sub.tombstone_carry_forward_us = Saturating(tombstone_carry_forward_us + time_us);
         stats_sub.num_tombstones_carried_forward =
             Saturating(num_tombstones_carried_forward as u64);
 
@@ -2317,13 +2433,26 @@ impl AccountsDb {
     }
 
     /// return a store that can contain 'size' bytes
+    /// The new store is placed on the same tier as 'old_store'.
     pub fn get_store_for_shrink(
         &self,
         slot: Slot,
         old_store: Arc<AccountStorageEntry>,
         size: u64,
     ) -> ShrinkInProgress<'_> {
-        let shrunken_store = Arc::new(self.create_store(slot, size));
+        let tier = old_store.tier(&self.storage_placement);
+        self.get_store_for_shrink_in_tier(slot, old_store, size, tier)
+    }
+
+    /// return a store on 'tier' that can contain 'size' bytes
+    pub(crate) fn get_store_for_shrink_in_tier(
+        &self,
+        slot: Slot,
+        old_store: Arc<AccountStorageEntry>,
+        size: u64,
+        tier: StorageTier,
+    ) -> ShrinkInProgress<'_> {
+        let shrunken_store = Arc::new(self.create_store_in_tier(slot, size, tier));
         self.storage
             .shrinking_in_progress(slot, old_store, shrunken_store)
     }
@@ -2460,6 +2589,77 @@ impl AccountsDb {
             .select_slots_us
             .fetch_add(select_slots_us, Ordering::Relaxed);
         self.combine_ancient_slots_packed(sorted_slots, can_randomly_shrink);
+
+        if self.storage_placement.is_tiered() {
+            self.move_storages_to_ancient_tier(oldest_non_ancient_slot);
+            self.storage_placement
+                .report(self.storage.iter().map(|(_, storage)| storage));
+        }
+    }
+
+    /// Moves the storages of slots older than `oldest_non_ancient_slot` that are still on the
+    /// hot tier onto the ancient tier, oldest first, until the per-pass byte budget is used up.
+    ///
+    /// Each storage is copied to an ancient path and the copy replaces the original in place,
+    /// with the same id and offsets, so the accounts index does not change. This runs as part of
+    /// the ancient pass, serialized with clean and shrink.
+    fn move_storages_to_ancient_tier(&self, oldest_non_ancient_slot: Slot) {
+        let mut bytes_moved = 0;
+        for slot in self.get_sorted_potential_ancient_slots(oldest_non_ancient_slot) {
+            if bytes_moved >= MAX_ANCIENT_TIER_MIGRATION_BYTES_PER_PASS {
+                break;
+            }
+            let Some(storage) = self.storage.get_slot_storage_entry(slot) else {
+                continue;
+            };
+            if storage.tier(&self.storage_placement) == StorageTier::Ancient {
+                continue;
+            }
+            match self.move_storage_to_tier(&storage, StorageTier::Ancient) {
+                Ok(()) => bytes_moved += storage.written_bytes(),
+                Err(err) => {
+                    warn!(
+                        "Failed to move storage '{}' to the ancient tier: {err}",
+                        storage.path().display(),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Replaces `storage` with a copy of it placed on `tier`
+    fn move_storage_to_tier(
+        &self,
+        storage: &AccountStorageEntry,
+        tier: StorageTier,
+    ) -> Result<(), AccountsFileError> {
+        storage.flush()?;
+        let accounts = storage
+            .accounts
+            .copy_to(self.storage_placement.pick_path(tier))?;
+        // Dead accounts removed from the original after the copy is built would be lost, so hold
+        // off removals until the copy has replaced it.
+        let _storage_move_guard = self.storage_move_lock.write().unwrap();
+        let new_storage = storage.with_copied_accounts(accounts, tier);
+        // Lookups can race with the replace below, and must find the same accounts at the same
+        // offsets in either storage.
+        assert_eq!(storage.id(), new_storage.id());
+        assert_eq!(storage.accounts.len(), new_storage.accounts.len());
+        // A pinned file is part of a bank snapshot that fastboot may load from. The pin moves to
+        // the copy, and the original is removed once the last reference to it is dropped, so
+        // the storage is only ever found on one path at startup.
+        if !storage.accounts.removes_file_on_drop() {
+            new_storage.disable_remove_on_drop();
+            storage.accounts.enable_remove_on_drop();
+        }
+        let num_bytes = new_storage.written_bytes();
+        self.storage
+            .replace_storage_with_equivalent(storage.slot(), Arc::new(new_storage));
+
+        let stats = self.storage_placement.stats(tier);
+        stats.migrated_storages.fetch_add(1, Ordering::Relaxed);
+        stats.migrated_bytes.fetch_add(num_bytes, Ordering::Relaxed);
+        Ok(())
     }
 
     pub fn shrink_candidate_slots(&self, epoch_schedule: &EpochSchedule) -> usize {
@@ -2659,27 +2859,54 @@ impl AccountsDb {
         if let Some(min) = ancestors.min_slot() {
             max_root = max_root.min(min);
         }
-        self.accounts_index.scan_accounts(
-            ancestors,
-            max_root,
-            |pubkey, (account_info, slot)| {
-                if let Some((cached_account, cache_slot)) = cached_versions.remove(pubkey)
-                    && cache_slot >= slot
-                {
-                    scan_func(Some((pubkey, cached_account.account.clone(), cache_slot)));
-                    return;
-                }
+        // Loads from storage are batched, if enabled. Batches are loaded once full, and at the
+        // end of the index scan.
+        BatchedStorageReader::with_thread_reader(self.storage_read_io, |mut batched_reader| {
+            let mut batch = Vec::new();
+            self.accounts_index.scan_accounts(
+                ancestors,
+                max_root,
+                |pubkey, (account_info, slot)| {
+                    if let Some((cached_account, cache_slot)) = cached_versions.remove(pubkey)
+                        && cache_slot >= slot
+                    {
+                        scan_func(Some((pubkey, cached_account.account.clone(), cache_slot)));
+                        return;
+                    }
 
-                let mut account_accessor =
-                    self.get_account_accessor(slot, &account_info.storage_location());
+                    if let Some(batched_reader) = batched_reader.as_deref_mut() {
+                        // Look up the storage now, like a single load would, as shrink may
+                        // replace it before the batch is loaded
+                        let storage = match account_info.storage_location() {
+                            StorageLocation::AccountsFile(store_id, offset) => self
+                                .storage
+                                .get_account_storage_entry(slot, store_id)
+                                .map(|storage| {
+                                    self.storage_placement.record_read(&storage);
+                                    (storage, offset)
+                                }),
+                        };
+                        batch.push((*pubkey, slot, storage));
+                        if batch.len() >= LOAD_BATCH_SIZE {
+                            Self::scan_storage_batch(batched_reader, &mut batch, &mut scan_func);
+                        }
+                        return;
+                    }
 
-                let account_slot = account_accessor.get_loaded_account(|loaded_account| {
-                    (pubkey, loaded_account.take_account(), slot)
-                });
-                scan_func(account_slot)
-            },
-            config,
-        );
+                    let mut account_accessor =
+                        self.get_account_accessor(slot, &account_info.storage_location());
+
+                    let account_slot = account_accessor.get_loaded_account(|loaded_account| {
+                        (pubkey, loaded_account.take_account(), slot)
+                    });
+                    scan_func(account_slot)
+                },
+                config,
+            );
+            if let Some(batched_reader) = batched_reader {
+                Self::scan_storage_batch(batched_reader, &mut batch, &mut scan_func);
+            }
+        });
 
         // Step 3: Call scan_func on cache-only entries — pubkeys that exist in the cache but not
         // in the accounts index at all.
@@ -2700,6 +2927,48 @@ impl AccountsDb {
         Ok(())
     }
 
+    /// Loads the accounts of `batch` from storage with `batched_reader` and calls `scan_func`
+    /// with each of them, in order. `batch` is cleared afterwards.
+    ///
+    /// Like single loads, calls `scan_func` with `None` for accounts whose storage had been
+    /// removed when the index was read.
+    fn scan_storage_batch<F>(
+        batched_reader: &mut BatchedStorageReader,
+        batch: &mut Vec<(Pubkey, Slot, Option<(Arc<AccountStorageEntry>, Offset)>)>,
+        scan_func: &mut F,
+    ) where
+        F: FnMut(Option<(&Pubkey, AccountSharedData, Slot)>),
+    {
+        let (batch_indexes, to_load): (Vec<_>, Vec<_>) = batch
+            .iter()
+            .enumerate()
+            .filter_map(|(batch_index, (_pubkey, _slot, storage))| {
+                storage
+                    .as_ref()
+                    .map(|(storage, offset)| (batch_index, (&storage.accounts, *offset)))
+            })
+            .unzip();
+
+        let mut accounts = vec![None; batch.len()];
+        let result = batched_reader.load_accounts(&to_load, |index, account| {
+            accounts[batch_indexes[index]] = account;
+        });
+        if let Err(err) = result {
+            warn!("Failed batched load of {} accounts: {err}", to_load.len());
+            // load the accounts one by one instead
+            for (batch_index, (_pubkey, _slot, storage)) in batch.iter().enumerate() {
+                accounts[batch_index] = storage.as_ref().and_then(|(storage, offset)| {
+                    storage.accounts.get_account_shared_data(*offset)
+                });
+            }
+        }
+
+        for ((pubkey, slot, _storage), account) in batch.iter().zip(accounts) {
+            scan_func(account.map(|account| (pubkey, account, *slot)));
+        }
+        batch.clear();
+    }
+
     pub(crate) fn index_scan_accounts<F>(
         &self,
         ancestors: &Ancestors,
@@ -3228,6 +3497,135 @@ impl AccountsDb {
         maybe_account.map(|account| (account, slot))
     }
 
+    /// Loads the accounts of `pubkeys`, in order, like `load()` does for each of them
+    ///
+    /// Accounts that are not in the accounts caches are loaded from storage in batches, if
+    /// enabled. This returns None for accounts with zero lamports.
+    pub fn load_many(
+        &self,
+        ancestors: &Ancestors,
+        pubkeys: &[Pubkey],
+        load_hint: LoadHint,
+        populate_read_cache: PopulateReadCache,
+    ) -> Vec<Option<(AccountSharedData, Slot)>> {
+        BatchedStorageReader::with_thread_reader(self.storage_read_io, |batched_reader| {
+            let Some(batched_reader) = batched_reader else {
+                return pubkeys
+                    .iter()
+                    .map(|pubkey| {
+                        self.load(
+                            ancestors,
+                            pubkey,
+                            load_hint,
+                            populate_read_cache,
+                            None::<fn(_, &_, _) -> _>,
+                        )
+                    })
+                    .collect();
+            };
+            let starting_max_root = self.max_root();
+
+            // Accounts found in the caches are returned as is, the others are located in their
+            // storages now, like a single load would, and loaded afterwards
+            let mut accounts = Vec::with_capacity(pubkeys.len());
+            let mut to_load = Vec::new();
+            for (index, pubkey) in pubkeys.iter().enumerate() {
+                if let Some((cached_account, cached_slot)) =
+                    self.accounts_cache.load_latest(pubkey, ancestors)
+                {
+                    self.load_account_stats
+                        .num_loaded_from_write_cache
+                        .fetch_add(1, Ordering::Relaxed);
+                    accounts.push(Some((cached_account.account.clone(), cached_slot)));
+                    continue;
+                }
+
+                let Some((slot, storage_location, _maybe_account_accessor)) =
+                    self.read_index_for_accessor_or_load_slow(ancestors, pubkey, false)
+                else {
+                    accounts.push(None);
+                    continue;
+                };
+
+                if let Some(account) = self.read_only_accounts_cache.load(*pubkey, slot) {
+                    self.load_account_stats
+                        .num_loaded_from_read_cache
+                        .fetch_add(1, Ordering::Relaxed);
+                    accounts.push(Some((account, slot)));
+                    continue;
+                }
+
+                match self.get_account_accessor(slot, &storage_location) {
+                    LoadedAccountAccessor::Stored(Some((storage, offset))) => {
+                        to_load.push((index, slot, storage, offset));
+                        accounts.push(None);
+                    }
+                    // the storage was removed since the index was read, which a single load
+                    // retries from the index read
+                    LoadedAccountAccessor::Stored(None) => accounts.push(self.do_load(
+                        ancestors,
+                        pubkey,
+                        load_hint,
+                        populate_read_cache,
+                        None::<fn(_, &_, _) -> _>,
+                    )),
+                }
+            }
+
+            for batch in to_load.chunks(LOAD_BATCH_SIZE) {
+                let storages: Vec<_> = batch
+                    .iter()
+                    .map(|(_index, _slot, storage, offset)| (&storage.accounts, *offset))
+                    .collect();
+                let mut loaded = vec![None; batch.len()];
+                let result = batched_reader.load_accounts(&storages, |batch_index, account| {
+                    loaded[batch_index] = account;
+                });
+                if let Err(err) = result {
+                    warn!("Failed batched load of {} accounts: {err}", batch.len());
+                }
+
+                for ((index, slot, storage, offset), account) in batch.iter().zip(loaded) {
+                    // load the accounts of a failed batch one by one instead
+                    let account = account
+                        .or_else(|| storage.accounts.get_account_shared_data(*offset))
+                        .expect(
+                            "If a storage entry was found in the storage map, it must not have \
+                             been reset yet",
+                        );
+                    self.load_account_stats
+                        .num_loaded_from_index_storage
+                        .fetch_add(1, Ordering::Relaxed);
+                    if populate_read_cache == PopulateReadCache::True {
+                        // This is safe for the same reasons as in do_load()
+                        self.read_only_accounts_cache.store(
+                            pubkeys[*index],
+                            *slot,
+                            account.clone(),
+                        );
+                    }
+                    accounts[*index] = Some((account, *slot));
+                }
+            }
+
+            if load_hint == LoadHint::FixedMaxRoot {
+                let ending_max_root = self.max_root();
+                if starting_max_root != ending_max_root {
+                    warn!(
+                        "load_many() loading {} pubkeys called with fixed max root, but max root \
+                         changed from {starting_max_root} to {ending_max_root} during function \
+                         call",
+                        pubkeys.len(),
+                    );
+                }
+            }
+            accounts
+                .into_iter()
+                .map(|account| account.filter(|(account, _slot)| !account.is_zero_lamport()))
+                .collect()
+        })
+    }
+
     #[cfg_attr(test, qualifiers(pub(crate)))]
     fn get_account_accessor(
         &self,
@@ -3239,7 +3637,10 @@ impl AccountsDb {
                 let maybe_storage_entry = self
                     .storage
                     .get_account_storage_entry(slot, *store_id)
-                    .map(|account_storage_entry| (account_storage_entry, *offset));
+                    .map(|account_storage_entry| {
+                        self.storage_placement.record_read(&account_storage_entry);
+                        (account_storage_entry, *offset)
+                    });
                 LoadedAccountAccessor::Stored(maybe_storage_entry)
             }
         }
@@ -3247,18 +3648,42 @@ impl AccountsDb {
 
     #[cfg_attr(test, qualifiers(pub(crate)))]
     fn create_store(&self, slot: Slot, size: u64) -> AccountStorageEntry {
+        self.create_store_in_tier(slot, size, StorageTier::Hot)
+    }
+
+    fn create_store_in_tier(
+        &self,
+        slot: Slot,
+        size: u64,
+        tier: StorageTier,
+    ) -> AccountStorageEntry {
+        self.create_store_with_provider(slot, size, self.accounts_file_provider, tier)
+    }
+
+    #[cfg_attr(test, qualifiers(pub(crate)))]
+    fn create_store_with_provider(
+        &self,
+        slot: Slot,
+        size: u64,
+        accounts_file_provider: AccountsFileProvider,
+        tier: StorageTier,
+    ) -> AccountStorageEntry {
         self.stats
             .create_store_count
             .fetch_add(1, Ordering::Relaxed);
-        let paths = &self.paths;
-        let path_index = rng().random_range(0..paths.len());
-        AccountStorageEntry::new(
-            Path::new(&paths[path_index]),
+        let storage = AccountStorageEntry::new(
+            self.storage_placement.pick_path(tier),
             slot,
             self.next_id(),
             size,
-            self.accounts_file_provider,
-        )
+            accounts_file_provider,
+        );
+        // Without ancient paths, every storage is on the hot tier
+        if self.storage_placement.is_tiered() {
+            storage.with_tier(tier)
+        } else {
+            storage.with_tier(StorageTier::Hot)
+        }
     }
 
     pub fn enable_bank_drop_callback(&self) {
@@ -4445,6 +4870,7 @@ impl AccountsDb {
             .fetch_add(reclaimed_offsets.len() as u64, Ordering::Relaxed);
 
         reclaimed_offsets.into_iter().for_each(|(slot, offsets)| {
+            let _storage_move_guard = self.storage_move_lock.read().unwrap();
             if let Some(store) = self.storage.get_slot_storage_entry(slot) {
                 assert_eq!(
                     slot,
@@ -4609,7 +5035,7 @@ impl AccountsDb {
     pub(crate) fn store_accounts_for_squash<'a>(
         &self,
         accounts: impl StorableAccounts<'a>,
-        storage: &AccountStorageEntry,
+        shrink_in_progress: &mut ShrinkInProgress<'_>,
     ) -> StoreAccountsForSquashStats {
         let slot = accounts.target_slot();
 
@@ -4628,14 +5054,15 @@ impl AccountsDb {
             0
         };
 
-        let store_accounts_for_shrink_stats = self.store_accounts_for_shrink(accounts, storage);
+        let store_accounts_for_shrink_stats =
+            self.store_accounts_for_shrink(accounts, shrink_in_progress);
         StoreAccountsForSquashStats {
             store_accounts_for_shrink_stats,
             flush_read_cache_us,
         }
     }
 
-    /// Stores accounts in the storage and updates the index.
+    /// Stores accounts in the new storage of `shrink_in_progress` and updates the index.
     /// This function is intended for accounts that are being shrunk (moving from one store to another)
     /// - `UpsertReclaims` is set to `IgnoreReclaims`. If the slot in `accounts` differs from the new slot,
     ///   accounts may be removed from the account index. In such cases, the caller must ensure that alive
@@ -4643,41 +5070,60 @@ impl AccountsDb {
     pub fn store_accounts_for_shrink<'a>(
         &self,
         accounts: impl StorableAccounts<'a>,
-        storage: &AccountStorageEntry,
+        shrink_in_progress: &mut ShrinkInProgress<'_>,
     ) -> StoreAccountsForShrinkStats {
         let slot = accounts.target_slot();
-        let num_accounts_stored = accounts.len();
+        let mut stats = StoreAccountsForShrinkStats {
+            num_accounts_stored: accounts.len() as u64,
+            ..StoreAccountsForShrinkStats::default()
+        };
 
         // Write the accounts to storage
         let write_accounts_time = Measure::start("write_accounts");
-        let infos = self.write_accounts_to_storage(slot, storage, &accounts);
-        let write_accounts_us = write_accounts_time.end_as_us();
+        let infos = self.write_for_shrink(shrink_in_progress, &mut stats, |storage| {
+            self.write_accounts_to_storage(slot, storage, &accounts)
+        });
+        stats.write_accounts_us = write_accounts_time.end_as_us();
 
         let update_index_time = Measure::start("update_index");
         self.update_index_for_shrink(&infos, &accounts);
-        let update_index_us = update_index_time.end_as_us();
+        stats.update_index_us = update_index_time.end_as_us();
 
-        StoreAccountsForShrinkStats {
-            write_accounts_us,
-            update_index_us,
-            num_accounts_stored: num_accounts_stored as u64,
-        }
+        stats
     }
 
-    /// Write tombstones into new_storage and store the new offsets on its tombstone_offsets
-    /// Note: They are not added to the index
-    /// Returns the number of tombstones stored
-    fn store_tombstones<'a>(
+    /// Writes to the new storage of `shrink_in_progress` with `write`.
+    ///
+    /// Cold storages can fail to write. The new storage is then replaced with an AppendVec of the
+    /// same capacity and tier, and `write` is called again to write to it instead. The caller must
+    /// not have updated the index to refer to the new storage yet.
+    fn write_for_shrink<T>(
         &self,
-        new_storage: &AccountStorageEntry,
-        tombstones: impl StorableAccounts<'a>,
-    ) -> usize {
-        if tombstones.is_empty() {
-            return 0;
+        shrink_in_progress: &mut ShrinkInProgress<'_>,
+        stats: &mut StoreAccountsForShrinkStats,
+        mut write: impl FnMut(&AccountStorageEntry) -> Result<T, AccountsFileError>,
+    ) -> T {
+        let new_storage = Arc::clone(shrink_in_progress.new_storage());
+        match write(&new_storage) {
+            Ok(written) => written,
+            Err(err) => {
+                warn!(
+                    "Failed to write to storage {} of slot {}, writing to an append vec instead: \
+                     {err}",
+                    new_storage.id(),
+                    new_storage.slot(),
+                );
+                stats.num_write_failures += 1;
+                let append_vec = self.create_store_with_provider(
+                    new_storage.slot(),
+                    new_storage.accounts.capacity(),
+                    AccountsFileProvider::AppendVec,
+                    new_storage.tier(&self.storage_placement),
+                );
+                shrink_in_progress.replace_new_storage(Arc::new(append_vec));
+                write(shrink_in_progress.new_storage()).expect("append vecs never fail to write")
+            }
         }
-        let tombstone_infos =
-            self.write_accounts_to_storage(tombstones.target_slot(), new_storage, &tombstones);
-        new_storage.batch_insert_tombstone_offsets(tombstone_infos.iter().map(|info| info.offset()))
     }
 
     /// Stores accounts into a new storage and updates the index.
@@ -4695,11 +5141,20 @@ impl AccountsDb {
 
         debug_assert!(self.accounts_cache.contains_unflushed_root(slot));
 
-        let storage = self.create_store(slot, size_for_new_storage);
+        // Flushed slots are still hot, so they are always written as append vecs. They move to
+        // the configured format once shrunk or packed into an ancient storage.
+        let storage = self.create_store_with_provider(
+            slot,
+            size_for_new_storage,
+            AccountsFileProvider::AppendVec,
+            StorageTier::Hot,
+        );
 
         // Write the accounts to storage
         let write_accounts_time = Measure::start("write_accounts");
-        let infos = self.write_accounts_to_storage(slot, &storage, &accounts);
+        let infos = self
+            .write_accounts_to_storage(slot, &storage, &accounts)
+            .expect("append vecs never fail to write");
         let write_accounts_us = write_accounts_time.end_as_us();
 
         // This ensures that all updates are written to storage, before any
@@ -4791,6 +5246,11 @@ impl AccountsDb {
             ..Default::default()
         };
         let mut store_account = BitVec::new_fill(false, len as u64);
+        let telemetry_batch_start = self
+            .write_telemetry
+            .as_ref()
+            .map(|write_telemetry| write_telemetry.start_batch(len));
+        let mut sampled_writes = Vec::new();
 
         (0..len).rev().for_each(|index| {
             accounts_and_meta_to_store.account_default_if_zero_lamport(index, |account| {
@@ -4818,6 +5278,16 @@ impl AccountsDb {
 
                 let account_shared_data = account.take_account();
                 let account_data_len = account_shared_data.data().len();
+                if let (Some(write_telemetry), Some(batch_start)) =
+                    (&self.write_telemetry, telemetry_batch_start)
+                    && write_telemetry.is_sampled(batch_start, index)
+                {
+                    sampled_writes.push(SampledWrite {
+                        pubkey: *pubkey,
+                        owner: *account_shared_data.owner(),
+                        data_len: account_data_len,
+                    });
+                }
                 self.accounts_cache.store(slot, pubkey, account_shared_data);
                 store_account.set(index as u64, true);
                 stats.num_accounts_stored += 1;
@@ -4825,6 +5295,11 @@ impl AccountsDb {
             })
         });
 
+        if let Some(write_telemetry) = &self.write_telemetry {
+            write_telemetry.record(slot, &sampled_writes);
+            write_telemetry.maybe_submit_metrics();
+        }
+
         (store_account, stats)
     }
 
@@ -4833,17 +5308,17 @@ impl AccountsDb {
         slot: Slot,
         storage: &AccountStorageEntry,
         accounts_and_meta_to_store: &impl StorableAccounts<'a>,
-    ) -> Vec<AccountInfo> {
+    ) -> Result<Vec<AccountInfo>, AccountsFileError> {
         let num_accounts = accounts_and_meta_to_store.len();
         let mut infos = Vec::with_capacity(num_accounts);
         if num_accounts == 0 {
-            return infos;
+            return Ok(infos);
         }
 
         let store_id = storage.id();
         let stored_accounts_info = storage
             .accounts
-            .write_accounts(accounts_and_meta_to_store)
+            .write_accounts(accounts_and_meta_to_store)?
             .unwrap_or_else(|| {
                 panic!(
                     "failed to write accounts to storage: slot! {slot}, id: {store_id}, len: {} \
@@ -4872,7 +5347,7 @@ impl AccountsDb {
             stored_accounts_info.size,
         );
 
-        infos
+        Ok(infos)
     }
 
     fn report_store_timings(&self) {
@@ -4928,6 +5403,31 @@ impl AccountsDb {
                     read_cache_stats.evict_run_count,
                     i64
                 ),
+                (
+                    "read_only_accounts_cache_admission_rejects",
+                    read_cache_stats.admission_rejects,
+                    i64
+                ),
+                (
+                    "read_only_accounts_cache_promotions",
+                    read_cache_stats.promotions,
+                    i64
+                ),
+                (
+                    "read_only_accounts_cache_resizes",
+                    read_cache_stats.resizes,
+                    i64
+                ),
+                (
+                    "read_only_accounts_cache_max_data_size",
+                    read_cache_stats.max_data_size,
+                    i64
+                ),
+                (
+                    "read_only_accounts_cache_eviction_policy",
+                    self.read_only_accounts_cache.eviction_policy().as_str(),
+                    String
+                ),
                 (
                     "handle_dead_keys_us",
                     self.stats.handle_dead_keys_us.swap(0, Ordering::Relaxed),
@@ -5009,6 +5509,20 @@ impl AccountsDb {
             .store(true, Ordering::Release);
     }
 
+    /// Returns the `limit` accounts and owner programs with the most writes since startup,
+    /// or None if write telemetry is disabled
+    pub fn write_telemetry_report(&self, limit: usize) -> Option<WriteTelemetryReport> {
+        self.write_telemetry
+            .as_ref()
+            .map(|write_telemetry| write_telemetry.report(limit))
+    }
+
+    /// Compacts the disk index where more than `free_space_threshold` (0.0 - 1.0) of it is free,
+    /// such as after many accounts were closed. Returns the number of index bins compacted.
+    pub fn compact_accounts_index(&self, free_space_threshold: f64) -> usize {
+        self.accounts_index.compact_disk_index(free_space_threshold)
+    }
+
     /// Marks slots <= slot as already swept for zero-lamport-single-ref shrink eligibility
     pub fn set_last_swept_full_snapshot_slot(&self, slot: Slot) {
         // Prior to setting this, the latest full snapshot slot must be set, and
@@ -5030,6 +5544,8 @@ impl AccountsDb {
         accum: &mut IndexGenerationAccumulator,
         storage_index: usize,
         storage: &'a AccountStorageEntry,
+        scan_cache: Option<&StorageScanCache>,
+        mut verification: Option<&mut VerificationBatch<'_>>,
     ) {
         let slot = storage.slot();
         let store_id = storage.id();
@@ -5060,64 +5576,143 @@ impl AccountsDb {
         // Since we scan the storage from oldest to newest, we can simply increment a local
         // counter per account and use that for the write version.
         let mut write_version_for_geyser = 0;
-        let num_obsolete_accounts_skipped = storage
-            .scan_accounts(reader, |offset, account| {
-                let data_len = account.data.len();
-                stored_size_alive += storage.accounts.calculate_stored_size(data_len);
-                let is_account_zero_lamport = account.is_zero_lamport();
-                if !is_account_zero_lamport {
-                    accounts_data_len += data_len as u64;
-                    all_accounts_are_zero_lamports = false;
+        // The accounts of a storage hashed by an earlier, interrupted, startup are already in the
+        // lt hash of the verification progress.
+        let is_verified = verification
+            .as_ref()
+            .is_some_and(|verification| verification.is_verified(slot));
+        let scan_result = scan_cache.and_then(|scan_cache| scan_cache.load(storage));
+        let num_obsolete_accounts_skipped = if let Some(scan_result) = scan_result {
+            // The scan cache covers every account in the storage, so subtract the ones that are
+            // excluded now. Those are read from the storage, which is cheap as there are few.
+            let excluded_offsets = storage.excluded_offsets();
+            let mut excluded_lt_hash_acc = batch::Accumulator::new();
+            let mut excluded_capitalization = 0_u64;
+            let mut excluded_accounts_data_len = 0;
+            let mut excluded_stored_size = 0;
+            for offset in &excluded_offsets {
+                storage
+                    .accounts
+                    .get_stored_account_callback(*offset, |account| {
+                        let data_len = account.data.len();
+                        excluded_stored_size += storage.accounts.calculate_stored_size(data_len);
+                        if !account.is_zero_lamport() {
+                            excluded_accounts_data_len += data_len as u64;
+                        }
+                        Self::add_account_to_lt_hash(
+                            &mut excluded_lt_hash_acc,
+                            &account,
+                            account.pubkey,
+                        );
+                        excluded_capitalization += account.lamports();
+                    })
+                    .expect("excluded account must exist in storage");
+            }
+            for account in &scan_result.accounts {
+                if excluded_offsets.contains(&account.offset) {
+                    continue;
+                }
+                if account.is_zero_lamport {
+                    zero_lamport_pubkeys.push(account.pubkey);
                 } else {
-                    // Collect zero-lamport pubkeys so they can be added to `uncleaned_pubkeys`
-                    // after the scan, for clean to examine and remove.
-                    zero_lamport_pubkeys.push(*account.pubkey);
+                    all_accounts_are_zero_lamports = false;
                 }
                 keyed_account_infos.push((
-                    *account.pubkey,
+                    account.pubkey,
                     AccountInfo::new(
-                        StorageLocation::AccountsFile(store_id, offset), // will never be cached
-                        is_account_zero_lamport,
+                        StorageLocation::AccountsFile(store_id, account.offset), // will never be cached
+                        account.is_zero_lamport,
                     ),
                 ));
+            }
+            stored_size_alive = scan_result.stored_size as usize - excluded_stored_size;
+            accounts_data_len = scan_result.accounts_data_len - excluded_accounts_data_len;
+            capitalization = scan_result.capitalization - excluded_capitalization;
+            if !is_verified {
+                let mut lt_hash = scan_result.lt_hash;
+                lt_hash.mix_out(&excluded_lt_hash_acc.into_lt_hash());
+                lt_hash_acc.mix_in(&lt_hash);
+            }
+            accum.num_storages_from_scan_cache += 1;
+            excluded_offsets.len() as u64
+        } else {
+            // With verification progress in use, the storage is hashed on its own so that it can
+            // be recorded in the progress.
+            let mut storage_lt_hash_acc = if is_verified {
+                None
+            } else if let Some(verification) = verification.as_deref_mut() {
+                Some(verification.lt_hash_acc())
+            } else {
+                Some(&mut *lt_hash_acc)
+            };
+            let num_excluded = storage
+                .scan_accounts(reader, |offset, account| {
+                    let data_len = account.data.len();
+                    stored_size_alive += storage.accounts.calculate_stored_size(data_len);
+                    let is_account_zero_lamport = account.is_zero_lamport();
+                    if !is_account_zero_lamport {
+                        accounts_data_len += data_len as u64;
+                        all_accounts_are_zero_lamports = false;
+                    } else {
+                        // Collect zero-lamport pubkeys so they can be added to `uncleaned_pubkeys`
+                        // after the scan, for clean to examine and remove.
+                        zero_lamport_pubkeys.push(*account.pubkey);
+                    }
+                    keyed_account_infos.push((
+                        *account.pubkey,
+                        AccountInfo::new(
+                            StorageLocation::AccountsFile(store_id, offset), // will never be cached
+                            is_account_zero_lamport,
+                        ),
+                    ));
 
-                if !self.account_indexes.is_empty() {
-                    self.accounts_index.update_secondary_indexes(
-                        account.pubkey,
-                        &account,
-                        &self.account_indexes,
-                    );
-                }
-
-                if !is_account_zero_lamport {
-                    Self::add_account_to_lt_hash(lt_hash_acc, &account, account.pubkey);
-                }
+                    if !self.account_indexes.is_empty() {
+                        self.accounts_index.update_secondary_indexes(
+                            account.pubkey,
+                            &account,
+                            &self.account_indexes,
+                        );
+                    }
 
-                // SAFETY: The bank capitalization field is a u64, so the lamport sum of
-                // all accounts modified in a single slot must fit into a u64.
-                capitalization = capitalization
-                    .checked_add(account.lamports())
-                    .expect("capitalization cannot overflow");
+                    if !is_account_zero_lamport
+                        && let Some(storage_lt_hash_acc) = &mut storage_lt_hash_acc
+                    {
+                        Self::add_account_to_lt_hash(storage_lt_hash_acc, &account, account.pubkey);
+                    }
 
-                if let Some(geyser_notifier) = geyser_notifier {
-                    debug_assert!(geyser_notifier.snapshot_notifications_enabled());
-                    let account_for_geyser = AccountForGeyser {
-                        pubkey: account.pubkey(),
-                        lamports: account.lamports(),
-                        owner: account.owner(),
-                        executable: account.executable(),
-                        rent_epoch: account.rent_epoch(),
-                        data: account.data(),
-                    };
-                    geyser_notifier.notify_account_restore_from_snapshot(
-                        slot,
-                        write_version_for_geyser,
-                        &account_for_geyser,
-                    );
-                    write_version_for_geyser += 1;
-                }
-            })
-            .expect("must scan accounts storage");
+                    // SAFETY: The bank capitalization field is a u64, so the lamport sum of
+                    // all accounts modified in a single slot must fit into a u64.
+                    capitalization = capitalization
+                        .checked_add(account.lamports())
+                        .expect("capitalization cannot overflow");
+
+                    if let Some(geyser_notifier) = geyser_notifier {
+                        debug_assert!(geyser_notifier.snapshot_notifications_enabled());
+                        let account_for_geyser = AccountForGeyser {
+                            pubkey: account.pubkey(),
+                            lamports: account.lamports(),
+                            owner: account.owner(),
+                            executable: account.executable(),
+                            rent_epoch: account.rent_epoch(),
+                            data: account.data(),
+                        };
+                        geyser_notifier.notify_account_restore_from_snapshot(
+                            slot,
+                            write_version_for_geyser,
+                            &account_for_geyser,
+                        );
+                        write_version_for_geyser += 1;
+                    }
+                })
+                .expect("must scan accounts storage");
+            if !is_verified
+                && let Some(verification) = verification
+                && let Some(lt_hash) = verification.push(slot)
+            {
+                lt_hash_acc.mix_in(&lt_hash);
+            }
+            num_excluded
+        };
 
         accum.capitalization = accum
             .capitalization
@@ -5177,15 +5772,59 @@ impl AccountsDb {
         &self,
         limit_load_slot_count_from_snapshot: Option<usize>,
         verify: bool,
+    ) -> IndexGenerationInfo {
+        self.generate_index_with_scan_cache(limit_load_slot_count_from_snapshot, verify, None, None)
+    }
+
+    /// Generates the index, taking each storage's accounts from `scan_cache` when it holds a
+    /// valid entry for the storage instead of scanning the storage
+    ///
+    /// With `verification_progress`, the lt hashes of storages hashed by an earlier startup are
+    /// taken from it, and the storages hashed now are recorded in it.
+    pub fn generate_index_with_scan_cache(
+        &self,
+        limit_load_slot_count_from_snapshot: Option<usize>,
+        verify: bool,
+        scan_cache: Option<&StorageScanCache>,
+        verification_progress: Option<&AccountsVerificationProgress>,
     ) -> IndexGenerationInfo {
         let mut total_time = Measure::start("generate_index");
 
+        // Secondary indexes and geyser notifications need every account's data, which the
+        // scan cache does not have.
+        let scan_cache = scan_cache.filter(|scan_cache| {
+            let needs_account_data = !self.account_indexes.is_empty()
+                || self
+                    .accounts_update_notifier
+                    .as_ref()
+                    .is_some_and(|notifier| notifier.snapshot_notifications_enabled());
+            if needs_account_data {
+                info!(
+                    "Ignoring storage scan cache at '{}': account data is required to generate \
+                     the index",
+                    scan_cache.dir().display(),
+                );
+            }
+            !needs_account_data
+        });
+
         let mut storages = self.storage.all_storages();
         storages.sort_unstable_by_key(|storage| storage.slot());
         if let Some(limit) = limit_load_slot_count_from_snapshot {
             storages.truncate(limit); // get rid of the newer slots and keep just the older
         }
         let num_storages = storages.len();
+        let verified_storages = verification_progress.map(|verification_progress| {
+            let slots = storages.iter().map(|storage| storage.slot()).collect();
+            let verified_storages = verification_progress.resume(&slots);
+            info!(
+                "Resuming accounts verification from '{}': {} of {num_storages} storages already \
+                 hashed",
+                verification_progress.path().display(),
+                verified_storages.slots.len(),
+            );
+            verified_storages
+        });
 
         // `storages` is sorted by slot, so the last one is the highest root.
         if let Some(storage) = storages.last() {
@@ -5195,11 +5834,14 @@ impl AccountsDb {
         self.accounts_index.set_startup(Startup::Startup);
 
         let mut total_accum = IndexGenerationAccumulator::with_slots_capacity(num_storages);
+        if let Some(verified_storages) = &verified_storages {
+            total_accum.lt_hash_acc.mix_in(&verified_storages.lt_hash);
+        }
         let storages_orderer =
             AccountStoragesOrderer::with_random_order(&storages).into_concurrent_consumer();
         let exit_logger = AtomicBool::new(false);
         let num_processed = AtomicU64::new(0);
-        let num_threads = num_cpus::get();
+        let num_threads = self.num_index_generation_threads;
         let mut index_time = Measure::start("index");
         thread::scope(|s| {
             let thread_handles = (0..num_threads)
@@ -5211,6 +5853,11 @@ impl AccountsDb {
                                 num_storages.div_ceil(num_threads),
                             );
                             let mut reader = append_vec::new_scan_accounts_reader();
+                            let mut verification = verification_progress
+                                .zip(verified_storages.as_ref())
+                                .map(|(verification_progress, verified_storages)| {
+                                    VerificationBatch::new(verification_progress, verified_storages)
+                                });
                             for next_item in storages_orderer.iter() {
                                 let storage = next_item.storage;
                                 self.generate_index_for_slot(
@@ -5218,9 +5865,14 @@ impl AccountsDb {
                                     &mut thread_accum,
                                     next_item.original_index,
                                     storage,
+                                    scan_cache,
+                                    verification.as_mut(),
                                 );
                                 num_processed.fetch_add(1, Ordering::Relaxed);
                             }
+                            if let Some(mut verification) = verification {
+                                thread_accum.lt_hash_acc.mix_in(&verification.record());
+                            }
                             thread_accum
                         })
                 })
@@ -5230,11 +5882,18 @@ impl AccountsDb {
                 .name("solGenIndexLog".to_string())
                 .spawn_scoped(s, || {
                     let mut last_update = Instant::now();
+                    let mut last_reported = None;
                     loop {
                         if exit_logger.load(Ordering::Relaxed) {
                             break;
                         }
                         let num_processed = num_processed.load(Ordering::Relaxed);
+                        if let Some(callback) = &self.index_generation_progress_callback
+                            && last_reported != Some(num_processed)
+                        {
+                            (callback.0)(num_processed, num_storages as u64);
+                            last_reported = Some(num_processed);
+                        }
                         if num_processed == num_storages as u64 {
                             info!("generating index: processed all slots");
                             break;
@@ -5374,6 +6033,7 @@ impl AccountsDb {
             total_slots: num_storages as u64,
             all_accounts_are_zero_lamports_slots: total_accum.all_accounts_are_zero_lamports_slots,
             num_obsolete_accounts_skipped: total_accum.num_obsolete_accounts_skipped,
+            num_storages_from_scan_cache: total_accum.num_storages_from_scan_cache,
             ..GenerateIndexTimings::default()
         };
 
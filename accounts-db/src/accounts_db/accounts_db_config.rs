@@ -1,16 +1,24 @@
 use {
-    super::{AccountShrinkThreshold, DEFAULT_ACCOUNTS_SHRINK_THRESHOLD_OPTION},
+    super::{
+        AccountShrinkThreshold, DEFAULT_ACCOUNTS_SHRINK_THRESHOLD_OPTION,
+        IndexGenerationProgressCallback,
+    },
     crate::{
         accounts_file::AccountsFileProvider,
         accounts_index::{
             ACCOUNTS_INDEX_CONFIG_FOR_BENCHMARKS, ACCOUNTS_INDEX_CONFIG_FOR_TESTING,
             AccountSecondaryIndexes, AccountsIndexConfig, ScanFilter,
         },
+        batched_storage_reader::StorageReadIo,
         partitioned_rewards::{
             DEFAULT_PARTITIONED_EPOCH_REWARDS_CONFIG, PartitionedEpochRewardsConfig,
         },
+        read_only_accounts_cache::ReadCacheEvictionPolicy,
+    },
+    std::{
+        num::{NonZeroU64, NonZeroUsize},
+        path::PathBuf,
     },
-    std::{num::NonZeroUsize, path::PathBuf},
 };
 
 #[derive(Debug, Default, Clone)]
@@ -28,12 +36,22 @@ pub struct AccountsDbConfig {
     /// Number of shards for the read-only accounts cache's DashMap.
     /// Must be a power of two. If None, defaults to 65536.
     pub read_cache_num_shards: Option<usize>,
+    /// How the read cache picks the entries to evict.
+    pub read_cache_eviction_policy: ReadCacheEvictionPolicy,
+    /// If set, the read cache's high watermark is adapted between this size
+    /// and the configured one, in bytes, based on the cache's hit rate and
+    /// the available memory. The low watermark is scaled along.
+    pub read_cache_adaptive_min_bytes: Option<usize>,
     pub write_cache_limit_bytes: Option<u64>,
     /// if None, ancient append vecs are set to ANCIENT_APPEND_VEC_DEFAULT_OFFSET
     /// Some(offset) means include slots up to (max_slot - (slots_per_epoch - 'offset'))
     pub ancient_append_vec_offset: Option<i64>,
     pub ancient_storage_ideal_size: Option<u64>,
     pub max_ancient_storages: Option<usize>,
+    /// Paths for the ancient storage tier, typically on larger, slower disks than the account
+    /// paths. Storages that become ancient are moved here by the ancient pass.
+    /// If empty, storages are spread across the account paths regardless of their age.
+    pub ancient_storage_paths: Vec<PathBuf>,
     pub skip_initial_hash_calc: bool,
     pub verify_index: bool,
     pub partitioned_epoch_rewards_config: PartitionedEpochRewardsConfig,
@@ -42,7 +60,17 @@ pub struct AccountsDbConfig {
     pub num_background_threads: Option<NonZeroUsize>,
     /// Number of threads for foreground operations (`thread_pool_foreground`)
     pub num_foreground_threads: Option<NonZeroUsize>,
+    /// Number of threads generating the index at startup, which also calculates the accounts lt
+    /// hash that the snapshot is verified against
+    pub num_index_generation_threads: Option<NonZeroUsize>,
+    /// If set, called as generating the index at startup progresses
+    pub index_generation_progress_callback: Option<IndexGenerationProgressCallback>,
     pub accounts_file_provider: AccountsFileProvider,
+    /// How storages are read when many accounts are loaded at once
+    pub storage_read_io: StorageReadIo,
+    /// If set, one in this many accounts stored is sampled to track the accounts and owner
+    /// programs written the most
+    pub write_telemetry_sample_rate: Option<NonZeroU64>,
 }
 
 pub const ACCOUNTS_DB_CONFIG_FOR_TESTING: AccountsDbConfig = AccountsDbConfig {
@@ -53,17 +81,24 @@ pub const ACCOUNTS_DB_CONFIG_FOR_TESTING: AccountsDbConfig = AccountsDbConfig {
     read_cache_limit_bytes: None,
     read_cache_evict_sample_size: None,
     read_cache_num_shards: None,
+    read_cache_eviction_policy: ReadCacheEvictionPolicy::SampledLru,
+    read_cache_adaptive_min_bytes: None,
     write_cache_limit_bytes: None,
     ancient_append_vec_offset: None,
     ancient_storage_ideal_size: None,
     max_ancient_storages: None,
+    ancient_storage_paths: Vec::new(),
     skip_initial_hash_calc: false,
     verify_index: false,
     partitioned_epoch_rewards_config: DEFAULT_PARTITIONED_EPOCH_REWARDS_CONFIG,
     scan_filter_for_shrinking: ScanFilter::OnlyAbnormalTest,
     num_background_threads: None,
     num_foreground_threads: None,
+    num_index_generation_threads: None,
+    index_generation_progress_callback: None,
     accounts_file_provider: AccountsFileProvider::AppendVec,
+    storage_read_io: StorageReadIo::Sync,
+    write_telemetry_sample_rate: None,
 };
 
 pub const ACCOUNTS_DB_CONFIG_FOR_BENCHMARKS: AccountsDbConfig = AccountsDbConfig {
@@ -74,15 +109,22 @@ pub const ACCOUNTS_DB_CONFIG_FOR_BENCHMARKS: AccountsDbConfig = AccountsDbConfig
     read_cache_limit_bytes: None,
     read_cache_evict_sample_size: None,
     read_cache_num_shards: None,
+    read_cache_eviction_policy: ReadCacheEvictionPolicy::SampledLru,
+    read_cache_adaptive_min_bytes: None,
     write_cache_limit_bytes: None,
     ancient_append_vec_offset: None,
     ancient_storage_ideal_size: None,
     max_ancient_storages: None,
+    ancient_storage_paths: Vec::new(),
     skip_initial_hash_calc: false,
     verify_index: false,
     partitioned_epoch_rewards_config: DEFAULT_PARTITIONED_EPOCH_REWARDS_CONFIG,
     scan_filter_for_shrinking: ScanFilter::OnlyAbnormal,
     num_background_threads: None,
     num_foreground_threads: None,
+    num_index_generation_threads: None,
+    index_generation_progress_callback: None,
     accounts_file_provider: AccountsFileProvider::AppendVec,
+    storage_read_io: StorageReadIo::Sync,
+    write_telemetry_sample_rate: None,
 };
@@ -0,0 +1,223 @@
+//! Writable views of the accounts as of the latest root, which are never rooted.
+//!
+//! A view pins the latest root the same way an ongoing scan does, so clean keeps the state of
+//! the accounts as of that root for as long as the view is alive. Accounts stored into the view
+//! go into the write cache, in a slot of their own that no bank will ever use, and are read back
+//! before falling back to the pinned root. The slot is purged from the write cache when the
+//! view is dropped, the same way the slot of a dropped unrooted bank is.
+
+use {
+    crate::{
+        accounts_db::{AccountsDb, LoadHint, PopulateReadCache, stats::PurgeStats},
+        accounts_scan::ScanGuard,
+        ancestors::Ancestors,
+        is_zero_lamport::IsZeroLamport,
+    },
+    solana_account::AccountSharedData,
+    solana_clock::Slot,
+    solana_pubkey::Pubkey,
+    std::{iter, sync::atomic::Ordering},
+};
+
+/// The slot of the first view created. Real slots never get anywhere near this high.
+pub(super) const FIRST_FORKED_VIEW_SLOT: Slot = 1 << 63;
+
+/// A copy-on-write view of the accounts as of a root
+///
+/// Loads return the accounts stored into the view, or the accounts as of the root the view was
+/// created at. Nothing stored into the view is visible outside of it, and nothing stored into
+/// later slots of the real forks is visible inside of it.
+///
+/// A live view is costly to the rest of accounts-db, so views should be short-lived:
+/// * The view holds a [`ScanGuard`] on its root, like an ongoing scan does. Clean cannot advance
+///   past that root while the view is alive, so the old versions of the accounts written since
+///   are not removed from storage and the accounts index.
+/// * The accounts stored into the view stay in the write cache until the view is dropped, as its
+///   slot is never rooted and so never flushed. They count towards the size of the write cache,
+///   which makes flushes of rooted slots more aggressive once it exceeds its limit.
+#[derive(Debug)]
+pub struct ForkedAccountsView<'a> {
+    accounts_db: &'a AccountsDb,
+    /// pins the root the view is based on
+    scan_guard: ScanGuard<'a>,
+    /// the root the view is based on, as ancestors to load with
+    base_ancestors: Ancestors,
+    /// the slot the accounts stored into the view are cached in
+    slot: Slot,
+}
+
+impl<'a> ForkedAccountsView<'a> {
+    fn new(accounts_db: &'a AccountsDb) -> Self {
+        let scan_guard =
+            ScanGuard::new_without_bank(&accounts_db.scan_tracker, || accounts_db.max_root());
+        let base_ancestors = Ancestors::from(vec![scan_guard.max_root()]);
+        let slot = accounts_db
+            .next_forked_view_slot
+            .fetch_add(1, Ordering::Relaxed);
+        Self {
+            accounts_db,
+            scan_guard,
+            base_ancestors,
+            slot,
+        }
+    }
+
+    /// The root the view is based on
+    pub fn base_slot(&self) -> Slot {
+        self.scan_guard.max_root()
+    }
+
+    /// The slot the accounts stored into the view are cached in
+    pub fn slot(&self) -> Slot {
+        self.slot
+    }
+
+    /// Loads the account stored into the view, or else the account as of the root of the view.
+    /// Returns None for accounts with zero lamports.
+    pub fn load(&self, pubkey: &Pubkey) -> Option<AccountSharedData> {
+        if let Some(cached_account) = self.accounts_db.accounts_cache.load(self.slot, pubkey) {
+            return (!cached_account.account.is_zero_lamport())
+                .then(|| cached_account.account.clone());
+        }
+        self.accounts_db
+            .load(
+                &self.base_ancestors,
+                pubkey,
+                LoadHint::Unspecified,
+                PopulateReadCache::False,
+                None::<fn(u64, &Pubkey, usize) -> bool>,
+            )
+            .map(|(account, _slot)| account)
+    }
+
+    /// Stores `accounts` into the view, replacing the accounts previously stored or loaded.
+    /// Storing an account with zero lamports closes it.
+    pub fn store_accounts(&self, accounts: &[(Pubkey, AccountSharedData)]) {
+        for (pubkey, account) in accounts {
+            self.accounts_db
+                .accounts_cache
+                .store(self.slot, pubkey, account.clone());
+        }
+    }
+
+    /// Returns the accounts stored into the view, including the closed ones
+    pub fn stored_accounts(&self) -> Vec<(Pubkey, AccountSharedData)> {
+        self.accounts_db
+            .accounts_cache
+            .slot_cache(self.slot)
+            .map(|slot_cache| {
+                slot_cache
+                    .iter()
+                    .map(|item| (*item.key(), item.value().account.clone()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+impl Drop for ForkedAccountsView<'_> {
+    fn drop(&mut self) {
+        self.accounts_db
+            .purge_slots_from_cache(iter::once(&self.slot), &PurgeStats::default());
+    }
+}
+
+impl AccountsDb {
+    /// Creates a writable view of the accounts as of the latest root
+    ///
+    /// Accounts stored into the view are only visible to the view, and are removed when it is
+    /// dropped. Clean is held back, and the accounts stored into the view are kept in the write
+    /// cache, for as long as the view is alive. See [`ForkedAccountsView`].
+    pub fn forked_view(&self) -> ForkedAccountsView<'_> {
+        ForkedAccountsView::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, solana_account::ReadableAccount};
+
+    #[test]
+    fn test_forked_view() {
+        let accounts_db = AccountsDb::default_for_tests();
+        let owner = Pubkey::new_unique();
+        let pubkey = Pubkey::new_unique();
+        let closed_pubkey = Pubkey::new_unique();
+        let new_pubkey = Pubkey::new_unique();
+        let unchanged_pubkey = Pubkey::new_unique();
+        let account = |lamports| AccountSharedData::new(lamports, 0, &owner);
+
+        accounts_db.store_for_tests((
+            0,
+            [
+                (pubkey, account(10)),
+                (closed_pubkey, account(20)),
+                (unchanged_pubkey, account(40)),
+            ]
+            .as_slice(),
+        ));
+        accounts_db.add_root_and_flush_write_cache(0);
+
+        let view = accounts_db.forked_view();
+        assert_eq!(view.base_slot(), 0);
+        assert!(view.slot() >= FIRST_FORKED_VIEW_SLOT);
+        assert_eq!(view.load(&pubkey), Some(account(10)));
+        assert_eq!(accounts_db.scan_tracker.min_ongoing_scan_root(), Some(0));
+
+        view.store_accounts(&[
+            (pubkey, account(11)),
+            (closed_pubkey, account(0)),
+            (new_pubkey, account(30)),
+        ]);
+        assert_eq!(view.load(&pubkey), Some(account(11)));
+        assert_eq!(view.load(&closed_pubkey), None);
+        assert_eq!(view.load(&new_pubkey), Some(account(30)));
+        let mut stored_accounts = view.stored_accounts();
+        stored_accounts.sort_unstable_by_key(|(pubkey, _)| *pubkey);
+        let mut expected = vec![
+            (pubkey, account(11)),
+            (closed_pubkey, account(0)),
+            (new_pubkey, account(30)),
+        ];
+        expected.sort_unstable_by_key(|(pubkey, _)| *pubkey);
+        assert_eq!(stored_accounts, expected);
+
+        // the real fork does not see the view, and the view does not see later roots
+        let root_ancestors = Ancestors::from(vec![0]);
+        let load = |pubkey| {
+            accounts_db
+                .load(
+                    &root_ancestors,
+                    pubkey,
+                    LoadHint::Unspecified,
+                    PopulateReadCache::False,
+                    None::<fn(u64, &Pubkey, usize) -> bool>,
+                )
+                .map(|(account, _slot)| account.lamports())
+        };
+        assert_eq!(load(&pubkey), Some(10));
+        assert_eq!(load(&closed_pubkey), Some(20));
+        assert_eq!(load(&new_pubkey), None);
+        accounts_db.store_for_tests((
+            1,
+            [(pubkey, account(12)), (unchanged_pubkey, account(41))].as_slice(),
+        ));
+        accounts_db.add_root_and_flush_write_cache(1);
+        assert_eq!(view.load(&pubkey), Some(account(11)));
+        assert_eq!(view.load(&unchanged_pubkey), Some(account(40)));
+        let other_view = accounts_db.forked_view();
+        assert_eq!(other_view.base_slot(), 1);
+        assert_ne!(other_view.slot(), view.slot());
+        assert_eq!(other_view.load(&pubkey), Some(account(12)));
+        assert_eq!(other_view.load(&unchanged_pubkey), Some(account(41)));
+        assert_eq!(other_view.load(&new_pubkey), None);
+
+        // dropping the views removes what was stored into them, and unpins their roots
+        let slot = view.slot();
+        drop(view);
+        drop(other_view);
+        assert!(!accounts_db.accounts_cache.contains(slot));
+        assert_eq!(accounts_db.scan_tracker.min_ongoing_scan_root(), None);
+        assert_eq!(load(&new_pubkey), None);
+    }
+}
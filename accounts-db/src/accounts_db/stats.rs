@@ -106,6 +106,8 @@ pub struct StoreAccountsForShrinkStats {
     pub write_accounts_us: u64,
     pub update_index_us: u64,
     pub num_accounts_stored: u64,
+    /// number of new storages which failed to write and were replaced by an AppendVec
+    pub num_write_failures: u64,
 }
 
 impl StoreAccountsForShrinkStats {
@@ -113,6 +115,7 @@ impl StoreAccountsForShrinkStats {
         self.write_accounts_us += other.write_accounts_us;
         self.update_index_us += other.update_index_us;
         self.num_accounts_stored += other.num_accounts_stored;
+        self.num_write_failures += other.num_write_failures;
     }
 }
 
@@ -363,6 +366,8 @@ pub struct ShrinkStats {
     pub write_accounts_us: AtomicU64,
     pub update_index_us: AtomicU64,
     pub num_accounts_stored: AtomicU64,
+    /// number of new storages which failed to write and were replaced by an AppendVec
+    pub num_write_failures: AtomicU64,
     pub remove_old_stores_shrink_us: AtomicU64,
     pub rewrite_elapsed: AtomicU64,
     pub tombstone_carry_forward_us: AtomicU64,
@@ -415,6 +420,8 @@ impl ShrinkStats {
             .fetch_add(store_accounts_stats.update_index_us, Ordering::Relaxed);
         self.num_accounts_stored
             .fetch_add(store_accounts_stats.num_accounts_stored, Ordering::Relaxed);
+        self.num_write_failures
+            .fetch_add(store_accounts_stats.num_write_failures, Ordering::Relaxed);
     }
 
     pub fn report(&self) {
@@ -484,6 +491,11 @@ impl ShrinkStats {
                     self.num_accounts_stored.swap(0, Ordering::Relaxed),
                     i64
                 ),
+                (
+                    "num_write_failures",
+                    self.num_write_failures.swap(0, Ordering::Relaxed),
+                    i64
+                ),
                 (
                     "remove_old_stores_shrink_us",
                     self.remove_old_stores_shrink_us.swap(0, Ordering::Relaxed),
@@ -645,6 +657,13 @@ impl ShrinkAncientStats {
                     .swap(0, Ordering::Relaxed),
                 i64
             ),
+            (
+                "num_write_failures",
+                self.shrink_stats
+                    .num_write_failures
+                    .swap(0, Ordering::Relaxed),
+                i64
+            ),
             (
                 "remove_old_stores_shrink_us",
                 self.shrink_stats
@@ -18,7 +18,11 @@ pub fn append_single_account_with_default_hash(
     let accounts = [(pubkey, account)];
     let slice = &accounts[..];
     let storable_accounts = (slot, slice);
-    let stored_accounts_info = storage.accounts.write_accounts(&storable_accounts).unwrap();
+    let stored_accounts_info = storage
+        .accounts
+        .write_accounts(&storable_accounts)
+        .unwrap()
+        .unwrap();
     if mark_alive {
         // updates 'alive_bytes' on the storage
         storage.add_accounts(1, stored_accounts_info.size);
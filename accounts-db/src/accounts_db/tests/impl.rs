@@ -1,12 +1,13 @@
 use {
     super::*,
     crate::{
-        accounts_file::AccountsFileProvider,
+        accounts_file::{AccountsFile, AccountsFileProvider},
         accounts_index::{
             ACCOUNTS_INDEX_CONFIG_FOR_TESTING, AccountIndex, AccountSecondaryIndexesIncludeExclude,
             AccountsIndexConfig, IndexLimit, IndexLimitThreshold, test_utils::*,
         },
         append_vec::{AppendVec, STORE_META_OVERHEAD},
+        write_telemetry::{HotAccount, HotOwner},
     },
     itertools::Itertools as _,
     rand::{prelude::SliceRandom as _, rng},
@@ -15,14 +16,16 @@ use {
         WritableAccount as _,
     },
     solana_clock::Slot,
+    solana_hash::Hash,
     solana_lattice_hash::lt_hash::Checksum as LtHashChecksum,
     solana_pubkey::{PUBKEY_BYTES, Pubkey},
     std::{
         collections::{HashMap, HashSet},
         iter,
+        num::{NonZeroU64, NonZeroUsize},
         str::FromStr as _,
         sync::{
-            Arc, RwLock,
+            Arc, Mutex, RwLock,
             atomic::{AtomicBool, Ordering},
         },
         thread::{self, Builder, JoinHandle, sleep},
@@ -156,14 +159,14 @@ fn test_generate_index_duplicates_within_slot() {
     let storable_accounts = (slot0, &data[..]);
 
     // construct store with account to generate an index from
-    store.accounts.write_accounts(&storable_accounts);
+    store.accounts.write_accounts(&storable_accounts).unwrap();
     db.storage.insert(Arc::new(store));
 
     assert!(!db.accounts_index.contains(&pubkey));
     let storage = db.get_storage_for_slot(slot0).unwrap();
     let mut reader = crate::append_vec::new_scan_accounts_reader();
     let mut accum = IndexGenerationAccumulator::with_slots_capacity(1);
-    db.generate_index_for_slot(&mut reader, &mut accum, 0, &storage);
+    db.generate_index_for_slot(&mut reader, &mut accum, 0, &storage, None);
 }
 
 #[test]
@@ -176,7 +179,10 @@ fn test_generate_index_for_single_ref_zero_lamport_slot() {
 
     let data = [(&pubkey, &account)];
     let storable_accounts = (slot0, &data[..]);
-    append_vec.accounts.write_accounts(&storable_accounts);
+    append_vec
+        .accounts
+        .write_accounts(&storable_accounts)
+        .unwrap();
     let append_vec = Arc::new(append_vec);
     db.storage.insert(Arc::clone(&append_vec));
     assert!(!db.accounts_index.contains(&pubkey));
@@ -208,6 +214,225 @@ fn test_generate_index_for_single_ref_zero_lamport_slot() {
     assert!(db.storage.get_slot_storage_entry(slot0).is_none());
 }
 
+/// Generating the index from a scan cache gives the same result as scanning the storages, even
+/// when accounts were marked obsolete after the scan cache was written.
+#[test]
+fn test_generate_index_with_scan_cache() {
+    let slot = 1;
+    let owner = Pubkey::new_unique();
+    let pubkeys: Vec<_> = iter::repeat_with(Pubkey::new_unique).take(4).collect();
+    let accounts = [
+        AccountSharedData::new(1, 1, &owner),
+        AccountSharedData::new(2, 10, &owner),
+        AccountSharedData::new(0, 0, &owner),
+        AccountSharedData::new(3, 100, &owner),
+    ];
+    let data: Vec<_> = pubkeys.iter().zip(&accounts).collect();
+
+    let db = AccountsDb::new_for_tests_with_config(Vec::new(), DEFAULT_ACCOUNTS_DB_CONFIG);
+    let storage = Arc::new(db.create_store(slot, DEFAULT_FILE_SIZE));
+    let offsets = storage
+        .accounts
+        .write_accounts(&(slot, data.as_slice()))
+        .unwrap()
+        .unwrap()
+        .offsets;
+    storage.flush().unwrap();
+
+    let scan_cache_dir = TempDir::new().unwrap();
+    let scan_cache = StorageScanCache::new(scan_cache_dir.path());
+    scan_cache.update(&[Arc::clone(&storage)]).unwrap();
+
+    // the second account is replaced in a later slot after the scan cache is written
+    storage
+        .obsolete_accounts()
+        .write()
+        .unwrap()
+        .mark_accounts_obsolete(
+            [(offsets[1], accounts[1].data().len())].into_iter(),
+            slot + 1,
+        );
+    assert!(scan_cache.load(&storage).is_some());
+
+    db.storage.insert(Arc::clone(&storage));
+    let expected = db.generate_index(None, false);
+    let expected_alive_bytes = storage.alive_bytes();
+
+    let db = AccountsDb::new_for_tests_with_config(Vec::new(), DEFAULT_ACCOUNTS_DB_CONFIG);
+    db.storage.insert(Arc::clone(&storage));
+    let result = db.generate_index_with_scan_cache(None, true, Some(&scan_cache), None);
+
+    assert_eq!(result.accounts_data_len, expected.accounts_data_len);
+    assert_eq!(result.calculated_capitalization, 4);
+    assert_eq!(
+        result.calculated_capitalization,
+        expected.calculated_capitalization,
+    );
+    assert_eq!(
+        result.calculated_accounts_lt_hash,
+        expected.calculated_accounts_lt_hash,
+    );
+    assert_eq!(storage.alive_bytes(), expected_alive_bytes);
+    assert_eq!(storage.count(), 3);
+    assert!(!db.accounts_index.contains(&pubkeys[1]));
+    assert_eq!(
+        db.uncleaned_pubkeys.get(&slot).unwrap().value(),
+        &vec![pubkeys[2]],
+    );
+}
+
+/// Storages hashed while generating the index are recorded in the verification progress, so that
+/// generating the index again after an interrupted startup resumes from them with the same result.
+#[test]
+fn test_generate_index_with_verification_progress() {
+    let owner = Pubkey::new_unique();
+    let progress_dir = TempDir::new().unwrap();
+    let progress_path = progress_dir.path().join("progress");
+    let archives = [(3, Hash::new_unique())];
+    let reported = Arc::new(Mutex::new(Vec::new()));
+    let config = AccountsDbConfig {
+        num_index_generation_threads: NonZeroUsize::new(2),
+        index_generation_progress_callback: Some(IndexGenerationProgressCallback::new({
+            let reported = Arc::clone(&reported);
+            move |num_processed, num_storages| {
+                reported.lock().unwrap().push((num_processed, num_storages))
+            }
+        })),
+        ..DEFAULT_ACCOUNTS_DB_CONFIG
+    };
+
+    let db = AccountsDb::new_for_tests_with_config(Vec::new(), DEFAULT_ACCOUNTS_DB_CONFIG);
+    let storages: Vec<_> = (1..=3)
+        .map(|slot| {
+            let pubkeys: Vec<_> = iter::repeat_with(Pubkey::new_unique).take(3).collect();
+            let accounts: Vec<_> = (0..3)
+                .map(|i| AccountSharedData::new(slot * 10 + i, i as usize, &owner))
+                .collect();
+            let data: Vec<_> = pubkeys.iter().zip(&accounts).collect();
+            let storage = Arc::new(db.create_store(slot, DEFAULT_FILE_SIZE));
+            let offsets = storage
+                .accounts
+                .write_accounts(&(slot, data.as_slice()))
+                .unwrap()
+                .unwrap()
+                .offsets;
+            storage.flush().unwrap();
+            // storages with obsolete accounts are recorded too
+            if slot == 2 {
+                storage
+                    .obsolete_accounts()
+                    .write()
+                    .unwrap()
+                    .mark_accounts_obsolete(
+                        [(offsets[1], accounts[1].data().len())].into_iter(),
+                        slot + 1,
+                    );
+            }
+            storage
+        })
+        .collect();
+    for storage in &storages {
+        db.storage.insert(Arc::clone(storage));
+    }
+    let expected = db.generate_index(None, false);
+
+    let generate_index = |progress: &AccountsVerificationProgress| {
+        let db = AccountsDb::new_for_tests_with_config(Vec::new(), config.clone());
+        for storage in &storages {
+            db.storage.insert(Arc::clone(storage));
+        }
+        let result = db.generate_index_with_scan_cache(None, true, None, Some(progress));
+        assert_eq!(result.accounts_data_len, expected.accounts_data_len);
+        assert_eq!(
+            result.calculated_capitalization,
+            expected.calculated_capitalization,
+        );
+        assert_eq!(
+            result.calculated_accounts_lt_hash,
+            expected.calculated_accounts_lt_hash,
+        );
+    };
+
+    generate_index(&AccountsVerificationProgress::open(
+        &progress_path,
+        &archives,
+    ));
+    assert_eq!(reported.lock().unwrap().last(), Some(&(3, 3)));
+    let slots = storages.iter().map(|storage| storage.slot()).collect();
+    let verified = AccountsVerificationProgress::open(&progress_path, &archives).resume(&slots);
+    assert_eq!(verified.slots, slots);
+
+    let progress = AccountsVerificationProgress::open(&progress_path, &archives);
+    generate_index(&progress);
+    progress.remove();
+    assert!(!progress_path.exists());
+}
+
+#[test_case(false; "unpinned")]
+#[test_case(true; "pinned")]
+fn test_move_storages_to_ancient_tier(pinned: bool) {
+    let ancient_dir = TempDir::new().unwrap();
+    let ancient_path = ancient_dir.path().to_path_buf();
+    let db = AccountsDb::new_for_tests_with_config(
+        Vec::new(),
+        AccountsDbConfig {
+            ancient_storage_paths: vec![ancient_path.clone()],
+            ..DEFAULT_ACCOUNTS_DB_CONFIG
+        },
+    );
+    assert!(db.paths.contains(&ancient_path));
+
+    let slot = 1;
+    let pubkey = Pubkey::new_unique();
+    let account = AccountSharedData::new(1, 10, &Pubkey::default());
+    db.store_for_tests((slot, [(&pubkey, &account)].as_slice()));
+    db.add_root_and_flush_write_cache(slot);
+
+    // newly flushed storages are placed on the hot tier
+    let storage = db.storage.get_slot_storage_entry(slot).unwrap();
+    assert_eq!(storage.tier(&db.storage_placement), StorageTier::Hot);
+    if pinned {
+        storage.disable_remove_on_drop();
+    }
+    let old_path = storage.path().to_path_buf();
+
+    // the slot is not ancient yet
+    db.move_storages_to_ancient_tier(slot);
+    assert!(Arc::ptr_eq(
+        &storage,
+        &db.storage.get_slot_storage_entry(slot).unwrap()
+    ));
+
+    db.move_storages_to_ancient_tier(slot + 1);
+    let new_storage = db.storage.get_slot_storage_entry(slot).unwrap();
+    assert_eq!(new_storage.path().parent(), Some(ancient_path.as_path()));
+    assert_eq!(
+        new_storage.tier(&db.storage_placement),
+        StorageTier::Ancient
+    );
+    assert_eq!(new_storage.id(), storage.id());
+    assert_eq!(new_storage.written_bytes(), storage.written_bytes());
+    assert_eq!(new_storage.count(), storage.count());
+    assert_eq!(new_storage.alive_bytes(), storage.alive_bytes());
+    // the pin moves over to the copy
+    assert_eq!(new_storage.accounts.removes_file_on_drop(), !pinned);
+
+    let stats = db.storage_placement.stats(StorageTier::Ancient);
+    assert_eq!(stats.migrated_storages.load(Ordering::Relaxed), 1);
+    assert_eq!(
+        stats.migrated_bytes.load(Ordering::Relaxed),
+        storage.written_bytes()
+    );
+    db.assert_load_account(slot, pubkey, 1);
+    assert!(stats.reads.load(Ordering::Relaxed) > 0);
+
+    // the original file is removed once the last reference to it is dropped
+    assert!(old_path.exists());
+    drop(storage);
+    assert!(!old_path.exists());
+    assert!(new_storage.path().exists());
+}
+
 #[test]
 fn test_accountsdb_add_root() {
     let db = AccountsDb::new_for_tests_with_config(Vec::new(), DEFAULT_ACCOUNTS_DB_CONFIG);
@@ -1267,6 +1492,7 @@ fn test_clean_converts_zero_lamport_single_ref_account_to_tombstone_after_shrink
     storage1
         .accounts
         .write_accounts(&(slot1, accounts_to_write.as_slice()))
+        .unwrap()
         .unwrap();
     accounts_db.storage.insert(Arc::clone(&storage1));
     accounts_db.add_root(slot1);
@@ -2088,6 +2314,176 @@ fn test_accountsdb_scan_accounts() {
     assert_eq!(accounts.len(), 2);
 }
 
+#[test_case(StorageReadIo::Sync; "sync")]
+#[test_case(StorageReadIo::IoUring; "io_uring")]
+#[test_case(StorageReadIo::IoUringDirect; "io_uring_direct")]
+fn test_accountsdb_scan_accounts_storage_read_io(storage_read_io: StorageReadIo) {
+    let db = AccountsDb::new_for_tests_with_config(
+        Vec::new(),
+        AccountsDbConfig {
+            storage_read_io,
+            ..DEFAULT_ACCOUNTS_DB_CONFIG
+        },
+    );
+
+    // enough accounts for more than one batch, some of which don't fit the first read
+    let mut expected_accounts = Vec::new();
+    for slot in 0..3 {
+        let accounts: Vec<_> = (0..LOAD_BATCH_SIZE / 2 + 1)
+            .map(|i| {
+                let data_len = if i % 100 == 0 { 10_000 } else { i % 200 };
+                let account = AccountSharedData::new(i as u64 + 1, data_len, &Pubkey::default());
+                (solana_pubkey::new_rand(), account)
+            })
+            .collect();
+        db.store_for_tests((slot, accounts.as_slice()));
+        expected_accounts.extend(
+            accounts
+                .into_iter()
+                .map(|(pubkey, account)| (pubkey, account, slot)),
+        );
+        db.add_root_and_flush_write_cache(slot);
+    }
+    // and an account that is only in the write cache
+    let cached_slot = 3;
+    let cached_account = (
+        solana_pubkey::new_rand(),
+        AccountSharedData::new(1, 0, &Pubkey::default()),
+    );
+    db.store_for_tests((
+        cached_slot,
+        [(&cached_account.0, &cached_account.1)].as_slice(),
+    ));
+    expected_accounts.push((cached_account.0, cached_account.1, cached_slot));
+
+    let ancestors = Ancestors::from(vec![cached_slot]);
+    let mut accounts = Vec::new();
+    db.scan_accounts(
+        &ancestors,
+        0,
+        |scan_result| {
+            if let Some((pubkey, account, slot)) = scan_result {
+                accounts.push((*pubkey, account, slot));
+            }
+        },
+        &ScanConfig::default(),
+    )
+    .expect("should scan accounts");
+    accounts.sort_unstable_by_key(|(pubkey, ..)| *pubkey);
+    expected_accounts.sort_unstable_by_key(|(pubkey, ..)| *pubkey);
+    assert_eq!(accounts, expected_accounts);
+}
+
+#[test_case(StorageReadIo::Sync; "sync")]
+#[test_case(StorageReadIo::IoUring; "io_uring")]
+#[test_case(StorageReadIo::IoUringDirect; "io_uring_direct")]
+fn test_load_many_storage_read_io(storage_read_io: StorageReadIo) {
+    let db = AccountsDb::new_for_tests_with_config(
+        Vec::new(),
+        AccountsDbConfig {
+            storage_read_io,
+            ..DEFAULT_ACCOUNTS_DB_CONFIG
+        },
+    );
+
+    // enough accounts for more than one batch, some of which don't fit the first read
+    let mut pubkeys = Vec::new();
+    let mut expected_accounts = Vec::new();
+    for slot in 0..3 {
+        let accounts: Vec<_> = (0..LOAD_BATCH_SIZE / 2 + 1)
+            .map(|i| {
+                let data_len = if i % 100 == 0 { 10_000 } else { i % 200 };
+                let account = AccountSharedData::new(i as u64 + 1, data_len, &Pubkey::default());
+                (solana_pubkey::new_rand(), account)
+            })
+            .collect();
+        db.store_for_tests((slot, accounts.as_slice()));
+        for (pubkey, account) in accounts {
+            pubkeys.push(pubkey);
+            expected_accounts.push(Some((account, slot)));
+        }
+        db.add_root_and_flush_write_cache(slot);
+    }
+    // an account that is only in the write cache
+    let cached_slot = 3;
+    let cached_account = (
+        solana_pubkey::new_rand(),
+        AccountSharedData::new(1, 0, &Pubkey::default()),
+    );
+    // and an account that was removed there
+    let removed_account = (pubkeys[0], AccountSharedData::new(0, 0, &Pubkey::default()));
+    db.store_for_tests((
+        cached_slot,
+        [
+            (&cached_account.0, &cached_account.1),
+            (&removed_account.0, &removed_account.1),
+        ]
+        .as_slice(),
+    ));
+    pubkeys.push(cached_account.0);
+    expected_accounts.push(Some((cached_account.1, cached_slot)));
+    expected_accounts[0] = None;
+    // and an account that does not exist
+    pubkeys.push(solana_pubkey::new_rand());
+    expected_accounts.push(None);
+
+    let ancestors = Ancestors::from(vec![cached_slot]);
+    for load_hint in [LoadHint::Unspecified, LoadHint::FixedMaxRoot] {
+        // the second load of each account is from the read cache
+        for _ in 0..2 {
+            let accounts = db.load_many(&ancestors, &pubkeys, load_hint, PopulateReadCache::True);
+            assert_eq!(accounts, expected_accounts);
+        }
+    }
+}
+
+#[test]
+fn test_write_telemetry() {
+    let db = AccountsDb::new_for_tests_with_config(
+        Vec::new(),
+        AccountsDbConfig {
+            write_telemetry_sample_rate: NonZeroU64::new(1),
+            ..DEFAULT_ACCOUNTS_DB_CONFIG
+        },
+    );
+    let owner = Pubkey::new_unique();
+    let hot = Pubkey::new_unique();
+    let cold = Pubkey::new_unique();
+    for slot in 0..3 {
+        let account = AccountSharedData::new(1, 10, &owner);
+        db.store_for_tests((slot, [(&hot, &account)].as_slice()));
+    }
+    let account = AccountSharedData::new(1, 0, &Pubkey::default());
+    db.store_for_tests((3, [(&cold, &account)].as_slice()));
+
+    let report = db.write_telemetry_report(1).unwrap();
+    assert_eq!(report.sample_rate, 1);
+    assert_eq!(
+        report.accounts,
+        [HotAccount {
+            pubkey: hot,
+            owner,
+            writes: 3,
+            bytes: 30,
+            first_slot: 0,
+            last_slot: 2,
+        }]
+    );
+    assert_eq!(
+        report.owners,
+        [HotOwner {
+            owner,
+            writes: 3,
+            bytes: 30,
+            rewrites: 2,
+        }]
+    );
+
+    // write telemetry is disabled by default
+    let db = AccountsDb::default_for_tests();
+    assert!(db.write_telemetry_report(1).is_none());
+}
+
 #[test]
 fn test_cleanup_key_not_removed() {
     let db = AccountsDb::new_for_tests_with_config(Vec::new(), DEFAULT_ACCOUNTS_DB_CONFIG);
@@ -2664,7 +3060,8 @@ fn test_select_candidates_by_total_usage_3_way_split_condition() {
     ));
     store1
         .accounts
-        .write_accounts(&(store1_slot, [(&Pubkey::new_unique(), &account)].as_slice()));
+        .write_accounts(&(store1_slot, [(&Pubkey::new_unique(), &account)].as_slice()))
+        .unwrap();
     db.storage.insert(Arc::clone(&store1));
     store1.num_alive_bytes.store(0, Ordering::Release);
     candidates.insert(store1_slot);
@@ -2679,7 +3076,8 @@ fn test_select_candidates_by_total_usage_3_way_split_condition() {
     ));
     store2
         .accounts
-        .write_accounts(&(store2_slot, [(&Pubkey::new_unique(), &account)].as_slice()));
+        .write_accounts(&(store2_slot, [(&Pubkey::new_unique(), &account)].as_slice()))
+        .unwrap();
     db.storage.insert(Arc::clone(&store2));
     store2
         .num_alive_bytes
@@ -2696,7 +3094,8 @@ fn test_select_candidates_by_total_usage_3_way_split_condition() {
     ));
     store3
         .accounts
-        .write_accounts(&(store3_slot, [(&Pubkey::new_unique(), &account)].as_slice()));
+        .write_accounts(&(store3_slot, [(&Pubkey::new_unique(), &account)].as_slice()))
+        .unwrap();
     db.storage.insert(Arc::clone(&store3));
     store3
         .num_alive_bytes
@@ -2737,7 +3136,8 @@ fn test_select_candidates_by_total_usage_2_way_split_condition() {
     ));
     store1
         .accounts
-        .write_accounts(&(store1_slot, [(&Pubkey::new_unique(), &account)].as_slice()));
+        .write_accounts(&(store1_slot, [(&Pubkey::new_unique(), &account)].as_slice()))
+        .unwrap();
     db.storage.insert(Arc::clone(&store1));
     store1.num_alive_bytes.store(0, Ordering::Release);
     candidates.insert(store1_slot);
@@ -2752,7 +3152,8 @@ fn test_select_candidates_by_total_usage_2_way_split_condition() {
     ));
     store2
         .accounts
-        .write_accounts(&(store2_slot, [(&Pubkey::new_unique(), &account)].as_slice()));
+        .write_accounts(&(store2_slot, [(&Pubkey::new_unique(), &account)].as_slice()))
+        .unwrap();
     db.storage.insert(Arc::clone(&store2));
     store2
         .num_alive_bytes
@@ -2769,7 +3170,8 @@ fn test_select_candidates_by_total_usage_2_way_split_condition() {
     ));
     store3
         .accounts
-        .write_accounts(&(store3_slot, [(&Pubkey::new_unique(), &account)].as_slice()));
+        .write_accounts(&(store3_slot, [(&Pubkey::new_unique(), &account)].as_slice()))
+        .unwrap();
     db.storage.insert(Arc::clone(&store3));
     store3
         .num_alive_bytes
@@ -2807,7 +3209,8 @@ fn test_select_candidates_by_total_usage_all_clean() {
     ));
     store1
         .accounts
-        .write_accounts(&(store1_slot, [(&Pubkey::new_unique(), &account)].as_slice()));
+        .write_accounts(&(store1_slot, [(&Pubkey::new_unique(), &account)].as_slice()))
+        .unwrap();
     db.storage.insert(Arc::clone(&store1));
     store1
         .num_alive_bytes
@@ -2824,7 +3227,8 @@ fn test_select_candidates_by_total_usage_all_clean() {
     ));
     store2
         .accounts
-        .write_accounts(&(store2_slot, [(&Pubkey::new_unique(), &account)].as_slice()));
+        .write_accounts(&(store2_slot, [(&Pubkey::new_unique(), &account)].as_slice()))
+        .unwrap();
     db.storage.insert(Arc::clone(&store2));
     store2
         .num_alive_bytes
@@ -2867,6 +3271,7 @@ fn test_select_candidates_by_total_usage_with_tombstones() {
     let stored_accounts_info = store_with_tombstones
         .accounts
         .write_accounts(&(slot_with_tombstones, accounts_to_store.as_slice()))
+        .unwrap()
         .unwrap();
     store_with_tombstones.batch_insert_tombstone_offsets(stored_accounts_info.offsets);
     store_with_tombstones.num_alive_bytes.store(
@@ -2889,6 +3294,7 @@ fn test_select_candidates_by_total_usage_with_tombstones() {
     store_no_tombstones
         .accounts
         .write_accounts(&(slot_with_tombstones, accounts_to_store.as_slice()))
+        .unwrap()
         .unwrap();
     store_no_tombstones.num_alive_bytes.store(
         store_no_tombstones.written_bytes() as usize,
@@ -5115,14 +5521,17 @@ fn test_is_shrinking_productive() {
         file_size,
         accounts.accounts_file_provider,
     ));
-    store.accounts.write_accounts(&(
-        slot,
-        [(
-            Pubkey::new_unique(),
-            AccountSharedData::new(1, account_size, &Pubkey::default()),
-        )]
-        .as_slice(),
-    ));
+    store
+        .accounts
+        .write_accounts(&(
+            slot,
+            [(
+                Pubkey::new_unique(),
+                AccountSharedData::new(1, account_size, &Pubkey::default()),
+            )]
+            .as_slice(),
+        ))
+        .unwrap();
 
     store.add_accounts(5, store.written_bytes() as usize);
     assert!(!accounts.is_shrinking_productive(&store));
@@ -5148,14 +5557,17 @@ fn test_is_candidate_for_shrink() {
         store_file_size,
         accounts.accounts_file_provider,
     ));
-    entry.accounts.write_accounts(&(
-        slot,
-        [(
-            Pubkey::new_unique(),
-            AccountSharedData::new(1, 100, &Pubkey::default()),
-        )]
-        .as_slice(),
-    ));
+    entry
+        .accounts
+        .write_accounts(&(
+            slot,
+            [(
+                Pubkey::new_unique(),
+                AccountSharedData::new(1, 100, &Pubkey::default()),
+            )]
+            .as_slice(),
+        ))
+        .unwrap();
     let written_bytes = entry.written_bytes() as usize;
     match accounts.shrink_ratio {
         AccountShrinkThreshold::TotalSpace { shrink_ratio } => {
@@ -5202,13 +5614,14 @@ fn test_calculate_storage_count_and_alive_bytes() {
     let storage = accounts.create_store(slot0, 4_000);
     storage
         .accounts
-        .write_accounts(&(slot0, &[(&shared_key, &account)][..]));
+        .write_accounts(&(slot0, &[(&shared_key, &account)][..]))
+        .unwrap();
     accounts.storage.insert(Arc::new(storage));
 
     let storage = accounts.storage.get_slot_storage_entry(slot0).unwrap();
     let mut reader = crate::append_vec::new_scan_accounts_reader();
     let mut accum = IndexGenerationAccumulator::with_slots_capacity(1);
-    accounts.generate_index_for_slot(&mut reader, &mut accum, 0, &storage);
+    accounts.generate_index_for_slot(&mut reader, &mut accum, 0, &storage, None);
     assert_eq!(accum.storage_info.len(), 1);
     for (slot, value) in accum.storage_info {
         let expected_stored_size = 144;
@@ -5227,7 +5640,7 @@ fn test_calculate_storage_count_and_alive_bytes_0_accounts() {
     let storage = accounts.create_store(0, 1);
     let mut reader = crate::append_vec::new_scan_accounts_reader();
     let mut accum = IndexGenerationAccumulator::with_slots_capacity(1);
-    accounts.generate_index_for_slot(&mut reader, &mut accum, 0, &storage);
+    accounts.generate_index_for_slot(&mut reader, &mut accum, 0, &storage, None);
     assert!(accum.storage_info.is_empty());
 }
 
@@ -5258,11 +5671,12 @@ fn test_calculate_storage_count_and_alive_bytes_2_accounts() {
     let storage = accounts.create_store(slot0, 4_000);
     storage
         .accounts
-        .write_accounts(&(slot0, &[(&keys[0], &account), (&keys[1], &account_big)][..]));
+        .write_accounts(&(slot0, &[(&keys[0], &account), (&keys[1], &account_big)][..]))
+        .unwrap();
 
     let mut reader = crate::append_vec::new_scan_accounts_reader();
     let mut accum = IndexGenerationAccumulator::with_slots_capacity(1);
-    accounts.generate_index_for_slot(&mut reader, &mut accum, 0, &storage);
+    accounts.generate_index_for_slot(&mut reader, &mut accum, 0, &storage, None);
     assert_eq!(accum.storage_info.len(), 1);
     for (slot, value) in accum.storage_info {
         let expected_stored_size = 1280;
@@ -5301,7 +5715,10 @@ fn test_calculate_storage_count_and_alive_bytes_obsolete_account(
 
     let slot0 = 0;
     let storage = accounts.create_store(slot0, 10_000);
-    let offsets = storage.accounts.write_accounts(&(slot0, &account_list[..]));
+    let offsets = storage
+        .accounts
+        .write_accounts(&(slot0, &account_list[..]))
+        .unwrap();
 
     let offsets = offsets.unwrap().offsets;
     let data_lens = storage.accounts.get_account_data_lens(&offsets);
@@ -5322,7 +5739,7 @@ fn test_calculate_storage_count_and_alive_bytes_obsolete_account(
 
     let mut reader = crate::append_vec::new_scan_accounts_reader();
     let mut accum = IndexGenerationAccumulator::with_slots_capacity(1);
-    accounts.generate_index_for_slot(&mut reader, &mut accum, 0, &storage);
+    accounts.generate_index_for_slot(&mut reader, &mut accum, 0, &storage, None);
     assert_eq!(
         accum.num_obsolete_accounts_skipped,
         num_accounts_to_mark_obsolete as u64
@@ -5547,6 +5964,52 @@ fn test_mark_dirty_dead_stores() {
     assert!(db.storage.get_slot_storage_entry(slot).is_some());
 }
 
+#[test]
+fn test_write_for_shrink_replaces_failed_storage() {
+    let slot = 0;
+    let db = AccountsDb::new_for_tests_with_config(
+        Vec::new(),
+        AccountsDbConfig {
+            accounts_file_provider: AccountsFileProvider::ColdStorage,
+            ..DEFAULT_ACCOUNTS_DB_CONFIG
+        },
+    );
+    let old_store = Arc::new(db.create_store(slot, 1));
+    db.storage.insert(Arc::clone(&old_store));
+    let mut shrink_in_progress = db.get_store_for_shrink(slot, old_store, 100);
+    let failed_id = shrink_in_progress.new_storage().id();
+    assert!(matches!(
+        shrink_in_progress.new_storage().accounts,
+        AccountsFile::ColdStorage(_)
+    ));
+
+    // The first write fails, the second one is to the append vec replacing the new storage
+    let mut stats = StoreAccountsForShrinkStats::default();
+    let mut written_ids = Vec::new();
+    db.write_for_shrink(&mut shrink_in_progress, &mut stats, |storage| {
+        written_ids.push(storage.id());
+        if written_ids.len() == 1 {
+            Err(AccountsFileError::Io(std::io::Error::other("write failed")))
+        } else {
+            Ok(())
+        }
+    });
+    let new_storage = Arc::clone(shrink_in_progress.new_storage());
+    assert_eq!(written_ids, vec![failed_id, new_storage.id()]);
+    assert_ne!(new_storage.id(), failed_id);
+    assert_eq!(new_storage.slot(), slot);
+    assert_eq!(new_storage.accounts.capacity(), 100);
+    assert!(matches!(new_storage.accounts, AccountsFile::AppendVec(_)));
+    assert_eq!(stats.num_write_failures, 1);
+
+    // The replacement takes the place of the old storage once the shrink is done
+    db.mark_dirty_dead_stores(slot, Some(shrink_in_progress), false);
+    assert_eq!(
+        db.storage.get_slot_storage_entry(slot).unwrap().id(),
+        new_storage.id()
+    );
+}
+
 #[test]
 fn test_sweep_get_oldest_non_ancient_slot_max() {
     let epoch_schedule = EpochSchedule::default();
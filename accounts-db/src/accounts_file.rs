@@ -4,6 +4,7 @@ use {
         account_storage::stored_account_info::{StoredAccountInfo, StoredAccountInfoWithoutData},
         accounts_db::AccountsFileId,
         append_vec::{AppendVec, AppendVecError},
+        cold_storage::{AppendVecEncoder, ColdStorage, ColdStorageError},
         storable_accounts::StorableAccounts,
     },
     agave_fs::{FileInfo, buffered_reader::RequiredLenBufFileRead, file_io::open_for_reading},
@@ -11,7 +12,7 @@ use {
     solana_clock::Slot,
     solana_pubkey::Pubkey,
     std::{
-        fs::File,
+        fs::{self, File, OpenOptions},
         io, mem,
         path::{Path, PathBuf},
     },
@@ -39,6 +40,9 @@ pub enum AccountsFileError {
 
     #[error("AppendVecError: {0}")]
     AppendVecError(#[from] AppendVecError),
+
+    #[error("ColdStorageError: {0}")]
+    ColdStorageError(#[from] ColdStorageError),
 }
 
 #[derive(Debug)]
@@ -46,16 +50,24 @@ pub enum AccountsFileError {
 /// under different formats.
 pub enum AccountsFile {
     AppendVec(AppendVec),
+    ColdStorage(ColdStorage),
 }
 
 impl AccountsFile {
     /// Create an AccountsFile instance from the specified path.
     ///
     /// The second element of the returned tuple is the number of accounts in the
-    /// accounts file.
+    /// accounts file. Cold storage files describe their own length, so `current_len` only
+    /// applies to AppendVecs.
     #[cfg(feature = "dev-context-only-utils")]
     pub fn new_from_file(path: impl Into<PathBuf>, current_len: usize) -> Result<(Self, usize)> {
-        let (av, num_accounts) = AppendVec::new_from_file(path, current_len)?;
+        let file_info = FileInfo::new_from_path(path)?;
+        if ColdStorage::is_cold_storage_file(&file_info.file, file_info.size)? {
+            let cold_storage = ColdStorage::new_for_startup(file_info)?;
+            let num_accounts = cold_storage.num_accounts();
+            return Ok((Self::ColdStorage(cold_storage), num_accounts));
+        }
+        let (av, num_accounts) = AppendVec::new_from_file(file_info.path, current_len)?;
         Ok((Self::AppendVec(av), num_accounts))
     }
 
@@ -64,7 +76,13 @@ impl AccountsFile {
     /// This version of `new()` may only be called when reconstructing storages as part of startup.
     /// The storage length is taken to be the full file size; this is trusted and relies on later
     /// index generation or accounts verification to ensure it is valid.
+    ///
+    /// The format of the file is detected from its contents.
     pub fn new_for_startup(file_info: FileInfo) -> Result<Self> {
+        if ColdStorage::is_cold_storage_file(&file_info.file, file_info.size)? {
+            let cold_storage = ColdStorage::new_for_startup(file_info)?;
+            return Ok(Self::ColdStorage(cold_storage));
+        }
         let av = AppendVec::new_for_startup(file_info)?;
         Ok(Self::AppendVec(av))
     }
@@ -73,6 +91,10 @@ impl AccountsFile {
     pub(crate) fn reopen_as_readonly(&self) -> Option<Self> {
         match self {
             Self::AppendVec(av) => av.reopen_as_readonly_file_io().map(Self::AppendVec),
+            // Reopening only pays off for append vecs, whose reads switch from mmap to file io.
+            // Cold storages are never memory mapped and are always read with file io, so a
+            // read-only copy would read exactly like this one. `None` keeps using this instance.
+            Self::ColdStorage(_) => None,
         }
     }
 
@@ -81,14 +103,70 @@ impl AccountsFile {
     pub fn disable_remove_on_drop(&self) {
         match self {
             Self::AppendVec(av) => av.disable_remove_on_drop(),
+            Self::ColdStorage(cs) => cs.disable_remove_on_drop(),
+        }
+    }
+
+    /// Re-attach the on-disk file to this storage's lifetime; see
+    /// [`AppendVec::enable_remove_on_drop`].
+    pub(crate) fn enable_remove_on_drop(&self) {
+        match self {
+            Self::AppendVec(av) => av.enable_remove_on_drop(),
+            Self::ColdStorage(cs) => cs.enable_remove_on_drop(),
         }
     }
 
+    /// Returns true if dropping this storage removes the on-disk file
+    pub(crate) fn removes_file_on_drop(&self) -> bool {
+        match self {
+            Self::AppendVec(av) => av.removes_file_on_drop(),
+            Self::ColdStorage(cs) => cs.removes_file_on_drop(),
+        }
+    }
+
+    /// Copies the underlying file into `dir` and opens the copy, read-only.
+    ///
+    /// The copy is written under a temporary name and only renamed into place once it is
+    /// complete and synced, so an interrupted copy is never mistaken for a storage file.
+    pub(crate) fn copy_to(&self, dir: &Path) -> Result<Self> {
+        assert_ne!(
+            self.path().parent(),
+            Some(dir),
+            "storage must be copied into another directory",
+        );
+        let file_name = self
+            .path()
+            .file_name()
+            .expect("storage path must have a file name");
+        let path = dir.join(file_name);
+        let mut tmp_path = path.clone().into_os_string();
+        tmp_path.push(".tmp");
+        let tmp_path = PathBuf::from(tmp_path);
+
+        let copy = || {
+            fs::copy(self.path(), &tmp_path)?;
+            let file = OpenOptions::new().write(true).open(&tmp_path)?;
+            if let Self::AppendVec(av) = self {
+                // AppendVecs are preallocated past the bytes in use, but reopening one takes
+                // the file size as its length.
+                file.set_len(av.len() as u64)?;
+            }
+            file.sync_all()?;
+            fs::rename(&tmp_path, &path)?;
+            Self::new_for_startup(FileInfo::new_from_path(&path)?)
+        };
+        copy().inspect_err(|_| {
+            _ = fs::remove_file(&tmp_path);
+            _ = fs::remove_file(&path);
+        })
+    }
+
     /// Return the total number of bytes of the zero lamport accounts in the storage.
     /// Those bytes are "dead" and can be shrunk away.
     pub(crate) fn dead_bytes_due_to_zero_lamport_accounts(&self, count: usize) -> usize {
         match self {
             Self::AppendVec(av) => av.dead_bytes_due_to_zero_lamport_accounts(count),
+            Self::ColdStorage(_) => AppendVec::calculate_stored_size(0) * count,
         }
     }
 
@@ -96,6 +174,7 @@ impl AccountsFile {
     pub fn flush(&self) -> Result<()> {
         match self {
             Self::AppendVec(av) => av.flush()?,
+            Self::ColdStorage(cs) => cs.flush()?,
         }
         Ok(())
     }
@@ -104,12 +183,22 @@ impl AccountsFile {
     pub fn len(&self) -> usize {
         match self {
             Self::AppendVec(av) => av.len(),
+            Self::ColdStorage(cs) => cs.len(),
         }
     }
 
     pub fn is_empty(&self) -> bool {
         match self {
             Self::AppendVec(av) => av.is_empty(),
+            Self::ColdStorage(cs) => cs.is_empty(),
+        }
+    }
+
+    /// Returns the number of bytes, *not accounts*, the AccountsFile can hold
+    pub fn capacity(&self) -> u64 {
+        match self {
+            Self::AppendVec(av) => av.capacity(),
+            Self::ColdStorage(cs) => cs.capacity(),
         }
     }
 
@@ -131,6 +220,7 @@ impl AccountsFile {
     ) -> Option<Ret> {
         match self {
             Self::AppendVec(av) => av.get_stored_account_without_data_callback(offset, callback),
+            Self::ColdStorage(cs) => cs.get_stored_account_without_data_callback(offset, callback),
         }
     }
 
@@ -148,6 +238,7 @@ impl AccountsFile {
     ) -> Option<Ret> {
         match self {
             Self::AppendVec(av) => av.get_stored_account_callback(offset, callback),
+            Self::ColdStorage(cs) => cs.get_stored_account_callback(offset, callback),
         }
     }
 
@@ -155,6 +246,7 @@ impl AccountsFile {
     pub(crate) fn get_account_shared_data(&self, offset: usize) -> Option<AccountSharedData> {
         match self {
             Self::AppendVec(av) => av.get_account_shared_data(offset),
+            Self::ColdStorage(cs) => cs.get_account_shared_data(offset),
         }
     }
 
@@ -162,6 +254,7 @@ impl AccountsFile {
     pub fn path(&self) -> &Path {
         match self {
             Self::AppendVec(av) => av.path(),
+            Self::ColdStorage(cs) => cs.path(),
         }
     }
 
@@ -178,6 +271,7 @@ impl AccountsFile {
     ) -> Result<()> {
         match self {
             Self::AppendVec(av) => av.scan_accounts_without_data(callback)?,
+            Self::ColdStorage(cs) => cs.scan_accounts_without_data(callback)?,
         }
         Ok(())
     }
@@ -197,15 +291,18 @@ impl AccountsFile {
     ) -> Result<()> {
         match self {
             Self::AppendVec(av) => av.scan_accounts(reader, callback)?,
+            // cold storages decompress whole blocks and have no use for `reader`
+            Self::ColdStorage(cs) => cs.scan_accounts(callback)?,
         }
         Ok(())
     }
 
     /// Calculate the amount of storage required for an account with the passed
     /// in data_len
-    pub(crate) fn calculate_stored_size(&self, data_len: usize) -> usize {
+    pub fn calculate_stored_size(&self, data_len: usize) -> usize {
         match self {
-            Self::AppendVec(_) => AppendVec::calculate_stored_size(data_len),
+            // cold storages account for their contents as if they were AppendVecs
+            Self::AppendVec(_) | Self::ColdStorage(_) => AppendVec::calculate_stored_size(data_len),
         }
     }
 
@@ -213,6 +310,7 @@ impl AccountsFile {
     pub(crate) fn get_account_data_lens(&self, sorted_offsets: &[usize]) -> Vec<usize> {
         match self {
             Self::AppendVec(av) => av.get_account_data_lens(sorted_offsets),
+            Self::ColdStorage(cs) => cs.get_account_data_lens(sorted_offsets),
         }
     }
 
@@ -220,6 +318,7 @@ impl AccountsFile {
     pub fn scan_pubkeys(&self, callback: impl FnMut(&Pubkey)) -> Result<()> {
         match self {
             Self::AppendVec(av) => av.scan_pubkeys(callback)?,
+            Self::ColdStorage(cs) => cs.scan_pubkeys(callback)?,
         }
         Ok(())
     }
@@ -231,12 +330,16 @@ impl AccountsFile {
     /// So, return.len() is 1 + (number of accounts written)
     /// After each account is appended, the internal `current_len` is updated
     /// and will be available to other threads.
+    ///
+    /// Cold storages compress and write the accounts as a whole segment, which can fail. The
+    /// error is returned and the storage is left as it was. AppendVecs never fail to write.
     pub fn write_accounts<'a>(
         &self,
         accounts: &impl StorableAccounts<'a>,
-    ) -> Option<StoredAccountsInfo> {
+    ) -> Result<Option<StoredAccountsInfo>> {
         match self {
-            Self::AppendVec(av) => av.append_accounts(accounts),
+            Self::AppendVec(av) => Ok(av.append_accounts(accounts)),
+            Self::ColdStorage(cs) => Ok(cs.write_accounts(accounts)?),
         }
     }
 
@@ -249,9 +352,36 @@ impl AccountsFile {
         } else {
             Ok(match self {
                 Self::AppendVec(av) => av.open_file_for_archive(),
+                Self::ColdStorage(cs) => cs.open_file_for_archive(),
             })
         }
     }
+
+    /// Returns the number of bytes of the underlying file read when archiving this storage.
+    ///
+    /// Cold storages are re-encoded as AppendVecs by [`Self::append_vec_encoder`] instead of
+    /// being copied, so none of their file is read by the archive's file reader.
+    pub fn archive_read_limit(&self) -> usize {
+        match self {
+            Self::AppendVec(av) => av.len(),
+            Self::ColdStorage(_) => 0,
+        }
+    }
+
+    /// Returns a reader that produces this storage in the AppendVec format, skipping the accounts
+    /// at `sorted_excluded_offsets` (sorted in descending order), or `None` if the storage is
+    /// already an AppendVec.
+    pub(crate) fn append_vec_encoder(
+        &self,
+        sorted_excluded_offsets: impl IntoIterator<Item = Offset>,
+    ) -> Option<AppendVecEncoder<'_>> {
+        match self {
+            Self::AppendVec(_) => None,
+            Self::ColdStorage(cs) => {
+                Some(cs.append_vec_encoder(sorted_excluded_offsets.into_iter().collect()))
+            }
+        }
+    }
 }
 
 /// An enum that creates AccountsFile instance with the specified format.
@@ -259,12 +389,18 @@ impl AccountsFile {
 pub enum AccountsFileProvider {
     #[default]
     AppendVec,
+    /// Block-compressed storages; see [`ColdStorage`]. Only storages created by shrink and
+    /// ancient packing use this format, since freshly flushed slots are still hot.
+    ColdStorage,
 }
 
 impl AccountsFileProvider {
     pub fn new_writable(&self, path: impl Into<PathBuf>, file_size: u64) -> AccountsFile {
         match self {
             Self::AppendVec => AccountsFile::AppendVec(AppendVec::new(path, file_size as usize)),
+            Self::ColdStorage => {
+                AccountsFile::ColdStorage(ColdStorage::new(path, file_size as usize))
+            }
         }
     }
 }
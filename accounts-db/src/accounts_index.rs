@@ -262,6 +262,20 @@ impl<T: IndexValue, U: DiskIndexValue + From<T> + Into<T>> AccountsIndex<T, U> {
         self.storage.storage.is_disk_index_enabled()
     }
 
+    /// Compacts the buckets of the disk index of which more than `free_space_threshold`
+    /// (0.0 - 1.0) is free, and returns the number of buckets compacted.
+    ///
+    /// The bg threads already do this periodically with
+    /// `DISK_INDEX_COMPACTION_FREE_SPACE_THRESHOLD`.
+    pub fn compact_disk_index(&self, free_space_threshold: f64) -> usize {
+        self.storage
+            .storage
+            .disk
+            .as_ref()
+            .map(|disk| disk.compact(free_space_threshold))
+            .unwrap_or_default()
+    }
+
     /// Gets the index's entry for `pubkey` and applies `callback` to it
     ///
     /// If `callback`'s boolean return value is true, add this entry to the in-mem cache.
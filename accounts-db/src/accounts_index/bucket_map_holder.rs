@@ -51,6 +51,13 @@ pub const DEFAULT_NUM_ENTRIES_TO_EVICT: usize = 10_000;
 /// Byte threshold used when the deprecated `minimal` index limit is specified.
 pub const MINIMAL_THRESHOLD_NUM_BYTES: u64 = 25_000_000_000;
 
+/// The disk index is compacted where more than this share of it is free, such as after many
+/// accounts were closed.
+pub const DISK_INDEX_COMPACTION_FREE_SPACE_THRESHOLD: f64 = 0.5;
+
+/// How often the bg threads check whether the disk index needs to be compacted
+const DISK_INDEX_COMPACTION_INTERVAL_MS: u64 = 60_000;
+
 pub struct BucketMapHolder<T: IndexValue, U: DiskIndexValue + From<T> + Into<T>> {
     pub disk: Option<BucketMap<(Slot, U)>>,
 
@@ -74,6 +81,9 @@ pub struct BucketMapHolder<T: IndexValue, U: DiskIndexValue + From<T> + Into<T>>
     age_ms: u64,
     age_timer: AtomicInterval,
 
+    /// Paces the compaction of the disk index by the bg threads
+    disk_compaction_timer: AtomicInterval,
+
     // used by bg processing to know when any bucket has become dirty
     pub wait_dirty_or_aged: Arc<WaitableCondvar>,
     next_bucket_to_flush: AtomicUsize,
@@ -363,6 +373,7 @@ impl<T: IndexValue, U: DiskIndexValue + From<T> + Into<T>> BucketMapHolder<T, U>
             next_bucket_to_flush: AtomicUsize::new(0),
             age_ms,
             age_timer: AtomicInterval::default(),
+            disk_compaction_timer: AtomicInterval::default(),
             bins,
             startup: AtomicBool::default(),
             threads,
@@ -451,6 +462,19 @@ impl<T: IndexValue, U: DiskIndexValue + From<T> + Into<T>> BucketMapHolder<T, U>
         }
     }
 
+    /// Compacts the disk index where more than `DISK_INDEX_COMPACTION_FREE_SPACE_THRESHOLD` of
+    /// it is free, if it is time to check again
+    fn maybe_compact_disk(&self) {
+        if let Some(disk) = &self.disk
+            && !self.get_startup()
+            && self
+                .disk_compaction_timer
+                .should_update(DISK_INDEX_COMPACTION_INTERVAL_MS)
+        {
+            disk.compact(DISK_INDEX_COMPACTION_FREE_SPACE_THRESHOLD);
+        }
+    }
+
     // intended to execute in a bg thread
     pub fn background(
         &self,
@@ -521,6 +545,9 @@ impl<T: IndexValue, U: DiskIndexValue + From<T> + Into<T>> BucketMapHolder<T, U>
                     break;
                 }
             }
+            if flush {
+                self.maybe_compact_disk();
+            }
             self.stats.active_threads.fetch_sub(1, Ordering::Relaxed);
         }
     }
@@ -199,6 +199,9 @@ impl Stats {
             })
             .unwrap_or_default();
         let disk_stats = Self::get_stats(disk_per_bucket_counts);
+        if let Some(disk) = disk {
+            disk.update_free_bytes();
+        }
         let mem_per_bucket_counts = in_mem.iter().map(|bin| bin.len()).collect();
         let mem_stats = Self::get_stats(mem_per_bucket_counts);
 
@@ -442,6 +445,30 @@ impl Stats {
                         .unwrap_or_default(),
                     i64
                 ),
+                (
+                    "disk_index_fragmentation_percent",
+                    disk.map(|disk| disk.stats.index.fragmentation_percent())
+                        .unwrap_or_default(),
+                    i64
+                ),
+                (
+                    "disk_index_compactions",
+                    disk.map(|disk| disk.stats.index.compactions.swap(0, Ordering::Relaxed))
+                        .unwrap_or_default(),
+                    i64
+                ),
+                (
+                    "disk_index_compact_us",
+                    disk.map(|disk| disk.stats.index.compact_us.swap(0, Ordering::Relaxed))
+                        .unwrap_or_default(),
+                    i64
+                ),
+                (
+                    "disk_index_compacted_bytes",
+                    disk.map(|disk| disk.stats.index.compacted_bytes.swap(0, Ordering::Relaxed))
+                        .unwrap_or_default(),
+                    i64
+                ),
                 (
                     "disk_index_find_index_entry_mut_us",
                     disk.map(|disk| disk
@@ -474,6 +501,30 @@ impl Stats {
                         .unwrap_or_default(),
                     i64
                 ),
+                (
+                    "disk_data_fragmentation_percent",
+                    disk.map(|disk| disk.stats.data.fragmentation_percent())
+                        .unwrap_or_default(),
+                    i64
+                ),
+                (
+                    "disk_data_compactions",
+                    disk.map(|disk| disk.stats.data.compactions.swap(0, Ordering::Relaxed))
+                        .unwrap_or_default(),
+                    i64
+                ),
+                (
+                    "disk_data_compact_us",
+                    disk.map(|disk| disk.stats.data.compact_us.swap(0, Ordering::Relaxed))
+                        .unwrap_or_default(),
+                    i64
+                ),
+                (
+                    "disk_data_compacted_bytes",
+                    disk.map(|disk| disk.stats.data.compacted_bytes.swap(0, Ordering::Relaxed))
+                        .unwrap_or_default(),
+                    i64
+                ),
                 (
                     "disk_data_file_count",
                     disk.map(|disk| disk.stats.data.file_count.load(Ordering::Relaxed))
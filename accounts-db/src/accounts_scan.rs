@@ -94,7 +94,8 @@ impl ScanTracker {
 pub(crate) struct ScanGuard<'a> {
     scan_tracker: &'a ScanTracker,
     max_root: Slot,
-    scan_bank_id: BankId,
+    /// The bank at the tip of the scanned fork, if the scan is tied to a bank
+    scan_bank_id: Option<BankId>,
 }
 
 impl<'a> ScanGuard<'a> {
@@ -116,6 +117,29 @@ impl<'a> ScanGuard<'a> {
             }
         }
 
+        Some(Self::pin(
+            scan_tracker,
+            Some(scan_bank_id),
+            max_root_inclusive_fn,
+        ))
+    }
+
+    /// Pins `max_root` in `ongoing_scan_roots` for a reader that is not tied to a bank, and so
+    /// can never be removed, such as a forked view of the accounts.
+    ///
+    /// `max_root_inclusive_fn` is called while holding the `ongoing_scan_roots` write lock.
+    pub(crate) fn new_without_bank(
+        scan_tracker: &'a ScanTracker,
+        max_root_inclusive_fn: impl FnOnce() -> Slot,
+    ) -> Self {
+        Self::pin(scan_tracker, None, max_root_inclusive_fn)
+    }
+
+    fn pin(
+        scan_tracker: &'a ScanTracker,
+        scan_bank_id: Option<BankId>,
+        max_root_inclusive_fn: impl FnOnce() -> Slot,
+    ) -> Self {
         let max_root_inclusive = {
             let mut w_ongoing_scan_roots = scan_tracker
                 // This lock is also grabbed by clean_accounts(), so clean
@@ -144,11 +168,11 @@ impl<'a> ScanGuard<'a> {
         };
 
         scan_tracker.active_scans.fetch_add(1, Ordering::Relaxed);
-        Some(Self {
+        Self {
             scan_tracker,
             max_root: max_root_inclusive,
             scan_bank_id,
-        })
+        }
     }
 
     /// The inclusive max root pinned by this scan guard.
@@ -236,11 +260,13 @@ impl<'a> ScanGuard<'a> {
     /// Finalize the scan: returns whether the bank was removed during the scan.
     /// The `Drop` impl handles unpinning regardless of whether this is called.
     pub(crate) fn was_scan_corrupted(self) -> bool {
-        self.scan_tracker
-            .removed_bank_ids
-            .lock()
-            .unwrap()
-            .contains(&self.scan_bank_id)
+        self.scan_bank_id.is_some_and(|scan_bank_id| {
+            self.scan_tracker
+                .removed_bank_ids
+                .lock()
+                .unwrap()
+                .contains(&scan_bank_id)
+        })
     }
 }
 
@@ -0,0 +1,404 @@
+//! Progress of verifying the accounts of snapshot archives at startup.
+//!
+//! Booting from snapshot archives unpacks their storages, and generating the index hashes every
+//! account in them into the accounts lt hash that the snapshot is verified against. Hashing is
+//! most of that work, so it is recorded as it goes: a startup interrupted before verification
+//! completes resumes from the storages already hashed when it boots from the same archives again.
+//!
+//! The progress file starts with a header identifying the archives, followed by a record per
+//! batch of storages hashed by an index generation thread:
+//!
+//! ```text
+//! header: | magic (8 bytes) | num archives (8 bytes) | slot and hash (40 bytes/archive) |
+//! record: | num storages (8 bytes) | slots (8 bytes/storage) | lt hash (2048 bytes) | checksum (32 bytes) |
+//! ```
+//!
+//! A storage is identified by its slot, as the storages of an archive each have their own slot.
+//! The lt hash of a record covers the accounts its storages contribute to the snapshot, so it
+//! excludes obsolete accounts. Those are the same every time the same archives are unpacked, so
+//! unlike the storage scan cache, storages with obsolete accounts are recorded too.
+//!
+//! A record left partially written by an interruption fails its checksum, and is dropped along
+//! with any record after it.
+
+use {
+    log::*,
+    solana_clock::Slot,
+    solana_hash::Hash,
+    solana_lattice_hash::{batch, lt_hash::LtHash},
+    std::{
+        collections::HashSet,
+        fs::{self, File, OpenOptions},
+        io::{self, Write as _},
+        mem,
+        path::{Path, PathBuf},
+        sync::Mutex,
+    },
+};
+
+const FILE_MAGIC: [u8; 8] = *b"AGVVRFY1";
+const LT_HASH_SIZE: usize = LtHash::NUM_ELEMENTS * size_of::<u16>();
+const CHECKSUM_SIZE: usize = 32;
+/// Number of storages an index generation thread hashes before recording them. This bounds the
+/// work each thread loses when startup is interrupted.
+const BATCH_NUM_STORAGES: usize = 1024;
+const TMP_FILE_EXTENSION: &str = "tmp";
+
+/// The storages hashed by an earlier startup, and the sum of their lt hashes
+#[derive(Debug)]
+pub(crate) struct VerifiedStorages {
+    pub(crate) slots: HashSet<Slot>,
+    pub(crate) lt_hash: LtHash,
+}
+
+impl Default for VerifiedStorages {
+    fn default() -> Self {
+        Self {
+            slots: HashSet::default(),
+            lt_hash: LtHash::identity(),
+        }
+    }
+}
+
+/// A batch of storages, as recorded in the progress file
+#[derive(Debug, PartialEq)]
+struct Record {
+    slots: Vec<Slot>,
+    lt_hash: LtHash,
+}
+
+impl Record {
+    fn encode(slots: &[Slot], lt_hash: &LtHash, out: &mut Vec<u8>) {
+        let start = out.len();
+        out.extend_from_slice(&(slots.len() as u64).to_le_bytes());
+        for slot in slots {
+            out.extend_from_slice(&slot.to_le_bytes());
+        }
+        for element in lt_hash.0 {
+            out.extend_from_slice(&element.to_le_bytes());
+        }
+        let checksum = blake3::hash(&out[start..]);
+        out.extend_from_slice(checksum.as_bytes());
+    }
+
+    /// Decodes the record at the start of `bytes`, returning it and its length
+    fn decode(bytes: &[u8]) -> Option<(Self, usize)> {
+        let num_slots = usize::try_from(read_u64(bytes, 0)?).ok()?;
+        let contents_len = num_slots
+            .checked_mul(size_of::<Slot>())?
+            .checked_add(size_of::<u64>() + LT_HASH_SIZE)?;
+        let checksum = bytes.get(contents_len..contents_len.checked_add(CHECKSUM_SIZE)?)?;
+        let contents = &bytes[..contents_len];
+        if blake3::hash(contents).as_bytes() != checksum {
+            return None;
+        }
+
+        let (slots, lt_hash_bytes) =
+            contents[size_of::<u64>()..].split_at(num_slots * size_of::<Slot>());
+        let slots = slots
+            .chunks_exact(size_of::<Slot>())
+            .map(|slot| Slot::from_le_bytes(slot.try_into().unwrap()))
+            .collect();
+        let mut lt_hash = LtHash::identity();
+        for (element, bytes) in lt_hash.0.iter_mut().zip(lt_hash_bytes.chunks_exact(2)) {
+            *element = u16::from_le_bytes(bytes.try_into().unwrap());
+        }
+        Some((Self { slots, lt_hash }, contents_len + CHECKSUM_SIZE))
+    }
+}
+
+/// The progress file of verifying the accounts of snapshot archives
+#[derive(Debug)]
+pub struct AccountsVerificationProgress {
+    path: PathBuf,
+    header: Vec<u8>,
+    /// The records in the file when it was opened, until `resume` takes them
+    loaded: Mutex<Vec<Record>>,
+    /// The file records are appended to, once `resume` has rewritten it
+    file: Mutex<Option<File>>,
+}
+
+impl AccountsVerificationProgress {
+    /// Opens the progress file at `path` for booting from `archives`, the slot and hash of each
+    /// snapshot archive the storages are unpacked from
+    ///
+    /// The records in the file are only loaded if it was written for the same archives.
+    pub fn open(path: impl Into<PathBuf>, archives: &[(Slot, Hash)]) -> Self {
+        let path = path.into();
+        let mut header = Vec::new();
+        header.extend_from_slice(&FILE_MAGIC);
+        header.extend_from_slice(&(archives.len() as u64).to_le_bytes());
+        for (slot, hash) in archives {
+            header.extend_from_slice(&slot.to_le_bytes());
+            header.extend_from_slice(hash.as_ref());
+        }
+
+        let loaded = match fs::read(&path) {
+            Ok(bytes) => bytes
+                .strip_prefix(header.as_slice())
+                .map(|mut bytes| {
+                    let mut records = Vec::new();
+                    while let Some((record, len)) = Record::decode(bytes) {
+                        records.push(record);
+                        bytes = &bytes[len..];
+                    }
+                    records
+                })
+                .unwrap_or_default(),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => {
+                warn!(
+                    "Unable to read accounts verification progress '{}': {err}",
+                    path.display(),
+                );
+                Vec::new()
+            }
+        };
+        Self {
+            path,
+            header,
+            loaded: Mutex::new(loaded),
+            file: Mutex::default(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the storages, among those in `slots`, that were hashed by an earlier startup
+    ///
+    /// A record with a storage not in `slots`, or one already covered by an earlier record, is
+    /// dropped. The file is rewritten with the records kept, and the storages of the dropped
+    /// records are hashed and recorded again.
+    pub(crate) fn resume(&self, slots: &HashSet<Slot>) -> VerifiedStorages {
+        let mut verified = VerifiedStorages::default();
+        let mut contents = self.header.clone();
+        for record in mem::take(&mut *self.loaded.lock().unwrap()) {
+            let is_usable = record
+                .slots
+                .iter()
+                .all(|slot| slots.contains(slot) && !verified.slots.contains(slot));
+            if is_usable {
+                Record::encode(&record.slots, &record.lt_hash, &mut contents);
+                verified.slots.extend(&record.slots);
+                verified.lt_hash.mix_in(&record.lt_hash);
+            }
+        }
+        match self.rewrite(&contents) {
+            Ok(file) => *self.file.lock().unwrap() = Some(file),
+            Err(err) => warn!(
+                "Unable to write accounts verification progress '{}': {err}",
+                self.path.display(),
+            ),
+        }
+        verified
+    }
+
+    /// Removes the progress file, once the snapshot has been verified or has failed verification
+    pub fn remove(&self) {
+        *self.file.lock().unwrap() = None;
+        match fs::remove_file(&self.path) {
+            Ok(()) => {}
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => warn!(
+                "Unable to remove accounts verification progress '{}': {err}",
+                self.path.display(),
+            ),
+        }
+    }
+
+    /// Writes `contents` via a temporary file, and opens the result for appending records
+    fn rewrite(&self, contents: &[u8]) -> io::Result<File> {
+        let tmp_path = self.path.with_extension(TMP_FILE_EXTENSION);
+        fs::write(&tmp_path, contents)?;
+        fs::rename(&tmp_path, &self.path)?;
+        OpenOptions::new().append(true).open(&self.path)
+    }
+
+    /// Appends a record of the storages in `slots`, whose accounts hash to `lt_hash`
+    fn record(&self, slots: &[Slot], lt_hash: &LtHash) {
+        let mut bytes = Vec::with_capacity(
+            size_of::<u64>() + slots.len() * size_of::<Slot>() + LT_HASH_SIZE + CHECKSUM_SIZE,
+        );
+        Record::encode(slots, lt_hash, &mut bytes);
+        let mut file = self.file.lock().unwrap();
+        if let Some(writer) = file.as_mut()
+            && let Err(err) = writer.write_all(&bytes)
+        {
+            warn!(
+                "Unable to write accounts verification progress '{}': {err}",
+                self.path.display(),
+            );
+            // A partially written record hides the ones after it, so stop recording
+            *file = None;
+        }
+    }
+}
+
+/// The storages an index generation thread has hashed since it last recorded them
+pub(crate) struct VerificationBatch<'a> {
+    progress: &'a AccountsVerificationProgress,
+    verified: &'a VerifiedStorages,
+    slots: Vec<Slot>,
+    lt_hash_acc: batch::Accumulator,
+}
+
+impl<'a> VerificationBatch<'a> {
+    pub(crate) fn new(
+        progress: &'a AccountsVerificationProgress,
+        verified: &'a VerifiedStorages,
+    ) -> Self {
+        Self {
+            progress,
+            verified,
+            slots: Vec::with_capacity(BATCH_NUM_STORAGES),
+            lt_hash_acc: batch::Accumulator::new(),
+        }
+    }
+
+    /// Returns true if the storage in `slot` was hashed by an earlier startup
+    pub(crate) fn is_verified(&self, slot: Slot) -> bool {
+        self.verified.slots.contains(&slot)
+    }
+
+    /// The accumulator to hash the accounts of the next storage into
+    pub(crate) fn lt_hash_acc(&mut self) -> &mut batch::Accumulator {
+        &mut self.lt_hash_acc
+    }
+
+    /// Adds the storage in `slot`, whose accounts were hashed into `lt_hash_acc()`
+    ///
+    /// Once the batch is full, it is recorded and its lt hash is returned.
+    pub(crate) fn push(&mut self, slot: Slot) -> Option<LtHash> {
+        self.slots.push(slot);
+        (self.slots.len() >= BATCH_NUM_STORAGES).then(|| self.record())
+    }
+
+    /// Records the storages added so far, and returns their lt hash
+    pub(crate) fn record(&mut self) -> LtHash {
+        let lt_hash = self.lt_hash_acc.take_lt_hash();
+        if !self.slots.is_empty() {
+            self.progress.record(&self.slots, &lt_hash);
+            self.slots.clear();
+        }
+        lt_hash
+    }
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> Option<u64> {
+    let bytes = bytes.get(offset..offset + size_of::<u64>())?;
+    Some(u64::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, tempfile::TempDir};
+
+    fn new_lt_hash(seed: u16) -> LtHash {
+        let mut lt_hash = LtHash::identity();
+        lt_hash.0[0] = seed;
+        lt_hash.0[LtHash::NUM_ELEMENTS - 1] = seed.wrapping_mul(3);
+        lt_hash
+    }
+
+    fn record(progress: &AccountsVerificationProgress, slots: &[Slot], lt_hash: &LtHash) {
+        let verified = VerifiedStorages::default();
+        let mut batch = VerificationBatch::new(progress, &verified);
+        batch.lt_hash_acc().mix_in(lt_hash);
+        for slot in slots {
+            assert!(batch.push(*slot).is_none());
+        }
+        assert_eq!(&batch.record(), lt_hash);
+    }
+
+    #[test]
+    fn test_resume() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("progress");
+        let archives = [(100, Hash::new_unique()), (150, Hash::new_unique())];
+        let slots: HashSet<_> = (1..=6).collect();
+
+        let progress = AccountsVerificationProgress::open(&path, &archives);
+        let verified = progress.resume(&slots);
+        assert!(verified.slots.is_empty());
+        record(&progress, &[1, 2], &new_lt_hash(1));
+        record(&progress, &[3], &new_lt_hash(2));
+
+        let progress = AccountsVerificationProgress::open(&path, &archives);
+        let verified = progress.resume(&slots);
+        assert_eq!(verified.slots, HashSet::from([1, 2, 3]));
+        let mut expected_lt_hash = new_lt_hash(1);
+        expected_lt_hash.mix_in(&new_lt_hash(2));
+        assert_eq!(verified.lt_hash, expected_lt_hash);
+
+        // a record with a storage that is not loaded is dropped, and so is not loaded again
+        let progress = AccountsVerificationProgress::open(&path, &archives);
+        let verified = progress.resume(&HashSet::from([1, 3, 4]));
+        assert_eq!(verified.slots, HashSet::from([3]));
+        let progress = AccountsVerificationProgress::open(&path, &archives);
+        let verified = progress.resume(&slots);
+        assert_eq!(verified.slots, HashSet::from([3]));
+
+        // progress is only resumed when booting from the same archives
+        let progress = AccountsVerificationProgress::open(&path, &archives[..1]);
+        assert!(progress.resume(&slots).slots.is_empty());
+
+        progress.remove();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_resume_partial_record() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("progress");
+        let archives = [(100, Hash::new_unique())];
+        let slots: HashSet<_> = (1..=6).collect();
+
+        let progress = AccountsVerificationProgress::open(&path, &archives);
+        progress.resume(&slots);
+        record(&progress, &[1, 2], &new_lt_hash(1));
+        record(&progress, &[3, 4], &new_lt_hash(2));
+        let len = fs::metadata(&path).unwrap().len();
+        OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_len(len - 1)
+            .unwrap();
+
+        let progress = AccountsVerificationProgress::open(&path, &archives);
+        let verified = progress.resume(&slots);
+        assert_eq!(verified.slots, HashSet::from([1, 2]));
+        assert_eq!(verified.lt_hash, new_lt_hash(1));
+    }
+
+    #[test]
+    fn test_batch_records_when_full() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("progress");
+        let archives = [(100, Hash::new_unique())];
+        let slots: HashSet<_> = (0..BATCH_NUM_STORAGES as Slot + 1).collect();
+
+        let progress = AccountsVerificationProgress::open(&path, &archives);
+        let verified = progress.resume(&slots);
+        let mut batch = VerificationBatch::new(&progress, &verified);
+        for slot in 0..BATCH_NUM_STORAGES as Slot - 1 {
+            assert!(batch.push(slot).is_none());
+        }
+        batch.lt_hash_acc().mix_in(&new_lt_hash(1));
+        assert_eq!(
+            batch.push(BATCH_NUM_STORAGES as Slot - 1),
+            Some(new_lt_hash(1)),
+        );
+        // the next storage starts a new batch, which is not recorded until it is full
+        batch.lt_hash_acc().mix_in(&new_lt_hash(2));
+        assert!(batch.push(BATCH_NUM_STORAGES as Slot).is_none());
+
+        let progress = AccountsVerificationProgress::open(&path, &archives);
+        let verified = progress.resume(&slots);
+        assert_eq!(verified.slots.len(), BATCH_NUM_STORAGES);
+        assert!(!verified.slots.contains(&(BATCH_NUM_STORAGES as Slot)));
+        assert_eq!(verified.lt_hash, new_lt_hash(1));
+    }
+}
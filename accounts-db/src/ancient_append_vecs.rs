@@ -15,6 +15,7 @@ use {
         },
         active_stats::ActiveStatItem,
         storable_accounts::{StorableAccounts, StorableAccountsBySlot},
+        storage_placement::StorageTier,
         u64_align,
     },
     rand::{Rng, rng},
@@ -550,11 +551,11 @@ impl AccountsDb {
             .storage
             .get_slot_storage_entry_shrinking_in_progress_ok(target_slot)
             .expect("ancient shrink target slot must already have a storage");
-        let (shrink_in_progress, create_and_insert_store_elapsed_us) =
-            measure_us!(self.get_store_for_shrink(target_slot, old_store, bytes));
-        let (store_accounts_stats, rewrite_elapsed_us) = measure_us!(
-            self.store_accounts_for_squash(accounts_to_write, shrink_in_progress.new_storage())
+        let (mut shrink_in_progress, create_and_insert_store_elapsed_us) = measure_us!(
+            self.get_store_for_shrink_in_tier(target_slot, old_store, bytes, StorageTier::Ancient)
         );
+        let (store_accounts_stats, rewrite_elapsed_us) =
+            measure_us!(self.store_accounts_for_squash(accounts_to_write, &mut shrink_in_progress));
 
         // Count the bytes actually written into the packed storage
         self.shrink_ancient_stats
@@ -26,7 +26,7 @@ use {
             BufReaderWithOverflow, BufferedReader, FileBufRead as _, RequiredLenBufFileRead,
             RequiredLenBufRead as _,
         },
-        file_io::{read_into_buffer, write_buffer_to_file},
+        file_io::{open_for_reading, read_into_buffer, write_buffer_to_file},
     },
     log::*,
     meta::{AccountMeta, StoredAccountNoData, StoredMeta},
@@ -42,7 +42,7 @@ use {
         path::{Path, PathBuf},
         ptr, slice,
         sync::{
-            Arc, Mutex, MutexGuard,
+            Arc, Mutex, MutexGuard, OnceLock,
             atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
         },
     },
@@ -168,6 +168,10 @@ pub struct AppendVec {
     /// will only need to be flushed once.  This avoids unnecessary syscalls/kernel work
     /// when nothing in the AppendVec has changed.
     is_dirty: AtomicBool,
+
+    /// The underlying file opened for direct IO reads, once needed.
+    /// `None` if the file could not be opened for direct IO.
+    direct_io_file: OnceLock<Option<File>>,
 }
 
 const PAGE_SIZE: usize = 4 * 1024;
@@ -244,6 +248,7 @@ impl AppendVec {
             file_size: size,
             remove_file_on_drop: AtomicBool::new(true),
             is_dirty: AtomicBool::new(false),
+            direct_io_file: OnceLock::new(),
         }
     }
 
@@ -284,6 +289,18 @@ impl AppendVec {
         self.remove_file_on_drop.store(false, Ordering::Release);
     }
 
+    /// Re-attach the on-disk file to this AppendVec's lifetime so dropping the AppendVec removes
+    /// the file again. Used when ownership of the file is handed back, e.g. once a copy of it
+    /// has taken its place.
+    pub(crate) fn enable_remove_on_drop(&self) {
+        self.remove_file_on_drop.store(true, Ordering::Release);
+    }
+
+    /// Returns true if dropping this AppendVec removes the on-disk file
+    pub(crate) fn removes_file_on_drop(&self) -> bool {
+        self.remove_file_on_drop.load(Ordering::Acquire)
+    }
+
     /// Return AppendVec opened in read-only file-io mode or `None` if it already is such
     pub(crate) fn reopen_as_readonly_file_io(&self) -> Option<Self> {
         if matches!(self.read_write_state, ReadWriteState::ReadOnly) {
@@ -353,6 +370,7 @@ impl AppendVec {
             file_size: file_info.size,
             remove_file_on_drop: AtomicBool::new(true),
             is_dirty: AtomicBool::new(false),
+            direct_io_file: OnceLock::new(),
         })
     }
 
@@ -620,6 +638,39 @@ impl AppendVec {
         }))
     }
 
+    /// Returns the size of the account stored at the start of `bytes`, without the alignment
+    /// padding, or `None` if `bytes` is too short to hold the account's metadata.
+    pub(crate) fn unaligned_stored_size_from_bytes(bytes: &[u8]) -> Option<usize> {
+        let (meta, _) = Self::get_type::<StoredMeta>(ValidSlice::new(bytes), 0)?;
+        assert!(
+            meta.data_len <= MAX_PERMITTED_DATA_LENGTH,
+            "{}",
+            meta.data_len
+        );
+        Self::calculate_unaligned_stored_size_checked(meta.data_len as usize)
+    }
+
+    /// Returns an `AccountSharedData` for the account stored at the start of `bytes`, which were
+    /// read from `offset`, or `None` if `bytes` don't hold all of the account.
+    pub(crate) fn account_shared_data_from_bytes(
+        bytes: &[u8],
+        offset: usize,
+    ) -> Option<AccountSharedData> {
+        let valid_bytes = ValidSlice::new(bytes);
+        let (meta, next) = Self::get_type::<StoredMeta>(valid_bytes, 0)?;
+        let (account_meta, next) = Self::get_type::<AccountMeta>(valid_bytes, next)?;
+        let (_hash, next) = Self::get_type::<ObsoleteAccountHash>(valid_bytes, next)?;
+        let (data, stored_size) = Self::get_slice(valid_bytes, next, meta.data_len as usize)?;
+        let account = StoredAccountMeta {
+            meta,
+            account_meta,
+            data,
+            offset,
+            stored_size,
+        };
+        Some(create_account_shared_data(&account))
+    }
+
     /// return an `AccountSharedData` for an account at `offset`.
     /// This fn can efficiently return exactly what is needed by a caller.
     /// This is on the critical path of tx processing for accounts not in the read or write caches.
@@ -722,6 +773,30 @@ impl AppendVec {
         self.path.as_path()
     }
 
+    /// Returns the file to read the data from, opened with `O_DIRECT` if `use_direct_io` is true
+    ///
+    /// The file for direct IO is opened on first use and then kept open alongside the regular
+    /// one. If it can't be opened, e.g. because the file system doesn't support direct IO, the
+    /// regular file is returned instead.
+    pub(crate) fn file_for_reading(&self, use_direct_io: bool) -> &File {
+        if !use_direct_io {
+            return &self.file;
+        }
+        self.direct_io_file
+            .get_or_init(|| {
+                open_for_reading(&self.path, true)
+                    .inspect_err(|err| {
+                        debug!(
+                            "Failed to open {} for direct IO: {err}",
+                            self.path.display()
+                        )
+                    })
+                    .ok()
+            })
+            .as_ref()
+            .unwrap_or(&self.file)
+    }
+
     /// help with the math of offsets when navigating the on-disk layout in an AppendVec.
     /// data is at the end of each account and is variable sized
     /// the next account is then aligned on a 64 bit boundary.
@@ -1050,6 +1125,34 @@ impl AppendVec {
     pub(crate) fn open_file_for_archive(&self) -> OpenFileForArchive<'_> {
         OpenFileForArchive::Borrowed(&self.file)
     }
+
+    /// Appends the bytes of `account`, exactly as `append_accounts()` would store them, to `out`.
+    ///
+    /// Used to produce AppendVec-formatted files from storages in other formats.
+    pub(crate) fn encode_account(
+        pubkey: &Pubkey,
+        account: &impl ReadableAccount,
+        out: &mut Vec<u8>,
+    ) {
+        let data = account.data();
+        let start = out.len();
+        // StoredMeta
+        out.extend_from_slice(&0u64.to_ne_bytes());
+        out.extend_from_slice(&(data.len() as u64).to_ne_bytes());
+        out.extend_from_slice(pubkey.as_ref());
+        // AccountMeta, including the padding after `executable`
+        out.extend_from_slice(&account.lamports().to_ne_bytes());
+        out.extend_from_slice(&account.rent_epoch().to_ne_bytes());
+        out.extend_from_slice(account.owner().as_ref());
+        out.push(u8::from(account.executable()));
+        out.resize(
+            start + mem::size_of::<StoredMeta>() + mem::size_of::<AccountMeta>(),
+            0,
+        );
+        out.extend_from_slice(&ObsoleteAccountHash::ZEROED.0);
+        out.extend_from_slice(data);
+        out.resize(start + Self::calculate_stored_size(data.len()), 0);
+    }
 }
 
 /// Create a reusable buffered reader tuned for scanning storages with account data.
@@ -0,0 +1,285 @@
+//! Loading of many accounts from storages at once.
+//!
+//! Single account loads read the storage with one blocking read each, which makes loading many
+//! accounts that are not in the page cache bound by the latency of every read. Scans of the
+//! accounts index, and loads of many accounts, such as those of `getMultipleAccounts` or of a
+//! transaction batch, instead hand their reads to a [`BatchedStorageReader`], which submits them
+//! in batches, so that the device serves them in parallel.
+//!
+//! Setting up a reader creates an io_uring instance and allocates its read buffer, so each
+//! thread keeps its reader and reuses it for the loads it runs, see
+//! [`BatchedStorageReader::with_thread_reader`].
+
+use {
+    crate::{accounts_file::AccountsFile, append_vec::AppendVec},
+    agave_fs::{
+        FileSize, IoSize,
+        batched_reader::{BatchedFileRead, ReadRequest, batched_file_reader},
+        io_setup::IoSetupState,
+    },
+    log::*,
+    solana_account::AccountSharedData,
+    std::{cell::RefCell, fmt, io},
+};
+
+/// The number of accounts loaded in a single batch
+pub const LOAD_BATCH_SIZE: usize = 1024;
+
+/// The size of the first read of each account, which covers the metadata and data of most
+/// accounts. Accounts with more data are read again, in full.
+const INITIAL_READ_LEN: IoSize = 4096;
+
+/// The memory used for reads in flight
+const READ_BUFFER_SIZE: usize = 8 * 1024 * 1024;
+
+/// How account storages are read when loading many accounts at once
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum StorageReadIo {
+    /// Blocking reads, one account at a time
+    #[default]
+    Sync,
+    /// Reads submitted to io_uring in batches
+    IoUring,
+    /// Reads submitted to io_uring in batches, with direct IO, which bypasses the page cache
+    IoUringDirect,
+}
+
+impl StorageReadIo {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Sync => "sync",
+            Self::IoUring => "io-uring",
+            Self::IoUringDirect => "io-uring-direct",
+        }
+    }
+}
+
+impl fmt::Display for StorageReadIo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+thread_local! {
+    /// The reader of the current thread, kept between loads
+    static THREAD_READER: RefCell<Option<BatchedStorageReader>> = const { RefCell::new(None) };
+}
+
+/// Loads batches of accounts from storages
+pub struct BatchedStorageReader {
+    reader: Box<dyn BatchedFileRead>,
+    read_io: StorageReadIo,
+    use_direct_io: bool,
+    /// Accounts read in the current batch whose data did not fit into the first read, along with
+    /// their stored size
+    reread: Vec<(usize, usize)>,
+}
+
+impl BatchedStorageReader {
+    /// Creates a reader for `read_io`, or returns `None` if accounts should be loaded one by one
+    pub fn new(read_io: StorageReadIo) -> Option<Self> {
+        let use_direct_io = match read_io {
+            StorageReadIo::Sync => return None,
+            StorageReadIo::IoUring => false,
+            StorageReadIo::IoUringDirect => true,
+        };
+        let io_setup = IoSetupState::default().with_direct_io(use_direct_io);
+        match batched_file_reader(READ_BUFFER_SIZE, &io_setup) {
+            Ok(reader) => Some(Self {
+                reader,
+                read_io,
+                use_direct_io,
+                reread: Vec::new(),
+            }),
+            Err(err) => {
+                warn!("Failed to create batched reader for {read_io} storage reads: {err}");
+                None
+            }
+        }
+    }
+
+    /// Calls `f` with the reader of the current thread for `read_io`, which is created on first
+    /// use and kept for later calls, or with `None` if accounts should be loaded one by one
+    pub fn with_thread_reader<R>(
+        read_io: StorageReadIo,
+        f: impl FnOnce(Option<&mut Self>) -> R,
+    ) -> R {
+        // The reader is taken out while in use, so a nested call creates a reader of its own
+        let mut reader = THREAD_READER
+            .take()
+            .filter(|reader| reader.read_io == read_io)
+            .or_else(|| Self::new(read_io));
+        let result = f(reader.as_mut());
+        THREAD_READER.set(reader);
+        result
+    }
+
+    /// Loads the accounts at the given offsets of their storages
+    ///
+    /// Calls `callback` with the index of each account in `accounts` and the account, or `None`
+    /// if there is no account at its offset. Callbacks are made in no particular order.
+    pub fn load_accounts(
+        &mut self,
+        accounts: &[(&AccountsFile, usize)],
+        mut callback: impl FnMut(usize, Option<AccountSharedData>),
+    ) -> io::Result<()> {
+        let mut indexes = Vec::with_capacity(accounts.len());
+        let mut requests = Vec::with_capacity(accounts.len());
+        for (index, (accounts_file, offset)) in accounts.iter().enumerate() {
+            match accounts_file {
+                AccountsFile::AppendVec(av) => {
+                    indexes.push(index);
+                    requests.push(ReadRequest {
+                        file: av.file_for_reading(self.use_direct_io),
+                        offset: *offset as FileSize,
+                        len: INITIAL_READ_LEN,
+                        valid_file_len: av.len() as FileSize,
+                    });
+                }
+                // cold storages decompress whole blocks, which is not worth batching
+                AccountsFile::ColdStorage(cs) => {
+                    callback(index, cs.get_account_shared_data(*offset));
+                }
+            }
+        }
+
+        self.reread.clear();
+        self.reader
+            .read_batch(&requests, &mut |request_index, bytes| {
+                let index = indexes[request_index];
+                match AppendVec::unaligned_stored_size_from_bytes(bytes) {
+                    Some(stored_size) if stored_size <= bytes.len() => {
+                        callback(
+                            index,
+                            AppendVec::account_shared_data_from_bytes(
+                                &bytes[..stored_size],
+                                accounts[index].1,
+                            ),
+                        );
+                    }
+                    Some(stored_size) => self.reread.push((index, stored_size)),
+                    None => callback(index, None),
+                }
+            })?;
+        if self.reread.is_empty() {
+            return Ok(());
+        }
+
+        // Read the accounts with more data again, in full
+        indexes.clear();
+        requests.clear();
+        for &(index, stored_size) in &self.reread {
+            let (accounts_file, offset) = accounts[index];
+            let AccountsFile::AppendVec(av) = accounts_file else {
+                unreachable!("only append vecs are read in batches");
+            };
+            indexes.push(index);
+            requests.push(ReadRequest {
+                file: av.file_for_reading(self.use_direct_io),
+                offset: offset as FileSize,
+                len: stored_size as IoSize,
+                valid_file_len: av.len() as FileSize,
+            });
+        }
+        self.reader
+            .read_batch(&requests, &mut |request_index, bytes| {
+                let index = indexes[request_index];
+                callback(
+                    index,
+                    AppendVec::account_shared_data_from_bytes(bytes, accounts[index].1),
+                );
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::append_vec::test_utils::get_append_vec_path,
+        solana_account::{ReadableAccount, WritableAccount},
+        solana_clock::Slot,
+        solana_pubkey::Pubkey,
+        test_case::test_case,
+    };
+
+    #[test_case(StorageReadIo::IoUring)]
+    #[test_case(StorageReadIo::IoUringDirect)]
+    fn test_load_accounts(read_io: StorageReadIo) {
+        // accounts with data that fits into the first read, and accounts that have to be read again
+        let accounts: Vec<_> = [0, 1, 165, 4096, 100_000, 3, 20_000]
+            .into_iter()
+            .enumerate()
+            .map(|(i, data_len)| {
+                let mut account =
+                    AccountSharedData::new(i as u64 + 1, data_len, &Pubkey::new_unique());
+                account.data_as_mut_slice().fill(i as u8);
+                (Pubkey::new_unique(), account)
+            })
+            .collect();
+        let path = get_append_vec_path("test_load_accounts");
+        let file_size = accounts
+            .iter()
+            .map(|(_, account)| AppendVec::calculate_stored_size(account.data().len()))
+            .sum();
+        let accounts_file = AccountsFile::AppendVec(AppendVec::new(&path.path, file_size));
+        let offsets = accounts_file
+            .write_accounts(&(Slot::MAX, accounts.as_slice()))
+            .unwrap()
+            .unwrap()
+            .offsets;
+
+        // load every account twice, and past the end of the storage
+        let mut requests: Vec<_> = offsets
+            .iter()
+            .chain(offsets.iter().rev())
+            .map(|offset| (&accounts_file, *offset))
+            .collect();
+        requests.push((&accounts_file, accounts_file.len()));
+
+        let mut reader = BatchedStorageReader::new(read_io).unwrap();
+        let mut loaded = vec![None; requests.len()];
+        reader
+            .load_accounts(&requests, |index, account| {
+                assert!(loaded[index].replace(account).is_none());
+            })
+            .unwrap();
+        for ((accounts_file, offset), loaded) in requests.iter().zip(loaded) {
+            assert_eq!(
+                loaded.unwrap(),
+                accounts_file.get_account_shared_data(*offset),
+            );
+        }
+    }
+
+    #[test]
+    fn test_sync_has_no_reader() {
+        assert!(BatchedStorageReader::new(StorageReadIo::Sync).is_none());
+        assert!(BatchedStorageReader::with_thread_reader(
+            StorageReadIo::Sync,
+            |reader| reader.is_none()
+        ));
+    }
+
+    #[test]
+    fn test_thread_reader_is_reused() {
+        let reader_ptr = |read_io| {
+            BatchedStorageReader::with_thread_reader(read_io, |reader| {
+                std::ptr::from_ref(reader.unwrap().reader.as_ref()).cast::<()>()
+            })
+        };
+        let reader = reader_ptr(StorageReadIo::IoUring);
+        assert_eq!(reader_ptr(StorageReadIo::IoUring), reader);
+        // a reader for another kind of io replaces the kept one
+        let direct_reader = reader_ptr(StorageReadIo::IoUringDirect);
+        assert_eq!(reader_ptr(StorageReadIo::IoUringDirect), direct_reader);
+        // nested calls get a reader of their own
+        BatchedStorageReader::with_thread_reader(StorageReadIo::IoUringDirect, |outer| {
+            let inner = reader_ptr(StorageReadIo::IoUringDirect);
+            assert_ne!(
+                std::ptr::from_ref(outer.unwrap().reader.as_ref()).cast::<()>(),
+                inner
+            );
+        });
+    }
+}
@@ -0,0 +1,1270 @@
+//! Compressed, read-optimized storage for cold accounts.
+//!
+//! Accounts that have been shrunk or packed into ancient storages are rarely written again, so
+//! their data is worth compressing. A cold storage file is a sequence of segments, one per
+//! write, following an 8 byte file magic:
+//!
+//! ```text
+//! +----------------+---------------------+----------------+------------------------+
+//! | segment header | index entries       | block entries  | zstd compressed blocks |
+//! | (32 bytes)     | (112 bytes/account) | (16 bytes/blk) | (padded to 8 bytes)    |
+//! +----------------+---------------------+----------------+------------------------+
+//! ```
+//!
+//! Each index entry holds an account's metadata plus the location of its data within the
+//! decompressed blocks, so metadata-only reads and scans never decompress anything. Account data
+//! is packed into blocks of roughly `TARGET_BLOCK_SIZE` bytes before compression.
+//!
+//! Accounts are addressed by the offset they would have in an equivalent AppendVec. This keeps
+//! storage lengths, stored sizes, dead bytes and shrink heuristics identical across formats, and
+//! lets [`AppendVecEncoder`] turn a cold storage back into an AppendVec byte for byte, which is
+//! how cold storages are written into snapshot archives.
+
+use {
+    crate::{
+        account_info::Offset,
+        account_storage::stored_account_info::{StoredAccountInfo, StoredAccountInfoWithoutData},
+        accounts_file::{ALIGN_BOUNDARY_OFFSET, OpenFileForArchive, StoredAccountsInfo},
+        append_vec::{AppendVec, MAXIMUM_APPEND_VEC_FILE_SIZE},
+        storable_accounts::StorableAccounts,
+        u64_align,
+        utils::create_account_shared_data,
+    },
+    agave_fs::{
+        FileInfo, FileSize,
+        file_io::{read_into_buffer, write_buffer_to_file},
+    },
+    log::*,
+    solana_account::{AccountSharedData, ReadableAccount},
+    solana_clock::Epoch,
+    solana_pubkey::Pubkey,
+    solana_system_interface::MAX_PERMITTED_DATA_LENGTH,
+    std::{
+        collections::VecDeque,
+        fs::{File, OpenOptions, remove_file},
+        io::{self, Read},
+        path::{Path, PathBuf},
+        sync::{
+            Arc, Mutex, RwLock,
+            atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        },
+    },
+    thiserror::Error,
+};
+
+/// Identifies a cold storage file; AppendVecs start with a zero write version instead.
+const FILE_MAGIC: [u8; 8] = *b"AGVCOLD1";
+const SEGMENT_MAGIC: u64 = u64::from_le_bytes(*b"AGVCSEG1");
+const SEGMENT_HEADER_SIZE: usize = 32;
+const INDEX_ENTRY_SIZE: usize = 112;
+const BLOCK_ENTRY_SIZE: usize = 16;
+/// Uncompressed size at which a block is closed. Accounts larger than this get a block of their own.
+const TARGET_BLOCK_SIZE: usize = 64 * 1024;
+const COMPRESSION_LEVEL: i32 = 3;
+/// Number of index entries read at once when scanning
+const SCAN_CHUNK_LEN: usize = 1024;
+/// Number of decompressed blocks kept for loads of single accounts, which tend to hit the same
+/// few blocks of a storage in a row
+const BLOCK_CACHE_LEN: usize = 4;
+
+pub type Result<T> = std::result::Result<T, ColdStorageError>;
+
+/// An enum for ColdStorage related errors.
+#[derive(Error, Debug)]
+pub enum ColdStorageError {
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("not a cold storage file: {}", .0.display())]
+    InvalidMagic(PathBuf),
+
+    #[error("corrupt segment at offset {1} in cold storage {}", .0.display())]
+    CorruptSegment(PathBuf, u64),
+
+    #[error("corrupt account at offset {1} in cold storage {}", .0.display())]
+    CorruptAccount(PathBuf, Offset),
+}
+
+/// The fixed-size portion of an account, as stored in a segment's index
+#[derive(Debug, Clone, Copy)]
+struct IndexEntry {
+    pubkey: Pubkey,
+    owner: Pubkey,
+    lamports: u64,
+    rent_epoch: Epoch,
+    data_len: u64,
+    /// offset of this account in the equivalent AppendVec
+    offset: u64,
+    /// index of the block, within the segment, holding this account's data
+    block: u32,
+    /// offset of this account's data within the decompressed block
+    offset_in_block: u32,
+    executable: bool,
+}
+
+impl IndexEntry {
+    fn encode(&self, out: &mut Vec<u8>) {
+        let start = out.len();
+        out.extend_from_slice(self.pubkey.as_ref());
+        out.extend_from_slice(self.owner.as_ref());
+        out.extend_from_slice(&self.lamports.to_le_bytes());
+        out.extend_from_slice(&self.rent_epoch.to_le_bytes());
+        out.extend_from_slice(&self.data_len.to_le_bytes());
+        out.extend_from_slice(&self.offset.to_le_bytes());
+        out.extend_from_slice(&self.block.to_le_bytes());
+        out.extend_from_slice(&self.offset_in_block.to_le_bytes());
+        out.push(u8::from(self.executable));
+        out.resize(start + INDEX_ENTRY_SIZE, 0);
+    }
+
+    fn decode(bytes: &[u8]) -> Self {
+        debug_assert_eq!(bytes.len(), INDEX_ENTRY_SIZE);
+        Self {
+            pubkey: Pubkey::new_from_array(bytes[0..32].try_into().unwrap()),
+            owner: Pubkey::new_from_array(bytes[32..64].try_into().unwrap()),
+            lamports: read_u64(bytes, 64),
+            rent_epoch: read_u64(bytes, 72),
+            data_len: read_u64(bytes, 80),
+            offset: read_u64(bytes, 88),
+            block: read_u32(bytes, 96),
+            offset_in_block: read_u32(bytes, 100),
+            executable: bytes[104] != 0,
+        }
+    }
+
+    fn stored_size(&self) -> usize {
+        AppendVec::calculate_stored_size(self.data_len as usize)
+    }
+
+    fn account_without_data(&self) -> StoredAccountInfoWithoutData<'_> {
+        StoredAccountInfoWithoutData {
+            pubkey: &self.pubkey,
+            lamports: self.lamports,
+            owner: &self.owner,
+            data_len: self.data_len as usize,
+            executable: self.executable,
+            rent_epoch: self.rent_epoch,
+        }
+    }
+
+    fn account<'a>(&'a self, data: &'a [u8]) -> StoredAccountInfo<'a> {
+        StoredAccountInfo {
+            pubkey: &self.pubkey,
+            lamports: self.lamports,
+            owner: &self.owner,
+            data,
+            executable: self.executable,
+            rent_epoch: self.rent_epoch,
+        }
+    }
+}
+
+fn read_u64(bytes: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap())
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+}
+
+/// Location of a compressed block within the file
+#[derive(Debug, Clone, Copy)]
+struct BlockLocation {
+    offset: u64,
+    compressed_len: u32,
+    uncompressed_len: u32,
+}
+
+/// The in-memory description of one segment of the file
+#[derive(Debug)]
+struct Segment {
+    /// position of this segment's first account among all accounts in the storage
+    first_account: usize,
+    num_accounts: usize,
+    /// file offset of this segment's first index entry
+    index_offset: u64,
+    blocks: Vec<BlockLocation>,
+}
+
+#[derive(Debug, Default)]
+struct ColdIndex {
+    /// the offset of every account, in units of `ALIGN_BOUNDARY_OFFSET`, in storage order
+    offsets: Vec<u32>,
+    segments: Vec<Arc<Segment>>,
+}
+
+impl ColdIndex {
+    /// Returns the segment holding the account at `offset`, and the account's position within it
+    fn locate(&self, offset: Offset) -> Option<(Arc<Segment>, usize)> {
+        if offset % ALIGN_BOUNDARY_OFFSET != 0 {
+            return None;
+        }
+        let key = u32::try_from(offset / ALIGN_BOUNDARY_OFFSET).ok()?;
+        let position = self.offsets.binary_search(&key).ok()?;
+        let segment_index = self
+            .segments
+            .partition_point(|segment| segment.first_account <= position)
+            - 1;
+        let segment = &self.segments[segment_index];
+        Some((Arc::clone(segment), position - segment.first_account))
+    }
+}
+
+/// Collects account data into blocks and compresses each block once it is full
+#[derive(Default)]
+struct BlockBuilder {
+    current: Vec<u8>,
+    /// compressed bytes and uncompressed length of each closed block
+    blocks: Vec<(Vec<u8>, u32)>,
+}
+
+impl BlockBuilder {
+    /// Adds `data` and returns its block index and offset within that block
+    fn push(&mut self, data: &[u8]) -> io::Result<(u32, u32)> {
+        if !data.is_empty()
+            && !self.current.is_empty()
+            && self.current.len() + data.len() > TARGET_BLOCK_SIZE
+        {
+            self.close_block()?;
+        }
+        let location = (self.blocks.len() as u32, self.current.len() as u32);
+        self.current.extend_from_slice(data);
+        Ok(location)
+    }
+
+    fn close_block(&mut self) -> io::Result<()> {
+        let compressed = zstd::bulk::compress(&self.current, COMPRESSION_LEVEL)?;
+        self.blocks.push((compressed, self.current.len() as u32));
+        self.current.clear();
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<Vec<(Vec<u8>, u32)>> {
+        if !self.current.is_empty() {
+            self.close_block()?;
+        }
+        Ok(self.blocks)
+    }
+}
+
+/// Identifies a block by the first account of its segment and its index within the segment
+type BlockKey = (usize, u32);
+
+/// The most recently decompressed blocks, least recently used first
+#[derive(Debug, Default)]
+struct BlockCache {
+    blocks: VecDeque<(BlockKey, Arc<[u8]>)>,
+}
+
+impl BlockCache {
+    fn get(&mut self, key: BlockKey) -> Option<Arc<[u8]>> {
+        let position = self.blocks.iter().position(|(cached, _)| *cached == key)?;
+        let block = self.blocks.remove(position)?;
+        let data = Arc::clone(&block.1);
+        self.blocks.push_back(block);
+        Some(data)
+    }
+
+    fn insert(&mut self, key: BlockKey, data: Arc<[u8]>) {
+        if self.blocks.iter().any(|(cached, _)| *cached == key) {
+            return;
+        }
+        if self.blocks.len() >= BLOCK_CACHE_LEN {
+            self.blocks.pop_front();
+        }
+        self.blocks.push_back((key, data));
+    }
+}
+
+/// A file-backed storage of accounts whose data is compressed in blocks. Writes are serialized
+/// and each one appends a new segment; reads may happen concurrently with writes.
+#[derive(Debug)]
+pub struct ColdStorage {
+    /// The file path where the data is stored.
+    path: PathBuf,
+
+    /// the underlying file that backs this storage
+    file: File,
+
+    /// Serializes writes. `None` if the storage was opened read-only.
+    append_lock: Option<Mutex<()>>,
+
+    index: RwLock<ColdIndex>,
+
+    /// Decompressed blocks of the accounts loaded most recently
+    block_cache: Mutex<BlockCache>,
+
+    /// The number of bytes the accounts would take in an AppendVec
+    len: AtomicUsize,
+
+    /// The maximum value of `len`
+    capacity: usize,
+
+    /// The number of bytes written to the file
+    file_len: AtomicU64,
+
+    /// if true, remove file when dropped
+    remove_file_on_drop: AtomicBool,
+
+    /// Flags if there are writes that have not been flushed to disk
+    is_dirty: AtomicBool,
+}
+
+impl Drop for ColdStorage {
+    fn drop(&mut self) {
+        if self.remove_file_on_drop.load(Ordering::Acquire)
+            && let Err(err) = remove_file(&self.path)
+        {
+            warn!(
+                "ColdStorage failed to remove {}: {err}",
+                self.path.display()
+            );
+        }
+    }
+}
+
+impl ColdStorage {
+    /// Creates a new, writable ColdStorage at `path` that holds up to `capacity` bytes of
+    /// accounts, as measured in the AppendVec format.
+    pub fn new(path: impl Into<PathBuf>, capacity: usize) -> Self {
+        let path = path.into();
+        let _ignored = remove_file(&path);
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .unwrap_or_else(|err| {
+                panic!(
+                    "Unable to create cold storage file {}: {err}",
+                    path.display()
+                )
+            });
+        write_buffer_to_file(&file, &FILE_MAGIC, 0).expect("must write cold storage header");
+
+        Self {
+            path,
+            file,
+            append_lock: Some(Mutex::new(())),
+            index: RwLock::default(),
+            block_cache: Mutex::default(),
+            len: AtomicUsize::new(0),
+            capacity: capacity.min(MAXIMUM_APPEND_VEC_FILE_SIZE as usize),
+            file_len: AtomicU64::new(FILE_MAGIC.len() as u64),
+            remove_file_on_drop: AtomicBool::new(true),
+            is_dirty: AtomicBool::new(true),
+        }
+    }
+
+    /// Returns true if `file` starts with the cold storage magic
+    pub(crate) fn is_cold_storage_file(file: &File, file_size: FileSize) -> io::Result<bool> {
+        let mut magic = [0; FILE_MAGIC.len()];
+        let bytes_read = read_into_buffer(file, file_size, 0, &mut magic)?;
+        Ok(bytes_read == magic.len() && magic == FILE_MAGIC)
+    }
+
+    /// Opens the existing ColdStorage at `file_info` read-only
+    ///
+    /// Every segment header and index entry is checked, so the resulting storage length is
+    /// exactly the length of the accounts in the file.
+    pub fn new_for_startup(file_info: FileInfo) -> Result<Self> {
+        let FileInfo { file, path, size } = file_info;
+        if !Self::is_cold_storage_file(&file, size)? {
+            return Err(ColdStorageError::InvalidMagic(path));
+        }
+
+        let mut index = ColdIndex::default();
+        let mut len = 0;
+        let mut position = FILE_MAGIC.len() as u64;
+        while position < size {
+            let (segment, next_position) =
+                Self::read_segment(&file, size, position, &mut len, &mut index.offsets)
+                    .ok_or_else(|| ColdStorageError::CorruptSegment(path.clone(), position))?;
+            index.segments.push(Arc::new(segment));
+            position = next_position;
+        }
+
+        Ok(Self {
+            path,
+            file,
+            append_lock: None,
+            index: RwLock::new(index),
+            block_cache: Mutex::default(),
+            len: AtomicUsize::new(len),
+            capacity: len,
+            file_len: AtomicU64::new(size),
+            remove_file_on_drop: AtomicBool::new(true),
+            is_dirty: AtomicBool::new(false),
+        })
+    }
+
+    /// Parses and validates the segment at `position`, appending its accounts' offsets to
+    /// `offsets` and advancing `len` past them.
+    ///
+    /// Returns the segment and the file offset where the next one starts, or `None` if the
+    /// segment is malformed.
+    fn read_segment(
+        file: &File,
+        file_size: FileSize,
+        position: u64,
+        len: &mut usize,
+        offsets: &mut Vec<u32>,
+    ) -> Option<(Segment, u64)> {
+        let mut header = [0; SEGMENT_HEADER_SIZE];
+        read_exact_at(file, file_size, position, &mut header).ok()?;
+        if read_u64(&header, 0) != SEGMENT_MAGIC {
+            return None;
+        }
+        let num_accounts = usize::try_from(read_u64(&header, 8)).ok()?;
+        let num_blocks = usize::try_from(read_u64(&header, 16)).ok()?;
+        let blocks_len = read_u64(&header, 24);
+        if num_accounts == 0 {
+            return None;
+        }
+
+        let index_offset = position + SEGMENT_HEADER_SIZE as u64;
+        let block_entries_offset =
+            index_offset.checked_add(num_accounts.checked_mul(INDEX_ENTRY_SIZE)? as u64)?;
+        let blocks_offset =
+            block_entries_offset.checked_add(num_blocks.checked_mul(BLOCK_ENTRY_SIZE)? as u64)?;
+        let end = blocks_offset.checked_add(blocks_len)?;
+        if end > file_size {
+            return None;
+        }
+
+        let mut block_entries = vec![0; num_blocks * BLOCK_ENTRY_SIZE];
+        read_exact_at(file, file_size, block_entries_offset, &mut block_entries).ok()?;
+        let blocks = block_entries
+            .chunks_exact(BLOCK_ENTRY_SIZE)
+            .map(|entry| {
+                let relative_offset = read_u64(entry, 0);
+                let compressed_len = read_u32(entry, 8);
+                let uncompressed_len = read_u32(entry, 12);
+                (relative_offset.checked_add(u64::from(compressed_len))? <= blocks_len).then_some(
+                    BlockLocation {
+                        offset: blocks_offset + relative_offset,
+                        compressed_len,
+                        uncompressed_len,
+                    },
+                )
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        let first_account = offsets.len();
+        let mut entries = vec![0; SCAN_CHUNK_LEN.min(num_accounts) * INDEX_ENTRY_SIZE];
+        let mut remaining = num_accounts;
+        let mut chunk_offset = index_offset;
+        while remaining > 0 {
+            let count = remaining.min(SCAN_CHUNK_LEN);
+            let chunk = &mut entries[..count * INDEX_ENTRY_SIZE];
+            read_exact_at(file, file_size, chunk_offset, chunk).ok()?;
+            for entry in chunk.chunks_exact(INDEX_ENTRY_SIZE).map(IndexEntry::decode) {
+                if entry.offset != *len as u64 || entry.data_len > MAX_PERMITTED_DATA_LENGTH {
+                    return None;
+                }
+                if entry.data_len > 0 {
+                    let block = blocks.get(entry.block as usize)?;
+                    if u64::from(entry.offset_in_block) + entry.data_len
+                        > u64::from(block.uncompressed_len)
+                    {
+                        return None;
+                    }
+                }
+                offsets.push(u32::try_from(*len / ALIGN_BOUNDARY_OFFSET).ok()?);
+                *len += entry.stored_size();
+                if *len as u64 > MAXIMUM_APPEND_VEC_FILE_SIZE {
+                    return None;
+                }
+            }
+            remaining -= count;
+            chunk_offset += (count * INDEX_ENTRY_SIZE) as u64;
+        }
+
+        let segment = Segment {
+            first_account,
+            num_accounts,
+            index_offset,
+            blocks,
+        };
+        Some((segment, end))
+    }
+
+    /// Returns the number of bytes, *not accounts*, the accounts would use in an AppendVec
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Acquire)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of bytes, *not accounts*, the storage can hold, as measured in the
+    /// AppendVec format
+    pub fn capacity(&self) -> u64 {
+        self.capacity as u64
+    }
+
+    /// Returns the number of accounts in the storage
+    pub fn num_accounts(&self) -> usize {
+        self.index.read().unwrap().offsets.len()
+    }
+
+    /// Return the path of the underlying file.
+    pub fn path(&self) -> &Path {
+        self.path.as_path()
+    }
+
+    /// Detach the on-disk file from this storage's lifetime so dropping it no longer removes
+    /// the file.
+    pub fn disable_remove_on_drop(&self) {
+        self.remove_file_on_drop.store(false, Ordering::Release);
+    }
+
+    /// Re-attach the on-disk file to this storage's lifetime so dropping it removes the file
+    /// again.
+    pub(crate) fn enable_remove_on_drop(&self) {
+        self.remove_file_on_drop.store(true, Ordering::Release);
+    }
+
+    /// Returns true if dropping this storage removes the on-disk file
+    pub(crate) fn removes_file_on_drop(&self) -> bool {
+        self.remove_file_on_drop.load(Ordering::Acquire)
+    }
+
+    /// Flushes contents to disk
+    pub fn flush(&self) -> Result<()> {
+        if self.is_dirty.swap(false, Ordering::AcqRel) {
+            self.file.sync_all()?;
+        }
+        Ok(())
+    }
+
+    fn read_exact_at(&self, offset: u64, buffer: &mut [u8]) -> io::Result<()> {
+        read_exact_at(
+            &self.file,
+            self.file_len.load(Ordering::Acquire),
+            offset,
+            buffer,
+        )
+    }
+
+    /// Reads `count` index entries of `segment`, starting at its `first` account
+    fn read_entries(
+        &self,
+        segment: &Segment,
+        first: usize,
+        count: usize,
+    ) -> io::Result<Vec<IndexEntry>> {
+        let mut bytes = vec![0; count * INDEX_ENTRY_SIZE];
+        let offset = segment.index_offset + (first * INDEX_ENTRY_SIZE) as u64;
+        self.read_exact_at(offset, &mut bytes)?;
+        Ok(bytes
+            .chunks_exact(INDEX_ENTRY_SIZE)
+            .map(IndexEntry::decode)
+            .collect())
+    }
+
+    /// Returns the index entry of the account at `offset`, and its segment
+    fn get_entry(&self, offset: Offset) -> Option<(IndexEntry, Arc<Segment>)> {
+        let (segment, position) = self.index.read().unwrap().locate(offset)?;
+        let entry = self.read_entries(&segment, position, 1).ok()?.pop()?;
+        Some((entry, segment))
+    }
+
+    fn read_block(&self, block: &BlockLocation) -> io::Result<Vec<u8>> {
+        let mut compressed = vec![0; block.compressed_len as usize];
+        self.read_exact_at(block.offset, &mut compressed)?;
+        let data = zstd::bulk::decompress(&compressed, block.uncompressed_len as usize)?;
+        if data.len() != block.uncompressed_len as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "decompressed block has the wrong length",
+            ));
+        }
+        Ok(data)
+    }
+
+    fn corrupt_account(&self, entry: &IndexEntry) -> ColdStorageError {
+        ColdStorageError::CorruptAccount(self.path.clone(), entry.offset as Offset)
+    }
+
+    /// Returns the location of the block holding the data of the account described by `entry`
+    fn block_location<'s>(
+        &self,
+        segment: &'s Segment,
+        entry: &IndexEntry,
+    ) -> Result<&'s BlockLocation> {
+        segment
+            .blocks
+            .get(entry.block as usize)
+            .ok_or_else(|| self.corrupt_account(entry))
+    }
+
+    /// Returns the data of the account described by `entry` within its decompressed block
+    fn data_in_block<'b>(&self, entry: &IndexEntry, block_data: &'b [u8]) -> Result<&'b [u8]> {
+        let start = entry.offset_in_block as usize;
+        block_data
+            .get(start..start + entry.data_len as usize)
+            .ok_or_else(|| self.corrupt_account(entry))
+    }
+
+    /// Returns the data of the account described by `entry`, decompressing its block into
+    /// `cached_block` unless that block is already cached there.
+    fn account_data<'c>(
+        &self,
+        segment: &Segment,
+        entry: &IndexEntry,
+        cached_block: &'c mut Option<(u32, Vec<u8>)>,
+    ) -> Result<&'c [u8]> {
+        if entry.data_len == 0 {
+            return Ok(&[]);
+        }
+        if cached_block
+            .as_ref()
+            .is_none_or(|(block, _)| *block != entry.block)
+        {
+            let block = self.block_location(segment, entry)?;
+            *cached_block = Some((entry.block, self.read_block(block)?));
+        }
+        let (_, block_data) = cached_block.as_ref().unwrap();
+        self.data_in_block(entry, block_data)
+    }
+
+    /// Returns the decompressed block holding the data of the account described by `entry`,
+    /// through the block cache, so that loading several accounts of the same block decompresses
+    /// it once.
+    fn cached_block(&self, segment: &Segment, entry: &IndexEntry) -> Result<Arc<[u8]>> {
+        let key = (segment.first_account, entry.block);
+        if let Some(block_data) = self.block_cache.lock().unwrap().get(key) {
+            return Ok(block_data);
+        }
+        // decompress outside the lock, concurrent loads of the same block just race to insert it
+        let block_data = Arc::<[u8]>::from(self.read_block(self.block_location(segment, entry)?)?);
+        self.block_cache
+            .lock()
+            .unwrap()
+            .insert(key, Arc::clone(&block_data));
+        Ok(block_data)
+    }
+
+    /// Calls `callback` with the stored account at `offset`.
+    ///
+    /// Returns `None` if there is no account at `offset`, otherwise returns the result of
+    /// `callback` in `Some`. Account data is not read.
+    pub fn get_stored_account_without_data_callback<Ret>(
+        &self,
+        offset: Offset,
+        mut callback: impl for<'local> FnMut(StoredAccountInfoWithoutData<'local>) -> Ret,
+    ) -> Option<Ret> {
+        let (entry, _segment) = self.get_entry(offset)?;
+        Some(callback(entry.account_without_data()))
+    }
+
+    /// Calls `callback` with the stored account at `offset`, including its data.
+    ///
+    /// Returns `None` if there is no account at `offset`, otherwise returns the result of
+    /// `callback` in `Some`.
+    pub fn get_stored_account_callback<Ret>(
+        &self,
+        offset: Offset,
+        mut callback: impl for<'local> FnMut(StoredAccountInfo<'local>) -> Ret,
+    ) -> Option<Ret> {
+        let (entry, segment) = self.get_entry(offset)?;
+        if entry.data_len == 0 {
+            return Some(callback(entry.account(&[])));
+        }
+        let block_data = self.cached_block(&segment, &entry).ok()?;
+        let data = self.data_in_block(&entry, &block_data).ok()?;
+        Some(callback(entry.account(data)))
+    }
+
+    /// return an `AccountSharedData` for an account at `offset`, if any.  Otherwise return None.
+    pub fn get_account_shared_data(&self, offset: Offset) -> Option<AccountSharedData> {
+        self.get_stored_account_callback(offset, |account| create_account_shared_data(&account))
+    }
+
+    /// for each offset in `sorted_offsets`, get the data size
+    pub(crate) fn get_account_data_lens(&self, sorted_offsets: &[Offset]) -> Vec<usize> {
+        sorted_offsets
+            .iter()
+            .map_while(|&offset| {
+                self.get_entry(offset)
+                    .map(|(entry, _segment)| entry.data_len as usize)
+            })
+            .collect()
+    }
+
+    /// Calls `callback` with every index entry, in storage order
+    fn scan_entries(&self, mut callback: impl FnMut(&Segment, &IndexEntry)) -> Result<()> {
+        let segments = self.index.read().unwrap().segments.clone();
+        for segment in &segments {
+            let mut first = 0;
+            while first < segment.num_accounts {
+                let count = (segment.num_accounts - first).min(SCAN_CHUNK_LEN);
+                for entry in self.read_entries(segment, first, count)? {
+                    callback(segment.as_ref(), &entry);
+                }
+                first += count;
+            }
+        }
+        Ok(())
+    }
+
+    /// Iterate over all accounts and call `callback` with each account, without its data.
+    pub fn scan_accounts_without_data(
+        &self,
+        mut callback: impl for<'local> FnMut(Offset, StoredAccountInfoWithoutData<'local>),
+    ) -> Result<()> {
+        self.scan_entries(|_segment, entry| {
+            callback(entry.offset as Offset, entry.account_without_data())
+        })
+    }
+
+    /// Iterate over all accounts and call `callback` with each account, including its data.
+    ///
+    /// Each block is decompressed once, no matter how many accounts it holds.
+    pub fn scan_accounts(
+        &self,
+        mut callback: impl for<'local> FnMut(Offset, StoredAccountInfo<'local>),
+    ) -> Result<()> {
+        let mut cached_block = None;
+        let mut cached_segment = None;
+        let mut result = Ok(());
+        self.scan_entries(|segment, entry| {
+            if result.is_err() {
+                return;
+            }
+            // block indexes are per segment, so the cache is too
+            if cached_segment != Some(segment.first_account) {
+                cached_segment = Some(segment.first_account);
+                cached_block = None;
+            }
+            match self.account_data(segment, entry, &mut cached_block) {
+                Ok(data) => callback(entry.offset as Offset, entry.account(data)),
+                Err(err) => result = Err(err),
+            }
+        })?;
+        result
+    }
+
+    /// iterate over all pubkeys
+    pub fn scan_pubkeys(&self, mut callback: impl FnMut(&Pubkey)) -> Result<()> {
+        self.scan_entries(|_segment, entry| callback(&entry.pubkey))
+    }
+
+    /// Writes `accounts` as a new segment.
+    ///
+    /// Stops at the first account that would grow the storage past its capacity, and returns
+    /// `None` if no accounts fit. Otherwise, returns the offset of each account written and their
+    /// total stored size, as an AppendVec would.
+    ///
+    /// If compressing or writing the segment fails, the error is returned and the storage is left
+    /// as it was, since a segment only becomes visible once it has been written.
+    pub fn write_accounts<'a>(
+        &self,
+        accounts: &impl StorableAccounts<'a>,
+    ) -> Result<Option<StoredAccountsInfo>> {
+        let _lock = self
+            .append_lock
+            .as_ref()
+            .expect("write not allowed on read-only cold storage")
+            .lock()
+            .unwrap();
+        let start_offset = self.len();
+        let mut offset = start_offset;
+        let mut entries = Vec::with_capacity(accounts.len());
+        let mut blocks = BlockBuilder::default();
+        for i in 0..accounts.len() {
+            let fits = accounts.account_default_if_zero_lamport(i, |account| {
+                let data = account.data();
+                let stored_size = AppendVec::calculate_stored_size(data.len());
+                if offset + stored_size > self.capacity {
+                    return Ok(false);
+                }
+                let (block, offset_in_block) = blocks.push(data)?;
+                entries.push(IndexEntry {
+                    pubkey: *account.pubkey(),
+                    owner: *account.owner(),
+                    lamports: account.lamports(),
+                    rent_epoch: account.rent_epoch(),
+                    data_len: data.len() as u64,
+                    offset: offset as u64,
+                    block,
+                    offset_in_block,
+                    executable: account.executable(),
+                });
+                offset += stored_size;
+                io::Result::Ok(true)
+            })?;
+            if !fits {
+                break;
+            }
+        }
+        if entries.is_empty() {
+            return Ok(None);
+        }
+        let blocks = blocks.finish()?;
+
+        let segment_start = self.file_len.load(Ordering::Acquire);
+        let compressed_len: usize = blocks.iter().map(|(compressed, _)| compressed.len()).sum();
+        let blocks_len = u64_align!(compressed_len);
+        let block_entries_len = blocks.len() * BLOCK_ENTRY_SIZE;
+        let segment_len =
+            SEGMENT_HEADER_SIZE + entries.len() * INDEX_ENTRY_SIZE + block_entries_len + blocks_len;
+        let mut buffer = Vec::with_capacity(segment_len);
+        buffer.extend_from_slice(&SEGMENT_MAGIC.to_le_bytes());
+        buffer.extend_from_slice(&(entries.len() as u64).to_le_bytes());
+        buffer.extend_from_slice(&(blocks.len() as u64).to_le_bytes());
+        buffer.extend_from_slice(&(blocks_len as u64).to_le_bytes());
+        entries.iter().for_each(|entry| entry.encode(&mut buffer));
+
+        let blocks_offset = segment_start + buffer.len() as u64 + block_entries_len as u64;
+        let mut relative_offset = 0;
+        let mut locations = Vec::with_capacity(blocks.len());
+        for (compressed, uncompressed_len) in &blocks {
+            buffer.extend_from_slice(&(relative_offset as u64).to_le_bytes());
+            buffer.extend_from_slice(&(compressed.len() as u32).to_le_bytes());
+            buffer.extend_from_slice(&uncompressed_len.to_le_bytes());
+            locations.push(BlockLocation {
+                offset: blocks_offset + relative_offset as u64,
+                compressed_len: compressed.len() as u32,
+                uncompressed_len: *uncompressed_len,
+            });
+            relative_offset += compressed.len();
+        }
+        blocks
+            .iter()
+            .for_each(|(compressed, _)| buffer.extend_from_slice(compressed));
+        buffer.resize(segment_len, 0);
+
+        write_buffer_to_file(&self.file, &buffer, segment_start)?;
+
+        // Publish the new file length before the index, so any account found through the index
+        // is readable, and the index before `len`, so every account within `len` can be found.
+        self.file_len
+            .store(segment_start + buffer.len() as u64, Ordering::Release);
+        {
+            let mut index = self.index.write().unwrap();
+            let first_account = index.offsets.len();
+            index.offsets.extend(
+                entries
+                    .iter()
+                    .map(|entry| (entry.offset as usize / ALIGN_BOUNDARY_OFFSET) as u32),
+            );
+            index.segments.push(Arc::new(Segment {
+                first_account,
+                num_accounts: entries.len(),
+                index_offset: segment_start + SEGMENT_HEADER_SIZE as u64,
+                blocks: locations,
+            }));
+        }
+        self.len.store(offset, Ordering::Release);
+        self.is_dirty.store(true, Ordering::Release);
+
+        Ok(Some(StoredAccountsInfo {
+            offsets: entries.iter().map(|entry| entry.offset as Offset).collect(),
+            size: offset - start_offset,
+        }))
+    }
+
+    /// Returns the way to access this accounts file when archiving
+    pub(crate) fn open_file_for_archive(&self) -> OpenFileForArchive<'_> {
+        OpenFileForArchive::Borrowed(&self.file)
+    }
+
+    /// Returns a reader producing this storage in the AppendVec format, skipping the accounts
+    /// at `sorted_excluded_offsets`, which must be sorted in descending order.
+    pub(crate) fn append_vec_encoder(
+        &self,
+        sorted_excluded_offsets: Vec<Offset>,
+    ) -> AppendVecEncoder<'_> {
+        AppendVecEncoder {
+            storage: self,
+            segments: self.index.read().unwrap().segments.clone(),
+            len: self.len(),
+            sorted_excluded_offsets,
+            segment: 0,
+            next_in_segment: 0,
+            entries: Vec::new(),
+            next_entry: 0,
+            cached_block: None,
+            pending: Vec::new(),
+            pending_start: 0,
+        }
+    }
+}
+
+fn read_exact_at(
+    file: &File,
+    file_len: FileSize,
+    offset: u64,
+    buffer: &mut [u8],
+) -> io::Result<()> {
+    let bytes_read = read_into_buffer(file, file_len, offset, buffer)?;
+    if bytes_read != buffer.len() {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(())
+}
+
+/// Re-encodes the accounts of a [`ColdStorage`] in the AppendVec format.
+///
+/// Snapshot archives only contain AppendVecs, so cold storages are converted while archiving.
+/// The output of an encoder with no excluded offsets is exactly `ColdStorage::len()` bytes long,
+/// and each excluded account shortens it by the account's stored size.
+pub(crate) struct AppendVecEncoder<'a> {
+    storage: &'a ColdStorage,
+    segments: Vec<Arc<Segment>>,
+    /// only accounts below this offset are encoded
+    len: Offset,
+    /// offsets of the accounts to skip, sorted in descending order
+    sorted_excluded_offsets: Vec<Offset>,
+    /// the segment `entries` belong to
+    segment: usize,
+    /// position, within the segment, of the first entry not yet loaded into `entries`
+    next_in_segment: usize,
+    entries: Vec<IndexEntry>,
+    next_entry: usize,
+    cached_block: Option<(u32, Vec<u8>)>,
+    /// encoded bytes not yet returned by `read()`
+    pending: Vec<u8>,
+    pending_start: usize,
+}
+
+impl AppendVecEncoder<'_> {
+    /// Encodes the next account that is not excluded into `pending`.
+    ///
+    /// Returns false once all accounts have been encoded.
+    fn encode_next_account(&mut self) -> Result<bool> {
+        loop {
+            if self.next_entry == self.entries.len() {
+                let Some(segment) = self.segments.get(self.segment) else {
+                    return Ok(false);
+                };
+                if self.next_in_segment == segment.num_accounts {
+                    self.segment += 1;
+                    self.next_in_segment = 0;
+                    self.cached_block = None;
+                    continue;
+                }
+                let count = (segment.num_accounts - self.next_in_segment).min(SCAN_CHUNK_LEN);
+                self.entries = self
+                    .storage
+                    .read_entries(segment, self.next_in_segment, count)?;
+                self.next_entry = 0;
+                self.next_in_segment += count;
+            }
+
+            let entry = self.entries[self.next_entry];
+            self.next_entry += 1;
+            let offset = entry.offset as Offset;
+            if offset >= self.len {
+                return Ok(false);
+            }
+            while self
+                .sorted_excluded_offsets
+                .last()
+                .is_some_and(|excluded| *excluded < offset)
+            {
+                self.sorted_excluded_offsets.pop();
+            }
+            if self.sorted_excluded_offsets.last() == Some(&offset) {
+                self.sorted_excluded_offsets.pop();
+                continue;
+            }
+
+            let segment = Arc::clone(&self.segments[self.segment]);
+            let data = self
+                .storage
+                .account_data(&segment, &entry, &mut self.cached_block)?;
+            self.pending.clear();
+            self.pending_start = 0;
+            AppendVec::encode_account(&entry.pubkey, &entry.account(data), &mut self.pending);
+            return Ok(true);
+        }
+    }
+}
+
+impl Read for AppendVecEncoder<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut total_read = 0;
+        while total_read < buf.len() {
+            if self.pending_start == self.pending.len()
+                && !self.encode_next_account().map_err(io::Error::other)?
+            {
+                break;
+            }
+            let pending = &self.pending[self.pending_start..];
+            let len = pending.len().min(buf.len() - total_read);
+            buf[total_read..][..len].copy_from_slice(&pending[..len]);
+            self.pending_start += len;
+            total_read += len;
+        }
+        Ok(total_read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::accounts_file::AccountsFile,
+        assert_matches::assert_matches,
+        rand::{Rng as _, rng},
+        solana_account::{WritableAccount as _, accounts_equal},
+        solana_clock::Slot,
+        tempfile::TempDir,
+    };
+
+    const SLOT: Slot = 0;
+
+    /// Accounts with data sizes spanning several blocks, including empty and oversized ones
+    fn new_accounts(count: usize) -> Vec<(Pubkey, AccountSharedData)> {
+        let mut rng = rng();
+        (0..count)
+            .map(|i| {
+                let data_len = match i % 4 {
+                    0 => 0,
+                    1 => rng.random_range(1..200),
+                    2 => rng.random_range(1..10_000),
+                    _ => rng.random_range(TARGET_BLOCK_SIZE..2 * TARGET_BLOCK_SIZE),
+                };
+                let mut account =
+                    AccountSharedData::new(i as u64 + 1, data_len, &Pubkey::new_unique());
+                rng.fill(account.data_as_mut_slice());
+                account.set_executable(i % 3 == 0);
+                account.set_rent_epoch(i as Epoch);
+                (Pubkey::new_unique(), account)
+            })
+            .collect()
+    }
+
+    fn assert_accounts(storage: &ColdStorage, accounts: &[(Pubkey, AccountSharedData)]) {
+        let mut offsets = Vec::new();
+        storage
+            .scan_accounts(|offset, account| {
+                let (pubkey, expected) = &accounts[offsets.len()];
+                assert_eq!(account.pubkey(), pubkey);
+                assert!(accounts_equal(&account, expected));
+                offsets.push(offset);
+            })
+            .unwrap();
+        assert_eq!(offsets.len(), accounts.len());
+
+        let mut num_scanned = 0;
+        storage
+            .scan_accounts_without_data(|offset, account| {
+                assert_eq!(offset, offsets[num_scanned]);
+                assert_eq!(account.data_len, accounts[num_scanned].1.data().len());
+                num_scanned += 1;
+            })
+            .unwrap();
+        assert_eq!(num_scanned, accounts.len());
+
+        for (offset, (pubkey, expected)) in offsets.iter().zip(accounts) {
+            let account = storage.get_account_shared_data(*offset).unwrap();
+            assert!(accounts_equal(&account, expected));
+            storage
+                .get_stored_account_without_data_callback(*offset, |account| {
+                    assert_eq!(account.pubkey(), pubkey);
+                    assert_eq!(account.lamports, expected.lamports());
+                })
+                .unwrap();
+        }
+        assert_eq!(
+            storage.get_account_data_lens(&offsets),
+            accounts
+                .iter()
+                .map(|(_, account)| account.data().len())
+                .collect::<Vec<_>>(),
+        );
+    }
+
+    #[test]
+    fn test_cold_storage_write_and_read() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = ColdStorage::new(temp_dir.path().join("cold"), 64 * 1024 * 1024);
+        assert!(storage.is_empty());
+
+        let accounts = new_accounts(100);
+        let info = storage
+            .write_accounts(&(SLOT, &accounts[..]))
+            .unwrap()
+            .unwrap();
+
+        // offsets and sizes match those of an AppendVec
+        let av = AppendVec::new(temp_dir.path().join("av"), 64 * 1024 * 1024);
+        let av_info = av.append_accounts(&(SLOT, &accounts[..])).unwrap();
+        assert_eq!(info.offsets, av_info.offsets);
+        assert_eq!(info.size, av_info.size);
+        assert_eq!(storage.len(), av.len());
+
+        assert_accounts(&storage, &accounts);
+        assert!(storage.get_account_shared_data(storage.len()).is_none());
+        assert!(storage.get_account_shared_data(1).is_none());
+    }
+
+    #[test]
+    fn test_cold_storage_block_cache() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = ColdStorage::new(temp_dir.path().join("cold"), 64 * 1024 * 1024);
+        // every account fills a block of its own
+        let accounts: Vec<_> = (0..BLOCK_CACHE_LEN + 1)
+            .map(|_| {
+                let account = AccountSharedData::new(1, TARGET_BLOCK_SIZE, &Pubkey::new_unique());
+                (Pubkey::new_unique(), account)
+            })
+            .collect();
+        let offsets = storage
+            .write_accounts(&(SLOT, &accounts[..]))
+            .unwrap()
+            .unwrap()
+            .offsets;
+        let cached_blocks = || {
+            storage
+                .block_cache
+                .lock()
+                .unwrap()
+                .blocks
+                .iter()
+                .map(|((_segment, block), _data)| *block)
+                .collect::<Vec<_>>()
+        };
+
+        storage.get_account_shared_data(offsets[0]).unwrap();
+        storage.get_account_shared_data(offsets[0]).unwrap();
+        assert_eq!(cached_blocks(), vec![0]);
+
+        // the least recently used block is evicted
+        for offset in &offsets[1..BLOCK_CACHE_LEN] {
+            storage.get_account_shared_data(*offset).unwrap();
+        }
+        storage.get_account_shared_data(offsets[0]).unwrap();
+        storage
+            .get_account_shared_data(offsets[BLOCK_CACHE_LEN])
+            .unwrap();
+        let mut expected: Vec<_> = (2..=BLOCK_CACHE_LEN as u32).collect();
+        expected.insert(BLOCK_CACHE_LEN - 2, 0);
+        assert_eq!(cached_blocks(), expected);
+    }
+
+    #[test]
+    fn test_cold_storage_multiple_segments() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = ColdStorage::new(temp_dir.path().join("cold"), 64 * 1024 * 1024);
+
+        let accounts = new_accounts(30);
+        for chunk in accounts.chunks(7) {
+            storage.write_accounts(&(SLOT, chunk)).unwrap().unwrap();
+        }
+        assert_eq!(storage.num_accounts(), accounts.len());
+        assert_accounts(&storage, &accounts);
+    }
+
+    #[test]
+    fn test_cold_storage_capacity() {
+        let temp_dir = TempDir::new().unwrap();
+        let accounts = new_accounts(4);
+        let capacity = AppendVec::calculate_stored_size(accounts[0].1.data().len())
+            + AppendVec::calculate_stored_size(accounts[1].1.data().len());
+        let storage = ColdStorage::new(temp_dir.path().join("cold"), capacity);
+
+        let info = storage
+            .write_accounts(&(SLOT, &accounts[..]))
+            .unwrap()
+            .unwrap();
+        assert_eq!(info.offsets.len(), 2);
+        assert_eq!(storage.len(), capacity);
+        assert!(
+            storage
+                .write_accounts(&(SLOT, &accounts[2..]))
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_cold_storage_reopen() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("cold");
+        let accounts = new_accounts(50);
+        let len = {
+            let storage = ColdStorage::new(&path, 64 * 1024 * 1024);
+            storage
+                .write_accounts(&(SLOT, &accounts[..25]))
+                .unwrap()
+                .unwrap();
+            storage
+                .write_accounts(&(SLOT, &accounts[25..]))
+                .unwrap()
+                .unwrap();
+            storage.flush().unwrap();
+            storage.disable_remove_on_drop();
+            storage.len()
+        };
+
+        let file_info = FileInfo::new_from_path(&path).unwrap();
+        let storage = match AccountsFile::new_for_startup(file_info).unwrap() {
+            AccountsFile::ColdStorage(storage) => storage,
+            accounts_file => panic!("unexpected accounts file: {accounts_file:?}"),
+        };
+        assert_eq!(storage.len(), len);
+        assert_accounts(&storage, &accounts);
+
+        // a truncated file is rejected
+        let file = OpenOptions::new().write(true).open(&path).unwrap();
+        file.set_len(file.metadata().unwrap().len() - 1).unwrap();
+        assert_matches!(
+            ColdStorage::new_for_startup(FileInfo::new_from_path(&path).unwrap()),
+            Err(ColdStorageError::CorruptSegment(..))
+        );
+    }
+
+    #[test]
+    fn test_append_vec_encoder() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = ColdStorage::new(temp_dir.path().join("cold"), 64 * 1024 * 1024);
+        let accounts = new_accounts(40);
+        let offsets = storage
+            .write_accounts(&(SLOT, &accounts[..]))
+            .unwrap()
+            .unwrap()
+            .offsets;
+
+        // exclude every fifth account
+        let excluded: Vec<_> = (0..accounts.len()).step_by(5).collect();
+        let sorted_excluded_offsets = excluded.iter().rev().map(|i| offsets[*i]).collect();
+        let mut encoded = Vec::new();
+        storage
+            .append_vec_encoder(sorted_excluded_offsets)
+            .read_to_end(&mut encoded)
+            .unwrap();
+        let excluded_len: usize = excluded
+            .iter()
+            .map(|i| AppendVec::calculate_stored_size(accounts[*i].1.data().len()))
+            .sum();
+        assert_eq!(encoded.len(), storage.len() - excluded_len);
+
+        let path = temp_dir.path().join("av");
+        std::fs::write(&path, &encoded).unwrap();
+        let (av, num_accounts) = AppendVec::new_from_file(&path, encoded.len()).unwrap();
+        let expected: Vec<_> = accounts
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !excluded.contains(i))
+            .map(|(_, account)| account)
+            .collect();
+        assert_eq!(num_accounts, expected.len());
+        let mut num_scanned = 0;
+        av.scan_accounts_without_data(|offset, account| {
+            let (pubkey, expected_account) = expected[num_scanned];
+            assert_eq!(account.pubkey(), pubkey);
+            let loaded = av.get_account_shared_data(offset).unwrap();
+            assert!(accounts_equal(&loaded, expected_account));
+            num_scanned += 1;
+        })
+        .unwrap();
+        assert_eq!(num_scanned, expected.len());
+    }
+}
@@ -15,6 +15,7 @@ pub mod accounts_hash;
 pub mod accounts_index;
 pub mod accounts_scan;
 pub mod accounts_update_notifier_interface;
+pub mod accounts_verification_progress;
 mod active_stats;
 pub mod ancestors;
 mod ancient_append_vecs;
@@ -22,7 +23,15 @@ mod ancient_append_vecs;
 pub mod append_vec;
 #[cfg(not(feature = "dev-context-only-utils"))]
 mod append_vec;
+#[cfg(feature = "dev-context-only-utils")]
+pub mod batched_storage_reader;
+#[cfg(not(feature = "dev-context-only-utils"))]
+mod batched_storage_reader;
 pub mod blockhash_queue;
+#[cfg(feature = "dev-context-only-utils")]
+pub mod cold_storage;
+#[cfg(not(feature = "dev-context-only-utils"))]
+mod cold_storage;
 pub mod contains;
 pub mod is_loadable;
 mod is_zero_lamport;
@@ -36,10 +45,17 @@ mod read_only_accounts_cache;
 mod rolling_bit_field;
 pub mod stake_rewards;
 pub mod storable_accounts;
+mod storage_placement;
+pub mod storage_scan_cache;
 pub mod utils;
 pub mod waitable_condvar;
+pub mod write_telemetry;
 
-pub use obsolete_accounts::{ObsoleteAccountItem, ObsoleteAccounts};
+pub use {
+    batched_storage_reader::StorageReadIo,
+    obsolete_accounts::{ObsoleteAccountItem, ObsoleteAccounts},
+    read_only_accounts_cache::ReadCacheEvictionPolicy,
+};
 
 #[macro_use]
 extern crate solana_metrics;
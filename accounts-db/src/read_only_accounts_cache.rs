@@ -1,5 +1,10 @@
 //! ReadOnlyAccountsCache used to store accounts, such as executable accounts,
 //! which can be large, loaded many times, and rarely change.
+//!
+//! Once the cache exceeds its high watermark, entries are evicted in the
+//! background until it is back at its low watermark. Which entries are evicted
+//! is up to its [`ReadCacheEvictionPolicy`]. The watermarks are either fixed or
+//! adapted to the hit rate of the cache and the memory available to the system.
 #[cfg(feature = "dev-context-only-utils")]
 use qualifier_attr::{field_qualifiers, qualifiers};
 use {
@@ -16,10 +21,13 @@ use {
     solana_measure::{measure::Measure, measure_us},
     solana_pubkey::Pubkey,
     std::{
-        mem::ManuallyDrop,
+        collections::{HashSet, VecDeque},
+        fmt,
+        hash::BuildHasher,
+        mem::{self, ManuallyDrop},
         sync::{
             Arc, Condvar, Mutex,
-            atomic::{AtomicU64, AtomicUsize, Ordering},
+            atomic::{AtomicU8, AtomicU64, AtomicUsize, Ordering},
         },
         thread,
         time::{Duration, Instant},
@@ -30,8 +38,42 @@ use {
 const CACHE_ENTRY_SIZE: usize =
     size_of::<ReadOnlyAccountCacheEntry>() + size_of::<ReadOnlyCacheKey>();
 
+/// The S3-FIFO small queue holds up to this fraction of the cache's data size
+const S3_FIFO_SMALL_QUEUE_DIVISOR: usize = 10;
+/// Loads of an entry counted by S3-FIFO, which it keeps in the main queue for
+/// as many passes
+const S3_FIFO_MAX_FREQUENCY: u8 = 3;
+/// The S3-FIFO queues are purged of removed entries once they hold this many
+/// times as many entries as the cache
+const S3_FIFO_MAX_QUEUED_PER_ENTRY: usize = 2;
+
+/// Number of counters of the frequency sketch incremented per load
+const SKETCH_DEPTH: usize = 4;
+/// Counters of the frequency sketch saturate at this value
+const SKETCH_MAX_FREQUENCY: u8 = 15;
+/// Average data size assumed for cached accounts, to size the frequency sketch
+/// to the number of entries the cache can hold
+const SKETCH_ASSUMED_ACCOUNT_DATA_SIZE: usize = 1024;
+const SKETCH_MIN_WIDTH: usize = 1 << 10;
+const SKETCH_MAX_WIDTH: usize = 1 << 24;
+/// The counters of the frequency sketch are halved after this many loads per
+/// counter, so that the frequencies follow changes in the workload
+const SKETCH_LOADS_PER_COUNTER_BEFORE_AGING: usize = 10;
+
+/// How often the adaptive sizing adjusts the watermarks
+const ADAPTIVE_SIZING_INTERVAL: Duration = Duration::from_secs(10);
+/// The watermarks change by this fraction of their value per adjustment
+const ADAPTIVE_SIZING_STEP_DIVISOR: usize = 8;
+/// The cache grows while it evicts entries and its hit rate is below this
+const ADAPTIVE_SIZING_TARGET_HIT_RATE: f64 = 0.98;
+/// The cache shrinks while less than this fraction of the system's memory is
+/// available
+const ADAPTIVE_SIZING_LOW_MEMORY_FRACTION: f64 = 0.1;
+
 type ReadOnlyCacheKey = Pubkey;
 
+type Cache = DashMap<ReadOnlyCacheKey, ReadOnlyAccountCacheEntry, AHashRandomState>;
+
 #[derive(Debug)]
 #[cfg_attr(feature = "dev-context-only-utils", qualifiers(pub))]
 #[cfg_attr(
@@ -47,6 +89,46 @@ struct ReadOnlyAccountCacheEntry {
     slot: Slot,
     /// Timestamp when the entry was updated, in ns
     last_update_time: AtomicU64,
+    /// Loads since the entry was stored or last passed the head of an S3-FIFO
+    /// queue, up to `S3_FIFO_MAX_FREQUENCY`
+    frequency: AtomicU8,
+    /// Tells the S3-FIFO queue item of this entry apart from those of entries
+    /// previously stored and removed for the same key
+    queue_seq: u64,
+}
+
+/// How the read cache picks the entries to evict
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ReadCacheEvictionPolicy {
+    /// Evicts the least recently used of a random sample of entries
+    #[default]
+    SampledLru,
+    /// Keeps new entries in a small FIFO queue and evicts those which are not
+    /// loaded again before they leave it, so that accounts loaded once do not
+    /// displace frequently loaded ones. Entries loaded again move to the main
+    /// FIFO queue, where entries loaded since their last pass get another one.
+    S3Fifo,
+    /// Estimates how often every account is loaded, and only stores a new
+    /// entry into a full cache if it is loaded more often than the entries it
+    /// would displace. Evicts the least frequently, then least recently used of
+    /// a random sample of entries.
+    TinyLfu,
+}
+
+impl ReadCacheEvictionPolicy {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::SampledLru => "sampled-lru",
+            Self::S3Fifo => "s3-fifo",
+            Self::TinyLfu => "tiny-lfu",
+        }
+    }
+}
+
+impl fmt::Display for ReadCacheEvictionPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -58,6 +140,14 @@ pub struct ReadOnlyCacheStats {
     pub store_us: u64,
     pub evict_us: u64,
     pub evict_run_count: u64,
+    /// New entries the eviction policy did not store
+    pub admission_rejects: u64,
+    /// Entries moved from the small to the main S3-FIFO queue
+    pub promotions: u64,
+    /// Adjustments of the watermarks by the adaptive sizing
+    pub resizes: u64,
+    /// The current high watermark
+    pub max_data_size: usize,
 }
 
 #[derive(Default, Debug)]
@@ -69,6 +159,9 @@ struct AtomicReadOnlyCacheStats {
     store_us: AtomicU64,
     evict_us: AtomicU64,
     evict_run_count: AtomicU64,
+    admission_rejects: AtomicU64,
+    promotions: AtomicU64,
+    resizes: AtomicU64,
 }
 
 /// Shared state between the cache and its evictor thread, used to signal
@@ -80,14 +173,47 @@ struct EvictorControl {
     wake: Condvar,
 }
 
+/// Once the data size exceeds `hi`, entries are evicted until it is at most
+/// `lo`
+#[derive(Debug)]
+struct DataSizeLimits {
+    lo: AtomicUsize,
+    hi: AtomicUsize,
+}
+
+/// What the evictor needs to know to pick the entries to evict
+#[derive(Debug)]
+struct Eviction {
+    policy: PolicyState,
+    limits: DataSizeLimits,
+    adaptive: Option<AdaptiveSizing>,
+    sample_size: usize,
+}
+
+#[derive(Debug)]
+enum PolicyState {
+    SampledLru,
+    S3Fifo(S3FifoQueues),
+    TinyLfu(FrequencySketch),
+}
+
+impl PolicyState {
+    fn policy(&self) -> ReadCacheEvictionPolicy {
+        match self {
+            Self::SampledLru => ReadCacheEvictionPolicy::SampledLru,
+            Self::S3Fifo(_) => ReadCacheEvictionPolicy::S3Fifo,
+            Self::TinyLfu(_) => ReadCacheEvictionPolicy::TinyLfu,
+        }
+    }
+}
+
 #[cfg_attr(feature = "dev-context-only-utils", qualifiers(pub))]
 #[derive(Debug)]
 pub(crate) struct ReadOnlyAccountsCache {
-    cache: Arc<DashMap<ReadOnlyCacheKey, ReadOnlyAccountCacheEntry, AHashRandomState>>,
-    _max_data_size_lo: usize,
-    _max_data_size_hi: usize,
+    cache: Arc<Cache>,
     data_size: Arc<AtomicUsize>,
     cache_len: Arc<AtomicUsize>,
+    eviction: Arc<Eviction>,
 
     // Performance statistics
     stats: Arc<AtomicReadOnlyCacheStats>,
@@ -112,6 +238,30 @@ impl ReadOnlyAccountsCache {
         max_data_size_hi: usize,
         evict_sample_size: usize,
         num_shards: usize,
+    ) -> Self {
+        Self::new_with_policy(
+            max_data_size_lo,
+            max_data_size_hi,
+            evict_sample_size,
+            num_shards,
+            ReadCacheEvictionPolicy::default(),
+            None,
+        )
+    }
+
+    /// Creates a cache evicting entries with `policy`. With
+    /// `adaptive_min_data_size_hi`, the high watermark starts at
+    /// `max_data_size_hi` and is adapted within
+    /// `adaptive_min_data_size_hi..=max_data_size_hi`, with the low watermark
+    /// scaled along.
+    #[cfg_attr(feature = "dev-context-only-utils", qualifiers(pub))]
+    pub(crate) fn new_with_policy(
+        max_data_size_lo: usize,
+        max_data_size_hi: usize,
+        evict_sample_size: usize,
+        num_shards: usize,
+        policy: ReadCacheEvictionPolicy,
+        adaptive_min_data_size_hi: Option<usize>,
     ) -> Self {
         assert!(max_data_size_lo <= max_data_size_hi);
         assert!(evict_sample_size > 0);
@@ -119,6 +269,9 @@ impl ReadOnlyAccountsCache {
             num_shards.is_power_of_two(),
             "num_shards must be a power of two, got {num_shards}"
         );
+        if let Some(min_data_size_hi) = adaptive_min_data_size_hi {
+            assert!(min_data_size_hi <= max_data_size_hi);
+        }
         let cache = Arc::new(DashMap::with_hasher_and_shard_amount(
             AHashRandomState::default(),
             num_shards,
@@ -126,6 +279,27 @@ impl ReadOnlyAccountsCache {
         let data_size = Arc::new(AtomicUsize::default());
         let cache_len = Arc::new(AtomicUsize::default());
         let stats = Arc::new(AtomicReadOnlyCacheStats::default());
+        let eviction = Arc::new(Eviction {
+            policy: match policy {
+                ReadCacheEvictionPolicy::SampledLru => PolicyState::SampledLru,
+                ReadCacheEvictionPolicy::S3Fifo => PolicyState::S3Fifo(S3FifoQueues::default()),
+                ReadCacheEvictionPolicy::TinyLfu => {
+                    PolicyState::TinyLfu(FrequencySketch::new(max_data_size_hi))
+                }
+            },
+            limits: DataSizeLimits {
+                lo: AtomicUsize::new(max_data_size_lo),
+                hi: AtomicUsize::new(max_data_size_hi),
+            },
+            adaptive: adaptive_min_data_size_hi.map(|min_data_size_hi| AdaptiveSizing {
+                min_data_size_hi,
+                max_data_size_lo,
+                max_data_size_hi,
+                hits: AtomicU64::default(),
+                misses: AtomicU64::default(),
+            }),
+            sample_size: evict_sample_size,
+        });
         let timer = Instant::now();
         let evictor_control = Arc::new(EvictorControl {
             exit: Mutex::new(false),
@@ -133,22 +307,19 @@ impl ReadOnlyAccountsCache {
         });
         let evictor_thread_handle = Self::spawn_evictor(
             evictor_control.clone(),
-            max_data_size_lo,
-            max_data_size_hi,
+            eviction.clone(),
             data_size.clone(),
             cache_len.clone(),
-            evict_sample_size,
             cache.clone(),
             stats.clone(),
         );
 
         Self {
             highest_slot_stored: AtomicU64::default(),
-            _max_data_size_lo: max_data_size_lo,
-            _max_data_size_hi: max_data_size_hi,
             cache,
             data_size,
             cache_len,
+            eviction,
             stats,
             timer,
             evictor_thread_handle: ManuallyDrop::new(evictor_thread_handle),
@@ -156,9 +327,16 @@ impl ReadOnlyAccountsCache {
         }
     }
 
+    pub(crate) fn eviction_policy(&self) -> ReadCacheEvictionPolicy {
+        self.eviction.policy.policy()
+    }
+
     #[cfg_attr(feature = "dev-context-only-utils", qualifiers(pub))]
     pub(crate) fn load(&self, pubkey: Pubkey, slot: Slot) -> Option<AccountSharedData> {
         let (account, load_us) = measure_us!({
+            if let PolicyState::TinyLfu(sketch) = &self.eviction.policy {
+                sketch.increment(&pubkey);
+            }
             let mut found = None;
             if let Some(entry) = self.cache.get(&pubkey)
                 && entry.slot == slot
@@ -166,6 +344,16 @@ impl ReadOnlyAccountsCache {
                 entry
                     .last_update_time
                     .store(self.timestamp(), Ordering::Relaxed);
+                if let PolicyState::S3Fifo(_) = &self.eviction.policy {
+                    let _ = entry.frequency.fetch_update(
+                        Ordering::Relaxed,
+                        Ordering::Relaxed,
+                        |frequency| {
+                            (frequency < S3_FIFO_MAX_FREQUENCY)
+                                .then_some(frequency.saturating_add(1))
+                        },
+                    );
+                }
                 let account = entry.account.clone();
                 drop(entry);
                 self.stats.hits.fetch_add(1, Ordering::Relaxed);
@@ -175,6 +363,9 @@ impl ReadOnlyAccountsCache {
             if found.is_none() {
                 self.stats.misses.fetch_add(1, Ordering::Relaxed);
             }
+            if let Some(adaptive) = &self.eviction.adaptive {
+                adaptive.record_load(found.is_some());
+            }
             found
         });
         self.stats.load_us.fetch_add(load_us, Ordering::Relaxed);
@@ -199,13 +390,26 @@ impl ReadOnlyAccountsCache {
         timestamp: u64,
     ) {
         let measure_store = Measure::start("");
-        self.highest_slot_stored.fetch_max(slot, Ordering::Release);
         let new_account_size = Self::account_size(&account);
+        if !self.admit(&pubkey, new_account_size) {
+            self.stats.admission_rejects.fetch_add(1, Ordering::Relaxed);
+            self.stats
+                .store_us
+                .fetch_add(measure_store.end_as_us(), Ordering::Relaxed);
+            return;
+        }
+        self.highest_slot_stored.fetch_max(slot, Ordering::Release);
         let old_account_size;
         match self.cache.entry(pubkey) {
             Entry::Vacant(entry) => {
                 old_account_size = 0;
-                entry.insert(ReadOnlyAccountCacheEntry::new(account, slot, timestamp));
+                let queue_seq = match &self.eviction.policy {
+                    PolicyState::S3Fifo(queues) => queues.push(pubkey, new_account_size),
+                    PolicyState::SampledLru | PolicyState::TinyLfu(_) => 0,
+                };
+                entry.insert(ReadOnlyAccountCacheEntry::new(
+                    account, slot, timestamp, queue_seq,
+                ));
                 self.cache_len.fetch_add(1, Ordering::Relaxed);
             }
             Entry::Occupied(mut entry) => {
@@ -221,6 +425,47 @@ impl ReadOnlyAccountsCache {
         self.stats.store_us.fetch_add(store_us, Ordering::Relaxed);
     }
 
+    /// Whether the eviction policy stores the account of `pubkey`.
+    ///
+    /// TinyLFU only stores a new entry into a full cache if it is loaded more
+    /// often than the least frequently loaded of a sample of the entries it
+    /// competes with for eviction. Other policies store every entry.
+    fn admit(&self, pubkey: &Pubkey, new_account_size: usize) -> bool {
+        let PolicyState::TinyLfu(sketch) = &self.eviction.policy else {
+            return true;
+        };
+        let data_size = self.data_size.load(Ordering::Relaxed) + new_account_size;
+        if data_size <= self.eviction.limits.lo.load(Ordering::Relaxed)
+            || self.cache.contains_key(pubkey)
+        {
+            return true;
+        }
+
+        let frequency = sketch.frequency(pubkey);
+        let mut rng = rand::rng();
+        let mut victim_frequency = None;
+        let mut remaining_samples = self.eviction.sample_size;
+        // Visits every shard at most once, starting from a random one, so
+        // that sampling a sparse cache does not stall the store
+        let shards = self.cache.shards();
+        let first_shard = rng.random_range(0..shards.len());
+        for shard in shards.iter().cycle().skip(first_shard).take(shards.len()) {
+            let shard = shard.read();
+            for (key, _entry) in shard.iter().choose_multiple(&mut rng, remaining_samples) {
+                let key_frequency = sketch.frequency(key);
+                victim_frequency =
+                    Some(victim_frequency.map_or(key_frequency, |victim_frequency| {
+                        key_frequency.min(victim_frequency)
+                    }));
+                remaining_samples = remaining_samples.saturating_sub(1);
+            }
+            if remaining_samples == 0 {
+                break;
+            }
+        }
+        victim_frequency.is_none_or(|victim_frequency| frequency > victim_frequency)
+    }
+
     /// true if any pubkeys could have ever been stored into the cache at `slot`
     pub(crate) fn can_slot_be_in_cache(&self, slot: Slot) -> bool {
         self.highest_slot_stored.load(Ordering::Acquire) >= slot
@@ -245,7 +490,7 @@ impl ReadOnlyAccountsCache {
     /// Removes `key` from the cache, if present, and returns the account entry.
     fn do_remove(
         key: &ReadOnlyCacheKey,
-        cache: &DashMap<ReadOnlyCacheKey, ReadOnlyAccountCacheEntry, AHashRandomState>,
+        cache: &Cache,
         data_size: &AtomicUsize,
         cache_len: &AtomicUsize,
     ) -> Option<ReadOnlyAccountCacheEntry> {
@@ -273,6 +518,10 @@ impl ReadOnlyAccountsCache {
         let store_us = self.stats.store_us.swap(0, Ordering::Relaxed);
         let evict_us = self.stats.evict_us.swap(0, Ordering::Relaxed);
         let evict_run_count = self.stats.evict_run_count.swap(0, Ordering::Relaxed);
+        let admission_rejects = self.stats.admission_rejects.swap(0, Ordering::Relaxed);
+        let promotions = self.stats.promotions.swap(0, Ordering::Relaxed);
+        let resizes = self.stats.resizes.swap(0, Ordering::Relaxed);
+        let max_data_size = self.eviction.limits.hi.load(Ordering::Relaxed);
 
         ReadOnlyCacheStats {
             hits,
@@ -282,25 +531,32 @@ impl ReadOnlyAccountsCache {
             store_us,
             evict_us,
             evict_run_count,
+            admission_rejects,
+            promotions,
+            resizes,
+            max_data_size,
         }
     }
 
     /// Spawns the background thread to handle evictions
     fn spawn_evictor(
         control: Arc<EvictorControl>,
-        max_data_size_lo: usize,
-        max_data_size_hi: usize,
+        eviction: Arc<Eviction>,
         data_size: Arc<AtomicUsize>,
         cache_len: Arc<AtomicUsize>,
-        evict_sample_size: usize,
-        cache: Arc<DashMap<ReadOnlyCacheKey, ReadOnlyAccountCacheEntry, AHashRandomState>>,
+        cache: Arc<Cache>,
         stats: Arc<AtomicReadOnlyCacheStats>,
     ) -> thread::JoinHandle<()> {
         thread::Builder::new()
             .name("solAcctReadCache".to_string())
             .spawn(move || {
-                info!("AccountsReadCacheEvictor has started");
+                info!(
+                    "AccountsReadCacheEvictor has started, eviction policy: {}",
+                    eviction.policy.policy()
+                );
                 let mut rng = SmallRng::from_os_rng();
+                let mut last_adaptive_sizing = Instant::now();
+                let mut evicts_since_adaptive_sizing: u64 = 0;
                 loop {
                     // Wait up to 10 ms, or until the exit flag is set.
                     // Ensure this timeout stays many times smaller than the slot time.
@@ -314,30 +570,54 @@ impl ReadOnlyAccountsCache {
                     }
                     drop(exit_flag);
 
-                    if data_size.load(Ordering::Relaxed) <= max_data_size_hi {
+                    if let Some(adaptive) = &eviction.adaptive
+                        && last_adaptive_sizing.elapsed() >= ADAPTIVE_SIZING_INTERVAL
+                    {
+                        if adaptive.adapt(
+                            &eviction.limits,
+                            evicts_since_adaptive_sizing,
+                            available_memory_fraction(),
+                        ) {
+                            stats.resizes.fetch_add(1, Ordering::Relaxed);
+                        }
+                        last_adaptive_sizing = Instant::now();
+                        evicts_since_adaptive_sizing = 0;
+                    }
+                    if let PolicyState::S3Fifo(queues) = &eviction.policy {
+                        queues.purge_if_oversized(&cache, cache_len.load(Ordering::Relaxed));
+                    }
+
+                    if data_size.load(Ordering::Relaxed)
+                        <= eviction.limits.hi.load(Ordering::Relaxed)
+                    {
                         continue;
                     }
                     stats.evict_run_count.fetch_add(1, Ordering::Relaxed);
 
                     #[cfg(not(feature = "dev-context-only-utils"))]
-                    let (num_evicts, evict_us) = measure_us!(Self::evict(
-                        max_data_size_lo,
+                    let (num_evicts, evict_us) = measure_us!(Self::evict_with_policy(
+                        &eviction,
+                        eviction.limits.lo.load(Ordering::Relaxed),
                         &data_size,
                         &cache_len,
-                        evict_sample_size,
+                        eviction.sample_size,
                         &cache,
+                        &stats,
                         &mut rng,
                     ));
                     #[cfg(feature = "dev-context-only-utils")]
-                    let (num_evicts, evict_us) = measure_us!(Self::evict(
-                        max_data_size_lo,
+                    let (num_evicts, evict_us) = measure_us!(Self::evict_with_policy(
+                        &eviction,
+                        eviction.limits.lo.load(Ordering::Relaxed),
                         &data_size,
                         &cache_len,
-                        evict_sample_size,
+                        eviction.sample_size,
                         &cache,
+                        &stats,
                         &mut rng,
                         |_, _| {}
                     ));
+                    evicts_since_adaptive_sizing += num_evicts;
                     stats.evicts.fetch_add(num_evicts, Ordering::Relaxed);
                     stats.evict_us.fetch_add(evict_us, Ordering::Relaxed);
                 }
@@ -346,10 +626,68 @@ impl ReadOnlyAccountsCache {
             .expect("spawn accounts read cache evictor thread")
     }
 
+    /// Evicts entries until the cache's size is <= `target_data_size`,
+    /// following the eviction policy of `eviction`.
+    ///
+    /// Returns the number of entries evicted.
+    fn evict_with_policy<R>(
+        eviction: &Eviction,
+        target_data_size: usize,
+        data_size: &AtomicUsize,
+        cache_len: &AtomicUsize,
+        evict_sample_size: usize,
+        cache: &Cache,
+        stats: &AtomicReadOnlyCacheStats,
+        rng: &mut R,
+        #[cfg(feature = "dev-context-only-utils")] callback: impl FnMut(
+            &Pubkey,
+            Option<ReadOnlyAccountCacheEntry>,
+        ),
+    ) -> u64
+    where
+        R: Rng,
+    {
+        match &eviction.policy {
+            PolicyState::SampledLru => Self::evict(
+                target_data_size,
+                data_size,
+                cache_len,
+                evict_sample_size,
+                cache,
+                None,
+                rng,
+                #[cfg(feature = "dev-context-only-utils")]
+                callback,
+            ),
+            PolicyState::TinyLfu(sketch) => Self::evict(
+                target_data_size,
+                data_size,
+                cache_len,
+                evict_sample_size,
+                cache,
+                Some(sketch),
+                rng,
+                #[cfg(feature = "dev-context-only-utils")]
+                callback,
+            ),
+            PolicyState::S3Fifo(queues) => Self::evict_s3_fifo(
+                target_data_size,
+                data_size,
+                cache_len,
+                cache,
+                queues,
+                stats,
+                #[cfg(feature = "dev-context-only-utils")]
+                callback,
+            ),
+        }
+    }
+
     /// Evicts entries until the cache's size is <= `target_data_size`,
     /// following the sampled LRU eviction method, where a sample of size
     /// `evict_sample_size` is randomly selected from the cache, using the
-    /// provided `rng`.
+    /// provided `rng`. With a frequency `sketch`, the least frequently loaded
+    /// entry of the sample is evicted, and of those the least recently used.
     ///
     /// Returns the number of entries evicted.
     fn evict<R>(
@@ -357,7 +695,8 @@ impl ReadOnlyAccountsCache {
         data_size: &AtomicUsize,
         cache_len: &AtomicUsize,
         evict_sample_size: usize,
-        cache: &DashMap<ReadOnlyCacheKey, ReadOnlyAccountCacheEntry, AHashRandomState>,
+        cache: &Cache,
+        sketch: Option<&FrequencySketch>,
         rng: &mut R,
         #[cfg(feature = "dev-context-only-utils")] mut callback: impl FnMut(
             &Pubkey,
@@ -370,7 +709,7 @@ impl ReadOnlyAccountsCache {
         let mut num_evicts: u64 = 0;
         while data_size.load(Ordering::Relaxed) > target_data_size {
             let mut key_to_evict = None;
-            let mut min_update_time = u64::MAX;
+            let mut min_rank = (u8::MAX, u64::MAX);
             let mut remaining_samples = evict_sample_size;
             // NOTE: This can loop indefinitely if the cache is misconfigured
             // and when we get here there aren't at least `evict_sample_size`
@@ -386,9 +725,11 @@ impl ReadOnlyAccountsCache {
                     .expect("number of shards should be greater than zero");
                 let shard = shard.read();
                 for (key, entry) in shard.iter().choose_multiple(rng, remaining_samples) {
+                    let frequency = sketch.map_or(0, |sketch| sketch.frequency(key));
                     let last_update_time = entry.get().last_update_time.load(Ordering::Relaxed);
-                    if last_update_time < min_update_time {
-                        min_update_time = last_update_time;
+                    let rank = (frequency, last_update_time);
+                    if rank < min_rank {
+                        min_rank = rank;
                         key_to_evict = Some(key.to_owned());
                     }
 
@@ -408,6 +749,81 @@ impl ReadOnlyAccountsCache {
         num_evicts
     }
 
+    /// Evicts entries until the cache's size is <= `target_data_size`,
+    /// following S3-FIFO: entries are evicted from the small queue while it
+    /// exceeds its share of the cache, and from the main queue otherwise.
+    ///
+    /// Returns the number of entries evicted.
+    fn evict_s3_fifo(
+        target_data_size: usize,
+        data_size: &AtomicUsize,
+        cache_len: &AtomicUsize,
+        cache: &Cache,
+        queues: &S3FifoQueues,
+        stats: &AtomicReadOnlyCacheStats,
+        #[cfg(feature = "dev-context-only-utils")] mut callback: impl FnMut(
+            &Pubkey,
+            Option<ReadOnlyAccountCacheEntry>,
+        ),
+    ) -> u64 {
+        let max_small_data_size = target_data_size / S3_FIFO_SMALL_QUEUE_DIVISOR;
+        let mut num_evicts: u64 = 0;
+        while data_size.load(Ordering::Relaxed) > target_data_size {
+            let from_small = queues.small_data_size.load(Ordering::Relaxed) > max_small_data_size;
+            let Some((item, from_small)) = from_small
+                .then(|| queues.pop_small())
+                .flatten()
+                .map(|item| (item, true))
+                .or_else(|| queues.pop_main().map(|item| (item, false)))
+                .or_else(|| queues.pop_small().map(|item| (item, true)))
+            else {
+                // Every entry is queued, so the queues only run empty when
+                // the cache is emptied concurrently
+                break;
+            };
+
+            let Some(frequency) = cache
+                .get(&item.key)
+                .filter(|entry| entry.queue_seq == item.seq)
+                .map(|entry| {
+                    if from_small {
+                        entry.frequency.swap(0, Ordering::Relaxed)
+                    } else {
+                        entry
+                            .frequency
+                            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |frequency| {
+                                Some(frequency.saturating_sub(1))
+                            })
+                            .unwrap()
+                    }
+                })
+            else {
+                // The entry was removed since it was queued
+                continue;
+            };
+
+            if frequency > 0 {
+                if from_small {
+                    stats.promotions.fetch_add(1, Ordering::Relaxed);
+                }
+                queues.main.lock().unwrap().push_back(item);
+                continue;
+            }
+
+            if from_small {
+                queues.remember_evicted(item.key);
+            }
+            let _entry = Self::do_remove(&item.key, cache, data_size, cache_len);
+            #[cfg(feature = "dev-context-only-utils")]
+            {
+                #[allow(clippy::used_underscore_binding)]
+                callback(&item.key, _entry);
+            }
+            num_evicts = num_evicts.saturating_add(1);
+        }
+        num_evicts
+    }
+
     /// Return the elapsed time of the cache.
     fn timestamp(&self) -> u64 {
         self.timer.elapsed().as_nanos() as u64
@@ -428,14 +844,14 @@ impl ReadOnlyAccountsCache {
         R: Rng,
         C: FnMut(&Pubkey, Option<ReadOnlyAccountCacheEntry>),
     {
-        #[allow(clippy::used_underscore_binding)]
-        let target_data_size = self._max_data_size_lo;
-        Self::evict(
-            target_data_size,
+        Self::evict_with_policy(
+            &self.eviction,
+            self.eviction.limits.lo.load(Ordering::Relaxed),
             &self.data_size,
             &self.cache_len,
             evict_sample_size,
             &self.cache,
+            &self.stats,
             rng,
             callback,
         )
@@ -458,15 +874,287 @@ impl Drop for ReadOnlyAccountsCache {
 }
 
 impl ReadOnlyAccountCacheEntry {
-    fn new(account: AccountSharedData, slot: Slot, timestamp: u64) -> Self {
+    fn new(account: AccountSharedData, slot: Slot, timestamp: u64, queue_seq: u64) -> Self {
         Self {
             account,
             slot,
             last_update_time: AtomicU64::new(timestamp),
+            frequency: AtomicU8::default(),
+            queue_seq,
         }
     }
 }
 
+/// An entry in an S3-FIFO queue
+#[derive(Debug, Clone, Copy)]
+struct QueuedEntry {
+    key: ReadOnlyCacheKey,
+    seq: u64,
+    account_size: usize,
+}
+
+/// The queues of S3-FIFO. Every stored entry is queued once, and stays queued
+/// after it is removed from the cache until it reaches the head of its queue
+/// or the queues are purged.
+///
+/// The queues are only locked briefly, and never while waiting on a lock of
+/// the cache, which stores hold while queuing new entries.
+#[derive(Debug, Default)]
+struct S3FifoQueues {
+    /// Entries stored since they were last evicted
+    small: Mutex<VecDeque<QueuedEntry>>,
+    /// Data size of the entries in `small`
+    small_data_size: AtomicUsize,
+    /// Entries loaded again while in `small`, and entries stored again while
+    /// their key is in `ghost`
+    main: Mutex<VecDeque<QueuedEntry>>,
+    /// Keys recently evicted from `small`
+    ghost: Mutex<GhostQueue>,
+    next_seq: AtomicU64,
+}
+
+impl S3FifoQueues {
+    /// Queues a new entry, and returns its sequence number
+    fn push(&self, key: ReadOnlyCacheKey, account_size: usize) -> u64 {
+        let seq = self.next_seq.fetch_add(1, Ordering::Relaxed);
+        let item = QueuedEntry {
+            key,
+            seq,
+            account_size,
+        };
+        if self.ghost.lock().unwrap().remove(&key) {
+            self.main.lock().unwrap().push_back(item);
+        } else {
+            self.small_data_size
+                .fetch_add(account_size, Ordering::Relaxed);
+            self.small.lock().unwrap().push_back(item);
+        }
+        seq
+    }
+
+    fn pop_small(&self) -> Option<QueuedEntry> {
+        let item = self.small.lock().unwrap().pop_front()?;
+        self.small_data_size
+            .fetch_sub(item.account_size, Ordering::Relaxed);
+        Some(item)
+    }
+
+    fn pop_main(&self) -> Option<QueuedEntry> {
+        self.main.lock().unwrap().pop_front()
+    }
+
+    /// Remembers `key` as evicted from the small queue, forgetting the
+    /// oldest keys beyond as many as the main queue holds
+    fn remember_evicted(&self, key: ReadOnlyCacheKey) {
+        let max_len = self.main.lock().unwrap().len().max(1);
+        self.ghost.lock().unwrap().insert(key, max_len);
+    }
+
+    /// Drops the items of removed entries once the queues hold more than
+    /// `S3_FIFO_MAX_QUEUED_PER_ENTRY` items per cached entry, since entries
+    /// which are removed rather than evicted would otherwise stay queued until
+    /// the cache fills up
+    fn purge_if_oversized(&self, cache: &Cache, cache_len: usize) {
+        let num_queued = self.small.lock().unwrap().len() + self.main.lock().unwrap().len();
+        if num_queued <= cache_len.max(1) * S3_FIFO_MAX_QUEUED_PER_ENTRY {
+            return;
+        }
+        let is_cached = |item: &QueuedEntry| {
+            cache
+                .get(&item.key)
+                .is_some_and(|entry| entry.queue_seq == item.seq)
+        };
+
+        // The queues are taken out while purging, so that the cache is not
+        // accessed while holding their locks
+        let small = mem::take(&mut *self.small.lock().unwrap());
+        let (small, purged): (VecDeque<_>, Vec<_>) = small.into_iter().partition(is_cached);
+        let purged_data_size = purged.iter().map(|item| item.account_size).sum();
+        self.small_data_size
+            .fetch_sub(purged_data_size, Ordering::Relaxed);
+        Self::prepend(&self.small, small);
+
+        let main = mem::take(&mut *self.main.lock().unwrap());
+        let main = main.into_iter().filter(is_cached).collect();
+        Self::prepend(&self.main, main);
+    }
+
+    /// Puts `items` in front of the items queued while they were taken out
+    fn prepend(queue: &Mutex<VecDeque<QueuedEntry>>, mut items: VecDeque<QueuedEntry>) {
+        let mut queue = queue.lock().unwrap();
+        items.append(&mut queue);
+        *queue = items;
+    }
+}
+
+/// A bounded FIFO set of keys.
+///
+/// A key removed and inserted again is queued twice, so it may be forgotten
+/// early, which only makes S3-FIFO treat its next store as a new entry.
+#[derive(Debug, Default)]
+struct GhostQueue {
+    keys: HashSet<ReadOnlyCacheKey>,
+    order: VecDeque<ReadOnlyCacheKey>,
+}
+
+impl GhostQueue {
+    fn insert(&mut self, key: ReadOnlyCacheKey, max_len: usize) {
+        if self.keys.insert(key) {
+            self.order.push_back(key);
+        }
+        while self.order.len() > max_len {
+            if let Some(oldest) = self.order.pop_front() {
+                self.keys.remove(&oldest);
+            }
+        }
+    }
+
+    fn remove(&mut self, key: &ReadOnlyCacheKey) -> bool {
+        self.keys.remove(key)
+    }
+}
+
+/// Count-min sketch estimating how often keys are loaded, using saturating
+/// 4-bit counters which are halved periodically to age old loads out
+#[derive(Debug)]
+struct FrequencySketch {
+    counters: Box<[AtomicU8]>,
+    hasher: AHashRandomState,
+    loads: AtomicUsize,
+}
+
+impl FrequencySketch {
+    /// Creates a sketch sized for the number of entries of a cache of
+    /// `max_data_size` bytes
+    fn new(max_data_size: usize) -> Self {
+        let width = (max_data_size / (CACHE_ENTRY_SIZE + SKETCH_ASSUMED_ACCOUNT_DATA_SIZE))
+            .clamp(SKETCH_MIN_WIDTH, SKETCH_MAX_WIDTH)
+            .next_power_of_two();
+        Self {
+            counters: (0..width).map(|_| AtomicU8::default()).collect(),
+            hasher: AHashRandomState::default(),
+            loads: AtomicUsize::default(),
+        }
+    }
+
+    fn counters(&self, key: &ReadOnlyCacheKey) -> impl Iterator<Item = &AtomicU8> {
+        let hash = self.hasher.hash_one(key);
+        // Derives the indexes by double hashing, from the two halves of `hash`
+        let (hash1, hash2) = (hash as u32 as usize, ((hash >> 32) as usize) | 1);
+        let mask = self.counters.len() - 1;
+        (0..SKETCH_DEPTH).filter_map(move |i| {
+            self.counters
+                .get(hash1.wrapping_add(i.wrapping_mul(hash2)) & mask)
+        })
+    }
+
+    fn increment(&self, key: &ReadOnlyCacheKey) {
+        for counter in self.counters(key) {
+            let _ = counter.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |count| {
+                (count < SKETCH_MAX_FREQUENCY).then_some(count.saturating_add(1))
+            });
+        }
+        let loads = self.loads.fetch_add(1, Ordering::Relaxed) + 1;
+        if loads >= self.counters.len() * SKETCH_LOADS_PER_COUNTER_BEFORE_AGING {
+            self.loads.store(0, Ordering::Relaxed);
+            for counter in self.counters.iter() {
+                counter.store(counter.load(Ordering::Relaxed) / 2, Ordering::Relaxed);
+            }
+        }
+    }
+
+    fn frequency(&self, key: &ReadOnlyCacheKey) -> u8 {
+        self.counters(key)
+            .map(|counter| counter.load(Ordering::Relaxed))
+            .min()
+            .unwrap_or_default()
+    }
+}
+
+/// Adapts the watermarks within a range, based on the hit rate and the
+/// memory available to the system
+#[derive(Debug)]
+struct AdaptiveSizing {
+    min_data_size_hi: usize,
+    max_data_size_lo: usize,
+    max_data_size_hi: usize,
+    /// Loads since the last adjustment
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl AdaptiveSizing {
+    fn record_load(&self, hit: bool) {
+        let counter = if hit { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Shrinks the watermarks while memory is low, and grows them while the
+    /// cache evicts entries without reaching the target hit rate. Returns
+    /// whether the watermarks changed.
+    fn adapt(
+        &self,
+        limits: &DataSizeLimits,
+        num_evicts: u64,
+        available_memory_fraction: Option<f64>,
+    ) -> bool {
+        let hits = self.hits.swap(0, Ordering::Relaxed);
+        let misses = self.misses.swap(0, Ordering::Relaxed);
+        let loads = hits + misses;
+        let hi = limits.hi.load(Ordering::Relaxed);
+        let step = (hi / ADAPTIVE_SIZING_STEP_DIVISOR).max(1);
+
+        let new_hi = if available_memory_fraction
+            .is_some_and(|fraction| fraction < ADAPTIVE_SIZING_LOW_MEMORY_FRACTION)
+        {
+            hi.saturating_sub(step).max(self.min_data_size_hi)
+        } else if num_evicts > 0
+            && loads > 0
+            && (hits as f64) < ADAPTIVE_SIZING_TARGET_HIT_RATE * loads as f64
+        {
+            hi.saturating_add(step).min(self.max_data_size_hi)
+        } else {
+            hi
+        };
+        if new_hi == hi {
+            return false;
+        }
+
+        // Keeps the ratio of the configured watermarks
+        let new_lo = (self.max_data_size_lo as u128 * new_hi as u128)
+            .checked_div(self.max_data_size_hi as u128)
+            .unwrap_or_default() as usize;
+        debug!("Adapting the read cache watermarks to {new_lo}, {new_hi}");
+        limits.lo.store(new_lo, Ordering::Relaxed);
+        limits.hi.store(new_hi, Ordering::Relaxed);
+        true
+    }
+}
+
+/// Returns the fraction of the system's memory which is available, if known
+#[cfg(target_os = "linux")]
+fn available_memory_fraction() -> Option<f64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let field_kb = |name: &str| {
+        meminfo.lines().find_map(|line| {
+            line.strip_prefix(name)?
+                .trim()
+                .strip_suffix("kB")?
+                .trim()
+                .parse::<u64>()
+                .ok()
+        })
+    };
+    let total = field_kb("MemTotal:")?;
+    let available = field_kb("MemAvailable:")?;
+    (total > 0).then(|| available as f64 / total as f64)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn available_memory_fraction() -> Option<f64> {
+    None
+}
+
 /// Updates atomic `stat` with the delta of `old` and `new`
 #[inline]
 fn update_stat(stat: &AtomicUsize, old: usize, new: usize) {
@@ -668,4 +1356,199 @@ mod tests {
         update_stat(&stat, old, new);
         assert_eq!(stat.into_inner(), val - old + new);
     }
+
+    #[test]
+    fn test_s3_fifo_evicts_entries_loaded_once() {
+        const ACCOUNT_DATA_SIZE: usize = 200;
+        const NUM_HOT_ACCOUNTS: usize = 10;
+        const NUM_COLD_ACCOUNTS: usize = 5;
+        let account_size = CACHE_ENTRY_SIZE + ACCOUNT_DATA_SIZE;
+        let cache = ReadOnlyAccountsCache::new_with_policy(
+            NUM_HOT_ACCOUNTS * account_size,
+            usize::MAX, // <-- do not evict in the background
+            1,
+            8,
+            ReadCacheEvictionPolicy::S3Fifo,
+            None,
+        );
+        let account = AccountSharedData::new(1, ACCOUNT_DATA_SIZE, &Pubkey::default());
+        let hot_pubkeys: Vec<_> = repeat_with(Pubkey::new_unique)
+            .take(NUM_HOT_ACCOUNTS)
+            .collect();
+        for pubkey in &hot_pubkeys {
+            cache.store(*pubkey, 0, account.clone());
+            assert!(cache.load(*pubkey, 0).is_some());
+        }
+        let cold_pubkeys: Vec<_> = repeat_with(Pubkey::new_unique)
+            .take(NUM_COLD_ACCOUNTS)
+            .collect();
+        for pubkey in &cold_pubkeys {
+            cache.store(*pubkey, 0, account.clone());
+        }
+
+        let mut rng = SmallRng::seed_from_u64(0);
+        let mut evicted = Vec::new();
+        let num_evicts = cache.evict_in_foreground(1, &mut rng, |pubkey, entry| {
+            assert!(entry.is_some());
+            evicted.push(*pubkey);
+        });
+        assert_eq!(num_evicts, NUM_COLD_ACCOUNTS as u64);
+        assert_eq!(evicted, cold_pubkeys);
+        assert_eq!(cache.cache_len(), NUM_HOT_ACCOUNTS);
+        assert!(
+            hot_pubkeys
+                .iter()
+                .all(|pubkey| cache.cache.contains_key(pubkey))
+        );
+        assert_eq!(
+            cache.get_and_reset_stats().promotions,
+            NUM_HOT_ACCOUNTS as u64
+        );
+
+        // Accounts stored again soon after their eviction skip the small queue
+        let PolicyState::S3Fifo(queues) = &cache.eviction.policy else {
+            panic!("unexpected eviction policy");
+        };
+        let pubkey = cold_pubkeys[0];
+        cache.store(pubkey, 0, account);
+        assert!(queues.small.lock().unwrap().is_empty());
+        assert!(
+            queues
+                .main
+                .lock()
+                .unwrap()
+                .iter()
+                .any(|item| item.key == pubkey)
+        );
+    }
+
+    #[test]
+    fn test_s3_fifo_purges_removed_entries() {
+        let cache = ReadOnlyAccountsCache::new_with_policy(
+            usize::MAX,
+            usize::MAX,
+            1,
+            8,
+            ReadCacheEvictionPolicy::S3Fifo,
+            None,
+        );
+        let PolicyState::S3Fifo(queues) = &cache.eviction.policy else {
+            panic!("unexpected eviction policy");
+        };
+        let account = AccountSharedData::new(1, 0, &Pubkey::default());
+        let pubkey = Pubkey::new_unique();
+        for _ in 0..10 {
+            cache.store(pubkey, 0, account.clone());
+            cache.remove(&pubkey);
+        }
+        cache.store(pubkey, 0, account);
+
+        // The evictor may have purged the queues already, which is the same
+        queues.purge_if_oversized(&cache.cache, cache.cache_len());
+        let small = queues.small.lock().unwrap();
+        assert_eq!(small.len(), 1);
+        assert_eq!(
+            queues.small_data_size.load(Ordering::Relaxed),
+            CACHE_ENTRY_SIZE
+        );
+        assert_eq!(
+            small.front().unwrap().seq,
+            cache.cache.get(&pubkey).unwrap().queue_seq
+        );
+    }
+
+    #[test]
+    fn test_tiny_lfu_admission() {
+        const NUM_ACCOUNTS: usize = 10;
+        let cache = ReadOnlyAccountsCache::new_with_policy(
+            NUM_ACCOUNTS * CACHE_ENTRY_SIZE,
+            usize::MAX, // <-- do not evict in the background
+            4,
+            8,
+            ReadCacheEvictionPolicy::TinyLfu,
+            None,
+        );
+        let account = AccountSharedData::new(1, 0, &Pubkey::default());
+        let pubkeys: Vec<_> = repeat_with(Pubkey::new_unique).take(NUM_ACCOUNTS).collect();
+        for pubkey in &pubkeys {
+            cache.store(*pubkey, 0, account.clone());
+            for _ in 0..3 {
+                assert!(cache.load(*pubkey, 0).is_some());
+            }
+        }
+
+        // A full cache does not store an account loaded less often than the
+        // cached ones
+        let pubkey = Pubkey::new_unique();
+        cache.store(pubkey, 0, account.clone());
+        assert!(!cache.cache.contains_key(&pubkey));
+        assert_eq!(cache.get_and_reset_stats().admission_rejects, 1);
+
+        // ...but one loaded more often, and evicts the others first
+        for _ in 0..5 {
+            assert!(cache.load(pubkey, 0).is_none());
+        }
+        cache.store(pubkey, 0, account);
+        assert!(cache.cache.contains_key(&pubkey));
+        assert_eq!(cache.get_and_reset_stats().admission_rejects, 0);
+
+        let mut rng = SmallRng::seed_from_u64(0);
+        let num_evicts = cache.evict_in_foreground(NUM_ACCOUNTS, &mut rng, |evicted, _| {
+            assert_ne!(*evicted, pubkey);
+        });
+        assert_eq!(num_evicts, 1);
+        assert!(cache.cache.contains_key(&pubkey));
+    }
+
+    #[test]
+    fn test_adaptive_sizing() {
+        let limits = DataSizeLimits {
+            lo: AtomicUsize::new(800),
+            hi: AtomicUsize::new(1000),
+        };
+        let adaptive = AdaptiveSizing {
+            min_data_size_hi: 700,
+            max_data_size_lo: 800,
+            max_data_size_hi: 1000,
+            hits: AtomicU64::default(),
+            misses: AtomicU64::default(),
+        };
+        let watermarks = || {
+            (
+                limits.lo.load(Ordering::Relaxed),
+                limits.hi.load(Ordering::Relaxed),
+            )
+        };
+
+        // Shrinks while memory is low, down to the minimum size
+        assert!(adaptive.adapt(&limits, 0, Some(0.05)));
+        assert_eq!(watermarks(), (700, 875));
+        assert!(adaptive.adapt(&limits, 0, Some(0.05)));
+        assert_eq!(watermarks(), (612, 766));
+        assert!(adaptive.adapt(&limits, 0, Some(0.05)));
+        assert_eq!(watermarks(), (560, 700));
+        assert!(!adaptive.adapt(&limits, 0, Some(0.05)));
+
+        // Does not grow without evictions, or while the hit rate is high
+        adaptive.record_load(false);
+        assert!(!adaptive.adapt(&limits, 0, Some(0.5)));
+        for _ in 0..100 {
+            adaptive.record_load(true);
+        }
+        assert!(!adaptive.adapt(&limits, 1, Some(0.5)));
+        assert_eq!(watermarks(), (560, 700));
+
+        // Grows while evicting and missing, up to the maximum size
+        let mut num_resizes = 0;
+        loop {
+            adaptive.record_load(true);
+            adaptive.record_load(false);
+            if !adaptive.adapt(&limits, 1, None) {
+                break;
+            }
+            num_resizes += 1;
+        }
+        assert_eq!(num_resizes, 4);
+        assert_eq!(watermarks(), (800, 1000));
+    }
 }
@@ -633,8 +633,10 @@ mod tests {
 
                     let storage = setup_sample_storage(&db, source_slot);
                     // store the accounts so they can be looked up later in `db`
-                    if let Some(offsets) =
-                        storage.accounts.write_accounts(&(source_slot, &three[..]))
+                    if let Some(offsets) = storage
+                        .accounts
+                        .write_accounts(&(source_slot, &three[..]))
+                        .unwrap()
                     {
                         three_accounts_from_storage_byval
                             .iter_mut()
@@ -764,6 +766,7 @@ mod tests {
                                     if let Some(offsets) = storage
                                         .accounts
                                         .write_accounts(&(slot, &raw2_refs[range.clone()]))
+                                        .unwrap()
                                     {
                                         result.iter_mut().zip(offsets.offsets.iter()).for_each(
                                             |(account, offset)| {
@@ -0,0 +1,214 @@
+//! Placement of account storages across tiers of storage paths.
+//!
+//! Storages for recent slots live on the hot tier, which is made up of the regular account
+//! paths and is expected to be backed by fast disks. When ancient storage paths are configured,
+//! storages that have become ancient are written to, or moved onto, the ancient tier instead.
+//! Ancient storages are rarely written and read much less often than recent ones, so the
+//! ancient tier can be backed by larger, slower disks.
+
+use {
+    crate::account_storage_entry::AccountStorageEntry,
+    rand::{Rng, rng},
+    std::{
+        path::{Path, PathBuf},
+        sync::{
+            Arc,
+            atomic::{AtomicU64, Ordering},
+        },
+    },
+};
+
+/// The tier a storage is placed on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum StorageTier {
+    /// Fast storage for recent slots
+    Hot,
+    /// Slow storage for ancient slots
+    Ancient,
+}
+
+/// Counters for the accesses to, and migrations onto, a storage tier
+#[derive(Debug, Default)]
+pub(crate) struct StorageTierStats {
+    /// Number of account loads served from storages on this tier
+    pub(crate) reads: AtomicU64,
+    /// Number of storages moved onto this tier
+    pub(crate) migrated_storages: AtomicU64,
+    /// Number of bytes moved onto this tier
+    pub(crate) migrated_bytes: AtomicU64,
+}
+
+#[derive(Debug)]
+pub(crate) struct StoragePlacement {
+    hot_paths: Vec<PathBuf>,
+    ancient_paths: Vec<PathBuf>,
+    hot_stats: StorageTierStats,
+    ancient_stats: StorageTierStats,
+}
+
+impl StoragePlacement {
+    /// Creates a placement over `paths`, of which `ancient_paths` make up the ancient tier.
+    ///
+    /// All other paths make up the hot tier. If there are none, the hot tier falls back to all
+    /// of `paths`. If `ancient_paths` is empty, there is only a single tier and storages are
+    /// spread across all of `paths` regardless of the requested tier.
+    pub(crate) fn new(paths: &[PathBuf], ancient_paths: &[PathBuf]) -> Self {
+        let mut hot_paths: Vec<_> = paths
+            .iter()
+            .filter(|path| !ancient_paths.contains(path))
+            .cloned()
+            .collect();
+        if hot_paths.is_empty() {
+            hot_paths = paths.to_vec();
+        }
+        Self {
+            hot_paths,
+            ancient_paths: ancient_paths.to_vec(),
+            hot_stats: StorageTierStats::default(),
+            ancient_stats: StorageTierStats::default(),
+        }
+    }
+
+    /// Returns true if storages are placed on separate hot and ancient tiers
+    pub(crate) fn is_tiered(&self) -> bool {
+        !self.ancient_paths.is_empty()
+    }
+
+    /// Returns the paths of `tier`
+    pub(crate) fn paths(&self, tier: StorageTier) -> &[PathBuf] {
+        match tier {
+            StorageTier::Ancient if self.is_tiered() => &self.ancient_paths,
+            _ => &self.hot_paths,
+        }
+    }
+
+    /// Picks a random path of `tier` to create a new storage in
+    pub(crate) fn pick_path(&self, tier: StorageTier) -> &Path {
+        let paths = self.paths(tier);
+        &paths[rng().random_range(0..paths.len())]
+    }
+
+    /// Returns the tier the storage file at `path` is placed on
+    ///
+    /// Storages remember their tier, see `AccountStorageEntry::tier()`, so this is only needed
+    /// once per storage.
+    pub(crate) fn tier_of(&self, path: &Path) -> StorageTier {
+        let is_ancient = self.is_tiered()
+            && path
+                .parent()
+                .is_some_and(|dir| self.ancient_paths.iter().any(|path| path == dir));
+        if is_ancient {
+            StorageTier::Ancient
+        } else {
+            StorageTier::Hot
+        }
+    }
+
+    /// Returns the stats of `tier`
+    pub(crate) fn stats(&self, tier: StorageTier) -> &StorageTierStats {
+        match tier {
+            StorageTier::Hot => &self.hot_stats,
+            StorageTier::Ancient => &self.ancient_stats,
+        }
+    }
+
+    /// Counts a load of an account from `storage`, if tiering is enabled
+    pub(crate) fn record_read(&self, storage: &AccountStorageEntry) {
+        if self.is_tiered() {
+            self.stats(storage.tier(self))
+                .reads
+                .fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Reports the number of storages and bytes on each tier, along with the stats gathered
+    /// since the last report
+    pub(crate) fn report(&self, storages: impl Iterator<Item = Arc<AccountStorageEntry>>) {
+        let mut num_storages = [0u64; 2];
+        let mut bytes = [0u64; 2];
+        for storage in storages {
+            let tier = storage.tier(self) as usize;
+            num_storages[tier] += 1;
+            bytes[tier] += storage.written_bytes();
+        }
+        let take = |stat: &AtomicU64| stat.swap(0, Ordering::Relaxed) as i64;
+        datapoint_info!(
+            "accounts_db_storage_tiers",
+            ("hot_storages", num_storages[StorageTier::Hot as usize], i64),
+            ("hot_bytes", bytes[StorageTier::Hot as usize], i64),
+            ("hot_reads", take(&self.hot_stats.reads), i64),
+            (
+                "ancient_storages",
+                num_storages[StorageTier::Ancient as usize],
+                i64
+            ),
+            ("ancient_bytes", bytes[StorageTier::Ancient as usize], i64),
+            ("ancient_reads", take(&self.ancient_stats.reads), i64),
+            (
+                "migrated_storages",
+                take(&self.ancient_stats.migrated_storages),
+                i64
+            ),
+            (
+                "migrated_bytes",
+                take(&self.ancient_stats.migrated_bytes),
+                i64
+            ),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_storage_placement_untiered() {
+        let paths = vec![PathBuf::from("/a"), PathBuf::from("/b")];
+        let placement = StoragePlacement::new(&paths, &[]);
+        assert!(!placement.is_tiered());
+        assert_eq!(placement.paths(StorageTier::Hot), paths);
+        assert_eq!(placement.paths(StorageTier::Ancient), paths);
+        assert_eq!(
+            placement.tier_of(&PathBuf::from("/b/1.1")),
+            StorageTier::Hot
+        );
+    }
+
+    #[test]
+    fn test_storage_placement_tiered() {
+        let paths = vec![
+            PathBuf::from("/hot"),
+            PathBuf::from("/slow0"),
+            PathBuf::from("/slow1"),
+        ];
+        let ancient_paths = vec![PathBuf::from("/slow0"), PathBuf::from("/slow1")];
+        let placement = StoragePlacement::new(&paths, &ancient_paths);
+        assert!(placement.is_tiered());
+        assert_eq!(placement.paths(StorageTier::Hot), [PathBuf::from("/hot")]);
+        assert_eq!(placement.paths(StorageTier::Ancient), ancient_paths);
+        assert_eq!(placement.pick_path(StorageTier::Hot), Path::new("/hot"));
+        assert!(
+            ancient_paths
+                .iter()
+                .any(|path| path == placement.pick_path(StorageTier::Ancient))
+        );
+        assert_eq!(
+            placement.tier_of(&PathBuf::from("/hot/1.1")),
+            StorageTier::Hot
+        );
+        assert_eq!(
+            placement.tier_of(&PathBuf::from("/slow1/1.1")),
+            StorageTier::Ancient
+        );
+    }
+
+    #[test]
+    fn test_storage_placement_only_ancient_paths() {
+        // if every path is an ancient path, the hot tier shares them
+        let paths = vec![PathBuf::from("/slow")];
+        let placement = StoragePlacement::new(&paths, &paths);
+        assert_eq!(placement.paths(StorageTier::Hot), paths);
+        assert_eq!(placement.paths(StorageTier::Ancient), paths);
+    }
+}
@@ -0,0 +1,631 @@
+//! A cache of the results of scanning each storage at startup.
+//!
+//! Generating the accounts index at startup reads every account in every storage, data included,
+//! to compute the accounts lt hash and capitalization. Storages are immutable once flushed, so the
+//! result of that scan only ever needs to be computed once per storage. A storage scan cache is a
+//! directory holding one file per storage with everything index generation needs from it:
+//!
+//! ```text
+//! +--------------+----------------+------------------------+------------+
+//! | header       | lt hash        | entries                | checksum   |
+//! | (104 bytes)  | (2048 bytes)   | (40 bytes/account)     | (32 bytes) |
+//! +--------------+----------------+------------------------+------------+
+//! ```
+//!
+//! The accounts index itself is not persisted: it is still generated at every startup, in memory
+//! and in the disk buckets, from the accounts and totals read from these files instead of from the
+//! storages.
+//!
+//! A file is only used if its slot, id and length match the storage it describes and the
+//! fingerprint of the storage file's contents is unchanged. Anything else, including a failed
+//! checksum, is ignored and index generation falls back to scanning the storage.
+//!
+//! Files describe *all* the accounts in a storage. Accounts marked obsolete later on are
+//! subtracted when the file is loaded, so a file written once is reused by every later snapshot
+//! that references the same storage.
+//!
+//! A file is matched to its storage by a fingerprint of the storage file's length and both ends,
+//! not of its whole contents, so that validating a scan cache does not read every storage it
+//! stands in for. Storages are only ever appended to before they are flushed, and a storage
+//! rewritten by shrink or ancient packing gets a new id, so this catches every change the
+//! validator makes. A storage file modified in place by anything else may go unnoticed.
+
+use {
+    crate::{
+        account_info::Offset,
+        account_storage_entry::AccountStorageEntry,
+        accounts_db::{AccountsDb, AccountsFileId},
+        accounts_file::AccountsFile,
+        append_vec,
+        is_zero_lamport::IsZeroLamport as _,
+    },
+    agave_fs::{buffered_reader::RequiredLenBufFileRead, file_io::read_into_buffer},
+    log::*,
+    rayon::prelude::*,
+    solana_account::ReadableAccount as _,
+    solana_clock::Slot,
+    solana_lattice_hash::{batch, lt_hash::LtHash},
+    solana_pubkey::Pubkey,
+    std::{
+        collections::HashSet,
+        fs::{self, File},
+        io,
+        path::{Path, PathBuf},
+        sync::{Arc, Mutex},
+    },
+};
+
+const FILE_MAGIC: [u8; 8] = *b"AGVIDXC1";
+const HEADER_SIZE: usize = 104;
+const LT_HASH_SIZE: usize = LtHash::NUM_ELEMENTS * size_of::<u16>();
+const ENTRY_SIZE: usize = 40;
+const CHECKSUM_SIZE: usize = 32;
+/// Number of bytes hashed from each end of a storage file to fingerprint its contents. See the
+/// module documentation for why the whole file is not hashed.
+const FINGERPRINT_LEN: usize = 4096;
+/// Set in an entry's offset when the account has zero lamports
+const ZERO_LAMPORT_FLAG: u64 = 1 << 63;
+const TMP_FILE_EXTENSION: &str = "tmp";
+
+/// An account, as recorded in a scan result
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ScannedAccount {
+    pub(crate) pubkey: Pubkey,
+    pub(crate) offset: Offset,
+    pub(crate) is_zero_lamport: bool,
+}
+
+/// Everything index generation needs from one storage
+#[derive(Debug, PartialEq)]
+pub(crate) struct StorageScanResult {
+    pub(crate) accounts: Vec<ScannedAccount>,
+    /// sum of the stored sizes of all accounts
+    pub(crate) stored_size: u64,
+    /// sum of the data lens of all non-zero-lamport accounts
+    pub(crate) accounts_data_len: u64,
+    pub(crate) capitalization: u64,
+    /// lt hash of all accounts
+    pub(crate) lt_hash: LtHash,
+}
+
+/// The fixed fields identifying the storage a scan result file was written for
+#[derive(Debug, PartialEq, Eq)]
+struct Header {
+    slot: Slot,
+    id: u64,
+    len: u64,
+    fingerprint: [u8; 32],
+    num_accounts: u64,
+    stored_size: u64,
+    accounts_data_len: u64,
+    capitalization: u64,
+}
+
+impl Header {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&FILE_MAGIC);
+        out.extend_from_slice(&self.slot.to_le_bytes());
+        out.extend_from_slice(&self.id.to_le_bytes());
+        out.extend_from_slice(&self.len.to_le_bytes());
+        out.extend_from_slice(&self.fingerprint);
+        out.extend_from_slice(&self.num_accounts.to_le_bytes());
+        out.extend_from_slice(&self.stored_size.to_le_bytes());
+        out.extend_from_slice(&self.accounts_data_len.to_le_bytes());
+        out.extend_from_slice(&self.capitalization.to_le_bytes());
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < HEADER_SIZE || bytes[..8] != FILE_MAGIC {
+            return None;
+        }
+        Some(Self {
+            slot: read_u64(bytes, 8),
+            id: read_u64(bytes, 16),
+            len: read_u64(bytes, 24),
+            fingerprint: bytes[32..64].try_into().unwrap(),
+            num_accounts: read_u64(bytes, 64),
+            stored_size: read_u64(bytes, 72),
+            accounts_data_len: read_u64(bytes, 80),
+            capitalization: read_u64(bytes, 88),
+        })
+    }
+
+    /// Returns true if this header was written for `storage` in its current state
+    fn matches(&self, storage: &AccountStorageEntry) -> io::Result<bool> {
+        Ok(self.slot == storage.slot()
+            && self.id == u64::from(storage.id())
+            && self.len == storage.accounts.len() as u64
+            && self.fingerprint == fingerprint(&storage.accounts)?)
+    }
+}
+
+/// Counts of what [`StorageScanCache::update`] did
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ScanCacheUpdateStats {
+    /// storages whose scan result file was written
+    pub num_written: usize,
+    /// storages whose existing scan result file was kept
+    pub num_reused: usize,
+    /// scan result files removed because their storage is gone
+    pub num_removed: usize,
+}
+
+/// A directory of per-storage scan result files
+#[derive(Debug)]
+pub struct StorageScanCache {
+    dir: PathBuf,
+    /// The storages known to have a valid file, written or checked by an earlier update.
+    /// `None` until the first update, which also removes any stale file left in the directory.
+    cached: Mutex<Option<HashSet<(Slot, AccountsFileId)>>>,
+}
+
+impl StorageScanCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            cached: Mutex::default(),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Brings the scan cache up to date with `storages`
+    ///
+    /// Files are written for storages that lack a valid one, and files for storages not in
+    /// `storages` are removed. Storages must be flushed and no longer written to.
+    ///
+    /// Storages are immutable once flushed, so a storage that had a valid file at an earlier
+    /// update of this scan cache is skipped without being checked again. Only the first update
+    /// checks every storage, and lists the directory to find stale files.
+    pub fn update(
+        &self,
+        storages: &[Arc<AccountStorageEntry>],
+    ) -> io::Result<ScanCacheUpdateStats> {
+        let mut cached = self.cached.lock().unwrap();
+        let previously_cached = cached.take();
+        let is_first_update = previously_cached.is_none();
+        let previously_cached = previously_cached.unwrap_or_default();
+        if is_first_update {
+            fs::create_dir_all(&self.dir)?;
+        }
+
+        let new_storages: Vec<_> = storages
+            .iter()
+            .filter(|storage| !previously_cached.contains(&(storage.slot(), storage.id())))
+            .collect();
+        let was_written = new_storages
+            .par_iter()
+            .map_init(append_vec::new_scan_accounts_reader, |reader, storage| {
+                if self.has_valid_header(storage)? {
+                    return Ok(false);
+                }
+                let scan_result = StorageScanResult::new_from_storage(reader, storage)?;
+                self.write(storage, &scan_result)?;
+                Ok(true)
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        let num_written = was_written
+            .iter()
+            .filter(|was_written| **was_written)
+            .count();
+
+        let current: HashSet<_> = storages
+            .iter()
+            .map(|storage| (storage.slot(), storage.id()))
+            .collect();
+        let mut num_removed = 0;
+        if is_first_update {
+            let file_names: HashSet<_> = current
+                .iter()
+                .map(|(slot, id)| AccountsFile::file_name(*slot, *id))
+                .collect();
+            for entry in fs::read_dir(&self.dir)? {
+                let path = entry?.path();
+                let is_current = path
+                    .file_name()
+                    .and_then(|file_name| file_name.to_str())
+                    .is_some_and(|file_name| file_names.contains(file_name));
+                if !is_current {
+                    fs::remove_file(&path)?;
+                    num_removed += 1;
+                }
+            }
+        } else {
+            for (slot, id) in previously_cached.difference(&current) {
+                match fs::remove_file(self.path(*slot, *id)) {
+                    Ok(()) => num_removed += 1,
+                    Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                    Err(err) => return Err(err),
+                }
+            }
+        }
+        // Only remember the storages once every file is in place, so a failed update is retried
+        // in full by the next one
+        *cached = Some(current);
+
+        Ok(ScanCacheUpdateStats {
+            num_written,
+            num_reused: storages.len() - num_written,
+            num_removed,
+        })
+    }
+
+    /// Loads the scan result for `storage`
+    ///
+    /// Returns None if there is no scan result for `storage` or it does not match the storage.
+    /// Corrupt files are removed so the next update rewrites them.
+    pub(crate) fn load(&self, storage: &AccountStorageEntry) -> Option<StorageScanResult> {
+        let path = self.path(storage.slot(), storage.id());
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return None,
+            Err(err) => {
+                warn!("Unable to read scan result '{}': {err}", path.display());
+                return None;
+            }
+        };
+        let Some(header) = Header::decode(&bytes) else {
+            warn!("Removing invalid scan result '{}'", path.display());
+            self.remove(storage);
+            return None;
+        };
+        match header.matches(storage) {
+            Ok(true) => {}
+            Ok(false) => return None,
+            Err(err) => {
+                warn!(
+                    "Unable to fingerprint storage '{}': {err}",
+                    storage.path().display()
+                );
+                return None;
+            }
+        }
+        let scan_result = StorageScanResult::decode(&header, &bytes);
+        if scan_result.is_none() {
+            warn!("Removing corrupt scan result '{}'", path.display());
+            self.remove(storage);
+        }
+        scan_result
+    }
+
+    /// Removes the file of `storage`, so the next update writes it again
+    fn remove(&self, storage: &AccountStorageEntry) {
+        if let Some(cached) = self.cached.lock().unwrap().as_mut() {
+            cached.remove(&(storage.slot(), storage.id()));
+        }
+        _ = fs::remove_file(self.path(storage.slot(), storage.id()));
+    }
+
+    fn path(&self, slot: Slot, id: u32) -> PathBuf {
+        self.dir.join(AccountsFile::file_name(slot, id))
+    }
+
+    fn has_valid_header(&self, storage: &AccountStorageEntry) -> io::Result<bool> {
+        let path = self.path(storage.slot(), storage.id());
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(err) => return Err(err),
+        };
+        let mut bytes = [0; HEADER_SIZE];
+        let bytes_read = read_into_buffer(&file, file.metadata()?.len(), 0, &mut bytes)?;
+        match Header::decode(&bytes[..bytes_read]) {
+            Some(header) => header.matches(storage),
+            None => Ok(false),
+        }
+    }
+
+    /// Writes `scan_result` via a temporary file so readers never see a partial file
+    fn write(
+        &self,
+        storage: &AccountStorageEntry,
+        scan_result: &StorageScanResult,
+    ) -> io::Result<()> {
+        let header = Header {
+            slot: storage.slot(),
+            id: u64::from(storage.id()),
+            len: storage.accounts.len() as u64,
+            fingerprint: fingerprint(&storage.accounts)?,
+            num_accounts: scan_result.accounts.len() as u64,
+            stored_size: scan_result.stored_size,
+            accounts_data_len: scan_result.accounts_data_len,
+            capitalization: scan_result.capitalization,
+        };
+        let path = self.path(storage.slot(), storage.id());
+        let tmp_path = path.with_extension(TMP_FILE_EXTENSION);
+        fs::write(&tmp_path, scan_result.encode(&header))?;
+        fs::rename(&tmp_path, &path)
+    }
+}
+
+impl StorageScanResult {
+    /// Scans every account in `storage`, including ones marked obsolete
+    fn new_from_storage<'a>(
+        reader: &mut impl RequiredLenBufFileRead<'a>,
+        storage: &'a AccountStorageEntry,
+    ) -> io::Result<Self> {
+        let mut accounts = Vec::new();
+        let mut stored_size = 0;
+        let mut accounts_data_len = 0;
+        let mut capitalization = 0_u64;
+        let mut lt_hash_acc = batch::Accumulator::new();
+        storage
+            .accounts
+            .scan_accounts(reader, |offset, account| {
+                let data_len = account.data.len();
+                stored_size += storage.accounts.calculate_stored_size(data_len) as u64;
+                let is_zero_lamport = account.is_zero_lamport();
+                if !is_zero_lamport {
+                    accounts_data_len += data_len as u64;
+                }
+                capitalization = capitalization
+                    .checked_add(account.lamports())
+                    .expect("capitalization cannot overflow");
+                AccountsDb::add_account_to_lt_hash(&mut lt_hash_acc, &account, account.pubkey);
+                accounts.push(ScannedAccount {
+                    pubkey: *account.pubkey,
+                    offset,
+                    is_zero_lamport,
+                });
+            })
+            .map_err(io::Error::other)?;
+        Ok(Self {
+            accounts,
+            stored_size,
+            accounts_data_len,
+            capitalization,
+            lt_hash: lt_hash_acc.into_lt_hash(),
+        })
+    }
+
+    fn encode(&self, header: &Header) -> Vec<u8> {
+        let mut out = Vec::with_capacity(
+            HEADER_SIZE + LT_HASH_SIZE + self.accounts.len() * ENTRY_SIZE + CHECKSUM_SIZE,
+        );
+        header.encode(&mut out);
+        for element in self.lt_hash.0 {
+            out.extend_from_slice(&element.to_le_bytes());
+        }
+        for account in &self.accounts {
+            let mut offset = account.offset as u64;
+            if account.is_zero_lamport {
+                offset |= ZERO_LAMPORT_FLAG;
+            }
+            out.extend_from_slice(account.pubkey.as_ref());
+            out.extend_from_slice(&offset.to_le_bytes());
+        }
+        let checksum = blake3::hash(&out);
+        out.extend_from_slice(checksum.as_bytes());
+        out
+    }
+
+    fn decode(header: &Header, bytes: &[u8]) -> Option<Self> {
+        let num_accounts = usize::try_from(header.num_accounts).ok()?;
+        let expected_len = num_accounts
+            .checked_mul(ENTRY_SIZE)?
+            .checked_add(HEADER_SIZE + LT_HASH_SIZE + CHECKSUM_SIZE)?;
+        if bytes.len() != expected_len {
+            return None;
+        }
+        let (contents, checksum) = bytes.split_at(bytes.len() - CHECKSUM_SIZE);
+        if blake3::hash(contents).as_bytes() != checksum {
+            return None;
+        }
+
+        let mut lt_hash = LtHash::identity();
+        let lt_hash_bytes = &contents[HEADER_SIZE..HEADER_SIZE + LT_HASH_SIZE];
+        for (element, bytes) in lt_hash.0.iter_mut().zip(lt_hash_bytes.chunks_exact(2)) {
+            *element = u16::from_le_bytes(bytes.try_into().unwrap());
+        }
+        let accounts = contents[HEADER_SIZE + LT_HASH_SIZE..]
+            .chunks_exact(ENTRY_SIZE)
+            .map(|entry| {
+                let offset = read_u64(entry, 32);
+                ScannedAccount {
+                    pubkey: Pubkey::new_from_array(entry[..32].try_into().unwrap()),
+                    offset: (offset & !ZERO_LAMPORT_FLAG) as Offset,
+                    is_zero_lamport: offset & ZERO_LAMPORT_FLAG != 0,
+                }
+            })
+            .collect();
+        Some(Self {
+            accounts,
+            stored_size: header.stored_size,
+            accounts_data_len: header.accounts_data_len,
+            capitalization: header.capitalization,
+            lt_hash,
+        })
+    }
+}
+
+/// Hashes the length and both ends of `accounts_file`'s contents
+///
+/// Ids are reassigned when booting from an archive, so a stale scan result file can share a slot,
+/// id and length with an unrelated storage. The fingerprint tells them apart without reading the
+/// whole storage.
+fn fingerprint(accounts_file: &AccountsFile) -> io::Result<[u8; 32]> {
+    let len = accounts_file.len() as u64;
+    let file = File::open(accounts_file.path())?;
+    // AppendVecs may be preallocated past their length, while cold storages are shorter than
+    // their length once compressed
+    let contents_len = len.min(file.metadata()?.len());
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&len.to_le_bytes());
+    let mut buffer = [0; FINGERPRINT_LEN];
+    for start in [0, contents_len.saturating_sub(FINGERPRINT_LEN as u64)] {
+        let bytes_read = read_into_buffer(&file, contents_len, start, &mut buffer)?;
+        hasher.update(&buffer[..bytes_read]);
+    }
+    Ok(*hasher.finalize().as_bytes())
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{accounts_file::AccountsFileProvider, storage_placement::StorageTier},
+        solana_account::{AccountSharedData, ReadableAccount},
+        tempfile::TempDir,
+        test_case::test_case,
+    };
+
+    fn new_storage(
+        db: &AccountsDb,
+        slot: Slot,
+        accounts: &[(Pubkey, AccountSharedData)],
+        provider: AccountsFileProvider,
+    ) -> Arc<AccountStorageEntry> {
+        let storage = db.create_store_with_provider(slot, 4096 * 4, provider, StorageTier::Hot);
+        let accounts: Vec<_> = accounts
+            .iter()
+            .map(|(pubkey, account)| (pubkey, account))
+            .collect();
+        storage
+            .accounts
+            .write_accounts(&(slot, accounts.as_slice()))
+            .unwrap()
+            .unwrap();
+        storage.flush().unwrap();
+        Arc::new(storage)
+    }
+
+    fn new_accounts(num_accounts: u64) -> Vec<(Pubkey, AccountSharedData)> {
+        (0..num_accounts)
+            .map(|i| {
+                // every third account has zero lamports
+                let lamports = if i % 3 == 0 { 0 } else { i * 100 };
+                let data_len = (i * 10) as usize;
+                (
+                    Pubkey::new_unique(),
+                    AccountSharedData::new(lamports, data_len, &Pubkey::new_unique()),
+                )
+            })
+            .collect()
+    }
+
+    #[test_case(AccountsFileProvider::AppendVec)]
+    #[test_case(AccountsFileProvider::ColdStorage)]
+    fn test_update_and_load(provider: AccountsFileProvider) {
+        let db = AccountsDb::default_for_tests();
+        let scan_cache_dir = TempDir::new().unwrap();
+        let scan_cache = StorageScanCache::new(scan_cache_dir.path());
+        let accounts = new_accounts(10);
+        let storage = new_storage(&db, 1, &accounts, provider);
+
+        let stats = scan_cache.update(std::slice::from_ref(&storage)).unwrap();
+        assert_eq!(
+            stats,
+            ScanCacheUpdateStats {
+                num_written: 1,
+                ..ScanCacheUpdateStats::default()
+            }
+        );
+
+        let mut reader = append_vec::new_scan_accounts_reader();
+        let expected = StorageScanResult::new_from_storage(&mut reader, &storage).unwrap();
+        let loaded = scan_cache.load(&storage).unwrap();
+        assert_eq!(loaded, expected);
+        assert_eq!(loaded.accounts.len(), accounts.len());
+        for ((pubkey, account), loaded) in accounts.iter().zip(&loaded.accounts) {
+            assert_eq!(&loaded.pubkey, pubkey);
+            assert_eq!(loaded.is_zero_lamport, account.lamports() == 0);
+        }
+        let capitalization: u64 = accounts.iter().map(|(_, account)| account.lamports()).sum();
+        assert_eq!(loaded.capitalization, capitalization);
+
+        // a second update keeps the existing file
+        let stats = scan_cache.update(std::slice::from_ref(&storage)).unwrap();
+        assert_eq!(
+            stats,
+            ScanCacheUpdateStats {
+                num_reused: 1,
+                ..ScanCacheUpdateStats::default()
+            }
+        );
+
+        // and so does the first update of another instance, after checking the file
+        let scan_cache = StorageScanCache::new(scan_cache_dir.path());
+        let stats = scan_cache.update(std::slice::from_ref(&storage)).unwrap();
+        assert_eq!(
+            stats,
+            ScanCacheUpdateStats {
+                num_reused: 1,
+                ..ScanCacheUpdateStats::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_update_removes_stale_files() {
+        let db = AccountsDb::default_for_tests();
+        let scan_cache_dir = TempDir::new().unwrap();
+        let scan_cache = StorageScanCache::new(scan_cache_dir.path());
+        let storage1 = new_storage(&db, 1, &new_accounts(3), AccountsFileProvider::AppendVec);
+        let storage2 = new_storage(&db, 2, &new_accounts(3), AccountsFileProvider::AppendVec);
+        scan_cache
+            .update(&[Arc::clone(&storage1), Arc::clone(&storage2)])
+            .unwrap();
+
+        let stats = scan_cache.update(&[Arc::clone(&storage2)]).unwrap();
+        assert_eq!(
+            stats,
+            ScanCacheUpdateStats {
+                num_written: 0,
+                num_reused: 1,
+                num_removed: 1,
+            }
+        );
+        assert!(scan_cache.load(&storage1).is_none());
+        assert!(scan_cache.load(&storage2).is_some());
+    }
+
+    #[test]
+    fn test_load_mismatched_storage() {
+        let db = AccountsDb::default_for_tests();
+        let scan_cache_dir = TempDir::new().unwrap();
+        let scan_cache = StorageScanCache::new(scan_cache_dir.path());
+        let storage = new_storage(&db, 1, &new_accounts(3), AccountsFileProvider::AppendVec);
+        scan_cache.update(std::slice::from_ref(&storage)).unwrap();
+
+        // same slot and id, different contents
+        let path = scan_cache.path(storage.slot(), storage.id());
+        let other_db = AccountsDb::default_for_tests();
+        let other = new_storage(
+            &other_db,
+            1,
+            &new_accounts(3),
+            AccountsFileProvider::AppendVec,
+        );
+        assert_eq!(other.id(), storage.id());
+        assert!(scan_cache.load(&other).is_none());
+        // a mismatch is not corruption, so the file is kept
+        assert!(path.exists());
+    }
+
+    #[test]
+    fn test_load_corrupt_file() {
+        let db = AccountsDb::default_for_tests();
+        let scan_cache_dir = TempDir::new().unwrap();
+        let scan_cache = StorageScanCache::new(scan_cache_dir.path());
+        let storage = new_storage(&db, 1, &new_accounts(3), AccountsFileProvider::AppendVec);
+        scan_cache.update(std::slice::from_ref(&storage)).unwrap();
+
+        let path = scan_cache.path(storage.slot(), storage.id());
+        let mut bytes = fs::read(&path).unwrap();
+        let last = bytes.len() - CHECKSUM_SIZE - 1;
+        bytes[last] ^= 0xff;
+        fs::write(&path, bytes).unwrap();
+
+        assert!(scan_cache.load(&storage).is_none());
+        assert!(!path.exists());
+        let stats = scan_cache.update(std::slice::from_ref(&storage)).unwrap();
+        assert_eq!(stats.num_written, 1);
+        assert!(scan_cache.load(&storage).is_some());
+    }
+}
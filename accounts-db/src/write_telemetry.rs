@@ -0,0 +1,382 @@
+//! Sampled telemetry of the accounts written into the write cache.
+//!
+//! Every account write goes through the write cache, so it is where the owner programs and
+//! accounts responsible for the load on accounts-db can be told apart. Tracking every write would
+//! be too expensive, so only one in every `sample_rate` stored accounts is recorded, and counts
+//! are scaled back up by the sample rate when they are read. They are estimates.
+
+use {
+    ahash::HashMap,
+    serde::{Deserialize, Serialize},
+    solana_clock::Slot,
+    solana_pubkey::Pubkey,
+    solana_time_utils::AtomicInterval,
+    std::{
+        num::NonZeroU64,
+        sync::{
+            Mutex,
+            atomic::{AtomicU64, Ordering},
+        },
+    },
+};
+
+/// Once more accounts (or owners) than this are tracked, the half written the least is dropped.
+/// Entries with as many writes are dropped by pubkey, so the half is dropped even when most entries
+/// have been sampled only once.
+const MAX_TRACKED_ENTRIES: usize = 100_000;
+
+/// Number of owners and accounts reported to metrics
+const NUM_REPORTED_ENTRIES: usize = 10;
+
+const REPORT_INTERVAL_MS: u64 = 10_000;
+
+/// A sampled write of an account
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SampledWrite {
+    pub pubkey: Pubkey,
+    pub owner: Pubkey,
+    pub data_len: usize,
+}
+
+/// Estimated writes of an account since startup
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HotAccount {
+    pub pubkey: Pubkey,
+    /// Owner of the account when it was last written
+    pub owner: Pubkey,
+    pub writes: u64,
+    /// Bytes of account data written
+    pub bytes: u64,
+    /// First and last slot the account was seen written in
+    pub first_slot: Slot,
+    pub last_slot: Slot,
+}
+
+/// Estimated writes of the accounts owned by a program since startup
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HotOwner {
+    pub owner: Pubkey,
+    pub writes: u64,
+    /// Bytes of account data written
+    pub bytes: u64,
+    /// Writes to accounts that had been written before, in this slot or an earlier one
+    pub rewrites: u64,
+}
+
+/// The accounts and owners written the most
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WriteTelemetryReport {
+    pub sample_rate: u64,
+    pub accounts: Vec<HotAccount>,
+    pub owners: Vec<HotOwner>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct AccountWrites {
+    owner: Pubkey,
+    writes: u64,
+    bytes: u64,
+    first_slot: Slot,
+    last_slot: Slot,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct OwnerWrites {
+    writes: u64,
+    bytes: u64,
+    rewrites: u64,
+}
+
+#[derive(Debug, Default)]
+struct Tracked {
+    accounts: HashMap<Pubkey, AccountWrites>,
+    owners: HashMap<Pubkey, OwnerWrites>,
+}
+
+/// Counts the writes of a sample of the stored accounts, per account and per owner
+#[derive(Debug)]
+pub(crate) struct WriteTelemetry {
+    sample_rate: NonZeroU64,
+    /// Number of accounts stored so far, decides which accounts are sampled
+    num_stored: AtomicU64,
+    tracked: Mutex<Tracked>,
+    last_report: AtomicInterval,
+}
+
+impl WriteTelemetry {
+    pub(crate) fn new(sample_rate: NonZeroU64) -> Self {
+        Self {
+            sample_rate,
+            num_stored: AtomicU64::default(),
+            tracked: Mutex::default(),
+            last_report: AtomicInterval::default(),
+        }
+    }
+
+    /// Reserves `num_accounts` writes, returns the index of the first one to pass to
+    /// [`Self::is_sampled`]
+    pub(crate) fn start_batch(&self, num_accounts: usize) -> u64 {
+        self.num_stored
+            .fetch_add(num_accounts as u64, Ordering::Relaxed)
+    }
+
+    /// Returns true if the account at `index` of the batch started at `batch_start` is sampled
+    pub(crate) fn is_sampled(&self, batch_start: u64, index: usize) -> bool {
+        (batch_start + index as u64) % self.sample_rate == 0
+    }
+
+    /// Records the sampled writes of accounts into `slot`
+    pub(crate) fn record(&self, slot: Slot, writes: &[SampledWrite]) {
+        if writes.is_empty() {
+            return;
+        }
+        let mut tracked = self.tracked.lock().unwrap();
+        for write in writes {
+            let mut is_rewrite = false;
+            tracked
+                .accounts
+                .entry(write.pubkey)
+                .and_modify(|account| {
+                    is_rewrite = true;
+                    account.owner = write.owner;
+                    account.writes += 1;
+                    account.bytes += write.data_len as u64;
+                    account.first_slot = account.first_slot.min(slot);
+                    account.last_slot = account.last_slot.max(slot);
+                })
+                .or_insert(AccountWrites {
+                    owner: write.owner,
+                    writes: 1,
+                    bytes: write.data_len as u64,
+                    first_slot: slot,
+                    last_slot: slot,
+                });
+            let owner = tracked.owners.entry(write.owner).or_default();
+            owner.writes += 1;
+            owner.bytes += write.data_len as u64;
+            owner.rewrites += u64::from(is_rewrite);
+        }
+        retain_most_written(&mut tracked.accounts, |account| account.writes);
+        retain_most_written(&mut tracked.owners, |owner| owner.writes);
+    }
+
+    /// Returns the `limit` accounts and owners with the most writes
+    ///
+    /// The tracked entries are copied under the lock and sorted after releasing it, so that
+    /// stores recording their writes meanwhile are not held up.
+    pub(crate) fn report(&self, limit: usize) -> WriteTelemetryReport {
+        let sample_rate = self.sample_rate.get();
+        let (accounts, owners) = {
+            let tracked = self.tracked.lock().unwrap();
+            (
+                tracked
+                    .accounts
+                    .iter()
+                    .map(|(pubkey, account)| (*pubkey, *account))
+                    .collect::<Vec<_>>(),
+                tracked
+                    .owners
+                    .iter()
+                    .map(|(pubkey, owner)| (*pubkey, *owner))
+                    .collect::<Vec<_>>(),
+            )
+        };
+        let accounts = most_written(accounts, limit, |account| account.writes)
+            .map(|(pubkey, account)| HotAccount {
+                pubkey,
+                owner: account.owner,
+                writes: account.writes * sample_rate,
+                bytes: account.bytes * sample_rate,
+                first_slot: account.first_slot,
+                last_slot: account.last_slot,
+            })
+            .collect();
+        let owners = most_written(owners, limit, |owner| owner.writes)
+            .map(|(pubkey, owner)| HotOwner {
+                owner: pubkey,
+                writes: owner.writes * sample_rate,
+                bytes: owner.bytes * sample_rate,
+                rewrites: owner.rewrites * sample_rate,
+            })
+            .collect();
+        WriteTelemetryReport {
+            sample_rate,
+            accounts,
+            owners,
+        }
+    }
+
+    /// Reports the accounts and owners with the most writes to metrics, if it is time to
+    pub(crate) fn maybe_submit_metrics(&self) {
+        if !self.last_report.should_update(REPORT_INTERVAL_MS) {
+            return;
+        }
+
+        let (num_tracked_accounts, num_tracked_owners) = {
+            let tracked = self.tracked.lock().unwrap();
+            (tracked.accounts.len(), tracked.owners.len())
+        };
+        datapoint_info!(
+            "accounts_db_write_telemetry",
+            ("sample_rate", self.sample_rate.get(), i64),
+            ("num_stored", self.num_stored.load(Ordering::Relaxed), i64),
+            ("num_tracked_accounts", num_tracked_accounts, i64),
+            ("num_tracked_owners", num_tracked_owners, i64),
+        );
+
+        let report = self.report(NUM_REPORTED_ENTRIES);
+        for (rank, owner) in report.owners.iter().enumerate() {
+            datapoint_info!(
+                "accounts_db_write_telemetry_owner",
+                "rank" => rank.to_string(),
+                ("owner", owner.owner.to_string(), String),
+                ("writes", owner.writes, i64),
+                ("bytes", owner.bytes, i64),
+                ("rewrites", owner.rewrites, i64),
+            );
+        }
+        for (rank, account) in report.accounts.iter().enumerate() {
+            datapoint_info!(
+                "accounts_db_write_telemetry_account",
+                "rank" => rank.to_string(),
+                ("pubkey", account.pubkey.to_string(), String),
+                ("owner", account.owner.to_string(), String),
+                ("writes", account.writes, i64),
+                ("bytes", account.bytes, i64),
+                ("first_slot", account.first_slot, i64),
+                ("last_slot", account.last_slot, i64),
+            );
+        }
+    }
+}
+
+/// Returns the `limit` entries with the most writes, most written first
+fn most_written<V>(
+    mut entries: Vec<(Pubkey, V)>,
+    limit: usize,
+    writes: impl Fn(&V) -> u64,
+) -> impl Iterator<Item = (Pubkey, V)> {
+    let by_rank = |(pubkey_a, a): &(Pubkey, V), (pubkey_b, b): &(Pubkey, V)| {
+        writes(b).cmp(&writes(a)).then(pubkey_a.cmp(pubkey_b))
+    };
+    if entries.len() > limit && limit > 0 {
+        entries.select_nth_unstable_by(limit - 1, &by_rank);
+    }
+    entries.truncate(limit);
+    entries.sort_unstable_by(&by_rank);
+    entries.into_iter()
+}
+
+/// Keeps only the half of the entries of `map` with the most writes, once it is full
+fn retain_most_written<V>(map: &mut HashMap<Pubkey, V>, writes: impl Fn(&V) -> u64) {
+    if map.len() <= MAX_TRACKED_ENTRIES {
+        return;
+    }
+    let mut ranked: Vec<_> = map
+        .iter()
+        .map(|(pubkey, value)| (writes(value), *pubkey))
+        .collect();
+    let num_retained = ranked.len() / 2;
+    // most writes first, ties broken by pubkey
+    ranked.select_nth_unstable_by(
+        num_retained,
+        |(writes_a, pubkey_a), (writes_b, pubkey_b)| {
+            writes_b.cmp(writes_a).then(pubkey_a.cmp(pubkey_b))
+        },
+    );
+    for (_, pubkey) in &ranked[num_retained..] {
+        map.remove(pubkey);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sampled_write(pubkey: Pubkey, owner: Pubkey, data_len: usize) -> SampledWrite {
+        SampledWrite {
+            pubkey,
+            owner,
+            data_len,
+        }
+    }
+
+    #[test]
+    fn test_is_sampled() {
+        let telemetry = WriteTelemetry::new(NonZeroU64::new(4).unwrap());
+        let first_batch = telemetry.start_batch(6);
+        let second_batch = telemetry.start_batch(6);
+        let sampled: Vec<_> = (0..6)
+            .filter(|index| telemetry.is_sampled(first_batch, *index))
+            .chain((0..6).filter(|index| telemetry.is_sampled(second_batch, *index)))
+            .collect();
+        assert_eq!(sampled, [0, 4, 2]);
+    }
+
+    #[test]
+    fn test_report() {
+        let telemetry = WriteTelemetry::new(NonZeroU64::new(10).unwrap());
+        let owner_a = Pubkey::new_unique();
+        let owner_b = Pubkey::new_unique();
+        let hot = Pubkey::new_unique();
+        let cold = Pubkey::new_unique();
+        telemetry.record(
+            5,
+            &[
+                sampled_write(hot, owner_a, 100),
+                sampled_write(cold, owner_b, 1),
+                sampled_write(hot, owner_a, 100),
+            ],
+        );
+        telemetry.record(7, &[sampled_write(hot, owner_a, 200)]);
+
+        let report = telemetry.report(1);
+        assert_eq!(report.sample_rate, 10);
+        assert_eq!(
+            report.accounts,
+            [HotAccount {
+                pubkey: hot,
+                owner: owner_a,
+                writes: 30,
+                bytes: 4000,
+                first_slot: 5,
+                last_slot: 7,
+            }]
+        );
+        assert_eq!(
+            report.owners,
+            [HotOwner {
+                owner: owner_a,
+                writes: 30,
+                bytes: 4000,
+                rewrites: 20,
+            }]
+        );
+        assert_eq!(telemetry.report(10).accounts.len(), 2);
+    }
+
+    #[test]
+    fn test_retain_most_written() {
+        let mut map: HashMap<_, _> = (0..=MAX_TRACKED_ENTRIES as u64)
+            .map(|writes| (Pubkey::new_unique(), writes))
+            .collect();
+        retain_most_written(&mut map, |writes| *writes);
+        assert_eq!(map.len(), MAX_TRACKED_ENTRIES / 2);
+        assert!(
+            map.values()
+                .all(|writes| *writes > MAX_TRACKED_ENTRIES as u64 / 2)
+        );
+    }
+
+    #[test]
+    fn test_retain_most_written_ties() {
+        // when sampling, most entries are seen written once
+        let mut map: HashMap<_, _> = (0..=MAX_TRACKED_ENTRIES)
+            .map(|i| (Pubkey::new_unique(), u64::from(i == 0) + 1))
+            .collect();
+        let most_written = *map.iter().find(|(_, writes)| **writes == 2).unwrap().0;
+        retain_most_written(&mut map, |writes| *writes);
+        assert_eq!(map.len(), MAX_TRACKED_ENTRIES / 2);
+        assert!(map.contains_key(&most_written));
+    }
+}
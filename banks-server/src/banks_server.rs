@@ -186,6 +186,8 @@ fn simulate_transaction(
         post_balances: _,
         pre_token_balances: _,
         post_token_balances: _,
+        compute_profile: _,
+        account_diffs: _,
     } = bank.simulate_transaction_unchecked(&sanitized_transaction, true);
 
     let simulation_details = TransactionSimulationDetails {
@@ -694,41 +694,18 @@ impl<'b, T: Clone + Copy + PartialEq + std::fmt::Debug + 'static> Bucket<T> {
                 count += 1;
                 // grow relative to the current capacity
                 let new_capacity = (current_capacity * 110 / 100).max(anticipated_size);
-                let (mut index, file_name) = BucketStorage::new_with_capacity(
-                    Arc::clone(&self.drives),
-                    1,
-                    std::mem::size_of::<IndexEntry<T>>() as u64,
-                    Capacity::Actual(new_capacity),
-                    self.index.max_search,
-                    Arc::clone(&self.stats.index),
-                    Arc::clone(&self.index.count),
-                );
-                // index may have allocated something larger than we asked for,
-                // so, in case we fail to reindex into this larger size, grow from this size next iteration.
-                current_capacity = index.capacity();
-                let mut valid = true;
-                for ix in 0..self.index.capacity() {
-                    if !self.index.is_free(ix) {
-                        let elem: &IndexEntry<T> = self.index.get(ix);
-                        let new_ix =
-                            Self::bucket_create_key(&mut index, &elem.key, self.random, true);
-                        if new_ix.is_err() {
-                            valid = false;
-                            break;
-                        }
-                        let new_ix = new_ix.unwrap();
-                        let new_elem: &mut IndexEntry<T> = index.get_mut(new_ix);
-                        *new_elem = *elem;
-                        index.copying_entry(new_ix, &self.index, ix);
+                match self.reindex(new_capacity) {
+                    Ok((index, file_name)) => {
+                        self.stats.index.update_max_size(index.capacity());
+                        let mut items = self.reallocated.items.lock().unwrap();
+                        items.index = Some(index);
+                        self.reallocated.add_reallocation();
+                        self.restartable_bucket.set_file(file_name, self.random);
+                        break;
                     }
-                }
-                if valid {
-                    self.stats.index.update_max_size(index.capacity());
-                    let mut items = self.reallocated.items.lock().unwrap();
-                    items.index = Some(index);
-                    self.reallocated.add_reallocation();
-                    self.restartable_bucket.set_file(file_name, self.random);
-                    break;
+                    // index may have allocated something larger than we asked for,
+                    // so, in case we fail to reindex into this larger size, grow from this size next iteration.
+                    Err(allocated_capacity) => current_capacity = allocated_capacity,
                 }
             }
             m.stop();
@@ -746,11 +723,42 @@ impl<'b, T: Clone + Copy + PartialEq + std::fmt::Debug + 'static> Bucket<T> {
         }
     }
 
-    pub fn apply_grow_index(&mut self, mut index: BucketStorage<IndexBucket<T>>) {
+    /// copy every entry of the index into a new index file with room for at least `capacity` entries.
+    /// Returns the new index and its file name.
+    /// If an entry cannot be placed within max_search of where it hashes to, returns the capacity that was allocated.
+    fn reindex(&self, capacity: u64) -> Result<(BucketStorage<IndexBucket<T>>, u128), u64> {
+        let (mut index, file_name) = BucketStorage::new_with_capacity(
+            Arc::clone(&self.drives),
+            1,
+            std::mem::size_of::<IndexEntry<T>>() as u64,
+            Capacity::Actual(capacity),
+            self.index.max_search,
+            Arc::clone(&self.stats.index),
+            Arc::clone(&self.index.count),
+        );
+        for ix in 0..self.index.capacity() {
+            if !self.index.is_free(ix) {
+                let elem: &IndexEntry<T> = self.index.get(ix);
+                let Ok(new_ix) = Self::bucket_create_key(&mut index, &elem.key, self.random, true)
+                else {
+                    return Err(index.capacity());
+                };
+                let new_elem: &mut IndexEntry<T> = index.get_mut(new_ix);
+                *new_elem = *elem;
+                index.copying_entry(new_ix, &self.index, ix);
+            }
+        }
+        Ok((index, file_name))
+    }
+
+    pub fn apply_grow_index(&mut self, index: BucketStorage<IndexBucket<T>>) {
         self.stats
             .index
             .resize_grow(self.index.capacity_bytes(), index.capacity_bytes());
+        self.set_index(index);
+    }
 
+    fn set_index(&mut self, mut index: BucketStorage<IndexBucket<T>>) {
         if self.restartable_bucket.restart.is_some() {
             // we are keeping track of which files we use for restart.
             // And we are resizing.
@@ -763,6 +771,199 @@ impl<'b, T: Clone + Copy + PartialEq + std::fmt::Debug + 'static> Bucket<T> {
         self.index = index;
     }
 
+    /// Return the number of bytes of the (index, data) files that are allocated to free cells
+    pub(crate) fn free_bytes(&self) -> (u64, u64) {
+        (
+            self.index.free_bytes(),
+            self.data.iter().map(BucketStorage::free_bytes).sum(),
+        )
+    }
+
+    /// Returns true if more than `free_space_threshold` (0.0 - 1.0) of the cells of `storage`
+    /// are free
+    fn exceeds_free_space_threshold<O: BucketOccupied>(
+        storage: &BucketStorage<O>,
+        free_space_threshold: f64,
+    ) -> bool {
+        let capacity = storage.capacity();
+        let free = capacity.saturating_sub(storage.count.load(Ordering::Relaxed));
+        free as f64 > capacity as f64 * free_space_threshold
+    }
+
+    /// The capacity to compact an index holding `count` entries to, with the over-allocation the
+    /// index is grown with
+    fn compacted_index_capacity(count: u64) -> u64 {
+        (count * 140 / 100).max(1 << DEFAULT_CAPACITY_POW2)
+    }
+
+    /// The capacity, as a power of 2, to compact a data bucket holding `count` cells to
+    fn compacted_data_capacity_pow2(count: u64) -> u8 {
+        let capacity = (count * 140 / 100).max(1).next_power_of_two();
+        (capacity.trailing_zeros() as u8).max(DEFAULT_CAPACITY_POW2)
+    }
+
+    /// Returns true if [`Self::compact`] would shrink the index or a data bucket
+    pub(crate) fn should_compact(&self, free_space_threshold: f64) -> bool {
+        let index_should_compact =
+            Self::exceeds_free_space_threshold(&self.index, free_space_threshold)
+                && Self::compacted_index_capacity(self.index.count.load(Ordering::Relaxed))
+                    < self.index.capacity();
+        index_should_compact
+            || self.data.iter().any(|data| {
+                Self::exceeds_free_space_threshold(data, free_space_threshold)
+                    && Self::compacted_data_capacity_pow2(data.count.load(Ordering::Relaxed))
+                        < data.contents.capacity_pow2()
+            })
+    }
+
+    /// Shrink the index and the data buckets of which more than `free_space_threshold`
+    /// (0.0 - 1.0) of the cells are free, such as after many entries were deleted.
+    /// Returns true if the index or any data bucket was compacted.
+    pub fn compact(&mut self, free_space_threshold: f64) -> bool {
+        let mut compacted = self.compact_index(free_space_threshold);
+        for data_ix in 0..self.data.len() {
+            compacted |= self.compact_data(data_ix, free_space_threshold);
+        }
+        compacted
+    }
+
+    /// Shrink the index if more than `free_space_threshold` (0.0 - 1.0) of its cells are free.
+    /// The index is rebuilt into a smaller file with the over-allocation it is grown with.
+    /// Returns true if the index was compacted.
+    fn compact_index(&mut self, free_space_threshold: f64) -> bool {
+        if !Self::exceeds_free_space_threshold(&self.index, free_space_threshold) {
+            return false;
+        }
+        let current_capacity = self.index.capacity();
+        let count = self.index.count.load(Ordering::Relaxed);
+
+        let mut m = Measure::start("compact_index");
+        let mut new_capacity = Self::compacted_index_capacity(count);
+        let mut failed = 0;
+        let compacted = loop {
+            if new_capacity >= current_capacity {
+                // the entries cannot be placed in a smaller index
+                break None;
+            }
+            match self.reindex(new_capacity) {
+                Ok(compacted) => break Some(compacted),
+                Err(allocated_capacity) => {
+                    failed += 1;
+                    new_capacity = allocated_capacity * 110 / 100;
+                }
+            }
+        };
+        m.stop();
+        self.stats
+            .index
+            .failed_resizes
+            .fetch_add(failed, Ordering::Relaxed);
+        self.stats
+            .index
+            .compact_us
+            .fetch_add(m.as_us(), Ordering::Relaxed);
+        let Some((index, file_name)) = compacted else {
+            return false;
+        };
+
+        self.restartable_bucket.set_file(file_name, self.random);
+        self.stats
+            .index
+            .resize_shrink(self.index.capacity_bytes(), index.capacity_bytes());
+        self.stats.index.compactions.fetch_add(1, Ordering::Relaxed);
+        self.set_index(index);
+        true
+    }
+
+    /// Shrink data bucket `data_ix` if more than `free_space_threshold` (0.0 - 1.0) of its cells
+    /// are free.
+    /// The location of a slot list is encoded relative to the capacity of its data bucket, so
+    /// every slot list is copied into a smaller data bucket and the index entry referring to it
+    /// is pointed at its new location. Cells keep their relative position, which spreads them
+    /// over the new data bucket as evenly as over the old one.
+    /// Returns true if the data bucket was compacted.
+    fn compact_data(&mut self, data_ix: usize, free_space_threshold: f64) -> bool {
+        let data = &self.data[data_ix];
+        if !Self::exceeds_free_space_threshold(data, free_space_threshold) {
+            return false;
+        }
+        let current_capacity_pow2 = data.contents.capacity_pow2();
+        let new_capacity_pow2 =
+            Self::compacted_data_capacity_pow2(data.count.load(Ordering::Relaxed));
+        if new_capacity_pow2 >= current_capacity_pow2 {
+            return false;
+        }
+
+        let mut m = Measure::start("compact_data");
+        let (mut new_data, _file_name) = BucketStorage::<DataBucket>::new_with_capacity(
+            Arc::clone(&self.drives),
+            1 << data_ix,
+            Self::elem_size(),
+            Capacity::Pow2(new_capacity_pow2),
+            self.index.max_search,
+            Arc::clone(&self.stats.data),
+            Arc::default(),
+        );
+        let new_capacity = new_data.capacity();
+        let shift = current_capacity_pow2 - new_capacity_pow2;
+        // copy the slot lists first, the index is only updated once all of them fit
+        let mut moved = Vec::new();
+        for ix in 0..self.index.capacity() {
+            if self.index.is_free(ix) {
+                continue;
+            }
+            let elem = IndexEntryPlaceInBucket::new(ix);
+            let OccupiedEnum::MultipleSlots(multiple_slots) = elem.get_slot_count_enum(&self.index)
+            else {
+                continue;
+            };
+            if multiple_slots.data_bucket_ix() as usize != data_ix {
+                continue;
+            }
+            let old_loc = multiple_slots.data_loc(data);
+            let start = old_loc >> shift;
+            let Some(new_loc) = (start..start + new_capacity)
+                .map(|loc| loc % new_capacity)
+                .find(|loc| new_data.is_free(*loc))
+            else {
+                // more cells are in use than counted, leave the data bucket as it is
+                return false;
+            };
+            new_data.occupy(new_loc, false).unwrap();
+            MultipleSlots::set_ref_count(
+                &mut new_data,
+                new_loc,
+                MultipleSlots::ref_count(data, old_loc),
+            );
+            let num_slots = multiple_slots.num_slots();
+            if num_slots > 0 {
+                let slots: &[T] = data.get_slice(old_loc, num_slots, IncludeHeader::NoHeader);
+                new_data
+                    .get_slice_mut(new_loc, num_slots, IncludeHeader::NoHeader)
+                    .copy_from_slice(slots);
+            }
+            moved.push((elem, new_loc));
+        }
+        for (elem, new_loc) in moved {
+            let multiple_slots = elem.get_multiple_slots_mut(&mut self.index).unwrap();
+            multiple_slots.set_storage_offset(new_loc);
+            multiple_slots.set_storage_capacity_when_created_pow2(new_capacity_pow2);
+        }
+        m.stop();
+
+        self.stats
+            .data
+            .compact_us
+            .fetch_add(m.as_us(), Ordering::Relaxed);
+        self.stats.data.resize_shrink(
+            self.data[data_ix].capacity_bytes(),
+            new_data.capacity_bytes(),
+        );
+        self.stats.data.compactions.fetch_add(1, Ordering::Relaxed);
+        self.data[data_ix] = new_data;
+        true
+    }
+
     fn elem_size() -> u64 {
         std::mem::size_of::<T>() as u64
     }
@@ -110,6 +110,38 @@ impl<T: Clone + Copy + PartialEq + std::fmt::Debug> BucketApi<T> {
         bucket
     }
 
+    /// Return the number of bytes of the (index, data) files that are allocated to free cells
+    pub fn free_bytes(&self) -> (u64, u64) {
+        self.bucket
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|bucket| bucket.free_bytes())
+            .unwrap_or_default()
+    }
+
+    /// Shrink the index and the data buckets of which more than `free_space_threshold`
+    /// (0.0 - 1.0) is free.
+    /// The write lock is only taken if there is something to compact.
+    /// Returns true if the index or any data bucket was compacted.
+    pub fn compact(&self, free_space_threshold: f64) -> bool {
+        let should_compact = self
+            .bucket
+            .read()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|bucket| bucket.should_compact(free_space_threshold));
+        if !should_compact {
+            return false;
+        }
+        let mut bucket = self.bucket.write().unwrap();
+        let Some(bucket) = bucket.as_mut() else {
+            return false;
+        };
+        bucket.handle_delayed_grows();
+        bucket.compact(free_space_threshold)
+    }
+
     pub fn insert(&self, pubkey: &Pubkey, value: (&[T], RefCount)) {
         let mut bucket = self.get_write_bucket();
         bucket.as_mut().unwrap().insert(pubkey, value)
@@ -10,7 +10,7 @@ use {
         fmt::Debug,
         fs::{self},
         path::PathBuf,
-        sync::{Arc, Mutex},
+        sync::{Arc, Mutex, atomic::Ordering},
     },
     tempfile::TempDir,
 };
@@ -181,6 +181,41 @@ impl<T: Clone + Copy + Debug + PartialEq> BucketMap<T> {
         self.get_bucket(key).update(key, updatefn)
     }
 
+    /// Shrink the index and the data buckets of every bucket of which more than
+    /// `free_space_threshold` (0.0 - 1.0) of the cells are free, such as after many entries were
+    /// deleted.
+    /// Buckets are compacted one at a time, so the rest of the map stays available.
+    /// Returns the number of buckets that were compacted.
+    pub fn compact(&self, free_space_threshold: f64) -> usize {
+        let compacted = self
+            .buckets
+            .iter()
+            .filter(|bucket| bucket.compact(free_space_threshold))
+            .count();
+        self.update_free_bytes();
+        compacted
+    }
+
+    /// Update the free bytes of the index and data files in `stats`, which are used to report
+    /// fragmentation
+    pub fn update_free_bytes(&self) {
+        let (index_free_bytes, data_free_bytes) =
+            self.buckets
+                .iter()
+                .fold((0, 0), |(index_free_bytes, data_free_bytes), bucket| {
+                    let (index, data) = bucket.free_bytes();
+                    (index_free_bytes + index, data_free_bytes + data)
+                });
+        self.stats
+            .index
+            .free_bytes
+            .store(index_free_bytes, Ordering::Relaxed);
+        self.stats
+            .data
+            .free_bytes
+            .store(data_free_bytes, Ordering::Relaxed);
+    }
+
     pub fn get_bucket(&self, key: &Pubkey) -> &Arc<BucketApi<T>> {
         self.get_bucket_from_index(self.bucket_ix(key))
     }
@@ -360,6 +395,68 @@ mod tests {
         }
     }
 
+    #[test]
+    fn bucket_map_test_compact() {
+        let config = BucketMapConfig::new(1);
+        let index = BucketMap::new(config);
+        let keys: Vec<Pubkey> = (0..10_000).map(|_| Pubkey::new_unique()).collect();
+        let value = |i: usize| {
+            // some slot lists are stored in data buckets
+            let len = if i % 10 == 0 { 3 } else { 1 };
+            (vec![i as u64; len], 1)
+        };
+        for (i, key) in keys.iter().enumerate() {
+            index.insert(key, (&value(i).0, 1));
+        }
+        let (deleted, kept) = keys.split_at(9_500);
+        deleted.iter().for_each(|key| index.delete_key(key));
+
+        index.update_free_bytes();
+        assert!(index.stats.index.fragmentation_percent() > 90);
+        assert!(index.stats.data.fragmentation_percent() > 90);
+        let file_size = index.stats.index.total_file_size.load(Ordering::Relaxed);
+        let data_file_size = index.stats.data.total_file_size.load(Ordering::Relaxed);
+
+        // not fragmented enough
+        assert_eq!(index.compact(0.995), 0);
+        assert_eq!(index.compact(0.5), 1);
+        assert_eq!(index.stats.index.compactions.load(Ordering::Relaxed), 1);
+        let compacted_file_size = index.stats.index.total_file_size.load(Ordering::Relaxed);
+        assert!(compacted_file_size < file_size / 10);
+        assert_eq!(
+            index.stats.index.compacted_bytes.load(Ordering::Relaxed),
+            file_size - compacted_file_size
+        );
+        assert!(index.stats.index.fragmentation_percent() < 50);
+        // the data bucket holding the slot lists of len 3 is compacted too
+        assert_eq!(index.stats.data.compactions.load(Ordering::Relaxed), 1);
+        let compacted_data_file_size = index.stats.data.total_file_size.load(Ordering::Relaxed);
+        assert!(compacted_data_file_size < data_file_size / 4);
+        assert_eq!(
+            index.stats.data.compacted_bytes.load(Ordering::Relaxed),
+            data_file_size - compacted_data_file_size
+        );
+        // already compacted
+        assert_eq!(index.compact(0.5), 0);
+
+        assert_eq!(
+            index.get_bucket_from_index(0).bucket_len(),
+            kept.len() as u64
+        );
+        for (i, key) in keys.iter().enumerate() {
+            let expected = (i >= deleted.len()).then(|| value(i));
+            assert_eq!(index.read_value(key), expected);
+        }
+
+        // the compacted index grows again
+        for (i, key) in deleted.iter().enumerate() {
+            index.insert(key, (&value(i).0, 1));
+        }
+        for (i, key) in keys.iter().enumerate() {
+            assert_eq!(index.read_value(key), Some(value(i)));
+        }
+    }
+
     #[test]
     fn bucket_map_test_n_delete() {
         let config = BucketMapConfig::new(1 << 2);
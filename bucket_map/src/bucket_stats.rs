@@ -20,6 +20,13 @@ pub struct BucketStats {
     pub find_index_entry_mut_us: AtomicU64,
     pub file_count: AtomicU64,
     pub total_file_size: AtomicU64,
+    /// bytes of the files that are allocated to free cells, as of the last call to
+    /// `BucketMap::update_free_bytes`
+    pub free_bytes: AtomicU64,
+    pub compactions: AtomicU64,
+    pub compact_us: AtomicU64,
+    /// bytes of the files given back by compactions
+    pub compacted_bytes: AtomicU64,
     pub startup: StartupBucketStats,
     pub index_uses_uncommon_slot_list_len_or_refcount: AtomicBool,
 }
@@ -34,6 +41,24 @@ impl BucketStats {
         self.total_file_size
             .fetch_add(size_change, Ordering::Relaxed);
     }
+
+    pub fn resize_shrink(&self, old_size: u64, new_size: u64) {
+        let size_change = old_size.saturating_sub(new_size);
+        self.total_file_size
+            .fetch_sub(size_change, Ordering::Relaxed);
+        self.compacted_bytes
+            .fetch_add(size_change, Ordering::Relaxed);
+    }
+
+    /// percentage of the bytes of the files that are allocated to free cells
+    pub fn fragmentation_percent(&self) -> u64 {
+        let total_file_size = self.total_file_size.load(Ordering::Relaxed);
+        if total_file_size == 0 {
+            return 0;
+        }
+        let free_bytes = self.free_bytes.load(Ordering::Relaxed);
+        free_bytes.min(total_file_size) * 100 / total_file_size
+    }
 }
 
 #[derive(Debug, Default)]
@@ -534,6 +534,13 @@ impl<O: BucketOccupied> BucketStorage<O> {
     pub fn capacity(&self) -> u64 {
         self.contents.capacity()
     }
+
+    /// Return the number of bytes allocated to cells that are free
+    pub(crate) fn free_bytes(&self) -> u64 {
+        self.capacity()
+            .saturating_sub(self.count.load(Ordering::Relaxed))
+            * self.cell_size
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,149 @@
+use {
+    crate::{
+        ArgConstant, hidden_unless_forced,
+        input_validators::{is_parsable, is_pubkey},
+    },
+    clap::{Arg, ArgGroup, ArgMatches, values_t},
+    solana_pubkey::Pubkey,
+    solana_signature::Signature,
+    std::path::PathBuf,
+};
+
+pub const CONFORMANCE_FIXTURE_DIR_ARG: ArgConstant<'static> = ArgConstant {
+    name: "conformance_fixture_dir",
+    long: "conformance-fixture-dir",
+    help: "Write the executed transactions selected by --conformance-fixture-program and \
+           --conformance-fixture-signature to DIR as transaction conformance fixtures, along with \
+           the accounts, sysvars and features they were executed against",
+};
+
+pub const CONFORMANCE_FIXTURE_PROGRAM_ARG: ArgConstant<'static> = ArgConstant {
+    name: "conformance_fixture_program",
+    long: "conformance-fixture-program",
+    help: "Capture a fixture of every transaction that invokes this program",
+};
+
+pub const CONFORMANCE_FIXTURE_SIGNATURE_ARG: ArgConstant<'static> = ArgConstant {
+    name: "conformance_fixture_signature",
+    long: "conformance-fixture-signature",
+    help: "Capture a fixture of the transaction with this signature",
+};
+
+/// Name of the group of the arguments that select the captured transactions,
+/// at least one of which is required along with the fixture directory
+pub const CONFORMANCE_FIXTURE_FILTER_GROUP: &str = "conformance_fixture_filter";
+
+/// Where to write conformance fixtures and which transactions to capture
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConformanceFixtureConfig {
+    pub output_dir: PathBuf,
+    pub program_ids: Vec<Pubkey>,
+    pub signatures: Vec<Signature>,
+}
+
+/// Arguments that configure capturing conformance fixtures. They must be
+/// added along with [`conformance_fixture_filter_group`].
+pub fn conformance_fixture_args<'a, 'b>() -> Box<[Arg<'a, 'b>]> {
+    vec![
+        Arg::with_name(CONFORMANCE_FIXTURE_DIR_ARG.name)
+            .long(CONFORMANCE_FIXTURE_DIR_ARG.long)
+            .value_name("DIR")
+            .takes_value(true)
+            .requires(CONFORMANCE_FIXTURE_FILTER_GROUP)
+            .hidden(hidden_unless_forced())
+            .help(CONFORMANCE_FIXTURE_DIR_ARG.help),
+        Arg::with_name(CONFORMANCE_FIXTURE_PROGRAM_ARG.name)
+            .long(CONFORMANCE_FIXTURE_PROGRAM_ARG.long)
+            .validator(is_pubkey)
+            .value_name("ADDRESS")
+            .multiple(true)
+            .takes_value(true)
+            .requires(CONFORMANCE_FIXTURE_DIR_ARG.name)
+            .hidden(hidden_unless_forced())
+            .help(CONFORMANCE_FIXTURE_PROGRAM_ARG.help),
+        Arg::with_name(CONFORMANCE_FIXTURE_SIGNATURE_ARG.name)
+            .long(CONFORMANCE_FIXTURE_SIGNATURE_ARG.long)
+            .validator(is_parsable::<Signature>)
+            .value_name("SIGNATURE")
+            .multiple(true)
+            .takes_value(true)
+            .requires(CONFORMANCE_FIXTURE_DIR_ARG.name)
+            .hidden(hidden_unless_forced())
+            .help(CONFORMANCE_FIXTURE_SIGNATURE_ARG.help),
+    ]
+    .into_boxed_slice()
+}
+
+pub fn conformance_fixture_filter_group<'a>() -> ArgGroup<'a> {
+    ArgGroup::with_name(CONFORMANCE_FIXTURE_FILTER_GROUP)
+        .args(&[
+            CONFORMANCE_FIXTURE_PROGRAM_ARG.name,
+            CONFORMANCE_FIXTURE_SIGNATURE_ARG.name,
+        ])
+        .multiple(true)
+}
+
+/// Returns the fixture capture configuration, if a fixture directory was given
+pub fn parse_conformance_fixture_args(
+    matches: &ArgMatches<'_>,
+) -> Option<ConformanceFixtureConfig> {
+    let output_dir = matches.value_of(CONFORMANCE_FIXTURE_DIR_ARG.name)?;
+    Some(ConformanceFixtureConfig {
+        output_dir: PathBuf::from(output_dir),
+        program_ids: values_t!(matches, CONFORMANCE_FIXTURE_PROGRAM_ARG.name, Pubkey)
+            .unwrap_or_default(),
+        signatures: values_t!(matches, CONFORMANCE_FIXTURE_SIGNATURE_ARG.name, Signature)
+            .unwrap_or_default(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, clap::App};
+
+    fn app<'a, 'b>() -> App<'a, 'b> {
+        App::new("test")
+            .args(&conformance_fixture_args())
+            .group(conformance_fixture_filter_group())
+    }
+
+    #[test]
+    fn test_parse_conformance_fixture_args() {
+        let matches = app().get_matches_from(vec!["test"]);
+        assert_eq!(parse_conformance_fixture_args(&matches), None);
+
+        let program_id = Pubkey::new_unique();
+        let matches = app().get_matches_from(vec![
+            "test",
+            "--conformance-fixture-dir",
+            "fixtures",
+            "--conformance-fixture-program",
+            &program_id.to_string(),
+        ]);
+        assert_eq!(
+            parse_conformance_fixture_args(&matches),
+            Some(ConformanceFixtureConfig {
+                output_dir: PathBuf::from("fixtures"),
+                program_ids: vec![program_id],
+                signatures: vec![],
+            })
+        );
+
+        // A directory without a filter would capture nothing
+        assert!(
+            app()
+                .get_matches_from_safe(vec!["test", "--conformance-fixture-dir", "fixtures"])
+                .is_err()
+        );
+        // A filter without a directory has nowhere to write to
+        assert!(
+            app()
+                .get_matches_from_safe(vec![
+                    "test",
+                    "--conformance-fixture-program",
+                    &program_id.to_string(),
+                ])
+                .is_err()
+        );
+    }
+}
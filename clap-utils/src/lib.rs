@@ -29,6 +29,7 @@ pub fn hidden_unless_forced() -> bool {
 }
 
 pub mod compute_budget;
+pub mod conformance_fixture;
 pub mod fee_payer;
 pub mod input_parsers;
 pub mod input_validators;
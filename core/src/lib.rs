@@ -26,6 +26,7 @@ pub mod forwarding_stage;
 pub mod gen_keys;
 pub mod next_leader;
 pub mod optimistic_confirmation_verifier;
+pub mod program_cache_recorder_service;
 pub mod repair;
 pub mod replay_stage;
 pub mod resource_limits;
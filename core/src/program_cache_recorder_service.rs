@@ -0,0 +1,68 @@
+//! Records the programs compiled into the program cache of the root bank in
+//! the [`JitCache`], so the next startup can compile them before replay.
+//!
+//! The programs are recorded periodically and at exit, so a validator that
+//! did not shut down cleanly still finds the programs it was running recently.
+
+use {
+    solana_runtime::{bank_forks::BankForks, jit_cache::JitCache},
+    std::{
+        sync::{
+            Arc, RwLock,
+            atomic::{AtomicBool, Ordering},
+        },
+        thread::{self, Builder, JoinHandle, sleep},
+        time::{Duration, Instant},
+    },
+};
+
+const RECORD_INTERVAL: Duration = Duration::from_secs(60);
+const SLEEP_INTERVAL: Duration = Duration::from_millis(500);
+
+pub struct ProgramCacheRecorderService {
+    thread_hdl: JoinHandle<()>,
+}
+
+impl ProgramCacheRecorderService {
+    pub fn new(
+        jit_cache: Arc<JitCache>,
+        bank_forks: Arc<RwLock<BankForks>>,
+        exit: Arc<AtomicBool>,
+    ) -> Self {
+        let thread_hdl = Builder::new()
+            .name("solPrgCacheRec".to_string())
+            .spawn(move || {
+                info!("ProgramCacheRecorderService has started");
+                Self::run(&jit_cache, &bank_forks, &exit);
+                info!("ProgramCacheRecorderService has stopped");
+            })
+            .unwrap();
+
+        Self { thread_hdl }
+    }
+
+    fn run(jit_cache: &JitCache, bank_forks: &RwLock<BankForks>, exit: &AtomicBool) {
+        let mut last_record_time = Instant::now();
+        while !exit.load(Ordering::Relaxed) {
+            if last_record_time.elapsed() >= RECORD_INTERVAL {
+                last_record_time = Instant::now();
+                Self::record(jit_cache, bank_forks);
+            }
+            sleep(SLEEP_INTERVAL);
+        }
+        Self::record(jit_cache, bank_forks);
+    }
+
+    fn record(jit_cache: &JitCache, bank_forks: &RwLock<BankForks>) {
+        let root_bank = bank_forks.read().unwrap().root_bank();
+        let num_recorded = root_bank.save_jit_cache(jit_cache);
+        debug!(
+            "Recorded {num_recorded} programs in the JIT cache at {}",
+            jit_cache.dir().display()
+        );
+    }
+
+    pub fn join(self) -> thread::Result<()> {
+        self.thread_hdl.join()
+    }
+}
@@ -13,6 +13,7 @@ pub mod repair_weight;
 pub mod repair_weighted_traversal;
 pub mod request_response;
 pub mod result;
+pub mod rooted_slot_repair_service;
 pub mod serve_repair;
 pub mod serve_repair_service;
 pub(crate) mod standard_repair_handler;
@@ -0,0 +1,625 @@
+//! The `RootedSlotRepairService` re-fetches rooted slots that failed a
+//! blockstore scrub.
+//!
+//! Rooted slots are rejected by the regular shred ingestion path, so this
+//! service sends its own repair requests from a dedicated socket, collects the
+//! data shreds of the slot, checks them against the slot leader and then swaps
+//! them in with [`Blockstore::replace_rooted_slot_shreds`].
+//!
+//! A leader can sign more than one version of a slot, so the repaired shreds
+//! also have to chain their merkle roots from one erasure set to the next, and
+//! the root of the last erasure set has to match what the blockstore already
+//! knows about the rooted block: the `MerkleRootMeta` of the slot or the block
+//! id a rooted child slot chains to. The slot is left untouched if a complete
+//! and consistent set of shreds can not be fetched.
+
+use {
+    crate::repair::{
+        repair_service::{MAX_REPAIR_LENGTH, OutstandingShredRepairs, RepairInfo, RepairStats},
+        serve_repair::{REPAIR_PEERS_CACHE_CAPACITY, RepairPeers, ServeRepair, ShredRepairType},
+        serve_repair_service::RESPONSE_CHANNEL_SIZE,
+        standard_repair_handler::StandardRepairHandler,
+    },
+    crossbeam_channel::{Receiver, RecvTimeoutError, Sender, bounded},
+    lazy_lru::LruCache,
+    solana_clock::Slot,
+    solana_hash::Hash,
+    solana_ledger::{
+        blockstore::Blockstore,
+        blockstore_meta::SlotMeta,
+        leader_schedule_cache::LeaderScheduleCache,
+        shred::{Shred, ShredFetchStats, layout},
+    },
+    solana_perf::{packet::PacketFlags, recycler::Recycler},
+    solana_pubkey::Pubkey,
+    solana_streamer::{
+        sendmmsg::batch_send,
+        streamer::{self, PacketBatchReceiver, StreamerReceiveStats},
+    },
+    solana_time_utils::timestamp,
+    std::{
+        collections::{BTreeMap, HashMap, hash_map::Entry},
+        net::UdpSocket,
+        sync::{
+            Arc,
+            atomic::{AtomicBool, Ordering},
+        },
+        thread::{self, Builder, JoinHandle},
+        time::{Duration, Instant},
+    },
+};
+
+pub type BadRootedSlotsSender = Sender<Slot>;
+pub type BadRootedSlotsReceiver = Receiver<Slot>;
+
+/// Bad slots that have not been picked up yet. The scrubber only reports a
+/// slot once, so slots beyond this are dropped rather than blocking it.
+pub const MAX_PENDING_BAD_ROOTED_SLOTS: usize = 256;
+
+// Give up on a slot if its shreds could not all be fetched within this time
+const ROOTED_SLOT_REPAIR_TIMEOUT: Duration = Duration::from_secs(60);
+// Requests for shreds that are still missing are resent at this interval
+const ROOTED_SLOT_REPAIR_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct RootedSlotRepairService {
+    thread_hdls: Vec<JoinHandle<()>>,
+}
+
+impl RootedSlotRepairService {
+    pub fn new(
+        exit: Arc<AtomicBool>,
+        blockstore: Arc<Blockstore>,
+        leader_schedule_cache: Arc<LeaderScheduleCache>,
+        repair_socket: Arc<UdpSocket>,
+        repair_info: RepairInfo,
+        bad_rooted_slots_receiver: BadRootedSlotsReceiver,
+    ) -> Self {
+        let (response_sender, response_receiver) = bounded(RESPONSE_CHANNEL_SIZE);
+        let t_receiver = streamer::receiver(
+            "solRcvrRootRep".to_string(),
+            repair_socket.clone(),
+            exit.clone(),
+            response_sender,
+            Recycler::default(),
+            Arc::new(StreamerReceiveStats::new(
+                "rooted_slot_repair_response_receiver",
+            )),
+            Some(Duration::from_millis(1)), // coalesce
+            false,                          // use_pinned_memory
+            false,                          // is_staked_service
+        );
+
+        let t_repair = Builder::new()
+            .name("solRootedRepair".to_string())
+            .spawn(move || {
+                info!("RootedSlotRepairService has started");
+                let mut repairer = RootedSlotRepairer::new(
+                    blockstore,
+                    leader_schedule_cache,
+                    repair_socket,
+                    repair_info,
+                    response_receiver,
+                );
+                while !exit.load(Ordering::Relaxed) {
+                    let slot = match bad_rooted_slots_receiver.recv_timeout(Duration::from_secs(1))
+                    {
+                        Ok(slot) => slot,
+                        Err(RecvTimeoutError::Timeout) => continue,
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    };
+                    match repairer.repair_slot(slot, &exit) {
+                        Ok(num_shreds) => {
+                            info!("Restored rooted slot {slot} from {num_shreds} repaired shreds");
+                            datapoint_info!(
+                                "rooted-slot-repair",
+                                ("slot", slot as i64, i64),
+                                ("num_shreds", num_shreds as i64, i64),
+                            );
+                        }
+                        Err(err) => {
+                            warn!("Unable to restore rooted slot {slot} through repair: {err}");
+                            datapoint_error!(
+                                "rooted-slot-repair-failed",
+                                ("slot", slot as i64, i64),
+                                ("error", err, String),
+                            );
+                        }
+                    }
+                }
+                info!("RootedSlotRepairService has stopped");
+            })
+            .unwrap();
+
+        Self {
+            thread_hdls: vec![t_receiver, t_repair],
+        }
+    }
+
+    pub fn join(self) -> thread::Result<()> {
+        for thread_hdl in self.thread_hdls {
+            thread_hdl.join()?;
+        }
+        Ok(())
+    }
+}
+
+struct RootedSlotRepairer {
+    blockstore: Arc<Blockstore>,
+    leader_schedule_cache: Arc<LeaderScheduleCache>,
+    repair_socket: Arc<UdpSocket>,
+    repair_info: RepairInfo,
+    response_receiver: PacketBatchReceiver,
+    serve_repair: ServeRepair,
+    peers_cache: LruCache<Slot, RepairPeers>,
+    outstanding_requests: OutstandingShredRepairs,
+    repair_stats: RepairStats,
+    fetch_stats: ShredFetchStats,
+}
+
+impl RootedSlotRepairer {
+    fn new(
+        blockstore: Arc<Blockstore>,
+        leader_schedule_cache: Arc<LeaderScheduleCache>,
+        repair_socket: Arc<UdpSocket>,
+        repair_info: RepairInfo,
+        response_receiver: PacketBatchReceiver,
+    ) -> Self {
+        let (sharable_banks, migration_status) = {
+            let bank_forks = repair_info.bank_forks.read().unwrap();
+            (bank_forks.sharable_banks(), bank_forks.migration_status())
+        };
+        let serve_repair = ServeRepair::new(
+            repair_info.cluster_info.clone(),
+            sharable_banks,
+            repair_info.repair_whitelist.clone(),
+            Box::new(StandardRepairHandler::new(blockstore.clone())),
+            migration_status,
+        );
+        Self {
+            blockstore,
+            leader_schedule_cache,
+            repair_socket,
+            repair_info,
+            response_receiver,
+            serve_repair,
+            peers_cache: LruCache::new(REPAIR_PEERS_CACHE_CAPACITY),
+            outstanding_requests: OutstandingShredRepairs::default(),
+            repair_stats: RepairStats::default(),
+            fetch_stats: ShredFetchStats::default(),
+        }
+    }
+
+    // Fetches every data shred of rooted `slot` and replaces the shreds in
+    // the blockstore with them. Returns the number of shreds written.
+    fn repair_slot(&mut self, slot: Slot, exit: &AtomicBool) -> Result<usize, String> {
+        if !self.blockstore.is_root(slot) {
+            return Err("slot is not rooted".to_string());
+        }
+        let root_bank = self.repair_info.bank_forks.read().unwrap().root_bank();
+        let leader = self
+            .leader_schedule_cache
+            .slot_leader_at(slot, Some(&root_bank))
+            .ok_or_else(|| "slot leader is unknown".to_string())?;
+        let slot_meta = self
+            .blockstore
+            .meta(slot)
+            .map_err(|err| err.to_string())?
+            .ok_or_else(|| "slot meta is missing".to_string())?;
+        // The last index of the stored slot may itself be corrupt, so it is
+        // only used until a repaired shred flagged as last in slot arrives
+        let mut last_index = slot_meta.last_index;
+        let mut last_index_repaired = false;
+        let mut shreds = BTreeMap::<u64, Shred>::new();
+
+        let deadline = Instant::now() + ROOTED_SLOT_REPAIR_TIMEOUT;
+        loop {
+            if exit.load(Ordering::Relaxed) {
+                return Err("exiting".to_string());
+            }
+            if Instant::now() > deadline {
+                return Err(format!(
+                    "timed out with {} shreds repaired, last index {last_index:?}",
+                    shreds.len()
+                ));
+            }
+            let mut requests = Vec::new();
+            if !last_index_repaired {
+                requests.push(ShredRepairType::HighestShred(slot, 0));
+            }
+            if let Some(last_index) = last_index {
+                requests.extend(
+                    (0..=last_index)
+                        .filter(|index| !shreds.contains_key(index))
+                        .take(MAX_REPAIR_LENGTH)
+                        .map(|index| ShredRepairType::Shred(slot, index)),
+                );
+            }
+            if requests.is_empty() {
+                break;
+            }
+            self.send_requests(requests);
+            self.receive_responses(slot, &leader.id, &mut shreds, exit);
+
+            if let Some(index) = shreds
+                .iter()
+                .find_map(|(index, shred)| shred.last_in_slot().then_some(*index))
+            {
+                last_index = Some(index);
+                last_index_repaired = true;
+            }
+        }
+
+        let shreds: Vec<_> = shreds.into_values().collect();
+        let expected_merkle_roots = self.expected_merkle_roots(&slot_meta, &shreds)?;
+        check_merkle_roots(&shreds, &expected_merkle_roots)?;
+        let num_shreds = shreds.len();
+        self.blockstore
+            .replace_rooted_slot_shreds(slot, shreds)
+            .map_err(|err| err.to_string())?;
+        Ok(num_shreds)
+    }
+
+    fn send_requests(&mut self, requests: Vec<ShredRepairType>) {
+        let batch: Vec<_> = requests
+            .into_iter()
+            .filter_map(|request| {
+                let (to, request) = self
+                    .serve_repair
+                    .repair_request(
+                        &self.repair_info,
+                        request,
+                        &mut self.peers_cache,
+                        &mut self.repair_stats,
+                        &mut self.outstanding_requests,
+                    )
+                    .ok()??;
+                Some((request, to))
+            })
+            .collect();
+        let batch = batch.iter().map(|(bytes, addr)| (bytes, addr));
+        if let Err(err) = batch_send(&self.repair_socket, batch) {
+            warn!("Failed to send rooted slot repair requests: {err:?}");
+        }
+    }
+
+    // Collects the verified shreds of `slot` received until the next retry
+    fn receive_responses(
+        &mut self,
+        slot: Slot,
+        leader: &Pubkey,
+        shreds: &mut BTreeMap<u64, Shred>,
+        exit: &AtomicBool,
+    ) {
+        let keypair = self.repair_info.cluster_info.keypair();
+        let retry_at = Instant::now() + ROOTED_SLOT_REPAIR_RETRY_INTERVAL;
+        while !exit.load(Ordering::Relaxed) {
+            let timeout = retry_at.saturating_duration_since(Instant::now());
+            let Ok(mut packet_batch) = self.response_receiver.recv_timeout(timeout) else {
+                break;
+            };
+            ServeRepair::handle_repair_response_pings(
+                &self.repair_socket,
+                &keypair,
+                &mut packet_batch,
+                &mut self.fetch_stats,
+            );
+            for mut packet in packet_batch.iter_mut() {
+                packet.meta_mut().flags |= PacketFlags::REPAIR;
+            }
+            let now = timestamp();
+            for packet in packet_batch
+                .iter()
+                .filter(|packet| !packet.meta().discard())
+            {
+                let Some((payload, Some(nonce))) = layout::get_shred_and_repair_nonce(packet)
+                else {
+                    continue;
+                };
+                if self
+                    .outstanding_requests
+                    .register_response(nonce, payload, now, |_| ())
+                    .is_none()
+                {
+                    continue;
+                }
+                let Ok(shred) = Shred::new_from_serialized_shred(payload.to_vec()) else {
+                    continue;
+                };
+                if shred.slot() != slot
+                    || !shred.is_data()
+                    || shred.sanitize().is_err()
+                    || !shred.verify(leader)
+                {
+                    continue;
+                }
+                shreds.insert(u64::from(shred.index()), shred);
+            }
+        }
+    }
+
+    // Looks up the merkle roots of the rooted block that the repaired
+    // `shreds` have to reproduce
+    fn expected_merkle_roots(
+        &self,
+        slot_meta: &SlotMeta,
+        shreds: &[Shred],
+    ) -> Result<ExpectedMerkleRoots, String> {
+        let mut erasure_sets = HashMap::new();
+        for fec_set_index in shreds.iter().map(Shred::fec_set_index) {
+            if let Entry::Vacant(entry) = erasure_sets.entry(fec_set_index)
+                && let Some(merkle_root) = self
+                    .blockstore
+                    .get_erasure_set_merkle_root(slot_meta.slot, fec_set_index)
+                    .map_err(|err| err.to_string())?
+            {
+                entry.insert(merkle_root);
+            }
+        }
+        // Only a rooted child is known to chain to the rooted version of the
+        // slot, other children may have been built on an equivocated one
+        let block_id = slot_meta
+            .next_slots
+            .iter()
+            .filter(|child| self.blockstore.is_root(**child))
+            .find_map(|child| self.blockstore.get_parent_chained_block_id(*child).ok());
+        let parent_block_id = slot_meta.parent_slot.and_then(|parent_slot| {
+            self.blockstore
+                .get_last_shred_merkle_root(parent_slot)
+                .ok()
+                .flatten()
+        });
+        Ok(ExpectedMerkleRoots {
+            erasure_sets,
+            block_id,
+            parent_block_id,
+        })
+    }
+}
+
+/// Merkle roots of a rooted block that its repaired shreds have to match.
+#[derive(Debug, Default)]
+struct ExpectedMerkleRoots {
+    /// Roots recorded in the `MerkleRootMeta` of the slot, by fec set index.
+    erasure_sets: HashMap<u32, Hash>,
+    /// Root of the last erasure set, as chained to by a rooted child slot.
+    block_id: Option<Hash>,
+    /// Root of the last erasure set of the parent slot, which the first
+    /// erasure set chains to.
+    parent_block_id: Option<Hash>,
+}
+
+// Checks that `shreds`, sorted by index, form a single chain of erasure sets
+// and that the chain matches the rooted block. Shreds are signed
+// individually, so a leader that equivocated could have produced shreds of
+// the same erasure set with different roots, or a whole other version of the
+// block. The chained roots commit each erasure set to the previous one, so
+// matching the root of the last set pins down the whole block.
+fn check_merkle_roots(shreds: &[Shred], expected: &ExpectedMerkleRoots) -> Result<(), String> {
+    // (fec set index, merkle root, chained merkle root) of each erasure set
+    let mut erasure_sets = Vec::<(u32, Hash, Hash)>::new();
+    for shred in shreds {
+        let fec_set_index = shred.fec_set_index();
+        let merkle_root = shred.merkle_root().map_err(|err| err.to_string())?;
+        match erasure_sets.last() {
+            Some((last_fec_set_index, last_merkle_root, _))
+                if *last_fec_set_index == fec_set_index =>
+            {
+                if *last_merkle_root != merkle_root {
+                    return Err(format!(
+                        "repaired shreds of erasure set {fec_set_index} have different merkle \
+                         roots"
+                    ));
+                }
+            }
+            Some((last_fec_set_index, _, _)) if *last_fec_set_index > fec_set_index => {
+                return Err(format!(
+                    "repaired shreds of erasure set {fec_set_index} are interleaved with erasure \
+                     set {last_fec_set_index}"
+                ));
+            }
+            _ => {
+                let chained_merkle_root =
+                    shred.chained_merkle_root().map_err(|err| err.to_string())?;
+                erasure_sets.push((fec_set_index, merkle_root, chained_merkle_root));
+            }
+        }
+    }
+
+    let Some(&(first_fec_set_index, _, first_chained_merkle_root)) = erasure_sets.first() else {
+        return Err("no shreds were repaired".to_string());
+    };
+    if first_fec_set_index != 0 {
+        return Err(format!(
+            "repaired shreds start at erasure set {first_fec_set_index}"
+        ));
+    }
+    if expected
+        .parent_block_id
+        .is_some_and(|parent_block_id| parent_block_id != first_chained_merkle_root)
+    {
+        return Err("repaired shreds do not chain to the parent slot".to_string());
+    }
+    for window in erasure_sets.windows(2) {
+        let (prev_fec_set_index, prev_merkle_root, _) = window[0];
+        let (fec_set_index, _, chained_merkle_root) = window[1];
+        if chained_merkle_root != prev_merkle_root {
+            return Err(format!(
+                "repaired erasure set {fec_set_index} does not chain to erasure set \
+                 {prev_fec_set_index}"
+            ));
+        }
+    }
+    for &(fec_set_index, merkle_root, _) in &erasure_sets {
+        if expected
+            .erasure_sets
+            .get(&fec_set_index)
+            .is_some_and(|expected| *expected != merkle_root)
+        {
+            return Err(format!(
+                "repaired erasure set {fec_set_index} does not match the stored merkle root"
+            ));
+        }
+    }
+
+    let &(last_fec_set_index, last_merkle_root, _) = erasure_sets.last().unwrap();
+    if let Some(block_id) = expected.block_id {
+        if block_id != last_merkle_root {
+            return Err(
+                "repaired shreds do not match the block id chained to by the child slot"
+                    .to_string(),
+            );
+        }
+    } else if !expected.erasure_sets.contains_key(&last_fec_set_index) {
+        return Err(format!(
+            "no stored merkle root or block id to check repaired erasure set {last_fec_set_index} \
+             against"
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        solana_entry::entry::create_ticks,
+        solana_keypair::Keypair,
+        solana_ledger::shred::{ProcessShredsStats, ReedSolomonCache, Shredder},
+    };
+
+    // Makes the data shreds of a full block spanning several erasure sets
+    fn make_block(slot: Slot, parent_block_id: Hash) -> Vec<Shred> {
+        let entries = create_ticks(2_000, 1, Hash::new_unique());
+        let shreds: Vec<_> = Shredder::new(slot, slot - 1, 0, 0)
+            .unwrap()
+            .make_merkle_shreds_from_entries(
+                &Keypair::new(),
+                &entries,
+                true, // is_last_in_slot
+                parent_block_id,
+                0, // next_shred_index
+                0, // next_code_index
+                &ReedSolomonCache::default(),
+                &mut ProcessShredsStats::default(),
+            )
+            .into_iter()
+            .filter(Shred::is_data)
+            .collect();
+        assert!(shreds.last().unwrap().fec_set_index() > 0);
+        shreds
+    }
+
+    fn stored_merkle_roots(shreds: &[Shred]) -> HashMap<u32, Hash> {
+        shreds
+            .iter()
+            .map(|shred| (shred.fec_set_index(), shred.merkle_root().unwrap()))
+            .collect()
+    }
+
+    fn block_id(shreds: &[Shred]) -> Hash {
+        shreds.last().unwrap().merkle_root().unwrap()
+    }
+
+    #[test]
+    fn test_check_merkle_roots() {
+        let parent_block_id = Hash::new_unique();
+        let shreds = make_block(5, parent_block_id);
+
+        let expected = ExpectedMerkleRoots {
+            erasure_sets: stored_merkle_roots(&shreds),
+            block_id: Some(block_id(&shreds)),
+            parent_block_id: Some(parent_block_id),
+        };
+        assert_eq!(check_merkle_roots(&shreds, &expected), Ok(()));
+
+        let expected = ExpectedMerkleRoots {
+            block_id: Some(block_id(&shreds)),
+            ..ExpectedMerkleRoots::default()
+        };
+        assert_eq!(check_merkle_roots(&shreds, &expected), Ok(()));
+
+        let expected = ExpectedMerkleRoots {
+            erasure_sets: stored_merkle_roots(&shreds),
+            ..ExpectedMerkleRoots::default()
+        };
+        assert_eq!(check_merkle_roots(&shreds, &expected), Ok(()));
+
+        // Nothing to anchor the repaired block to
+        assert!(check_merkle_roots(&shreds, &ExpectedMerkleRoots::default()).is_err());
+    }
+
+    #[test]
+    fn test_check_merkle_roots_other_block() {
+        let parent_block_id = Hash::new_unique();
+        let shreds = make_block(5, parent_block_id);
+        // Consistent on its own, but not the block that was rooted
+        let other_shreds = make_block(5, parent_block_id);
+        assert_eq!(
+            check_merkle_roots(
+                &other_shreds,
+                &ExpectedMerkleRoots {
+                    block_id: Some(block_id(&other_shreds)),
+                    ..ExpectedMerkleRoots::default()
+                }
+            ),
+            Ok(())
+        );
+
+        let expected = ExpectedMerkleRoots {
+            block_id: Some(block_id(&shreds)),
+            ..ExpectedMerkleRoots::default()
+        };
+        assert!(check_merkle_roots(&other_shreds, &expected).is_err());
+
+        let expected = ExpectedMerkleRoots {
+            erasure_sets: stored_merkle_roots(&shreds),
+            ..ExpectedMerkleRoots::default()
+        };
+        assert!(check_merkle_roots(&other_shreds, &expected).is_err());
+
+        // Only the last erasure set is stored
+        let last_fec_set_index = shreds.last().unwrap().fec_set_index();
+        let expected = ExpectedMerkleRoots {
+            erasure_sets: HashMap::from([(last_fec_set_index, block_id(&shreds))]),
+            ..ExpectedMerkleRoots::default()
+        };
+        assert!(check_merkle_roots(&other_shreds, &expected).is_err());
+
+        let other_shreds = make_block(5, Hash::new_unique());
+        let expected = ExpectedMerkleRoots {
+            block_id: Some(block_id(&other_shreds)),
+            parent_block_id: Some(parent_block_id),
+            ..ExpectedMerkleRoots::default()
+        };
+        assert!(check_merkle_roots(&other_shreds, &expected).is_err());
+    }
+
+    #[test]
+    fn test_check_merkle_roots_broken_chain() {
+        let parent_block_id = Hash::new_unique();
+        let shreds = make_block(5, parent_block_id);
+        let other_shreds = make_block(5, parent_block_id);
+        let expected = ExpectedMerkleRoots {
+            block_id: Some(block_id(&other_shreds)),
+            ..ExpectedMerkleRoots::default()
+        };
+
+        // The first erasure set of one block followed by the rest of another
+        let mixed_shreds: Vec<_> = shreds
+            .iter()
+            .filter(|shred| shred.fec_set_index() == 0)
+            .chain(
+                other_shreds
+                    .iter()
+                    .filter(|shred| shred.fec_set_index() != 0),
+            )
+            .cloned()
+            .collect();
+        assert!(check_merkle_roots(&mixed_shreds, &expected).is_err());
+
+        // A single shred of another block within an erasure set
+        let mut mixed_shreds = other_shreds.clone();
+        mixed_shreds[1] = shreds[1].clone();
+        assert!(check_merkle_roots(&mixed_shreds, &expected).is_err());
+    }
+}
@@ -5,8 +5,11 @@ use {
         SnapshotKind, paths as snapshot_paths, snapshot_config::SnapshotConfig,
         snapshot_hash::StartingSnapshotHashes,
     },
+    crossbeam_channel::{Receiver, Sender, unbounded},
     snapshot_gossip_manager::SnapshotGossipManager,
-    solana_accounts_db::account_storage_entry::AccountStorageEntry,
+    solana_accounts_db::{
+        account_storage_entry::AccountStorageEntry, storage_scan_cache::StorageScanCache,
+    },
     solana_clock::Slot,
     solana_gossip::cluster_info::ClusterInfo,
     solana_measure::{meas_dur, measure::Measure, measure_us},
@@ -58,14 +61,20 @@ impl SnapshotPackagerService {
                 renice_this_thread(niceness_adj).unwrap();
                 let mut snapshot_gossip_manager = enable_gossip_push
                     .then(|| SnapshotGossipManager::new(cluster_info, starting_snapshot_hashes));
+                let storage_scan_cache_updater = snapshot_config
+                    .use_storage_scan_cache
+                    .then(|| StorageScanCacheUpdater::new(snapshot_config));
 
                 let mut teardown_state = None;
                 loop {
                     if exit.load(Ordering::Relaxed) {
                         if let Some(teardown_state) = teardown_state {
                             info!("Received exit request, tearing down...");
-                            let (_, dur) =
-                                meas_dur!(Self::teardown(teardown_state, snapshot_config));
+                            let (_, dur) = meas_dur!(Self::teardown(
+                                teardown_state,
+                                snapshot_config,
+                                storage_scan_cache_updater.as_ref(),
+                            ));
                             info!("Teardown completed in {dur:?}.");
                         }
                         break;
@@ -146,6 +155,14 @@ impl SnapshotPackagerService {
                         break;
                     };
 
+                    // Without exit backpressure the bank snapshot was finalized above, so its
+                    // storages are flushed and will not change.
+                    if exit_backpressure.is_none()
+                        && let Some(updater) = &storage_scan_cache_updater
+                    {
+                        updater.update(snapshot_package.snapshot_storages.clone());
+                    }
+
                     // Snapshot archive is unlikely to be read back soon, so allow direct-io now.
                     let io_setup = io_setup.with_direct_io(snapshot_config.use_direct_io);
                     if let SnapshotKind::Archive(snapshot_archive_kind) = snapshot_kind {
@@ -208,6 +225,9 @@ impl SnapshotPackagerService {
                         ("purge_old_archives_time_us", purge_archives_time_us, i64),
                     );
                 }
+                if let Some(updater) = storage_scan_cache_updater {
+                    updater.join();
+                }
                 info!("{} has stopped", Self::NAME);
                 if let Some(exit_backpressure) = &exit_backpressure {
                     exit_backpressure.store(false, Ordering::Relaxed);
@@ -232,7 +252,11 @@ impl SnapshotPackagerService {
     }
 
     /// Performs final operations before gracefully shutting down
-    fn teardown(state: TeardownState, snapshot_config: &SnapshotConfig) {
+    fn teardown(
+        state: TeardownState,
+        snapshot_config: &SnapshotConfig,
+        storage_scan_cache_updater: Option<&StorageScanCacheUpdater>,
+    ) {
         let TeardownState {
             snapshot_slot,
             snapshot_storages,
@@ -337,6 +361,79 @@ impl SnapshotPackagerService {
         if let Err(err) = result {
             warn!("Failed to mark bank snapshot as loadable: {err}");
         }
+
+        // The update is finished before exiting, when the service joins the updater
+        if let Some(updater) = storage_scan_cache_updater {
+            updater.update(snapshot_storages);
+        }
+    }
+}
+
+/// Keeps the storage scan cache up to date with the storages of loadable snapshots, on
+/// a thread of its own so it never delays archiving a snapshot.
+///
+/// Updates only write files for storages the scan cache has not seen yet, so each one is cheap
+/// after the first. If snapshots come in faster than the updates complete, the intermediate ones
+/// are skipped in favor of the latest.
+struct StorageScanCacheUpdater {
+    sender: Sender<Vec<Arc<AccountStorageEntry>>>,
+    thread: JoinHandle<()>,
+}
+
+impl StorageScanCacheUpdater {
+    fn new(snapshot_config: &SnapshotConfig) -> Self {
+        let scan_cache = StorageScanCache::new(snapshot_paths::build_storage_scan_cache_dir(
+            &snapshot_config.bank_snapshots_dir,
+        ));
+        let (sender, receiver) = unbounded();
+        let thread = Builder::new()
+            .name("solScanCacheUpd".to_string())
+            .spawn(move || Self::run(&scan_cache, &receiver))
+            .unwrap();
+        Self { sender, thread }
+    }
+
+    /// Queues an update of the scan cache to `snapshot_storages`
+    fn update(&self, snapshot_storages: Vec<Arc<AccountStorageEntry>>) {
+        // The receiver only hangs up if the update thread panicked, which join() reports
+        _ = self.sender.send(snapshot_storages);
+    }
+
+    /// Finishes the queued updates
+    fn join(self) {
+        drop(self.sender);
+        self.thread.join().expect("storage scan cache updater");
+    }
+
+    fn run(scan_cache: &StorageScanCache, receiver: &Receiver<Vec<Arc<AccountStorageEntry>>>) {
+        while let Ok(snapshot_storages) = receiver.recv() {
+            let snapshot_storages = receiver.try_iter().last().unwrap_or(snapshot_storages);
+            Self::update_scan_cache(scan_cache, &snapshot_storages);
+        }
+    }
+
+    /// Brings the storage scan cache up to date with the storages of a loadable snapshot
+    ///
+    /// The scan cache is only an optimization for booting from local state, so failing to update
+    /// it is not fatal.
+    fn update_scan_cache(
+        scan_cache: &StorageScanCache,
+        snapshot_storages: &[Arc<AccountStorageEntry>],
+    ) {
+        let (result, update_us) = measure_us!(scan_cache.update(snapshot_storages));
+        match result {
+            Ok(stats) => datapoint_info!(
+                "storage_scan_cache",
+                ("update_us", update_us, i64),
+                ("num_written", stats.num_written, i64),
+                ("num_reused", stats.num_reused, i64),
+                ("num_removed", stats.num_removed, i64),
+            ),
+            Err(err) => warn!(
+                "Failed to update storage scan cache '{}': {err}",
+                scan_cache.dir().display(),
+            ),
+        }
     }
 }
 
@@ -19,6 +19,7 @@ use {
         repair::{
             block_id_repair_service::BlockIdRepairChannels,
             repair_service::{OutstandingShredRepairs, RepairInfo, RepairServiceChannels},
+            rooted_slot_repair_service::{BadRootedSlotsReceiver, RootedSlotRepairService},
         },
         replay_stage::{ReplayReceivers, ReplaySenders, ReplayStage, ReplayStageConfig},
         shred_fetch_stage::{SHRED_FETCH_CHANNEL_SIZE, ShredFetchStage},
@@ -65,7 +66,7 @@ use {
         leader_schedule_cache::LeaderScheduleCache,
         shred::filter::TurbineMode,
     },
-    solana_net_utils::PinnedXdpSender,
+    solana_net_utils::{PinnedXdpSender, bind_to_unspecified},
     solana_poh::{poh_controller::PohController, poh_recorder::PohRecorder},
     solana_pubkey::Pubkey,
     solana_rpc::{
@@ -121,6 +122,7 @@ pub struct Tvu {
     shred_sigverify: JoinHandle<()>,
     retransmit_stage: RetransmitStage,
     window_service: WindowService,
+    rooted_slot_repair_service: Option<RootedSlotRepairService>,
     cluster_slots_service: ClusterSlotsService,
     replay_stage: ReplayStage,
     blockstore_cleanup_service: BlockstoreCleanupService,
@@ -161,6 +163,8 @@ pub struct TvuConfig {
     pub bls_sigverify_threads: NonZeroUsize,
     pub turbine_xdp_sender: Option<TurbineXdpSender>,
     pub repair_xdp_sender: Option<PinnedXdpSender>,
+    // Rooted slots that failed a blockstore scrub and should be re-fetched
+    pub bad_rooted_slots_receiver: Option<BadRootedSlotsReceiver>,
 }
 
 impl Default for TvuConfig {
@@ -177,6 +181,7 @@ impl Default for TvuConfig {
             bls_sigverify_threads: NonZeroUsize::new(1).expect("1 is non-zero"),
             turbine_xdp_sender: None,
             repair_xdp_sender: None,
+            bad_rooted_slots_receiver: None,
         }
     }
 }
@@ -455,7 +460,7 @@ impl Tvu {
         // Shared latest switch-bank request from Votor to ReplayStage.
         let latest_switch_request = LatestSwitchRequest::default();
 
-        let window_service = {
+        let (window_service, rooted_slot_repair_service) = {
             let epoch_schedule = bank_forks
                 .read()
                 .unwrap()
@@ -485,7 +490,23 @@ impl Tvu {
                 repair_service_channels,
                 block_id_repair_channels,
             );
-            WindowService::new(
+            let rooted_slot_repair_service = tvu_config
+                .bad_rooted_slots_receiver
+                .map(|bad_rooted_slots_receiver| -> Result<_, String> {
+                    let socket = bind_to_unspecified().map_err(|err| {
+                        format!("failed to bind rooted slot repair socket: {err}")
+                    })?;
+                    Ok(RootedSlotRepairService::new(
+                        exit.clone(),
+                        blockstore.clone(),
+                        leader_schedule_cache.clone(),
+                        Arc::new(socket),
+                        repair_info.clone(),
+                        bad_rooted_slots_receiver,
+                    ))
+                })
+                .transpose()?;
+            let window_service = WindowService::new(
                 blockstore.clone(),
                 repair_socket,
                 ancestor_hashes_socket,
@@ -496,7 +517,8 @@ impl Tvu {
                 tvu_config.shred_version,
                 outstanding_repair_requests,
                 tvu_config.repair_xdp_sender,
-            )
+            );
+            (window_service, rooted_slot_repair_service)
         };
 
         let (cluster_slots_update_sender, cluster_slots_update_receiver) = unbounded();
@@ -672,6 +694,7 @@ impl Tvu {
             shred_sigverify,
             retransmit_stage,
             window_service,
+            rooted_slot_repair_service,
             cluster_slots_service,
             replay_stage,
             blockstore_cleanup_service,
@@ -695,6 +718,9 @@ impl Tvu {
         self.votor_transport_endpoint.stop();
         self.retransmit_stage.join()?;
         self.window_service.join()?;
+        if let Some(rooted_slot_repair_service) = self.rooted_slot_repair_service {
+            rooted_slot_repair_service.join()?;
+        }
         self.cluster_slots_service.join()?;
         self.fetch_stage.join()?;
         self.shred_sigverify.join()?;
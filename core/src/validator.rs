@@ -17,8 +17,11 @@ use {
             verify_blockstore_root_with_vote_history,
         },
         forwarding_stage::ForwardingClientConfig,
+        program_cache_recorder_service::ProgramCacheRecorderService,
         repair::{
-            self, repair_handler::RepairHandlerType, serve_repair_service::ServeRepairService,
+            self, repair_handler::RepairHandlerType,
+            rooted_slot_repair_service::MAX_PENDING_BAD_ROOTED_SLOTS,
+            serve_repair_service::ServeRepairService,
         },
         resource_limits::{ResourceLimitError, adjust_nofile_limit},
         sample_performance_service::SamplePerformanceService,
@@ -47,7 +50,9 @@ use {
     serde::{Deserialize, Serialize},
     solana_account::{ReadableAccount, state_traits::StateMutWincode as _},
     solana_accounts_db::{
-        accounts_db::{ACCOUNTS_DB_CONFIG_FOR_TESTING, AccountsDbConfig},
+        accounts_db::{
+            ACCOUNTS_DB_CONFIG_FOR_TESTING, AccountsDbConfig, IndexGenerationProgressCallback,
+        },
         accounts_update_notifier_interface::AccountsUpdateNotifier,
         utils::validate_account_paths_for_direct_io,
     },
@@ -83,7 +88,7 @@ use {
     solana_ledger::{
         bank_forks_utils,
         blockstore::{
-            Blockstore, BlockstoreError, MAX_COMPLETED_SLOTS_IN_CHANNEL,
+            Blockstore, BlockstoreError, BlockstoreScrubService, MAX_COMPLETED_SLOTS_IN_CHANNEL,
             MAX_REPLAY_WAKE_UP_SIGNALS, MAX_UPDATE_PARENT_SIGNALS, PurgeType, UpdateParentReceiver,
         },
         blockstore_metric_report_service::BlockstoreMetricReportService,
@@ -132,6 +137,7 @@ use {
         bank_forks_controller::BankForksControllerHandle,
         commitment::BlockCommitmentCache,
         dependency_tracker::DependencyTracker,
+        jit_cache::JitCache,
         prioritization_fee_cache::PrioritizationFeeCache,
         runtime_config::RuntimeConfig,
         snapshot_bank_utils,
@@ -339,6 +345,11 @@ pub struct ValidatorConfig {
     pub snapshot_config: SnapshotConfig,
     pub blockstore_cleanup_strategy: BlockstoreCleanupStrategy,
     pub blockstore_options: BlockstoreOptions,
+    /// Run the background scrubber that checks rooted slots for inconsistent
+    /// shreds and shred metadata
+    pub enable_blockstore_scrub: bool,
+    /// Re-fetch rooted slots that fail a scrub through repair
+    pub blockstore_scrub_repair: bool,
     pub broadcast_stage_type: BroadcastStageType,
     pub turbine_mode: TurbineMode,
     pub fixed_leader_schedule: Option<FixedSchedule>,
@@ -365,6 +376,12 @@ pub struct ValidatorConfig {
     pub vote_history_storage: Arc<dyn VoteHistoryStorage>,
     pub debug_keys: Option<Arc<HashSet<Pubkey>>>,
     pub filter_keys: Arc<HashSet<Pubkey>>,
+    /// Programs compiled into the program cache at startup, so the first
+    /// transactions invoking them do not pay for the compilation
+    pub program_cache_prewarm: Vec<Pubkey>,
+    /// Directory recording the compiled programs while running and at exit,
+    /// which are compiled again at startup if they are still valid
+    pub jit_cache_dir: Option<PathBuf>,
     pub contact_debug_interval: u64,
     pub contact_save_interval: u64,
     pub send_transaction_service_config: SendTransactionServiceConfig,
@@ -420,6 +437,8 @@ impl ValidatorConfig {
             voting_disabled: false,
             blockstore_cleanup_strategy: BlockstoreCleanupStrategy::None,
             blockstore_options: BlockstoreOptions::default_for_tests(),
+            enable_blockstore_scrub: false,
+            blockstore_scrub_repair: false,
             account_paths: Vec::new(),
             account_snapshot_paths: Vec::new(),
             rpc_config: JsonRpcConfig::default_for_test(),
@@ -447,6 +466,8 @@ impl ValidatorConfig {
             vote_history_storage: Arc::new(NullVoteHistoryStorage::default()),
             debug_keys: None,
             filter_keys: Arc::default(),
+            program_cache_prewarm: Vec::new(),
+            jit_cache_dir: None,
             contact_debug_interval: DEFAULT_CONTACT_DEBUG_INTERVAL_MILLIS,
             contact_save_interval: DEFAULT_CONTACT_SAVE_INTERVAL_MILLIS,
             send_transaction_service_config: SendTransactionServiceConfig::default(),
@@ -524,6 +545,12 @@ pub enum ValidatorStartProgress {
     CleaningBlockStore,
     CleaningAccounts,
     LoadingLedger,
+    // Generating the accounts index from the snapshot's storages. This calculates the accounts
+    // lt hash the snapshot is verified against.
+    GeneratingAccountsIndex {
+        processed_storages: u64,
+        num_storages: u64,
+    },
     ProcessingLedger {
         slot: Slot,
         max_slot: Slot,
@@ -719,8 +746,10 @@ pub struct Validator {
     /// notifications.
     _contact_info_notifier: Option<GeyserContactInfoNotifier>,
     blockstore_metric_report_service: BlockstoreMetricReportService,
+    blockstore_scrub_service: Option<BlockstoreScrubService>,
     accounts_background_service: AccountsBackgroundService,
     xdp_transmitter: Option<Transmitter>,
+    program_cache_recorder_service: Option<ProgramCacheRecorderService>,
     // This runtime is used to run the client owned by SendTransactionService.
     // We don't wait for its JoinHandle here because ownership and shutdown
     // are managed elsewhere. This variable is intentionally unused.
@@ -989,6 +1018,38 @@ impl Validator {
             let root_bank = bank_forks.read().unwrap().root_bank();
             (root_bank.slot(), root_bank.hard_forks())
         };
+
+        if !config.program_cache_prewarm.is_empty() {
+            let root_bank = bank_forks.read().unwrap().root_bank();
+            let num_prewarmed = root_bank.prewarm_program_cache(&config.program_cache_prewarm);
+            info!(
+                "Prewarmed {num_prewarmed} of {} programs in the program cache",
+                config.program_cache_prewarm.len()
+            );
+        }
+
+        let jit_cache = config.jit_cache_dir.as_ref().and_then(|jit_cache_dir| {
+            JitCache::new(jit_cache_dir.clone())
+                .inspect_err(|err| {
+                    warn!(
+                        "Failed to open the JIT cache at {}: {err}",
+                        jit_cache_dir.display()
+                    )
+                })
+                .ok()
+        });
+        let program_cache_recorder_service = jit_cache.map(|jit_cache| {
+            let root_bank = bank_forks.read().unwrap().root_bank();
+            let mut load_time = Measure::start("load_jit_cache");
+            match root_bank.load_jit_cache(&jit_cache) {
+                Ok(num_compiled) => {
+                    load_time.stop();
+                    info!("Compiled {num_compiled} programs from the JIT cache in {load_time}");
+                }
+                Err(err) => warn!("Failed to load the JIT cache: {err}"),
+            }
+            ProgramCacheRecorderService::new(Arc::new(jit_cache), bank_forks.clone(), exit.clone())
+        });
         let shred_version = compute_shred_version(&genesis_config.hash(), Some(&hard_forks));
         info!("shred version: {shred_version}, hard forks: {hard_forks:?}");
 
@@ -1557,6 +1618,21 @@ impl Validator {
 
         let blockstore_metric_report_service =
             BlockstoreMetricReportService::new(blockstore.clone(), exit.clone());
+        let (bad_rooted_slots_sender, bad_rooted_slots_receiver) =
+            if config.enable_blockstore_scrub && config.blockstore_scrub_repair {
+                let (sender, receiver) = bounded(MAX_PENDING_BAD_ROOTED_SLOTS);
+                (Some(sender), Some(receiver))
+            } else {
+                (None, None)
+            };
+        let blockstore_scrub_service = config.enable_blockstore_scrub.then(|| {
+            BlockstoreScrubService::new(
+                blockstore.clone(),
+                leader_schedule_cache.clone(),
+                bad_rooted_slots_sender,
+                exit.clone(),
+            )
+        });
 
         let wait_for_vote_to_start_leader =
             !waited_for_supermajority && !config.no_wait_for_vote_to_start_leader;
@@ -1712,6 +1788,7 @@ impl Validator {
                 bls_sigverify_threads: config.tvu_bls_sigverify_threads,
                 turbine_xdp_sender: turbine_xdp_sender.clone(),
                 repair_xdp_sender,
+                bad_rooted_slots_receiver,
             },
             &max_slots,
             block_metadata_notifier,
@@ -1885,8 +1962,10 @@ impl Validator {
             geyser_plugin_service,
             _contact_info_notifier: contact_info_notifier,
             blockstore_metric_report_service,
+            blockstore_scrub_service,
             accounts_background_service,
             xdp_transmitter,
+            program_cache_recorder_service,
             _tpu_client_next_runtime: tpu_client_next_runtime,
         })
     }
@@ -2027,6 +2106,12 @@ impl Validator {
                 .expect("sample_performance_service");
         }
 
+        if let Some(program_cache_recorder_service) = self.program_cache_recorder_service {
+            program_cache_recorder_service
+                .join()
+                .expect("program_cache_recorder_service");
+        }
+
         if let Some(entry_notifier_service) = self.entry_notifier_service {
             entry_notifier_service
                 .join()
@@ -2047,6 +2132,11 @@ impl Validator {
         self.blockstore_metric_report_service
             .join()
             .expect("ledger_metric_report_service");
+        if let Some(blockstore_scrub_service) = self.blockstore_scrub_service {
+            blockstore_scrub_service
+                .join()
+                .expect("blockstore_scrub_service");
+        }
         self.accounts_background_service
             .join()
             .expect("accounts_background_service");
@@ -2417,6 +2507,21 @@ fn load_blockstore(
         use_snapshot_archives_at_startup: config.use_snapshot_archives_at_startup,
         ..blockstore_processor::ProcessOptions::default()
     };
+    process_options
+        .accounts_db_config
+        .index_generation_progress_callback = Some(IndexGenerationProgressCallback::new({
+        let start_progress = Arc::clone(start_progress);
+        move |processed_storages, num_storages| {
+            *start_progress.write().unwrap() = if processed_storages < num_storages {
+                ValidatorStartProgress::GeneratingAccountsIndex {
+                    processed_storages,
+                    num_storages,
+                }
+            } else {
+                ValidatorStartProgress::LoadingLedger
+            };
+        }
+    }));
 
     let (blockstore, bank_from_snapshot_opt) = thread::scope(|scope| {
         let load_snapshot_handle = thread::Builder::new()
@@ -2,22 +2,39 @@
 pub use solana_file_download::DownloadProgressRecord;
 use {
     agave_snapshots::{
-        ArchiveFormat, SnapshotArchiveKind, ZstdConfig, paths as snapshot_paths,
+        ArchiveFormat, IndexedConfig, SnapshotArchiveKind, ZstdConfig,
+        indexed_archive::{self, IndexedArchiveReader},
+        paths as snapshot_paths,
         snapshot_hash::SnapshotHash,
     },
+    indicatif::{ProgressBar, ProgressStyle},
     log::*,
+    reqwest::{
+        StatusCode,
+        blocking::{Client, Response},
+        header::{CONTENT_RANGE, RANGE},
+    },
     solana_clock::Slot,
     solana_file_download::{DownloadProgressCallbackOption, download_file},
     solana_genesis_config::DEFAULT_GENESIS_ARCHIVE,
     solana_runtime::snapshot_utils,
     std::{
-        fs,
+        fs::{self, File},
+        io::{self, Read, Seek, SeekFrom},
         net::SocketAddr,
         num::NonZeroUsize,
         path::{Path, PathBuf},
+        time::{Duration, Instant},
     },
 };
 
+/// Snapshot archives are downloaded to a file with this suffix, and only
+/// moved into place once they are complete
+const PARTIAL_DOWNLOAD_SUFFIX: &str = ".partial";
+
+// Same as `download_file`, progress is reported at most this often
+const PROGRESS_NOTIFICATION_INTERVAL: Duration = Duration::from_secs(5);
+
 pub fn download_genesis_if_missing(
     rpc_addr: &SocketAddr,
     genesis_package: &Path,
@@ -43,6 +60,11 @@ pub fn download_genesis_if_missing(
 
 /// Download a snapshot archive from `rpc_addr`.  Use `snapshot_kind` to specify downloading either
 /// a full snapshot or an incremental snapshot.
+///
+/// Interrupted downloads of indexed archives are resumed: the archive is downloaded to a `.partial`
+/// file that is kept if the download fails, and partial downloads of other snapshots of the same
+/// kind are removed. Tar archives can not be verified until they are unpacked, so they are always
+/// downloaded in full.
 pub fn download_snapshot_archive(
     rpc_addr: &SocketAddr,
     full_snapshot_archives_dir: &Path,
@@ -67,12 +89,20 @@ pub fn download_snapshot_archive(
             SnapshotArchiveKind::Incremental(_) => incremental_snapshot_archives_dir,
         });
     fs::create_dir_all(&snapshot_archives_remote_dir).unwrap();
+    purge_stale_partial_downloads(
+        &snapshot_archives_remote_dir,
+        snapshot_kind,
+        desired_snapshot_hash,
+    );
 
     for archive_format in [
         ArchiveFormat::TarZstd {
             config: ZstdConfig::default(),
         },
         ArchiveFormat::TarLz4,
+        ArchiveFormat::Indexed {
+            config: IndexedConfig::default(),
+        },
     ] {
         let destination_path = match snapshot_kind {
             SnapshotArchiveKind::Full => snapshot_paths::build_full_snapshot_archive_path(
@@ -96,16 +126,27 @@ pub fn download_snapshot_archive(
             return Ok(());
         }
 
-        match download_file(
-            &format!(
-                "http://{}/{}",
-                rpc_addr,
-                destination_path.file_name().unwrap().to_str().unwrap()
-            ),
-            &destination_path,
-            use_progress_bar,
-            progress_notify_callback,
-        ) {
+        let url = format!(
+            "http://{}/{}",
+            rpc_addr,
+            destination_path.file_name().unwrap().to_str().unwrap()
+        );
+        let result = if let ArchiveFormat::Indexed { .. } = archive_format {
+            download_indexed_snapshot_archive(
+                &url,
+                &destination_path,
+                use_progress_bar,
+                progress_notify_callback,
+            )
+        } else {
+            download_file(
+                &url,
+                &destination_path,
+                use_progress_bar,
+                progress_notify_callback,
+            )
+        };
+        match result {
             Ok(()) => return Ok(()),
             Err(err) => info!("{err}"),
         }
@@ -115,3 +156,316 @@ pub fn download_snapshot_archive(
         desired_snapshot_hash.0, rpc_addr
     ))
 }
+
+/// Removes the partial downloads in `snapshot_archives_remote_dir` of archives of `snapshot_kind`
+/// other than the one for `desired_snapshot_hash`
+///
+/// Partial downloads are kept so that a failed download can be resumed, but once a different
+/// snapshot is wanted they will never be completed.
+fn purge_stale_partial_downloads(
+    snapshot_archives_remote_dir: &Path,
+    snapshot_kind: SnapshotArchiveKind,
+    desired_snapshot_hash: (Slot, SnapshotHash),
+) {
+    let Ok(dir_entries) = fs::read_dir(snapshot_archives_remote_dir) else {
+        return;
+    };
+    for dir_entry in dir_entries.flatten() {
+        let file_name = dir_entry.file_name();
+        let Some(archive_file_name) = file_name
+            .to_str()
+            .and_then(|file_name| file_name.strip_suffix(PARTIAL_DOWNLOAD_SUFFIX))
+        else {
+            continue;
+        };
+        let is_stale = match snapshot_kind {
+            SnapshotArchiveKind::Full => {
+                snapshot_paths::parse_full_snapshot_archive_filename(archive_file_name)
+                    .is_ok_and(|(slot, hash, _)| (slot, hash) != desired_snapshot_hash)
+            }
+            SnapshotArchiveKind::Incremental(base_slot) => {
+                snapshot_paths::parse_incremental_snapshot_archive_filename(archive_file_name)
+                    .is_ok_and(|(base, slot, hash, _)| {
+                        (base, slot, hash)
+                            != (base_slot, desired_snapshot_hash.0, desired_snapshot_hash.1)
+                    })
+            }
+        };
+        if is_stale {
+            info!(
+                "Removing stale partial download {}",
+                dir_entry.path().display()
+            );
+            if let Err(err) = fs::remove_file(dir_entry.path()) {
+                warn!(
+                    "Failed to remove stale partial download {}: {err}",
+                    dir_entry.path().display()
+                );
+            }
+        }
+    }
+}
+
+fn partial_download_path(destination_path: &Path) -> PathBuf {
+    destination_path.with_file_name(format!(
+        "{}{PARTIAL_DOWNLOAD_SUFFIX}",
+        destination_path.file_name().unwrap().to_str().unwrap()
+    ))
+}
+
+fn new_http_client() -> Result<Client, String> {
+    Client::builder()
+        .timeout(None)
+        .build()
+        .map_err(|err| format!("Failed to create http client: {err}"))
+}
+
+/// Downloads an indexed snapshot archive from `url`, resuming a previous
+/// partial download if there is one
+///
+/// The index of the remote archive is fetched first. Its chunk hashes are used
+/// to find how much of the partial download is intact, and only the rest of
+/// the archive is downloaded. The complete archive is verified against the
+/// index before it is moved to `destination_path`.
+fn download_indexed_snapshot_archive(
+    url: &str,
+    destination_path: &Path,
+    use_progress_bar: bool,
+    progress_notify_callback: &mut DownloadProgressCallbackOption<'_>,
+) -> Result<(), String> {
+    let client = new_http_client()?;
+
+    let footer_response = get_range(&client, url, &format!("-{}", indexed_archive::FOOTER_LEN))?;
+    let archive_len = content_range_total(&footer_response)
+        .ok_or_else(|| format!("Missing or invalid content range in response from {url}"))?;
+    let footer = footer_response
+        .bytes()
+        .map_err(|err| format!("Failed to download {url}: {err}"))?;
+    let footer = footer
+        .as_ref()
+        .try_into()
+        .map_err(|_| format!("Invalid archive footer from {url}"))?;
+    let index_range = indexed_archive::index_range(footer, archive_len)
+        .map_err(|err| format!("Invalid archive from {url}: {err}"))?;
+    let index = get_range(
+        &client,
+        url,
+        &format!(
+            "{}-{}",
+            index_range.start,
+            index_range.end.saturating_sub(1)
+        ),
+    )?
+    .bytes()
+    .map_err(|err| format!("Failed to download {url}: {err}"))?;
+    let entries = indexed_archive::decode_index(&index, index_range.start)
+        .map_err(|err| format!("Invalid archive from {url}: {err}"))?;
+
+    let partial_path = partial_download_path(destination_path);
+    let resume_from = match File::open(&partial_path) {
+        Ok(partial) => indexed_archive::verified_prefix_len(&partial, &entries)
+            .map_err(|err| format!("Failed to verify {}: {err}", partial_path.display()))?,
+        Err(_) => 0,
+    };
+    if resume_from > 0 {
+        info!("Resuming download of {url} at byte {resume_from} of {archive_len}");
+    }
+
+    let response = get_range(&client, url, &format!("{resume_from}-"))?;
+    write_partial_download(
+        url,
+        response,
+        &partial_path,
+        resume_from,
+        Some(archive_len),
+        use_progress_bar,
+        progress_notify_callback,
+    )?;
+
+    // Verify everything, including the chunks downloaded by previous attempts
+    // If this fails, the next attempt resumes before the first corrupt chunk
+    IndexedArchiveReader::open(&partial_path)
+        .and_then(|archive| archive.verify())
+        .map_err(|err| {
+            format!(
+                "Downloaded archive {} is invalid: {err}",
+                partial_path.display()
+            )
+        })?;
+    fs::rename(&partial_path, destination_path).map_err(|err| {
+        format!(
+            "Failed to move {} to {}: {err}",
+            partial_path.display(),
+            destination_path.display()
+        )
+    })?;
+    info!("Downloaded {url} to {}", destination_path.display());
+    Ok(())
+}
+
+/// Writes the body of `response` to `partial_path`, after its first
+/// `resume_from` bytes, reporting progress the same way as `download_file`
+///
+/// Only indexed archives are downloaded here, since their partial downloads
+/// are verified chunk by chunk before they are resumed. `download_file` always
+/// downloads from the start of the file, so it can not be used to resume one.
+fn write_partial_download(
+    url: &str,
+    response: Response,
+    partial_path: &Path,
+    resume_from: u64,
+    archive_len: Option<u64>,
+    use_progress_bar: bool,
+    progress_notify_callback: &mut DownloadProgressCallbackOption<'_>,
+) -> Result<(), String> {
+    let mut partial = File::options()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(partial_path)
+        .map_err(|err| format!("Failed to open {}: {err}", partial_path.display()))?;
+    info!(
+        "Downloading {url}, {} bytes",
+        archive_len.map_or_else(|| "unknown".to_string(), |len| len.to_string())
+    );
+
+    let progress_bar = use_progress_bar.then(|| {
+        let progress_bar = ProgressBar::new(archive_len.unwrap_or(0));
+        progress_bar.set_style(
+            ProgressStyle::default_bar()
+                .template(
+                    "{spinner:.green}{wide_msg} [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})",
+                )
+                .expect("ProgresStyle::template direct input to be correct")
+                .progress_chars("=> "),
+        );
+        progress_bar.set_position(resume_from);
+        progress_bar.set_message(format!("Downloading {url}"));
+        progress_bar
+    });
+    let mut source = DownloadProgress::new(
+        response,
+        resume_from,
+        archive_len,
+        progress_bar,
+        progress_notify_callback,
+    );
+    partial
+        .set_len(resume_from)
+        .and_then(|()| partial.seek(SeekFrom::Start(resume_from)))
+        .and_then(|_| io::copy(&mut source, &mut partial))
+        .and_then(|_| partial.sync_all())
+        .map_err(|err| format!("Failed to download {url}: {err}"))
+}
+
+/// Reports the progress of reading a download through a progress bar, the log
+/// and the caller's progress callback, which may abort the download
+struct DownloadProgress<'a, 'b, R> {
+    response: R,
+    progress_bar: Option<ProgressBar>,
+    callback: &'a mut DownloadProgressCallbackOption<'b>,
+    start_time: Instant,
+    last_notification_time: Instant,
+    last_notification_bytes: u64,
+    notification_count: u64,
+    current_bytes: u64,
+    total_bytes: Option<u64>,
+}
+
+impl<'a, 'b, R> DownloadProgress<'a, 'b, R> {
+    fn new(
+        response: R,
+        resume_from: u64,
+        total_bytes: Option<u64>,
+        progress_bar: Option<ProgressBar>,
+        callback: &'a mut DownloadProgressCallbackOption<'b>,
+    ) -> Self {
+        let now = Instant::now();
+        Self {
+            response,
+            progress_bar,
+            callback,
+            start_time: now,
+            last_notification_time: now,
+            last_notification_bytes: resume_from,
+            notification_count: 0,
+            current_bytes: resume_from,
+            total_bytes,
+        }
+    }
+}
+
+impl<R: Read> Read for DownloadProgress<'_, '_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.response.read(buf)?;
+        self.current_bytes = self.current_bytes.saturating_add(n as u64);
+        if let Some(progress_bar) = &self.progress_bar {
+            progress_bar.inc(n as u64);
+        }
+
+        let last_elapsed_time = self.last_notification_time.elapsed();
+        if last_elapsed_time < PROGRESS_NOTIFICATION_INTERVAL {
+            return Ok(n);
+        }
+        let total_bytes = self.total_bytes.unwrap_or(self.current_bytes) as f32;
+        let last_throughput = self
+            .current_bytes
+            .saturating_sub(self.last_notification_bytes) as f32
+            / last_elapsed_time.as_secs_f32();
+        self.notification_count = self.notification_count.saturating_add(1);
+        let progress_record = DownloadProgressRecord {
+            elapsed_time: self.start_time.elapsed(),
+            last_elapsed_time,
+            last_throughput,
+            total_throughput: self.current_bytes as f32 / self.start_time.elapsed().as_secs_f32(),
+            total_bytes: total_bytes as usize,
+            current_bytes: self.current_bytes as usize,
+            percentage_done: 100_f32 * (self.current_bytes as f32 / total_bytes),
+            estimated_remaining_time: if last_throughput > 0_f32 {
+                (total_bytes - self.current_bytes as f32) / last_throughput
+            } else {
+                f32::MAX
+            },
+            notification_count: self.notification_count,
+        };
+        self.last_notification_time = Instant::now();
+        self.last_notification_bytes = self.current_bytes;
+
+        if self.progress_bar.is_none() {
+            info!(
+                "downloaded {} bytes {:.1}% {:.1} bytes/s",
+                progress_record.current_bytes,
+                progress_record.percentage_done,
+                progress_record.last_throughput,
+            );
+        }
+        if let Some(callback) = self.callback.as_mut()
+            && !callback(&progress_record)
+        {
+            info!("Download is aborted by the caller");
+            return Err(io::Error::other("Download is aborted by the caller"));
+        }
+        Ok(n)
+    }
+}
+
+fn get_range(client: &Client, url: &str, range: &str) -> Result<Response, String> {
+    let response = client
+        .get(url)
+        .header(RANGE, format!("bytes={range}"))
+        .send()
+        .map_err(|err| format!("Failed to download {url}: {err}"))?;
+    if response.status() != StatusCode::PARTIAL_CONTENT {
+        return Err(format!(
+            "Failed to download {url}: range request returned {}",
+            response.status()
+        ));
+    }
+    Ok(response)
+}
+
+// Parses the complete length out of a `Content-Range: bytes <start>-<end>/<len>` header
+fn content_range_total(response: &Response) -> Option<u64> {
+    let content_range = response.headers().get(CONTENT_RANGE)?.to_str().ok()?;
+    content_range.rsplit_once('/')?.1.parse().ok()
+}
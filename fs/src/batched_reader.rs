@@ -0,0 +1,121 @@
+//! Batched positioned reads from files.
+//!
+//! Loading many small records scattered across files (e.g. records found through an index) with
+//! a blocking read each is bound by the latency of every single read. Readers in this module take
+//! the whole batch of reads up front, so that they can be submitted together and served by the
+//! device in parallel.
+#[cfg(target_os = "linux")]
+pub use crate::io_uring::batched_reader::{IoUringBatchedReader, IoUringBatchedReaderBuilder};
+use {
+    crate::{FileSize, IoSize, file_io::read_into_buffer, io_setup::IoSetupState},
+    std::{fs::File, io},
+};
+
+/// A read of `len` bytes at `offset` of `file`
+#[derive(Debug, Clone, Copy)]
+pub struct ReadRequest<'a> {
+    pub file: &'a File,
+    pub offset: FileSize,
+    pub len: IoSize,
+    /// The number of valid bytes in the file, the read is cut short at this length
+    pub valid_file_len: FileSize,
+}
+
+/// Reader executing batches of positioned reads
+pub trait BatchedFileRead {
+    /// Reads all of `requests`, calling `callback` with the index of each request and the bytes
+    /// read for it.
+    ///
+    /// Callbacks are made in no particular order. The bytes are shorter than requested if the
+    /// read reached `valid_file_len` or the end of the file.
+    fn read_batch(
+        &mut self,
+        requests: &[ReadRequest<'_>],
+        callback: &mut dyn FnMut(usize, &[u8]),
+    ) -> io::Result<()>;
+}
+
+/// Reader executing a blocking read for each request, one after another
+#[derive(Debug, Default)]
+pub struct SyncBatchedReader {
+    buf: Vec<u8>,
+}
+
+impl BatchedFileRead for SyncBatchedReader {
+    fn read_batch(
+        &mut self,
+        requests: &[ReadRequest<'_>],
+        callback: &mut dyn FnMut(usize, &[u8]),
+    ) -> io::Result<()> {
+        for (index, request) in requests.iter().enumerate() {
+            self.buf.resize(request.len as usize, 0);
+            let bytes_read = read_into_buffer(
+                request.file,
+                request.valid_file_len,
+                request.offset,
+                &mut self.buf,
+            )?;
+            callback(index, &self.buf[..bytes_read]);
+        }
+        Ok(())
+    }
+}
+
+/// Create a reader of batches of positioned reads using `buf_size` memory for reads in flight
+/// (if `io_uring` is supported by the platform, otherwise reads are blocking)
+///
+/// When `io_setup` enables direct IO, reads are aligned such that they can be used with files
+/// opened with `O_DIRECT`, see [`crate::file_io::open_for_reading`].
+pub fn batched_file_reader(
+    buf_size: usize,
+    io_setup: &IoSetupState,
+) -> io::Result<Box<dyn BatchedFileRead>> {
+    #[cfg(target_os = "linux")]
+    if agave_io_uring::io_uring_supported() {
+        let reader = IoUringBatchedReaderBuilder::new()
+            .use_direct_io(io_setup.use_direct_io)
+            .use_registered_buffers(io_setup.use_registered_io_uring_buffers)
+            .shared_sqpoll(io_setup.shared_sqpoll_fd())
+            .build(buf_size)?;
+        return Ok(Box::new(reader));
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = (buf_size, io_setup);
+    }
+    Ok(Box::new(SyncBatchedReader::default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::io::Write, tempfile::tempfile};
+
+    #[test]
+    fn test_sync_batched_reader() {
+        let mut file = tempfile().unwrap();
+        let bytes: Vec<u8> = (0..64).collect();
+        file.write_all(&bytes).unwrap();
+
+        let request = |offset, len| ReadRequest {
+            file: &file,
+            offset,
+            len,
+            valid_file_len: 48,
+        };
+        let requests = [request(0, 16), request(40, 16), request(48, 8)];
+        let mut results = vec![];
+        SyncBatchedReader::default()
+            .read_batch(&requests, &mut |index, bytes| {
+                results.push((index, bytes.to_vec()))
+            })
+            .unwrap();
+        assert_eq!(
+            results,
+            [
+                (0, bytes[..16].to_vec()),
+                (1, bytes[40..48].to_vec()),
+                (2, vec![]),
+            ]
+        );
+    }
+}
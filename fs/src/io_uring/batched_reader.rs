@@ -0,0 +1,514 @@
+#![allow(clippy::arithmetic_side_effects)]
+
+use {
+    super::{
+        IO_PRIO_BE_HIGHEST,
+        memory::{IoBufferChunk, PageAlignedMemory},
+        sequential_file_reader::DIRECT_IO_READ_LEN_ALIGNMENT,
+        sqpoll,
+    },
+    crate::{
+        FileSize, IoSize,
+        batched_reader::{BatchedFileRead, ReadRequest},
+        file_io::read_into_buffer,
+    },
+    agave_io_uring::{Completion, Ring, RingAccess as _, RingOp},
+    io_uring::{opcode, squeue, types},
+    std::{
+        fs::File,
+        io,
+        ops::Range,
+        os::fd::{AsRawFd, BorrowedFd},
+        slice,
+    },
+};
+
+// Reads are typically of single small records, so the slots only need to fit a few pages.
+// Larger reads are rare and served synchronously.
+const DEFAULT_SLOT_SIZE: IoSize = 64 * 1024;
+// Random reads are mostly served without blocking the submitter, allow a few workers for the
+// ones that have to be punted to a worker (e.g. buffered reads missing the page cache).
+const DEFAULT_MAX_IOWQ_WORKERS: u32 = 4;
+
+/// Utility for building `IoUringBatchedReader` with specified tuning options.
+pub struct IoUringBatchedReaderBuilder<'sp> {
+    slot_size: IoSize,
+    max_iowq_workers: u32,
+    shared_sqpoll_fd: Option<BorrowedFd<'sp>>,
+    /// Register buffer as fixed with the kernel
+    register_buffer: bool,
+    /// Align reads for files opened with the O_DIRECT flag
+    use_direct_io: bool,
+}
+
+impl<'sp> IoUringBatchedReaderBuilder<'sp> {
+    pub fn new() -> Self {
+        Self {
+            slot_size: DEFAULT_SLOT_SIZE,
+            max_iowq_workers: DEFAULT_MAX_IOWQ_WORKERS,
+            shared_sqpoll_fd: None,
+            register_buffer: false,
+            use_direct_io: false,
+        }
+    }
+
+    /// Override the default size of the buffer used by a single read in flight
+    ///
+    /// Reads that don't fit into a slot are executed synchronously.
+    #[cfg(test)]
+    pub fn slot_size(mut self, slot_size: IoSize) -> Self {
+        self.slot_size = slot_size;
+        self
+    }
+
+    /// Set whether to register buffer with `io_uring` for improved performance.
+    ///
+    /// Enabling requires available memlock ulimit to be higher than sizes of registered buffers.
+    pub fn use_registered_buffers(mut self, register_buffers: bool) -> Self {
+        self.register_buffer = register_buffers;
+        self
+    }
+
+    /// Align reads to the requirements of files opened in direct-IO mode.
+    ///
+    /// Aligned reads are also valid for regular files, but read slightly more than requested.
+    pub fn use_direct_io(mut self, use_direct_io: bool) -> Self {
+        self.use_direct_io = use_direct_io;
+        self
+    }
+
+    /// Use (or remove) a shared kernel thread to drain submission queue for IO operations
+    pub fn shared_sqpoll(mut self, shared_sqpoll_fd: Option<BorrowedFd<'sp>>) -> Self {
+        self.shared_sqpoll_fd = shared_sqpoll_fd;
+        self
+    }
+
+    /// Build a new `IoUringBatchedReader` with internally allocated buffer.
+    ///
+    /// Buffer will hold at least `buf_capacity` bytes (increased to `slot_size` if it's lower),
+    /// the number of slots it's divided into is the maximum number of reads in flight.
+    pub fn build(self, buf_capacity: usize) -> io::Result<IoUringBatchedReader> {
+        if self.use_direct_io {
+            assert!(
+                self.slot_size.is_multiple_of(DIRECT_IO_READ_LEN_ALIGNMENT),
+                "slot size is not aligned for direct IO({} is not a multiple of \
+                 {DIRECT_IO_READ_LEN_ALIGNMENT})",
+                self.slot_size
+            );
+        }
+        let buf_capacity = buf_capacity.max(self.slot_size as usize);
+        let mut buffer = PageAlignedMemory::new(buf_capacity)?;
+        let buf_capacity = buffer.len() / self.slot_size as usize * self.slot_size as usize;
+        let buf_slice_mut = &mut buffer.as_mut()[..buf_capacity];
+
+        // Safety: slots contain unsafe pointers to `buffer`, but we make sure they are
+        // dropped before `_backing_buffer` in `IoUringBatchedReader` is dropped.
+        let bufs: Box<[_]> = unsafe {
+            IoBufferChunk::split_buffer_chunks(buf_slice_mut, self.slot_size, self.register_buffer)
+        }
+        .collect();
+        let num_slots = bufs.len();
+        let slots = Slots {
+            free: (0..num_slots).rev().collect(),
+            completed: Vec::with_capacity(num_slots),
+            bufs,
+        };
+
+        // agave io_uring uses cqsize to define state slab size, so cqsize == max inflight ops
+        let io_uring = sqpoll::io_uring_builder_with(self.shared_sqpoll_fd)
+            .setup_cqsize(num_slots as u32)
+            .build(num_slots as u32)?;
+        io_uring
+            .submitter()
+            .register_iowq_max_workers(&mut [self.max_iowq_workers, 1])?;
+        let ring = Ring::new(io_uring, slots);
+
+        if self.register_buffer {
+            // Safety: kernel holds unsafe pointers to `buffer`, struct field declaration order
+            // guarantees that the ring is destroyed before `_backing_buffer` is dropped.
+            unsafe { IoBufferChunk::register(buf_slice_mut, &ring)? };
+        }
+
+        Ok(IoUringBatchedReader {
+            ring,
+            slot_size: self.slot_size,
+            use_direct_io: self.use_direct_io,
+            overflow_buf: Vec::new(),
+            _backing_buffer: buffer,
+        })
+    }
+}
+
+/// Reader of batches of positioned reads.
+///
+/// Reads of a batch are submitted to io_uring together, up to the number of buffer slots at
+/// a time, so that the device can serve them in parallel.
+pub struct IoUringBatchedReader {
+    // Note: ring's state is tied to `_backing_buffer` - contains unsafe pointer references
+    // to the buffer. Ring should be drained and dropped before `_backing_buffer`.
+    ring: Ring<Slots, ReadOp>,
+    slot_size: IoSize,
+    use_direct_io: bool,
+    /// Buffer for reads that don't fit into a slot
+    overflow_buf: Vec<u8>,
+    /// Owned buffer used (chunked into slots) across lifespan of `ring`
+    /// (should get dropped last)
+    _backing_buffer: PageAlignedMemory,
+}
+
+impl IoUringBatchedReader {
+    /// Returns the file offset and length of the read covering `len` bytes at `offset`
+    fn read_range(&self, offset: FileSize, len: IoSize) -> (FileSize, IoSize) {
+        if self.use_direct_io {
+            let alignment = DIRECT_IO_READ_LEN_ALIGNMENT as FileSize;
+            let start = offset / alignment * alignment;
+            let end = (offset + len as FileSize).next_multiple_of(alignment);
+            (start, (end - start) as IoSize)
+        } else {
+            (offset, len)
+        }
+    }
+
+    /// Synchronously reads `len` bytes at `offset` of `file` into the overflow buffer
+    fn read_overflow(
+        &mut self,
+        file: &File,
+        offset: FileSize,
+        len: IoSize,
+        (read_offset, read_len): (FileSize, IoSize),
+    ) -> io::Result<&[u8]> {
+        // Over-allocate so that the read can start at an aligned address for direct IO
+        let alignment = DIRECT_IO_READ_LEN_ALIGNMENT as usize;
+        self.overflow_buf.resize(read_len as usize + alignment, 0);
+        let buf_start = if self.use_direct_io {
+            self.overflow_buf.as_ptr().align_offset(alignment)
+        } else {
+            0
+        };
+        let buf = &mut self.overflow_buf[buf_start..buf_start + read_len as usize];
+        let bytes_read = read_into_buffer(file, offset + len as FileSize, read_offset, buf)?;
+        let requested_start = (offset - read_offset) as usize;
+        Ok(&buf[requested_start.min(bytes_read)..bytes_read])
+    }
+
+    fn do_read_batch(
+        &mut self,
+        requests: &[ReadRequest<'_>],
+        callback: &mut dyn FnMut(usize, &[u8]),
+    ) -> io::Result<()> {
+        let mut next_request_index = 0;
+        let mut num_reading = 0;
+        loop {
+            // Start reads of the following requests into all free slots
+            while let Some(request) = requests.get(next_request_index) {
+                if self.ring.context().free.is_empty() {
+                    break;
+                }
+                let request_index = next_request_index;
+                next_request_index += 1;
+
+                let len = request
+                    .valid_file_len
+                    .saturating_sub(request.offset)
+                    .min(request.len as FileSize) as IoSize;
+                if len == 0 {
+                    callback(request_index, &[]);
+                    continue;
+                }
+                let (read_offset, read_len) = self.read_range(request.offset, len);
+                if read_len > self.slot_size {
+                    let bytes = self.read_overflow(
+                        request.file,
+                        request.offset,
+                        len,
+                        (read_offset, read_len),
+                    )?;
+                    callback(request_index, bytes);
+                    continue;
+                }
+
+                let slots = self.ring.context_mut();
+                let slot_index = slots.free.pop().unwrap();
+                let buf = &slots.bufs[slot_index];
+                // Safety: the slot is free, so its buffer is not used by any other read.
+                let buf_ptr = unsafe { buf.as_mut_ptr() };
+                let io_buf_index = buf.io_buf_index();
+                let requested_start = (request.offset - read_offset) as IoSize;
+                self.ring.push(ReadOp {
+                    fd: types::Fd(request.file.as_raw_fd()),
+                    buf_ptr,
+                    io_buf_index,
+                    slot_index,
+                    request_index,
+                    file_offset: read_offset,
+                    read_len: 0,
+                    total_len: read_len,
+                    requested: requested_start..requested_start + len,
+                })?;
+                num_reading += 1;
+            }
+
+            if num_reading == 0 {
+                break;
+            }
+            // Completions may have already been processed while pushing new reads, only block
+            // if there is nothing to hand out yet.
+            if self.ring.context().completed.is_empty() {
+                self.ring.submit_and_wait(1, None)?;
+            } else {
+                self.ring.submit()?;
+            }
+            self.ring.process_completions()?;
+
+            let slots = self.ring.context_mut();
+            while let Some(CompletedRead {
+                slot_index,
+                request_index,
+                requested,
+            }) = slots.completed.pop()
+            {
+                // Safety: the read into the slot has completed, so the kernel no longer uses its
+                // buffer, and `requested` is within the bytes that were read.
+                let bytes = unsafe {
+                    slice::from_raw_parts(
+                        slots.bufs[slot_index]
+                            .as_ptr()
+                            .add(requested.start as usize),
+                        requested.len(),
+                    )
+                };
+                callback(request_index, bytes);
+                slots.free.push(slot_index);
+                num_reading -= 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Waits for all reads in flight and frees their slots, so the reader can be used again
+    fn reset_after_error(&mut self) {
+        // The first error is the one returned, errors of the remaining reads are irrelevant
+        while self.ring.drain().is_err() {}
+        let slots = self.ring.context_mut();
+        slots.completed.clear();
+        slots.free = (0..slots.bufs.len()).rev().collect();
+    }
+}
+
+impl BatchedFileRead for IoUringBatchedReader {
+    fn read_batch(
+        &mut self,
+        requests: &[ReadRequest<'_>],
+        callback: &mut dyn FnMut(usize, &[u8]),
+    ) -> io::Result<()> {
+        self.do_read_batch(requests, callback)
+            .inspect_err(|_| self.reset_after_error())
+    }
+}
+
+/// Holds the buffer slots that may be submitted to the kernel for reading.
+struct Slots {
+    bufs: Box<[IoBufferChunk]>,
+    /// Indices of slots available for new reads
+    free: Vec<usize>,
+    /// Completed reads that were not yet handed out
+    completed: Vec<CompletedRead>,
+}
+
+#[derive(Debug)]
+struct CompletedRead {
+    slot_index: usize,
+    request_index: usize,
+    /// The range of the slot's buffer holding the bytes that were requested
+    requested: Range<IoSize>,
+}
+
+#[derive(Debug)]
+struct ReadOp {
+    fd: types::Fd,
+    /// Pointer to the buffer of slot `slot_index`
+    buf_ptr: *mut u8,
+    io_buf_index: Option<u16>,
+    slot_index: usize,
+    request_index: usize,
+    /// The offset in the file of the start of the buffer.
+    file_offset: FileSize,
+    /// The number of bytes already read into the buffer. It's typically 0, but can be non-zero if a
+    /// previous read returned less data than requested and we submitted a new read for the
+    /// remaining data.
+    read_len: IoSize,
+    /// The number of bytes to read into the buffer, which covers the `requested` bytes.
+    total_len: IoSize,
+    /// The range of the buffer holding the bytes that were requested.
+    requested: Range<IoSize>,
+}
+
+impl RingOp<Slots> for ReadOp {
+    fn entry(&mut self) -> squeue::Entry {
+        // Safety: reads are only started if the slot's buffer is large enough to hold them.
+        let buf_ptr = unsafe { self.buf_ptr.byte_add(self.read_len as usize) };
+        let len = self.total_len - self.read_len;
+        let offset = self.file_offset + self.read_len as FileSize;
+
+        let entry = match self.io_buf_index {
+            Some(io_buf_index) => opcode::ReadFixed::new(self.fd, buf_ptr, len, io_buf_index)
+                .offset(offset)
+                .ioprio(IO_PRIO_BE_HIGHEST)
+                .build(),
+            None => opcode::Read::new(self.fd, buf_ptr, len)
+                .offset(offset)
+                .ioprio(IO_PRIO_BE_HIGHEST)
+                .build(),
+        };
+        entry.flags(squeue::Flags::ASYNC)
+    }
+
+    fn complete(
+        &mut self,
+        completion: &mut Completion<Slots, Self>,
+        res: io::Result<i32>,
+    ) -> io::Result<()> {
+        let last_read_len = res? as IoSize;
+        let read_len = self.read_len + last_read_len;
+
+        if last_read_len > 0 && read_len < self.total_len {
+            // Partial read, retry the op with updated offsets
+            let op = ReadOp {
+                fd: self.fd,
+                buf_ptr: self.buf_ptr,
+                io_buf_index: self.io_buf_index,
+                slot_index: self.slot_index,
+                request_index: self.request_index,
+                file_offset: self.file_offset,
+                read_len,
+                total_len: self.total_len,
+                requested: self.requested.clone(),
+            };
+            // Safety:
+            // The op points to a buffer which is guaranteed to be valid for the
+            // lifetime of the operation
+            completion.push(op)?;
+        } else {
+            // The read is only short of the requested bytes if it reached the end of file
+            let end = self.requested.end.min(read_len);
+            let start = self.requested.start.min(end);
+            completion.context_mut().completed.push(CompletedRead {
+                slot_index: self.slot_index,
+                request_index: self.request_index,
+                requested: start..end,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*, crate::file_io::open_for_reading, std::io::Write, tempfile::NamedTempFile,
+        test_case::test_case,
+    };
+
+    fn create_test_file(file_size: usize) -> (NamedTempFile, Vec<u8>) {
+        let pattern = (0..file_size).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let mut temp_file = NamedTempFile::new().unwrap();
+        temp_file.write_all(&pattern).unwrap();
+        temp_file.flush().unwrap();
+        (temp_file, pattern)
+    }
+
+    fn read_all(
+        reader: &mut IoUringBatchedReader,
+        requests: &[ReadRequest<'_>],
+    ) -> Vec<Option<Vec<u8>>> {
+        let mut results = vec![None; requests.len()];
+        reader
+            .read_batch(requests, &mut |index, bytes| {
+                assert!(results[index].replace(bytes.to_vec()).is_none());
+            })
+            .unwrap();
+        results
+    }
+
+    #[test_case(false; "buffered")]
+    #[test_case(true; "direct")]
+    fn test_batched_reader(use_direct_io: bool) {
+        let file_size = 1024 * 1024 + 123;
+        let (temp_file, contents) = create_test_file(file_size);
+        // Fall back to a regular file if the filesystem doesn't support direct IO
+        let file = open_for_reading(temp_file.path(), use_direct_io)
+            .or_else(|_| open_for_reading(temp_file.path(), false))
+            .unwrap();
+        let valid_file_len = file_size as FileSize - 100;
+
+        // Few slots, so that reads have to wait for slots to be freed
+        let mut reader = IoUringBatchedReaderBuilder::new()
+            .slot_size(8192)
+            .use_direct_io(use_direct_io)
+            .build(4 * 8192)
+            .unwrap();
+        let requests = [
+            (0, 100),
+            (1, 4096),
+            (4000, 200),
+            (12345, 8192),              // doesn't fit a slot when aligned
+            (500_000, 100_000),         // doesn't fit a slot
+            (valid_file_len - 50, 100), // cut short by valid_file_len
+            (valid_file_len, 10),       // nothing to read
+            (700_001, 3),
+            (64, 0),
+        ]
+        .into_iter()
+        .cycle()
+        .take(50)
+        .map(|(offset, len)| ReadRequest {
+            file: &file,
+            offset,
+            len,
+            valid_file_len,
+        })
+        .collect::<Vec<_>>();
+
+        // The reader is reusable across batches
+        for _ in 0..2 {
+            let results = read_all(&mut reader, &requests);
+            for (request, result) in requests.iter().zip(results) {
+                let start = request.offset as usize;
+                let end = (start + request.len as usize).min(valid_file_len as usize);
+                let expected = contents.get(start..end).unwrap_or_default();
+                assert_eq!(result.unwrap(), expected, "{request:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_batched_reader_error() {
+        let (temp_file, contents) = create_test_file(8192);
+        let file = File::open(temp_file.path()).unwrap();
+        // Reads of a directory fail
+        let dir = File::open(temp_file.path().parent().unwrap()).unwrap();
+        let mut reader = IoUringBatchedReaderBuilder::new()
+            .slot_size(4096)
+            .build(2 * 4096)
+            .unwrap();
+
+        let request = |file| ReadRequest {
+            file,
+            offset: 0,
+            len: 10,
+            valid_file_len: 8192,
+        };
+        let requests = [request(&file), request(&dir), request(&file)];
+        assert!(reader.read_batch(&requests, &mut |_, _| {}).is_err());
+
+        // After an error all slots are available again
+        let requests = vec![request(&file); 5];
+        let results = read_all(&mut reader, &requests);
+        assert!(
+            results
+                .into_iter()
+                .all(|result| result.unwrap() == contents[..10])
+        );
+    }
+}
@@ -1,5 +1,6 @@
 #![cfg(target_os = "linux")]
 
+pub mod batched_reader;
 pub mod dir_remover;
 pub mod file_creator;
 pub mod file_writer;
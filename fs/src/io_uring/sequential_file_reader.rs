@@ -33,7 +33,7 @@ const DEFAULT_READ_SIZE: IoSize = 1024 * 1024;
 const DEFAULT_MAX_IOWQ_WORKERS: u32 = 2;
 // This is conservative read size alignment for use with direct IO, some block devices may have
 // relaxed requirements, but detecting it is not trivial.
-const DIRECT_IO_READ_LEN_ALIGNMENT: IoSize = 4096;
+pub(super) const DIRECT_IO_READ_LEN_ALIGNMENT: IoSize = 4096;
 
 /// Utility for building `SequentialFileReader` with specified tuning options.
 pub struct SequentialFileReaderBuilder<'sp> {
@@ -1,5 +1,6 @@
 #![cfg(feature = "agave-unstable-api")]
 
+pub mod batched_reader;
 pub mod buffered_reader;
 pub mod buffered_writer;
 pub mod dirs;
@@ -4,6 +4,7 @@ use {
     log::*,
     solana_account_decoder::{UiAccountEncoding, UiDataSliceConfig},
     solana_accounts_db::{
+        ReadCacheEvictionPolicy, StorageReadIo,
         accounts_db::{AccountShrinkThreshold, AccountsDbConfig},
         accounts_file::AccountsFileProvider,
         accounts_index::{
@@ -13,6 +14,7 @@ use {
         partitioned_rewards::PartitionedEpochRewardsConfig,
     },
     solana_clap_utils::{
+        conformance_fixture::{ConformanceFixtureConfig, parse_conformance_fixture_args},
         hidden_unless_forced,
         input_parsers::pubkeys_of,
         input_validators::{is_parsable, is_pow2},
@@ -23,10 +25,14 @@ use {
         blockstore_processor::ProcessOptions,
         use_snapshot_archives_at_startup::{self, UseSnapshotArchivesAtStartup},
     },
-    solana_runtime::runtime_config::RuntimeConfig,
+    solana_runtime::{
+        fixture_capture::{FixtureCapture, FixtureCaptureFilter},
+        runtime_config::{RuntimeConfig, TransactionFixtureRecorder},
+    },
     std::{
         collections::HashSet,
         path::{Path, PathBuf},
+        process::exit,
         sync::Arc,
     },
 };
@@ -228,6 +234,7 @@ pub fn parse_process_options(ledger_path: &Path, arg_matches: &ArgMatches<'_>) -
     let log_messages_bytes_limit = value_t!(arg_matches, "log_messages_bytes_limit", usize).ok();
     let runtime_config = RuntimeConfig {
         log_messages_bytes_limit,
+        transaction_fixture_recorder: parse_conformance_fixture_capture(arg_matches),
         ..RuntimeConfig::default()
     };
 
@@ -369,6 +376,8 @@ pub fn get_accounts_db_config(
         read_cache_limit_bytes: None,
         read_cache_evict_sample_size: None,
         read_cache_num_shards: None,
+        read_cache_eviction_policy: ReadCacheEvictionPolicy::default(),
+        read_cache_adaptive_min_bytes: None,
         write_cache_limit_bytes: None,
         ancient_append_vec_offset: value_t!(arg_matches, "accounts_db_ancient_append_vecs", i64)
             .ok(),
@@ -379,13 +388,18 @@ pub fn get_accounts_db_config(
         )
         .ok(),
         max_ancient_storages: value_t!(arg_matches, "accounts_db_max_ancient_storages", usize).ok(),
+        ancient_storage_paths: Vec::new(),
         verify_index: arg_matches.is_present("accounts_db_verify_index"),
         skip_initial_hash_calc: arg_matches.is_present("accounts_db_skip_initial_hash_calculation"),
         partitioned_epoch_rewards_config: PartitionedEpochRewardsConfig::default(),
         scan_filter_for_shrinking,
         num_background_threads: None,
         num_foreground_threads: None,
+        num_index_generation_threads: None,
+        index_generation_progress_callback: None,
         accounts_file_provider: AccountsFileProvider::AppendVec,
+        storage_read_io: StorageReadIo::default(),
+        write_telemetry_sample_rate: None,
     }
 }
 
@@ -420,6 +434,28 @@ pub(crate) fn parse_account_output_config(matches: &ArgMatches<'_>) -> CliAccoun
     }
 }
 
+fn parse_conformance_fixture_capture(
+    arg_matches: &ArgMatches<'_>,
+) -> Option<Arc<dyn TransactionFixtureRecorder>> {
+    let ConformanceFixtureConfig {
+        output_dir,
+        program_ids,
+        signatures,
+    } = parse_conformance_fixture_args(arg_matches)?;
+    let filter = FixtureCaptureFilter {
+        program_ids: program_ids.into_iter().collect(),
+        signatures: signatures.into_iter().collect(),
+    };
+    let fixture_capture = FixtureCapture::new(output_dir.clone(), filter).unwrap_or_else(|err| {
+        eprintln!(
+            "Unable to create conformance fixture directory {}: {err}",
+            output_dir.display()
+        );
+        exit(1);
+    });
+    Some(Arc::new(fixture_capture))
+}
+
 // This function is duplicated in validator/src/main.rs...
 pub fn hardforks_of(matches: &ArgMatches<'_>, name: &str) -> Option<Vec<Slot>> {
     if matches.is_present(name) {
@@ -2,9 +2,13 @@
 
 use {
     crate::{
+        args::{accounts_db_args, load_genesis_arg, parse_process_options, snapshot_args},
         error::{LedgerToolError, Result},
         ledger_path::canonicalize_ledger_path,
-        ledger_utils::get_program_ids,
+        ledger_utils::{
+            LoadAndProcessLedgerOutput, get_access_type, get_program_ids,
+            load_and_process_ledger_or_exit, open_genesis_config_by,
+        },
         output::{CliDuplicateSlotProof, SlotBounds, SlotInfo, output_ledger, output_slot},
     },
     chrono::{DateTime, Utc},
@@ -25,6 +29,7 @@ use {
             column::{Column, ColumnName},
         },
         blockstore_options::AccessType,
+        leader_schedule_cache::LeaderScheduleCache,
         shred::Shred,
     },
     std::{
@@ -33,7 +38,7 @@ use {
         fs::File,
         io::{BufRead, BufReader},
         path::{Path, PathBuf},
-        sync::atomic::AtomicBool,
+        sync::{Arc, atomic::AtomicBool},
         time::{Duration, UNIX_EPOCH},
     },
 };
@@ -508,6 +513,35 @@ pub fn blockstore_subcommands<'a, 'b>(hidden: bool) -> Vec<App<'a, 'b>> {
                     .required(true)
                     .help("Override the maximum number of slots to check for root repair"),
             ),
+        SubCommand::with_name("scrub")
+            .about(
+                "Check that the shreds of rooted slots are intact and consistent with the slot \
+                 meta, index, erasure meta and merkle root meta columns",
+            )
+            .settings(&hidden)
+            .arg(&starting_slot_arg)
+            .arg(&ending_slot_arg)
+            .arg(load_genesis_arg())
+            .args(&accounts_db_args())
+            .args(&snapshot_args())
+            .arg(
+                Arg::with_name("print_all_slots")
+                    .long("print-all-slots")
+                    .takes_value(false)
+                    .help("Print a line for every scrubbed slot, not just inconsistent ones"),
+            )
+            .arg(
+                Arg::with_name("skip_signature_verification")
+                    .long("skip-signature-verification")
+                    .takes_value(false)
+                    .help("Skip verifying shred signatures against the slot leaders")
+                    .long_help(
+                        "Skip verifying shred signatures against the slot leaders. Without this \
+                         flag, the snapshot bank is loaded to derive the leader schedule from, \
+                         and shreds of slots in epochs whose stakes the bank no longer holds are \
+                         scrubbed without verifying their signatures.",
+                    ),
+            ),
         SubCommand::with_name("set-dead-slot")
             .about("Mark one or more slots dead")
             .settings(&hidden)
@@ -904,6 +938,87 @@ fn do_blockstore_process_command(ledger_path: &Path, matches: &ArgMatches<'_>) -
             )?;
             println!("Successfully repaired {num_repaired_roots} roots");
         }
+        ("scrub", Some(arg_matches)) => {
+            let starting_slot = value_t_or_exit!(arg_matches, "starting_slot", Slot);
+            let ending_slot = value_t!(arg_matches, "ending_slot", Slot).unwrap_or(Slot::MAX);
+            let print_all_slots = arg_matches.is_present("print_all_slots");
+
+            // Shred signatures are checked against the leader schedule of the
+            // snapshot bank, which only knows the leaders of the epochs it
+            // holds stakes for
+            let (blockstore, leader_schedule) = if arg_matches
+                .is_present("skip_signature_verification")
+            {
+                let blockstore =
+                    crate::open_blockstore(&ledger_path, arg_matches, AccessType::ReadOnly);
+                (Arc::new(blockstore), None)
+            } else {
+                let mut process_options = parse_process_options(&ledger_path, arg_matches);
+                process_options.halt_at_slot = Some(0);
+                let genesis_config = open_genesis_config_by(&ledger_path, arg_matches);
+                let blockstore = Arc::new(crate::open_blockstore(
+                    &ledger_path,
+                    arg_matches,
+                    get_access_type(&process_options),
+                ));
+                let LoadAndProcessLedgerOutput { bank_forks, .. } = load_and_process_ledger_or_exit(
+                    arg_matches,
+                    &genesis_config,
+                    blockstore.clone(),
+                    process_options,
+                    None,
+                );
+                let root_bank = bank_forks.read().unwrap().root_bank();
+                let leader_schedule_cache = LeaderScheduleCache::new_from_bank(&root_bank);
+                (blockstore, Some((leader_schedule_cache, root_bank)))
+            };
+
+            let stats = blockstore.scrub_rooted_slots(
+                starting_slot,
+                ending_slot,
+                |slot| {
+                    leader_schedule
+                        .as_ref()
+                        .and_then(|(leader_schedule_cache, bank)| {
+                            leader_schedule_cache.slot_leader_at(slot, Some(bank))
+                        })
+                },
+                |report| {
+                    if report.is_ok() {
+                        if print_all_slots {
+                            println!(
+                                "Slot {}: ok, {} data shreds, {} coding shreds, {} erasure sets",
+                                report.slot,
+                                report.num_data_shreds,
+                                report.num_coding_shreds,
+                                report.num_erasure_sets,
+                            );
+                        }
+                        return;
+                    }
+                    println!("Slot {}: {} errors", report.slot, report.errors.len());
+                    for error in &report.errors {
+                        println!("  {error}");
+                    }
+                },
+                &AtomicBool::new(false),
+            )?;
+            println!(
+                "Scrubbed {} rooted slots: {} data shreds, {} coding shreds, {} erasure sets",
+                stats.num_slots,
+                stats.num_data_shreds,
+                stats.num_coding_shreds,
+                stats.num_erasure_sets,
+            );
+            if !stats.bad_slots.is_empty() {
+                return Err(LedgerToolError::Generic(format!(
+                    "found {} errors in {} slots: {:?}",
+                    stats.num_errors,
+                    stats.bad_slots.len(),
+                    stats.bad_slots,
+                )));
+            }
+        }
         ("set-dead-slot", Some(arg_matches)) => {
             let slots = values_t_or_exit!(arg_matches, "slots", Slot);
             let blockstore = crate::open_blockstore(
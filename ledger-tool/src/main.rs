@@ -11,6 +11,7 @@ use {
             SlotBankHash,
         },
         program::*,
+        snapshot::*,
     },
     agave_feature_set::{self as feature_set, FeatureSet},
     agave_reserved_account_keys::ReservedAccountKeys,
@@ -20,7 +21,7 @@ use {
     },
     clap::{
         App, AppSettings, Arg, ArgMatches, SubCommand, crate_description, crate_name, value_t,
-        value_t_or_exit, values_t_or_exit,
+        value_t_or_exit, values_t, values_t_or_exit,
     },
     dashmap::DashMap,
     log::*,
@@ -29,6 +30,7 @@ use {
         AccountSharedData, ReadableAccount, WritableAccount, state_traits::StateMutWincode as _,
     },
     solana_clap_utils::{
+        conformance_fixture::{conformance_fixture_args, conformance_fixture_filter_group},
         input_parsers::{cluster_type_of, pubkey_of, pubkeys_of},
         input_validators::{
             is_parsable, is_pubkey, is_pubkey_or_keypair, is_slot, is_valid_percentage,
@@ -70,7 +72,7 @@ use {
         inflation_rewards::points::{InflationPointCalculationEvent, PointValue},
         installed_scheduler_pool::BankWithScheduler,
         snapshot_bank_utils,
-        snapshot_minimizer::SnapshotMinimizer,
+        snapshot_minimizer::{AccountFilter, AccountSelection, SnapshotMinimizer},
         stake_utils,
         transaction_execution::{TransactionStatusMessage, TransactionStatusSender},
     },
@@ -90,7 +92,7 @@ use {
         borrow::Cow,
         collections::{HashMap, HashSet},
         ffi::{OsStr, OsString},
-        fs::{File, read_dir},
+        fs::{self, File, read_dir},
         io::{self, Write},
         mem::swap,
         path::{Path, PathBuf},
@@ -112,6 +114,7 @@ mod ledger_path;
 mod ledger_utils;
 mod output;
 mod program;
+mod snapshot;
 
 fn render_dot(dot: String, output_file: &str, output_format: &str) -> io::Result<()> {
     let mut child = Command::new("dot")
@@ -517,6 +520,7 @@ fn minimize_bank_for_snapshot(
     bank: &Bank,
     snapshot_slot: Slot,
     ending_slot: Slot,
+    selection: &AccountSelection,
     should_recalculate_accounts_lt_hash: bool,
 ) -> bool {
     let ((transaction_account_set, possibly_incomplete), transaction_accounts_measure) = measure_time!(
@@ -526,15 +530,121 @@ fn minimize_bank_for_snapshot(
     let total_accounts_len = transaction_account_set.len();
     info!("Added {total_accounts_len} accounts from transactions. {transaction_accounts_measure}");
 
-    SnapshotMinimizer::minimize(
+    SnapshotMinimizer::minimize_with_selection(
         bank,
         snapshot_slot,
         transaction_account_set,
+        selection,
         should_recalculate_accounts_lt_hash,
     );
     possibly_incomplete
 }
 
+/// Builds the accounts to keep in a minimized snapshot, besides those needed for
+/// replay, from the --minimized-keep-* options of create-snapshot
+fn minimized_account_selection_of(arg_matches: &ArgMatches<'_>) -> AccountSelection {
+    let mut pubkeys = HashSet::new();
+    for path in arg_matches
+        .values_of("minimized_keep_accounts_file")
+        .into_iter()
+        .flatten()
+    {
+        let contents = fs::read_to_string(path).unwrap_or_else(|err| {
+            eprintln!("Unable to read accounts file {path}: {err}");
+            exit(1);
+        });
+        // One pubkey per line; blank lines and lines starting with '#' are ignored
+        for line in contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        {
+            let pubkey = Pubkey::from_str(line).unwrap_or_else(|err| {
+                eprintln!("Invalid pubkey '{line}' in accounts file {path}: {err}");
+                exit(1);
+            });
+            pubkeys.insert(pubkey);
+        }
+    }
+
+    AccountSelection {
+        pubkeys,
+        owners: pubkeys_of(arg_matches, "minimized_keep_owner")
+            .unwrap_or_default()
+            .into_iter()
+            .collect(),
+        filters: values_t!(arg_matches, "minimized_keep_filter", AccountFilter).unwrap_or_default(),
+    }
+}
+
+/// Arguments of create-snapshot that select what a minimized snapshot keeps
+fn minimized_snapshot_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::with_name("minimized")
+            .long("minimized")
+            .takes_value(false)
+            .help(
+                "Create a minimized snapshot instead of a full snapshot. This snapshot will only \
+                 include information needed to replay the ledger from the snapshot slot to the \
+                 ending slot.",
+            )
+            .conflicts_with("incremental")
+            .requires("ending_slot"),
+        Arg::with_name("ending_slot")
+            .long("ending-slot")
+            .takes_value(true)
+            .value_name("ENDING_SLOT")
+            .help("Ending slot for minimized snapshot creation"),
+        Arg::with_name("recalculate_accounts_lt_hash")
+            .long("recalculate-accounts-lt-hash")
+            .takes_value(false)
+            .help("Recalculate the accounts lt hash for minimized snapshots")
+            .long_help(
+                "Recalculate the accounts lt hash for minimized snapshots. Without this flag, \
+                 loading the minimized snapshot will fail startup accounts verification because \
+                 the accounts lt hash will not match due to the pruned account state. If not \
+                 recalculating the accounts lt hash, pass \
+                 `--accounts-db-skip-initial-hash-calculation` to `leder-tool verify` in order to \
+                 bypass this check.",
+            )
+            .requires("minimized"),
+        Arg::with_name("minimized_keep_owner")
+            .long("minimized-keep-owner")
+            .takes_value(true)
+            .value_name("PROGRAM_ID")
+            .validator(is_pubkey)
+            .multiple(true)
+            .requires("minimized")
+            .help("Keep all accounts owned by this program in the minimized snapshot"),
+        Arg::with_name("minimized_keep_accounts_file")
+            .long("minimized-keep-accounts-file")
+            .takes_value(true)
+            .value_name("PATH")
+            .multiple(true)
+            .requires("minimized")
+            .help(
+                "Keep the accounts listed in this file, one pubkey per line, in the minimized \
+                 snapshot",
+            ),
+        Arg::with_name("minimized_keep_filter")
+            .long("minimized-keep-filter")
+            .takes_value(true)
+            .value_name("EXPRESSION")
+            .validator(|value| value.parse::<AccountFilter>().map(|_| ()))
+            .multiple(true)
+            .requires("minimized")
+            .help("Keep all accounts matching this expression in the minimized snapshot")
+            .long_help(
+                "Keep all accounts matching this expression in the minimized snapshot. An \
+                 expression is one or more terms joined by `&&`: `owner == PUBKEY`, `owner != \
+                 PUBKEY`, `lamports OP N`, `data_len OP N`, `executable == BOOL` and \
+                 `memcmp(OFFSET, BASE58_BYTES)`, where OP is one of ==, !=, <, <=, >, >=. \
+                 Accounts referenced by kept accounts (owners, programdata, lookup table \
+                 addresses and token mints) are kept as well.",
+            ),
+    ]
+}
+
 fn assert_capitalization(bank: &Bank) {
     let calculated = bank.calculate_capitalization_for_tests();
     let expected = bank.capitalization();
@@ -1143,6 +1253,8 @@ fn main() {
                 .arg(&debug_key_arg)
                 .arg(&geyser_plugin_args)
                 .arg(&log_messages_bytes_limit_arg)
+                .args(&conformance_fixture_args())
+                .group(conformance_fixture_filter_group())
                 .arg(
                     Arg::with_name("skip_poh_verify")
                         .long("skip-poh-verify")
@@ -1468,40 +1580,7 @@ fn main() {
                         )
                         .conflicts_with("no_snapshot"),
                 )
-                .arg(
-                    Arg::with_name("minimized")
-                        .long("minimized")
-                        .takes_value(false)
-                        .help(
-                            "Create a minimized snapshot instead of a full snapshot. This \
-                             snapshot will only include information needed to replay the ledger \
-                             from the snapshot slot to the ending slot.",
-                        )
-                        .conflicts_with("incremental")
-                        .requires("ending_slot"),
-                )
-                .arg(
-                    Arg::with_name("ending_slot")
-                        .long("ending-slot")
-                        .takes_value(true)
-                        .value_name("ENDING_SLOT")
-                        .help("Ending slot for minimized snapshot creation"),
-                )
-                .arg(
-                    Arg::with_name("recalculate_accounts_lt_hash")
-                        .long("recalculate-accounts-lt-hash")
-                        .takes_value(false)
-                        .help("Recalculate the accounts lt hash for minimized snapshots")
-                        .long_help(
-                            "Recalculate the accounts lt hash for minimized snapshots. Without \
-                             this flag, loading the minimized snapshot will fail startup accounts \
-                             verification because the accounts lt hash will not match due to the \
-                             pruned account state. If not recalculating the accounts lt hash, \
-                             pass `--accounts-db-skip-initial-hash-calculation` to `leder-tool \
-                             verify` in order to bypass this check.",
-                        )
-                        .requires("minimized"),
-                )
+                .args(&minimized_snapshot_args())
                 .arg(
                     Arg::with_name("snapshot_archive_format")
                         .long("snapshot-archive-format")
@@ -1526,6 +1605,18 @@ fn main() {
                              information.",
                         ),
                 )
+                .arg(
+                    Arg::with_name("snapshot_archive_uncompressed_storages")
+                        .long("snapshot-archive-uncompressed-storages")
+                        .takes_value(false)
+                        .help("Store account storages uncompressed in indexed snapshot archives")
+                        .long_help(
+                            "Store account storages uncompressed in indexed snapshot archives. \
+                             The archives are larger, but their storages are copied out of a \
+                             mapping of the archive instead of being decompressed when the \
+                             snapshot is unpacked. Only applies to the indexed archive format.",
+                        ),
+                )
                 .arg(
                     Arg::with_name("enable_capitalization_change")
                         .long("enable-capitalization-change")
@@ -1702,6 +1793,7 @@ fn main() {
                 .arg(&allow_dead_slots_arg),
         )
         .program_subcommand()
+        .snapshot_subcommand()
         .get_matches();
 
     let logfile = value_t!(matches, "logfile", PathBuf).ok();
@@ -1728,6 +1820,7 @@ fn main() {
         ("bigtable", Some(arg_matches)) => bigtable_process_command(&ledger_path, arg_matches),
         ("blockstore", Some(arg_matches)) => blockstore_process_command(&ledger_path, arg_matches),
         ("program", Some(arg_matches)) => program(&ledger_path, arg_matches),
+        ("snapshot", Some(arg_matches)) => snapshot(&ledger_path, arg_matches),
         // This match case provides legacy support for commands that were previously top level
         // subcommands of the binary, but have been moved under the blockstore subcommand.
         ("analyze-storage", Some(_))
@@ -2082,12 +2175,18 @@ fn main() {
                             .unwrap_or_else(|| {
                                 panic!("Archive format not recognized: {archive_format_str}")
                             });
-                        if let ArchiveFormat::TarZstd { config } = &mut archive_format {
-                            config.compression_level = value_t_or_exit!(
-                                arg_matches,
-                                "snapshot_zstd_compression_level",
-                                i32
-                            );
+                        let compression_level =
+                            value_t_or_exit!(arg_matches, "snapshot_zstd_compression_level", i32);
+                        match &mut archive_format {
+                            ArchiveFormat::TarZstd { config } => {
+                                config.compression_level = compression_level;
+                            }
+                            ArchiveFormat::Indexed { config } => {
+                                config.compression_level = compression_level;
+                                config.compress_storages = !arg_matches
+                                    .is_present("snapshot_archive_uncompressed_storages");
+                            }
+                            ArchiveFormat::TarLz4 => {}
                         }
                         archive_format
                     };
@@ -2548,6 +2647,7 @@ fn main() {
                             &bank,
                             snapshot_slot,
                             ending_slot.unwrap(),
+                            &minimized_account_selection_of(arg_matches),
                             arg_matches.is_present("recalculate_accounts_lt_hash"),
                         )
                     } else {
@@ -3311,3 +3411,60 @@ fn main() {
     measure_total_execution_time.stop();
     info!("{measure_total_execution_time}");
 }
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::iter};
+
+    fn minimized_matches<'a>(args: &[&str]) -> ArgMatches<'a> {
+        App::new("test")
+            .arg(Arg::with_name("incremental").long("incremental"))
+            .args(&minimized_snapshot_args())
+            .get_matches_from(iter::once("test").chain(args.iter().copied()))
+    }
+
+    #[test]
+    fn test_minimized_account_selection_without_filters() {
+        let selection = minimized_account_selection_of(&minimized_matches(&[
+            "--minimized",
+            "--ending-slot",
+            "1",
+        ]));
+        assert!(selection.pubkeys.is_empty());
+        assert!(selection.owners.is_empty());
+        assert!(selection.filters.is_empty());
+    }
+
+    #[test]
+    fn test_minimized_account_selection_with_filters() {
+        let owner = Pubkey::new_unique();
+        let filter = format!("owner == {owner}");
+        let selection = minimized_account_selection_of(&minimized_matches(&[
+            "--minimized",
+            "--ending-slot",
+            "1",
+            "--minimized-keep-owner",
+            &owner.to_string(),
+            "--minimized-keep-filter",
+            &filter,
+        ]));
+        assert_eq!(selection.owners, HashSet::from([owner]));
+        assert_eq!(
+            selection.filters,
+            vec![filter.parse::<AccountFilter>().unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_minimized_keep_options_require_minimized() {
+        let result = App::new("test")
+            .arg(Arg::with_name("incremental").long("incremental"))
+            .args(&minimized_snapshot_args())
+            .get_matches_from_safe([
+                "test",
+                "--minimized-keep-owner",
+                "11111111111111111111111111111111",
+            ]);
+        assert!(result.is_err());
+    }
+}
@@ -5,14 +5,18 @@ use {
     serde::{Deserialize, Serialize},
     serde_json::Result,
     solana_account::{AccountSharedData, WritableAccount},
+    solana_clap_utils::{input_parsers::pubkeys_of, input_validators::is_pubkey},
     solana_cli_output::{OutputFormat, QuietDisplay, VerboseDisplay},
     solana_clock::Slot,
     solana_ledger::blockstore_options::AccessType,
     solana_loader_v3_interface::state::UpgradeableLoaderState,
     solana_program_runtime::{
+        compute_profile::ComputeProfile,
         create_vm,
+        debugger::{DebuggerConfig, debug_metadata},
+        flamegraph::{Flamegraph, Symbolizer},
         invoke_context::{BpfAllocator, InvokeContext},
-        loaded_programs::ProgramRuntimeEnvironment,
+        loaded_programs::{ProgramCacheEntrySummary, ProgramRuntimeEnvironment},
         memory_context::MemoryContext,
         program_cache_entry::{
             DELAY_VISIBILITY_SLOT_OFFSET, ProgramCacheEntry, ProgramCacheEntryType,
@@ -38,7 +42,8 @@ use {
         IndexOfAccount, instruction::InstructionContext, instruction_accounts::InstructionAccount,
     },
     std::{
-        collections::HashMap,
+        cell::RefCell,
+        collections::{HashMap, HashSet},
         fmt::{self, Debug, Formatter},
         fs::File,
         io::{Read, Seek, Write},
@@ -197,6 +202,16 @@ and the following fields are required
                         .value_name("PORT")
                         .default_value("9001"),
                 )
+                .arg(
+                    Arg::with_name("debug_cpi")
+                        .help(
+                            "In 'debugger' mode, also host a debugger session for every program \
+                             invoked through CPI. Each session waits for its own connection on \
+                             the debugger port.",
+                        )
+                        .long("debug-cpi")
+                        .takes_value(false),
+                )
                 .arg(
                     Arg::with_name("trace")
                         .help("Output instruction trace")
@@ -205,8 +220,57 @@ and the following fields are required
                         .takes_value(true)
                         .value_name("FILE"),
                 )
+                .arg(
+                    Arg::with_name("trace_format")
+                        .help(
+                            "Format of the instruction trace, where 'disassembly' writes one file \
+                             per instruction with the executed instructions and registers, and \
+                             'flamegraph' writes the call stacks of all instructions in the folded \
+                             format of flamegraph tools and reports the instructions executed per \
+                             function.",
+                        )
+                        .long("trace-format")
+                        .takes_value(true)
+                        .value_name("FORMAT")
+                        .possible_values(&["disassembly", "flamegraph"])
+                        .default_value("disassembly")
+                        .requires("trace"),
+                )
+                .arg(
+                    Arg::with_name("compute_profile")
+                        .help("Output the compute units consumed per instruction and per syscall")
+                        .long("compute-profile")
+                        .takes_value(false),
+                )
                 .arg(&program_arg)
         )
+        .subcommand(
+            SubCommand::with_name("cache")
+                .about(
+                    "Display the program cache after replaying the ledger, with the deployment \
+                     slot, tier, usage and compilation cost of every cached program",
+                )
+                .arg(&load_genesis_config_arg)
+                .args(&snapshot_config_args)
+                .arg(
+                    Arg::with_name("prewarm")
+                        .help("Compile this program into the program cache before displaying it")
+                        .long("prewarm")
+                        .takes_value(true)
+                        .value_name("ADDRESS")
+                        .multiple(true)
+                        .validator(is_pubkey),
+                )
+                .arg(
+                    Arg::with_name("pin")
+                        .help("Pin this program in the program cache before displaying it")
+                        .long("pin")
+                        .takes_value(true)
+                        .value_name("ADDRESS")
+                        .multiple(true)
+                        .validator(is_pubkey),
+                )
+        )
         )
     }
 }
@@ -217,6 +281,67 @@ struct Output {
     instruction_count: u64,
     execution_time: Duration,
     log: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    compute_profile: Option<Vec<InstructionComputeProfileOutput>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    function_instructions: Option<Vec<FunctionInstructionsOutput>>,
+}
+
+#[derive(Serialize)]
+struct InstructionComputeProfileOutput {
+    index_in_trace: usize,
+    stack_height: usize,
+    program_id: String,
+    compute_units_consumed: u64,
+    syscalls: Vec<SyscallComputeProfileOutput>,
+}
+
+#[derive(Serialize)]
+struct SyscallComputeProfileOutput {
+    name: String,
+    count: u64,
+    compute_units: u64,
+}
+
+fn compute_profile_output(profile: ComputeProfile) -> Vec<InstructionComputeProfileOutput> {
+    profile
+        .instructions
+        .into_iter()
+        .map(|instruction| InstructionComputeProfileOutput {
+            index_in_trace: instruction.index_in_trace,
+            stack_height: instruction.stack_height,
+            program_id: instruction.program_id.to_string(),
+            compute_units_consumed: instruction.compute_units_consumed,
+            syscalls: instruction
+                .syscalls
+                .into_iter()
+                .map(|syscall| SyscallComputeProfileOutput {
+                    name: syscall.name,
+                    count: syscall.count,
+                    compute_units: syscall.compute_units,
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+#[derive(Serialize)]
+struct FunctionInstructionsOutput {
+    name: String,
+    exclusive: u64,
+    inclusive: u64,
+}
+
+fn function_instructions_output(flamegraph: &Flamegraph) -> Vec<FunctionInstructionsOutput> {
+    flamegraph
+        .function_instructions()
+        .into_iter()
+        .map(|function| FunctionInstructionsOutput {
+            name: function.name,
+            exclusive: function.exclusive,
+            inclusive: function.inclusive,
+        })
+        .collect()
 }
 
 impl fmt::Display for Output {
@@ -228,6 +353,36 @@ impl fmt::Display for Output {
         for line in &self.log {
             writeln!(f, "{line}")?;
         }
+        if let Some(compute_profile) = &self.compute_profile {
+            writeln!(f, "Compute profile:")?;
+            for instruction in compute_profile {
+                let indent = "  ".repeat(instruction.stack_height);
+                writeln!(
+                    f,
+                    "{indent}Instruction {} Program {}: {} CUs",
+                    instruction.index_in_trace,
+                    instruction.program_id,
+                    instruction.compute_units_consumed,
+                )?;
+                for syscall in &instruction.syscalls {
+                    writeln!(
+                        f,
+                        "{indent}  {} x{}: {} CUs",
+                        syscall.name, syscall.count, syscall.compute_units,
+                    )?;
+                }
+            }
+        }
+        if let Some(function_instructions) = &self.function_instructions {
+            writeln!(f, "Function instructions (exclusive / inclusive):")?;
+            for function in function_instructions {
+                writeln!(
+                    f,
+                    "  {}: {} / {}",
+                    function.name, function.exclusive, function.inclusive,
+                )?;
+            }
+        }
         Ok(())
     }
 }
@@ -235,6 +390,42 @@ impl fmt::Display for Output {
 impl QuietDisplay for Output {}
 impl VerboseDisplay for Output {}
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ProgramCacheOutput {
+    slot: Slot,
+    entries: Vec<ProgramCacheEntrySummary>,
+}
+
+impl fmt::Display for ProgramCacheOutput {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Program cache at slot {}:", self.slot)?;
+        ProgramCacheEntrySummary::write_table(f, &self.entries)
+    }
+}
+
+impl QuietDisplay for ProgramCacheOutput {}
+impl VerboseDisplay for ProgramCacheOutput {}
+
+fn process_cache_action(ledger_path: &Path, matches: &ArgMatches<'_>) {
+    let ledger_path = canonicalize_ledger_path(ledger_path);
+    let bank = load_blockstore(&ledger_path, matches);
+    let prewarm = pubkeys_of(matches, "prewarm").unwrap_or_default();
+    if !prewarm.is_empty() {
+        let num_prewarmed = bank.prewarm_program_cache(&prewarm);
+        info!("Prewarmed {num_prewarmed} of {} programs", prewarm.len());
+    }
+    for program_id in pubkeys_of(matches, "pin").unwrap_or_default() {
+        bank.pin_cached_program(program_id);
+    }
+    let output = ProgramCacheOutput {
+        slot: bank.slot(),
+        entries: bank.program_cache_entries(),
+    };
+    let output_format = OutputFormat::from_matches(matches, "output_format", false);
+    println!("{}", output_format.formatted_string(&output));
+}
+
 // Replace with std::lazy::Lazy when stabilized.
 // https://github.com/rust-lang/rust/issues/74465
 struct LazyAnalysis<'a, 'b> {
@@ -325,6 +516,55 @@ fn load_program<'a>(
     }
 }
 
+/// Folds the register traces of all instructions into the call stacks of a
+/// flamegraph, with one root frame per program. Functions of the program run
+/// from `program_path` are named from its debug info when it has any.
+fn write_flamegraph(
+    invoke_context: &InvokeContext,
+    program_id: Pubkey,
+    program_path: &Path,
+    trace_option: &str,
+) -> Flamegraph {
+    let symbolizers = RefCell::new(HashMap::<Pubkey, Symbolizer>::new());
+    let flamegraph = RefCell::new(Flamegraph::default());
+    invoke_context.iterate_vm_traces(&|instruction_context: InstructionContext,
+                                       executable,
+                                       register_trace| {
+        let traced_program_id = instruction_context
+            .get_program_key()
+            .copied()
+            .unwrap_or_default();
+        let mut symbolizers = symbolizers.borrow_mut();
+        let symbolizer = symbolizers.entry(traced_program_id).or_insert_with(|| {
+            let mut symbolizer = Symbolizer::from_executable(executable);
+            if traced_program_id == program_id
+                && let Err(err) = symbolizer.load_debug_info(program_path)
+            {
+                warn!(
+                    "Could not load debug info of {}, using the symbol table only: {err}",
+                    program_path.display(),
+                );
+            }
+            symbolizer
+        });
+        flamegraph.borrow_mut().add_register_trace(
+            &traced_program_id.to_string(),
+            executable.get_text_bytes().1,
+            symbolizer,
+            register_trace,
+        );
+    });
+    let flamegraph = flamegraph.into_inner();
+    if trace_option == "stdout" {
+        flamegraph.write_folded(std::io::stdout().lock()).unwrap();
+    } else {
+        flamegraph
+            .write_folded(File::create(trace_option).unwrap())
+            .unwrap();
+    }
+    flamegraph
+}
+
 enum Action {
     Cfg,
     Dis,
@@ -362,6 +602,10 @@ pub fn program(ledger_path: &Path, matches: &ArgMatches<'_>) {
             process_static_action(Action::Dis, arg_matches);
             return;
         }
+        ("cache", Some(arg_matches)) => {
+            process_cache_action(ledger_path, arg_matches);
+            return;
+        }
         ("run", Some(arg_matches)) => arg_matches,
         _ => unreachable!(),
     };
@@ -500,18 +744,43 @@ pub fn program(ledger_path: &Path, matches: &ArgMatches<'_>) {
             instruction_data,
         )
         .unwrap();
+    if matches.is_present("compute_profile") {
+        invoke_context.enable_compute_profile_recording();
+    }
+    let mode = matches.value_of("mode").unwrap();
+    if mode == "debugger" {
+        invoke_context.debugger_config = Some(DebuggerConfig {
+            port: matches.value_of("port").unwrap().parse::<u16>().unwrap(),
+            break_on_cpi: matches.is_present("debug_cpi"),
+            program_ids: HashSet::new(),
+        });
+    }
     invoke_context.push().unwrap();
+    let instruction_context = invoke_context
+        .transaction_context
+        .get_current_instruction_context()
+        .unwrap();
     let (_parameter_bytes, regions, account_lengths, instruction_data_offset) =
         serialize_parameters(
-            &invoke_context
-                .transaction_context
-                .get_current_instruction_context()
-                .unwrap(),
+            &instruction_context,
             false, // virtual_address_space_adjustments
             false, // account_data_direct_mapping
             false, // direct_account_pointers_in_program_input
         )
         .unwrap();
+    let debugger = invoke_context
+        .debugger_config
+        .as_ref()
+        .map(|debugger_config| {
+            let metadata = debug_metadata(
+                invoke_context.transaction_context,
+                &instruction_context,
+                &account_lengths,
+                instruction_data_offset,
+            )
+            .unwrap();
+            (debugger_config.port, metadata)
+        });
 
     let regions = [
         MemoryRegion::new_empty(MM_RODATA_START),
@@ -556,15 +825,15 @@ pub fn program(ledger_path: &Path, matches: &ArgMatches<'_>) {
     };
     let start_time = Instant::now();
 
-    let mode = matches.value_of("mode").unwrap();
     let mut execution_mode = if mode == "jit" {
         ExecutionMode::Jit
-    } else if mode == "debugger" {
-        vm.debug_port = Some(matches.value_of("port").unwrap().parse::<u16>().unwrap());
-        ExecutionMode::Interpreted
     } else {
         ExecutionMode::Interpreted
     };
+    if let Some((port, metadata)) = debugger {
+        vm.debug_port = Some(port);
+        vm.debug_metadata = Some(metadata);
+    }
     let mut call_frames = match execution_mode {
         ExecutionMode::Jit => vec![],
         ExecutionMode::Interpreted | ExecutionMode::PreferJit => {
@@ -577,47 +846,56 @@ pub fn program(ledger_path: &Path, matches: &ArgMatches<'_>) {
     let (instruction_count, result) =
         vm.execute_program(&verified_executable, &mut execution_mode, &mut call_frames);
     let duration = Instant::now() - start_time;
+    let mut function_instructions = None;
     if let Some(trace_option) = matches.value_of("trace") {
-        vm.context()
-            .iterate_vm_traces(&|instruction_context: InstructionContext,
-                                 executable,
-                                 register_trace| {
-                let mut analysis = LazyAnalysis::new(executable);
-                if trace_option == "stdout" {
-                    writeln!(
-                        &mut std::io::stdout(),
-                        "TX Instruction {} Program {:?}",
-                        instruction_context.get_index_in_trace(),
-                        instruction_context.get_program_key(),
-                    )
-                    .unwrap();
-                    analysis
-                        .analyze()
-                        .disassemble_register_trace(&mut std::io::stdout(), register_trace)
+        if matches.value_of("trace_format") == Some("flamegraph") {
+            let flamegraph =
+                write_flamegraph(vm.context(), program_id, Path::new(program), trace_option);
+            function_instructions = Some(function_instructions_output(&flamegraph));
+        } else {
+            vm.context().iterate_vm_traces(
+                &|instruction_context: InstructionContext, executable, register_trace| {
+                    let mut analysis = LazyAnalysis::new(executable);
+                    if trace_option == "stdout" {
+                        writeln!(
+                            &mut std::io::stdout(),
+                            "TX Instruction {} Program {:?}",
+                            instruction_context.get_index_in_trace(),
+                            instruction_context.get_program_key(),
+                        )
                         .unwrap();
-                } else {
-                    let filename = format!(
-                        "{}.{}",
-                        trace_option,
-                        instruction_context.get_index_in_trace()
-                    );
-                    let mut fd = File::create(filename).unwrap();
-                    writeln!(
-                        &fd,
-                        "TX Instruction {} Program {:?}",
-                        instruction_context.get_index_in_trace(),
-                        instruction_context.get_program_key(),
-                    )
-                    .unwrap();
-                    analysis
-                        .analyze()
-                        .disassemble_register_trace(&mut fd, register_trace)
+                        analysis
+                            .analyze()
+                            .disassemble_register_trace(&mut std::io::stdout(), register_trace)
+                            .unwrap();
+                    } else {
+                        let filename = format!(
+                            "{}.{}",
+                            trace_option,
+                            instruction_context.get_index_in_trace()
+                        );
+                        let mut fd = File::create(filename).unwrap();
+                        writeln!(
+                            &fd,
+                            "TX Instruction {} Program {:?}",
+                            instruction_context.get_index_in_trace(),
+                            instruction_context.get_program_key(),
+                        )
                         .unwrap();
-                }
-            });
+                        analysis
+                            .analyze()
+                            .disassemble_register_trace(&mut fd, register_trace)
+                            .unwrap();
+                    }
+                },
+            );
+        }
     }
     drop(vm);
 
+    let compute_profile = invoke_context
+        .take_compute_profile()
+        .map(compute_profile_output);
     let output = Output {
         result: format!("{result:?}"),
         instruction_count,
@@ -628,6 +906,8 @@ pub fn program(ledger_path: &Path, matches: &ArgMatches<'_>) {
             .borrow()
             .get_recorded_content()
             .to_vec(),
+        compute_profile,
+        function_instructions,
     };
     let output_format = OutputFormat::from_matches(matches, "output_format", false);
     println!("{}", output_format.formatted_string(&output));
@@ -0,0 +1,1037 @@
+use {
+    crate::{LEDGER_TOOL_DIRECTORY, args::*, canonicalize_ledger_path, ledger_utils::*},
+    agave_snapshots::{
+        paths::BANK_SNAPSHOTS_DIR,
+        snapshot_archive_info::{
+            FullSnapshotArchiveInfo, IncrementalSnapshotArchiveInfo, SnapshotArchiveInfoGetter as _,
+        },
+        snapshot_config::SnapshotConfig,
+    },
+    clap::{App, AppSettings, Arg, ArgMatches, SubCommand, value_t},
+    log::*,
+    serde::Serialize,
+    solana_account::{AccountSharedData, ReadableAccount, state_traits::StateMutWincode as _},
+    solana_accounts_db::{
+        accounts_db::AccountsDb, utils::create_all_accounts_run_and_snapshot_dirs,
+    },
+    solana_clap_utils::input_validators::is_slot,
+    solana_cli_output::{OutputFormat, QuietDisplay, VerboseDisplay},
+    solana_clock::Slot,
+    solana_genesis_config::GenesisConfig,
+    solana_lattice_hash::lt_hash::LtHash,
+    solana_ledger::blockstore_options::AccessType,
+    solana_pubkey::Pubkey,
+    solana_runtime::{
+        bank::Bank, snapshot_account_source::SnapshotAccountSource, snapshot_bank_utils,
+    },
+    solana_runtime_transaction::runtime_transaction::RuntimeTransaction,
+    solana_stake_interface::state::StakeStateV2,
+    solana_svm::transaction_processor::ExecutionRecordingConfig,
+    solana_transaction::sanitized::{MessageHash, SanitizedTransaction},
+    solana_transaction_status::{EncodedTransaction, TransactionBinaryEncoding},
+    std::{
+        cmp::Ordering,
+        collections::BTreeSet,
+        fmt::{self, Display, Formatter},
+        fs::{self, File},
+        io::{self, BufWriter, Write},
+        path::{Path, PathBuf},
+        process::exit,
+        sync::{Arc, atomic::AtomicBool},
+    },
+    tempfile::TempDir,
+};
+
+pub trait SnapshotSubCommand {
+    fn snapshot_subcommand(self) -> Self;
+}
+
+impl SnapshotSubCommand for App<'_, '_> {
+    fn snapshot_subcommand(self) -> Self {
+        self.subcommand(
+            SubCommand::with_name("snapshot")
+                .about("Snapshot inspection commands")
+                .setting(AppSettings::InferSubcommands)
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("diff")
+                        .about(
+                            "Compare the accounts of two snapshots, or of a snapshot and the \
+                             ledger",
+                        )
+                        .arg(
+                            Arg::with_name("snapshot_a")
+                                .index(1)
+                                .value_name("SNAPSHOT_A")
+                                .required(true)
+                                .help("Full snapshot archive to compare from"),
+                        )
+                        .arg(
+                            Arg::with_name("snapshot_b")
+                                .index(2)
+                                .value_name("SNAPSHOT_B")
+                                .help(
+                                    "Full snapshot archive to compare to. If omitted, the bank \
+                                     from processing the ledger is compared to, see --halt-at-slot",
+                                ),
+                        )
+                        .arg(
+                            Arg::with_name("incremental_snapshot_a")
+                                .long("incremental-snapshot-a")
+                                .value_name("ARCHIVE")
+                                .takes_value(true)
+                                .help("Incremental snapshot archive to load on top of SNAPSHOT_A"),
+                        )
+                        .arg(
+                            Arg::with_name("incremental_snapshot_b")
+                                .long("incremental-snapshot-b")
+                                .value_name("ARCHIVE")
+                                .takes_value(true)
+                                .requires("snapshot_b")
+                                .help("Incremental snapshot archive to load on top of SNAPSHOT_B"),
+                        )
+                        .arg(
+                            Arg::with_name("halt_at_slot")
+                                .long("halt-at-slot")
+                                .value_name("SLOT")
+                                .validator(is_slot)
+                                .takes_value(true)
+                                .conflicts_with("snapshot_b")
+                                .help(
+                                    "When comparing to the ledger, halt processing the ledger at \
+                                     this slot",
+                                ),
+                        )
+                        .arg(load_genesis_arg())
+                        .args(&accounts_db_args())
+                        .args(&snapshot_args())
+                        .arg(
+                            Arg::with_name("max_accounts")
+                                .long("max-accounts")
+                                .value_name("COUNT")
+                                .takes_value(true)
+                                .default_value("100")
+                                .help(
+                                    "Maximum number of changed accounts to print. All changed \
+                                     accounts are always counted in the summary",
+                                ),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("replay")
+                        .about(
+                            "Execute transactions against the accounts of an unpacked snapshot, \
+                             without loading a bank",
+                        )
+                        .arg(
+                            Arg::with_name("snapshot_dir")
+                                .index(1)
+                                .value_name("SNAPSHOT_DIR")
+                                .required(true)
+                                .help(
+                                    "Directory a snapshot archive has been unpacked to, \
+                                     containing the accounts directory",
+                                ),
+                        )
+                        .arg(
+                            Arg::with_name("transactions")
+                                .index(2)
+                                .value_name("TRANSACTION")
+                                .multiple(true)
+                                .required(true)
+                                .help(
+                                    "Serialized transactions to execute in order. Each \
+                                     transaction sees the accounts written by the ones before it",
+                                ),
+                        )
+                        .arg(
+                            Arg::with_name("encoding")
+                                .long("encoding")
+                                .value_name("ENCODING")
+                                .takes_value(true)
+                                .possible_values(&["base58", "base64"])
+                                .default_value("base64")
+                                .help("Encoding of the transactions"),
+                        ),
+                ),
+        )
+    }
+}
+
+pub fn snapshot(ledger_path: &Path, matches: &ArgMatches<'_>) {
+    match matches.subcommand() {
+        ("diff", Some(arg_matches)) => {
+            let ledger_path = canonicalize_ledger_path(ledger_path);
+            let genesis_config = open_genesis_config_by(&ledger_path, arg_matches);
+            let bank_a = load_bank_from_snapshot_archives(
+                &ledger_path,
+                arg_matches,
+                &genesis_config,
+                "a",
+                Path::new(arg_matches.value_of("snapshot_a").unwrap()),
+                arg_matches
+                    .value_of("incremental_snapshot_a")
+                    .map(Path::new),
+            );
+            let bank_b = match arg_matches.value_of("snapshot_b") {
+                Some(snapshot_b) => Arc::new(load_bank_from_snapshot_archives(
+                    &ledger_path,
+                    arg_matches,
+                    &genesis_config,
+                    "b",
+                    Path::new(snapshot_b),
+                    arg_matches
+                        .value_of("incremental_snapshot_b")
+                        .map(Path::new),
+                )),
+                None => load_bank_from_ledger(&ledger_path, arg_matches, &genesis_config),
+            };
+
+            let max_accounts = value_t!(arg_matches, "max_accounts", usize).unwrap_or(100);
+            let work_dir = ledger_path.join(LEDGER_TOOL_DIRECTORY);
+            let diff = SnapshotDiff::new(&bank_a, &bank_b, max_accounts, &work_dir);
+            let output_format = OutputFormat::from_matches(arg_matches, "output_format", false);
+            println!("{}", output_format.formatted_string(&diff));
+        }
+        ("replay", Some(arg_matches)) => {
+            let snapshot_dir = Path::new(arg_matches.value_of("snapshot_dir").unwrap());
+            let encoding = match arg_matches.value_of("encoding") {
+                Some("base58") => TransactionBinaryEncoding::Base58,
+                _ => TransactionBinaryEncoding::Base64,
+            };
+            let source = SnapshotAccountSource::new(snapshot_dir).unwrap_or_else(|err| {
+                eprintln!(
+                    "Failed to open snapshot directory {}: {err}",
+                    snapshot_dir.display()
+                );
+                exit(1);
+            });
+
+            let transactions: Vec<_> = arg_matches
+                .values_of("transactions")
+                .unwrap()
+                .enumerate()
+                .map(|(index, transaction)| {
+                    let transaction = EncodedTransaction::Binary(transaction.to_string(), encoding)
+                        .decode()
+                        .unwrap_or_else(|| {
+                            eprintln!("Failed to decode transaction {index}");
+                            exit(1);
+                        });
+                    RuntimeTransaction::try_create(
+                        transaction,
+                        MessageHash::Compute,
+                        None,
+                        &source,
+                        source.reserved_account_keys(),
+                    )
+                    .unwrap_or_else(|err| {
+                        eprintln!("Failed to sanitize transaction {index}: {err}");
+                        exit(1);
+                    })
+                })
+                .collect();
+
+            let replay = SnapshotReplay::new(&source, &transactions);
+            let output_format = OutputFormat::from_matches(arg_matches, "output_format", false);
+            println!("{}", output_format.formatted_string(&replay));
+        }
+        _ => unreachable!(),
+    }
+}
+
+fn load_bank_from_snapshot_archives(
+    ledger_path: &Path,
+    arg_matches: &ArgMatches<'_>,
+    genesis_config: &GenesisConfig,
+    name: &str,
+    full_snapshot_archive_path: &Path,
+    incremental_snapshot_archive_path: Option<&Path>,
+) -> Bank {
+    let full_snapshot_archive_info =
+        FullSnapshotArchiveInfo::new_from_path(full_snapshot_archive_path.to_path_buf())
+            .unwrap_or_else(|err| exit_load_failed(name, err));
+    let incremental_snapshot_archive_info = incremental_snapshot_archive_path.map(|path| {
+        IncrementalSnapshotArchiveInfo::new_from_path(path.to_path_buf())
+            .unwrap_or_else(|err| exit_load_failed(name, err))
+    });
+
+    // Each side gets its own scratch space, so that both banks can be loaded
+    // at the same time
+    let work_dir = ledger_path
+        .join(LEDGER_TOOL_DIRECTORY)
+        .join(format!("snapshot_diff_{name}"));
+    let _ = fs::remove_dir_all(&work_dir);
+    let (account_paths, _) =
+        create_all_accounts_run_and_snapshot_dirs(&[work_dir.join("accounts")])
+            .unwrap_or_else(|err| exit_load_failed(name, err));
+    let bank_snapshots_dir = work_dir.join(BANK_SNAPSHOTS_DIR);
+    fs::create_dir_all(&bank_snapshots_dir).unwrap_or_else(|err| exit_load_failed(name, err));
+
+    let archives_dir = |path: &Path| {
+        path.parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| PathBuf::from("."))
+    };
+    let snapshot_config = SnapshotConfig {
+        full_snapshot_archives_dir: archives_dir(full_snapshot_archive_info.path()),
+        incremental_snapshot_archives_dir: incremental_snapshot_archive_info
+            .as_ref()
+            .map(|info| archives_dir(info.path()))
+            .unwrap_or_else(|| archives_dir(full_snapshot_archive_info.path())),
+        bank_snapshots_dir,
+        ..SnapshotConfig::new_load_only()
+    };
+
+    let process_options = parse_process_options(ledger_path, arg_matches);
+    snapshot_bank_utils::bank_from_snapshot_archives(
+        &account_paths,
+        &full_snapshot_archive_info,
+        incremental_snapshot_archive_info.as_ref(),
+        &snapshot_config,
+        genesis_config,
+        &process_options.runtime_config,
+        None,
+        None,
+        None,
+        true,
+        false,
+        false,
+        process_options.accounts_db_config,
+        None,
+        Arc::new(AtomicBool::new(false)),
+    )
+    .unwrap_or_else(|err| exit_load_failed(name, err))
+}
+
+fn exit_load_failed(name: &str, err: impl Display) -> ! {
+    eprintln!("Failed to load snapshot {name}: {err}");
+    exit(1);
+}
+
+fn load_bank_from_ledger(
+    ledger_path: &Path,
+    arg_matches: &ArgMatches<'_>,
+    genesis_config: &GenesisConfig,
+) -> Arc<Bank> {
+    let process_options = parse_process_options(ledger_path, arg_matches);
+    let blockstore = open_blockstore(ledger_path, arg_matches, AccessType::ReadOnly);
+    let LoadAndProcessLedgerOutput { bank_forks, .. } = load_and_process_ledger_or_exit(
+        arg_matches,
+        genesis_config,
+        Arc::new(blockstore),
+        process_options,
+        None,
+    );
+    bank_forks.read().unwrap().working_bank()
+}
+
+// Account checksums are spilled to disk in this many partitions, by the first
+// byte of the pubkey, so that only one partition of each bank is held in
+// memory while the checksums are merged
+const NUM_CHECKSUM_PARTITIONS: usize = 256;
+// A pubkey followed by its checksum
+const CHECKSUM_RECORD_LEN: usize = 64;
+
+/// The lattice hash checksum of every account with a non-zero balance of a
+/// bank, spilled to disk
+struct AccountChecksums {
+    dir: TempDir,
+}
+
+impl AccountChecksums {
+    fn new(bank: &Bank, work_dir: &Path) -> io::Result<Self> {
+        fs::create_dir_all(work_dir)?;
+        let dir = TempDir::new_in(work_dir)?;
+        let mut partitions = (0..NUM_CHECKSUM_PARTITIONS)
+            .map(|partition| {
+                File::create(Self::partition_path(&dir, partition)).map(BufWriter::new)
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        let mut write_result = Ok(());
+        bank.scan_all_accounts(|item| {
+            if let Some((pubkey, account, _slot)) = item
+                && account.lamports() != 0
+                && write_result.is_ok()
+            {
+                let checksum = AccountsDb::lt_hash_account(&account, pubkey).0.checksum();
+                let partition = &mut partitions[usize::from(pubkey.as_array()[0])];
+                write_result = partition
+                    .write_all(pubkey.as_array())
+                    .and_then(|()| partition.write_all(&checksum.0));
+            }
+        })
+        .map_err(io::Error::other)?;
+        write_result?;
+        for partition in partitions {
+            partition.into_inner().map_err(|err| err.into_error())?;
+        }
+        Ok(Self { dir })
+    }
+
+    /// Returns the checksums of the accounts in `partition`, sorted by pubkey
+    fn partition(&self, partition: usize) -> io::Result<Vec<(Pubkey, [u8; 32])>> {
+        let records = fs::read(Self::partition_path(&self.dir, partition))?;
+        let mut checksums: Vec<_> = records
+            .chunks_exact(CHECKSUM_RECORD_LEN)
+            .map(|record| {
+                let (pubkey, checksum) = record.split_at(CHECKSUM_RECORD_LEN / 2);
+                (
+                    Pubkey::try_from(pubkey).unwrap(),
+                    checksum.try_into().unwrap(),
+                )
+            })
+            .collect();
+        checksums.sort_unstable_by_key(|(pubkey, _)| *pubkey);
+        Ok(checksums)
+    }
+
+    fn partition_path(dir: &TempDir, partition: usize) -> PathBuf {
+        dir.path().join(format!("{partition:02x}"))
+    }
+}
+
+/// Merges two lists of account checksums that are sorted by pubkey, and calls
+/// `on_change` with every account that was added, removed or modified from
+/// `checksums_a` to `checksums_b`, in pubkey order
+fn merge_account_checksums(
+    checksums_a: &[(Pubkey, [u8; 32])],
+    checksums_b: &[(Pubkey, [u8; 32])],
+    mut on_change: impl FnMut(Pubkey, AccountChange),
+) {
+    let (mut iter_a, mut iter_b) = (checksums_a.iter().peekable(), checksums_b.iter().peekable());
+    loop {
+        let (pubkey, change) = match (iter_a.peek(), iter_b.peek()) {
+            (None, None) => break,
+            (Some((pubkey, _)), None) => {
+                iter_a.next();
+                (*pubkey, AccountChange::Removed)
+            }
+            (None, Some((pubkey, _))) => {
+                iter_b.next();
+                (*pubkey, AccountChange::Added)
+            }
+            (Some((pubkey_a, checksum_a)), Some((pubkey_b, checksum_b))) => {
+                match pubkey_a.cmp(pubkey_b) {
+                    Ordering::Less => {
+                        iter_a.next();
+                        (*pubkey_a, AccountChange::Removed)
+                    }
+                    Ordering::Greater => {
+                        iter_b.next();
+                        (*pubkey_b, AccountChange::Added)
+                    }
+                    Ordering::Equal => {
+                        let modified = checksum_a != checksum_b;
+                        let pubkey = *pubkey_a;
+                        iter_a.next();
+                        iter_b.next();
+                        if !modified {
+                            continue;
+                        }
+                        (pubkey, AccountChange::Modified)
+                    }
+                }
+            }
+        };
+        on_change(pubkey, change);
+    }
+}
+
+fn get_account(bank: &Bank, pubkey: &Pubkey) -> Option<AccountSharedData> {
+    bank.get_account_with_fixed_root(pubkey)
+        .filter(|account| account.lamports() != 0)
+}
+
+fn get_delegation(account: Option<&AccountSharedData>) -> Option<(Pubkey, u64)> {
+    let account = account.filter(|account| solana_sdk_ids::stake::check_id(account.owner()))?;
+    match account.state() {
+        Ok(StakeStateV2::Stake(_meta, stake, _flags)) => {
+            Some((stake.delegation.voter_pubkey, stake.delegation.stake))
+        }
+        _ => None,
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum AccountChange {
+    Added,
+    Removed,
+    Modified,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountDiff {
+    pub pubkey: String,
+    pub change: AccountChange,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner_a: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner_b: Option<String>,
+    pub lamports_a: u64,
+    pub lamports_b: u64,
+    pub data_len_a: usize,
+    pub data_len_b: usize,
+    pub data_changed: bool,
+    /// Checksum of this account's contribution to the accounts lattice hash,
+    /// i.e. its lattice hash in B mixed out by its lattice hash in A
+    pub lt_hash_delta: String,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct VoteAccountDiff {
+    pub pubkey: String,
+    pub node_pubkey: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stake_a: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stake_b: Option<u64>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct StakeAccountDiff {
+    pub pubkey: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub voter_a: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub voter_b: Option<String>,
+    pub stake_a: u64,
+    pub stake_b: u64,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotDiff {
+    pub slot_a: Slot,
+    pub slot_b: Slot,
+    pub bank_hash_a: String,
+    pub bank_hash_b: String,
+    pub capitalization_a: u64,
+    pub capitalization_b: u64,
+    pub capitalization_delta: i128,
+    pub num_added: usize,
+    pub num_removed: usize,
+    pub num_modified: usize,
+    pub accounts_lt_hash_a: String,
+    pub accounts_lt_hash_b: String,
+    /// Checksum of the sum of the lattice hash deltas of all changed accounts
+    pub accounts_lt_hash_delta: String,
+    /// Whether mixing the delta into A's accounts lattice hash yields B's
+    pub accounts_lt_hash_matches: bool,
+    pub vote_accounts: Vec<VoteAccountDiff>,
+    pub stake_accounts: Vec<StakeAccountDiff>,
+    pub accounts: Vec<AccountDiff>,
+    pub num_accounts_omitted: usize,
+}
+
+impl AccountDiff {
+    /// Returns the difference between `account_a` and `account_b`, the
+    /// versions of the account at `pubkey` in A and B, and the account's
+    /// lattice hash delta
+    fn new(
+        pubkey: &Pubkey,
+        change: AccountChange,
+        account_a: Option<&AccountSharedData>,
+        account_b: Option<&AccountSharedData>,
+    ) -> (Self, LtHash) {
+        let lt_hash = |account: Option<&AccountSharedData>| {
+            account
+                .map(|account| AccountsDb::lt_hash_account(account, pubkey).0)
+                .unwrap_or_else(LtHash::identity)
+        };
+        let mut lt_hash_delta = lt_hash(account_b);
+        lt_hash_delta.mix_out(&lt_hash(account_a));
+
+        let account_diff = Self {
+            pubkey: pubkey.to_string(),
+            change,
+            owner_a: account_a.map(|account| account.owner().to_string()),
+            owner_b: account_b.map(|account| account.owner().to_string()),
+            lamports_a: account_a.map(ReadableAccount::lamports).unwrap_or_default(),
+            lamports_b: account_b.map(ReadableAccount::lamports).unwrap_or_default(),
+            data_len_a: account_a
+                .map(|account| account.data().len())
+                .unwrap_or_default(),
+            data_len_b: account_b
+                .map(|account| account.data().len())
+                .unwrap_or_default(),
+            data_changed: account_a.map(ReadableAccount::data)
+                != account_b.map(ReadableAccount::data),
+            lt_hash_delta: lt_hash_delta.checksum().to_string(),
+        };
+        (account_diff, lt_hash_delta)
+    }
+}
+
+impl SnapshotDiff {
+    /// Compares the accounts of `bank_a` and `bank_b`
+    ///
+    /// The account checksums of both banks are spilled to `work_dir` and then
+    /// merged one partition at a time, so neither bank's accounts are ever
+    /// all held in memory.
+    pub fn new(bank_a: &Bank, bank_b: &Bank, max_accounts: usize, work_dir: &Path) -> Self {
+        let scan = |bank: &Bank| {
+            info!("Scanning accounts of bank {}", bank.slot());
+            AccountChecksums::new(bank, work_dir).unwrap_or_else(|err| {
+                eprintln!("Failed to scan accounts of bank {}: {err}", bank.slot());
+                exit(1);
+            })
+        };
+        let checksums_a = scan(bank_a);
+        let checksums_b = scan(bank_b);
+        let read_partition = |checksums: &AccountChecksums, partition| {
+            checksums.partition(partition).unwrap_or_else(|err| {
+                eprintln!("Failed to read account checksums: {err}");
+                exit(1);
+            })
+        };
+
+        let mut total_lt_hash_delta = LtHash::identity();
+        let mut accounts = Vec::new();
+        let mut stake_accounts = Vec::new();
+        let (mut num_added, mut num_removed, mut num_modified) = (0, 0, 0);
+        for partition in 0..NUM_CHECKSUM_PARTITIONS {
+            merge_account_checksums(
+                &read_partition(&checksums_a, partition),
+                &read_partition(&checksums_b, partition),
+                |pubkey, change| {
+                    match change {
+                        AccountChange::Added => num_added += 1,
+                        AccountChange::Removed => num_removed += 1,
+                        AccountChange::Modified => num_modified += 1,
+                    }
+                    let account_a = get_account(bank_a, &pubkey);
+                    let account_b = get_account(bank_b, &pubkey);
+                    let (account_diff, lt_hash_delta) =
+                        AccountDiff::new(&pubkey, change, account_a.as_ref(), account_b.as_ref());
+                    total_lt_hash_delta.mix_in(&lt_hash_delta);
+
+                    let delegation_a = get_delegation(account_a.as_ref());
+                    let delegation_b = get_delegation(account_b.as_ref());
+                    if delegation_a != delegation_b {
+                        stake_accounts.push(StakeAccountDiff {
+                            pubkey: pubkey.to_string(),
+                            voter_a: delegation_a.map(|(voter, _)| voter.to_string()),
+                            voter_b: delegation_b.map(|(voter, _)| voter.to_string()),
+                            stake_a: delegation_a.map(|(_, stake)| stake).unwrap_or_default(),
+                            stake_b: delegation_b.map(|(_, stake)| stake).unwrap_or_default(),
+                        });
+                    }
+
+                    if accounts.len() < max_accounts {
+                        accounts.push(account_diff);
+                    }
+                },
+            );
+        }
+
+        let vote_accounts_a = bank_a.vote_accounts();
+        let vote_accounts_b = bank_b.vote_accounts();
+        let vote_account_keys: BTreeSet<_> = vote_accounts_a
+            .keys()
+            .chain(vote_accounts_b.keys())
+            .collect();
+        let vote_accounts = vote_account_keys
+            .into_iter()
+            .filter_map(|pubkey| {
+                let vote_account_a = vote_accounts_a.get(pubkey);
+                let vote_account_b = vote_accounts_b.get(pubkey);
+                let stake_a = vote_account_a.map(|(stake, _)| *stake);
+                let stake_b = vote_account_b.map(|(stake, _)| *stake);
+                (stake_a != stake_b).then(|| {
+                    let (_, vote_account) = vote_account_b.or(vote_account_a).unwrap();
+                    VoteAccountDiff {
+                        pubkey: pubkey.to_string(),
+                        node_pubkey: vote_account.node_pubkey().to_string(),
+                        stake_a,
+                        stake_b,
+                    }
+                })
+            })
+            .collect();
+
+        let accounts_lt_hash_a = bank_a.accounts_lt_hash().0;
+        let accounts_lt_hash_b = bank_b.accounts_lt_hash().0;
+        let mut expected_accounts_lt_hash_b = accounts_lt_hash_a.clone();
+        expected_accounts_lt_hash_b.mix_in(&total_lt_hash_delta);
+
+        Self {
+            slot_a: bank_a.slot(),
+            slot_b: bank_b.slot(),
+            bank_hash_a: bank_a.hash().to_string(),
+            bank_hash_b: bank_b.hash().to_string(),
+            capitalization_a: bank_a.capitalization(),
+            capitalization_b: bank_b.capitalization(),
+            capitalization_delta: i128::from(bank_b.capitalization())
+                - i128::from(bank_a.capitalization()),
+            num_added,
+            num_removed,
+            num_modified,
+            accounts_lt_hash_a: accounts_lt_hash_a.checksum().to_string(),
+            accounts_lt_hash_b: accounts_lt_hash_b.checksum().to_string(),
+            accounts_lt_hash_delta: total_lt_hash_delta.checksum().to_string(),
+            accounts_lt_hash_matches: expected_accounts_lt_hash_b == accounts_lt_hash_b,
+            vote_accounts,
+            stake_accounts,
+            num_accounts_omitted: num_added + num_removed + num_modified - accounts.len(),
+            accounts,
+        }
+    }
+}
+
+impl QuietDisplay for SnapshotDiff {}
+impl VerboseDisplay for SnapshotDiff {}
+
+impl Display for SnapshotDiff {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(f, "A: slot {}, bank hash {}", self.slot_a, self.bank_hash_a)?;
+        writeln!(f, "B: slot {}, bank hash {}", self.slot_b, self.bank_hash_b)?;
+        writeln!(
+            f,
+            "Capitalization: {} -> {} ({:+})",
+            self.capitalization_a, self.capitalization_b, self.capitalization_delta,
+        )?;
+        writeln!(
+            f,
+            "Accounts: {} added, {} removed, {} modified",
+            self.num_added, self.num_removed, self.num_modified,
+        )?;
+        writeln!(
+            f,
+            "Accounts lattice hash: {} -> {}, delta {} ({})",
+            self.accounts_lt_hash_a,
+            self.accounts_lt_hash_b,
+            self.accounts_lt_hash_delta,
+            if self.accounts_lt_hash_matches {
+                "consistent"
+            } else {
+                "INCONSISTENT, A + delta != B"
+            },
+        )?;
+
+        if !self.vote_accounts.is_empty() {
+            writeln!(f, "\nVote account stake changes:")?;
+            for vote_account in &self.vote_accounts {
+                let stake = |stake: Option<u64>| {
+                    stake.map_or_else(|| "-".to_string(), |stake| stake.to_string())
+                };
+                writeln!(
+                    f,
+                    "  {} (node {}): {} -> {}",
+                    vote_account.pubkey,
+                    vote_account.node_pubkey,
+                    stake(vote_account.stake_a),
+                    stake(vote_account.stake_b),
+                )?;
+            }
+        }
+
+        if !self.stake_accounts.is_empty() {
+            writeln!(f, "\nStake delegation changes:")?;
+            for stake_account in &self.stake_accounts {
+                let voter = |voter: &Option<String>| voter.as_deref().unwrap_or("-").to_string();
+                writeln!(
+                    f,
+                    "  {}: {} to {} -> {} to {}",
+                    stake_account.pubkey,
+                    stake_account.stake_a,
+                    voter(&stake_account.voter_a),
+                    stake_account.stake_b,
+                    voter(&stake_account.voter_b),
+                )?;
+            }
+        }
+
+        if !self.accounts.is_empty() {
+            writeln!(f, "\nChanged accounts:")?;
+            for account in &self.accounts {
+                writeln!(f, "  {} {:?}", account.pubkey, account.change)?;
+                if account.owner_a != account.owner_b {
+                    writeln!(
+                        f,
+                        "    owner: {} -> {}",
+                        account.owner_a.as_deref().unwrap_or("-"),
+                        account.owner_b.as_deref().unwrap_or("-"),
+                    )?;
+                } else if let Some(owner) = &account.owner_b {
+                    writeln!(f, "    owner: {owner}")?;
+                }
+                writeln!(
+                    f,
+                    "    lamports: {} -> {}",
+                    account.lamports_a, account.lamports_b
+                )?;
+                writeln!(
+                    f,
+                    "    data: {} -> {} bytes{}",
+                    account.data_len_a,
+                    account.data_len_b,
+                    if account.data_changed {
+                        ", changed"
+                    } else {
+                        ""
+                    },
+                )?;
+                writeln!(f, "    lattice hash delta: {}", account.lt_hash_delta)?;
+            }
+            if self.num_accounts_omitted > 0 {
+                writeln!(
+                    f,
+                    "  ... {} more, see --max-accounts",
+                    self.num_accounts_omitted
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayedTransaction {
+    pub signature: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub err: Option<String>,
+    pub fee: u64,
+    pub compute_units_consumed: u64,
+    pub logs: Vec<String>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotReplay {
+    pub slot: Slot,
+    pub transactions: Vec<ReplayedTransaction>,
+}
+
+impl SnapshotReplay {
+    pub fn new(
+        source: &SnapshotAccountSource,
+        transactions: &[RuntimeTransaction<SanitizedTransaction>],
+    ) -> Self {
+        let output = source.process_transactions(
+            transactions,
+            ExecutionRecordingConfig::new_single_setting(true),
+        );
+        let transactions = transactions
+            .iter()
+            .zip(output.processing_results)
+            .map(|(transaction, processing_result)| {
+                let signature = transaction.signature().to_string();
+                match processing_result {
+                    Ok(processed_tx) => ReplayedTransaction {
+                        signature,
+                        err: processed_tx.status().err().map(|err| err.to_string()),
+                        fee: processed_tx.fee_details().total_fee(),
+                        compute_units_consumed: processed_tx.executed_units(),
+                        logs: processed_tx
+                            .execution_details()
+                            .and_then(|details| details.log_messages.clone())
+                            .unwrap_or_default(),
+                    },
+                    Err(err) => ReplayedTransaction {
+                        signature,
+                        err: Some(err.to_string()),
+                        fee: 0,
+                        compute_units_consumed: 0,
+                        logs: vec![],
+                    },
+                }
+            })
+            .collect();
+        Self {
+            slot: source.slot(),
+            transactions,
+        }
+    }
+}
+
+impl QuietDisplay for SnapshotReplay {}
+impl VerboseDisplay for SnapshotReplay {}
+
+impl Display for SnapshotReplay {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(f, "Slot: {}", self.slot)?;
+        for transaction in &self.transactions {
+            writeln!(f, "\n{}", transaction.signature)?;
+            writeln!(
+                f,
+                "  status: {}",
+                transaction.err.as_deref().unwrap_or("success")
+            )?;
+            writeln!(f, "  fee: {}", transaction.fee)?;
+            writeln!(
+                f,
+                "  compute units consumed: {}",
+                transaction.compute_units_consumed
+            )?;
+            if !transaction.logs.is_empty() {
+                writeln!(f, "  logs:")?;
+                for log in &transaction.logs {
+                    writeln!(f, "    {log}")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, solana_account::WritableAccount};
+
+    fn checksums(accounts: &[(Pubkey, u8)]) -> Vec<(Pubkey, [u8; 32])> {
+        let mut checksums: Vec<_> = accounts
+            .iter()
+            .map(|(pubkey, checksum)| (*pubkey, [*checksum; 32]))
+            .collect();
+        checksums.sort_unstable_by_key(|(pubkey, _)| *pubkey);
+        checksums
+    }
+
+    fn merge(
+        checksums_a: &[(Pubkey, [u8; 32])],
+        checksums_b: &[(Pubkey, [u8; 32])],
+    ) -> Vec<(Pubkey, AccountChange)> {
+        let mut changes = Vec::new();
+        merge_account_checksums(checksums_a, checksums_b, |pubkey, change| {
+            changes.push((pubkey, change))
+        });
+        changes
+    }
+
+    #[test]
+    fn test_merge_account_checksums() {
+        let mut pubkeys: Vec<_> = (0..6).map(|_| Pubkey::new_unique()).collect();
+        pubkeys.sort_unstable();
+        let [
+            removed,
+            unchanged,
+            added,
+            modified,
+            removed_last,
+            added_last,
+        ] = <[_; 6]>::try_from(pubkeys).unwrap();
+        let checksums_a = checksums(&[
+            (removed, 1),
+            (unchanged, 2),
+            (modified, 3),
+            (removed_last, 4),
+        ]);
+        let checksums_b = checksums(&[(unchanged, 2), (added, 5), (modified, 6), (added_last, 7)]);
+
+        assert_eq!(
+            merge(&checksums_a, &checksums_b),
+            vec![
+                (removed, AccountChange::Removed),
+                (added, AccountChange::Added),
+                (modified, AccountChange::Modified),
+                (removed_last, AccountChange::Removed),
+                (added_last, AccountChange::Added),
+            ],
+        );
+        assert_eq!(merge(&checksums_a, &checksums_a), vec![]);
+        assert_eq!(
+            merge(&[], &checksums_b),
+            checksums_b
+                .iter()
+                .map(|(pubkey, _)| (*pubkey, AccountChange::Added))
+                .collect::<Vec<_>>(),
+        );
+        assert_eq!(
+            merge(&checksums_a, &[]),
+            checksums_a
+                .iter()
+                .map(|(pubkey, _)| (*pubkey, AccountChange::Removed))
+                .collect::<Vec<_>>(),
+        );
+    }
+
+    #[test]
+    fn test_account_diff() {
+        let pubkey = Pubkey::new_unique();
+        let owner_a = Pubkey::new_unique();
+        let owner_b = Pubkey::new_unique();
+        let account_a = AccountSharedData::new(100, 10, &owner_a);
+        let lt_hash_a = AccountsDb::lt_hash_account(&account_a, &pubkey).0;
+
+        // Lamports changed
+        let mut account_b = account_a.clone();
+        account_b.set_lamports(150);
+        let (diff, lt_hash_delta) = AccountDiff::new(
+            &pubkey,
+            AccountChange::Modified,
+            Some(&account_a),
+            Some(&account_b),
+        );
+        assert_eq!(diff.pubkey, pubkey.to_string());
+        assert_eq!(diff.change, AccountChange::Modified);
+        assert_eq!((diff.lamports_a, diff.lamports_b), (100, 150));
+        assert_eq!(diff.owner_a, diff.owner_b);
+        assert_eq!((diff.data_len_a, diff.data_len_b), (10, 10));
+        assert!(!diff.data_changed);
+        let mut lt_hash_b = lt_hash_a.clone();
+        lt_hash_b.mix_in(&lt_hash_delta);
+        assert_eq!(
+            lt_hash_b,
+            AccountsDb::lt_hash_account(&account_b, &pubkey).0
+        );
+        assert_eq!(diff.lt_hash_delta, lt_hash_delta.checksum().to_string());
+
+        // Data changed, with and without changing its length
+        let mut account_b = account_a.clone();
+        account_b.data_as_mut_slice()[0] = 1;
+        let (diff, _) = AccountDiff::new(
+            &pubkey,
+            AccountChange::Modified,
+            Some(&account_a),
+            Some(&account_b),
+        );
+        assert_eq!((diff.data_len_a, diff.data_len_b), (10, 10));
+        assert!(diff.data_changed);
+        account_b.set_data_from_slice(&[0; 20]);
+        let (diff, _) = AccountDiff::new(
+            &pubkey,
+            AccountChange::Modified,
+            Some(&account_a),
+            Some(&account_b),
+        );
+        assert_eq!((diff.data_len_a, diff.data_len_b), (10, 20));
+        assert!(diff.data_changed);
+
+        // Owner changed
+        let mut account_b = account_a.clone();
+        account_b.set_owner(owner_b);
+        let (diff, _) = AccountDiff::new(
+            &pubkey,
+            AccountChange::Modified,
+            Some(&account_a),
+            Some(&account_b),
+        );
+        assert_eq!(diff.owner_a, Some(owner_a.to_string()));
+        assert_eq!(diff.owner_b, Some(owner_b.to_string()));
+        assert!(!diff.data_changed);
+
+        // Added and removed accounts only have one side, and their lattice
+        // hash deltas cancel out
+        let (added, lt_hash_delta_added) =
+            AccountDiff::new(&pubkey, AccountChange::Added, None, Some(&account_a));
+        assert_eq!(added.owner_a, None);
+        assert_eq!(added.owner_b, Some(owner_a.to_string()));
+        assert_eq!((added.lamports_a, added.lamports_b), (0, 100));
+        assert_eq!((added.data_len_a, added.data_len_b), (0, 10));
+        assert!(added.data_changed);
+        assert_eq!(lt_hash_delta_added, lt_hash_a);
+        let (removed, mut lt_hash_delta_removed) =
+            AccountDiff::new(&pubkey, AccountChange::Removed, Some(&account_a), None);
+        assert_eq!(removed.owner_a, Some(owner_a.to_string()));
+        assert_eq!(removed.owner_b, None);
+        assert_eq!((removed.lamports_a, removed.lamports_b), (100, 0));
+        lt_hash_delta_removed.mix_in(&lt_hash_delta_added);
+        assert_eq!(lt_hash_delta_removed, LtHash::identity());
+    }
+}
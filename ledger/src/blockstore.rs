@@ -100,9 +100,11 @@ use {
 };
 
 pub mod blockstore_purge;
+pub mod blockstore_scrub;
 pub mod cleanup_service;
 pub mod column;
 pub mod error;
+pub mod scrub_service;
 pub use {
     crate::{
         blockstore::error::{BlockstoreError, Result},
@@ -111,8 +113,10 @@ pub use {
         blockstore_metrics::{BlockstoreInsertionMetrics, BlockstoreSwitchBankMetrics},
     },
     blockstore_purge::PurgeType,
+    blockstore_scrub::{ScrubError, ScrubStats, SlotScrubReport},
     cleanup_service::*,
     rocksdb::properties as RocksProperties,
+    scrub_service::*,
 };
 
 pub const MAX_REPLAY_WAKE_UP_SIGNALS: usize = 1;
@@ -2259,7 +2263,7 @@ impl Blockstore {
     /// Core shred insertion logic.
     fn do_insert_shreds_locked<'a, 'db>(
         &'db self,
-        _insert_shreds_lock: &MutexGuard<'_, ()>,
+        insert_shreds_lock: &MutexGuard<'_, ()>,
         shreds: impl IntoIterator<
             Item = (Cow<'a, Shred>, /*is_repaired:*/ bool, BlockLocation),
             IntoIter: ExactSizeIterator,
@@ -2271,8 +2275,33 @@ impl Blockstore {
         metrics: &mut BlockstoreInsertionMetrics,
     ) -> Result<InsertResults> {
         let shreds = shreds.into_iter();
-        let mut shred_insertion_tracker = ShredInsertionTracker::new(shreds.len(), write_batch);
+        let shred_insertion_tracker = ShredInsertionTracker::new(shreds.len(), write_batch);
+        self.do_insert_shreds_with_tracker(
+            insert_shreds_lock,
+            shreds,
+            is_trusted,
+            shred_recovery_context,
+            pinnable_slice,
+            shred_insertion_tracker,
+            metrics,
+        )
+    }
 
+    /// Same as `do_insert_shreds_locked`, but starts from the working sets and
+    /// write batch of `shred_insertion_tracker` rather than empty ones.
+    fn do_insert_shreds_with_tracker<'a, 'db>(
+        &'db self,
+        _insert_shreds_lock: &MutexGuard<'_, ()>,
+        shreds: impl IntoIterator<
+            Item = (Cow<'a, Shred>, /*is_repaired:*/ bool, BlockLocation),
+            IntoIter: ExactSizeIterator,
+        >,
+        is_trusted: bool,
+        shred_recovery_context: Option<&mut ShredRecoveryContext>,
+        pinnable_slice: &mut DBPinnableSlice<'db>,
+        mut shred_insertion_tracker: ShredInsertionTracker<'a, '_>,
+        metrics: &mut BlockstoreInsertionMetrics,
+    ) -> Result<InsertResults> {
         self.attempt_shred_insertion(
             shreds,
             is_trusted,
@@ -0,0 +1,791 @@
+//! Consistency checks between the shred columns of the blockstore and the
+//! metadata columns that describe them.
+//!
+//! The checks performed for a slot are:
+//! - `SlotMeta` and `Index` agree with the data and coding shreds that are
+//!   actually present in the `ShredData` and `ShredCode` columns.
+//! - Every stored shred deserializes, sanitizes, is keyed at its own
+//!   `(slot, index)`, and (when the slot leader is known) carries a valid
+//!   leader signature.
+//! - All shreds of an erasure set recompute the same merkle root, and that
+//!   root matches the `MerkleRootMeta` of the erasure set.
+//! - Coding shreds agree with the `ErasureMeta` of their erasure set.
+
+use {
+    super::*,
+    solana_leader_schedule::SlotLeader,
+    std::fmt::{self, Display},
+};
+
+/// A single inconsistency found while scrubbing a slot.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ScrubError {
+    /// The slot is rooted but has no `SlotMeta`.
+    MissingSlotMeta,
+    /// The slot has received shreds but no `Index`.
+    MissingIndex,
+    /// `SlotMeta::consumed` or `SlotMeta::received` disagree with the data
+    /// shreds present in the slot.
+    SlotMetaMismatch {
+        field: &'static str,
+        expected: u64,
+        actual: u64,
+    },
+    /// The slot is marked full but some data shreds below `last_index` are
+    /// missing.
+    IncompleteFullSlot {
+        last_index: u64,
+        num_data_shreds: u64,
+    },
+    /// A shred is recorded in the `Index` but missing from its column.
+    MissingShred { index: u64, shred_type: ShredType },
+    /// A shred is present in its column but not recorded in the `Index`.
+    UnindexedShred { index: u64, shred_type: ShredType },
+    /// A stored shred payload failed to deserialize or sanitize.
+    InvalidShred {
+        index: u64,
+        shred_type: ShredType,
+        reason: String,
+    },
+    /// A stored shred is keyed under a different slot or index than the one
+    /// in its header.
+    MisplacedShred {
+        index: u64,
+        shred_type: ShredType,
+        header_index: u32,
+    },
+    /// A stored shred does not carry a valid signature from the slot leader.
+    InvalidSignature { index: u64, shred_type: ShredType },
+    /// Shreds of the same erasure set recompute different merkle roots.
+    MerkleRootMismatch {
+        fec_set_index: u32,
+        index: u64,
+        shred_type: ShredType,
+    },
+    /// The erasure set has shreds but no `MerkleRootMeta`.
+    MissingMerkleRootMeta { fec_set_index: u32 },
+    /// The merkle root recomputed from the shreds does not match the one
+    /// stored in `MerkleRootMeta`.
+    MerkleRootMetaMismatch { fec_set_index: u32 },
+    /// The erasure set has coding shreds but no `ErasureMeta`.
+    MissingErasureMeta { fec_set_index: u32 },
+    /// A coding shred disagrees with the `ErasureMeta` of its erasure set.
+    ErasureMetaMismatch { fec_set_index: u32, index: u64 },
+}
+
+impl Display for ScrubError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::MissingSlotMeta => write!(f, "missing slot meta"),
+            Self::MissingIndex => write!(f, "missing index"),
+            Self::SlotMetaMismatch {
+                field,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "slot meta {field} is {actual}, shred columns imply {expected}"
+            ),
+            Self::IncompleteFullSlot {
+                last_index,
+                num_data_shreds,
+            } => write!(
+                f,
+                "slot is full with last index {last_index} but only has {num_data_shreds} data \
+                 shreds"
+            ),
+            Self::MissingShred { index, shred_type } => {
+                write!(f, "{shred_type:?} shred {index} is indexed but missing")
+            }
+            Self::UnindexedShred { index, shred_type } => {
+                write!(f, "{shred_type:?} shred {index} is present but not indexed")
+            }
+            Self::InvalidShred {
+                index,
+                shred_type,
+                reason,
+            } => write!(f, "{shred_type:?} shred {index} is invalid: {reason}"),
+            Self::MisplacedShred {
+                index,
+                shred_type,
+                header_index,
+            } => write!(
+                f,
+                "{shred_type:?} shred stored at index {index} has header index {header_index}"
+            ),
+            Self::InvalidSignature { index, shred_type } => {
+                write!(
+                    f,
+                    "{shred_type:?} shred {index} has an invalid leader signature"
+                )
+            }
+            Self::MerkleRootMismatch {
+                fec_set_index,
+                index,
+                shred_type,
+            } => write!(
+                f,
+                "{shred_type:?} shred {index} has a different merkle root than the rest of \
+                 erasure set {fec_set_index}"
+            ),
+            Self::MissingMerkleRootMeta { fec_set_index } => {
+                write!(
+                    f,
+                    "erasure set {fec_set_index} is missing its merkle root meta"
+                )
+            }
+            Self::MerkleRootMetaMismatch { fec_set_index } => write!(
+                f,
+                "erasure set {fec_set_index} merkle root does not match its merkle root meta"
+            ),
+            Self::MissingErasureMeta { fec_set_index } => {
+                write!(f, "erasure set {fec_set_index} is missing its erasure meta")
+            }
+            Self::ErasureMetaMismatch {
+                fec_set_index,
+                index,
+            } => write!(
+                f,
+                "coding shred {index} does not match the erasure meta of erasure set \
+                 {fec_set_index}"
+            ),
+        }
+    }
+}
+
+/// The result of scrubbing a single slot.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SlotScrubReport {
+    pub slot: Slot,
+    pub num_data_shreds: u64,
+    pub num_coding_shreds: u64,
+    pub num_erasure_sets: u64,
+    /// Whether the shred signatures were checked against the slot leader.
+    pub verified_signatures: bool,
+    pub errors: Vec<ScrubError>,
+}
+
+impl SlotScrubReport {
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// Aggregate counters over a range of scrubbed slots.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ScrubStats {
+    pub num_slots: u64,
+    pub num_data_shreds: u64,
+    pub num_coding_shreds: u64,
+    pub num_erasure_sets: u64,
+    pub num_errors: u64,
+    /// Slots that had at least one inconsistency.
+    pub bad_slots: Vec<Slot>,
+}
+
+impl ScrubStats {
+    pub fn accumulate(&mut self, report: &SlotScrubReport) {
+        self.num_slots += 1;
+        self.num_data_shreds += report.num_data_shreds;
+        self.num_coding_shreds += report.num_coding_shreds;
+        self.num_erasure_sets += report.num_erasure_sets;
+        self.num_errors += report.errors.len() as u64;
+        if !report.is_ok() {
+            self.bad_slots.push(report.slot);
+        }
+    }
+
+    pub fn report(&self, starting_slot: Slot, ending_slot: Slot) {
+        datapoint_info!(
+            "blockstore-scrub",
+            ("starting_slot", starting_slot as i64, i64),
+            ("ending_slot", ending_slot as i64, i64),
+            ("num_slots", self.num_slots as i64, i64),
+            ("num_data_shreds", self.num_data_shreds as i64, i64),
+            ("num_coding_shreds", self.num_coding_shreds as i64, i64),
+            ("num_erasure_sets", self.num_erasure_sets as i64, i64),
+            ("num_errors", self.num_errors as i64, i64),
+            ("num_bad_slots", self.bad_slots.len() as i64, i64),
+        );
+    }
+}
+
+// Shreds of one erasure set seen while walking the shred columns
+#[derive(Default)]
+struct ErasureSetState {
+    merkle_root: Option<Hash>,
+    has_coding_shreds: bool,
+    // `ErasureMeta` of the set, read once on the first coding shred
+    erasure_meta: Option<Option<ErasureMeta>>,
+}
+
+impl Blockstore {
+    /// Checks that the metadata columns of `slot` agree with its shred
+    /// columns, and that the stored shreds themselves are well formed.
+    ///
+    /// Shred signatures are only verified if `leader` is provided.
+    pub fn scrub_slot(&self, slot: Slot, leader: Option<&SlotLeader>) -> Result<SlotScrubReport> {
+        let mut report = SlotScrubReport {
+            slot,
+            verified_signatures: leader.is_some(),
+            ..SlotScrubReport::default()
+        };
+        let Some(slot_meta) = self.meta(slot)? else {
+            report.errors.push(ScrubError::MissingSlotMeta);
+            return Ok(report);
+        };
+        let index = match self.get_index(slot)? {
+            Some(index) => index,
+            None => {
+                if slot_meta.received > 0 {
+                    report.errors.push(ScrubError::MissingIndex);
+                }
+                Index::new(slot)
+            }
+        };
+
+        let mut erasure_sets = BTreeMap::<u32, ErasureSetState>::new();
+        let mut data_indexes = Vec::new();
+        for ((_, key_index), payload) in self.slot_data_iterator(slot, 0)? {
+            data_indexes.push(key_index);
+            Self::scrub_shred(
+                slot,
+                key_index,
+                ShredType::Data,
+                &payload,
+                leader,
+                &mut erasure_sets,
+                &mut report,
+            );
+        }
+        let mut coding_indexes = Vec::new();
+        for ((_, key_index), payload) in self.slot_coding_iterator(slot, 0)? {
+            coding_indexes.push(key_index);
+            let Some(shred) = Self::scrub_shred(
+                slot,
+                key_index,
+                ShredType::Code,
+                &payload,
+                leader,
+                &mut erasure_sets,
+                &mut report,
+            ) else {
+                continue;
+            };
+            let state = erasure_sets
+                .get_mut(&shred.fec_set_index())
+                .expect("scrub_shred records the erasure set of every usable shred");
+            if state.erasure_meta.is_none() {
+                state.erasure_meta = Some(self.erasure_meta(shred.erasure_set())?);
+            }
+            if let Some(Some(erasure_meta)) = &state.erasure_meta
+                && !erasure_meta.check_coding_shred(&shred)
+            {
+                report.errors.push(ScrubError::ErasureMetaMismatch {
+                    fec_set_index: shred.fec_set_index(),
+                    index: key_index,
+                });
+            }
+        }
+        report.num_data_shreds = data_indexes.len() as u64;
+        report.num_coding_shreds = coding_indexes.len() as u64;
+        report.num_erasure_sets = erasure_sets.len() as u64;
+
+        Self::scrub_index(
+            index.data(),
+            &data_indexes,
+            ShredType::Data,
+            &mut report.errors,
+        );
+        Self::scrub_index(
+            index.coding(),
+            &coding_indexes,
+            ShredType::Code,
+            &mut report.errors,
+        );
+        Self::scrub_slot_meta(&slot_meta, &data_indexes, &mut report.errors);
+
+        for (fec_set_index, state) in erasure_sets {
+            let erasure_set = ErasureSetId::new(slot, fec_set_index);
+            match self.merkle_root_meta(erasure_set)? {
+                None => report
+                    .errors
+                    .push(ScrubError::MissingMerkleRootMeta { fec_set_index }),
+                Some(merkle_root_meta) => {
+                    if state.merkle_root.is_some()
+                        && merkle_root_meta.merkle_root() != state.merkle_root
+                    {
+                        report
+                            .errors
+                            .push(ScrubError::MerkleRootMetaMismatch { fec_set_index });
+                    }
+                }
+            }
+            // Every usable coding shred looked up the erasure meta of its set
+            if state.has_coding_shreds && matches!(state.erasure_meta, Some(None)) {
+                report
+                    .errors
+                    .push(ScrubError::MissingErasureMeta { fec_set_index });
+            }
+        }
+        Ok(report)
+    }
+
+    /// Returns the merkle root recorded in the `MerkleRootMeta` of the erasure
+    /// set at `fec_set_index` of `slot`.
+    pub fn get_erasure_set_merkle_root(
+        &self,
+        slot: Slot,
+        fec_set_index: u32,
+    ) -> Result<Option<Hash>> {
+        Ok(self
+            .merkle_root_meta(ErasureSetId::new(slot, fec_set_index))?
+            .and_then(|meta| meta.merkle_root()))
+    }
+
+    /// Replaces the shreds of rooted `slot` with `data_shreds`, which must be
+    /// the complete set of data shreds of the slot.
+    ///
+    /// This is used to restore a slot that failed a scrub from shreds fetched
+    /// over repair. The caller is responsible for verifying the shreds, since
+    /// they are inserted as trusted: rooted slots are otherwise rejected by
+    /// shred insertion. The root marker and the child links of the slot are
+    /// kept, and its `Index` and `MerkleRootMeta` are rebuilt from
+    /// `data_shreds`. The coding shreds and `ErasureMeta` of the slot are
+    /// removed, since they can not be rebuilt from the data shreds alone.
+    ///
+    /// The purge of the old shreds and the insertion of the new ones are
+    /// written in a single batch.
+    pub fn replace_rooted_slot_shreds(&self, slot: Slot, data_shreds: Vec<Shred>) -> Result<()> {
+        let lock = self.insert_shreds_lock.lock().unwrap();
+        let Some(old_slot_meta) = self.meta(slot)? else {
+            return Err(BlockstoreError::SlotUnavailable);
+        };
+        let parent_slot = data_shreds
+            .first()
+            .ok_or(BlockstoreError::MissingShred(slot, 0))?
+            .parent()
+            .map_err(|err| BlockstoreError::InvalidShredData(err.to_string()))?;
+
+        let mut write_batch = self.get_write_batch();
+        self.data_shred_cf
+            .delete_range_in_batch(&mut write_batch, slot, slot);
+        self.code_shred_cf
+            .delete_range_in_batch(&mut write_batch, slot, slot);
+        self.index_cf
+            .delete_range_in_batch(&mut write_batch, slot, slot);
+        self.erasure_meta_cf
+            .delete_range_in_batch(&mut write_batch, slot, slot);
+        self.merkle_root_meta_cf
+            .delete_range_in_batch(&mut write_batch, slot, slot);
+
+        // Insertion would otherwise load the metadata of the old shreds, which
+        // is only deleted once the batch is written. Start it from the state
+        // the slot has after the purge instead.
+        let mut shred_insertion_tracker =
+            ShredInsertionTracker::new(data_shreds.len(), &mut write_batch);
+        let mut slot_meta = old_slot_meta;
+        slot_meta.clear_unconfirmed_slot();
+        let cleared_slot_meta = slot_meta.clone();
+        slot_meta.parent_slot = Some(parent_slot);
+        let mut slot_meta_entry =
+            SlotMetaWorkingSetEntry::new(Rc::new(RefCell::new(slot_meta)), Some(cleared_slot_meta));
+        // The cleared slot meta is written even if no shred gets inserted
+        slot_meta_entry.did_insert_occur = true;
+        shred_insertion_tracker
+            .slot_meta_working_set
+            .insert((BlockLocation::Original, slot), slot_meta_entry);
+        shred_insertion_tracker.index_working_set.insert(
+            (BlockLocation::Original, slot),
+            IndexMetaWorkingSetEntry {
+                index: Index::new(slot),
+                did_insert_occur: false,
+            },
+        );
+        for shred in &data_shreds {
+            shred_insertion_tracker
+                .merkle_root_metas
+                .entry((BlockLocation::Original, shred.erasure_set()))
+                .or_insert_with(|| WorkingEntry::Dirty(MerkleRootMeta::from_shred(shred)));
+        }
+
+        let shreds = data_shreds.into_iter().map(|shred| {
+            (
+                Cow::Owned(shred),
+                /*is_repaired:*/ true,
+                BlockLocation::Original,
+            )
+        });
+        let mut pinnable_slice = self.new_pinnable_slice();
+        self.do_insert_shreds_with_tracker(
+            &lock,
+            shreds,
+            true, // is_trusted
+            None, // shred_recovery_context
+            &mut pinnable_slice,
+            shred_insertion_tracker,
+            &mut BlockstoreInsertionMetrics::default(),
+        )?;
+        Ok(())
+    }
+
+    /// Scrubs every rooted slot in `[starting_slot, ending_slot]`.
+    ///
+    /// `get_leader` is queried for each slot to decide whether shred
+    /// signatures can be verified. `on_report` is invoked with the report of
+    /// every scrubbed slot. The scrub stops early if `exit` is set.
+    pub fn scrub_rooted_slots<L, F>(
+        &self,
+        starting_slot: Slot,
+        ending_slot: Slot,
+        get_leader: L,
+        mut on_report: F,
+        exit: &AtomicBool,
+    ) -> Result<ScrubStats>
+    where
+        L: Fn(Slot) -> Option<SlotLeader>,
+        F: FnMut(&SlotScrubReport),
+    {
+        let starting_slot = starting_slot.max(self.lowest_cleanup_slot());
+        let mut stats = ScrubStats::default();
+        for slot in self
+            .rooted_slot_iterator(starting_slot)?
+            .take_while(|slot| *slot <= ending_slot)
+        {
+            if exit.load(Ordering::Relaxed) {
+                break;
+            }
+            let leader = get_leader(slot);
+            let report = self.scrub_slot(slot, leader.as_ref())?;
+            stats.accumulate(&report);
+            on_report(&report);
+        }
+        Ok(stats)
+    }
+
+    // Checks a single stored shred, records it in `erasure_sets` and returns
+    // the deserialized shred if it is usable for further checks.
+    fn scrub_shred(
+        slot: Slot,
+        key_index: u64,
+        shred_type: ShredType,
+        payload: &[u8],
+        leader: Option<&SlotLeader>,
+        erasure_sets: &mut BTreeMap<u32, ErasureSetState>,
+        report: &mut SlotScrubReport,
+    ) -> Option<Shred> {
+        let shred = match Shred::new_from_serialized_shred(payload.to_vec())
+            .and_then(|shred| shred.sanitize().map(|()| shred))
+        {
+            Ok(shred) => shred,
+            Err(err) => {
+                report.errors.push(ScrubError::InvalidShred {
+                    index: key_index,
+                    shred_type,
+                    reason: err.to_string(),
+                });
+                return None;
+            }
+        };
+        if shred.slot() != slot
+            || u64::from(shred.index()) != key_index
+            || shred.shred_type() != shred_type
+        {
+            report.errors.push(ScrubError::MisplacedShred {
+                index: key_index,
+                shred_type,
+                header_index: shred.index(),
+            });
+            return None;
+        }
+        if let Some(leader) = leader
+            && !shred.verify(&leader.id)
+        {
+            report.errors.push(ScrubError::InvalidSignature {
+                index: key_index,
+                shred_type,
+            });
+        }
+        let fec_set_index = shred.fec_set_index();
+        let state = erasure_sets.entry(fec_set_index).or_default();
+        state.has_coding_shreds |= shred.is_code();
+        match shred.merkle_root() {
+            Ok(merkle_root) => match state.merkle_root {
+                None => state.merkle_root = Some(merkle_root),
+                Some(expected) if expected != merkle_root => {
+                    report.errors.push(ScrubError::MerkleRootMismatch {
+                        fec_set_index,
+                        index: key_index,
+                        shred_type,
+                    });
+                }
+                Some(_) => (),
+            },
+            Err(err) => report.errors.push(ScrubError::InvalidShred {
+                index: key_index,
+                shred_type,
+                reason: err.to_string(),
+            }),
+        }
+        Some(shred)
+    }
+
+    // Compares the shred indexes recorded in `Index` against those present in
+    // the corresponding shred column.
+    fn scrub_index(
+        shred_index: &ShredIndex,
+        present: &[u64],
+        shred_type: ShredType,
+        errors: &mut Vec<ScrubError>,
+    ) {
+        let present_set: HashSet<u64> = present.iter().copied().collect();
+        errors.extend(
+            present
+                .iter()
+                .filter(|index| !shred_index.contains(**index))
+                .map(|&index| ScrubError::UnindexedShred { index, shred_type }),
+        );
+        errors.extend(
+            shred_index
+                .range(..)
+                .filter(|index| !present_set.contains(index))
+                .map(|index| ScrubError::MissingShred { index, shred_type }),
+        );
+    }
+
+    // Compares `SlotMeta` progress fields against the sorted data shred
+    // indexes present in the slot.
+    fn scrub_slot_meta(slot_meta: &SlotMeta, data_indexes: &[u64], errors: &mut Vec<ScrubError>) {
+        let consumed = data_indexes
+            .iter()
+            .enumerate()
+            .take_while(|(position, index)| *position as u64 == **index)
+            .count() as u64;
+        if slot_meta.consumed != consumed {
+            errors.push(ScrubError::SlotMetaMismatch {
+                field: "consumed",
+                expected: consumed,
+                actual: slot_meta.consumed,
+            });
+        }
+        let received = data_indexes.last().map(|index| index + 1).unwrap_or(0);
+        if slot_meta.received != received {
+            errors.push(ScrubError::SlotMetaMismatch {
+                field: "received",
+                expected: received,
+                actual: slot_meta.received,
+            });
+        }
+        if let Some(last_index) = slot_meta.last_index
+            && slot_meta.is_full()
+            && consumed != last_index + 1
+        {
+            errors.push(ScrubError::IncompleteFullSlot {
+                last_index,
+                num_data_shreds: data_indexes.len() as u64,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::get_tmp_ledger_path_auto_delete};
+
+    #[test]
+    fn test_scrub_slot_consistent() {
+        let ledger_path = get_tmp_ledger_path_auto_delete!();
+        let blockstore = Blockstore::open(ledger_path.path()).unwrap();
+
+        let (shreds, _) = make_many_slot_entries(0, 3, 10);
+        blockstore.insert_shreds(shreds, false).unwrap();
+
+        for slot in 0..3 {
+            let report = blockstore.scrub_slot(slot, None).unwrap();
+            assert!(report.is_ok(), "{:?}", report.errors);
+            assert!(report.num_data_shreds > 0);
+            assert!(report.num_erasure_sets > 0);
+            assert!(!report.verified_signatures);
+        }
+    }
+
+    #[test]
+    fn test_scrub_slot_missing_meta() {
+        let ledger_path = get_tmp_ledger_path_auto_delete!();
+        let blockstore = Blockstore::open(ledger_path.path()).unwrap();
+
+        let report = blockstore.scrub_slot(7, None).unwrap();
+        assert_eq!(report.errors, vec![ScrubError::MissingSlotMeta]);
+    }
+
+    #[test]
+    fn test_scrub_slot_missing_data_shred() {
+        let ledger_path = get_tmp_ledger_path_auto_delete!();
+        let blockstore = Blockstore::open(ledger_path.path()).unwrap();
+
+        let (shreds, _) = make_slot_entries(1, 0, 10);
+        let num_shreds = shreds.len() as u64;
+        assert!(num_shreds > 2);
+        blockstore.insert_shreds(shreds, false).unwrap();
+        blockstore.data_shred_cf.delete((1, 1)).unwrap();
+
+        let report = blockstore.scrub_slot(1, None).unwrap();
+        assert!(report.errors.contains(&ScrubError::MissingShred {
+            index: 1,
+            shred_type: ShredType::Data,
+        }));
+        assert!(report.errors.contains(&ScrubError::SlotMetaMismatch {
+            field: "consumed",
+            expected: 1,
+            actual: num_shreds,
+        }));
+        assert!(report.errors.contains(&ScrubError::IncompleteFullSlot {
+            last_index: num_shreds - 1,
+            num_data_shreds: num_shreds - 1,
+        }));
+    }
+
+    #[test]
+    fn test_scrub_slot_corrupted_shred() {
+        let ledger_path = get_tmp_ledger_path_auto_delete!();
+        let blockstore = Blockstore::open(ledger_path.path()).unwrap();
+
+        let (shreds, _) = make_slot_entries(1, 0, 10);
+        let mut payload = shreds[0].payload().to_vec();
+        blockstore.insert_shreds(shreds, false).unwrap();
+
+        // Flip a byte in the middle of the data section so the shred still
+        // deserializes but its merkle proof no longer matches its siblings.
+        let offset = payload.len() / 2;
+        payload[offset] ^= 0xff;
+        blockstore
+            .data_shred_cf
+            .put_bytes((1, 0), &payload)
+            .unwrap();
+
+        let report = blockstore.scrub_slot(1, None).unwrap();
+        assert!(!report.is_ok());
+        assert!(report.errors.iter().any(|err| matches!(
+            err,
+            ScrubError::MerkleRootMismatch { .. }
+                | ScrubError::MerkleRootMetaMismatch { .. }
+                | ScrubError::InvalidShred { .. }
+        )));
+    }
+
+    #[test]
+    fn test_scrub_slot_invalid_signature() {
+        let ledger_path = get_tmp_ledger_path_auto_delete!();
+        let blockstore = Blockstore::open(ledger_path.path()).unwrap();
+
+        let (shreds, _) = make_slot_entries(1, 0, 10);
+        let num_shreds = shreds.len();
+        blockstore.insert_shreds(shreds, false).unwrap();
+
+        // Test shreds are signed by a random keypair, so any other leader
+        // must fail signature verification on every shred.
+        let leader = SlotLeader::new_unique();
+        let report = blockstore.scrub_slot(1, Some(&leader)).unwrap();
+        assert!(report.verified_signatures);
+        assert_eq!(
+            report
+                .errors
+                .iter()
+                .filter(|err| matches!(err, ScrubError::InvalidSignature { .. }))
+                .count(),
+            num_shreds
+        );
+    }
+
+    #[test]
+    fn test_scrub_rooted_slots() {
+        let ledger_path = get_tmp_ledger_path_auto_delete!();
+        let blockstore = Blockstore::open(ledger_path.path()).unwrap();
+
+        let (shreds, _) = make_many_slot_entries(0, 5, 10);
+        blockstore.insert_shreds(shreds, false).unwrap();
+        blockstore.set_roots([0, 1, 2, 3].iter()).unwrap();
+        blockstore.data_shred_cf.delete((2, 0)).unwrap();
+
+        let mut reported = vec![];
+        let stats = blockstore
+            .scrub_rooted_slots(
+                0,
+                Slot::MAX,
+                |_| None,
+                |report| reported.push(report.slot),
+                &AtomicBool::new(false),
+            )
+            .unwrap();
+        assert_eq!(reported, vec![0, 1, 2, 3]);
+        assert_eq!(stats.num_slots, 4);
+        assert_eq!(stats.bad_slots, vec![2]);
+    }
+
+    #[test]
+    fn test_replace_rooted_slot_shreds() {
+        let ledger_path = get_tmp_ledger_path_auto_delete!();
+        let blockstore = Blockstore::open(ledger_path.path()).unwrap();
+
+        let (shreds, _) = make_many_slot_entries(0, 3, 10);
+        let data_shreds: Vec<_> = shreds
+            .iter()
+            .filter(|shred| shred.slot() == 1 && shred.is_data())
+            .cloned()
+            .collect();
+        blockstore.insert_shreds(shreds, false).unwrap();
+        blockstore.set_roots([0, 1, 2].iter()).unwrap();
+        blockstore.data_shred_cf.delete((1, 0)).unwrap();
+        assert!(!blockstore.scrub_slot(1, None).unwrap().is_ok());
+
+        blockstore
+            .replace_rooted_slot_shreds(1, data_shreds.clone())
+            .unwrap();
+
+        let report = blockstore.scrub_slot(1, None).unwrap();
+        assert!(report.is_ok(), "{:?}", report.errors);
+        assert_eq!(report.num_data_shreds, data_shreds.len() as u64);
+        assert!(blockstore.is_root(1));
+        let slot_meta = blockstore.meta(1).unwrap().unwrap();
+        assert!(slot_meta.is_full());
+        assert_eq!(slot_meta.parent_slot, Some(0));
+        assert_eq!(slot_meta.next_slots, vec![2]);
+    }
+
+    #[test]
+    fn test_replace_rooted_slot_shreds_with_other_block() {
+        let ledger_path = get_tmp_ledger_path_auto_delete!();
+        let blockstore = Blockstore::open(ledger_path.path()).unwrap();
+
+        let (shreds, _) = make_many_slot_entries(0, 3, 10);
+        blockstore.insert_shreds(shreds, false).unwrap();
+        blockstore.set_roots([0, 1, 2].iter()).unwrap();
+
+        // A failed replacement leaves the stored shreds in place
+        assert!(blockstore.replace_rooted_slot_shreds(1, vec![]).is_err());
+        assert!(blockstore.scrub_slot(1, None).unwrap().is_ok());
+
+        let (data_shreds, _) = make_slot_entries(1, 0, 20);
+        let merkle_root = data_shreds[0].merkle_root().unwrap();
+        blockstore
+            .replace_rooted_slot_shreds(1, data_shreds.clone())
+            .unwrap();
+
+        let report = blockstore.scrub_slot(1, None).unwrap();
+        assert!(report.is_ok(), "{:?}", report.errors);
+        assert_eq!(report.num_data_shreds, data_shreds.len() as u64);
+        assert_eq!(
+            blockstore.get_erasure_set_merkle_root(1, 0).unwrap(),
+            Some(merkle_root)
+        );
+        for shred in &data_shreds {
+            assert_eq!(
+                blockstore
+                    .get_data_shred(1, u64::from(shred.index()))
+                    .unwrap()
+                    .as_deref(),
+                Some(shred.payload().as_ref())
+            );
+        }
+    }
+}
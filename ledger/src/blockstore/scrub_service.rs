@@ -0,0 +1,146 @@
+//! The `BlockstoreScrubService` walks rooted slots in the background and checks
+//! that the shred columns agree with their metadata columns. See
+//! [`Blockstore::scrub_slot`] for the individual checks.
+//!
+//! The service starts at the lowest slot in the ledger and then follows the
+//! latest root. Slots that fail a check are logged, reported as metrics and
+//! optionally forwarded over a channel so that the caller can act on them,
+//! e.g. the validator re-fetches them through repair.
+
+use {
+    crate::{blockstore::Blockstore, leader_schedule_cache::LeaderScheduleCache},
+    crossbeam_channel::Sender,
+    solana_clock::Slot,
+    solana_measure::measure::Measure,
+    std::{
+        string::ToString,
+        sync::{
+            Arc,
+            atomic::{AtomicBool, Ordering},
+        },
+        thread::{self, Builder, JoinHandle},
+        time::{Duration, Instant},
+    },
+};
+
+// Scrubbing reads every shred of a slot, so cap the work done per pass in
+// order to bound the extra read load that the service puts on the blockstore.
+const MAX_SLOTS_PER_SCRUB_PASS: u64 = 64;
+// Rooted slots only need to be checked once, and roots advance roughly every
+// 400ms. There is no need to check for new roots more often than this.
+const CHECK_FOR_SCRUB_INTERVAL: Duration = Duration::from_secs(10);
+
+pub struct BlockstoreScrubService {
+    t_scrub: JoinHandle<()>,
+}
+
+impl BlockstoreScrubService {
+    pub fn new(
+        blockstore: Arc<Blockstore>,
+        leader_schedule_cache: Arc<LeaderScheduleCache>,
+        bad_slots_sender: Option<Sender<Slot>>,
+        exit: Arc<AtomicBool>,
+    ) -> Self {
+        let t_scrub = Builder::new()
+            .name("solBstoreScrub".to_string())
+            .spawn(move || {
+                info!("BlockstoreScrubService has started");
+                let mut next_slot = blockstore.lowest_slot();
+                let mut last_check_time = Instant::now();
+                loop {
+                    if exit.load(Ordering::Relaxed) {
+                        break;
+                    }
+
+                    if last_check_time.elapsed() > CHECK_FOR_SCRUB_INTERVAL {
+                        next_slot = Self::scrub_pass(
+                            &blockstore,
+                            &leader_schedule_cache,
+                            bad_slots_sender.as_ref(),
+                            next_slot,
+                            &exit,
+                        );
+                        last_check_time = Instant::now();
+                    }
+
+                    // Sleep for 1 second instead of CHECK_FOR_SCRUB_INTERVAL
+                    // so that this thread can respond to the exit flag toggling
+                    // in a timely manner
+                    thread::sleep(Duration::from_secs(1));
+                }
+                info!("BlockstoreScrubService has stopped");
+            })
+            .unwrap();
+        Self { t_scrub }
+    }
+
+    // Scrubs up to MAX_SLOTS_PER_SCRUB_PASS rooted slots starting at
+    // `next_slot` and returns the slot that the next pass should start at.
+    fn scrub_pass(
+        blockstore: &Blockstore,
+        leader_schedule_cache: &LeaderScheduleCache,
+        bad_slots_sender: Option<&Sender<Slot>>,
+        next_slot: Slot,
+        exit: &AtomicBool,
+    ) -> Slot {
+        let max_root = blockstore.max_root();
+        if next_slot > max_root {
+            return next_slot;
+        }
+        let ending_slot = max_root.min(next_slot.saturating_add(MAX_SLOTS_PER_SCRUB_PASS - 1));
+
+        let mut scrub_time = Measure::start("scrub_time");
+        let result = blockstore.scrub_rooted_slots(
+            next_slot,
+            ending_slot,
+            |slot| leader_schedule_cache.slot_leader_at(slot, None),
+            |report| {
+                if report.is_ok() {
+                    return;
+                }
+                for error in &report.errors {
+                    warn!("Blockstore scrub of slot {} failed: {error}", report.slot);
+                }
+                datapoint_error!(
+                    "blockstore-scrub-bad-slot",
+                    ("slot", report.slot as i64, i64),
+                    ("num_errors", report.errors.len() as i64, i64),
+                );
+                // Don't hold up the scrub if the receiver is busy repairing
+                // earlier slots; a dropped slot is still logged above
+                if let Some(sender) = bad_slots_sender
+                    && sender.try_send(report.slot).is_err()
+                {
+                    warn!(
+                        "Dropping bad slot {} instead of requesting its repair",
+                        report.slot
+                    );
+                }
+            },
+            exit,
+        );
+        scrub_time.stop();
+
+        match result {
+            Ok(stats) => {
+                stats.report(next_slot, ending_slot);
+                debug!(
+                    "Scrubbed {} rooted slots in [{next_slot}, {ending_slot}], {} bad, \
+                     {scrub_time}",
+                    stats.num_slots,
+                    stats.bad_slots.len(),
+                );
+                ending_slot + 1
+            }
+            Err(err) => {
+                // Retry the same range on the next pass
+                error!("Blockstore scrub of slots [{next_slot}, {ending_slot}] failed: {err:?}");
+                next_slot
+            }
+        }
+    }
+
+    pub fn join(self) -> thread::Result<()> {
+        self.t_scrub.join()
+    }
+}
@@ -21,6 +21,8 @@ pub fn safe_clone_config(config: &ValidatorConfig) -> ValidatorConfig {
         snapshot_config: config.snapshot_config.clone(),
         blockstore_cleanup_strategy: config.blockstore_cleanup_strategy,
         blockstore_options: config.blockstore_options.clone(),
+        enable_blockstore_scrub: config.enable_blockstore_scrub,
+        blockstore_scrub_repair: config.blockstore_scrub_repair,
         broadcast_stage_type: config.broadcast_stage_type.clone(),
         turbine_mode: config.turbine_mode.clone(),
         fixed_leader_schedule: config.fixed_leader_schedule.clone(),
@@ -39,6 +41,8 @@ pub fn safe_clone_config(config: &ValidatorConfig) -> ValidatorConfig {
         vote_history_storage: config.vote_history_storage.clone(),
         debug_keys: config.debug_keys.clone(),
         filter_keys: config.filter_keys.clone(),
+        program_cache_prewarm: config.program_cache_prewarm.clone(),
+        jit_cache_dir: config.jit_cache_dir.clone(),
         contact_debug_interval: config.contact_debug_interval,
         contact_save_interval: config.contact_save_interval,
         send_transaction_service_config: config.send_transaction_service_config.clone(),
@@ -0,0 +1,237 @@
+//! Recording of where the compute units of a transaction are spent
+
+use {solana_pubkey::Pubkey, solana_transaction_context::instruction::InstructionContext};
+
+/// Compute units consumed by a transaction, per instruction and per syscall
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ComputeProfile {
+    /// One entry per instruction, including CPIs, in the order they were invoked
+    pub instructions: Vec<InstructionComputeProfile>,
+}
+
+impl ComputeProfile {
+    /// Syscall counts and compute units summed over all instructions
+    pub fn syscall_totals(&self) -> Vec<SyscallComputeProfile> {
+        let mut totals: Vec<SyscallComputeProfile> = Vec::new();
+        for syscall in self
+            .instructions
+            .iter()
+            .flat_map(|instruction| &instruction.syscalls)
+        {
+            match totals.iter_mut().find(|total| total.name == syscall.name) {
+                Some(total) => {
+                    total.count = total.count.saturating_add(syscall.count);
+                    total.compute_units = total.compute_units.saturating_add(syscall.compute_units);
+                }
+                None => totals.push(syscall.clone()),
+            }
+        }
+        totals.sort_by(|a, b| b.compute_units.cmp(&a.compute_units));
+        totals
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstructionComputeProfile {
+    pub index_in_trace: usize,
+    /// Top level instructions are at `TRANSACTION_LEVEL_STACK_HEIGHT`
+    pub stack_height: usize,
+    pub program_id: Pubkey,
+    /// Compute units consumed by this instruction, including those consumed by
+    /// the instructions it invoked
+    pub compute_units_consumed: u64,
+    /// Syscalls made by this instruction, not including those made by the
+    /// instructions it invoked
+    pub syscalls: Vec<SyscallComputeProfile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyscallComputeProfile {
+    pub name: String,
+    pub count: u64,
+    /// Compute units charged by the syscall itself, i.e. not including those
+    /// consumed by instructions invoked through it
+    pub compute_units: u64,
+}
+
+struct ProfiledFrame {
+    /// Index into `ComputeProfiler::instructions`
+    instruction: usize,
+    remaining_units_at_entry: u64,
+    /// Compute units consumed by the instructions this one invoked so far
+    invoked_units: u64,
+}
+
+/// State captured when a syscall starts, to compute what it charged
+pub struct SyscallProfileStart {
+    remaining_units: u64,
+    invoked_units: u64,
+}
+
+/// Builds a `ComputeProfile` from the instruction and syscall events of an
+/// `InvokeContext`
+#[derive(Default)]
+pub struct ComputeProfiler {
+    instructions: Vec<InstructionComputeProfile>,
+    stack: Vec<ProfiledFrame>,
+}
+
+impl ComputeProfiler {
+    pub(crate) fn enter_instruction(
+        &mut self,
+        instruction_context: &InstructionContext,
+        remaining_units: u64,
+    ) {
+        self.stack.push(ProfiledFrame {
+            instruction: self.instructions.len(),
+            remaining_units_at_entry: remaining_units,
+            invoked_units: 0,
+        });
+        self.instructions.push(InstructionComputeProfile {
+            index_in_trace: instruction_context.get_index_in_trace(),
+            stack_height: instruction_context.get_stack_height(),
+            program_id: instruction_context
+                .get_program_key()
+                .copied()
+                .unwrap_or_default(),
+            compute_units_consumed: 0,
+            syscalls: Vec::new(),
+        });
+    }
+
+    pub(crate) fn exit_instruction(&mut self, remaining_units: u64) {
+        let Some(frame) = self.stack.pop() else {
+            return;
+        };
+        let compute_units_consumed = frame
+            .remaining_units_at_entry
+            .saturating_sub(remaining_units);
+        if let Some(instruction) = self.instructions.get_mut(frame.instruction) {
+            instruction.compute_units_consumed = compute_units_consumed;
+        }
+        if let Some(caller) = self.stack.last_mut() {
+            caller.invoked_units = caller.invoked_units.saturating_add(compute_units_consumed);
+        }
+    }
+
+    pub(crate) fn start_syscall(&self, remaining_units: u64) -> SyscallProfileStart {
+        SyscallProfileStart {
+            remaining_units,
+            invoked_units: self.stack.last().map_or(0, |frame| frame.invoked_units),
+        }
+    }
+
+    pub(crate) fn finish_syscall(
+        &mut self,
+        name: &str,
+        start: SyscallProfileStart,
+        remaining_units: u64,
+    ) {
+        let Some(frame) = self.stack.last() else {
+            return;
+        };
+        let invoked_units = frame.invoked_units.saturating_sub(start.invoked_units);
+        let compute_units = start
+            .remaining_units
+            .saturating_sub(remaining_units)
+            .saturating_sub(invoked_units);
+        let Some(instruction) = self.instructions.get_mut(frame.instruction) else {
+            return;
+        };
+        match instruction
+            .syscalls
+            .iter_mut()
+            .find(|syscall| syscall.name == name)
+        {
+            Some(syscall) => {
+                syscall.count = syscall.count.saturating_add(1);
+                syscall.compute_units = syscall.compute_units.saturating_add(compute_units);
+            }
+            None => instruction.syscalls.push(SyscallComputeProfile {
+                name: name.to_string(),
+                count: 1,
+                compute_units,
+            }),
+        }
+    }
+
+    /// Instructions still on the stack, e.g. when execution was aborted, are
+    /// charged up to `remaining_units`
+    pub(crate) fn finish(mut self, remaining_units: u64) -> ComputeProfile {
+        while !self.stack.is_empty() {
+            self.exit_instruction(remaining_units);
+        }
+        ComputeProfile {
+            instructions: self.instructions,
+        }
+    }
+}
+
+/// Strips the module paths from a type name, e.g. `a::SyscallHash<b::Sha256Hasher>`
+/// becomes `SyscallHash<Sha256Hasher>`
+pub fn short_type_name(type_name: &str) -> String {
+    let mut short_name = String::with_capacity(type_name.len());
+    let mut segment_start = 0;
+    for (index, c) in type_name.char_indices() {
+        if c.is_alphanumeric() || c == '_' || c == ':' {
+            continue;
+        }
+        short_name.push_str(last_path_segment(
+            type_name.get(segment_start..index).unwrap_or_default(),
+        ));
+        short_name.push(c);
+        segment_start = index.saturating_add(c.len_utf8());
+    }
+    short_name.push_str(last_path_segment(
+        type_name.get(segment_start..).unwrap_or_default(),
+    ));
+    short_name
+}
+
+fn last_path_segment(path: &str) -> &str {
+    path.rsplit("::").next().unwrap_or(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_short_type_name() {
+        assert_eq!(short_type_name("SyscallLog"), "SyscallLog");
+        assert_eq!(
+            short_type_name("solana_syscalls::logging::SyscallLog"),
+            "SyscallLog"
+        );
+        assert_eq!(
+            short_type_name("solana_syscalls::SyscallHash<solana_syscalls::Sha256Hasher>"),
+            "SyscallHash<Sha256Hasher>"
+        );
+    }
+
+    #[test]
+    fn test_syscall_totals() {
+        let syscall = |name: &str, count, compute_units| SyscallComputeProfile {
+            name: name.to_string(),
+            count,
+            compute_units,
+        };
+        let instruction = |syscalls| InstructionComputeProfile {
+            index_in_trace: 0,
+            stack_height: 1,
+            program_id: Pubkey::new_unique(),
+            compute_units_consumed: 1000,
+            syscalls,
+        };
+        let profile = ComputeProfile {
+            instructions: vec![
+                instruction(vec![syscall("a", 1, 10), syscall("b", 2, 200)]),
+                instruction(vec![syscall("a", 3, 300)]),
+            ],
+        };
+        assert_eq!(
+            profile.syscall_totals(),
+            vec![syscall("a", 4, 310), syscall("b", 2, 200)]
+        );
+    }
+}
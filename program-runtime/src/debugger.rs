@@ -0,0 +1,117 @@
+//! Interactive debugging of SBF programs over the GDB remote protocol
+//!
+//! A debugger session runs the program in the interpreter and stops at its
+//! first instruction until a debugger connects to the configured port.
+
+use {
+    crate::memory_context::SerializedAccountMetadata,
+    solana_instruction::error::InstructionError,
+    solana_pubkey::Pubkey,
+    solana_sbpf::ebpf,
+    solana_transaction_context::{
+        IndexOfAccount, instruction::InstructionContext, transaction::TransactionContext,
+    },
+    std::{collections::HashSet, fmt::Write},
+};
+
+/// Stack height of the instructions of a transaction, as opposed to the
+/// programs they invoke
+const TRANSACTION_LEVEL_STACK_HEIGHT: usize = 1;
+
+/// Selects the program invocations that are debugged
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DebuggerConfig {
+    /// Port the debugger connects to. Each debugged invocation waits for its
+    /// own connection.
+    pub port: u16,
+    /// Also debug programs invoked through CPI, not only the instructions of
+    /// the transaction
+    pub break_on_cpi: bool,
+    /// Only debug these programs, all programs if empty
+    pub program_ids: HashSet<Pubkey>,
+}
+
+impl DebuggerConfig {
+    /// Debugs every invocation, including CPIs
+    pub fn new(port: u16) -> Self {
+        Self {
+            port,
+            break_on_cpi: true,
+            program_ids: HashSet::new(),
+        }
+    }
+
+    /// Whether the invocation of `program_id` at `stack_height` is debugged
+    pub fn should_break(&self, program_id: &Pubkey, stack_height: usize) -> bool {
+        (self.break_on_cpi || stack_height <= TRANSACTION_LEVEL_STACK_HEIGHT)
+            && (self.program_ids.is_empty() || self.program_ids.contains(program_id))
+    }
+}
+
+/// Describes an invocation to the debugger, as `key=value` pairs separated by
+/// `;`: the program, its CPI level and caller, followed by where the
+/// serialized input of the program is mapped in the VM.
+///
+/// The input starts at `input`, the instruction data at `instruction_data`,
+/// and every instruction account is described by `account_<index>` with its
+/// key and the addresses of its serialized key, owner, lamports and data.
+pub fn debug_metadata(
+    transaction_context: &TransactionContext,
+    instruction_context: &InstructionContext,
+    accounts_metadata: &[SerializedAccountMetadata],
+    instruction_data_offset: usize,
+) -> Result<String, InstructionError> {
+    let caller = transaction_context
+        .get_instruction_stack_height()
+        .checked_sub(2)
+        .and_then(|nesting_level| {
+            transaction_context
+                .get_instruction_context_at_nesting_level(nesting_level)
+                .ok()
+        })
+        .and_then(|ctx| ctx.get_program_key().ok().map(|key| key.to_string()))
+        .unwrap_or_else(|| "none".into());
+    let mut metadata = format!(
+        "program_id={};cpi_level={};caller={caller};input={:#x};instruction_data={:#x}",
+        instruction_context.get_program_key()?,
+        instruction_context.get_stack_height().saturating_sub(1),
+        ebpf::MM_INPUT_START,
+        ebpf::MM_INPUT_START.saturating_add(instruction_data_offset as u64),
+    );
+    for (index, account) in accounts_metadata.iter().enumerate() {
+        let key = instruction_context.get_key_of_instruction_account(index as IndexOfAccount)?;
+        // Writing to a `String` cannot fail
+        let _ = write!(
+            metadata,
+            ";account_{index}={key},key={:#x},owner={:#x},lamports={:#x},data={:#x},data_len={}",
+            account.vm_key_addr,
+            account.vm_owner_addr,
+            account.vm_lamports_addr,
+            account.vm_data_addr,
+            account.original_data_len,
+        );
+    }
+    Ok(metadata)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_should_break() {
+        let program_id = Pubkey::new_unique();
+        let mut config = DebuggerConfig::new(9001);
+        assert!(config.should_break(&program_id, 1));
+        assert!(config.should_break(&program_id, 2));
+
+        config.break_on_cpi = false;
+        assert!(config.should_break(&program_id, 1));
+        assert!(!config.should_break(&program_id, 2));
+
+        config.program_ids.insert(Pubkey::new_unique());
+        assert!(!config.should_break(&program_id, 1));
+        config.program_ids.insert(program_id);
+        assert!(config.should_break(&program_id, 1));
+    }
+}
@@ -0,0 +1,300 @@
+//! Flamegraphs of SBF program execution, built from VM register traces
+//!
+//! Every entry of a register trace is one executed instruction, so the
+//! samples are counts of executed instructions. These are not compute units:
+//! the compute units charged by syscalls are not part of the trace.
+
+use {
+    crate::invoke_context::RegisterTrace,
+    solana_sbpf::{ebpf, elf::Executable as GenericExecutable, vm::ContextObject},
+    std::{
+        collections::{BTreeMap, HashMap, HashSet},
+        io::{self, Write},
+        path::Path,
+    },
+};
+
+/// Index of the program counter in a register trace entry
+const PC_REGISTER: usize = 11;
+
+/// Resolves the names of the functions of a program
+#[derive(Default)]
+pub struct Symbolizer {
+    /// Names from the symbol table of the ELF, by program counter
+    function_names: BTreeMap<usize, String>,
+    debug_info: Option<DebugInfo>,
+}
+
+struct DebugInfo {
+    loader: addr2line::Loader,
+    /// ELF address of the first instruction of the text section
+    text_address: u64,
+}
+
+impl Symbolizer {
+    /// Uses the function names of the executable's symbol table
+    pub fn from_executable<C: ContextObject>(executable: &GenericExecutable<C>) -> Self {
+        let function_names = executable
+            .get_function_registry()
+            .iter()
+            .filter(|(_key, (name, _pc))| !name.is_empty())
+            .map(|(_key, (name, pc))| {
+                let name = String::from_utf8_lossy(name);
+                (pc, addr2line::demangle_auto(name, None).into_owned())
+            })
+            .collect();
+        Self {
+            function_names,
+            debug_info: None,
+        }
+    }
+
+    /// Prefers the DWARF debug info and symbol table of the ELF file at
+    /// `elf_path` for naming functions
+    pub fn load_debug_info(&mut self, elf_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let loader = addr2line::Loader::new(elf_path)?;
+        let text_address = loader
+            .get_section_range(b".text")
+            .map(|range| range.begin)
+            .ok_or("missing .text section")?;
+        self.debug_info = Some(DebugInfo {
+            loader,
+            text_address,
+        });
+        Ok(())
+    }
+
+    /// Name of the function starting at `function_pc`
+    pub fn function_name(&self, function_pc: usize) -> String {
+        self.debug_info_function_name(function_pc)
+            .or_else(|| self.function_names.get(&function_pc).cloned())
+            .unwrap_or_else(|| format!("function_{function_pc}"))
+    }
+
+    fn debug_info_function_name(&self, function_pc: usize) -> Option<String> {
+        let debug_info = self.debug_info.as_ref()?;
+        let address = debug_info
+            .text_address
+            .saturating_add((function_pc as u64).saturating_mul(ebpf::INSN_SIZE as u64));
+        // Frames are ordered from the innermost inlined function outwards, at
+        // the entry of a function the outermost one is the function itself
+        let mut outermost = None;
+        if let Ok(mut frames) = debug_info.loader.find_frames(address) {
+            while let Ok(Some(frame)) = frames.next() {
+                if let Some(function) = frame.function
+                    && let Ok(name) = function.demangle()
+                {
+                    outermost = Some(name.into_owned());
+                }
+            }
+        }
+        outermost.or_else(|| {
+            debug_info
+                .loader
+                .find_symbol(address)
+                .map(|name| addr2line::demangle_auto(name.into(), None).into_owned())
+        })
+    }
+}
+
+/// Instructions executed per function
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionInstructions {
+    pub name: String,
+    /// Executed in the function itself
+    pub exclusive: u64,
+    /// Executed in the function and the functions it called
+    pub inclusive: u64,
+}
+
+/// Call stacks sampled at every executed instruction
+#[derive(Debug, Default)]
+pub struct Flamegraph {
+    stacks: HashMap<Vec<String>, u64>,
+}
+
+impl Flamegraph {
+    /// Adds the call stacks of `register_trace` below the `root` frame.
+    ///
+    /// Calls and returns are recovered from the program counters: a call
+    /// instruction followed by a jump enters a new function, and a jump back
+    /// to the instruction after the most recent call returns from it.
+    pub fn add_register_trace(
+        &mut self,
+        root: &str,
+        text: &[u8],
+        symbolizer: &Symbolizer,
+        register_trace: RegisterTrace,
+    ) {
+        let Some(first) = register_trace.first() else {
+            return;
+        };
+        let mut stack = vec![first[PC_REGISTER] as usize];
+        let mut return_pcs = Vec::new();
+        let mut samples: HashMap<Vec<usize>, u64> = HashMap::new();
+        let mut previous_pc: Option<usize> = None;
+        for registers in register_trace {
+            let pc = registers[PC_REGISTER] as usize;
+            if let Some(previous_pc) = previous_pc {
+                let next_pc = previous_pc.saturating_add(1);
+                if pc != next_pc {
+                    if is_call(text, previous_pc) {
+                        stack.push(pc);
+                        return_pcs.push(next_pc);
+                    } else if return_pcs.last() == Some(&pc) {
+                        stack.pop();
+                        return_pcs.pop();
+                    }
+                }
+            }
+            match samples.get_mut(stack.as_slice()) {
+                Some(count) => *count = count.saturating_add(1),
+                None => {
+                    samples.insert(stack.clone(), 1);
+                }
+            }
+            previous_pc = Some(pc);
+        }
+
+        let mut function_names = HashMap::new();
+        for (stack, count) in samples {
+            let names = std::iter::once(root.to_string())
+                .chain(stack.into_iter().map(|function_pc| {
+                    function_names
+                        .entry(function_pc)
+                        .or_insert_with(|| symbolizer.function_name(function_pc))
+                        .clone()
+                }))
+                .collect();
+            let total = self.stacks.entry(names).or_default();
+            *total = total.saturating_add(count);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stacks.is_empty()
+    }
+
+    /// Writes the call stacks in the folded format used by flamegraph tools,
+    /// one `frame;frame;frame count` line per distinct stack
+    pub fn write_folded<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let mut stacks: Vec<_> = self.stacks.iter().collect();
+        stacks.sort();
+        for (stack, count) in stacks {
+            let frames: Vec<_> = stack.iter().map(|frame| frame.replace(';', ",")).collect();
+            writeln!(writer, "{} {count}", frames.join(";"))?;
+        }
+        Ok(())
+    }
+
+    /// Instructions executed per function, most executed first. The root
+    /// frames are not included.
+    pub fn function_instructions(&self) -> Vec<FunctionInstructions> {
+        let mut functions: HashMap<&str, FunctionInstructions> = HashMap::new();
+        for (stack, count) in &self.stacks {
+            let Some((leaf, _)) = stack.split_last() else {
+                continue;
+            };
+            let mut seen = HashSet::new();
+            for name in stack.iter().skip(1) {
+                let function = functions
+                    .entry(name)
+                    .or_insert_with(|| FunctionInstructions {
+                        name: name.clone(),
+                        exclusive: 0,
+                        inclusive: 0,
+                    });
+                // Recursive functions are only counted once per stack
+                if seen.insert(name) {
+                    function.inclusive = function.inclusive.saturating_add(*count);
+                }
+            }
+            if stack.len() > 1
+                && let Some(function) = functions.get_mut(leaf.as_str())
+            {
+                function.exclusive = function.exclusive.saturating_add(*count);
+            }
+        }
+        let mut functions: Vec<_> = functions.into_values().collect();
+        functions.sort_by(|a, b| {
+            b.exclusive
+                .cmp(&a.exclusive)
+                .then_with(|| b.inclusive.cmp(&a.inclusive))
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        functions
+    }
+}
+
+fn is_call(text: &[u8], pc: usize) -> bool {
+    text.get(pc.saturating_mul(ebpf::INSN_SIZE))
+        .is_some_and(|opcode| matches!(*opcode, ebpf::CALL_IMM | ebpf::CALL_REG))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instruction(opcode: u8) -> [u8; ebpf::INSN_SIZE] {
+        [opcode, 0, 0, 0, 0, 0, 0, 0]
+    }
+
+    fn trace(pcs: &[usize]) -> Vec<[u64; 12]> {
+        pcs.iter()
+            .map(|pc| {
+                let mut registers = [0; 12];
+                registers[PC_REGISTER] = *pc as u64;
+                registers
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_flamegraph() {
+        // 0: mov, 1: call 4, 2: call 4, 3: exit, 4: mov, 5: exit
+        let text = [
+            instruction(ebpf::MOV64_IMM),
+            instruction(ebpf::CALL_IMM),
+            instruction(ebpf::CALL_IMM),
+            instruction(ebpf::EXIT),
+            instruction(ebpf::MOV64_IMM),
+            instruction(ebpf::EXIT),
+        ]
+        .concat();
+        let mut symbolizer = Symbolizer::default();
+        symbolizer
+            .function_names
+            .insert(0, "entrypoint".to_string());
+        symbolizer.function_names.insert(4, "helper".to_string());
+
+        let mut flamegraph = Flamegraph::default();
+        flamegraph.add_register_trace(
+            "program",
+            &text,
+            &symbolizer,
+            &trace(&[0, 1, 4, 5, 2, 4, 5, 3]),
+        );
+
+        let mut folded = Vec::new();
+        flamegraph.write_folded(&mut folded).unwrap();
+        assert_eq!(
+            String::from_utf8(folded).unwrap(),
+            "program;entrypoint 4\nprogram;entrypoint;helper 4\n"
+        );
+        assert_eq!(
+            flamegraph.function_instructions(),
+            vec![
+                FunctionInstructions {
+                    name: "entrypoint".to_string(),
+                    exclusive: 4,
+                    inclusive: 8,
+                },
+                FunctionInstructions {
+                    name: "helper".to_string(),
+                    exclusive: 4,
+                    inclusive: 4,
+                },
+            ]
+        );
+    }
+}
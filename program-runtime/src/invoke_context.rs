@@ -12,6 +12,8 @@ use {
 };
 use {
     crate::{
+        compute_profile::{ComputeProfile, ComputeProfiler, short_type_name},
+        debugger::DebuggerConfig,
         execution_budget::{SVMTransactionExecutionBudget, SVMTransactionExecutionCost},
         loaded_programs::{
             ProgramCacheForTxBatch, ProgramRuntimeEnvironment, ProgramRuntimeEnvironments,
@@ -62,6 +64,20 @@ pub type BuiltinFunctionRegisterer =
 pub type Executable = GenericExecutable<InvokeContext<'static, 'static>>;
 pub type RegisterTrace<'a> = &'a [[u64; 12]];
 
+/// Receives the VM register traces of SBF program invocations, as they are
+/// recorded. Traces are only recorded if the program runtime environment has
+/// its debugging features enabled.
+pub trait RegisterTraceInspector: Debug + Send + Sync {
+    /// `text` is the program's text section, which the traced program
+    /// counters index into
+    fn inspect_register_trace(
+        &self,
+        program_id: &Pubkey,
+        text: &[u8],
+        register_trace: RegisterTrace,
+    );
+}
+
 /// Adapter so we can unify the interfaces of built-in programs and syscalls
 #[macro_export]
 macro_rules! declare_process_instruction {
@@ -239,9 +255,12 @@ pub struct InvokeContext<'a, 'ix_data> {
     pub memory_contexts: MemoryContexts,
     /// Pairs of index in TX instruction trace and VM register trace
     register_traces: Vec<(usize, Vec<[u64; 12]>)>,
-    /// Debug port to use for this executing transaction.
-    #[cfg(feature = "sbpf-debugger")]
-    pub debug_port: Option<u16>,
+    /// Per-instruction compute unit accounting, only present if enabled
+    compute_profiler: Option<ComputeProfiler>,
+    /// Program invocations to host a debugger session for
+    pub debugger_config: Option<DebuggerConfig>,
+    /// Receives the register trace of every SBF program invocation
+    pub register_trace_inspector: Option<&'a dyn RegisterTraceInspector>,
 }
 
 impl<'a, 'ix_data> InvokeContext<'a, 'ix_data> {
@@ -265,11 +284,47 @@ impl<'a, 'ix_data> InvokeContext<'a, 'ix_data> {
             timings: ExecuteDetailsTimings::default(),
             memory_contexts: MemoryContexts::new(),
             register_traces: Vec::new(),
-            #[cfg(feature = "sbpf-debugger")]
-            debug_port: None,
+            compute_profiler: None,
+            debugger_config: None,
+            register_trace_inspector: None,
         }
     }
 
+    /// Record the compute units consumed by each instruction and syscall
+    pub fn enable_compute_profile_recording(&mut self) {
+        self.compute_profiler = Some(ComputeProfiler::default());
+    }
+
+    /// Returns the recorded compute profile, if recording was enabled
+    pub fn take_compute_profile(&mut self) -> Option<ComputeProfile> {
+        let remaining = self.compute_meter.0.get();
+        self.compute_profiler
+            .take()
+            .map(|compute_profiler| compute_profiler.finish(remaining))
+    }
+
+    /// Runs `syscall`, attributing the compute units it charged to the
+    /// current instruction in the compute profile
+    pub fn profile_syscall<T, E>(
+        &mut self,
+        type_name: &'static str,
+        syscall: impl FnOnce(&mut Self) -> Result<T, E>,
+    ) -> Result<T, E> {
+        let Some(start) = self
+            .compute_profiler
+            .as_ref()
+            .map(|compute_profiler| compute_profiler.start_syscall(self.compute_meter.0.get()))
+        else {
+            return syscall(self);
+        };
+        let result = syscall(self);
+        let remaining = self.compute_meter.0.get();
+        if let Some(compute_profiler) = self.compute_profiler.as_mut() {
+            compute_profiler.finish_syscall(&short_type_name(type_name), start, remaining);
+        }
+        result
+    }
+
     /// Push a stack frame onto the invocation stack
     #[cfg_attr(feature = "dev-context-only-utils", qualifiers(pub))]
     fn push(&mut self) -> Result<(), InstructionError> {
@@ -300,12 +355,21 @@ impl<'a, 'ix_data> InvokeContext<'a, 'ix_data> {
 
         self.transaction_context.push()?;
         self.memory_contexts.push_placeholder();
+        if let Some(compute_profiler) = self.compute_profiler.as_mut()
+            && let Ok(instruction_context) =
+                self.transaction_context.get_current_instruction_context()
+        {
+            compute_profiler.enter_instruction(&instruction_context, self.compute_meter.0.get());
+        }
         Ok(())
     }
 
     /// Pop a stack frame from the invocation stack
     #[cfg_attr(feature = "dev-context-only-utils", qualifiers(pub))]
     fn pop(&mut self) -> Result<(), InstructionError> {
+        if let Some(compute_profiler) = self.compute_profiler.as_mut() {
+            compute_profiler.exit_instruction(self.compute_meter.0.get());
+        }
         self.memory_contexts.pop();
         self.transaction_context.pop()
     }
@@ -818,6 +882,17 @@ impl<'a, 'ix_data> InvokeContext<'a, 'ix_data> {
         else {
             return;
         };
+        if let Some(register_trace_inspector) = self.register_trace_inspector
+            && let Ok(program_id) = instruction_context.get_program_key()
+            && let Some(entry) = self.program_cache_for_tx_batch.find(program_id)
+            && let ProgramCacheEntryType::Loaded(executable) = &entry.program
+        {
+            register_trace_inspector.inspect_register_trace(
+                program_id,
+                executable.get_text_bytes().1,
+                &register_trace,
+            );
+        }
         self.register_traces
             .push((instruction_context.get_index_in_trace(), register_trace));
     }
@@ -1135,9 +1210,12 @@ pub fn mock_process_instruction<F: FnMut(&mut InvokeContext), G: FnMut(&mut Invo
 mod tests {
     use {
         super::*,
-        crate::execution_budget::{
-            DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT, MAX_INSTRUCTION_STACK_DEPTH,
-            MAX_INSTRUCTION_STACK_DEPTH_SIMD_0268,
+        crate::{
+            compute_profile::{InstructionComputeProfile, SyscallComputeProfile},
+            execution_budget::{
+                DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT, MAX_INSTRUCTION_STACK_DEPTH,
+                MAX_INSTRUCTION_STACK_DEPTH_SIMD_0268,
+            },
         },
         openssl::{
             ec::{EcGroup, EcKey},
@@ -1595,6 +1673,112 @@ mod tests {
         invoke_context.pop().unwrap();
     }
 
+    #[test]
+    fn test_compute_profile_recording() {
+        let callee_program_id = solana_pubkey::new_rand();
+        let owned_account = AccountSharedData::new(42, 1, &callee_program_id);
+        let not_owned_account = AccountSharedData::new(84, 1, &solana_pubkey::new_rand());
+        let readonly_account = AccountSharedData::new(168, 1, &solana_pubkey::new_rand());
+        let mut program_account = AccountSharedData::new(1, 1, &native_loader::id());
+        program_account.set_executable(true);
+        let transaction_accounts = vec![
+            (solana_pubkey::new_rand(), owned_account),
+            (solana_pubkey::new_rand(), not_owned_account),
+            (solana_pubkey::new_rand(), readonly_account),
+            (callee_program_id, program_account),
+        ];
+        let metas = vec![
+            AccountMeta::new(transaction_accounts.first().unwrap().0, false),
+            AccountMeta::new(transaction_accounts.get(1).unwrap().0, false),
+            AccountMeta::new_readonly(transaction_accounts.get(2).unwrap().0, false),
+        ];
+        let instruction_accounts = (0..4)
+            .map(|instruction_account_index| {
+                InstructionAccount::new(
+                    instruction_account_index,
+                    false,
+                    instruction_account_index < 2,
+                )
+            })
+            .collect::<Vec<_>>();
+        with_mock_invoke_context!(invoke_context, transaction_context, transaction_accounts);
+        let mut program_cache_for_tx_batch = ProgramCacheForTxBatch::default();
+        program_cache_for_tx_batch.replenish(
+            callee_program_id,
+            Arc::new(ProgramCacheEntry::new_builtin(MockBuiltin::register)),
+        );
+        invoke_context.program_cache_for_tx_batch = &mut program_cache_for_tx_batch;
+        assert_eq!(invoke_context.take_compute_profile(), None);
+        invoke_context.enable_compute_profile_recording();
+
+        invoke_context
+            .transaction_context
+            .configure_top_level_instruction_for_tests(3, instruction_accounts, vec![])
+            .unwrap();
+        invoke_context.push().unwrap();
+        for _ in 0..2 {
+            invoke_context
+                .profile_syscall("solana_syscalls::logging::SyscallLog", |invoke_context| {
+                    invoke_context.compute_meter.consume_checked(3).unwrap();
+                    Ok::<_, InstructionError>(0)
+                })
+                .unwrap();
+        }
+        let inner_instruction = Instruction::new_with_bincode(
+            callee_program_id,
+            &MockInstruction::ConsumeComputeUnits {
+                compute_units_to_consume: 10,
+                desired_result: Ok(()),
+            },
+            metas,
+        );
+        invoke_context
+            .profile_syscall(
+                "solana_syscalls::cpi::SyscallInvokeSignedRust",
+                |invoke_context| {
+                    invoke_context.compute_meter.consume_checked(2).unwrap();
+                    invoke_context.prepare_next_cpi_instruction(inner_instruction, &[])?;
+                    invoke_context.process_instruction(&mut 0, &mut ExecuteTimings::default())
+                },
+            )
+            .unwrap();
+        invoke_context.pop().unwrap();
+
+        let inner_compute_units = 10 + MOCK_BUILTIN_COMPUTE_UNIT_COST;
+        assert_eq!(
+            invoke_context.take_compute_profile(),
+            Some(ComputeProfile {
+                instructions: vec![
+                    InstructionComputeProfile {
+                        index_in_trace: 0,
+                        stack_height: 1,
+                        program_id: callee_program_id,
+                        compute_units_consumed: 6 + 2 + inner_compute_units,
+                        syscalls: vec![
+                            SyscallComputeProfile {
+                                name: "SyscallLog".to_string(),
+                                count: 2,
+                                compute_units: 6,
+                            },
+                            SyscallComputeProfile {
+                                name: "SyscallInvokeSignedRust".to_string(),
+                                count: 1,
+                                compute_units: 2,
+                            },
+                        ],
+                    },
+                    InstructionComputeProfile {
+                        index_in_trace: 1,
+                        stack_height: 2,
+                        program_id: callee_program_id,
+                        compute_units_consumed: inner_compute_units,
+                        syscalls: vec![],
+                    },
+                ],
+            })
+        );
+    }
+
     #[test]
     fn test_invoke_context_compute_budget() {
         let transaction_accounts = vec![(solana_pubkey::new_rand(), AccountSharedData::default())];
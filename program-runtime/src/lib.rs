@@ -4,9 +4,13 @@
 #![deny(clippy::indexing_slicing)]
 
 pub use solana_sbpf;
+pub mod compute_profile;
 pub mod cpi;
+pub mod debugger;
 pub mod deploy;
 pub mod execution_budget;
+#[cfg(feature = "flamegraph")]
+pub mod flamegraph;
 pub mod invoke_context;
 pub mod loaded_programs;
 pub mod loading_task;
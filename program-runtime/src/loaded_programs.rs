@@ -8,6 +8,7 @@ use {
         program_metrics::{EMA_SCALE, ProgramCacheStats},
     },
     log::error,
+    serde::{Deserialize, Serialize},
     solana_clock::{Epoch, Slot},
     solana_pubkey::Pubkey,
     solana_sbpf::program::BuiltinProgram,
@@ -17,7 +18,8 @@ use {
         thread,
     },
     std::{
-        collections::{HashMap, hash_map::Entry},
+        collections::{HashMap, HashSet, hash_map::Entry},
+        fmt,
         sync::Weak,
     },
 };
@@ -257,6 +259,8 @@ pub struct ProgramCache<FG: ForkGraph> {
     pub fork_graph: Option<Weak<RwLock<FG>>>,
     /// Coordinates TX batches waiting for others to complete their task during cooperative loading
     pub loading_task_waiter: Arc<LoadingTaskWaiter>,
+    /// Programs whose compiled executables are never evicted
+    pinned: HashSet<Pubkey>,
 }
 
 impl<FG: ForkGraph> std::fmt::Debug for ProgramCache<FG> {
@@ -265,6 +269,7 @@ impl<FG: ForkGraph> std::fmt::Debug for ProgramCache<FG> {
             .field("root slot", &self.latest_root_slot)
             .field("stats", &self.stats)
             .field("index", &self.index)
+            .field("pinned", &self.pinned)
             .finish()
     }
 }
@@ -367,6 +372,140 @@ impl ProgramCacheForTxBatch {
     }
 }
 
+/// What an entry of the [ProgramCache] currently holds
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ProgramCacheEntryTier {
+    /// Verified and compiled
+    Loaded,
+    /// Evicted, only the usage statistics are kept
+    Unloaded,
+    Builtin,
+    FailedVerification,
+    /// Closed or not a program account
+    Closed,
+    /// Deployed in the current slot, not yet visible to transactions
+    DelayVisibility,
+}
+
+impl From<&ProgramCacheEntryType> for ProgramCacheEntryTier {
+    fn from(program: &ProgramCacheEntryType) -> Self {
+        match program {
+            ProgramCacheEntryType::Loaded(_) => Self::Loaded,
+            ProgramCacheEntryType::Unloaded(_) => Self::Unloaded,
+            ProgramCacheEntryType::Builtin(_) => Self::Builtin,
+            ProgramCacheEntryType::FailedVerification(_) => Self::FailedVerification,
+            ProgramCacheEntryType::Closed => Self::Closed,
+            ProgramCacheEntryType::DelayVisibility => Self::DelayVisibility,
+        }
+    }
+}
+
+impl fmt::Display for ProgramCacheEntryTier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Loaded => "loaded",
+            Self::Unloaded => "unloaded",
+            Self::Builtin => "builtin",
+            Self::FailedVerification => "failed-verification",
+            Self::Closed => "closed",
+            Self::DelayVisibility => "delay-visibility",
+        })
+    }
+}
+
+/// State and usage statistics of an entry of the [ProgramCache]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgramCacheEntrySummary {
+    #[serde(with = "pubkey_string")]
+    pub program_id: Pubkey,
+    pub deployment_slot: Slot,
+    /// The loader of the program
+    #[serde(with = "pubkey_string")]
+    pub account_owner: Pubkey,
+    pub tier: ProgramCacheEntryTier,
+    /// Whether the program is exempt from eviction
+    pub pinned: bool,
+    pub uses: u64,
+    pub latest_access_slot: Slot,
+    /// Eviction prefers entries with a lower score
+    pub retention_score: u64,
+    pub compilations: u64,
+    pub total_compilation_time_us: u64,
+    /// Exponential moving average of the compilation time
+    pub compilation_time_ema_us: u64,
+    pub jit_invocations: u64,
+    pub interpreted_invocations: u64,
+}
+
+impl ProgramCacheEntrySummary {
+    fn new(program_id: Pubkey, entry: &ProgramCacheEntry, pinned: &HashSet<Pubkey>) -> Self {
+        let stats = &entry.stats;
+        Self {
+            program_id,
+            deployment_slot: entry.deployment_slot,
+            account_owner: entry.account_owner(),
+            tier: ProgramCacheEntryTier::from(&entry.program),
+            pinned: pinned.contains(&program_id),
+            uses: stats.uses.load(Ordering::Relaxed),
+            latest_access_slot: entry.latest_access_slot.load(Ordering::Relaxed),
+            retention_score: entry.retention_score(),
+            compilations: stats.compilations.load(Ordering::Relaxed),
+            total_compilation_time_us: stats.total_compilation_time_us.load(Ordering::Relaxed),
+            compilation_time_ema_us: stats.compilation_time_ema.load(Ordering::Relaxed) / EMA_SCALE,
+            jit_invocations: stats.jit_invocations.load(Ordering::Relaxed),
+            interpreted_invocations: stats.interpreted_invocations.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Writes the entries as a table, one row per entry
+    pub fn write_table(f: &mut impl fmt::Write, entries: &[Self]) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<44} {:>10} {:<19} {:<6} {:>10} {:>12} {:>16}",
+            "Program", "Deployed", "Tier", "Pinned", "Uses", "Last Access", "Compile EMA (us)",
+        )?;
+        for entry in entries {
+            writeln!(
+                f,
+                "{:<44} {:>10} {:<19} {:<6} {:>10} {:>12} {:>16}",
+                entry.program_id.to_string(),
+                entry.deployment_slot,
+                entry.tier.to_string(),
+                if entry.pinned { "yes" } else { "no" },
+                entry.uses,
+                entry.latest_access_slot,
+                entry.compilation_time_ema_us,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Serializes a [Pubkey] as its base58 string, like the rest of the CLI output
+mod pubkey_string {
+    use {
+        serde::{Deserialize, Deserializer, Serializer, de::Error},
+        solana_pubkey::Pubkey,
+        std::str::FromStr,
+    };
+
+    pub(super) fn serialize<S: Serializer>(
+        pubkey: &Pubkey,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(pubkey)
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Pubkey, D::Error> {
+        let pubkey = String::deserialize(deserializer)?;
+        Pubkey::from_str(&pubkey).map_err(D::Error::custom)
+    }
+}
+
 impl<FG: ForkGraph> ProgramCache<FG> {
     pub fn new(root_slot: Slot) -> Self {
         Self {
@@ -378,6 +517,7 @@ impl<FG: ForkGraph> ProgramCache<FG> {
             stats: ProgramCacheStats::default(),
             fork_graph: None,
             loading_task_waiter: Arc::new(LoadingTaskWaiter::default()),
+            pinned: HashSet::new(),
         }
     }
 
@@ -815,15 +955,87 @@ impl<FG: ForkGraph> ProgramCache<FG> {
         }
     }
 
+    /// Returns a summary of every entry in the cache, ordered by program id
+    /// and deployment slot
+    pub fn entry_summaries(&self) -> Vec<ProgramCacheEntrySummary> {
+        let mut summaries: Vec<_> = match &self.index {
+            IndexImplementation::V1 { entries, .. } => entries
+                .iter()
+                .flat_map(|(id, second_level)| {
+                    second_level
+                        .iter()
+                        .map(|entry| ProgramCacheEntrySummary::new(*id, entry, &self.pinned))
+                })
+                .collect(),
+        };
+        summaries.sort_by_key(|summary| (summary.program_id, summary.deployment_slot));
+        summaries
+    }
+
+    /// Exempts the compiled executables of `key` from eviction. Returns
+    /// false if the program was already pinned.
+    pub fn pin_program(&mut self, key: Pubkey) -> bool {
+        self.pinned.insert(key)
+    }
+
+    /// Makes the compiled executables of `key` evictable again. Returns false
+    /// if the program was not pinned.
+    pub fn unpin_program(&mut self, key: &Pubkey) -> bool {
+        self.pinned.remove(key)
+    }
+
+    pub fn pinned_programs(&self) -> &HashSet<Pubkey> {
+        &self.pinned
+    }
+
+    /// Inserts a program that was loaded ahead of its first use. Only loaded
+    /// programs are inserted, and only if the cache has no entry of the same
+    /// deployment yet or just an unloaded one. Returns whether the entry was
+    /// inserted.
+    pub fn prewarm_program(
+        &mut self,
+        program_runtime_environment: &ProgramRuntimeEnvironment,
+        key: Pubkey,
+        entry: Arc<ProgramCacheEntry>,
+    ) -> bool {
+        if !matches!(entry.program, ProgramCacheEntryType::Loaded(_)) {
+            return false;
+        }
+        let occupied = match &self.index {
+            IndexImplementation::V1 { entries, .. } => {
+                entries.get(&key).is_some_and(|second_level| {
+                    second_level.iter().any(|existing| {
+                        existing.deployment_slot == entry.deployment_slot
+                            && existing.account_owner == entry.account_owner
+                            && !matches!(existing.program, ProgramCacheEntryType::Unloaded(_))
+                    })
+                })
+            }
+        };
+        if occupied {
+            return false;
+        }
+        let deployment_slot = entry.deployment_slot;
+        self.assign_program(program_runtime_environment, key, deployment_slot, entry);
+        true
+    }
+
+    /// Loaded entries which may be evicted, along with the number of loaded
+    /// entries including the pinned ones
+    fn eviction_candidates(&self) -> (usize, Vec<(Pubkey, Slot, Arc<ProgramCacheEntry>)>) {
+        let mut candidates = self.get_flattened_entries();
+        let num_loaded = candidates.len();
+        candidates.retain(|(id, _last_modification_slot, _entry)| !self.pinned.contains(id));
+        (num_loaded, candidates)
+    }
+
     /// Unloads programs which were used infrequently
     pub fn sort_and_unload(&mut self, shrink_to_percent: Percent) {
-        let mut sorted_candidates = self.get_flattened_entries();
+        let (num_loaded, mut sorted_candidates) = self.eviction_candidates();
         sorted_candidates.sort_by_cached_key(|(_id, _last_modification_slot, program)| {
             program.stats.uses.load(Ordering::Relaxed)
         });
-        let num_to_unload = sorted_candidates
-            .len()
-            .saturating_sub(percent_of_max_entries(shrink_to_percent));
+        let num_to_unload = num_loaded.saturating_sub(percent_of_max_entries(shrink_to_percent));
         for (program, last_modification_slot, entry) in sorted_candidates.iter().take(num_to_unload)
         {
             self.unload_program_entry(*program, *last_modification_slot, entry);
@@ -836,14 +1048,15 @@ impl<FG: ForkGraph> ProgramCache<FG> {
     /// The eviction is performed enough number of times to reduce the cache usage to the given
     /// percentage.
     pub fn evict_using_random_selection(&mut self, shrink_to_percent: Percent, now: Slot) {
-        let mut candidates = self.get_flattened_entries();
+        let (num_loaded, mut candidates) = self.eviction_candidates();
         let mut rng = rng();
         self.stats
             .water_level
-            .store(candidates.len() as u64, Ordering::Relaxed);
-        let num_to_unload = candidates
-            .len()
-            .saturating_sub(percent_of_max_entries(shrink_to_percent));
+            .store(num_loaded as u64, Ordering::Relaxed);
+        // Pinned programs count towards the water level, but are never evicted
+        let num_to_unload = num_loaded
+            .saturating_sub(percent_of_max_entries(shrink_to_percent))
+            .min(candidates.len());
         let mut sample_entry = |candidates: &Vec<(Pubkey, u64, Arc<ProgramCacheEntry>)>| {
             // gen_range is deprecated in favor of random_range in rand>=0.9, but we also get
             // rnd() from shuttle, which doesn't yet support rand 0.9 APIs
@@ -968,8 +1181,8 @@ pub(crate) mod tests {
         crate::{
             loaded_programs::{
                 BlockRelation, ForkGraph, MAX_TOMBSTONE_AGE_IN_SLOTS, Percent, ProgramCache,
-                ProgramCacheForTxBatch, ProgramRuntimeEnvironment, ProgramToLoad,
-                get_mock_program_runtime_environment,
+                ProgramCacheEntryTier, ProgramCacheForTxBatch, ProgramRuntimeEnvironment,
+                ProgramToLoad, get_mock_program_runtime_environment,
             },
             program_cache_entry::{
                 ProgramCacheEntry, ProgramCacheEntryOwner, ProgramCacheEntryType,
@@ -1309,6 +1522,66 @@ pub(crate) mod tests {
         assert_eq!(num_tombstones, num_tombstones_expected);
     }
 
+    #[test]
+    fn test_pinned_programs_are_not_evicted() {
+        let mut cache = ProgramCache::<TestForkGraph>::new(0);
+        let env = get_mock_program_runtime_environment();
+        let pinned = Pubkey::new_unique();
+        let unpinned = Pubkey::new_unique();
+        cache.assign_program(&env, pinned, 0, new_test_entry(0));
+        cache.assign_program(&env, unpinned, 0, new_test_entry(0));
+        assert!(cache.pin_program(pinned));
+        assert!(!cache.pin_program(pinned));
+
+        cache.sort_and_unload(0);
+        let summaries = cache.entry_summaries();
+        let tier = |key| {
+            summaries
+                .iter()
+                .find(|summary| summary.program_id == key)
+                .map(|summary| (summary.tier, summary.pinned))
+        };
+        assert_eq!(tier(pinned), Some((ProgramCacheEntryTier::Loaded, true)));
+        assert_eq!(
+            tier(unpinned),
+            Some((ProgramCacheEntryTier::Unloaded, false))
+        );
+
+        assert!(cache.unpin_program(&pinned));
+        cache.evict_using_random_selection(0, 0);
+        assert!(
+            cache
+                .entry_summaries()
+                .iter()
+                .all(|summary| summary.tier == ProgramCacheEntryTier::Unloaded)
+        );
+    }
+
+    #[test]
+    fn test_prewarm_program() {
+        let mut cache = ProgramCache::<TestForkGraph>::new(0);
+        let env = get_mock_program_runtime_environment();
+        let program = Pubkey::new_unique();
+
+        // Tombstones are not inserted
+        let tombstone = Arc::new(ProgramCacheEntry::new_closed_tombstone(
+            0,
+            ProgramCacheEntryOwner::LoaderV2,
+        ));
+        assert!(!cache.prewarm_program(&env, program, tombstone));
+        assert!(cache.entry_summaries().is_empty());
+
+        // An unloaded entry is replaced, a loaded one is not
+        insert_unloaded_entry(&mut cache, program, 0);
+        assert!(cache.prewarm_program(&env, program, new_test_entry(0)));
+        assert!(!cache.prewarm_program(&env, program, new_test_entry(0)));
+        let summaries = cache.entry_summaries();
+        assert_eq!(summaries.len(), 1);
+        let summary = summaries.first().unwrap();
+        assert_eq!(summary.tier, ProgramCacheEntryTier::Loaded);
+        assert_eq!(summary.deployment_slot, 0);
+    }
+
     #[test]
     fn test_usage_count_of_unloaded_program() {
         let mut cache = ProgramCache::<TestForkGraph>::new(0);
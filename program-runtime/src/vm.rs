@@ -4,6 +4,7 @@
 use qualifier_attr::qualifiers;
 use {
     crate::{
+        debugger::debug_metadata,
         execution_budget::MAX_INSTRUCTION_STACK_DEPTH_SIMD_0268,
         invoke_context::{BpfAllocator, InvokeContext},
         mem_pool::VmMemoryPool,
@@ -237,29 +238,22 @@ pub fn execute<'a, 'b: 'a>(
         })
         .collect::<Vec<_>>();
 
-    #[cfg(feature = "sbpf-debugger")]
-    let (debug_port, debug_metadata) = if invoke_context.debug_port.is_some() {
-        (
-            invoke_context.debug_port,
-            Some(format!(
-                "program_id={};cpi_level={};caller={}",
-                program_id,
-                instruction_context.get_stack_height().saturating_sub(1),
-                invoke_context
-                    .get_stack_height()
-                    .checked_sub(2)
-                    .and_then(|nesting_level| {
-                        transaction_context
-                            .get_instruction_context_at_nesting_level(nesting_level)
-                            .ok()
-                    })
-                    .and_then(|ctx| ctx.get_program_key().ok())
-                    .map(|key| key.to_string())
-                    .unwrap_or_else(|| "none".into())
-            )),
-        )
-    } else {
-        (None, None)
+    let (debug_port, debug_metadata) = match &invoke_context.debugger_config {
+        Some(debugger_config)
+            if debugger_config
+                .should_break(&program_id, instruction_context.get_stack_height()) =>
+        {
+            (
+                Some(debugger_config.port),
+                Some(debug_metadata(
+                    transaction_context,
+                    &instruction_context,
+                    &accounts_metadata,
+                    instruction_data_offset,
+                )?),
+            )
+        }
+        _ => (None, None),
     };
 
     let mut create_vm_time = Measure::start("create_vm");
@@ -278,12 +272,12 @@ pub fn execute<'a, 'b: 'a>(
     };
 
     let execution_result = {
-        let mut execution_mode = ExecutionMode::PreferJit;
-
-        #[cfg(feature = "sbpf-debugger")]
-        if invoke_context.debug_port.is_some() {
-            execution_mode = ExecutionMode::Interpreted;
-        }
+        // The debugger is hosted by the interpreter
+        let mut execution_mode = if debug_port.is_some() {
+            ExecutionMode::Interpreted
+        } else {
+            ExecutionMode::PreferJit
+        };
 
         let compute_meter_prev = invoke_context.get_remaining();
         let (mut vm, stack, heap) = unsafe {
@@ -300,11 +294,8 @@ pub fn execute<'a, 'b: 'a>(
         };
 
         create_vm_time.stop();
-        #[cfg(feature = "sbpf-debugger")]
-        {
-            vm.debug_port = debug_port;
-            vm.debug_metadata = debug_metadata;
-        }
+        vm.debug_port = debug_port;
+        vm.debug_metadata = debug_metadata;
 
         let execute_time = Measure::start("execute");
         let prev_nested_exec_time = vm.context().total_nested_exec_time;
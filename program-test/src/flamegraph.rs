@@ -0,0 +1,121 @@
+//! Flamegraph recording of the SBF programs run by a `ProgramTest`
+
+use {
+    log::*,
+    solana_program_runtime::{
+        flamegraph::{Flamegraph, Symbolizer},
+        invoke_context::{RegisterTrace, RegisterTraceInspector},
+    },
+    solana_pubkey::Pubkey,
+    std::{
+        collections::HashMap,
+        fmt,
+        fs::File,
+        io::{self, BufWriter, Write},
+        path::{Path, PathBuf},
+        sync::Mutex,
+    },
+};
+
+/// Accumulates the register traces of every program into one flamegraph per
+/// program. On [`FlamegraphRecorder::flush`] and on drop, they are written to
+/// `<output_dir>/<program_id>.folded` along with the instructions executed per
+/// function in `<output_dir>/<program_id>.functions`
+pub(crate) struct FlamegraphRecorder {
+    output_dir: PathBuf,
+    /// Shared objects of the programs added to the test, for their debug info
+    program_files: HashMap<Pubkey, PathBuf>,
+    programs: Mutex<HashMap<Pubkey, (Symbolizer, Flamegraph)>>,
+}
+
+impl FlamegraphRecorder {
+    pub(crate) fn new(output_dir: PathBuf, program_files: HashMap<Pubkey, PathBuf>) -> Self {
+        Self {
+            output_dir,
+            program_files,
+            programs: Mutex::default(),
+        }
+    }
+
+    /// Writes the flamegraphs of all programs recorded so far
+    pub(crate) fn flush(&self) -> io::Result<()> {
+        let programs = self.programs.lock().unwrap();
+        if programs.is_empty() {
+            return Ok(());
+        }
+        std::fs::create_dir_all(&self.output_dir)?;
+        for (program_id, (_symbolizer, flamegraph)) in programs.iter() {
+            let path = |extension| self.output_dir.join(format!("{program_id}.{extension}"));
+            let mut writer = BufWriter::new(File::create(path("folded"))?);
+            flamegraph.write_folded(&mut writer)?;
+            writer.flush()?;
+            write_function_instructions(&path("functions"), flamegraph)?;
+        }
+        Ok(())
+    }
+
+    fn symbolizer(&self, program_id: &Pubkey) -> Symbolizer {
+        let mut symbolizer = Symbolizer::default();
+        if let Some(program_file) = self.program_files.get(program_id)
+            && let Err(err) = symbolizer.load_debug_info(program_file)
+        {
+            warn!(
+                "Could not load debug info of {}: {err}",
+                program_file.display()
+            );
+        }
+        symbolizer
+    }
+}
+
+fn write_function_instructions(path: &Path, flamegraph: &Flamegraph) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(
+        writer,
+        "{:>12} {:>12} function (executed instructions)",
+        "exclusive", "inclusive"
+    )?;
+    for function in flamegraph.function_instructions() {
+        writeln!(
+            writer,
+            "{:>12} {:>12} {}",
+            function.exclusive, function.inclusive, function.name
+        )?;
+    }
+    writer.flush()
+}
+
+impl Drop for FlamegraphRecorder {
+    fn drop(&mut self) {
+        if let Err(err) = self.flush() {
+            warn!(
+                "Failed to write flamegraphs to {}: {err}",
+                self.output_dir.display()
+            );
+        }
+    }
+}
+
+impl fmt::Debug for FlamegraphRecorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FlamegraphRecorder")
+            .field("output_dir", &self.output_dir)
+            .field("program_files", &self.program_files)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RegisterTraceInspector for FlamegraphRecorder {
+    fn inspect_register_trace(
+        &self,
+        program_id: &Pubkey,
+        text: &[u8],
+        register_trace: RegisterTrace,
+    ) {
+        let mut programs = self.programs.lock().unwrap();
+        let (symbolizer, flamegraph) = programs
+            .entry(*program_id)
+            .or_insert_with(|| (self.symbolizer(program_id), Flamegraph::default()));
+        flamegraph.add_register_trace(&program_id.to_string(), text, symbolizer, register_trace);
+    }
+}
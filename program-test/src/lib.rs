@@ -4,7 +4,11 @@
 
 // Export tokio for test clients
 pub use tokio;
+
+mod flamegraph;
+
 use {
+    crate::flamegraph::FlamegraphRecorder,
     agave_feature_set::{FEATURE_NAMES, FeatureSet, raise_cpi_nesting_limit_to_8},
     async_trait::async_trait,
     base64::{Engine, prelude::BASE64_STANDARD},
@@ -38,8 +42,11 @@ use {
     solana_program_entrypoint::{SUCCESS, deserialize},
     solana_program_error::{ProgramError, ProgramResult},
     solana_program_runtime::{
-        invoke_context::BuiltinFunctionRegisterer, program_cache_entry::ProgramCacheEntry,
-        serialization::serialize_parameters, stable_log, sysvar_cache::SysvarCache,
+        invoke_context::{BuiltinFunctionRegisterer, RegisterTraceInspector},
+        program_cache_entry::ProgramCacheEntry,
+        serialization::serialize_parameters,
+        stable_log,
+        sysvar_cache::SysvarCache,
     },
     solana_pubkey::Pubkey,
     solana_rent::Rent,
@@ -78,7 +85,7 @@ use {
 pub use {
     solana_banks_client::{BanksClient, BanksClientError},
     solana_banks_interface::BanksTransactionResultWithMetadata,
-    solana_program_runtime::invoke_context::InvokeContext,
+    solana_program_runtime::{debugger::DebuggerConfig, invoke_context::InvokeContext},
     solana_sbpf::{
         error::EbpfError,
         memory_region::MemoryMapping,
@@ -711,6 +718,12 @@ pub struct ProgramTest {
     prefer_bpf: bool,
     deactivate_feature_set: HashSet<Pubkey>,
     transaction_account_lock_limit: Option<usize>,
+    /// Shared objects of the SBF programs added to the test
+    program_files: HashMap<Pubkey, PathBuf>,
+    flamegraph_dir: Option<PathBuf>,
+    /// Records the flamegraphs of the bank set up for `flamegraph_dir`
+    flamegraph_recorder: Option<Arc<FlamegraphRecorder>>,
+    debugger_config: Option<DebuggerConfig>,
 }
 
 impl Default for ProgramTest {
@@ -742,6 +755,10 @@ impl Default for ProgramTest {
             prefer_bpf,
             deactivate_feature_set: HashSet::default(),
             transaction_account_lock_limit: None,
+            program_files: HashMap::default(),
+            flamegraph_dir: None,
+            flamegraph_recorder: None,
+            debugger_config: None,
         }
     }
 }
@@ -783,6 +800,37 @@ impl ProgramTest {
         self.transaction_account_lock_limit = Some(transaction_account_lock_limit);
     }
 
+    /// Record flamegraphs of the SBF programs run by the test.
+    ///
+    /// For every program, the call stacks of all its invocations are written to
+    /// `<output_dir>/<program_id>.folded` in the folded format of flamegraph
+    /// tools, and the instructions executed per function to
+    /// `<output_dir>/<program_id>.functions`. Both are counts of executed SBF
+    /// instructions, which leave out the compute units charged by syscalls.
+    /// Function names come from the debug info of the program's shared object
+    /// when it was built with any.
+    ///
+    /// The files are written once the test environment is dropped, or earlier
+    /// with [`ProgramTestContext::write_flamegraphs`].
+    ///
+    /// Programs run with register tracing enabled, which slows them down
+    /// considerably.
+    pub fn record_flamegraphs(&mut self, output_dir: impl Into<PathBuf>) {
+        self.flamegraph_dir = Some(output_dir.into());
+    }
+
+    /// Host a GDB remote protocol debugger session for the SBF program
+    /// invocations selected by `debugger_config`.
+    ///
+    /// Each selected invocation is interpreted and stops at the program's
+    /// entrypoint until a debugger connects to `debugger_config.port`. The
+    /// session describes where the serialized instruction data and accounts
+    /// are mapped in the program's memory. Programs added as native
+    /// processors, because `prefer_bpf` is not set, cannot be debugged.
+    pub fn debug_programs(&mut self, debugger_config: DebuggerConfig) {
+        self.debugger_config = Some(debugger_config);
+    }
+
     /// Add an account to the test environment's genesis config.
     pub fn add_genesis_account(&mut self, address: Pubkey, account: Account) {
         self.genesis_accounts
@@ -864,7 +912,8 @@ impl ProgramTest {
         let program_file = find_file(&format!("{program_name}.so")).unwrap_or_else(|| {
             panic!("Program file data not available for {program_name} ({program_id})")
         });
-        let elf = read_file(program_file);
+        let elf = read_file(&program_file);
+        self.program_files.insert(*program_id, program_file);
         let program_accounts =
             programs::bpf_loader_upgradeable_program_accounts(program_id, &elf, &Rent::default());
         for (address, account) in program_accounts {
@@ -919,6 +968,7 @@ impl ProgramTest {
                     rent_epoch: 0,
                 },
             );
+            this.program_files.insert(program_id, program_file);
         };
 
         let warn_invalid_program_name = || {
@@ -1068,6 +1118,12 @@ impl ProgramTest {
         debug!("Payer address: {}", mint_keypair.pubkey());
         debug!("Genesis config: {genesis_config}");
 
+        self.flamegraph_recorder = self.flamegraph_dir.clone().map(|output_dir| {
+            Arc::new(FlamegraphRecorder::new(
+                output_dir,
+                self.program_files.clone(),
+            ))
+        });
         let bank = Bank::new_from_genesis(
             &genesis_config,
             Arc::new(RuntimeConfig {
@@ -1080,6 +1136,11 @@ impl ProgramTest {
                     )
                 }),
                 transaction_account_lock_limit: self.transaction_account_lock_limit,
+                register_trace_inspector: self
+                    .flamegraph_recorder
+                    .clone()
+                    .map(|recorder| recorder as Arc<dyn RegisterTraceInspector>),
+                sbpf_debugger: self.debugger_config.clone(),
                 ..RuntimeConfig::default()
             }),
             Vec::default(),
@@ -1206,6 +1267,7 @@ impl ProgramTest {
             banks_client,
             last_blockhash,
             gci,
+            self.flamegraph_recorder.take(),
         )
     }
 }
@@ -1264,6 +1326,7 @@ pub struct ProgramTestContext {
     genesis_config: GenesisConfig,
     bank_forks: Arc<RwLock<BankForks>>,
     block_commitment_cache: Arc<RwLock<BlockCommitmentCache>>,
+    flamegraph_recorder: Option<Arc<FlamegraphRecorder>>,
     _bank_task: DroppableTask<()>,
 }
 
@@ -1274,6 +1337,7 @@ impl ProgramTestContext {
         banks_client: BanksClient,
         last_blockhash: Hash,
         genesis_config_info: GenesisConfigInfo,
+        flamegraph_recorder: Option<Arc<FlamegraphRecorder>>,
     ) -> Self {
         // Run a simulated PohService to provide the client with new blockhashes.  New blockhashes
         // are required when sending multiple otherwise identical transactions in series from a
@@ -1310,6 +1374,7 @@ impl ProgramTestContext {
             genesis_config: genesis_config_info.genesis_config,
             bank_forks,
             block_commitment_cache,
+            flamegraph_recorder,
             _bank_task: bank_task,
         }
     }
@@ -1318,6 +1383,15 @@ impl ProgramTestContext {
         &self.genesis_config
     }
 
+    /// Write the flamegraphs recorded so far, see
+    /// [`ProgramTest::record_flamegraphs`]
+    pub fn write_flamegraphs(&self) -> io::Result<()> {
+        match &self.flamegraph_recorder {
+            Some(flamegraph_recorder) => flamegraph_recorder.flush(),
+            None => Ok(()),
+        }
+    }
+
     pub fn is_active(&self, feature: &Address) -> bool {
         self.bank_forks
             .read()
@@ -3,18 +3,20 @@ pub use solana_rpc_client_types::response::{
     EncodedTransaction, EncodedTransactionWithStatusMeta, FeeCalculator, FeeRateGovernor,
     OptionSerializer, OptionalContext, ParsedAccount, ParsedInstruction, ProcessedSignatureResult,
     ReceivedSignatureResult, Response, Reward, RewardType, Rewards, RpcAccountBalance,
+    RpcAccountDataDiff, RpcAccountLtHash, RpcAccountStateDiff, RpcAccountsLtHashProof,
     RpcApiVersion, RpcBlockCommitment, RpcBlockProduction, RpcBlockProductionRange, RpcBlockUpdate,
-    RpcBlockUpdateError, RpcBlockhash, RpcBlockhashFeeCalculator,
+    RpcBlockUpdateError, RpcBlockhash, RpcBlockhashFeeCalculator, RpcComputeProfile,
     RpcConfirmedTransactionStatusWithSignature, RpcContactInfo, RpcFeeCalculator,
     RpcFeeRateGovernor, RpcIdentity, RpcInflationGovernor, RpcInflationRate, RpcInflationReward,
-    RpcKeyedAccount, RpcLeaderSchedule, RpcLogsResponse, RpcPerfSample, RpcPrioritizationFee,
-    RpcResponseContext, RpcSignatureConfirmation, RpcSignatureResult, RpcSimulateTransactionResult,
-    RpcSnapshotSlotInfo, RpcStorageTurn, RpcSupply, RpcTokenAccountBalance, RpcVersionInfo,
-    RpcVote, RpcVoteAccountInfo, RpcVoteAccountStatus, SlotInfo, SlotTransactionStats, SlotUpdate,
-    StakeActivationState, TransactionBinaryEncoding, TransactionConfirmationStatus,
-    TransactionError, TransactionParsedAccount, TransactionResult, UiAccount, UiAccountData,
-    UiAccountEncoding, UiAccountsList, UiCompiledInstruction, UiConfirmedBlock,
-    UiInnerInstructions, UiInstruction, UiLoadedAddresses, UiParsedInstruction,
+    RpcInstructionComputeProfile, RpcKeyedAccount, RpcLeaderSchedule, RpcLogsResponse,
+    RpcPerfSample, RpcPrioritizationFee, RpcResponseContext, RpcSignatureConfirmation,
+    RpcSignatureResult, RpcSimulateTransactionResult, RpcSimulateTransactionsResult,
+    RpcSnapshotSlotInfo, RpcStorageTurn, RpcSupply, RpcSyscallComputeProfile,
+    RpcTokenAccountBalance, RpcVersionInfo, RpcVote, RpcVoteAccountInfo, RpcVoteAccountStatus,
+    SlotInfo, SlotTransactionStats, SlotUpdate, StakeActivationState, TransactionBinaryEncoding,
+    TransactionConfirmationStatus, TransactionError, TransactionParsedAccount, TransactionResult,
+    UiAccount, UiAccountData, UiAccountEncoding, UiAccountsList, UiCompiledInstruction,
+    UiConfirmedBlock, UiInnerInstructions, UiInstruction, UiLoadedAddresses, UiParsedInstruction,
     UiPartiallyDecodedInstruction, UiReturnDataEncoding, UiTokenAmount, UiTransactionError,
     UiTransactionReturnData, UiTransactionStatusMeta, UiTransactionTokenBalance, Value,
     transaction,
@@ -47,6 +47,12 @@ pub struct RpcSimulateTransactionConfig {
     pub min_context_slot: Option<Slot>,
     #[serde(default)]
     pub inner_instructions: bool,
+    /// Report the compute units consumed per instruction and per syscall
+    #[serde(default)]
+    pub compute_profile: bool,
+    /// Report the pre and post transaction state of every writable account
+    #[serde(default)]
+    pub account_diffs: bool,
 }
 
 #[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -158,6 +158,7 @@ pub const MAX_GET_INFLATION_REWARD_ADDRESSES: usize = 32;
 pub const NUM_LARGEST_ACCOUNTS: usize = 20;
 pub const MAX_GET_PROGRAM_ACCOUNT_FILTERS: usize = 4;
 pub const MAX_GET_SLOT_LEADERS: usize = 5000;
+pub const MAX_SIMULATE_TRANSACTIONS: usize = 16;
 
 // Limit the length of the `epoch_credits` array for each validator in a `get_vote_accounts`
 // response
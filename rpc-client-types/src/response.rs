@@ -459,6 +459,94 @@ pub struct RpcSimulateTransactionResult {
     pub pre_token_balances: Option<Vec<UiTransactionTokenBalance>>,
     pub post_token_balances: Option<Vec<UiTransactionTokenBalance>>,
     pub loaded_addresses: Option<UiLoadedAddresses>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compute_profile: Option<RpcComputeProfile>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub account_diffs: Option<Vec<RpcAccountStateDiff>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcComputeProfile {
+    /// One entry per instruction, including CPIs, in the order they were invoked
+    pub instructions: Vec<RpcInstructionComputeProfile>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcInstructionComputeProfile {
+    pub index_in_trace: usize,
+    pub stack_height: usize,
+    pub program_id: String,
+    /// Includes the compute units consumed by invoked instructions
+    pub compute_units_consumed: u64,
+    pub syscalls: Vec<RpcSyscallComputeProfile>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcSyscallComputeProfile {
+    pub name: String,
+    pub count: u64,
+    pub compute_units: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcAccountStateDiff {
+    pub pubkey: String,
+    pub pre_lamports: u64,
+    pub post_lamports: u64,
+    pub pre_owner: String,
+    pub post_owner: String,
+    pub pre_data_len: usize,
+    pub post_data_len: usize,
+    /// Byte ranges of the post transaction data that changed
+    pub data_diff: Vec<RpcAccountDataDiff>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcAccountDataDiff {
+    pub offset: usize,
+    /// Base64 encoded post transaction data starting at `offset`
+    pub data: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcSimulateTransactionsResult {
+    /// One result per transaction, in the order they were simulated
+    pub results: Vec<RpcSimulateTransactionResult>,
+    /// Requested accounts in their state after the last transaction
+    pub accounts: Option<Vec<Option<UiAccount>>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcAccountLtHash {
+    pub pubkey: String,
+    /// None if the account does not exist or has zero lamports
+    pub account: Option<UiAccount>,
+    /// Base64 encoded lt hash of the account
+    pub lt_hash: String,
+    pub lt_hash_checksum: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcAccountsLtHashProof {
+    pub slot: Slot,
+    pub bank_hash: String,
+    pub parent_bank_hash: String,
+    pub signature_count: u64,
+    pub last_blockhash: String,
+    /// Base64 encoded accounts lt hash of the bank
+    pub accounts_lt_hash: String,
+    pub accounts_lt_hash_checksum: String,
+    /// Base64 encoded data mixed into the bank hash, if the bank is a hard fork
+    pub hard_fork_data: Option<String>,
+    pub accounts: Vec<RpcAccountLtHash>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
@@ -388,6 +388,8 @@ impl RpcSender for MockSender {
                     pre_token_balances: None,
                     post_token_balances: None,
                     loaded_addresses: None,
+                    compute_profile: None,
+                    account_diffs: None,
                 }
             })?,
             "getMinimumBalanceForRentExemption" => json![20],
@@ -50,6 +50,7 @@ use {
     solana_net_utils::Protocol,
     solana_perf::packet::PACKET_DATA_SIZE,
     solana_program_pack::Pack,
+    solana_program_runtime::compute_profile::ComputeProfile,
     solana_pubkey::{PUBKEY_BYTES, Pubkey},
     solana_rpc_client_api::{
         config::*,
@@ -60,13 +61,17 @@ use {
             MAX_GET_CONFIRMED_SIGNATURES_FOR_ADDRESS2_LIMIT, MAX_GET_INFLATION_REWARD_ADDRESSES,
             MAX_GET_PROGRAM_ACCOUNT_FILTERS, MAX_GET_SIGNATURE_STATUSES_QUERY_ITEMS,
             MAX_GET_SLOT_LEADERS, MAX_MULTIPLE_ACCOUNTS,
-            MAX_RPC_VOTE_ACCOUNT_INFO_EPOCH_CREDITS_HISTORY, NUM_LARGEST_ACCOUNTS,
-            TokenAccountsFilter,
+            MAX_RPC_VOTE_ACCOUNT_INFO_EPOCH_CREDITS_HISTORY, MAX_SIMULATE_TRANSACTIONS,
+            NUM_LARGEST_ACCOUNTS, TokenAccountsFilter,
         },
         response::{Response as RpcResponse, *},
     },
     solana_runtime::{
-        bank::{Bank, TransactionSimulationResult},
+        account_state_diff::AccountStateDiff,
+        bank::{
+            Bank, BundleSimulationResult, SimulationConfig, TransactionSimulationResult,
+            bank_hash_details::AccountsLtHashProof,
+        },
         bank_forks::BankForks,
         commitment::{BlockCommitmentArray, BlockCommitmentCache},
         non_circulating_supply::{NonCirculatingSupply, calculate_non_circulating_supply},
@@ -579,21 +584,80 @@ impl JsonRpcRequestProcessor {
         })?;
         let encoding = encoding.unwrap_or(UiAccountEncoding::Base64);
 
-        let mut accounts = Vec::with_capacity(pubkeys.len());
-        for pubkey in pubkeys {
-            let bank = Arc::clone(&bank);
-            accounts.push(
-                self.runtime
-                    .spawn_blocking(move || {
-                        get_encoded_account(&bank, &pubkey, encoding, data_slice, None)
-                    })
-                    .await
-                    .expect("rpc: get_encoded_account panicked")?,
-            );
-        }
+        let accounts = self
+            .runtime
+            .spawn_blocking({
+                let bank = Arc::clone(&bank);
+                move || {
+                    let accounts = bank.get_multiple_accounts(&pubkeys);
+                    pubkeys
+                        .iter()
+                        .zip(accounts)
+                        .map(|(pubkey, account)| {
+                            encode_loaded_account(
+                                &bank, pubkey, account, encoding, data_slice, None,
+                            )
+                        })
+                        .collect::<Result<Vec<_>>>()
+                }
+            })
+            .await
+            .expect("rpc: get_multiple_accounts panicked")?;
         Ok(new_response(&bank, accounts))
     }
 
+    pub async fn get_accounts_lt_hash_proof(
+        &self,
+        pubkeys: Vec<Pubkey>,
+        config: Option<RpcContextConfig>,
+    ) -> Result<RpcResponse<RpcAccountsLtHashProof>> {
+        let bank = self.get_bank_with_config(config.unwrap_or_default())?;
+        let proof = self
+            .runtime
+            .spawn_blocking({
+                let bank = Arc::clone(&bank);
+                move || AccountsLtHashProof::new_from_bank(&bank, &pubkeys)
+            })
+            .await
+            .expect("rpc: AccountsLtHashProof::new_from_bank panicked")
+            .map_err(Error::invalid_params)?;
+
+        let accounts = proof
+            .accounts
+            .into_iter()
+            .map(|details| RpcAccountLtHash {
+                pubkey: details.pubkey.to_string(),
+                account: details.account.map(|account| {
+                    encode_ui_account(
+                        &details.pubkey,
+                        &account,
+                        UiAccountEncoding::Base64,
+                        None,
+                        None,
+                    )
+                }),
+                lt_hash: details.lt_hash.0.to_string(),
+                lt_hash_checksum: details.lt_hash.0.checksum().to_string(),
+            })
+            .collect();
+        Ok(new_response(
+            &bank,
+            RpcAccountsLtHashProof {
+                slot: proof.slot,
+                bank_hash: proof.bank_hash.to_string(),
+                parent_bank_hash: proof.parent_bank_hash.to_string(),
+                signature_count: proof.signature_count,
+                last_blockhash: proof.last_blockhash.to_string(),
+                accounts_lt_hash: proof.accounts_lt_hash.0.to_string(),
+                accounts_lt_hash_checksum: proof.accounts_lt_hash.0.checksum().to_string(),
+                hard_fork_data: proof
+                    .hard_fork_data
+                    .map(|data| BASE64_STANDARD.encode(data)),
+                accounts,
+            },
+        ))
+    }
+
     pub fn get_minimum_balance_for_rent_exemption(
         &self,
         data_len: usize,
@@ -2564,7 +2628,28 @@ fn get_encoded_account(
     // only used for simulation results
     overwrite_accounts: Option<&HashMap<Pubkey, AccountSharedData>>,
 ) -> Result<Option<UiAccount>> {
-    match account_resolver::get_account_from_overwrites_or_bank(pubkey, bank, overwrite_accounts) {
+    let account =
+        account_resolver::get_account_from_overwrites_or_bank(pubkey, bank, overwrite_accounts);
+    encode_loaded_account(
+        bank,
+        pubkey,
+        account,
+        encoding,
+        data_slice,
+        overwrite_accounts,
+    )
+}
+
+fn encode_loaded_account(
+    bank: &Bank,
+    pubkey: &Pubkey,
+    account: Option<AccountSharedData>,
+    encoding: UiAccountEncoding,
+    data_slice: Option<UiDataSliceConfig>,
+    // only used for simulation results
+    overwrite_accounts: Option<&HashMap<Pubkey, AccountSharedData>>,
+) -> Result<Option<UiAccount>> {
+    match account {
         Some(account) => {
             let response = if is_known_spl_token_id(account.owner())
                 && encoding == UiAccountEncoding::JsonParsed
@@ -3252,6 +3337,14 @@ pub mod rpc_accounts {
             config: Option<RpcAccountInfoConfig>,
         ) -> BoxFuture<Result<RpcResponse<Vec<Option<UiAccount>>>>>;
 
+        #[rpc(meta, name = "getAccountsLtHashProof")]
+        fn get_accounts_lt_hash_proof(
+            &self,
+            meta: Self::Metadata,
+            pubkey_strs: Vec<String>,
+            config: Option<RpcContextConfig>,
+        ) -> BoxFuture<Result<RpcResponse<RpcAccountsLtHashProof>>>;
+
         #[rpc(meta, name = "getBlockCommitment")]
         fn get_block_commitment(
             &self,
@@ -3327,6 +3420,35 @@ pub mod rpc_accounts {
             .boxed()
         }
 
+        fn get_accounts_lt_hash_proof(
+            &self,
+            meta: Self::Metadata,
+            pubkey_strs: Vec<String>,
+            config: Option<RpcContextConfig>,
+        ) -> BoxFuture<Result<RpcResponse<RpcAccountsLtHashProof>>> {
+            debug!(
+                "get_accounts_lt_hash_proof rpc request received: {:?}",
+                pubkey_strs.len()
+            );
+            async move {
+                let max_multiple_accounts = meta
+                    .config
+                    .max_multiple_accounts
+                    .unwrap_or(MAX_MULTIPLE_ACCOUNTS);
+                if pubkey_strs.len() > max_multiple_accounts {
+                    return Err(Error::invalid_params(format!(
+                        "Too many inputs provided; max {max_multiple_accounts}"
+                    )));
+                }
+                let pubkeys = pubkey_strs
+                    .into_iter()
+                    .map(|pubkey_str| verify_pubkey(&pubkey_str))
+                    .collect::<Result<Vec<_>>>()?;
+                meta.get_accounts_lt_hash_proof(pubkeys, config).await
+            }
+            .boxed()
+        }
+
         fn get_block_commitment(
             &self,
             meta: Self::Metadata,
@@ -3589,6 +3711,14 @@ pub mod rpc_full {
             config: Option<RpcSimulateTransactionConfig>,
         ) -> Result<RpcResponse<RpcSimulateTransactionResult>>;
 
+        #[rpc(meta, name = "simulateTransactions")]
+        fn simulate_transactions(
+            &self,
+            meta: Self::Metadata,
+            data: Vec<String>,
+            config: Option<RpcSimulateTransactionConfig>,
+        ) -> Result<RpcResponse<RpcSimulateTransactionsResult>>;
+
         #[rpc(meta, name = "minimumLedgerSlot")]
         fn minimum_ledger_slot(&self, meta: Self::Metadata) -> Result<Slot>;
 
@@ -3969,6 +4099,8 @@ pub mod rpc_full {
                     post_balances: _,
                     pre_token_balances: _,
                     post_token_balances: _,
+                    compute_profile: _,
+                    account_diffs: _,
                 } = simulation_result
                 {
                     match err {
@@ -3996,6 +4128,8 @@ pub mod rpc_full {
                             pre_token_balances: None,
                             post_token_balances: None,
                             loaded_addresses: None,
+                            compute_profile: None,
+                            account_diffs: None,
                         },
                     }
                     .into());
@@ -4029,42 +4163,21 @@ pub mod rpc_full {
                 accounts: config_accounts,
                 min_context_slot,
                 inner_instructions: enable_cpi_recording,
+                compute_profile: enable_compute_profile_recording,
+                account_diffs: enable_account_diffs,
             } = config.unwrap_or_default();
-            let tx_encoding = encoding.unwrap_or(UiTransactionEncoding::Base58);
-            let binary_encoding = tx_encoding.into_binary_encoding().ok_or_else(|| {
-                Error::invalid_params(format!(
-                    "unsupported encoding: {tx_encoding}. Supported encodings: base58, base64"
-                ))
-            })?;
-            let (_, mut unsanitized_tx) =
-                decode_and_deserialize::<VersionedTransaction>(data, binary_encoding)?;
-
+            let binary_encoding = simulation_binary_encoding(encoding)?;
             let bank = &*meta.get_bank_with_config(RpcContextConfig {
                 commitment,
                 min_context_slot,
             })?;
-            let mut blockhash: Option<RpcBlockhash> = None;
-            if replace_recent_blockhash {
-                if sig_verify {
-                    return Err(Error::invalid_params(
-                        "sigVerify may not be used with replaceRecentBlockhash",
-                    ));
-                }
-                let recent_blockhash = bank.last_blockhash();
-                unsanitized_tx
-                    .message
-                    .set_recent_blockhash(recent_blockhash);
-                let last_valid_block_height = bank
-                    .get_blockhash_last_valid_block_height(&recent_blockhash)
-                    .expect("bank blockhash queue should contain blockhash");
-                blockhash.replace(RpcBlockhash {
-                    blockhash: recent_blockhash.to_string(),
-                    last_valid_block_height,
-                });
-            }
-
-            let transaction =
-                sanitize_transaction(unsanitized_tx, bank, bank.get_reserved_account_keys())?;
+            let (transaction, blockhash) = decode_transaction_for_simulation(
+                data,
+                binary_encoding,
+                bank,
+                replace_recent_blockhash,
+                sig_verify,
+            )?;
 
             let verification_error = if sig_verify {
                 transaction.verify().err()
@@ -4075,101 +4188,169 @@ pub mod rpc_full {
             let simulation_result = if let Some(err) = verification_error {
                 TransactionSimulationResult::new_error(err)
             } else {
-                bank.simulate_transaction(&transaction, enable_cpi_recording)
+                bank.simulate_transaction_with_config(
+                    &transaction,
+                    SimulationConfig {
+                        enable_cpi_recording,
+                        enable_compute_profile_recording,
+                        enable_account_diffs,
+                    },
+                )
             };
 
-            let TransactionSimulationResult {
-                result,
-                logs,
-                post_simulation_accounts,
-                units_consumed,
-                loaded_accounts_data_size,
-                return_data,
-                inner_instructions,
-                fee,
-                pre_balances,
-                post_balances,
-                pre_token_balances,
-                post_token_balances,
-            } = simulation_result;
-
-            let account_keys = transaction.message().account_keys();
-            let number_of_accounts = account_keys.len();
-
             let accounts = if let Some(config_accounts) = config_accounts {
-                let accounts_encoding = config_accounts
-                    .encoding
-                    .unwrap_or(UiAccountEncoding::Base64);
+                let post_simulation_accounts = simulation_result.result.is_ok().then(|| {
+                    simulation_result
+                        .post_simulation_accounts
+                        .iter()
+                        .cloned()
+                        .collect::<HashMap<_, _>>()
+                });
+                Some(encode_simulation_accounts(
+                    bank,
+                    &config_accounts,
+                    transaction.message().account_keys().len(),
+                    post_simulation_accounts.as_ref(),
+                )?)
+            } else {
+                None
+            };
 
-                if accounts_encoding == UiAccountEncoding::Binary
-                    || accounts_encoding == UiAccountEncoding::Base58
-                {
-                    return Err(Error::invalid_params("base58 encoding not supported"));
-                }
+            Ok(new_response(
+                bank,
+                rpc_simulate_transaction_result(
+                    &transaction,
+                    simulation_result,
+                    accounts,
+                    blockhash,
+                ),
+            ))
+        }
 
-                if config_accounts.addresses.len() > number_of_accounts {
-                    return Err(Error::invalid_params(format!(
-                        "Too many accounts provided; max {number_of_accounts}"
-                    )));
-                }
+        fn simulate_transactions(
+            &self,
+            meta: Self::Metadata,
+            data: Vec<String>,
+            config: Option<RpcSimulateTransactionConfig>,
+        ) -> Result<RpcResponse<RpcSimulateTransactionsResult>> {
+            debug!(
+                "simulate_transactions rpc request received: {:?}",
+                data.len()
+            );
+            if data.is_empty() || data.len() > MAX_SIMULATE_TRANSACTIONS {
+                return Err(Error::invalid_params(format!(
+                    "Between 1 and {MAX_SIMULATE_TRANSACTIONS} transactions may be simulated"
+                )));
+            }
+            let RpcSimulateTransactionConfig {
+                sig_verify,
+                replace_recent_blockhash,
+                commitment,
+                encoding,
+                accounts: config_accounts,
+                min_context_slot,
+                inner_instructions: enable_cpi_recording,
+                compute_profile: enable_compute_profile_recording,
+                account_diffs: enable_account_diffs,
+            } = config.unwrap_or_default();
+            let binary_encoding = simulation_binary_encoding(encoding)?;
+            let bank = &*meta.get_bank_with_config(RpcContextConfig {
+                commitment,
+                min_context_slot,
+            })?;
+            let (transactions, blockhashes): (Vec<_>, Vec<_>) = data
+                .into_iter()
+                .map(|data| {
+                    decode_transaction_for_simulation(
+                        data,
+                        binary_encoding,
+                        bank,
+                        replace_recent_blockhash,
+                        sig_verify,
+                    )
+                })
+                .collect::<Result<Vec<_>>>()?
+                .into_iter()
+                .unzip();
 
-                if result.is_err() {
-                    Some(vec![None; config_accounts.addresses.len()])
-                } else {
-                    let mut post_simulation_accounts_map = HashMap::new();
-                    for (pubkey, data) in post_simulation_accounts {
-                        post_simulation_accounts_map.insert(pubkey, data);
+            let number_of_accounts = transactions
+                .iter()
+                .map(|transaction| transaction.message().account_keys().len())
+                .sum();
+
+            // Same as `simulateTransaction`, a transaction that fails signature
+            // verification is reported as failed without being simulated. The
+            // transactions after it run without its state changes.
+            let mut verified_transactions = Vec::with_capacity(transactions.len());
+            let failed_transactions: Vec<_> = transactions
+                .into_iter()
+                .map(|transaction| {
+                    let verification = if sig_verify {
+                        transaction.verify()
+                    } else {
+                        Ok(())
+                    };
+                    match verification {
+                        Ok(()) => {
+                            verified_transactions.push(transaction);
+                            None
+                        }
+                        Err(err) => Some((transaction, err)),
                     }
+                })
+                .collect();
 
-                    Some(
-                        config_accounts
-                            .addresses
-                            .iter()
-                            .map(|address_str| {
-                                let pubkey = verify_pubkey(address_str)?;
-                                get_encoded_account(
-                                    bank,
-                                    &pubkey,
-                                    accounts_encoding,
-                                    None,
-                                    Some(&post_simulation_accounts_map),
-                                )
-                            })
-                            .collect::<Result<Vec<_>>>()?,
-                    )
-                }
+            let BundleSimulationResult {
+                transaction_results,
+                post_simulation_accounts,
+            } = bank.simulate_transactions_with_config(
+                &verified_transactions,
+                SimulationConfig {
+                    enable_cpi_recording,
+                    enable_compute_profile_recording,
+                    enable_account_diffs,
+                },
+            );
+
+            let accounts = if let Some(config_accounts) = config_accounts {
+                Some(encode_simulation_accounts(
+                    bank,
+                    &config_accounts,
+                    number_of_accounts,
+                    Some(&post_simulation_accounts),
+                )?)
             } else {
                 None
             };
 
-            let inner_instructions = inner_instructions.map(|info| {
-                map_inner_instructions(info)
-                    .map(|converted| parse_ui_inner_instructions(converted, &account_keys))
-                    .collect()
-            });
+            let mut verified_results = verified_transactions.iter().zip(transaction_results);
+            let results = failed_transactions
+                .into_iter()
+                .zip(blockhashes)
+                .map(|(failed_transaction, blockhash)| match failed_transaction {
+                    Some((transaction, err)) => rpc_simulate_transaction_result(
+                        &transaction,
+                        TransactionSimulationResult::new_error(err),
+                        None,
+                        blockhash,
+                    ),
+                    None => {
+                        let (transaction, simulation_result) = verified_results
+                            .next()
+                            .expect("a simulation result for every verified transaction");
+                        rpc_simulate_transaction_result(
+                            transaction,
+                            simulation_result,
+                            None,
+                            blockhash,
+                        )
+                    }
+                })
+                .collect();
 
             Ok(new_response(
                 bank,
-                RpcSimulateTransactionResult {
-                    err: result.err().map(Into::into),
-                    logs: Some(logs),
-                    accounts,
-                    units_consumed: Some(units_consumed),
-                    loaded_accounts_data_size: Some(loaded_accounts_data_size),
-                    return_data: return_data.map(|return_data| return_data.into()),
-                    inner_instructions,
-                    replacement_blockhash: blockhash,
-                    fee,
-                    pre_balances,
-                    post_balances,
-                    pre_token_balances: pre_token_balances.map(|balances| {
-                        balances.into_iter().map(|balance| solana_runtime::transaction_balances::svm_token_info_to_token_balance(balance).into()).collect()
-                    }),
-                    post_token_balances: post_token_balances.map(|balances| {
-                        balances.into_iter().map(|balance| solana_runtime::transaction_balances::svm_token_info_to_token_balance(balance).into()).collect()
-                    }),
-                    loaded_addresses: Some(UiLoadedAddresses::from(&transaction.get_loaded_addresses())),
-                },
+                RpcSimulateTransactionsResult { results, accounts },
             ))
         }
 
@@ -4394,6 +4575,214 @@ fn rpc_perf_sample_from_perf_sample(slot: u64, sample: PerfSample) -> RpcPerfSam
     }
 }
 
+fn simulation_binary_encoding(
+    encoding: Option<UiTransactionEncoding>,
+) -> Result<TransactionBinaryEncoding> {
+    let tx_encoding = encoding.unwrap_or(UiTransactionEncoding::Base58);
+    tx_encoding.into_binary_encoding().ok_or_else(|| {
+        Error::invalid_params(format!(
+            "unsupported encoding: {tx_encoding}. Supported encodings: base58, base64"
+        ))
+    })
+}
+
+/// Decodes and sanitizes a transaction to simulate, replacing its recent blockhash with the
+/// bank's if requested
+fn decode_transaction_for_simulation(
+    data: String,
+    binary_encoding: TransactionBinaryEncoding,
+    bank: &Bank,
+    replace_recent_blockhash: bool,
+    sig_verify: bool,
+) -> Result<(
+    RuntimeTransaction<SanitizedTransaction>,
+    Option<RpcBlockhash>,
+)> {
+    let (_, mut unsanitized_tx) =
+        decode_and_deserialize::<VersionedTransaction>(data, binary_encoding)?;
+
+    let mut blockhash: Option<RpcBlockhash> = None;
+    if replace_recent_blockhash {
+        if sig_verify {
+            return Err(Error::invalid_params(
+                "sigVerify may not be used with replaceRecentBlockhash",
+            ));
+        }
+        let recent_blockhash = bank.last_blockhash();
+        unsanitized_tx
+            .message
+            .set_recent_blockhash(recent_blockhash);
+        let last_valid_block_height = bank
+            .get_blockhash_last_valid_block_height(&recent_blockhash)
+            .expect("bank blockhash queue should contain blockhash");
+        blockhash.replace(RpcBlockhash {
+            blockhash: recent_blockhash.to_string(),
+            last_valid_block_height,
+        });
+    }
+
+    let transaction = sanitize_transaction(unsanitized_tx, bank, bank.get_reserved_account_keys())?;
+    Ok((transaction, blockhash))
+}
+
+/// Encodes the requested accounts in their post simulation state, or as `None` if the
+/// simulation failed
+fn encode_simulation_accounts(
+    bank: &Bank,
+    config_accounts: &RpcSimulateTransactionAccountsConfig,
+    max_accounts: usize,
+    post_simulation_accounts: Option<&HashMap<Pubkey, AccountSharedData>>,
+) -> Result<Vec<Option<UiAccount>>> {
+    let accounts_encoding = config_accounts
+        .encoding
+        .unwrap_or(UiAccountEncoding::Base64);
+
+    if accounts_encoding == UiAccountEncoding::Binary
+        || accounts_encoding == UiAccountEncoding::Base58
+    {
+        return Err(Error::invalid_params("base58 encoding not supported"));
+    }
+
+    if config_accounts.addresses.len() > max_accounts {
+        return Err(Error::invalid_params(format!(
+            "Too many accounts provided; max {max_accounts}"
+        )));
+    }
+
+    match post_simulation_accounts {
+        Some(post_simulation_accounts) => config_accounts
+            .addresses
+            .iter()
+            .map(|address_str| {
+                let pubkey = verify_pubkey(address_str)?;
+                get_encoded_account(
+                    bank,
+                    &pubkey,
+                    accounts_encoding,
+                    None,
+                    Some(post_simulation_accounts),
+                )
+            })
+            .collect(),
+        None => Ok(vec![None; config_accounts.addresses.len()]),
+    }
+}
+
+fn rpc_simulate_transaction_result(
+    transaction: &RuntimeTransaction<SanitizedTransaction>,
+    simulation_result: TransactionSimulationResult,
+    accounts: Option<Vec<Option<UiAccount>>>,
+    replacement_blockhash: Option<RpcBlockhash>,
+) -> RpcSimulateTransactionResult {
+    let TransactionSimulationResult {
+        result,
+        logs,
+        post_simulation_accounts: _,
+        units_consumed,
+        loaded_accounts_data_size,
+        return_data,
+        inner_instructions,
+        fee,
+        pre_balances,
+        post_balances,
+        pre_token_balances,
+        post_token_balances,
+        compute_profile,
+        account_diffs,
+    } = simulation_result;
+
+    let account_keys = transaction.message().account_keys();
+    let inner_instructions = inner_instructions.map(|info| {
+        map_inner_instructions(info)
+            .map(|converted| parse_ui_inner_instructions(converted, &account_keys))
+            .collect()
+    });
+
+    RpcSimulateTransactionResult {
+        err: result.err().map(Into::into),
+        logs: Some(logs),
+        accounts,
+        units_consumed: Some(units_consumed),
+        loaded_accounts_data_size: Some(loaded_accounts_data_size),
+        return_data: return_data.map(|return_data| return_data.into()),
+        inner_instructions,
+        replacement_blockhash,
+        fee,
+        pre_balances,
+        post_balances,
+        pre_token_balances: pre_token_balances.map(|balances| {
+            balances
+                .into_iter()
+                .map(|balance| {
+                    solana_runtime::transaction_balances::svm_token_info_to_token_balance(balance)
+                        .into()
+                })
+                .collect()
+        }),
+        post_token_balances: post_token_balances.map(|balances| {
+            balances
+                .into_iter()
+                .map(|balance| {
+                    solana_runtime::transaction_balances::svm_token_info_to_token_balance(balance)
+                        .into()
+                })
+                .collect()
+        }),
+        loaded_addresses: Some(UiLoadedAddresses::from(&transaction.get_loaded_addresses())),
+        compute_profile: compute_profile.map(rpc_compute_profile_from_compute_profile),
+        account_diffs: account_diffs.map(|account_diffs| {
+            account_diffs
+                .into_iter()
+                .map(rpc_account_state_diff_from_account_state_diff)
+                .collect()
+        }),
+    }
+}
+
+fn rpc_compute_profile_from_compute_profile(profile: ComputeProfile) -> RpcComputeProfile {
+    RpcComputeProfile {
+        instructions: profile
+            .instructions
+            .into_iter()
+            .map(|instruction| RpcInstructionComputeProfile {
+                index_in_trace: instruction.index_in_trace,
+                stack_height: instruction.stack_height,
+                program_id: instruction.program_id.to_string(),
+                compute_units_consumed: instruction.compute_units_consumed,
+                syscalls: instruction
+                    .syscalls
+                    .into_iter()
+                    .map(|syscall| RpcSyscallComputeProfile {
+                        name: syscall.name,
+                        count: syscall.count,
+                        compute_units: syscall.compute_units,
+                    })
+                    .collect(),
+            })
+            .collect(),
+    }
+}
+
+fn rpc_account_state_diff_from_account_state_diff(diff: AccountStateDiff) -> RpcAccountStateDiff {
+    RpcAccountStateDiff {
+        pubkey: diff.pubkey.to_string(),
+        pre_lamports: diff.pre_lamports,
+        post_lamports: diff.post_lamports,
+        pre_owner: diff.pre_owner.to_string(),
+        post_owner: diff.post_owner.to_string(),
+        pre_data_len: diff.pre_data_len,
+        post_data_len: diff.post_data_len,
+        data_diff: diff
+            .data_diff
+            .into_iter()
+            .map(|data_diff| RpcAccountDataDiff {
+                offset: data_diff.offset,
+                data: BASE64_STANDARD.encode(data_diff.data),
+            })
+            .collect(),
+    }
+}
+
 // Base58 encoding is deprecated, not increased for v1 transactions
 const MAX_BASE58_SIZE: usize = 1683; // Golden, bump if PACKET_DATA_SIZE changes
 // Cap base64 based on the largest supported transaction version,
@@ -5978,6 +6367,43 @@ pub mod tests {
         );
     }
 
+    #[test]
+    fn test_rpc_get_accounts_lt_hash_proof() {
+        let rpc = RpcHandler::start();
+        let bank = rpc.working_bank();
+        let non_existent_pubkey = Pubkey::new_unique();
+        let request = create_test_request(
+            "getAccountsLtHashProof",
+            Some(json!([[
+                rpc.mint_keypair.pubkey().to_string(),
+                non_existent_pubkey.to_string(),
+            ]])),
+        );
+
+        let (code, message) = parse_failure_response(rpc.handle_request_sync(request.clone()));
+        assert_eq!(code, ErrorCode::InvalidParams.code());
+        assert!(message.contains("must be frozen"), "{message}");
+
+        bank.freeze();
+        let result: RpcResponse<RpcAccountsLtHashProof> =
+            parse_success_result(rpc.handle_request_sync(request));
+        let proof = result.value;
+        assert_eq!(proof.slot, bank.slot());
+        assert_eq!(proof.bank_hash, bank.hash().to_string());
+        assert_eq!(
+            proof.accounts_lt_hash_checksum,
+            bank.accounts_lt_hash().0.checksum().to_string()
+        );
+        assert_eq!(proof.hard_fork_data, None);
+        assert_eq!(proof.accounts.len(), 2);
+        assert_eq!(
+            proof.accounts[0].account.as_ref().unwrap().lamports,
+            TEST_MINT_LAMPORTS
+        );
+        assert_eq!(proof.accounts[1].pubkey, non_existent_pubkey.to_string());
+        assert_eq!(proof.accounts[1].account, None);
+    }
+
     #[test]
     fn test_rpc_get_program_accounts() {
         let rpc = RpcHandler::start();
@@ -6838,6 +7264,224 @@ pub mod tests {
         assert_eq!(result, expected);
     }
 
+    #[test]
+    fn test_rpc_simulate_transaction_with_compute_profile() {
+        let rpc = RpcHandler::start();
+        let bank = rpc.working_bank();
+        let recent_blockhash = bank.confirmed_last_blockhash();
+        let RpcHandler {
+            ref meta, ref io, ..
+        } = rpc;
+
+        let from = rpc.mint_keypair;
+        let from_pubkey = from.pubkey();
+        let to = Keypair::new();
+
+        let instruction = TestBuiltinEntrypoint::instruction(
+            &from_pubkey,
+            &to.pubkey(),
+            bank.rent_collector().rent.minimum_balance(0),
+            0,
+            &Pubkey::new_unique(),
+        );
+        let tx = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&from_pubkey),
+            &[&from, &to],
+            recent_blockhash,
+        );
+        let tx_serialized_encoded =
+            base64::prelude::BASE64_STANDARD.encode(wincode::serialize(&tx).unwrap());
+        bank.freeze();
+
+        // `computeProfile` not provided, should not be in response
+        let req = format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"simulateTransaction","params":["{tx_serialized_encoded}", {{"encoding": "base64"}}]}}"#,
+        );
+        let res = io.handle_request_sync(&req, meta.clone());
+        let result: Value = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        assert!(result["result"]["value"].get("computeProfile").is_none());
+
+        // `computeProfile` provided as `true`, should have the builtin and its CPI
+        let req = format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"simulateTransaction","params":["{tx_serialized_encoded}", {{"encoding": "base64", "computeProfile": true}}]}}"#,
+        );
+        let res = io.handle_request_sync(&req, meta.clone());
+        let result: Value = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        assert_eq!(
+            result["result"]["value"]["computeProfile"],
+            json!({
+                "instructions": [
+                    {
+                        "indexInTrace": 0,
+                        "stackHeight": 1,
+                        "programId": TestBuiltinEntrypoint::PROGRAM_ID.to_string(),
+                        "computeUnitsConsumed": TestBuiltinEntrypoint::COMPUTE_UNITS + 150,
+                        "syscalls": [],
+                    },
+                    {
+                        "indexInTrace": 1,
+                        "stackHeight": 2,
+                        "programId": "11111111111111111111111111111111",
+                        "computeUnitsConsumed": 150,
+                        "syscalls": [],
+                    },
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn test_rpc_simulate_transaction_with_account_diffs() {
+        let rpc = RpcHandler::start();
+        let bank = rpc.working_bank();
+        let recent_blockhash = bank.confirmed_last_blockhash();
+        let RpcHandler {
+            ref meta, ref io, ..
+        } = rpc;
+
+        let from = rpc.mint_keypair;
+        let to = Pubkey::new_unique();
+        let amount = bank.get_minimum_balance_for_rent_exemption(0);
+        let tx = system_transaction::transfer(&from, &to, amount, recent_blockhash);
+        let tx_serialized_encoded =
+            base64::prelude::BASE64_STANDARD.encode(wincode::serialize(&tx).unwrap());
+        bank.freeze();
+        let from_balance = bank.get_balance(&from.pubkey());
+
+        // `accountDiffs` not provided, should not be in response
+        let req = format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"simulateTransaction","params":["{tx_serialized_encoded}", {{"encoding": "base64"}}]}}"#,
+        );
+        let res = io.handle_request_sync(&req, meta.clone());
+        let result: Value = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        assert!(result["result"]["value"].get("accountDiffs").is_none());
+
+        // `accountDiffs` provided as `true`, should have the payer and the recipient
+        let req = format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"simulateTransaction","params":["{tx_serialized_encoded}", {{"encoding": "base64", "accountDiffs": true}}]}}"#,
+        );
+        let res = io.handle_request_sync(&req, meta.clone());
+        let result: Value = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        let fee = result["result"]["value"]["fee"].as_u64().unwrap();
+        let system_program_id = system_program::id().to_string();
+        assert_eq!(
+            result["result"]["value"]["accountDiffs"],
+            json!([
+                {
+                    "pubkey": from.pubkey().to_string(),
+                    "preLamports": from_balance,
+                    "postLamports": from_balance - amount - fee,
+                    "preOwner": system_program_id,
+                    "postOwner": system_program_id,
+                    "preDataLen": 0,
+                    "postDataLen": 0,
+                    "dataDiff": [],
+                },
+                {
+                    "pubkey": to.to_string(),
+                    "preLamports": 0,
+                    "postLamports": amount,
+                    "preOwner": system_program_id,
+                    "postOwner": system_program_id,
+                    "preDataLen": 0,
+                    "postDataLen": 0,
+                    "dataDiff": [],
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_rpc_simulate_transactions() {
+        let rpc = RpcHandler::start();
+        let bank = rpc.working_bank();
+        let recent_blockhash = bank.confirmed_last_blockhash();
+        let RpcHandler {
+            ref meta, ref io, ..
+        } = rpc;
+
+        // The second transaction spends from the account the first one funds
+        let keypair = Keypair::new();
+        let to = Pubkey::new_unique();
+        let amount = bank.get_minimum_balance_for_rent_exemption(0);
+        let fund_tx = system_transaction::transfer(
+            &rpc.mint_keypair,
+            &keypair.pubkey(),
+            3 * amount,
+            recent_blockhash,
+        );
+        let spend_tx = system_transaction::transfer(&keypair, &to, amount, recent_blockhash);
+        let [fund_tx_encoded, spend_tx_encoded] = [fund_tx.clone(), spend_tx]
+            .map(|tx| base64::prelude::BASE64_STANDARD.encode(wincode::serialize(&tx).unwrap()));
+        bank.freeze();
+
+        let req = format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"simulateTransactions","params":[["{fund_tx_encoded}", "{spend_tx_encoded}"], {{"sigVerify": true, "encoding": "base64", "accounts": {{"encoding": "base64", "addresses": ["{}", "{to}"]}}}}]}}"#,
+            keypair.pubkey(),
+        );
+        let res = io.handle_request_sync(&req, meta.clone());
+        let result: Value = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        let RpcSimulateTransactionsResult { results, accounts } =
+            serde_json::from_value(result["result"]["value"].clone()).unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|result| result.err.is_none()));
+        assert!(results.iter().all(|result| result.accounts.is_none()));
+        let spend_fee = results[1].fee.unwrap();
+        let lamports: Vec<_> = accounts
+            .unwrap()
+            .into_iter()
+            .map(|account| account.unwrap().lamports)
+            .collect();
+        assert_eq!(lamports, vec![2 * amount - spend_fee, amount]);
+
+        // On its own the spending transaction has no funds to pay its fee
+        let req = format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"simulateTransactions","params":[["{spend_tx_encoded}"], {{"encoding": "base64"}}]}}"#,
+        );
+        let res = io.handle_request_sync(&req, meta.clone());
+        let result: Value = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        assert_eq!(
+            result["result"]["value"]["results"][0]["err"],
+            json!("AccountNotFound")
+        );
+
+        // A transaction that fails signature verification is reported as
+        // failed, and the transactions after it run without its changes
+        let mut unsigned_fund_tx = fund_tx;
+        unsigned_fund_tx.signatures[0] = Signature::default();
+        let unsigned_fund_tx_encoded =
+            base64::prelude::BASE64_STANDARD.encode(wincode::serialize(&unsigned_fund_tx).unwrap());
+        let req = format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"simulateTransactions","params":[["{unsigned_fund_tx_encoded}", "{spend_tx_encoded}"], {{"sigVerify": true, "encoding": "base64"}}]}}"#,
+        );
+        let res = io.handle_request_sync(&req, meta.clone());
+        let result: Value = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        assert_eq!(
+            result["result"]["value"]["results"][0]["err"],
+            json!("SignatureFailure")
+        );
+        assert_eq!(
+            result["result"]["value"]["results"][1]["err"],
+            json!("AccountNotFound")
+        );
+
+        let req = format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"simulateTransactions","params":[[], {{"encoding": "base64"}}]}}"#,
+        );
+        let res = io.handle_request_sync(&req, meta.clone());
+        let result: Value = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        assert_eq!(result["error"]["code"], json!(-32602));
+    }
+
     #[test]
     #[should_panic(expected = "simulation bank must be frozen")]
     fn test_rpc_simulate_transaction_panic_on_unfrozen_bank() {
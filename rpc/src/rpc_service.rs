@@ -49,7 +49,9 @@ use {
     solana_tls_utils::NotifyKeyUpdate,
     solana_validator_exit::Exit,
     std::{
+        io::SeekFrom,
         net::{SocketAddr, UdpSocket},
+        ops::Range,
         path::{Path, PathBuf},
         pin::Pin,
         sync::{
@@ -60,7 +62,10 @@ use {
         thread::{self, Builder, JoinHandle},
         time::{Duration, Instant},
     },
-    tokio::runtime::{Builder as TokioBuilder, Handle as RuntimeHandle, Runtime as TokioRuntime},
+    tokio::{
+        io::{AsyncReadExt, AsyncSeekExt},
+        runtime::{Builder as TokioBuilder, Handle as RuntimeHandle, Runtime as TokioRuntime},
+    },
     tokio_util::{
         bytes::Bytes,
         codec::{BytesCodec, FramedRead},
@@ -254,7 +259,44 @@ impl RpcRequestMiddleware {
         )
     }
 
-    fn process_file_get(&self, path: &str) -> RequestMiddlewareAction {
+    fn range_not_satisfiable(file_length: u64) -> hyper::Response<hyper::Body> {
+        hyper::Response::builder()
+            .status(hyper::StatusCode::RANGE_NOT_SATISFIABLE)
+            .header(
+                hyper::header::CONTENT_RANGE,
+                format!("bytes */{file_length}"),
+            )
+            .body(hyper::Body::empty())
+            .unwrap()
+    }
+
+    /// Parses a `Range` header with a single `<start>-<end>`, `<start>-` or
+    /// `-<suffix length>` byte range
+    ///
+    /// Returns `None` if the header is malformed, in which case it is ignored
+    /// and the whole file is served, and `Some(None)` if the range does not
+    /// overlap a file of `file_length` bytes.
+    fn parse_byte_range(range: &str, file_length: u64) -> Option<Option<Range<u64>>> {
+        let (start, end) = range.trim().strip_prefix("bytes=")?.split_once('-')?;
+        let range = match (start.trim(), end.trim()) {
+            ("", suffix_length) => {
+                let suffix_length = suffix_length.parse::<u64>().ok()?;
+                file_length.saturating_sub(suffix_length)..file_length
+            }
+            (start, "") => start.parse().ok()?..file_length,
+            (start, end) => {
+                let start = start.parse().ok()?;
+                let end = end.parse::<u64>().ok()?;
+                if end < start {
+                    return None;
+                }
+                start..end.saturating_add(1).min(file_length)
+            }
+        };
+        Some((range.start < range.end).then_some(range))
+    }
+
+    fn process_file_get(&self, path: &str, range: Option<&str>) -> RequestMiddlewareAction {
         let (filename, snapshot_type) = {
             let stem = Self::strip_leading_slash(path).expect("path already verified");
             match path {
@@ -269,11 +311,14 @@ impl RpcRequestMiddleware {
                 }
             }
         };
-        let file_length = std::fs::metadata(&filename)
-            .map(|m| m.len())
-            .unwrap_or(0)
-            .to_string();
+        let file_length = std::fs::metadata(&filename).map(|m| m.len()).unwrap_or(0);
         info!("get {path} -> {filename:?} ({file_length} bytes)");
+        // Ranges allow interrupted snapshot downloads to be resumed
+        let range = match range.and_then(|range| Self::parse_byte_range(range, file_length)) {
+            None => None,
+            Some(Some(range)) => Some(range),
+            Some(None) => return Self::range_not_satisfiable(file_length).into(),
+        };
 
         if cfg!(not(test)) {
             assert!(
@@ -318,18 +363,33 @@ impl RpcRequestMiddleware {
                     } else {
                         Self::internal_server_error()
                     }),
-                    Ok(file) => {
-                        let stream =
-                            FramedRead::new(file, BytesCodec::new()).map_ok(|b| b.freeze());
+                    Ok(mut file) => {
+                        let (start, end) = range
+                            .as_ref()
+                            .map_or((0, file_length), |range| (range.start, range.end));
+                        if start > 0 && file.seek(SeekFrom::Start(start)).await.is_err() {
+                            return Ok(Self::internal_server_error());
+                        }
+                        let content_length = end - start;
+                        let stream = FramedRead::new(file.take(content_length), BytesCodec::new())
+                            .map_ok(|b| b.freeze());
                         let body = if let Some(timeout) = snapshot_timeout {
                             hyper::Body::wrap_stream(TimeoutStream::new(stream, timeout))
                         } else {
                             hyper::Body::wrap_stream(stream)
                         };
-                        Ok(hyper::Response::builder()
-                            .header(hyper::header::CONTENT_LENGTH, file_length)
-                            .body(body)
-                            .unwrap())
+                        let response = hyper::Response::builder()
+                            .header(hyper::header::ACCEPT_RANGES, "bytes")
+                            .header(hyper::header::CONTENT_LENGTH, content_length);
+                        let response = if range.is_some() {
+                            response.status(hyper::StatusCode::PARTIAL_CONTENT).header(
+                                hyper::header::CONTENT_RANGE,
+                                format!("bytes {start}-{}/{file_length}", end - 1),
+                            )
+                        } else {
+                            response
+                        };
+                        Ok(response.body(body).unwrap())
                     }
                 }
             }),
@@ -396,7 +456,11 @@ impl RequestMiddleware for RpcRequestMiddleware {
         if let Some(path) = match_supply_path(request.uri().path()) {
             process_rest(self.bank_forks.clone(), path)
         } else if self.is_file_get_path(request.uri().path()) {
-            self.process_file_get(request.uri().path())
+            let range = request
+                .headers()
+                .get(hyper::header::RANGE)
+                .and_then(|range| range.to_str().ok());
+            self.process_file_get(request.uri().path(), range)
         } else if request.uri().path() == "/health" {
             hyper::Response::builder()
                 .status(hyper::StatusCode::OK)
@@ -1129,6 +1193,46 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_parse_byte_range() {
+        assert_eq!(
+            RpcRequestMiddleware::parse_byte_range("bytes=0-9", 100),
+            Some(Some(0..10))
+        );
+        assert_eq!(
+            RpcRequestMiddleware::parse_byte_range("bytes=90-200", 100),
+            Some(Some(90..100))
+        );
+        assert_eq!(
+            RpcRequestMiddleware::parse_byte_range("bytes=42-", 100),
+            Some(Some(42..100))
+        );
+        assert_eq!(
+            RpcRequestMiddleware::parse_byte_range("bytes=-24", 100),
+            Some(Some(76..100))
+        );
+        assert_eq!(
+            RpcRequestMiddleware::parse_byte_range("bytes=-200", 100),
+            Some(Some(0..100))
+        );
+        assert_eq!(
+            RpcRequestMiddleware::parse_byte_range("bytes=100-", 100),
+            Some(None)
+        );
+        assert_eq!(
+            RpcRequestMiddleware::parse_byte_range("bytes=9-0", 100),
+            None
+        );
+        assert_eq!(
+            RpcRequestMiddleware::parse_byte_range("bytes=0-9,20-29", 100),
+            None
+        );
+        assert_eq!(
+            RpcRequestMiddleware::parse_byte_range("items=0-9", 100),
+            None
+        );
+    }
+
     #[test]
     fn test_process_file_get() {
         let runtime = Runtime::new().unwrap();
@@ -1147,7 +1251,7 @@ mod tests {
         );
 
         // File does not exist => request should fail.
-        let action = rrm.process_file_get(DEFAULT_GENESIS_DOWNLOAD_PATH);
+        let action = rrm.process_file_get(DEFAULT_GENESIS_DOWNLOAD_PATH, None);
         if let RequestMiddlewareAction::Respond { response, .. } = action {
             let response = runtime.block_on(response);
             let response = response.unwrap();
@@ -1162,7 +1266,7 @@ mod tests {
         }
 
         // Normal file exist => request should succeed.
-        let action = rrm.process_file_get(DEFAULT_GENESIS_DOWNLOAD_PATH);
+        let action = rrm.process_file_get(DEFAULT_GENESIS_DOWNLOAD_PATH, None);
         if let RequestMiddlewareAction::Respond { response, .. } = action {
             let response = runtime.block_on(response);
             let response = response.unwrap();
@@ -1171,6 +1275,34 @@ mod tests {
             panic!("Unexpected RequestMiddlewareAction variant");
         }
 
+        // Range of an existing file => request should return just that range.
+        let action = rrm.process_file_get(DEFAULT_GENESIS_DOWNLOAD_PATH, Some("bytes=3-5"));
+        if let RequestMiddlewareAction::Respond { response, .. } = action {
+            let response = runtime.block_on(response);
+            let response = response.unwrap();
+            assert_eq!(response.status(), 206);
+            assert_eq!(
+                response.headers()[hyper::header::CONTENT_RANGE],
+                "bytes 3-5/12"
+            );
+            let body = runtime
+                .block_on(hyper::body::to_bytes(response.into_body()))
+                .unwrap();
+            assert_eq!(&body[..], b"uld");
+        } else {
+            panic!("Unexpected RequestMiddlewareAction variant");
+        }
+
+        // Range past the end of the file => request should fail.
+        let action = rrm.process_file_get(DEFAULT_GENESIS_DOWNLOAD_PATH, Some("bytes=12-"));
+        if let RequestMiddlewareAction::Respond { response, .. } = action {
+            let response = runtime.block_on(response);
+            let response = response.unwrap();
+            assert_eq!(response.status(), 416);
+        } else {
+            panic!("Unexpected RequestMiddlewareAction variant");
+        }
+
         std::fs::remove_file(&genesis_path).unwrap();
         {
             let mut file = std::fs::File::create(ledger_path.path().join("wrong")).unwrap();
@@ -1179,7 +1311,7 @@ mod tests {
         symlink::symlink_file("wrong", &genesis_path).unwrap();
 
         // File is a symbolic link => request should fail.
-        let action = rrm.process_file_get(DEFAULT_GENESIS_DOWNLOAD_PATH);
+        let action = rrm.process_file_get(DEFAULT_GENESIS_DOWNLOAD_PATH, None);
         if let RequestMiddlewareAction::Respond { response, .. } = action {
             let response = runtime.block_on(response);
             let response = response.unwrap();
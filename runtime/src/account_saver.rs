@@ -221,6 +221,7 @@ mod tests {
                     return_data: None,
                     executed_units: 0,
                     accounts_deltas,
+                    compute_profile: None,
                 },
                 loaded_transaction,
                 programs_modified_by_tx: HashMap::new(),
@@ -0,0 +1,167 @@
+//! Changes simulated transactions would make to their writable accounts
+
+use {
+    solana_account::{AccountSharedData, ReadableAccount},
+    solana_pubkey::Pubkey,
+    solana_svm::transaction_processing_result::{
+        ProcessedTransaction, TransactionProcessingResult,
+    },
+    solana_svm_transaction::svm_message::SVMMessage,
+    solana_transaction_context::transaction_accounts::KeyedAccountSharedData,
+};
+
+/// Changed data bytes separated by fewer unchanged bytes than this are
+/// reported as a single range
+const MAX_UNCHANGED_GAP: usize = 8;
+
+/// Pre and post transaction state of a writable account
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountStateDiff {
+    pub pubkey: Pubkey,
+    pub pre_lamports: u64,
+    pub post_lamports: u64,
+    pub pre_owner: Pubkey,
+    pub post_owner: Pubkey,
+    pub pre_data_len: usize,
+    pub post_data_len: usize,
+    /// Ranges of the post transaction data that differ from the pre
+    /// transaction data, including data the account was extended by
+    pub data_diff: Vec<AccountDataDiff>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountDataDiff {
+    pub offset: usize,
+    /// Post transaction data starting at `offset`
+    pub data: Vec<u8>,
+}
+
+impl AccountStateDiff {
+    pub fn new(pubkey: Pubkey, pre: &AccountSharedData, post: &AccountSharedData) -> Self {
+        Self {
+            pubkey,
+            pre_lamports: pre.lamports(),
+            post_lamports: post.lamports(),
+            pre_owner: *pre.owner(),
+            post_owner: *post.owner(),
+            pre_data_len: pre.data().len(),
+            post_data_len: post.data().len(),
+            data_diff: diff_account_data(pre.data(), post.data()),
+        }
+    }
+
+    pub fn is_unchanged(&self) -> bool {
+        self.pre_lamports == self.post_lamports
+            && self.pre_owner == self.post_owner
+            && self.pre_data_len == self.post_data_len
+            && self.data_diff.is_empty()
+    }
+}
+
+/// Byte ranges of `post` that differ from `pre`. Shrinking is not reported as
+/// a range, it shows in the data lengths.
+pub fn diff_account_data(pre: &[u8], post: &[u8]) -> Vec<AccountDataDiff> {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for (offset, byte) in post.iter().enumerate() {
+        if pre.get(offset) == Some(byte) {
+            continue;
+        }
+        match ranges.last_mut() {
+            Some((_start, end)) if offset - *end < MAX_UNCHANGED_GAP => *end = offset + 1,
+            _ => ranges.push((offset, offset + 1)),
+        }
+    }
+    ranges
+        .into_iter()
+        .map(|(start, end)| AccountDataDiff {
+            offset: start,
+            data: post[start..end].to_vec(),
+        })
+        .collect()
+}
+
+/// Writable accounts a processed transaction would commit. Failed
+/// transactions only commit their fee payer and nonce accounts.
+pub(crate) fn committed_accounts<'a>(
+    transaction: &impl SVMMessage,
+    processing_result: &'a TransactionProcessingResult,
+) -> Vec<&'a KeyedAccountSharedData> {
+    match processing_result {
+        Ok(ProcessedTransaction::Executed(executed_tx)) if executed_tx.was_successful() => {
+            executed_tx
+                .loaded_transaction
+                .accounts
+                .iter()
+                .take(transaction.account_keys().len())
+                .enumerate()
+                .filter(|(index, _account)| transaction.is_writable(*index))
+                .map(|(_index, account)| account)
+                .collect()
+        }
+        Ok(ProcessedTransaction::Executed(executed_tx)) => executed_tx
+            .loaded_transaction
+            .rollback_accounts
+            .iter()
+            .collect(),
+        Ok(ProcessedTransaction::FeesOnly(fees_only_tx)) => {
+            fees_only_tx.rollback_accounts.iter().collect()
+        }
+        Ok(ProcessedTransaction::NoOp(_)) | Err(_) => vec![],
+    }
+}
+
+/// Diffs `pre_accounts`, the writable accounts of a transaction, against the
+/// `committed_accounts` of the transaction
+pub(crate) fn account_state_diffs(
+    pre_accounts: Vec<KeyedAccountSharedData>,
+    committed_accounts: &[&KeyedAccountSharedData],
+) -> Vec<AccountStateDiff> {
+    pre_accounts
+        .into_iter()
+        .map(|(pubkey, pre)| {
+            let post = committed_accounts
+                .iter()
+                .find(|(address, _account)| *address == pubkey)
+                .map_or(&pre, |(_address, account)| account);
+            AccountStateDiff::new(pubkey, &pre, post)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_account_data() {
+        assert_eq!(diff_account_data(&[1, 2, 3], &[1, 2, 3]), vec![]);
+        assert_eq!(diff_account_data(&[1, 2, 3], &[1, 2]), vec![]);
+        // Nearby changes and the extension are merged into one range
+        assert_eq!(
+            diff_account_data(&[1, 2, 3], &[1, 9, 3, 4]),
+            vec![AccountDataDiff {
+                offset: 1,
+                data: vec![9, 3, 4],
+            }]
+        );
+
+        let pre = [0; 32];
+        let mut post = pre;
+        post[0] = 1;
+        post[20] = 2;
+        post[21] = 3;
+        assert_eq!(
+            diff_account_data(&pre, &post),
+            vec![
+                AccountDataDiff {
+                    offset: 0,
+                    data: vec![1],
+                },
+                AccountDataDiff {
+                    offset: 20,
+                    data: vec![2, 3],
+                },
+            ]
+        );
+    }
+}
@@ -41,6 +41,7 @@ pub use {
 use {
     crate::{
         account_saver::collect_accounts_to_store,
+        account_state_diff::{AccountStateDiff, account_state_diffs, committed_accounts},
         alpenglow_epoch_type::AlpenglowEpochType,
         bank::{
             entry_bytes_budget::EntryBytesBudget,
@@ -58,10 +59,11 @@ use {
         },
         inflation_rewards::points::InflationPointCalculationEvent,
         installed_scheduler_pool::{BankWithScheduler, InstalledSchedulerRwLock},
+        jit_cache::{JitCache, JitCacheKey, environment_hash, feature_set_hash},
         leader_schedule_utils::leader_schedule_from_vote_accounts,
         rent_collector::RentCollector,
         reward_info::RewardInfo,
-        runtime_config::RuntimeConfig,
+        runtime_config::{RuntimeConfig, TransactionFixtureInput, TransactionFixtureRecorder},
         slot_params::{SlotParams, SlotParamsArchive},
         stake_account::StakeAccount,
         stake_history::StakeHistory as CowStakeHistory,
@@ -98,7 +100,10 @@ use {
     ahash::AHashSet,
     log::*,
     partitioned_epoch_rewards::PartitionedRewardsCalculation,
-    rayon::ThreadPool,
+    rayon::{
+        ThreadPool,
+        iter::{IntoParallelRefIterator, ParallelIterator},
+    },
     serde::{Deserialize, Serialize},
     solana_account::{
         Account, AccountSharedData, InheritableAccountFields, ReadableAccount, WritableAccount,
@@ -137,15 +142,18 @@ use {
     solana_inflation::Inflation,
     solana_keypair::Keypair,
     solana_lattice_hash::lt_hash::LtHash,
+    solana_loader_v3_interface::state::UpgradeableLoaderState,
     solana_measure::{measure::Measure, measure_time, measure_us},
-    solana_message::{
-        AccountKeys, SanitizedMessage, inner_instruction::InnerInstructions, v0::LoadedAddresses,
-    },
+    solana_message::{SanitizedMessage, inner_instruction::InnerInstructions, v0::LoadedAddresses},
     solana_packet::PACKET_DATA_SIZE,
     solana_precompile_error::PrecompileError,
     solana_program_runtime::{
-        invoke_context::BuiltinFunctionRegisterer,
-        loaded_programs::{ProgramRuntimeEnvironment, ProgramRuntimeEnvironments},
+        compute_profile::ComputeProfile,
+        debugger::DebuggerConfig,
+        invoke_context::{BuiltinFunctionRegisterer, RegisterTraceInspector},
+        loaded_programs::{
+            ProgramCacheEntrySummary, ProgramRuntimeEnvironment, ProgramRuntimeEnvironments,
+        },
         program_cache_entry::ProgramCacheEntry,
     },
     solana_pubkey::Pubkey,
@@ -164,6 +172,7 @@ use {
     solana_svm::{
         account_loader::LoadedTransaction,
         account_overrides::AccountOverrides,
+        program_loader::{load_program_elf, load_program_with_pubkey},
         transaction_balances::{BalanceCollector, SvmTokenInfo},
         transaction_commit_result::{CommittedTransaction, TransactionCommitResult},
         transaction_error_metrics::TransactionErrorMetrics,
@@ -202,7 +211,7 @@ use {
     solana_vote_interface::state::VoteStateV4,
     std::{
         collections::{HashMap, HashSet},
-        fmt,
+        fmt, io,
         ops::AddAssign,
         path::PathBuf,
         slice,
@@ -223,18 +232,16 @@ use {
 use {
     dashmap::DashSet,
     qualifier_attr::{field_qualifiers, qualifiers},
-    rayon::iter::{IntoParallelRefIterator, ParallelIterator},
     solana_accounts_db::accounts_db::{
         ACCOUNTS_DB_CONFIG_FOR_BENCHMARKS, ACCOUNTS_DB_CONFIG_FOR_TESTING,
     },
     solana_nonce as nonce,
     solana_nonce_account::{SystemAccountKind, get_system_account_kind},
     solana_program_runtime::sysvar_cache::SysvarCache,
-    solana_svm::program_loader::load_program_with_pubkey,
 };
 
 mod accounts_lt_hash;
-mod address_lookup_table;
+pub(crate) mod address_lookup_table;
 pub mod bank_hash_details;
 pub mod builtins;
 mod check_transactions;
@@ -376,11 +383,32 @@ pub struct TransactionSimulationResult {
     pub post_balances: Option<Vec<u64>>,
     pub pre_token_balances: Option<Vec<SvmTokenInfo>>,
     pub post_token_balances: Option<Vec<SvmTokenInfo>>,
+    pub compute_profile: Option<ComputeProfile>,
+    pub account_diffs: Option<Vec<AccountStateDiff>>,
+}
+
+/// Results of `Bank::simulate_transactions_with_config()`, in the order of the transactions
+#[derive(Debug, PartialEq)]
+pub struct BundleSimulationResult {
+    pub transaction_results: Vec<TransactionSimulationResult>,
+    /// State of every account written by the transactions after the last one
+    pub post_simulation_accounts: HashMap<Pubkey, AccountSharedData>,
+}
+
+/// Optional recording for `Bank::simulate_transaction_with_config()`
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SimulationConfig {
+    pub enable_cpi_recording: bool,
+    pub enable_compute_profile_recording: bool,
+    /// Diff every writable account against its state before the transaction
+    pub enable_account_diffs: bool,
 }
 
 impl TransactionSimulationResult {
     pub fn new_error(err: TransactionError) -> Self {
         Self {
+            account_diffs: None,
+            compute_profile: None,
             fee: None,
             inner_instructions: None,
             loaded_accounts_data_size: 0,
@@ -706,6 +734,9 @@ impl PartialEq for Bank {
             collector_fee_details: _,
             compute_budget: _,
             transaction_account_lock_limit: _,
+            register_trace_inspector: _,
+            transaction_fixture_recorder: _,
+            sbpf_debugger: _,
             fee_structure: _,
             accounts_lt_hash_async_progress: _,
             block_id,
@@ -1038,6 +1069,17 @@ pub struct Bank {
     /// The max number of accounts that a transaction may lock.
     transaction_account_lock_limit: Option<usize>,
 
+    /// Receives the VM register traces of SBF programs, which are only
+    /// recorded if this is set.
+    register_trace_inspector: Option<Arc<dyn RegisterTraceInspector>>,
+
+    /// Records the executed transactions it selects, along with the state
+    /// they were executed against
+    transaction_fixture_recorder: Option<Arc<dyn TransactionFixtureRecorder>>,
+
+    /// SBF program invocations to host a debugger session for
+    sbpf_debugger: Option<DebuggerConfig>,
+
     /// Fee structure to use for assessing transaction fees.
     fee_structure: FeeStructure,
 
@@ -1273,6 +1315,9 @@ impl Bank {
             collector_fee_details: RwLock::new(CollectorFeeDetails::default()),
             compute_budget: None,
             transaction_account_lock_limit: None,
+            register_trace_inspector: None,
+            transaction_fixture_recorder: None,
+            sbpf_debugger: None,
             fee_structure: FeeStructure::default(),
             #[cfg(feature = "dev-context-only-utils")]
             hash_overrides: Arc::new(Mutex::new(HashOverrides::default())),
@@ -1328,6 +1373,9 @@ impl Bank {
                 .set_execution_cost(compute_budget.to_cost());
         }
         bank.transaction_account_lock_limit = runtime_config.transaction_account_lock_limit;
+        bank.register_trace_inspector = runtime_config.register_trace_inspector.clone();
+        bank.transaction_fixture_recorder = runtime_config.transaction_fixture_recorder.clone();
+        bank.sbpf_debugger = runtime_config.sbpf_debugger.clone();
         bank.transaction_debug_keys = debug_keys;
         bank.cluster_type = Some(genesis_config.cluster_type);
 
@@ -1540,6 +1588,9 @@ impl Bank {
             collector_fee_details: RwLock::new(CollectorFeeDetails::default()),
             compute_budget: parent.compute_budget,
             transaction_account_lock_limit: parent.transaction_account_lock_limit,
+            register_trace_inspector: parent.register_trace_inspector.clone(),
+            transaction_fixture_recorder: parent.transaction_fixture_recorder.clone(),
+            sbpf_debugger: parent.sbpf_debugger.clone(),
             fee_structure: parent.fee_structure.clone(),
             #[cfg(feature = "dev-context-only-utils")]
             hash_overrides: parent.hash_overrides.clone(),
@@ -2205,6 +2256,9 @@ impl Bank {
             collector_fee_details: RwLock::new(CollectorFeeDetails::default()),
             compute_budget: runtime_config.compute_budget,
             transaction_account_lock_limit: runtime_config.transaction_account_lock_limit,
+            register_trace_inspector: runtime_config.register_trace_inspector.clone(),
+            transaction_fixture_recorder: runtime_config.transaction_fixture_recorder.clone(),
+            sbpf_debugger: runtime_config.sbpf_debugger.clone(),
             fee_structure: FeeStructure::default(),
             #[cfg(feature = "dev-context-only-utils")]
             hash_overrides: Arc::new(Mutex::new(HashOverrides::default())),
@@ -3841,10 +3895,25 @@ impl Bank {
         &self,
         transaction: &impl TransactionWithMeta,
         enable_cpi_recording: bool,
+    ) -> TransactionSimulationResult {
+        self.simulate_transaction_with_config(
+            transaction,
+            SimulationConfig {
+                enable_cpi_recording,
+                ..SimulationConfig::default()
+            },
+        )
+    }
+
+    /// Run transactions against a frozen bank without committing the results
+    pub fn simulate_transaction_with_config(
+        &self,
+        transaction: &impl TransactionWithMeta,
+        config: SimulationConfig,
     ) -> TransactionSimulationResult {
         assert!(self.is_frozen(), "simulation bank must be frozen");
 
-        self.simulate_transaction_unchecked(transaction, enable_cpi_recording)
+        self.simulate_transaction_unchecked_with_config(transaction, config)
     }
 
     /// Run transactions against a bank without committing the results; does not check if the bank
@@ -3853,10 +3922,111 @@ impl Bank {
         &self,
         transaction: &impl TransactionWithMeta,
         enable_cpi_recording: bool,
+    ) -> TransactionSimulationResult {
+        self.simulate_transaction_unchecked_with_config(
+            transaction,
+            SimulationConfig {
+                enable_cpi_recording,
+                ..SimulationConfig::default()
+            },
+        )
+    }
+
+    /// Run transactions against a bank without committing the results; does not check if the bank
+    /// is frozen, enabling use in single-Bank test frameworks
+    pub fn simulate_transaction_unchecked_with_config(
+        &self,
+        transaction: &impl TransactionWithMeta,
+        config: SimulationConfig,
+    ) -> TransactionSimulationResult {
+        let account_overrides =
+            self.get_account_overrides_for_simulation(slice::from_ref(transaction));
+        self.simulate_transaction_with_overrides(transaction, config, &account_overrides, |_| ())
+    }
+
+    /// Run transactions in order against a frozen bank without committing the results. Each
+    /// transaction sees the state changes of the transactions before it.
+    pub fn simulate_transactions_with_config(
+        &self,
+        transactions: &[impl TransactionWithMeta],
+        config: SimulationConfig,
+    ) -> BundleSimulationResult {
+        assert!(self.is_frozen(), "simulation bank must be frozen");
+
+        self.simulate_transactions_unchecked_with_config(transactions, config)
+    }
+
+    /// Run transactions in order against a bank without committing the results; does not check if
+    /// the bank is frozen, enabling use in single-Bank test frameworks
+    pub fn simulate_transactions_unchecked_with_config(
+        &self,
+        transactions: &[impl TransactionWithMeta],
+        config: SimulationConfig,
+    ) -> BundleSimulationResult {
+        // The account overrides act as a scratch fork of the bank: the accounts each transaction
+        // would commit are layered over the bank for the transactions after it
+        let mut account_overrides = self.get_account_overrides_for_simulation(transactions);
+        let mut written_accounts = HashSet::new();
+        let transaction_results = transactions
+            .iter()
+            .map(|transaction| {
+                let mut committed_accounts = vec![];
+                let result = self.simulate_transaction_with_overrides(
+                    transaction,
+                    config,
+                    &account_overrides,
+                    |accounts| {
+                        committed_accounts.extend(
+                            accounts
+                                .iter()
+                                .map(|(pubkey, account)| (*pubkey, account.clone())),
+                        )
+                    },
+                );
+                for (pubkey, account) in committed_accounts {
+                    account_overrides.set_account(&pubkey, Some(account));
+                    written_accounts.insert(pubkey);
+                }
+                result
+            })
+            .collect();
+        let post_simulation_accounts = written_accounts
+            .into_iter()
+            .filter_map(|pubkey| Some((pubkey, account_overrides.get(&pubkey)?.clone())))
+            .collect();
+
+        BundleSimulationResult {
+            transaction_results,
+            post_simulation_accounts,
+        }
+    }
+
+    /// Run a transaction against the bank with `account_overrides` layered over it, handing the
+    /// writable accounts it would commit to `on_committed_accounts`
+    fn simulate_transaction_with_overrides(
+        &self,
+        transaction: &impl TransactionWithMeta,
+        config: SimulationConfig,
+        account_overrides: &AccountOverrides,
+        on_committed_accounts: impl FnOnce(&[&KeyedAccountSharedData]),
     ) -> TransactionSimulationResult {
         let account_keys = transaction.account_keys();
         let number_of_accounts = account_keys.len();
-        let account_overrides = self.get_account_overrides_for_simulation(&account_keys);
+        let pre_accounts = config.enable_account_diffs.then(|| {
+            account_keys
+                .iter()
+                .enumerate()
+                .filter(|(index, _pubkey)| transaction.is_writable(*index))
+                .map(|(_index, pubkey)| {
+                    let account = account_overrides
+                        .get(pubkey)
+                        .cloned()
+                        .or_else(|| self.get_account(pubkey))
+                        .unwrap_or_default();
+                    (*pubkey, account)
+                })
+                .collect::<Vec<_>>()
+        });
         let batch = self.prepare_unlocked_batch_from_single_tx(transaction);
         let mut timings = ExecuteTimings::default();
 
@@ -3874,15 +4044,18 @@ impl Bank {
             &mut timings,
             &mut TransactionErrorMetrics::default(),
             TransactionProcessingConfig {
-                account_overrides: Some(&account_overrides),
+                account_overrides: Some(account_overrides),
                 log_messages_bytes_limit: None,
                 limit_to_load_programs: true,
                 recording_config: ExecutionRecordingConfig {
-                    enable_cpi_recording,
+                    enable_cpi_recording: config.enable_cpi_recording,
                     enable_log_recording: true,
                     enable_return_data_recording: true,
                     enable_transaction_balance_recording: true,
+                    enable_compute_profile_recording: config.enable_compute_profile_recording,
                 },
+                debugger_config: None,
+                register_trace_inspector: None,
                 drop_on_failure: false,
                 all_or_nothing: false,
                 strict_nonce_size_check: true,
@@ -3895,6 +4068,11 @@ impl Bank {
         let processing_result = processing_results
             .pop()
             .unwrap_or(Err(TransactionError::InvalidProgramForExecution));
+        let committed = committed_accounts(transaction, &processing_result);
+        let account_diffs =
+            pre_accounts.map(|pre_accounts| account_state_diffs(pre_accounts, &committed));
+        on_committed_accounts(&committed);
+
         let (
             post_simulation_accounts,
             result,
@@ -3904,6 +4082,7 @@ impl Bank {
             inner_instructions,
             units_consumed,
             loaded_accounts_data_size,
+            compute_profile,
         ) = match processing_result {
             Ok(processed_tx) => {
                 let executed_units = processed_tx.executed_units();
@@ -3927,6 +4106,7 @@ impl Bank {
                             details.inner_instructions,
                             executed_units,
                             loaded_accounts_data_size,
+                            details.compute_profile,
                         )
                     }
                     ProcessedTransaction::FeesOnly(fees_only_tx) => (
@@ -3938,6 +4118,7 @@ impl Bank {
                         None,
                         executed_units,
                         loaded_accounts_data_size,
+                        None,
                     ),
                     ProcessedTransaction::NoOp(no_op_tx) => (
                         vec![],
@@ -3948,10 +4129,11 @@ impl Bank {
                         None,
                         executed_units,
                         loaded_accounts_data_size,
+                        None,
                     ),
                 }
             }
-            Err(error) => (vec![], Err(error), None, None, None, None, 0, 0),
+            Err(error) => (vec![], Err(error), None, None, None, None, 0, 0, None),
         };
         let logs = logs.unwrap_or_default();
 
@@ -3984,13 +4166,23 @@ impl Bank {
             post_balances,
             pre_token_balances,
             post_token_balances,
+            compute_profile,
+            account_diffs,
         }
     }
 
-    fn get_account_overrides_for_simulation(&self, account_keys: &AccountKeys) -> AccountOverrides {
+    fn get_account_overrides_for_simulation(
+        &self,
+        transactions: &[impl SVMMessage],
+    ) -> AccountOverrides {
         let mut account_overrides = AccountOverrides::default();
         let slot_history_id = sysvar::slot_history::id();
-        if account_keys.iter().any(|pubkey| *pubkey == slot_history_id) {
+        if transactions.iter().any(|transaction| {
+            transaction
+                .account_keys()
+                .iter()
+                .any(|pubkey| *pubkey == slot_history_id)
+        }) {
             let current_account = self.get_account_with_fixed_root(&slot_history_id);
             let slot_history = current_account
                 .as_ref()
@@ -4064,6 +4256,20 @@ impl Bank {
         ));
         timings.saturating_add_in_place(ExecuteTimingType::CheckUs, check_us);
 
+        // Collected before execution, as the batch's writes are visible to
+        // the bank right after
+        let fixture_inputs = self.collect_transaction_fixture_inputs(sanitized_txs);
+
+        let processing_config = TransactionProcessingConfig {
+            debugger_config: processing_config
+                .debugger_config
+                .or(self.sbpf_debugger.as_ref()),
+            register_trace_inspector: processing_config
+                .register_trace_inspector
+                .or(self.register_trace_inspector.as_deref()),
+            ..processing_config
+        };
+
         let (blockhash, blockhash_lamports_per_signature) =
             self.last_blockhash_and_lamports_per_signature();
         let effective_epoch_of_deployments =
@@ -4106,6 +4312,16 @@ impl Bank {
             measure_us!(self.collect_logs(sanitized_txs, &sanitized_output.processing_results));
         timings.saturating_add_in_place(ExecuteTimingType::CollectLogsUs, collect_logs_us);
 
+        if let Some(recorder) = &self.transaction_fixture_recorder {
+            for (index, input) in fixture_inputs {
+                recorder.record(
+                    &sanitized_txs[index].as_sanitized_transaction(),
+                    input,
+                    &sanitized_output.processing_results[index],
+                );
+            }
+        }
+
         let mut processed_counts = ProcessedTransactionCounts::default();
         let err_count = &mut error_counters.total;
 
@@ -4157,6 +4373,93 @@ impl Bank {
         }
     }
 
+    /// Inputs of the transactions the fixture recorder selects, by index in
+    /// `transactions`
+    fn collect_transaction_fixture_inputs(
+        &self,
+        transactions: &[impl TransactionWithMeta],
+    ) -> Vec<(usize, TransactionFixtureInput)> {
+        let Some(recorder) = &self.transaction_fixture_recorder else {
+            return vec![];
+        };
+        transactions
+            .iter()
+            .enumerate()
+            .filter_map(|(index, transaction)| {
+                let transaction = transaction.as_sanitized_transaction();
+                recorder
+                    .should_record(&transaction)
+                    .then(|| (index, self.transaction_fixture_input(transaction.message())))
+            })
+            .collect()
+    }
+
+    fn transaction_fixture_input(&self, message: &SanitizedMessage) -> TransactionFixtureInput {
+        let mut addresses: Vec<Pubkey> = message.account_keys().iter().copied().collect();
+        addresses.extend(
+            message
+                .message_address_table_lookups()
+                .iter()
+                .map(|lookup| lookup.account_key),
+        );
+        #[expect(deprecated)]
+        addresses.extend([
+            sysvar::clock::id(),
+            sysvar::epoch_rewards::id(),
+            sysvar::epoch_schedule::id(),
+            sysvar::last_restart_slot::id(),
+            sysvar::recent_blockhashes::id(),
+            sysvar::rent::id(),
+            sysvar::slot_hashes::id(),
+            stake_history::id(),
+        ]);
+
+        let mut accounts: Vec<(Pubkey, AccountSharedData)> = Vec::new();
+        let mut index = 0;
+        while let Some(address) = addresses.get(index).copied() {
+            index += 1;
+            if accounts.iter().any(|(pubkey, _account)| *pubkey == address) {
+                continue;
+            }
+            let Some(account) = self.get_account_with_fixed_root(&address) else {
+                continue;
+            };
+            // Invoked programs need their loader and, for upgradeable
+            // programs, their programdata
+            if account.executable() {
+                addresses.push(*account.owner());
+            }
+            if bpf_loader_upgradeable::check_id(account.owner())
+                && let Ok(UpgradeableLoaderState::Program {
+                    programdata_address,
+                }) = account.deserialize_data()
+            {
+                addresses.push(programdata_address);
+            }
+            accounts.push((address, account));
+        }
+
+        let mut blockhash_queue: Vec<_> = self
+            .blockhash_queue
+            .read()
+            .unwrap()
+            .get_recent_blockhashes()
+            .map(|item| (item.0, *item.1, item.2))
+            .collect();
+        blockhash_queue.sort_unstable_by_key(|(hash_index, _hash, _lamports)| *hash_index);
+
+        TransactionFixtureInput {
+            accounts,
+            feature_set: Arc::clone(&self.feature_set),
+            blockhash_queue: blockhash_queue
+                .into_iter()
+                .map(|(_hash_index, hash, lamports_per_signature)| (hash, lamports_per_signature))
+                .collect(),
+            fee_rate_governor: self.fee_rate_governor.clone(),
+            total_epoch_stake: self.get_current_epoch_total_stake(),
+        }
+    }
+
     fn collect_logs(
         &self,
         transactions: &[impl TransactionWithMeta],
@@ -4640,6 +4943,8 @@ impl Bank {
                 log_messages_bytes_limit,
                 limit_to_load_programs: false,
                 recording_config,
+                debugger_config: None,
+                register_trace_inspector: None,
                 drop_on_failure: false,
                 all_or_nothing: false,
                 strict_nonce_size_check: false,
@@ -4682,6 +4987,7 @@ impl Bank {
                 enable_log_recording: true,
                 enable_return_data_recording: true,
                 enable_transaction_balance_recording: false,
+                enable_compute_profile_recording: false,
             },
             &mut ExecuteTimings::default(),
             Some(1000 * 1000),
@@ -5023,15 +5329,143 @@ impl Bank {
             .as_ref()
             .unwrap_or(&ComputeBudget::new_with_defaults(simd_0268_active))
             .to_budget();
+        // Register traces are only recorded for the inspector
+        let debugging_features = self.register_trace_inspector.is_some();
         create_program_runtime_environment(
             &feature_set.runtime_features(),
             &compute_budget,
             false, /* deployment */
-            false, /* debugging_features */
+            debugging_features,
         )
         .unwrap()
     }
 
+    /// Loads, verifies and compiles `program_ids` into the program cache
+    /// ahead of their first use, in parallel. Programs which do not exist,
+    /// fail verification or are cached already are skipped. Returns the
+    /// number of programs inserted.
+    pub fn prewarm_program_cache(&self, program_ids: &[Pubkey]) -> usize {
+        let environment = &self.transaction_processor.program_runtime_environment;
+        program_ids
+            .par_iter()
+            .filter(|program_id| {
+                let Some((entry, _last_modification_slot)) = load_program_with_pubkey(
+                    self,
+                    environment,
+                    program_id,
+                    self.slot(),
+                    &mut ExecuteTimings::default(),
+                ) else {
+                    return false;
+                };
+                self.transaction_processor
+                    .global_program_cache
+                    .write()
+                    .unwrap()
+                    .prewarm_program(environment, **program_id, entry)
+            })
+            .count()
+    }
+
+    /// Exempts the compiled executables of `program_id` from eviction out of
+    /// the program cache, which is shared by all banks. Returns false if the
+    /// program was pinned already.
+    pub fn pin_cached_program(&self, program_id: Pubkey) -> bool {
+        self.transaction_processor
+            .global_program_cache
+            .write()
+            .unwrap()
+            .pin_program(program_id)
+    }
+
+    /// Returns false if `program_id` was not pinned
+    pub fn unpin_cached_program(&self, program_id: &Pubkey) -> bool {
+        self.transaction_processor
+            .global_program_cache
+            .write()
+            .unwrap()
+            .unpin_program(program_id)
+    }
+
+    /// Returns the state and usage statistics of every program cache entry
+    pub fn program_cache_entries(&self) -> Vec<ProgramCacheEntrySummary> {
+        self.transaction_processor
+            .global_program_cache
+            .read()
+            .unwrap()
+            .entry_summaries()
+    }
+
+    /// Records the programs compiled into the program cache for the runtime
+    /// environment of this bank in `jit_cache`. Returns the number of
+    /// programs recorded.
+    pub fn save_jit_cache(&self, jit_cache: &JitCache) -> usize {
+        let environment = &self.transaction_processor.program_runtime_environment;
+        let mut program_ids = self
+            .transaction_processor
+            .global_program_cache
+            .read()
+            .unwrap()
+            .get_flattened_entries()
+            .into_iter()
+            .filter(|(_program_id, _slot, entry)| {
+                entry.program.get_environment() == Some(environment)
+            })
+            .map(|(program_id, _slot, _entry)| program_id)
+            .collect::<Vec<_>>();
+        program_ids.sort_unstable();
+        program_ids.dedup();
+
+        let environment_hash = environment_hash(environment);
+        let feature_set_hash = feature_set_hash(&self.feature_set);
+        program_ids
+            .into_iter()
+            .filter_map(|program_id| {
+                Some(JitCacheKey {
+                    program_id,
+                    program_data_hash: self.program_data_hash(&program_id)?,
+                    environment_hash,
+                    feature_set_hash,
+                })
+            })
+            .filter(|key| {
+                jit_cache
+                    .store(key)
+                    .inspect_err(|err| {
+                        warn!(
+                            "Failed to record {} in the JIT cache: {err}",
+                            key.program_id
+                        )
+                    })
+                    .is_ok()
+            })
+            .count()
+    }
+
+    /// Compiles the programs recorded in `jit_cache` into the program cache,
+    /// if this bank holds the same version of the program and has the same
+    /// runtime environment and features as the bank they were recorded from.
+    /// Other records are removed. Returns the number of programs compiled.
+    pub fn load_jit_cache(&self, jit_cache: &JitCache) -> io::Result<usize> {
+        let environment_hash =
+            environment_hash(&self.transaction_processor.program_runtime_environment);
+        let feature_set_hash = feature_set_hash(&self.feature_set);
+        let program_ids = jit_cache
+            .load(|key| {
+                key.environment_hash == environment_hash
+                    && key.feature_set_hash == feature_set_hash
+                    && self.program_data_hash(&key.program_id) == Some(key.program_data_hash)
+            })?
+            .into_iter()
+            .map(|key| key.program_id)
+            .collect::<Vec<_>>();
+        Ok(self.prewarm_program_cache(&program_ids))
+    }
+
+    fn program_data_hash(&self, program_id: &Pubkey) -> Option<Hash> {
+        load_program_elf(self, program_id).map(|elf| hashv(&[&elf]))
+    }
+
     pub fn set_tick_height(&self, tick_height: u64) {
         self.tick_height.store(tick_height, Relaxed)
     }
@@ -5135,6 +5569,16 @@ impl Bank {
         self.load_slow(&self.ancestors, pubkey)
     }
 
+    // Same as get_account() for each of `pubkeys`, in order, with the loads from storage batched
+    pub fn get_multiple_accounts(&self, pubkeys: &[Pubkey]) -> Vec<Option<AccountSharedData>> {
+        self.rc
+            .accounts
+            .load_many_without_fixed_root(&self.ancestors, pubkeys)
+            .into_iter()
+            .map(|account| account.map(|(acc, _slot)| acc))
+            .collect()
+    }
+
     fn load_slow(
         &self,
         ancestors: &Ancestors,
@@ -5387,23 +5831,37 @@ impl Bank {
         self.get_signature_status_slot(signature).is_some()
     }
 
+    /// Hashes the components of a bank hash, before any hard fork is mixed in
+    fn hash_bank_hash_components(
+        parent_hash: &Hash,
+        signature_count: u64,
+        last_blockhash: &Hash,
+        accounts_lt_hash: &AccountsLtHash,
+    ) -> Hash {
+        let hash = hashv(&[
+            parent_hash.as_ref(),
+            &signature_count.to_le_bytes(),
+            last_blockhash.as_ref(),
+        ]);
+        let lt_hash_bytes = bytemuck::must_cast_slice(&accounts_lt_hash.0.0);
+        hashv(&[hash.as_ref(), lt_hash_bytes])
+    }
+
     /// Hash the `accounts` HashMap. This represents a validator's interpretation
     ///  of the delta of the ledger since the last vote and up to now
     fn hash_internal_state(&self) -> Hash {
         let measure_total = Measure::start("");
         let slot = self.slot();
 
-        let mut hash = hashv(&[
-            self.parent_hash.as_ref(),
-            &self.signature_count().to_le_bytes(),
-            self.last_blockhash().as_ref(),
-        ]);
-
-        let accounts_lt_hash_checksum = {
+        let (mut hash, accounts_lt_hash_checksum) = {
             let accounts_lt_hash = &*self.accounts_lt_hash.lock().unwrap();
-            let lt_hash_bytes = bytemuck::must_cast_slice(&accounts_lt_hash.0.0);
-            hash = hashv(&[hash.as_ref(), lt_hash_bytes]);
-            accounts_lt_hash.0.checksum()
+            let hash = Self::hash_bank_hash_components(
+                &self.parent_hash,
+                self.signature_count(),
+                &self.last_blockhash(),
+                accounts_lt_hash,
+            );
+            (hash, accounts_lt_hash.0.checksum())
         };
 
         let buf = self
@@ -6791,6 +7249,15 @@ impl TransactionProcessingCallback for Bank {
             .load_with_fixed_root(&self.ancestors, pubkey, None::<fn(_, &_, _) -> _>)
     }
 
+    fn get_multiple_account_shared_data(
+        &self,
+        pubkeys: &[Pubkey],
+    ) -> Vec<Option<(AccountSharedData, Slot)>> {
+        self.rc
+            .accounts
+            .load_many_with_fixed_root(&self.ancestors, pubkeys)
+    }
+
     fn inspect_account(&self, _address: &Pubkey, _account_state: AccountState, _is_writable: bool) {
         // nothing to do here
     }
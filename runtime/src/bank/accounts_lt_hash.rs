@@ -10,7 +10,9 @@ use {
         iter::{IntoParallelIterator, ParallelIterator},
     },
     solana_account::{AccountSharedData, ReadableAccount},
-    solana_accounts_db::{accounts_db::AccountsDb, storable_accounts::StorableAccounts},
+    solana_accounts_db::{
+        accounts_db::AccountsDb, accounts_hash::AccountsLtHash, storable_accounts::StorableAccounts,
+    },
     solana_lattice_hash::lt_hash::LtHash,
     solana_pubkey::Pubkey,
     std::{
@@ -34,6 +36,13 @@ const NUM_ACCOUNTS_HASHER_THREADS: usize = 4;
 const MAX_BYTES_SEEN_ACCOUNTS_FREELIST: usize = 10_000_000;
 
 impl Bank {
+    /// Returns the accounts lt hash of this bank
+    ///
+    /// The accounts lt hash is only complete once the bank is frozen.
+    pub fn accounts_lt_hash(&self) -> AccountsLtHash {
+        self.accounts_lt_hash.lock().unwrap().clone()
+    }
+
     /// Enqueues the accounts lt hash updates for `accounts` to the accounts hasher threads.
     ///
     /// This fn is meant to be called by on-chain events, e.g. transaction processing.
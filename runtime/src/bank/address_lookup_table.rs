@@ -10,7 +10,7 @@ use {
     solana_transaction_error::AddressLoaderError,
 };
 
-fn into_address_loader_error(err: AddressLookupError) -> AddressLoaderError {
+pub(crate) fn into_address_loader_error(err: AddressLookupError) -> AddressLoaderError {
     match err {
         AddressLookupError::LookupTableAccountNotFound => {
             AddressLoaderError::LookupTableAccountNotFound
@@ -10,10 +10,16 @@ use {
         ser::{SerializeSeq, Serializer},
     },
     solana_account::{Account, AccountSharedData, ReadableAccount},
+    solana_accounts_db::{
+        accounts_db::AccountsDb,
+        accounts_hash::{AccountLtHash, AccountsLtHash, ZERO_LAMPORT_ACCOUNT_LT_HASH},
+    },
     solana_clock::Slot,
     solana_fee_structure::FeeDetails,
+    solana_hash::Hash,
     solana_message::inner_instruction::InnerInstructionsList,
     solana_pubkey::Pubkey,
+    solana_sha256_hasher::hashv,
     solana_svm::transaction_commit_result::CommittedTransaction,
     solana_transaction_context::transaction::TransactionReturnData,
     solana_transaction_error::TransactionResult,
@@ -156,6 +162,114 @@ impl SlotDetails {
     }
 }
 
+/// The contribution of an account to the accounts lt hash of a bank
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AccountLtHashDetails {
+    pub pubkey: Pubkey,
+    /// The account, or None if it does not exist or has zero lamports
+    pub account: Option<AccountSharedData>,
+    pub lt_hash: AccountLtHash,
+}
+
+/// The contributions of a set of accounts to the accounts lt hash of a bank, along with the
+/// components of its bank hash
+///
+/// The accounts lt hash of a bank is the sum of the lt hashes of all its accounts, so this alone
+/// does not prove that the accounts are part of the bank. It does let the contents of the
+/// accounts be checked against the bank hash, once combined with the lt hashes of the other
+/// accounts, such as from a snapshot of the bank.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AccountsLtHashProof {
+    pub slot: Slot,
+    pub bank_hash: Hash,
+    pub parent_bank_hash: Hash,
+    pub signature_count: u64,
+    pub last_blockhash: Hash,
+    pub accounts_lt_hash: AccountsLtHash,
+    /// The data mixed into the bank hash, if the bank is a hard fork
+    pub hard_fork_data: Option<Vec<u8>>,
+    pub accounts: Vec<AccountLtHashDetails>,
+}
+
+impl AccountsLtHashProof {
+    pub fn new_from_bank(bank: &Bank, pubkeys: &[Pubkey]) -> Result<Self, String> {
+        let slot = bank.slot();
+        if !bank.is_frozen() {
+            return Err(format!(
+                "Bank {slot} must be frozen in order to get accounts lt hash proof"
+            ));
+        }
+
+        let accounts = pubkeys
+            .iter()
+            .map(|pubkey| {
+                let account = bank.get_account(pubkey);
+                let lt_hash = account_lt_hash(pubkey, account.as_ref());
+                AccountLtHashDetails {
+                    pubkey: *pubkey,
+                    account,
+                    lt_hash,
+                }
+            })
+            .collect();
+
+        Ok(Self {
+            slot,
+            bank_hash: bank.hash(),
+            parent_bank_hash: bank.parent_hash(),
+            signature_count: bank.signature_count(),
+            last_blockhash: bank.last_blockhash(),
+            accounts_lt_hash: bank.accounts_lt_hash(),
+            hard_fork_data: bank
+                .hard_forks
+                .read()
+                .unwrap()
+                .get_hash_data(slot, bank.parent_slot())
+                .map(Vec::from),
+            accounts,
+        })
+    }
+
+    /// Checks that the bank hash is the hash of its components, and that the lt hash of each
+    /// account is the hash of its contents
+    pub fn verify(&self) -> Result<(), String> {
+        let mut bank_hash = Bank::hash_bank_hash_components(
+            &self.parent_bank_hash,
+            self.signature_count,
+            &self.last_blockhash,
+            &self.accounts_lt_hash,
+        );
+        if let Some(hard_fork_data) = &self.hard_fork_data {
+            bank_hash = hashv(&[bank_hash.as_ref(), hard_fork_data]);
+        }
+        if bank_hash != self.bank_hash {
+            return Err(format!(
+                "bank hash mismatch for slot {}: expected {}, calculated {bank_hash}",
+                self.slot, self.bank_hash,
+            ));
+        }
+
+        for details in &self.accounts {
+            let lt_hash = account_lt_hash(&details.pubkey, details.account.as_ref());
+            if lt_hash != details.lt_hash {
+                return Err(format!(
+                    "accounts lt hash mismatch for account {}: expected {}, calculated {}",
+                    details.pubkey,
+                    details.lt_hash.0.checksum(),
+                    lt_hash.0.checksum(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn account_lt_hash(pubkey: &Pubkey, account: Option<&AccountSharedData>) -> AccountLtHash {
+    account.map_or(ZERO_LAMPORT_ACCOUNT_LT_HASH, |account| {
+        AccountsDb::lt_hash_account(account, pubkey)
+    })
+}
+
 /// Wrapper around a Vec<_> to facilitate custom Serialize/Deserialize trait
 /// implementations.
 #[derive(Clone, Debug, Eq, PartialEq, Default)]
@@ -276,7 +390,10 @@ pub fn write_bank_hash_details_file(bank: &Bank) -> std::result::Result<(), Stri
 
 #[cfg(test)]
 pub mod tests {
-    use super::*;
+    use {
+        super::*, solana_account::WritableAccount, solana_native_token::LAMPORTS_PER_SOL,
+        solana_signer::Signer,
+    };
 
     fn build_details(num_slots: usize) -> BankHashDetails {
         let slot_details: Vec<_> = (0..num_slots)
@@ -313,6 +430,41 @@ pub mod tests {
         BankHashDetails::new(slot_details)
     }
 
+    #[test]
+    fn test_accounts_lt_hash_proof() {
+        let (genesis_config, mint_keypair) =
+            solana_genesis_config::create_genesis_config(LAMPORTS_PER_SOL);
+        let bank = Bank::new_for_tests(&genesis_config);
+        let missing_pubkey = Pubkey::new_unique();
+        let pubkeys = [mint_keypair.pubkey(), missing_pubkey];
+        assert!(AccountsLtHashProof::new_from_bank(&bank, &pubkeys).is_err());
+        bank.freeze();
+
+        let proof = AccountsLtHashProof::new_from_bank(&bank, &pubkeys).unwrap();
+        assert_eq!(proof.bank_hash, bank.hash());
+        assert_eq!(proof.hard_fork_data, None);
+        assert_eq!(
+            proof.accounts[0].account.as_ref().unwrap().lamports(),
+            LAMPORTS_PER_SOL
+        );
+        assert_eq!(proof.accounts[1].account, None);
+        assert_eq!(proof.accounts[1].lt_hash, ZERO_LAMPORT_ACCOUNT_LT_HASH);
+        proof.verify().unwrap();
+
+        let mut tampered = proof.clone();
+        tampered.signature_count += 1;
+        assert!(tampered.verify().is_err());
+
+        let mut tampered = proof;
+        tampered.accounts[0]
+            .account
+            .as_mut()
+            .unwrap()
+            .checked_add_lamports(1)
+            .unwrap();
+        assert!(tampered.verify().is_err());
+    }
+
     #[test]
     fn test_serde_bank_hash_details() {
         let num_slots = 10;
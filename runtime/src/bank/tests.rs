@@ -106,6 +106,7 @@ use {
     solana_program_runtime::{
         declare_process_instruction,
         execution_budget::{self, MAX_COMPUTE_UNIT_LIMIT},
+        loaded_programs::ProgramCacheEntryTier,
         program_cache_entry::{ProgramCacheEntry, ProgramCacheEntryType},
         solana_sbpf::program::BuiltinFunctionDefinition,
     },
@@ -277,6 +278,7 @@ fn new_executed_processing_result(
                 return_data: None,
                 executed_units: 0,
                 accounts_deltas,
+                compute_profile: None,
             },
             programs_modified_by_tx: HashMap::new(),
         },
@@ -4859,6 +4861,7 @@ fn test_pre_post_transaction_balances() {
             enable_log_recording: false,
             enable_return_data_recording: false,
             enable_transaction_balance_recording: true,
+            enable_compute_profile_recording: false,
         },
         &mut ExecuteTimings::default(),
         None,
@@ -8923,6 +8926,7 @@ fn test_tx_log_order() {
                 enable_log_recording: true,
                 enable_return_data_recording: false,
                 enable_transaction_balance_recording: false,
+                enable_compute_profile_recording: false,
             },
             &mut ExecuteTimings::default(),
             None,
@@ -9037,6 +9041,7 @@ fn test_tx_return_data() {
                     enable_log_recording: false,
                     enable_return_data_recording: true,
                     enable_transaction_balance_recording: false,
+                    enable_compute_profile_recording: false,
                 },
                 &mut ExecuteTimings::default(),
                 None,
@@ -11659,6 +11664,98 @@ fn test_feature_activation_loaded_programs_epoch_transition() {
     assert!(bank.process_transaction(&transaction).is_ok());
 }
 
+#[test]
+fn test_prewarm_program_cache() {
+    let (mut genesis_config, _mint_keypair) = create_genesis_config(1_000_000 * LAMPORTS_PER_SOL);
+    genesis_config
+        .accounts
+        .remove(&feature_set::reenable_sbpf_v0_execution::id());
+    let (root_bank, bank_forks) = Bank::new_with_bank_forks_for_tests(&genesis_config);
+
+    let program_id = Pubkey::new_unique();
+    let program_data = include_bytes!("../../../programs/bpf_loader/test_elfs/out/noop_aligned.so");
+    let program_account = AccountSharedData::from(Account {
+        lamports: Rent::default().minimum_balance(program_data.len()).min(1),
+        data: program_data.to_vec(),
+        owner: bpf_loader::id(),
+        executable: true,
+        rent_epoch: 0,
+    });
+    root_bank.store_account(&program_id, &program_account);
+    goto_end_of_slot(root_bank.clone());
+    let bank = new_from_parent_with_fork_next_slot(root_bank, bank_forks.as_ref());
+
+    let cached_program = |program_id| {
+        bank.program_cache_entries()
+            .into_iter()
+            .find(|entry| entry.program_id == program_id)
+    };
+    assert!(cached_program(program_id).is_none());
+
+    // Programs that do not exist are skipped
+    assert_eq!(
+        bank.prewarm_program_cache(&[program_id, Pubkey::new_unique()]),
+        1
+    );
+    let entry = cached_program(program_id).unwrap();
+    assert_eq!(entry.tier, ProgramCacheEntryTier::Loaded);
+    assert_eq!(entry.account_owner, bpf_loader::id());
+    assert!(!entry.pinned);
+    // Programs that are cached already are skipped
+    assert_eq!(bank.prewarm_program_cache(&[program_id]), 0);
+
+    assert!(bank.pin_cached_program(program_id));
+    assert!(cached_program(program_id).unwrap().pinned);
+    assert!(bank.unpin_cached_program(&program_id));
+    assert!(!bank.unpin_cached_program(&program_id));
+}
+
+#[test]
+fn test_jit_cache() {
+    let program_id = Pubkey::new_unique();
+    let new_bank_with_program = |program_data: &[u8]| {
+        let (mut genesis_config, _mint_keypair) =
+            create_genesis_config(1_000_000 * LAMPORTS_PER_SOL);
+        genesis_config
+            .accounts
+            .remove(&feature_set::reenable_sbpf_v0_execution::id());
+        let (root_bank, bank_forks) = Bank::new_with_bank_forks_for_tests(&genesis_config);
+        let program_account = AccountSharedData::from(Account {
+            lamports: Rent::default().minimum_balance(program_data.len()).min(1),
+            data: program_data.to_vec(),
+            owner: bpf_loader::id(),
+            executable: true,
+            rent_epoch: 0,
+        });
+        root_bank.store_account(&program_id, &program_account);
+        goto_end_of_slot(root_bank.clone());
+        new_from_parent_with_fork_next_slot(root_bank, bank_forks.as_ref())
+    };
+    let program_data = include_bytes!("../../../programs/bpf_loader/test_elfs/out/noop_aligned.so");
+    let jit_cache_dir = tempfile::TempDir::new().unwrap();
+    let jit_cache = JitCache::new(jit_cache_dir.path().to_path_buf()).unwrap();
+
+    let bank = new_bank_with_program(program_data);
+    assert_eq!(bank.save_jit_cache(&jit_cache), 0);
+    assert_eq!(bank.prewarm_program_cache(&[program_id]), 1);
+    assert_eq!(bank.save_jit_cache(&jit_cache), 1);
+
+    // A restarted bank with the same program compiles it ahead of its use
+    let bank = new_bank_with_program(program_data);
+    assert_eq!(bank.load_jit_cache(&jit_cache).unwrap(), 1);
+    assert!(
+        bank.program_cache_entries()
+            .iter()
+            .any(|entry| entry.program_id == program_id
+                && entry.tier == ProgramCacheEntryTier::Loaded)
+    );
+
+    // The record of a program which has changed since is discarded
+    let bank = new_bank_with_program(&program_data[..program_data.len() - 1]);
+    assert_eq!(bank.load_jit_cache(&jit_cache).unwrap(), 0);
+    assert_eq!(std::fs::read_dir(jit_cache_dir.path()).unwrap().count(), 0);
+}
+
 #[test]
 fn test_verify_accounts() {
     let GenesisConfigInfo {
@@ -12121,10 +12218,196 @@ fn test_failed_simulation_load_error() {
             post_balances: Some(vec![mint_balance, 0]),
             pre_token_balances: Some(vec![]),
             post_token_balances: Some(vec![]),
+            compute_profile: None,
+            account_diffs: None,
         }
     );
 }
 
+#[test]
+fn test_simulation_account_diffs() {
+    let (genesis_config, mint_keypair) = create_genesis_config(LAMPORTS_PER_SOL);
+    let bank = Bank::new_for_tests(&genesis_config);
+    let (bank, _bank_forks) = bank.wrap_with_bank_forks_for_tests();
+    let to = Pubkey::new_unique();
+    let amount = bank.get_minimum_balance_for_rent_exemption(0);
+    let transaction =
+        system_transaction::transfer(&mint_keypair, &to, amount, bank.last_blockhash());
+
+    bank.freeze();
+    let mint_balance = bank.get_balance(&mint_keypair.pubkey());
+    let sanitized = RuntimeTransaction::from_transaction_for_tests(transaction);
+    let simulation = bank.simulate_transaction_with_config(
+        &sanitized,
+        SimulationConfig {
+            enable_account_diffs: true,
+            ..SimulationConfig::default()
+        },
+    );
+    assert_eq!(simulation.result, Ok(()));
+    let fee = simulation.fee.unwrap();
+    assert_eq!(
+        simulation.account_diffs,
+        Some(vec![
+            AccountStateDiff {
+                pubkey: mint_keypair.pubkey(),
+                pre_lamports: mint_balance,
+                post_lamports: mint_balance - amount - fee,
+                pre_owner: system_program::id(),
+                post_owner: system_program::id(),
+                pre_data_len: 0,
+                post_data_len: 0,
+                data_diff: vec![],
+            },
+            AccountStateDiff {
+                pubkey: to,
+                pre_lamports: 0,
+                post_lamports: amount,
+                pre_owner: system_program::id(),
+                post_owner: system_program::id(),
+                pre_data_len: 0,
+                post_data_len: 0,
+                data_diff: vec![],
+            },
+        ])
+    );
+    // Simulation does not commit
+    assert_eq!(bank.get_balance(&to), 0);
+
+    let simulation = bank.simulate_transaction(&sanitized, false);
+    assert_eq!(simulation.account_diffs, None);
+}
+
+#[test]
+fn test_simulate_transactions_carries_state() {
+    let (genesis_config, mint_keypair) = create_genesis_config(LAMPORTS_PER_SOL);
+    let bank = Bank::new_for_tests(&genesis_config);
+    let (bank, _bank_forks) = bank.wrap_with_bank_forks_for_tests();
+    let keypair = Keypair::new();
+    let to = Pubkey::new_unique();
+    let amount = bank.get_minimum_balance_for_rent_exemption(0);
+    let fund_tx = system_transaction::transfer(
+        &mint_keypair,
+        &keypair.pubkey(),
+        3 * amount,
+        bank.last_blockhash(),
+    );
+    let spend_tx = system_transaction::transfer(&keypair, &to, amount, bank.last_blockhash());
+    bank.freeze();
+
+    // The spending transaction alone has no funded fee payer
+    let spend_tx = RuntimeTransaction::from_transaction_for_tests(spend_tx);
+    let simulation = bank.simulate_transaction(&spend_tx, false);
+    assert_eq!(simulation.result, Err(TransactionError::AccountNotFound));
+
+    let transactions = [
+        RuntimeTransaction::from_transaction_for_tests(fund_tx),
+        spend_tx,
+    ];
+    let BundleSimulationResult {
+        transaction_results,
+        post_simulation_accounts,
+    } = bank.simulate_transactions_with_config(&transactions, SimulationConfig::default());
+    assert_eq!(transaction_results.len(), 2);
+    assert_eq!(transaction_results[0].result, Ok(()));
+    assert_eq!(transaction_results[1].result, Ok(()));
+    let spend_fee = transaction_results[1].fee.unwrap();
+    assert_eq!(
+        post_simulation_accounts[&keypair.pubkey()].lamports(),
+        2 * amount - spend_fee
+    );
+    assert_eq!(post_simulation_accounts[&to].lamports(), amount);
+    // Simulation does not commit
+    assert_eq!(bank.get_balance(&keypair.pubkey()), 0);
+}
+
+#[derive(Debug, Default)]
+struct TestFixtureRecorder {
+    signature: Signature,
+    recorded: Mutex<Vec<(Signature, TransactionFixtureInput, bool)>>,
+}
+
+impl TransactionFixtureRecorder for TestFixtureRecorder {
+    fn should_record(&self, transaction: &SanitizedTransaction) -> bool {
+        *transaction.signature() == self.signature
+    }
+
+    fn record(
+        &self,
+        transaction: &SanitizedTransaction,
+        input: TransactionFixtureInput,
+        processing_result: &TransactionProcessingResult,
+    ) {
+        self.recorded.lock().unwrap().push((
+            *transaction.signature(),
+            input,
+            processing_result.was_processed(),
+        ));
+    }
+}
+
+#[test]
+fn test_transaction_fixture_recorder() {
+    let (genesis_config, mint_keypair) = create_genesis_config(LAMPORTS_PER_SOL);
+    let to = Pubkey::new_unique();
+    let tx = system_transaction::transfer(&mint_keypair, &to, 1_000_000, genesis_config.hash());
+    let other_tx = system_transaction::transfer(
+        &mint_keypair,
+        &Pubkey::new_unique(),
+        1_000_000,
+        genesis_config.hash(),
+    );
+    let recorder = Arc::new(TestFixtureRecorder {
+        signature: tx.signatures[0],
+        ..TestFixtureRecorder::default()
+    });
+    let bank = Bank::new_from_genesis(
+        &genesis_config,
+        Arc::new(RuntimeConfig {
+            transaction_fixture_recorder: Some(recorder.clone()),
+            ..RuntimeConfig::default()
+        }),
+        vec![],
+        None,
+        BankTestConfig::default().accounts_db_config,
+        None,
+        None,
+        Arc::default(),
+        None,
+        None,
+    );
+    let (bank, _bank_forks) = bank.wrap_with_bank_forks_for_tests();
+    assert_eq!(bank.process_transaction(&other_tx), Ok(()));
+    let mint_balance = bank.get_balance(&mint_keypair.pubkey());
+    assert_eq!(bank.process_transaction(&tx), Ok(()));
+
+    let recorded = recorder.recorded.lock().unwrap();
+    assert_eq!(recorded.len(), 1);
+    let (signature, input, was_processed) = &recorded[0];
+    assert_eq!(*signature, tx.signatures[0]);
+    assert!(*was_processed);
+    // The inputs are the state before the transaction executed
+    let account = |pubkey: &Pubkey| {
+        input
+            .accounts
+            .iter()
+            .find(|(address, _account)| address == pubkey)
+            .map(|(_address, account)| account)
+    };
+    assert_eq!(
+        account(&mint_keypair.pubkey()).unwrap().lamports(),
+        mint_balance
+    );
+    assert!(account(&to).is_none());
+    assert!(account(&system_program::id()).unwrap().executable());
+    assert!(account(&sysvar::clock::id()).is_some());
+    assert!(account(&sysvar::rent::id()).is_some());
+    assert_eq!(
+        input.blockhash_queue.last().map(|(hash, _lamports)| *hash),
+        Some(genesis_config.hash())
+    );
+}
+
 #[test]
 fn test_filter_program_errors_and_collect_fee_details() {
     // TX  | PROCESSING RESULT           | COLLECT            | COLLECT
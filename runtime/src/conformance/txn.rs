@@ -51,28 +51,18 @@ use {
 #[cfg(feature = "conformance")]
 use {
     super::{deserialize_accounts, fee_rate_governor_from_proto, restore_blockhash_queue},
+    crate::fixture_capture::{ProtoTxnErrorFields, output_txn_result},
     agave_feature_set::virtual_address_space_adjustments,
-    agave_precompiles::is_precompile,
     ahash::AHashSet,
-    protosol::protos::{
-        AcctState, FeeDetails as ProtoFeeDetails, TxnContext as ProtoTxnContext,
-        TxnResult as ProtoTxnResult,
-    },
-    solana_instruction::error::InstructionError,
+    protosol::protos::{TxnContext as ProtoTxnContext, TxnResult as ProtoTxnResult},
     solana_message::SanitizedMessage,
     solana_runtime_transaction::transaction_with_meta::TransactionWithMeta,
     solana_signature::Signature,
     solana_svm::conformance::{
-        account_state::account_to_proto, direct_mapping::direct_mapping_handle_cu_exhaustion,
-        err::serialized_error_code, feature_set::feature_set_from_proto,
+        direct_mapping::direct_mapping_handle_cu_exhaustion, feature_set::feature_set_from_proto,
         versioned_transaction::versioned_transaction_from_proto,
     },
-    solana_svm::transaction_processing_result::{
-        ProcessedTransaction, TransactionProcessingResultExtensions,
-    },
-    solana_svm::{
-        account_loader::FeesOnlyTransaction, transaction_execution_result::ExecutedTransaction,
-    },
+    solana_svm::transaction_processing_result::ProcessedTransaction,
 };
 // Imports used only by the FFI entry point, which is excluded from `test` builds.
 #[cfg(all(feature = "conformance", not(test)))]
@@ -177,6 +167,7 @@ pub fn execute_txn(
         enable_log_recording: true,
         enable_return_data_recording: true,
         enable_transaction_balance_recording: false,
+        enable_compute_profile_recording: false,
     };
     let processing_config = TransactionProcessingConfig {
         recording_config,
@@ -207,202 +198,6 @@ pub fn execute_txn(
     }
 }
 
-/// Firedancer error numbers: the bincode-serialized enum discriminant `+ 1`.
-#[cfg(feature = "conformance")]
-#[derive(Default)]
-struct ProtoTxnErrorFields {
-    txn_error: u32,
-    instruction_error: u32,
-    custom_error: u32,
-    instruction_error_index: u32,
-}
-
-#[cfg(feature = "conformance")]
-impl ProtoTxnErrorFields {
-    fn from_processed_transaction(
-        txn: &ProcessedTransaction,
-        sanitized_message: &SanitizedMessage,
-    ) -> Self {
-        match txn.status() {
-            Ok(()) => Self::default(),
-            Err(transaction_error) => Self::from_transaction_error(&transaction_error)
-                .zero_precompile_custom_error(sanitized_message),
-        }
-    }
-
-    fn from_transaction_error(transaction_error: &TransactionError) -> Self {
-        let (instruction_error, custom_error, instruction_error_index) = match transaction_error {
-            TransactionError::InstructionError(instruction_error_index, instruction_error) => {
-                let custom_error = match instruction_error {
-                    InstructionError::Custom(custom_error) => *custom_error,
-                    _ => 0,
-                };
-                (
-                    serialized_error_code(instruction_error),
-                    custom_error,
-                    (*instruction_error_index).into(),
-                )
-            }
-            _ => (0, 0, 0),
-        };
-
-        Self {
-            txn_error: serialized_error_code(transaction_error),
-            instruction_error,
-            custom_error,
-            instruction_error_index,
-        }
-    }
-
-    /// Firedancer does not compare precompile custom error codes because minor
-    /// implementation differences can surface different custom values.
-    fn zero_precompile_custom_error(mut self, sanitized_message: &SanitizedMessage) -> Self {
-        // Custom error is zeroed when the failing instruction is a precompile.
-        if self.custom_error != 0
-            && instruction_is_precompile(self.instruction_error_index, sanitized_message)
-        {
-            self.custom_error = 0;
-        }
-        self
-    }
-}
-
-#[cfg(feature = "conformance")]
-fn instruction_is_precompile(
-    instruction_error_index: u32,
-    sanitized_message: &SanitizedMessage,
-) -> bool {
-    let Ok(instruction_error_index) = usize::try_from(instruction_error_index) else {
-        return false;
-    };
-
-    sanitized_message
-        .program_instructions_iter()
-        .nth(instruction_error_index)
-        .is_some_and(|(program_id, _)| is_precompile(program_id, |_| true))
-}
-
-#[cfg(feature = "conformance")]
-struct ProtoTxnEffects {
-    modified_accounts: Vec<AcctState>,
-    rollback_accounts: Vec<AcctState>,
-    return_data: Vec<u8>,
-}
-
-#[cfg(feature = "conformance")]
-impl ProtoTxnEffects {
-    fn from_processed_transaction(
-        txn: &ProcessedTransaction,
-        sanitized_message: &SanitizedMessage,
-    ) -> Self {
-        match txn {
-            ProcessedTransaction::Executed(executed_tx) => {
-                executed_transaction_effects(executed_tx, sanitized_message)
-            }
-            ProcessedTransaction::FeesOnly(tx) => fees_only_transaction_effects(tx),
-            ProcessedTransaction::NoOp(_) => ProtoTxnEffects {
-                modified_accounts: vec![],
-                rollback_accounts: vec![],
-                return_data: vec![],
-            },
-        }
-    }
-}
-
-#[cfg(feature = "conformance")]
-fn executed_transaction_effects(
-    executed_tx: &ExecutedTransaction,
-    sanitized_message: &SanitizedMessage,
-) -> ProtoTxnEffects {
-    let loaded = &executed_tx.loaded_transaction;
-    let modified_accounts = loaded
-        .accounts
-        .iter()
-        .enumerate()
-        .filter(|(index, _)| sanitized_message.is_writable(*index))
-        .map(|(_, (pubkey, account))| account_to_proto((*pubkey, account.clone().into())))
-        .collect();
-    let rollback_accounts = if executed_tx.execution_details.status.is_err() {
-        loaded
-            .rollback_accounts
-            .iter()
-            .map(|(pubkey, account)| account_to_proto((*pubkey, account.clone().into())))
-            .collect()
-    } else {
-        vec![]
-    };
-    let return_data = executed_tx
-        .execution_details
-        .return_data
-        .as_ref()
-        .map(|info| info.data.clone())
-        .unwrap_or_default();
-
-    ProtoTxnEffects {
-        modified_accounts,
-        rollback_accounts,
-        return_data,
-    }
-}
-
-#[cfg(feature = "conformance")]
-fn fees_only_transaction_effects(tx: &FeesOnlyTransaction) -> ProtoTxnEffects {
-    ProtoTxnEffects {
-        modified_accounts: vec![],
-        rollback_accounts: tx
-            .rollback_accounts
-            .iter()
-            .map(|(pubkey, account)| account_to_proto((*pubkey, account.clone().into())))
-            .collect(),
-        return_data: vec![],
-    }
-}
-
-/// Map the processor's result for the single executed transaction into a
-/// `TxnResult`.
-#[cfg(feature = "conformance")]
-fn output_txn_result(
-    execution_result: &TransactionProcessingResult,
-    sanitized_message: &SanitizedMessage,
-) -> ProtoTxnResult {
-    let executed = execution_result.was_processed();
-    match execution_result {
-        Ok(txn) => {
-            let error = ProtoTxnErrorFields::from_processed_transaction(txn, sanitized_message);
-            let effects = ProtoTxnEffects::from_processed_transaction(txn, sanitized_message);
-            let fees = txn.fee_details();
-
-            ProtoTxnResult {
-                executed,
-                txn_error: error.txn_error,
-                instruction_error: error.instruction_error,
-                instruction_error_index: error.instruction_error_index,
-                custom_error: error.custom_error,
-                return_data: effects.return_data,
-                executed_units: txn.executed_units(),
-                fee_details: Some(ProtoFeeDetails {
-                    transaction_fee: fees.transaction_fee(),
-                    prioritization_fee: fees.prioritization_fee(),
-                }),
-                loaded_accounts_data_size: u64::from(txn.loaded_accounts_data_size()),
-                modified_accounts: effects.modified_accounts,
-                rollback_accounts: effects.rollback_accounts,
-            }
-        }
-        Err(transaction_error) => {
-            let error = ProtoTxnErrorFields::from_transaction_error(transaction_error);
-            ProtoTxnResult {
-                executed,
-                txn_error: error.txn_error,
-                instruction_error: error.instruction_error,
-                instruction_error_index: error.instruction_error_index,
-                custom_error: error.custom_error,
-                ..Default::default()
-            }
-        }
-    }
-}
-
 /// Decode a `TxnContext` proto, run it through [`execute_txn`], and encode the
 /// effects as a `TxnResult` proto.
 #[cfg(feature = "conformance")]
@@ -861,7 +656,7 @@ mod tests {
         assert!(result.executed);
         assert_eq!(
             result.txn_error,
-            solana_svm::conformance::err::serialized_error_code(&validation_error)
+            solana_svm::conformance::error_code::serialized_error_code(&validation_error)
         );
         assert_eq!(result.instruction_error, 0);
         assert_eq!(result.instruction_error_index, 0);
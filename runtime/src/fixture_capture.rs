@@ -0,0 +1,431 @@
+//! Capture of executed transactions as conformance fixtures
+//!
+//! [`FixtureCapture`] writes the transactions its [`FixtureCaptureFilter`]
+//! selects as `TxnFixture`s for the transaction conformance harness
+//! (`sol_compat_txn_execute_v1`). A fixture holds the transaction, the
+//! accounts, sysvars, features and blockhash queue it was executed against and
+//! the effects it had, so that a transaction seen on a cluster can be executed
+//! again in isolation.
+
+use {
+    crate::runtime_config::{TransactionFixtureInput, TransactionFixtureRecorder},
+    agave_feature_set::FeatureSet,
+    agave_precompiles::is_precompile,
+    log::*,
+    prost::Message,
+    protosol::protos::{
+        AcctState, BlockhashQueueEntry as ProtoBlockhashQueueEntry,
+        CompiledInstruction as ProtoCompiledInstruction, FeatureSet as ProtoFeatureSet,
+        FeeDetails as ProtoFeeDetails, FeeRateGovernor as ProtoFeeRateGovernor, FixtureMetadata,
+        MessageAddressTableLookup as ProtoMessageAddressTableLookup,
+        MessageHeader as ProtoMessageHeader, SanitizedTransaction as ProtoSanitizedTransaction,
+        TransactionMessage as ProtoTransactionMessage, TxnBank as ProtoTxnBank,
+        TxnContext as ProtoTxnContext, TxnFixture as ProtoTxnFixture, TxnResult as ProtoTxnResult,
+    },
+    solana_account::AccountSharedData,
+    solana_instruction::error::InstructionError,
+    solana_message::{SanitizedMessage, VersionedMessage},
+    solana_pubkey::Pubkey,
+    solana_signature::Signature,
+    solana_svm::{
+        account_loader::FeesOnlyTransaction,
+        conformance::{account_state::account_to_proto, error_code::serialized_error_code},
+        transaction_execution_result::ExecutedTransaction,
+        transaction_processing_result::{
+            ProcessedTransaction, TransactionProcessingResult,
+            TransactionProcessingResultExtensions,
+        },
+    },
+    solana_transaction::{sanitized::SanitizedTransaction, versioned::VersionedTransaction},
+    solana_transaction_error::TransactionError,
+    std::{collections::HashSet, fs, io, path::PathBuf},
+};
+
+/// Harness entrypoint that executes `TxnFixture`s
+const TXN_FIXTURE_ENTRYPOINT: &str = "sol_compat_txn_execute_v1";
+
+/// Selects transactions by the programs they invoke or by signature
+#[derive(Debug, Default, Clone)]
+pub struct FixtureCaptureFilter {
+    pub program_ids: HashSet<Pubkey>,
+    pub signatures: HashSet<Signature>,
+}
+
+impl FixtureCaptureFilter {
+    pub fn matches(&self, transaction: &SanitizedTransaction) -> bool {
+        self.signatures.contains(transaction.signature())
+            || transaction
+                .message()
+                .program_instructions_iter()
+                .any(|(program_id, _instruction)| self.program_ids.contains(program_id))
+    }
+}
+
+/// Writes the fixture of every selected transaction to
+/// `<output_dir>/<signature>.fix`. A transaction executed more than once, as on
+/// several forks, leaves the fixture of its last execution.
+///
+/// Transactions rejected before being processed, such as for an expired
+/// blockhash or for having been processed already, are not captured: their
+/// outcome depends on bank state a fixture does not hold.
+#[derive(Debug)]
+pub struct FixtureCapture {
+    output_dir: PathBuf,
+    filter: FixtureCaptureFilter,
+}
+
+impl FixtureCapture {
+    pub fn new(output_dir: PathBuf, filter: FixtureCaptureFilter) -> io::Result<Self> {
+        fs::create_dir_all(&output_dir)?;
+        Ok(Self { output_dir, filter })
+    }
+
+    fn write(&self, signature: &Signature, fixture: &ProtoTxnFixture) -> io::Result<()> {
+        fs::write(
+            self.output_dir.join(format!("{signature}.fix")),
+            fixture.encode_to_vec(),
+        )
+    }
+}
+
+impl TransactionFixtureRecorder for FixtureCapture {
+    fn should_record(&self, transaction: &SanitizedTransaction) -> bool {
+        self.filter.matches(transaction)
+    }
+
+    fn record(
+        &self,
+        transaction: &SanitizedTransaction,
+        input: TransactionFixtureInput,
+        processing_result: &TransactionProcessingResult,
+    ) {
+        if processing_result.is_err() {
+            return;
+        }
+        let signature = transaction.signature();
+        let fixture = txn_fixture(transaction, input, processing_result);
+        match self.write(signature, &fixture) {
+            Ok(()) => debug!("Captured the fixture of transaction {signature}"),
+            Err(err) => warn!("Failed to write the fixture of transaction {signature}: {err}"),
+        }
+    }
+}
+
+fn txn_fixture(
+    transaction: &SanitizedTransaction,
+    input: TransactionFixtureInput,
+    processing_result: &TransactionProcessingResult,
+) -> ProtoTxnFixture {
+    let TransactionFixtureInput {
+        accounts,
+        feature_set,
+        blockhash_queue,
+        fee_rate_governor,
+        total_epoch_stake,
+    } = input;
+    let bank = ProtoTxnBank {
+        blockhash_queue: blockhash_queue
+            .into_iter()
+            .map(
+                |(blockhash, lamports_per_signature)| ProtoBlockhashQueueEntry {
+                    blockhash: blockhash.to_bytes().to_vec(),
+                    lamports_per_signature,
+                },
+            )
+            .collect(),
+        rbh_lamports_per_signature: u32::try_from(fee_rate_governor.lamports_per_signature)
+            .unwrap_or(u32::MAX),
+        fee_rate_governor: Some(ProtoFeeRateGovernor {
+            target_lamports_per_signature: fee_rate_governor.target_lamports_per_signature,
+            target_signatures_per_slot: fee_rate_governor.target_signatures_per_slot,
+            min_lamports_per_signature: fee_rate_governor.min_lamports_per_signature,
+            max_lamports_per_signature: fee_rate_governor.max_lamports_per_signature,
+            burn_percent: u32::from(fee_rate_governor.burn_percent),
+        }),
+        total_epoch_stake,
+        features: Some(feature_set_to_proto(&feature_set)),
+        ..ProtoTxnBank::default()
+    };
+    let context = ProtoTxnContext {
+        tx: Some(transaction_to_proto(
+            &transaction.to_versioned_transaction(),
+            transaction.message_hash().to_bytes().to_vec(),
+        )),
+        account_shared_data: accounts
+            .into_iter()
+            .map(|(pubkey, account)| account_to_proto((pubkey, account.into())))
+            .collect(),
+        bank: Some(bank),
+    };
+    ProtoTxnFixture {
+        metadata: Some(FixtureMetadata {
+            fn_entrypoint: TXN_FIXTURE_ENTRYPOINT.to_string(),
+        }),
+        input: Some(context),
+        output: Some(output_txn_result(processing_result, transaction.message())),
+    }
+}
+
+/// Features are identified by the first 8 bytes of their id, little endian
+fn feature_set_to_proto(feature_set: &FeatureSet) -> ProtoFeatureSet {
+    ProtoFeatureSet {
+        features: feature_set
+            .active()
+            .keys()
+            .map(|feature_id| u64::from_le_bytes(feature_id.to_bytes()[..8].try_into().unwrap()))
+            .collect(),
+    }
+}
+
+fn transaction_to_proto(
+    transaction: &VersionedTransaction,
+    message_hash: Vec<u8>,
+) -> ProtoSanitizedTransaction {
+    let message = &transaction.message;
+    let header = message.header();
+    let to_bytes = |pubkey: &Pubkey| pubkey.to_bytes().to_vec();
+    let address_table_lookups = message
+        .address_table_lookups()
+        .unwrap_or_default()
+        .iter()
+        .map(|lookup| ProtoMessageAddressTableLookup {
+            account_key: to_bytes(&lookup.account_key),
+            writable_indexes: lookup
+                .writable_indexes
+                .iter()
+                .copied()
+                .map(u32::from)
+                .collect(),
+            readonly_indexes: lookup
+                .readonly_indexes
+                .iter()
+                .copied()
+                .map(u32::from)
+                .collect(),
+        })
+        .collect();
+    ProtoSanitizedTransaction {
+        message: Some(ProtoTransactionMessage {
+            is_legacy: matches!(message, VersionedMessage::Legacy(_)),
+            header: Some(ProtoMessageHeader {
+                num_required_signatures: u32::from(header.num_required_signatures),
+                num_readonly_signed_accounts: u32::from(header.num_readonly_signed_accounts),
+                num_readonly_unsigned_accounts: u32::from(header.num_readonly_unsigned_accounts),
+            }),
+            account_keys: message.static_account_keys().iter().map(to_bytes).collect(),
+            recent_blockhash: message.recent_blockhash().to_bytes().to_vec(),
+            instructions: message
+                .instructions()
+                .iter()
+                .map(|instruction| ProtoCompiledInstruction {
+                    program_id_index: u32::from(instruction.program_id_index),
+                    accounts: instruction
+                        .accounts
+                        .iter()
+                        .copied()
+                        .map(u32::from)
+                        .collect(),
+                    data: instruction.data.clone(),
+                })
+                .collect(),
+            address_table_lookups,
+        }),
+        message_hash,
+        signatures: transaction
+            .signatures
+            .iter()
+            .map(|signature| signature.as_ref().to_vec())
+            .collect(),
+    }
+}
+
+/// Firedancer error numbers: the bincode-serialized enum discriminant `+ 1`.
+#[derive(Default)]
+pub(crate) struct ProtoTxnErrorFields {
+    pub(crate) txn_error: u32,
+    pub(crate) instruction_error: u32,
+    pub(crate) custom_error: u32,
+    pub(crate) instruction_error_index: u32,
+}
+
+impl ProtoTxnErrorFields {
+    fn from_processed_transaction(
+        txn: &ProcessedTransaction,
+        sanitized_message: &SanitizedMessage,
+    ) -> Self {
+        match txn.status() {
+            Ok(()) => Self::default(),
+            Err(transaction_error) => Self::from_transaction_error(&transaction_error)
+                .zero_precompile_custom_error(sanitized_message),
+        }
+    }
+
+    pub(crate) fn from_transaction_error(transaction_error: &TransactionError) -> Self {
+        let (instruction_error, custom_error, instruction_error_index) = match transaction_error {
+            TransactionError::InstructionError(instruction_error_index, instruction_error) => {
+                let custom_error = match instruction_error {
+                    InstructionError::Custom(custom_error) => *custom_error,
+                    _ => 0,
+                };
+                (
+                    serialized_error_code(instruction_error),
+                    custom_error,
+                    (*instruction_error_index).into(),
+                )
+            }
+            _ => (0, 0, 0),
+        };
+
+        Self {
+            txn_error: serialized_error_code(transaction_error),
+            instruction_error,
+            custom_error,
+            instruction_error_index,
+        }
+    }
+
+    /// Firedancer does not compare precompile custom error codes because minor
+    /// implementation differences can surface different custom values.
+    pub(crate) fn zero_precompile_custom_error(
+        mut self,
+        sanitized_message: &SanitizedMessage,
+    ) -> Self {
+        // Custom error is zeroed when the failing instruction is a precompile.
+        if self.custom_error != 0
+            && instruction_is_precompile(self.instruction_error_index, sanitized_message)
+        {
+            self.custom_error = 0;
+        }
+        self
+    }
+}
+
+fn instruction_is_precompile(
+    instruction_error_index: u32,
+    sanitized_message: &SanitizedMessage,
+) -> bool {
+    let Ok(instruction_error_index) = usize::try_from(instruction_error_index) else {
+        return false;
+    };
+
+    sanitized_message
+        .program_instructions_iter()
+        .nth(instruction_error_index)
+        .is_some_and(|(program_id, _)| is_precompile(program_id, |_| true))
+}
+
+struct ProtoTxnEffects {
+    modified_accounts: Vec<AcctState>,
+    rollback_accounts: Vec<AcctState>,
+    return_data: Vec<u8>,
+}
+
+impl ProtoTxnEffects {
+    fn from_processed_transaction(
+        txn: &ProcessedTransaction,
+        sanitized_message: &SanitizedMessage,
+    ) -> Self {
+        match txn {
+            ProcessedTransaction::Executed(executed_tx) => {
+                executed_transaction_effects(executed_tx, sanitized_message)
+            }
+            ProcessedTransaction::FeesOnly(tx) => fees_only_transaction_effects(tx),
+            ProcessedTransaction::NoOp(_) => ProtoTxnEffects {
+                modified_accounts: vec![],
+                rollback_accounts: vec![],
+                return_data: vec![],
+            },
+        }
+    }
+}
+
+fn executed_transaction_effects(
+    executed_tx: &ExecutedTransaction,
+    sanitized_message: &SanitizedMessage,
+) -> ProtoTxnEffects {
+    let loaded = &executed_tx.loaded_transaction;
+    let modified_accounts = loaded
+        .accounts
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| sanitized_message.is_writable(*index))
+        .map(|(_, (pubkey, account))| account_to_proto((*pubkey, account.clone().into())))
+        .collect();
+    let rollback_accounts = if executed_tx.execution_details.status.is_err() {
+        loaded
+            .rollback_accounts
+            .iter()
+            .map(|(pubkey, account)| account_to_proto((*pubkey, account.clone().into())))
+            .collect()
+    } else {
+        vec![]
+    };
+    let return_data = executed_tx
+        .execution_details
+        .return_data
+        .as_ref()
+        .map(|info| info.data.clone())
+        .unwrap_or_default();
+
+    ProtoTxnEffects {
+        modified_accounts,
+        rollback_accounts,
+        return_data,
+    }
+}
+
+fn fees_only_transaction_effects(tx: &FeesOnlyTransaction) -> ProtoTxnEffects {
+    ProtoTxnEffects {
+        modified_accounts: vec![],
+        rollback_accounts: tx
+            .rollback_accounts
+            .iter()
+            .map(|(pubkey, account)| account_to_proto((*pubkey, account.clone().into())))
+            .collect(),
+        return_data: vec![],
+    }
+}
+
+/// Map the processor's result for the single executed transaction into a
+/// `TxnResult`.
+pub(crate) fn output_txn_result(
+    execution_result: &TransactionProcessingResult,
+    sanitized_message: &SanitizedMessage,
+) -> ProtoTxnResult {
+    let executed = execution_result.was_processed();
+    match execution_result {
+        Ok(txn) => {
+            let error = ProtoTxnErrorFields::from_processed_transaction(txn, sanitized_message);
+            let effects = ProtoTxnEffects::from_processed_transaction(txn, sanitized_message);
+            let fees = txn.fee_details();
+
+            ProtoTxnResult {
+                executed,
+                txn_error: error.txn_error,
+                instruction_error: error.instruction_error,
+                instruction_error_index: error.instruction_error_index,
+                custom_error: error.custom_error,
+                return_data: effects.return_data,
+                executed_units: txn.executed_units(),
+                fee_details: Some(ProtoFeeDetails {
+                    transaction_fee: fees.transaction_fee(),
+                    prioritization_fee: fees.prioritization_fee(),
+                }),
+                loaded_accounts_data_size: u64::from(txn.loaded_accounts_data_size()),
+                modified_accounts: effects.modified_accounts,
+                rollback_accounts: effects.rollback_accounts,
+            }
+        }
+        Err(transaction_error) => {
+            let error = ProtoTxnErrorFields::from_transaction_error(transaction_error);
+            ProtoTxnResult {
+                executed,
+                txn_error: error.txn_error,
+                instruction_error: error.instruction_error,
+                instruction_error_index: error.instruction_error_index,
+                custom_error: error.custom_error,
+                ..Default::default()
+            }
+        }
+    }
+}
@@ -0,0 +1,219 @@
+//! Ahead-of-time compilation of the program cache after a restart
+//!
+//! [`JitCache`] records the programs compiled into the program cache in a
+//! local directory, one file per program version. A record is keyed by the
+//! hash of the program's ELF, of the runtime environment it was compiled for
+//! and of the active feature set. After a restart the records are validated
+//! against the state of the bank, and the programs which are unchanged are
+//! compiled before replay starts, instead of on their first invocation.
+//!
+//! The JIT-compiled machine code itself is not persisted: it embeds the
+//! addresses of the syscalls of the compiling process and is diversified on
+//! every compilation, so another process can not execute it.
+
+use {
+    agave_feature_set::FeatureSet,
+    log::*,
+    serde::{Deserialize, Serialize},
+    solana_hash::Hash,
+    solana_program_runtime::loaded_programs::ProgramRuntimeEnvironment,
+    solana_pubkey::Pubkey,
+    solana_sha256_hasher::{Hasher, hashv},
+    std::{
+        fs, io,
+        path::{Path, PathBuf},
+    },
+};
+
+/// Extension of the files holding a record
+const RECORD_FILE_EXTENSION: &str = "jit";
+
+/// Bumped whenever the record format or the meaning of its hashes changes, so
+/// that records of previous versions are discarded instead of misinterpreted
+const RECORD_VERSION: u32 = 1;
+
+/// Identifies a compiled program, which is only reusable while all of its
+/// inputs are unchanged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JitCacheKey {
+    pub program_id: Pubkey,
+    /// Hash of the program's ELF
+    pub program_data_hash: Hash,
+    /// Hash of the configuration and syscalls of the runtime environment
+    pub environment_hash: Hash,
+    /// Hash of the active features
+    pub feature_set_hash: Hash,
+}
+
+impl JitCacheKey {
+    fn file_name(&self) -> String {
+        let hash = hashv(&[
+            self.program_id.as_ref(),
+            self.program_data_hash.as_ref(),
+            self.environment_hash.as_ref(),
+            self.feature_set_hash.as_ref(),
+        ]);
+        format!("{hash}.{RECORD_FILE_EXTENSION}")
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct JitCacheRecord {
+    version: u32,
+    key: JitCacheKey,
+}
+
+/// Directory of the records of compiled programs
+#[derive(Debug)]
+pub struct JitCache {
+    dir: PathBuf,
+}
+
+impl JitCache {
+    pub fn new(dir: PathBuf) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Records `key`. The record is written to a temporary file first, so
+    /// that an interrupted write never leaves a truncated record behind.
+    pub fn store(&self, key: &JitCacheKey) -> io::Result<()> {
+        let record = JitCacheRecord {
+            version: RECORD_VERSION,
+            key: *key,
+        };
+        let data = bincode::serialize(&record).map_err(io::Error::other)?;
+        let path = self.dir.join(key.file_name());
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, data)?;
+        fs::rename(tmp_path, path)
+    }
+
+    /// Returns the recorded keys `is_valid` accepts, and removes the records
+    /// of all others along with records which can not be read
+    pub fn load(&self, is_valid: impl Fn(&JitCacheKey) -> bool) -> io::Result<Vec<JitCacheKey>> {
+        let mut keys = Vec::new();
+        for dir_entry in fs::read_dir(&self.dir)? {
+            let path = dir_entry?.path();
+            if !path
+                .extension()
+                .is_some_and(|extension| extension == RECORD_FILE_EXTENSION)
+            {
+                continue;
+            }
+            match Self::read_record(&path) {
+                Some(key) if is_valid(&key) => keys.push(key),
+                _ => {
+                    debug!("Removing stale JIT cache record {}", path.display());
+                    if let Err(err) = fs::remove_file(&path) {
+                        warn!(
+                            "Failed to remove JIT cache record {}: {err}",
+                            path.display()
+                        );
+                    }
+                }
+            }
+        }
+        Ok(keys)
+    }
+
+    /// Reads the record at `path`, which is only valid if it is of the
+    /// current version and stored under the name its key hashes to
+    fn read_record(path: &Path) -> Option<JitCacheKey> {
+        let data = fs::read(path).ok()?;
+        let record: JitCacheRecord = bincode::deserialize(&data).ok()?;
+        (record.version == RECORD_VERSION
+            && path
+                .file_name()
+                .is_some_and(|file_name| *file_name == *record.key.file_name()))
+        .then_some(record.key)
+    }
+}
+
+/// Hashes the configuration and the syscalls of `environment`, which
+/// determine the code the JIT compiler emits
+pub fn environment_hash(environment: &ProgramRuntimeEnvironment) -> Hash {
+    let mut hasher = Hasher::default();
+    hasher.hash(format!("{:?}", environment.get_config()).as_bytes());
+    let mut functions = environment
+        .get_function_registry()
+        .iter()
+        .map(|(key, (name, _function))| (key, name))
+        .collect::<Vec<_>>();
+    functions.sort_unstable();
+    for (key, name) in functions {
+        hasher.hashv(&[&key.to_le_bytes(), name]);
+    }
+    hasher.result()
+}
+
+/// Hashes the ids of the active features
+pub fn feature_set_hash(feature_set: &FeatureSet) -> Hash {
+    let mut active_features = feature_set.active().keys().collect::<Vec<_>>();
+    active_features.sort_unstable();
+    let mut hasher = Hasher::default();
+    for feature_id in active_features {
+        hasher.hash(feature_id.as_ref());
+    }
+    hasher.result()
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, tempfile::TempDir};
+
+    fn new_key() -> JitCacheKey {
+        JitCacheKey {
+            program_id: Pubkey::new_unique(),
+            program_data_hash: Hash::new_unique(),
+            environment_hash: Hash::new_unique(),
+            feature_set_hash: Hash::new_unique(),
+        }
+    }
+
+    #[test]
+    fn test_store_and_load() {
+        let dir = TempDir::new().unwrap();
+        let jit_cache = JitCache::new(dir.path().join("jit-cache")).unwrap();
+        let valid_key = new_key();
+        let stale_key = new_key();
+        jit_cache.store(&valid_key).unwrap();
+        jit_cache.store(&stale_key).unwrap();
+        // Records are validated against the name they are stored under
+        fs::write(
+            jit_cache
+                .dir()
+                .join(format!("corrupt.{RECORD_FILE_EXTENSION}")),
+            [0; 8],
+        )
+        .unwrap();
+        fs::copy(
+            jit_cache.dir().join(valid_key.file_name()),
+            jit_cache
+                .dir()
+                .join(format!("renamed.{RECORD_FILE_EXTENSION}")),
+        )
+        .unwrap();
+
+        let keys = jit_cache.load(|key| *key == valid_key).unwrap();
+        assert_eq!(keys, vec![valid_key]);
+        assert_eq!(fs::read_dir(jit_cache.dir()).unwrap().count(), 1);
+
+        // Loading again finds the remaining record only
+        let keys = jit_cache.load(|_key| true).unwrap();
+        assert_eq!(keys, vec![valid_key]);
+    }
+
+    #[test]
+    fn test_feature_set_hash() {
+        let mut feature_set = FeatureSet::default();
+        let default_hash = feature_set_hash(&feature_set);
+        assert_eq!(default_hash, feature_set_hash(&FeatureSet::default()));
+        feature_set.activate(&Pubkey::new_unique(), 0);
+        assert_ne!(default_hash, feature_set_hash(&feature_set));
+    }
+}
@@ -6,6 +6,7 @@
 mod account_saver;
 #[cfg(feature = "dev-context-only-utils")]
 pub mod account_saver;
+pub mod account_state_diff;
 pub mod accounts_background_service;
 mod alpenglow_epoch_type;
 pub mod bank;
@@ -19,9 +20,12 @@ pub mod commitment;
 pub mod conformance;
 pub mod dependency_tracker;
 pub mod epoch_stakes;
+#[cfg(feature = "fixture-capture")]
+pub mod fixture_capture;
 pub mod genesis_utils;
 pub mod inflation_rewards;
 pub mod installed_scheduler_pool;
+pub mod jit_cache;
 pub mod leader_schedule_utils;
 pub mod loader_utils;
 pub mod non_circulating_supply;
@@ -33,6 +37,7 @@ mod reward_info;
 pub mod runtime_config;
 pub mod serde_snapshot;
 pub mod slot_params;
+pub mod snapshot_account_source;
 pub mod snapshot_bank_utils;
 pub mod snapshot_controller;
 pub mod snapshot_minimizer;
@@ -1,4 +1,15 @@
-use solana_compute_budget::compute_budget::ComputeBudget;
+use {
+    agave_feature_set::FeatureSet,
+    solana_account::AccountSharedData,
+    solana_compute_budget::compute_budget::ComputeBudget,
+    solana_fee_calculator::FeeRateGovernor,
+    solana_hash::Hash,
+    solana_program_runtime::{debugger::DebuggerConfig, invoke_context::RegisterTraceInspector},
+    solana_pubkey::Pubkey,
+    solana_svm::transaction_processing_result::TransactionProcessingResult,
+    solana_transaction::sanitized::SanitizedTransaction,
+    std::{fmt, sync::Arc},
+};
 
 /// Encapsulates flags that can be used to tweak the runtime behavior.
 #[derive(Debug, Default, Clone)]
@@ -9,4 +20,44 @@ pub struct RuntimeConfig {
     /// When true, skip storing transaction signature keys in the status cache.
     /// Message hash keys are still stored for duplicate transaction detection.
     pub skip_transaction_signatures_in_status_cache: bool,
+    /// When set, SBF programs run with VM register tracing enabled and every
+    /// trace is handed to the inspector. Tracing slows down execution
+    /// considerably, so this is meant for testing and profiling only.
+    pub register_trace_inspector: Option<Arc<dyn RegisterTraceInspector>>,
+    /// When set, the transactions the recorder selects are recorded along
+    /// with the bank state they are executed against
+    pub transaction_fixture_recorder: Option<Arc<dyn TransactionFixtureRecorder>>,
+    /// When set, the selected SBF program invocations are interpreted and
+    /// wait for a debugger to connect before they run, which stalls the
+    /// bank. This is meant for local testing only.
+    pub sbpf_debugger: Option<DebuggerConfig>,
+}
+
+/// Bank state a transaction is executed against
+#[derive(Debug)]
+pub struct TransactionFixtureInput {
+    /// The accounts the transaction loads, the programdata of the upgradeable
+    /// programs it invokes and the sysvars, as of before the transaction's
+    /// batch is executed. Accounts that do not exist are omitted.
+    pub accounts: Vec<(Pubkey, AccountSharedData)>,
+    pub feature_set: Arc<FeatureSet>,
+    /// Blockhashes and their lamports per signature, oldest first
+    pub blockhash_queue: Vec<(Hash, u64)>,
+    pub fee_rate_governor: FeeRateGovernor,
+    pub total_epoch_stake: u64,
+}
+
+/// Receives the executed transactions it selects, with the bank state they
+/// were executed against
+pub trait TransactionFixtureRecorder: fmt::Debug + Send + Sync {
+    /// Whether `transaction` should be recorded. The input accounts are only
+    /// collected for the transactions that are.
+    fn should_record(&self, transaction: &SanitizedTransaction) -> bool;
+
+    fn record(
+        &self,
+        transaction: &SanitizedTransaction,
+        input: TransactionFixtureInput,
+        processing_result: &TransactionProcessingResult,
+    );
 }
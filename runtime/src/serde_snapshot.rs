@@ -32,7 +32,9 @@ use {
         accounts_file::AccountsFile,
         accounts_hash::AccountsLtHash,
         accounts_update_notifier_interface::AccountsUpdateNotifier,
+        accounts_verification_progress::AccountsVerificationProgress,
         blockhash_queue::BlockhashQueue,
+        storage_scan_cache::StorageScanCache,
     },
     solana_clock::{Epoch, Slot, UnixTimestamp},
     solana_epoch_schedule::EpochSchedule,
@@ -608,6 +610,8 @@ where
         None, // leader_for_tests
         limit_load_slot_count_from_snapshot,
         verify_index,
+        None, // storage_scan_cache
+        None, // accounts_verification_progress
         accounts_db_config,
         accounts_update_notifier,
         exit,
@@ -885,6 +889,8 @@ pub(crate) fn reconstruct_bank_from_fields(
     leader_for_tests: Option<SlotLeader>,
     limit_load_slot_count_from_snapshot: Option<usize>,
     verify_index: bool,
+    storage_scan_cache: Option<&StorageScanCache>,
+    accounts_verification_progress: Option<&AccountsVerificationProgress>,
     accounts_db_config: AccountsDbConfig,
     accounts_update_notifier: Option<AccountsUpdateNotifier>,
     exit: Arc<AtomicBool>,
@@ -906,6 +912,8 @@ pub(crate) fn reconstruct_bank_from_fields(
         storage_and_next_append_vec_id,
         limit_load_slot_count_from_snapshot,
         verify_index,
+        storage_scan_cache,
+        accounts_verification_progress,
         accounts_db_config,
         accounts_update_notifier,
         exit,
@@ -1095,6 +1103,8 @@ fn reconstruct_accountsdb_from_fields(
     storage_and_next_append_vec_id: StorageAndNextAccountsFileId,
     limit_load_slot_count_from_snapshot: Option<usize>,
     verify_index: bool,
+    storage_scan_cache: Option<&StorageScanCache>,
+    accounts_verification_progress: Option<&AccountsVerificationProgress>,
     accounts_db_config: AccountsDbConfig,
     accounts_update_notifier: Option<AccountsUpdateNotifier>,
     exit: Arc<AtomicBool>,
@@ -1143,7 +1153,12 @@ fn reconstruct_accountsdb_from_fields(
         accounts_data_len,
         calculated_accounts_lt_hash,
         calculated_capitalization,
-    } = accounts_db.generate_index(limit_load_slot_count_from_snapshot, verify_index);
+    } = accounts_db.generate_index_with_scan_cache(
+        limit_load_slot_count_from_snapshot,
+        verify_index,
+        storage_scan_cache,
+        accounts_verification_progress,
+    );
     info!("Building accounts index... Done in {:?}", start.elapsed());
 
     Ok((
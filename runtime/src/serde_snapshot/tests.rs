@@ -70,6 +70,8 @@ mod serde_snapshot_tests {
             storage_and_next_append_vec_id,
             None,
             false,
+            None,
+            None,
             accounts_db_config,
             None,
             Arc::default(),
@@ -126,7 +128,10 @@ mod serde_snapshot_tests {
             // Copy file to new directory
             let file_name = AccountsFile::file_name(storage_entry.slot(), storage_entry.id());
             let output_path = output_dir.as_ref().join(file_name);
-            buf_reader.set_file(file.as_ref(), storage_entry.accounts.len() as u64)?;
+            buf_reader.set_file(
+                file.as_ref(),
+                storage_entry.accounts.archive_read_limit() as u64,
+            )?;
             let mut reader = AccountStorageReader::new(
                 storage_entry,
                 None,
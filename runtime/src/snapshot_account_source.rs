@@ -0,0 +1,620 @@
+//! Transaction execution against the accounts of an unpacked snapshot, without a `Bank`
+//!
+//! [`SnapshotAccountSource`] serves accounts straight from the storage files
+//! of a snapshot archive that has been unpacked to a directory, so that the
+//! SVM can be hosted without rebuilding a bank and its accounts index. Only
+//! the location of every account is indexed up front; account data is read
+//! when first requested and cached.
+//!
+//! Transactions are executed against the slot, sysvars and features stored
+//! in the snapshot. The results of executed transactions are kept in memory,
+//! the snapshot itself is never written to. Blockhash age, status cache and
+//! epoch stakes are not available, so transactions are not checked against
+//! them.
+
+use {
+    crate::{
+        account_state_diff::committed_accounts,
+        bank::address_lookup_table::into_address_loader_error,
+    },
+    agave_feature_set::FeatureSet,
+    agave_precompiles::{get_precompile, is_precompile},
+    agave_reserved_account_keys::ReservedAccountKeys,
+    log::*,
+    solana_account::{AccountSharedData, ReadableAccount},
+    solana_accounts_db::{
+        accounts_file::{AccountsFile, AccountsFileError},
+        utils::create_account_shared_data,
+    },
+    solana_address_lookup_table_interface::{
+        error::AddressLookupError, program as address_lookup_table, state::AddressLookupTable,
+    },
+    solana_builtins::BUILTINS,
+    solana_clock::{Clock, Slot},
+    solana_compute_budget::compute_budget::{ComputeBudget, SVMTransactionExecutionBudget},
+    solana_feature_gate_interface as feature,
+    solana_fee::calculate_fee_details,
+    solana_fee_structure::FeeStructure,
+    solana_hash::Hash,
+    solana_loader_v3_interface as bpf_loader_upgradeable,
+    solana_message::{
+        AddressLoader,
+        v0::{LoadedAddresses, MessageAddressTableLookup},
+    },
+    solana_precompile_error::PrecompileError,
+    solana_program_runtime::{
+        execution_budget::SVMTransactionExecutionAndFeeBudgetLimits,
+        loaded_programs::{BlockRelation, ForkGraph, ProgramRuntimeEnvironments},
+        program_cache_entry::ProgramCacheEntry,
+    },
+    solana_pubkey::Pubkey,
+    solana_runtime_transaction::transaction_with_meta::TransactionWithMeta,
+    solana_slot_hashes::SlotHashes,
+    solana_svm::{
+        account_loader::{CheckedTransactionDetails, TransactionCheckResult},
+        transaction_processing_result::TransactionProcessingResult,
+        transaction_processor::{
+            ExecutionRecordingConfig, LoadAndExecuteSanitizedTransactionsOutput,
+            TransactionBatchProcessor, TransactionProcessingConfig,
+            TransactionProcessingEnvironment,
+        },
+    },
+    solana_svm_callback::{InvokeContextCallback, TransactionProcessingCallback},
+    solana_syscalls::create_program_runtime_environment,
+    solana_transaction_error::AddressLoaderError,
+    std::{
+        collections::{HashMap, HashSet},
+        fs, io,
+        path::{Path, PathBuf},
+        sync::{Arc, RwLock},
+    },
+    thiserror::Error,
+};
+
+/// Directory of the storage files in an unpacked snapshot archive
+const ACCOUNTS_DIR: &str = "accounts";
+
+#[derive(Error, Debug)]
+pub enum SnapshotAccountSourceError {
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("failed to read accounts file: {0}")]
+    AccountsFile(#[from] AccountsFileError),
+
+    #[error("invalid accounts file name: {}", .0.display())]
+    InvalidAccountsFileName(PathBuf),
+
+    #[error("no accounts files in {}", .0.display())]
+    NoAccountsFiles(PathBuf),
+
+    #[error("missing or invalid clock sysvar")]
+    MissingClock,
+}
+
+/// Where the latest version of an account is stored
+#[derive(Debug, Clone, Copy)]
+struct AccountLocation {
+    slot: Slot,
+    /// Index into `SnapshotAccountSource::storages`
+    storage: usize,
+    offset: usize,
+}
+
+/// The program cache only ever holds programs of the snapshot's slot and the
+/// slots after it
+#[derive(Debug, Default)]
+pub struct SnapshotForkGraph;
+
+impl ForkGraph for SnapshotForkGraph {
+    fn relationship(&self, a: Slot, b: Slot) -> BlockRelation {
+        match a.cmp(&b) {
+            std::cmp::Ordering::Less => BlockRelation::Ancestor,
+            std::cmp::Ordering::Equal => BlockRelation::Equal,
+            std::cmp::Ordering::Greater => BlockRelation::Descendant,
+        }
+    }
+}
+
+/// Accounts of an unpacked snapshot, loaded on demand, along with the
+/// transaction processor to execute transactions against them
+pub struct SnapshotAccountSource {
+    storages: Vec<AccountsFile>,
+    index: HashMap<Pubkey, AccountLocation>,
+    /// Accounts read from the storages or written by executed transactions.
+    /// `None` for accounts that do not exist.
+    cache: RwLock<HashMap<Pubkey, Option<AccountSharedData>>>,
+    clock: Clock,
+    feature_set: FeatureSet,
+    reserved_account_keys: HashSet<Pubkey>,
+    transaction_processor: TransactionBatchProcessor<SnapshotForkGraph>,
+    /// Kept alive for the program cache, which only holds a weak reference
+    _fork_graph: Arc<RwLock<SnapshotForkGraph>>,
+}
+
+impl SnapshotAccountSource {
+    /// Opens the snapshot unpacked to `snapshot_dir`, which contains the
+    /// `accounts` directory of the archive. An incremental snapshot may be
+    /// unpacked on top of its full snapshot.
+    pub fn new(snapshot_dir: &Path) -> Result<Self, SnapshotAccountSourceError> {
+        let accounts_dir = snapshot_dir.join(ACCOUNTS_DIR);
+        let (storages, index) = Self::index_storages(&accounts_dir)?;
+        info!(
+            "Indexed {} accounts in {} accounts files",
+            index.len(),
+            storages.len(),
+        );
+
+        let fork_graph = Arc::new(RwLock::new(SnapshotForkGraph));
+        let mut source = Self {
+            storages,
+            index,
+            cache: RwLock::default(),
+            clock: Clock::default(),
+            feature_set: FeatureSet::default(),
+            reserved_account_keys: HashSet::default(),
+            transaction_processor: TransactionBatchProcessor::default(),
+            _fork_graph: Arc::clone(&fork_graph),
+        };
+        source.clock = source
+            .get_account(&solana_sdk_ids::sysvar::clock::id())
+            .and_then(|account| bincode::deserialize(account.data()).ok())
+            .ok_or(SnapshotAccountSourceError::MissingClock)?;
+        source.feature_set = source.compute_feature_set();
+        source.reserved_account_keys = {
+            let mut reserved_account_keys = ReservedAccountKeys::default();
+            reserved_account_keys.update_active_set(&source.feature_set);
+            reserved_account_keys.active
+        };
+        source.transaction_processor = source.new_transaction_processor(&fork_graph);
+        Ok(source)
+    }
+
+    /// Opens every storage file in `accounts_dir` and locates the latest
+    /// version of every account in them
+    fn index_storages(
+        accounts_dir: &Path,
+    ) -> Result<(Vec<AccountsFile>, HashMap<Pubkey, AccountLocation>), SnapshotAccountSourceError>
+    {
+        let mut storages = Vec::new();
+        let mut index: HashMap<Pubkey, AccountLocation> = HashMap::new();
+        for entry in fs::read_dir(accounts_dir)? {
+            let path = entry?.path();
+            let slot = path
+                .file_name()
+                .and_then(|file_name| file_name.to_str())
+                .and_then(|file_name| file_name.split_once('.'))
+                .and_then(|(slot, _id)| slot.parse::<Slot>().ok())
+                .ok_or_else(|| SnapshotAccountSourceError::InvalidAccountsFileName(path.clone()))?;
+            let storage = AccountsFile::new_for_startup(agave_fs::FileInfo::new_from_path(&path)?)?;
+            // The storage belongs to the snapshot, not to us
+            storage.disable_remove_on_drop();
+
+            let storage_index = storages.len();
+            storage.scan_accounts_without_data(|offset, account| {
+                let location = AccountLocation {
+                    slot,
+                    storage: storage_index,
+                    offset,
+                };
+                index
+                    .entry(*account.pubkey)
+                    .and_modify(|latest| {
+                        if slot >= latest.slot {
+                            *latest = location;
+                        }
+                    })
+                    .or_insert(location);
+            })?;
+            storages.push(storage);
+        }
+        if storages.is_empty() {
+            return Err(SnapshotAccountSourceError::NoAccountsFiles(
+                accounts_dir.to_path_buf(),
+            ));
+        }
+        Ok((storages, index))
+    }
+
+    /// Features activated at or before the snapshot's slot
+    fn compute_feature_set(&self) -> FeatureSet {
+        let mut feature_set = FeatureSet::default();
+        let inactive: Vec<_> = feature_set.inactive().iter().copied().collect();
+        for feature_id in inactive {
+            if let Some(activated_at) = self
+                .get_account(&feature_id)
+                .and_then(|account| feature::state::from_account(&account))
+                .and_then(|feature| feature.activated_at)
+                .filter(|activated_at| *activated_at <= self.clock.slot)
+            {
+                feature_set.activate(&feature_id, activated_at);
+            }
+        }
+        feature_set
+    }
+
+    fn new_transaction_processor(
+        &self,
+        fork_graph: &Arc<RwLock<SnapshotForkGraph>>,
+    ) -> TransactionBatchProcessor<SnapshotForkGraph> {
+        let mut transaction_processor =
+            TransactionBatchProcessor::new_uninitialized(self.clock.slot, self.clock.epoch);
+        {
+            let mut program_cache = transaction_processor.global_program_cache.write().unwrap();
+            program_cache.set_fork_graph(Arc::downgrade(fork_graph));
+            program_cache.latest_root_slot = self.clock.slot;
+        }
+        transaction_processor
+            .epoch_boundary_preparation
+            .write()
+            .unwrap()
+            .upcoming_epoch = self.clock.epoch;
+
+        let compute_budget = ComputeBudget::new_with_defaults(
+            self.feature_set.snapshot().raise_cpi_nesting_limit_to_8,
+        );
+        transaction_processor.program_runtime_environment = create_program_runtime_environment(
+            &self.feature_set.runtime_features(),
+            &compute_budget.to_budget(),
+            false, /* deployment */
+            false, /* debugging_features */
+        )
+        .unwrap();
+
+        for builtin in BUILTINS.iter() {
+            // Builtins that were migrated to Core BPF are loaded from their
+            // program accounts instead
+            let builtin_is_bpf = builtin.core_bpf_migration_config.is_some()
+                && self
+                    .get_account(&builtin.program_id)
+                    .is_some_and(|account| account.owner() == &bpf_loader_upgradeable::id());
+            let builtin_is_active = builtin
+                .enable_feature_id
+                .is_none_or(|feature_id| self.feature_set.is_active(&feature_id));
+            if builtin_is_active && !builtin_is_bpf {
+                transaction_processor.add_builtin(
+                    builtin.program_id,
+                    ProgramCacheEntry::new_builtin(builtin.register_fn),
+                );
+            }
+        }
+
+        transaction_processor.fill_missing_sysvar_cache_entries(self);
+        transaction_processor
+    }
+
+    /// Slot of the snapshot, at which transactions are executed
+    pub fn slot(&self) -> Slot {
+        self.clock.slot
+    }
+
+    pub fn feature_set(&self) -> &FeatureSet {
+        &self.feature_set
+    }
+
+    /// Reserved account keys active at the snapshot's slot, for sanitizing
+    /// transactions
+    pub fn reserved_account_keys(&self) -> &HashSet<Pubkey> {
+        &self.reserved_account_keys
+    }
+
+    /// The current state of the account, reading it from the snapshot on first
+    /// use
+    pub fn get_account(&self, pubkey: &Pubkey) -> Option<AccountSharedData> {
+        if let Some(account) = self.cache.read().unwrap().get(pubkey) {
+            return account.clone();
+        }
+        let account = self.index.get(pubkey).and_then(|location| {
+            self.storages[location.storage]
+                .get_stored_account_callback(location.offset, |account| {
+                    create_account_shared_data(&account)
+                })
+                .filter(|account| account.lamports() != 0)
+        });
+        self.cache
+            .write()
+            .unwrap()
+            .entry(*pubkey)
+            .or_insert(account)
+            .clone()
+    }
+
+    /// Overwrites the state of the account for the transactions executed after
+    pub fn store_account(&self, pubkey: Pubkey, account: AccountSharedData) {
+        let account = (account.lamports() != 0).then_some(account);
+        self.cache.write().unwrap().insert(pubkey, account);
+    }
+
+    /// Executes `transactions` in order, in a single batch, and stores the
+    /// accounts they write for the transactions executed after.
+    pub fn process_transactions(
+        &self,
+        transactions: &[impl TransactionWithMeta],
+        recording_config: ExecutionRecordingConfig,
+    ) -> LoadAndExecuteSanitizedTransactionsOutput {
+        let check_results = transactions
+            .iter()
+            .map(|transaction| self.check_transaction(transaction))
+            .collect();
+
+        let sysvar_cache = self.transaction_processor.sysvar_cache();
+        let rent = sysvar_cache
+            .get_rent()
+            .map(|rent| (*rent).clone())
+            .unwrap_or_default();
+        #[allow(deprecated)]
+        let blockhash = sysvar_cache
+            .get_recent_blockhashes()
+            .ok()
+            .and_then(|recent_blockhashes| {
+                recent_blockhashes
+                    .first()
+                    .map(|entry| (entry.blockhash, entry.fee_calculator.lamports_per_signature))
+            });
+        drop(sysvar_cache);
+        let (blockhash, blockhash_lamports_per_signature) = blockhash.unwrap_or((
+            Hash::default(),
+            FeeStructure::default().lamports_per_signature,
+        ));
+
+        let program_runtime_environment = &self.transaction_processor.program_runtime_environment;
+        let environment = TransactionProcessingEnvironment {
+            blockhash,
+            blockhash_lamports_per_signature,
+            alpenglow_migration_succeeded: false,
+            epoch_total_stake: 0,
+            feature_set: self.feature_set.runtime_features(),
+            program_runtime_environments: ProgramRuntimeEnvironments::new(
+                program_runtime_environment.clone(),
+                program_runtime_environment.clone(),
+            ),
+            rent,
+        };
+        let config = TransactionProcessingConfig {
+            recording_config,
+            ..TransactionProcessingConfig::default()
+        };
+
+        let output = self
+            .transaction_processor
+            .load_and_execute_sanitized_transactions(
+                self,
+                transactions,
+                check_results,
+                &environment,
+                &config,
+            );
+        self.store_processing_results(transactions, &output.processing_results);
+        output
+    }
+
+    fn check_transaction(&self, transaction: &impl TransactionWithMeta) -> TransactionCheckResult {
+        let config = transaction.transaction_configuration(&self.feature_set)?;
+        let fee_details = calculate_fee_details(
+            transaction,
+            FeeStructure::default().lamports_per_signature,
+            config.priority_fee_lamports,
+            solana_fee::FeeFeatures {},
+        );
+        Ok(CheckedTransactionDetails::new(
+            None,
+            SVMTransactionExecutionAndFeeBudgetLimits {
+                budget: SVMTransactionExecutionBudget {
+                    compute_unit_limit: u64::from(config.compute_unit_limit),
+                    heap_size: config.updated_heap_bytes,
+                    ..SVMTransactionExecutionBudget::new_with_defaults(
+                        self.feature_set.snapshot().raise_cpi_nesting_limit_to_8,
+                    )
+                },
+                loaded_accounts_data_size_limit: config.loaded_accounts_data_size_limit,
+                fee_details,
+            },
+        ))
+    }
+
+    fn store_processing_results(
+        &self,
+        transactions: &[impl TransactionWithMeta],
+        processing_results: &[TransactionProcessingResult],
+    ) {
+        for (transaction, processing_result) in transactions.iter().zip(processing_results) {
+            for (pubkey, account) in committed_accounts(transaction, processing_result) {
+                self.store_account(*pubkey, account.clone());
+            }
+        }
+    }
+
+    fn load_lookup_table_addresses_into(
+        &self,
+        address_table_lookup: &MessageAddressTableLookup,
+        slot_hashes: &SlotHashes,
+        loaded_addresses: &mut LoadedAddresses,
+    ) -> Result<(), AddressLookupError> {
+        let table_account = self
+            .get_account(&address_table_lookup.account_key)
+            .ok_or(AddressLookupError::LookupTableAccountNotFound)?;
+        if table_account.owner() != &address_lookup_table::id() {
+            return Err(AddressLookupError::InvalidAccountOwner);
+        }
+        let lookup_table = AddressLookupTable::deserialize(table_account.data())
+            .map_err(|_ix_err| AddressLookupError::InvalidAccountData)?;
+
+        for (indexes, addresses) in [
+            (
+                &address_table_lookup.writable_indexes,
+                &mut loaded_addresses.writable,
+            ),
+            (
+                &address_table_lookup.readonly_indexes,
+                &mut loaded_addresses.readonly,
+            ),
+        ] {
+            for address in lookup_table.lookup_iter(self.clock.slot, indexes, slot_hashes)? {
+                addresses.push(address.ok_or(AddressLookupError::InvalidLookupIndex)?);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TransactionProcessingCallback for SnapshotAccountSource {
+    fn get_account_shared_data(&self, pubkey: &Pubkey) -> Option<(AccountSharedData, Slot)> {
+        let account = self.get_account(pubkey)?;
+        let slot = self
+            .index
+            .get(pubkey)
+            .map_or(self.clock.slot, |location| location.slot);
+        Some((account, slot))
+    }
+}
+
+impl InvokeContextCallback for SnapshotAccountSource {
+    fn is_precompile(&self, program_id: &Pubkey) -> bool {
+        is_precompile(program_id, |feature_id: &Pubkey| {
+            self.feature_set.is_active(feature_id)
+        })
+    }
+
+    fn process_precompile(
+        &self,
+        program_id: &Pubkey,
+        data: &[u8],
+        instruction_datas: Vec<&[u8]>,
+    ) -> Result<(), PrecompileError> {
+        if let Some(precompile) = get_precompile(program_id, |feature_id: &Pubkey| {
+            self.feature_set.is_active(feature_id)
+        }) {
+            precompile.verify(data, &instruction_datas, &self.feature_set)
+        } else {
+            Err(PrecompileError::InvalidPublicKey)
+        }
+    }
+}
+
+impl AddressLoader for &SnapshotAccountSource {
+    fn load_addresses(
+        self,
+        address_table_lookups: &[MessageAddressTableLookup],
+    ) -> Result<LoadedAddresses, AddressLoaderError> {
+        let slot_hashes = self
+            .transaction_processor
+            .sysvar_cache()
+            .get_slot_hashes()
+            .map_err(|_| AddressLoaderError::SlotHashesSysvarNotFound)?;
+
+        let mut loaded_addresses = LoadedAddresses::default();
+        for address_table_lookup in address_table_lookups {
+            self.load_lookup_table_addresses_into(
+                address_table_lookup,
+                &slot_hashes,
+                &mut loaded_addresses,
+            )
+            .map_err(into_address_loader_error)?;
+        }
+        Ok(loaded_addresses)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        solana_account::WritableAccount,
+        solana_accounts_db::append_vec::AppendVec,
+        solana_keypair::Keypair,
+        solana_native_token::LAMPORTS_PER_SOL,
+        solana_rent::Rent,
+        solana_runtime_transaction::runtime_transaction::RuntimeTransaction,
+        solana_sdk_ids::{native_loader, system_program, sysvar},
+        solana_signer::Signer,
+        solana_system_transaction as system_transaction,
+        std::fs::OpenOptions,
+    };
+
+    /// Writes `accounts` to a storage file for `slot`, sized like the ones in
+    /// snapshot archives
+    fn write_storage(accounts_dir: &Path, slot: Slot, accounts: &[(Pubkey, AccountSharedData)]) {
+        let path = accounts_dir.join(AccountsFile::file_name(slot, 0));
+        let append_vec = AppendVec::new(&path, 1024 * 1024);
+        append_vec.append_accounts(&(slot, accounts)).unwrap();
+        append_vec.flush().unwrap();
+        append_vec.disable_remove_on_drop();
+        let len = append_vec.len();
+        drop(append_vec);
+        OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_len(len as u64)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_process_transactions() {
+        let snapshot_dir = tempfile::TempDir::new().unwrap();
+        let accounts_dir = snapshot_dir.path().join(ACCOUNTS_DIR);
+        fs::create_dir(&accounts_dir).unwrap();
+
+        let payer = Keypair::new();
+        let to = Pubkey::new_unique();
+        let system_account = |lamports| AccountSharedData::new(lamports, 0, &system_program::id());
+        let mut system_program_account = AccountSharedData::new(1, 0, &native_loader::id());
+        system_program_account.set_data_from_slice(b"solana_system_program");
+        system_program_account.set_executable(true);
+        write_storage(
+            &accounts_dir,
+            5,
+            &[
+                (payer.pubkey(), system_account(LAMPORTS_PER_SOL)),
+                (system_program::id(), system_program_account),
+            ],
+        );
+        let clock = Clock {
+            slot: 10,
+            ..Clock::default()
+        };
+        write_storage(
+            &accounts_dir,
+            10,
+            &[
+                (payer.pubkey(), system_account(2 * LAMPORTS_PER_SOL)),
+                (
+                    sysvar::clock::id(),
+                    AccountSharedData::new_data(1, &clock, &sysvar::id()).unwrap(),
+                ),
+                (
+                    sysvar::rent::id(),
+                    AccountSharedData::new_data(1, &Rent::default(), &sysvar::id()).unwrap(),
+                ),
+            ],
+        );
+
+        let source = SnapshotAccountSource::new(snapshot_dir.path()).unwrap();
+        assert_eq!(source.slot(), 10);
+        // The latest version of the account is loaded
+        assert_eq!(
+            source.get_account(&payer.pubkey()).unwrap().lamports(),
+            2 * LAMPORTS_PER_SOL
+        );
+        assert_eq!(source.get_account(&to), None);
+
+        let amount = LAMPORTS_PER_SOL / 2;
+        let transaction = RuntimeTransaction::from_transaction_for_tests(
+            system_transaction::transfer(&payer, &to, amount, Hash::default()),
+        );
+        let output =
+            source.process_transactions(&[transaction], ExecutionRecordingConfig::default());
+        let processed_transaction = output.processing_results[0].as_ref().unwrap();
+        assert_eq!(processed_transaction.status(), Ok(()));
+        let fee = processed_transaction.fee_details().total_fee();
+
+        // Later transactions see the accounts written by this one
+        assert_eq!(source.get_account(&to).unwrap().lamports(), amount);
+        assert_eq!(
+            source.get_account(&payer.pubkey()).unwrap().lamports(),
+            2 * LAMPORTS_PER_SOL - amount - fee
+        );
+        // The snapshot itself is left as is
+        assert!(accounts_dir.join(AccountsFile::file_name(10, 0)).exists());
+    }
+}
@@ -45,6 +45,8 @@ use {
     solana_accounts_db::{
         accounts_db::{AccountsDbConfig, AtomicAccountsFileId},
         accounts_update_notifier_interface::AccountsUpdateNotifier,
+        accounts_verification_progress::AccountsVerificationProgress,
+        storage_scan_cache::StorageScanCache,
     },
     solana_clock::{Epoch, Slot},
     solana_genesis_config::GenesisConfig,
@@ -54,6 +56,7 @@ use {
     solana_slot_history::{Check, SlotHistory},
     std::{
         collections::{HashMap, HashSet},
+        iter,
         ops::RangeInclusive,
         path::PathBuf,
         sync::{Arc, atomic::AtomicBool},
@@ -201,6 +204,18 @@ pub fn bank_from_snapshot_archives(
         storage,
         next_append_vec_id,
     };
+    // Unpacking the same archives again gives the same storages, so a startup interrupted before
+    // the accounts are verified resumes from the storages it already hashed.
+    let archives: Vec<_> = iter::once(full_snapshot_archive_info.snapshot_archive_info())
+        .chain(incremental_snapshot_archive_info.map(|info| info.snapshot_archive_info()))
+        .map(|info| (info.slot, info.hash.0))
+        .collect();
+    let accounts_verification_progress = AccountsVerificationProgress::open(
+        snapshot_paths::build_accounts_verification_progress_path(
+            &snapshot_config.bank_snapshots_dir,
+        ),
+        &archives,
+    );
 
     let mut measure_rebuild = Measure::start("rebuild bank from snapshots");
     let (bank, info) = reconstruct_bank_from_fields(
@@ -214,6 +229,8 @@ pub fn bank_from_snapshot_archives(
         leader_for_tests,
         limit_load_slot_count_from_snapshot,
         verify_index,
+        None, // storage_scan_cache
+        Some(&accounts_verification_progress),
         accounts_db_config,
         accounts_update_notifier,
         exit,
@@ -265,13 +282,15 @@ pub fn bank_from_snapshot_archives(
     )?;
 
     let mut measure_verify = Measure::start("verify");
-    if !bank.verify_snapshot_bank(
+    let is_verified = bank.verify_snapshot_bank(
         accounts_db_skip_shrink || !full_snapshot_archive_info.is_remote(),
         accounts_db_force_initial_clean,
         full_snapshot_archive_info.slot(),
         Some(&info.calculated_accounts_lt_hash),
-    ) && limit_load_slot_count_from_snapshot.is_none()
-    {
+    );
+    // Either way, a later startup has nothing to resume
+    accounts_verification_progress.remove();
+    if !is_verified && limit_load_slot_count_from_snapshot.is_none() {
         panic!("Snapshot bank for slot {} failed to verify", bank.slot());
     }
     measure_verify.stop();
@@ -398,6 +417,21 @@ pub fn bank_from_snapshot_dir(
         storage,
         next_append_vec_id,
     };
+    // Storages keep their ids when booting from local state, so the storage scan cache
+    // kept alongside the bank snapshots can stand in for scanning them.
+    let storage_scan_cache = bank_snapshot
+        .snapshot_dir
+        .parent()
+        .map(snapshot_paths::build_storage_scan_cache_dir)
+        .filter(|scan_cache_dir| scan_cache_dir.is_dir())
+        .map(StorageScanCache::new);
+    if let Some(storage_scan_cache) = &storage_scan_cache {
+        info!(
+            "Using storage scan cache at '{}'",
+            storage_scan_cache.dir().display(),
+        );
+    }
+
     let snapshot_bank_fields = SnapshotBankFields::new(bank_fields, None);
     let snapshot_accounts_db_fields = SnapshotAccountsDbFields::new(accounts_db_fields, None);
     let ((bank, info), measure_rebuild_bank) = measure_time!(
@@ -412,6 +446,8 @@ pub fn bank_from_snapshot_dir(
             leader_for_tests,
             limit_load_slot_count_from_snapshot,
             verify_index,
+            storage_scan_cache.as_ref(),
+            None, // accounts_verification_progress
             accounts_db_config,
             accounts_update_notifier,
             exit,
@@ -2123,8 +2159,11 @@ mod tests {
     ///
     /// If zero lamport accounts are not handled correctly, Account1 or Account2 will come back
     /// failing the test
-    #[test]
-    fn test_fastboot_handle_zero_lamport_accounts() {
+    ///
+    /// The same must hold when the index is generated from a storage scan cache.
+    #[test_case(false)]
+    #[test_case(true)]
+    fn test_fastboot_handle_zero_lamport_accounts(use_storage_scan_cache: bool) {
         let key1 = Keypair::new();
         let key2 = Keypair::new();
 
@@ -2180,6 +2219,15 @@ mod tests {
             true,
         )
         .unwrap();
+        if use_storage_scan_cache {
+            let scan_cache = StorageScanCache::new(snapshot_paths::build_storage_scan_cache_dir(
+                &bank_snapshots_dir,
+            ));
+            let stats = scan_cache
+                .update(&bank2.get_snapshot_storages(None))
+                .unwrap();
+            assert!(stats.num_written > 0);
+        }
 
         let account_paths = &bank2.rc.accounts.accounts_db.paths;
         let bank_snapshot = get_highest_bank_snapshot(&bank_snapshots_dir).unwrap();
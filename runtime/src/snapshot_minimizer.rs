@@ -10,19 +10,27 @@ use {
         iter::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator},
         prelude::ParallelSlice,
     },
-    solana_account::ReadableAccount,
+    solana_account::{AccountSharedData, ReadableAccount},
     solana_accounts_db::{
         account_storage_entry::AccountStorageEntry,
         accounts_db::{AccountsDb, GetUniqueAccountsResult},
         storable_accounts::StorableAccountsBySlot,
     },
+    solana_address_lookup_table_interface::{
+        self as address_lookup_table, state::AddressLookupTable,
+    },
     solana_clock::Slot,
     solana_loader_v3_interface::state::UpgradeableLoaderState,
     solana_measure::measure_time,
     solana_pubkey::Pubkey,
     solana_sdk_ids::bpf_loader_upgradeable,
+    spl_generic_token::{
+        token::{self, GenericTokenAccount},
+        token_2022,
+    },
     std::{
         collections::HashSet,
+        str::FromStr,
         sync::{
             Arc, Mutex,
             atomic::{AtomicUsize, Ordering},
@@ -30,6 +38,169 @@ use {
     },
 };
 
+/// Accounts to keep in a minimized snapshot, in addition to the accounts needed
+/// to replay a slot range
+#[derive(Debug, Default, Clone)]
+pub struct AccountSelection {
+    /// Keep these accounts
+    pub pubkeys: HashSet<Pubkey>,
+    /// Keep all accounts owned by these programs
+    pub owners: HashSet<Pubkey>,
+    /// Keep all accounts matching any of these filters
+    pub filters: Vec<AccountFilter>,
+}
+
+impl AccountSelection {
+    fn requires_scan(&self) -> bool {
+        !self.owners.is_empty() || !self.filters.is_empty()
+    }
+
+    fn matches(&self, account: &AccountSharedData) -> bool {
+        self.owners.contains(account.owner())
+            || self.filters.iter().any(|filter| filter.matches(account))
+    }
+}
+
+/// A filter expression that selects accounts
+///
+/// An expression is one or more terms joined by `&&`, all of which must match:
+/// - `owner == PUBKEY`, `owner != PUBKEY`
+/// - `lamports OP N`, `data_len OP N`, where OP is one of `==`, `!=`, `<`, `<=`, `>`, `>=`
+/// - `executable == true`, `executable == false`
+/// - `memcmp(OFFSET, BYTES)`, where BYTES is base58 encoded and must be found
+///   in the account data at OFFSET
+///
+/// e.g. `owner == Stake11111111111111111111111111111111111111 && lamports >= 1000000000`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountFilter {
+    terms: Vec<FilterTerm>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum FilterTerm {
+    Owner(Comparison, Pubkey),
+    Lamports(Comparison, u64),
+    DataLen(Comparison, u64),
+    Executable(Comparison, bool),
+    Memcmp { offset: usize, bytes: Vec<u8> },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Comparison {
+    /// Operators ordered so that no operator is matched by a prefix of an earlier one
+    const OPERATORS: [(&'static str, Self); 6] = [
+        ("==", Self::Eq),
+        ("!=", Self::Ne),
+        ("<=", Self::Le),
+        (">=", Self::Ge),
+        ("<", Self::Lt),
+        (">", Self::Gt),
+    ];
+
+    fn compare<T: Ord>(self, lhs: &T, rhs: &T) -> bool {
+        match self {
+            Self::Eq => lhs == rhs,
+            Self::Ne => lhs != rhs,
+            Self::Lt => lhs < rhs,
+            Self::Le => lhs <= rhs,
+            Self::Gt => lhs > rhs,
+            Self::Ge => lhs >= rhs,
+        }
+    }
+}
+
+impl AccountFilter {
+    pub fn matches(&self, account: &impl ReadableAccount) -> bool {
+        self.terms.iter().all(|term| match term {
+            FilterTerm::Owner(comparison, owner) => comparison.compare(account.owner(), owner),
+            FilterTerm::Lamports(comparison, lamports) => {
+                comparison.compare(&account.lamports(), lamports)
+            }
+            FilterTerm::DataLen(comparison, data_len) => {
+                comparison.compare(&(account.data().len() as u64), data_len)
+            }
+            FilterTerm::Executable(comparison, executable) => {
+                comparison.compare(&account.executable(), executable)
+            }
+            FilterTerm::Memcmp { offset, bytes } => account
+                .data()
+                .get(*offset..)
+                .is_some_and(|data| data.starts_with(bytes)),
+        })
+    }
+}
+
+impl FromStr for AccountFilter {
+    type Err = String;
+
+    fn from_str(expression: &str) -> Result<Self, Self::Err> {
+        let terms = expression
+            .split("&&")
+            .map(|term| parse_filter_term(term.trim()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| format!("invalid filter expression '{expression}': {err}"))?;
+        Ok(Self { terms })
+    }
+}
+
+fn parse_filter_term(term: &str) -> Result<FilterTerm, String> {
+    if let Some(args) = term
+        .strip_prefix("memcmp(")
+        .and_then(|args| args.strip_suffix(')'))
+    {
+        let (offset, bytes) = args
+            .split_once(',')
+            .ok_or_else(|| format!("'{term}' must be memcmp(OFFSET, BYTES)"))?;
+        let offset = offset
+            .trim()
+            .parse()
+            .map_err(|err| format!("invalid memcmp offset in '{term}': {err}"))?;
+        let bytes = bs58::decode(bytes.trim())
+            .into_vec()
+            .map_err(|err| format!("invalid memcmp bytes in '{term}': {err}"))?;
+        return Ok(FilterTerm::Memcmp { offset, bytes });
+    }
+
+    let (field, comparison, value) = Comparison::OPERATORS
+        .iter()
+        .find_map(|(operator, comparison)| {
+            term.split_once(operator)
+                .map(|(field, value)| (field.trim(), *comparison, value.trim()))
+        })
+        .ok_or_else(|| format!("'{term}' has no comparison operator"))?;
+    let parse_err = |err: &dyn std::fmt::Display| format!("invalid value in '{term}': {err}");
+    match field {
+        "owner" | "executable" if !matches!(comparison, Comparison::Eq | Comparison::Ne) => {
+            Err(format!("'{term}' only supports == and !="))
+        }
+        "owner" => Pubkey::from_str(value)
+            .map(|owner| FilterTerm::Owner(comparison, owner))
+            .map_err(|err| parse_err(&err)),
+        "lamports" => value
+            .parse()
+            .map(|lamports| FilterTerm::Lamports(comparison, lamports))
+            .map_err(|err| parse_err(&err)),
+        "data_len" => value
+            .parse()
+            .map(|data_len| FilterTerm::DataLen(comparison, data_len))
+            .map_err(|err| parse_err(&err)),
+        "executable" => value
+            .parse()
+            .map(|executable| FilterTerm::Executable(comparison, executable))
+            .map_err(|err| parse_err(&err)),
+        _ => Err(format!("unknown field '{field}' in '{term}'")),
+    }
+}
+
 /// Used to modify bank and accounts_db to create a minimized snapshot
 pub struct SnapshotMinimizer<'a> {
     bank: &'a Bank,
@@ -49,6 +220,27 @@ impl<'a> SnapshotMinimizer<'a> {
         starting_slot: Slot,
         transaction_account_set: DashSet<Pubkey>,
         should_recalculate_accounts_lt_hash: bool,
+    ) {
+        Self::minimize_with_selection(
+            bank,
+            starting_slot,
+            transaction_account_set,
+            &AccountSelection::default(),
+            should_recalculate_accounts_lt_hash,
+        );
+    }
+
+    /// Same as `minimize`, but additionally keeps the accounts in `selection`.
+    ///
+    /// Kept accounts pull in the accounts they reference, transitively: owners,
+    /// programdata of upgradeable programs, addresses of lookup tables and mints
+    /// of token accounts.
+    pub fn minimize_with_selection(
+        bank: &'a Bank,
+        starting_slot: Slot,
+        transaction_account_set: DashSet<Pubkey>,
+        selection: &AccountSelection,
+        should_recalculate_accounts_lt_hash: bool,
     ) {
         let minimizer = SnapshotMinimizer {
             bank,
@@ -56,6 +248,10 @@ impl<'a> SnapshotMinimizer<'a> {
             minimized_account_set: transaction_account_set,
         };
 
+        minimizer.add_accounts(
+            |minimizer| minimizer.get_selected_accounts(selection),
+            "selected accounts",
+        );
         minimizer.add_accounts(Self::get_active_bank_features, "active bank features");
         minimizer.add_accounts(Self::get_inactive_bank_features, "inactive bank features");
         minimizer.add_accounts(Self::get_static_runtime_accounts, "static runtime accounts");
@@ -63,8 +259,7 @@ impl<'a> SnapshotMinimizer<'a> {
 
         minimizer.add_accounts(Self::get_vote_accounts, "vote accounts");
         minimizer.add_accounts(Self::get_stake_accounts, "stake accounts");
-        minimizer.add_accounts(Self::get_owner_accounts, "owner accounts");
-        minimizer.add_accounts(Self::get_programdata_accounts, "programdata accounts");
+        minimizer.add_accounts(Self::get_referenced_accounts, "referenced accounts");
 
         // The minimized output is the next full snapshot: no incremental snapshot will
         // build on an earlier one, so zero-lamport and tombstone retention is measured
@@ -157,13 +352,66 @@ impl<'a> SnapshotMinimizer<'a> {
         self.bank.get_stake_accounts(&self.minimized_account_set);
     }
 
+    /// Used to get the accounts in `selection` in `minimize`
+    /// Owners and filters are matched against every account in the bank
+    fn get_selected_accounts(&self, selection: &AccountSelection) {
+        selection.pubkeys.iter().for_each(|pubkey| {
+            self.minimized_account_set.insert(*pubkey);
+        });
+        if !selection.requires_scan() {
+            return;
+        }
+        self.bank
+            .scan_all_accounts(|item| {
+                if let Some((pubkey, account, _slot)) = item
+                    && account.lamports() != 0
+                    && selection.matches(&account)
+                {
+                    self.minimized_account_set.insert(*pubkey);
+                }
+            })
+            .expect("must scan accounts");
+    }
+
+    /// Used to get referenced accounts in `minimize`
+    /// Adds the accounts referenced by accounts in `minimized_account_set` until no
+    /// more are found, since a newly added account may itself reference others.
+    /// Each account is loaded once and shared by all of the reference lookups.
+    fn get_referenced_accounts(&self) {
+        let mut loaded_pubkeys = HashSet::new();
+        loop {
+            let pubkeys: Vec<_> = self
+                .minimized_account_set
+                .iter()
+                .map(|pubkey| *pubkey)
+                .filter(|pubkey| !loaded_pubkeys.contains(pubkey))
+                .collect();
+            if pubkeys.is_empty() {
+                break;
+            }
+            let accounts = self.load_accounts(&pubkeys);
+            loaded_pubkeys.extend(pubkeys);
+
+            self.get_owner_accounts(&accounts);
+            self.get_programdata_accounts(&accounts);
+            self.get_lookup_table_addresses(&accounts);
+            self.get_token_mint_accounts(&accounts);
+        }
+    }
+
+    /// Loads the accounts for `pubkeys` that exist in the bank
+    fn load_accounts(&self, pubkeys: &[Pubkey]) -> Vec<AccountSharedData> {
+        pubkeys
+            .par_iter()
+            .filter_map(|pubkey| self.bank.get_account(pubkey))
+            .collect()
+    }
+
     /// Used to get owner accounts in `minimize`
-    /// For each account in `minimized_account_set` adds the owner account's pubkey to `minimized_account_set`.
-    fn get_owner_accounts(&self) {
-        let owner_accounts: HashSet<_> = self
-            .minimized_account_set
+    /// For each account in `accounts` adds the owner account's pubkey to `minimized_account_set`.
+    fn get_owner_accounts(&self, accounts: &[AccountSharedData]) {
+        let owner_accounts: HashSet<_> = accounts
             .par_iter()
-            .filter_map(|pubkey| self.bank.get_account(&pubkey))
             .map(|account| *account.owner())
             .collect();
         owner_accounts.into_par_iter().for_each(|pubkey| {
@@ -173,11 +421,9 @@ impl<'a> SnapshotMinimizer<'a> {
 
     /// Used to get program data accounts in `minimize`
     /// For each upgradable bpf program, adds the programdata account pubkey to `minimized_account_set`
-    fn get_programdata_accounts(&self) {
-        let programdata_accounts: HashSet<_> = self
-            .minimized_account_set
+    fn get_programdata_accounts(&self, accounts: &[AccountSharedData]) {
+        let programdata_accounts: HashSet<_> = accounts
             .par_iter()
-            .filter_map(|pubkey| self.bank.get_account(&pubkey))
             .filter(|account| account.executable())
             .filter(|account| bpf_loader_upgradeable::check_id(account.owner()))
             .filter_map(|account| {
@@ -196,6 +442,43 @@ impl<'a> SnapshotMinimizer<'a> {
         });
     }
 
+    /// Used to get lookup table addresses in `minimize`
+    /// For each address lookup table, adds all of its addresses to `minimized_account_set`
+    fn get_lookup_table_addresses(&self, accounts: &[AccountSharedData]) {
+        let lookup_table_addresses: HashSet<_> = accounts
+            .par_iter()
+            .filter(|account| *account.owner() == address_lookup_table::program::id())
+            .flat_map_iter(|account| {
+                AddressLookupTable::deserialize(account.data())
+                    .map(|lookup_table| lookup_table.addresses.to_vec())
+                    .unwrap_or_default()
+            })
+            .collect();
+        lookup_table_addresses.into_par_iter().for_each(|pubkey| {
+            self.minimized_account_set.insert(pubkey);
+        });
+    }
+
+    /// Used to get token mint accounts in `minimize`
+    /// For each spl token account, adds the mint account pubkey to `minimized_account_set`
+    fn get_token_mint_accounts(&self, accounts: &[AccountSharedData]) {
+        let mint_accounts: HashSet<_> = accounts
+            .par_iter()
+            .filter_map(|account| {
+                if *account.owner() == token::id() {
+                    token::Account::unpack_account_mint(account.data()).copied()
+                } else if *account.owner() == token_2022::id() {
+                    token_2022::Account::unpack_account_mint(account.data()).copied()
+                } else {
+                    None
+                }
+            })
+            .collect();
+        mint_accounts.into_par_iter().for_each(|pubkey| {
+            self.minimized_account_set.insert(pubkey);
+        });
+    }
+
     /// Remove accounts not in `minimized_accoun_set` from accounts_db
     fn minimize_accounts_db(&self) {
         let (minimized_slot_set, minimized_slot_set_measure) =
@@ -313,21 +596,21 @@ impl<'a> SnapshotMinimizer<'a> {
 
         let mut shrink_in_progress = None;
         if total_bytes > 0 {
-            shrink_in_progress = Some(self.accounts_db().get_store_for_shrink(
-                slot,
-                Arc::clone(storage),
-                total_bytes as u64,
-            ));
-            let new_storage = shrink_in_progress.as_ref().unwrap().new_storage();
+            let shrink_in_progress =
+                shrink_in_progress.insert(self.accounts_db().get_store_for_shrink(
+                    slot,
+                    Arc::clone(storage),
+                    total_bytes as u64,
+                ));
 
             let accounts = [(slot, &keep_accounts[..])];
             let storable_accounts =
                 StorableAccountsBySlot::new(slot, &accounts, self.accounts_db());
 
             self.accounts_db()
-                .store_accounts_for_shrink(storable_accounts, new_storage);
+                .store_accounts_for_shrink(storable_accounts, shrink_in_progress);
 
-            new_storage.flush().unwrap();
+            shrink_in_progress.new_storage().flush().unwrap();
         }
 
         let mut dead_storages_this_time =
@@ -355,14 +638,18 @@ impl<'a> SnapshotMinimizer<'a> {
 mod tests {
     use {
         crate::{
-            bank::Bank, genesis_utils::create_genesis_config_with_leader,
-            runtime_config::RuntimeConfig, snapshot_bank_utils,
-            snapshot_minimizer::SnapshotMinimizer, snapshot_utils,
+            bank::Bank,
+            genesis_utils::create_genesis_config_with_leader,
+            runtime_config::RuntimeConfig,
+            snapshot_bank_utils,
+            snapshot_minimizer::{AccountFilter, AccountSelection, SnapshotMinimizer},
+            snapshot_utils,
         },
         agave_snapshots::snapshot_config::SnapshotConfig,
         dashmap::DashSet,
         solana_account::{AccountSharedData, ReadableAccount, WritableAccount},
         solana_accounts_db::accounts_db::{ACCOUNTS_DB_CONFIG_FOR_TESTING, AccountsDbConfig},
+        solana_address_lookup_table_interface::state::{AddressLookupTable, LookupTableMeta},
         solana_genesis_config::create_genesis_config,
         solana_hash::Hash,
         solana_loader_v3_interface::state::UpgradeableLoaderState,
@@ -371,7 +658,8 @@ mod tests {
         solana_sdk_ids::bpf_loader_upgradeable,
         solana_signer::Signer,
         solana_stake_interface as stake,
-        std::sync::Arc,
+        spl_generic_token::token::{self, GenericTokenAccount},
+        std::{borrow::Cow, sync::Arc},
         tempfile::TempDir,
         test_case::test_case,
     };
@@ -467,7 +755,7 @@ mod tests {
             minimized_account_set: owner_accounts,
         };
 
-        minimizer.get_owner_accounts();
+        minimizer.get_owner_accounts(&minimizer.load_accounts(&[pubkey]));
         assert!(minimizer.minimized_account_set.contains(&pubkey));
         assert!(minimizer.minimized_account_set.contains(&owner_pubkey));
     }
@@ -503,13 +791,13 @@ mod tests {
             starting_slot: 0,
             minimized_account_set: programdata_accounts,
         };
-        minimizer.get_programdata_accounts();
+        minimizer.get_programdata_accounts(&minimizer.load_accounts(&[non_program_id]));
         assert_eq!(minimizer.minimized_account_set.len(), 1);
         assert!(minimizer.minimized_account_set.contains(&non_program_id));
 
         // Programdata account adds the programdata address to the set
         minimizer.minimized_account_set.insert(program_id);
-        minimizer.get_programdata_accounts();
+        minimizer.get_programdata_accounts(&minimizer.load_accounts(&[non_program_id, program_id]));
         assert_eq!(minimizer.minimized_account_set.len(), 3);
         assert!(minimizer.minimized_account_set.contains(&non_program_id));
         assert!(minimizer.minimized_account_set.contains(&program_id));
@@ -520,6 +808,139 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_account_filter() {
+        let owner = Pubkey::new_unique();
+        let mut account = AccountSharedData::new(1_000, 4, &owner);
+        account.set_data_from_slice(&[1, 2, 3, 4]);
+
+        let filter = |expression: &str| expression.parse::<AccountFilter>().unwrap();
+        assert!(filter(&format!("owner == {owner}")).matches(&account));
+        assert!(!filter(&format!("owner != {owner}")).matches(&account));
+        assert!(filter("lamports >= 1000 && lamports<2000").matches(&account));
+        assert!(!filter("lamports > 1000").matches(&account));
+        assert!(filter("data_len == 4 && executable == false").matches(&account));
+        assert!(
+            filter(&format!(
+                "memcmp(1, {})",
+                bs58::encode([2, 3]).into_string()
+            ))
+            .matches(&account)
+        );
+        assert!(
+            !filter(&format!(
+                "memcmp(3, {})",
+                bs58::encode([4, 5]).into_string()
+            ))
+            .matches(&account)
+        );
+        assert!(
+            !filter(&format!("memcmp(5, {})", bs58::encode([1]).into_string())).matches(&account)
+        );
+
+        assert!("lamports".parse::<AccountFilter>().is_err());
+        assert!("lamports >= one".parse::<AccountFilter>().is_err());
+        assert!("rent_epoch == 0".parse::<AccountFilter>().is_err());
+        assert!(format!("owner < {owner}").parse::<AccountFilter>().is_err());
+        assert!("memcmp(0)".parse::<AccountFilter>().is_err());
+    }
+
+    #[test]
+    fn test_minimization_get_selected_accounts() {
+        agave_logger::setup();
+
+        let (genesis_config, _) = create_genesis_config(1_000_000);
+        let bank = Arc::new(Bank::new_for_tests(&genesis_config));
+
+        let owner = Pubkey::new_unique();
+        let owned_pubkey = Pubkey::new_unique();
+        let filtered_pubkey = Pubkey::new_unique();
+        let explicit_pubkey = Pubkey::new_unique();
+        let other_pubkey = Pubkey::new_unique();
+        bank.store_account(&owned_pubkey, &AccountSharedData::new(1, 0, &owner));
+        bank.store_account(
+            &filtered_pubkey,
+            &AccountSharedData::new(1, 123, &Pubkey::new_unique()),
+        );
+        bank.store_account(
+            &other_pubkey,
+            &AccountSharedData::new(1, 0, &Pubkey::new_unique()),
+        );
+
+        let selection = AccountSelection {
+            pubkeys: [explicit_pubkey].into(),
+            owners: [owner].into(),
+            filters: vec!["data_len == 123".parse().unwrap()],
+        };
+        let minimizer = SnapshotMinimizer {
+            bank: &bank,
+            starting_slot: 0,
+            minimized_account_set: DashSet::new(),
+        };
+        minimizer.get_selected_accounts(&selection);
+
+        assert_eq!(minimizer.minimized_account_set.len(), 3);
+        assert!(minimizer.minimized_account_set.contains(&owned_pubkey));
+        assert!(minimizer.minimized_account_set.contains(&filtered_pubkey));
+        assert!(minimizer.minimized_account_set.contains(&explicit_pubkey));
+    }
+
+    #[test]
+    fn test_minimization_get_referenced_accounts() {
+        agave_logger::setup();
+
+        let (genesis_config, _) = create_genesis_config(1_000_000);
+        let bank = Arc::new(Bank::new_for_tests(&genesis_config));
+
+        // lookup table -> token account -> mint, none of which are kept initially
+        let mint_pubkey = Pubkey::new_unique();
+        let token_account_pubkey = Pubkey::new_unique();
+        let lookup_table_pubkey = Pubkey::new_unique();
+
+        const SPL_TOKEN_INITIALIZED_OFFSET: usize = 108;
+        let mut token_account_data = vec![0; token::Account::get_packed_len()];
+        token_account_data[..32].copy_from_slice(mint_pubkey.as_ref());
+        token_account_data[SPL_TOKEN_INITIALIZED_OFFSET] = 1;
+        let mut token_account = AccountSharedData::new(1, token_account_data.len(), &token::id());
+        token_account.set_data_from_slice(&token_account_data);
+        bank.store_account(&token_account_pubkey, &token_account);
+
+        let lookup_table = AddressLookupTable {
+            meta: LookupTableMeta::default(),
+            addresses: Cow::Owned(vec![token_account_pubkey]),
+        };
+        let lookup_table_data = lookup_table.serialize_for_tests().unwrap();
+        let mut lookup_table_account = AccountSharedData::new(
+            1,
+            lookup_table_data.len(),
+            &solana_sdk_ids::address_lookup_table::id(),
+        );
+        lookup_table_account.set_data_from_slice(&lookup_table_data);
+        bank.store_account(&lookup_table_pubkey, &lookup_table_account);
+
+        let minimized_account_set = DashSet::new();
+        minimized_account_set.insert(lookup_table_pubkey);
+        let minimizer = SnapshotMinimizer {
+            bank: &bank,
+            starting_slot: 0,
+            minimized_account_set,
+        };
+        minimizer.get_referenced_accounts();
+
+        for pubkey in [
+            lookup_table_pubkey,
+            solana_sdk_ids::address_lookup_table::id(),
+            token_account_pubkey,
+            token::id(),
+            mint_pubkey,
+        ] {
+            assert!(
+                minimizer.minimized_account_set.contains(&pubkey),
+                "{pubkey}"
+            );
+        }
+    }
+
     #[test]
     fn test_minimize_accounts_db() {
         agave_logger::setup();
@@ -1440,6 +1440,10 @@ fn migrate_legacy_hardlinks(bank_snapshot_dir: &Path, account_run_paths: &[PathB
 /// don't parse as `<slot>.<id>` storage filenames are left alone.
 fn prune_stale_storages(account_paths: &[PathBuf], storages_list: StoragesList) -> Result<()> {
     let expected_storages = storages_list.into_slot_file_id_set();
+    // A storage moved to another account path (e.g. onto the ancient tier) may be left behind
+    // in both paths if the validator stopped before the original was removed. The copies are
+    // identical, so keep the first one found.
+    let mut found_storages = HashSet::with_capacity(expected_storages.len());
     for account_path in account_paths {
         let read_dir = fs::read_dir(account_path).map_err(|err| {
             IoError::other(format!(
@@ -1456,12 +1460,16 @@ fn prune_stale_storages(account_paths: &[PathBuf], storages_list: StoragesList)
                 // Not a storage file name — leave it alone.
                 continue;
             };
-            if !expected_storages.contains(&(slot, id as AccountsFileId)) {
+            let slot_and_id = (slot, id as AccountsFileId);
+            if !expected_storages.contains(&slot_and_id) {
                 info!(
                     "Removing stale storage file '{}' not in storages list",
                     path.display(),
                 );
                 fs::remove_file(&path)?
+            } else if !found_storages.insert(slot_and_id) {
+                info!("Removing duplicate storage file '{}'", path.display());
+                fs::remove_file(&path)?
             }
         }
     }
@@ -2886,4 +2894,35 @@ mod tests {
         assert!(!stale.exists(), "stale storage file was not removed");
         assert!(untouched.exists(), "non-storage file was wrongly removed");
     }
+
+    #[test]
+    fn test_prune_stale_storages_duplicates() {
+        let account_paths = [
+            tempfile::TempDir::new().unwrap(),
+            tempfile::TempDir::new().unwrap(),
+        ];
+        // The same storage in both paths, as left behind by an interrupted move between them
+        let original = account_paths[0]
+            .path()
+            .join(AccountsFile::file_name(100, 1));
+        let duplicate = account_paths[1]
+            .path()
+            .join(AccountsFile::file_name(100, 1));
+        for path in [&original, &duplicate] {
+            fs::write(path, b"x").unwrap();
+        }
+
+        let storages_list = StoragesList::from_items(vec![StorageListItem { slot: 100, id: 1 }]);
+        let account_paths: Vec<_> = account_paths
+            .iter()
+            .map(|path| path.path().to_path_buf())
+            .collect();
+        prune_stale_storages(&account_paths, storages_list).unwrap();
+
+        assert!(original.exists(), "expected storage file was deleted");
+        assert!(
+            !duplicate.exists(),
+            "duplicate storage file was not removed"
+        );
+    }
 }
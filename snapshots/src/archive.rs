@@ -1,8 +1,8 @@
 use {
     crate::{
         ArchiveFormat, Result, SnapshotArchiveKind, error::ArchiveSnapshotPackageError,
-        multiframe::MultiFrameZstdWriter, paths, snapshot_archive_info::SnapshotArchiveInfo,
-        snapshot_hash::SnapshotHash,
+        indexed_archive::IndexedArchiveWriter, multiframe::MultiFrameZstdWriter, paths,
+        snapshot_archive_info::SnapshotArchiveInfo, snapshot_hash::SnapshotHash,
     },
     agave_fs::{
         FileSize, buffered_reader::FileBufRead as _, buffered_writer::large_file_buf_writer,
@@ -21,7 +21,12 @@ use {
     solana_clock::Slot,
     solana_measure::measure::Measure,
     solana_metrics::datapoint_info,
-    std::{fs, io::Write, path::Path, sync::Arc},
+    std::{
+        fs,
+        io::{self, Read, Write},
+        path::Path,
+        sync::Arc,
+    },
 };
 
 // Balance large and small files order in snapshot tar with bias towards small (4 small + 1 large),
@@ -82,16 +87,17 @@ pub fn archive_snapshot(
         E::CanonicalizeSnapshotSourceDir(err, bank_snapshot_dir.as_ref().to_path_buf())
     })?;
     let staging_snapshot_file = staging_snapshot_dir.join(&slot_str);
-    let src_snapshot_file = src_snapshot_dir.join(slot_str);
+    let src_snapshot_file = src_snapshot_dir.join(&slot_str);
     symlink::symlink_file(&src_snapshot_file, &staging_snapshot_file)
-        .map_err(|err| E::SymlinkSnapshot(err, src_snapshot_file, staging_snapshot_file))?;
+        .map_err(|err| E::SymlinkSnapshot(err, src_snapshot_file, staging_snapshot_file.clone()))?;
 
     // Following the existing archive format, the status cache is under snapshots/, not under <slot>/
     // like in the snapshot dir.
     let staging_status_cache = staging_snapshots_dir.join(paths::SNAPSHOT_STATUS_CACHE_FILENAME);
     let src_status_cache = src_snapshot_dir.join(paths::SNAPSHOT_STATUS_CACHE_FILENAME);
-    symlink::symlink_file(&src_status_cache, &staging_status_cache)
-        .map_err(|err| E::SymlinkStatusCache(err, src_status_cache, staging_status_cache))?;
+    symlink::symlink_file(&src_status_cache, &staging_status_cache).map_err(|err| {
+        E::SymlinkStatusCache(err, src_status_cache, staging_status_cache.clone())
+    })?;
 
     // The bank snapshot has the version file, so symlink it to the correct staging path
     let staging_version_file = staging_dir.path().join(paths::SNAPSHOT_VERSION_FILENAME);
@@ -112,26 +118,14 @@ pub fn archive_snapshot(
         let archive_writer = large_file_buf_writer(&staging_archive_path, io_setup)
             .map_err(|err| E::CreateArchiveFile(err, staging_archive_path.clone()))?;
 
-        let do_archive_files = |encoder: &mut dyn Write| -> std::result::Result<(), E> {
-            let mut archive = tar::Builder::new(encoder);
-            // Disable sparse file handling.  This seems to be the root cause of an issue when
-            // upgrading v2.0 to v2.1, and the tar crate from 0.4.41 to 0.4.42.
-            // Since the tarball will still go through compression (zstd/etc) afterwards, disabling
-            // sparse handling in the tar itself should be fine.
-            //
-            // Likely introduced in [^1].  Tracking resolution in [^2].
-            // [^1] https://github.com/alexcrichton/tar-rs/pull/375
-            // [^2] https://github.com/alexcrichton/tar-rs/issues/403
-            archive.sparse(false);
-            // Serialize the version and snapshots files before accounts so we can quickly determine the version
-            // and other bank fields. This is necessary if we want to interleave unpacking with reconstruction
-            archive
-                .append_path_with_name(&staging_version_file, paths::SNAPSHOT_VERSION_FILENAME)
-                .map_err(E::ArchiveVersionFile)?;
-            archive
-                .append_dir_all(paths::BANK_SNAPSHOTS_DIR, &staging_snapshots_dir)
-                .map_err(E::ArchiveSnapshotsDir)?;
-
+        // Appends each storage to the archive via `append_storage`, which is
+        // passed the storage's path in the archive, its length and a reader
+        let archive_storages = |append_storage: &mut dyn FnMut(
+            &str,
+            usize,
+            &mut dyn Read,
+        ) -> io::Result<()>|
+         -> std::result::Result<(), E> {
             let storages_orderer = AccountStoragesOrderer::with_small_to_large_ratio(
                 snapshot_storages,
                 INTERLEAVE_TAR_ENTRIES_SMALL_TO_LARGE_RATIO,
@@ -176,20 +170,28 @@ pub fn archive_snapshot(
                 // it, so the io_uring pipeline can saturate across files.
                 for (storage, file) in &chunk {
                     chunk_reader
-                        .add_file_to_prefetch(file.as_ref(), storage.accounts.len() as FileSize)
+                        .add_file_to_prefetch(
+                            file.as_ref(),
+                            storage.accounts.archive_read_limit() as FileSize,
+                        )
                         .map_err(E::StorageFileBufReaderError)?;
                 }
 
                 for (storage, file) in &chunk {
-                    let path_in_archive = Path::new(ACCOUNTS_DIR)
-                        .join(AccountsFile::file_name(storage.slot(), storage.id()));
+                    let path_in_archive = format!(
+                        "{ACCOUNTS_DIR}/{}",
+                        AccountsFile::file_name(storage.slot(), storage.id()),
+                    );
 
                     chunk_reader
-                        .set_file(file.as_ref(), storage.accounts.len() as FileSize)
+                        .set_file(
+                            file.as_ref(),
+                            storage.accounts.archive_read_limit() as FileSize,
+                        )
                         .map_err(|err| {
                             E::AccountStorageReaderError(err, storage.path().to_path_buf())
                         })?;
-                    let reader = AccountStorageReader::new(
+                    let mut reader = AccountStorageReader::new(
                         storage,
                         Some(snapshot_slot),
                         tombstones_filter,
@@ -198,13 +200,7 @@ pub fn archive_snapshot(
                     .map_err(|err| {
                         E::AccountStorageReaderError(err, storage.path().to_path_buf())
                     })?;
-                    let mut header = tar::Header::new_gnu();
-                    header.set_path(path_in_archive).map_err(|err| {
-                        E::ArchiveAccountStorageFile(err, storage.path().to_path_buf())
-                    })?;
-                    header.set_size(reader.len() as u64);
-                    header.set_cksum();
-                    archive.append(&header, reader).map_err(|err| {
+                    append_storage(&path_in_archive, reader.len(), &mut reader).map_err(|err| {
                         E::ArchiveAccountStorageFile(err, storage.path().to_path_buf())
                     })?;
                 }
@@ -213,6 +209,36 @@ pub fn archive_snapshot(
                     .rebind()
                     .map_err(E::StorageFileBufReaderError)?;
             }
+            Ok(())
+        };
+
+        let do_archive_files = |encoder: &mut dyn Write| -> std::result::Result<(), E> {
+            let mut archive = tar::Builder::new(encoder);
+            // Disable sparse file handling.  This seems to be the root cause of an issue when
+            // upgrading v2.0 to v2.1, and the tar crate from 0.4.41 to 0.4.42.
+            // Since the tarball will still go through compression (zstd/etc) afterwards, disabling
+            // sparse handling in the tar itself should be fine.
+            //
+            // Likely introduced in [^1].  Tracking resolution in [^2].
+            // [^1] https://github.com/alexcrichton/tar-rs/pull/375
+            // [^2] https://github.com/alexcrichton/tar-rs/issues/403
+            archive.sparse(false);
+            // Serialize the version and snapshots files before accounts so we can quickly determine the version
+            // and other bank fields. This is necessary if we want to interleave unpacking with reconstruction
+            archive
+                .append_path_with_name(&staging_version_file, paths::SNAPSHOT_VERSION_FILENAME)
+                .map_err(E::ArchiveVersionFile)?;
+            archive
+                .append_dir_all(paths::BANK_SNAPSHOTS_DIR, &staging_snapshots_dir)
+                .map_err(E::ArchiveSnapshotsDir)?;
+
+            archive_storages(&mut |path_in_archive, len, reader| {
+                let mut header = tar::Header::new_gnu();
+                header.set_path(path_in_archive)?;
+                header.set_size(len as u64);
+                header.set_cksum();
+                archive.append(&header, reader)
+            })?;
 
             archive.into_inner().map_err(E::FinishArchive)?;
             Ok(())
@@ -240,6 +266,47 @@ pub fn archive_snapshot(
                 result.map_err(E::FinishEncoder)?;
                 writer.flush().map_err(E::FinishEncoder)?;
             }
+            ArchiveFormat::Indexed { config } => {
+                let mut archive =
+                    IndexedArchiveWriter::new(archive_writer, config.compression_level)
+                        .map_err(E::CreateEncoder)?;
+                // Same as for tar, the version and snapshots files come first
+                archive
+                    .append_path(
+                        paths::SNAPSHOT_VERSION_FILENAME,
+                        &staging_version_file,
+                        true,
+                    )
+                    .map_err(E::ArchiveVersionFile)?;
+                archive
+                    .append_path(
+                        &format!("{}/{slot_str}/{slot_str}", paths::BANK_SNAPSHOTS_DIR),
+                        &staging_snapshot_file,
+                        true,
+                    )
+                    .map_err(E::ArchiveSnapshotsDir)?;
+                archive
+                    .append_path(
+                        &format!(
+                            "{}/{}",
+                            paths::BANK_SNAPSHOTS_DIR,
+                            paths::SNAPSHOT_STATUS_CACHE_FILENAME,
+                        ),
+                        &staging_status_cache,
+                        true,
+                    )
+                    .map_err(E::ArchiveSnapshotsDir)?;
+                archive_storages(&mut |path_in_archive, len, reader| {
+                    archive.append_file(
+                        path_in_archive,
+                        len as u64,
+                        reader,
+                        config.compress_storages,
+                    )
+                })?;
+                let mut writer = archive.finish().map_err(E::FinishArchive)?;
+                writer.flush().map_err(E::FinishEncoder)?;
+            }
         };
     }
 
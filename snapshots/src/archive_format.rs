@@ -5,17 +5,19 @@ use {
 
 // SUPPORTED_ARCHIVE_COMPRESSION lists the compression types that can be
 // specified on the command line.
-pub const SUPPORTED_ARCHIVE_COMPRESSION: &[&str] = &["zstd", "lz4"];
+pub const SUPPORTED_ARCHIVE_COMPRESSION: &[&str] = &["zstd", "lz4", "indexed"];
 pub const DEFAULT_ARCHIVE_COMPRESSION: &str = "zstd";
 
 pub const TAR_ZSTD_EXTENSION: &str = "tar.zst";
 pub const TAR_LZ4_EXTENSION: &str = "tar.lz4";
+pub const INDEXED_EXTENSION: &str = "idx";
 
 /// The different archive formats used for snapshots
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Display)]
 pub enum ArchiveFormat {
     TarZstd { config: ZstdConfig },
     TarLz4,
+    Indexed { config: IndexedConfig },
 }
 
 impl ArchiveFormat {
@@ -24,6 +26,7 @@ impl ArchiveFormat {
         match self {
             ArchiveFormat::TarZstd { .. } => TAR_ZSTD_EXTENSION,
             ArchiveFormat::TarLz4 => TAR_LZ4_EXTENSION,
+            ArchiveFormat::Indexed { .. } => INDEXED_EXTENSION,
         }
     }
 
@@ -33,6 +36,9 @@ impl ArchiveFormat {
                 config: ZstdConfig::default(),
             }),
             "lz4" => Some(ArchiveFormat::TarLz4),
+            "indexed" => Some(ArchiveFormat::Indexed {
+                config: IndexedConfig::default(),
+            }),
             _ => None,
         }
     }
@@ -49,6 +55,9 @@ impl TryFrom<&str> for ArchiveFormat {
                 config: ZstdConfig::default(),
             }),
             TAR_LZ4_EXTENSION => Ok(ArchiveFormat::TarLz4),
+            INDEXED_EXTENSION => Ok(ArchiveFormat::Indexed {
+                config: IndexedConfig::default(),
+            }),
             _ => Err(ParseError::InvalidExtension(extension.to_string())),
         }
     }
@@ -76,6 +85,14 @@ impl<R: std::io::BufRead> ArchiveFormatDecompressor<R> {
             ArchiveFormat::TarLz4 => {
                 Self::Lz4(lz4::Decoder::new(input).map_err(std::io::Error::other)?)
             }
+            ArchiveFormat::Indexed { .. } => {
+                // Indexed archives are not a single compressed stream; they are
+                // read through `IndexedArchiveReader` instead.
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::Unsupported,
+                    "indexed archives cannot be decompressed as a stream",
+                ));
+            }
         })
     }
 }
@@ -111,6 +128,29 @@ pub struct ZstdConfig {
     pub compression_level: i32,
 }
 
+/// Configuration when using the indexed snapshot archive format
+///
+/// See [`crate::indexed_archive`] for the layout of the archive.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct IndexedConfig {
+    /// The zstd compression level to use for compressed chunks
+    pub compression_level: i32,
+    /// Whether to compress the account storage files
+    ///
+    /// Uncompressed storages are larger, but are copied out of a mapping of
+    /// the archive instead of being decompressed when the archive is unpacked.
+    pub compress_storages: bool,
+}
+
+impl Default for IndexedConfig {
+    fn default() -> Self {
+        Self {
+            compression_level: 0,
+            compress_storages: true,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use {super::*, std::iter::zip};
@@ -126,6 +166,13 @@ mod tests {
             TAR_ZSTD_EXTENSION
         );
         assert_eq!(ArchiveFormat::TarLz4.extension(), TAR_LZ4_EXTENSION);
+        assert_eq!(
+            ArchiveFormat::Indexed {
+                config: IndexedConfig::default(),
+            }
+            .extension(),
+            INDEXED_EXTENSION
+        );
     }
 
     #[test]
@@ -140,6 +187,12 @@ mod tests {
             ArchiveFormat::try_from(TAR_LZ4_EXTENSION),
             Ok(ArchiveFormat::TarLz4)
         );
+        assert_eq!(
+            ArchiveFormat::try_from(INDEXED_EXTENSION),
+            Ok(ArchiveFormat::Indexed {
+                config: IndexedConfig::default(),
+            })
+        );
         assert_eq!(
             ArchiveFormat::try_from(INVALID_EXTENSION),
             Err(ParseError::InvalidExtension(INVALID_EXTENSION.to_string()))
@@ -158,6 +211,12 @@ mod tests {
             ArchiveFormat::from_str(TAR_LZ4_EXTENSION),
             Ok(ArchiveFormat::TarLz4)
         );
+        assert_eq!(
+            ArchiveFormat::from_str(INDEXED_EXTENSION),
+            Ok(ArchiveFormat::Indexed {
+                config: IndexedConfig::default(),
+            })
+        );
         assert_eq!(
             ArchiveFormat::from_str(INVALID_EXTENSION),
             Err(ParseError::InvalidExtension(INVALID_EXTENSION.to_string()))
@@ -171,6 +230,9 @@ mod tests {
                 config: ZstdConfig::default(),
             }),
             Some(ArchiveFormat::TarLz4),
+            Some(ArchiveFormat::Indexed {
+                config: IndexedConfig::default(),
+            }),
         ];
 
         for (arg, expected) in zip(SUPPORTED_ARCHIVE_COMPRESSION.iter(), golden) {
@@ -1,16 +1,25 @@
 use {
-    agave_fs::file_io::{self, FileCreator},
+    crate::indexed_archive::{FileEntry, IndexedArchiveReader},
+    agave_fs::{
+        FileInfo,
+        file_io::{self, FileCreator},
+    },
     log::*,
     rand::{Rng, rng},
     solana_genesis_config::DEFAULT_GENESIS_FILE,
     std::{
         fs::{self, File},
         io::{self, Read},
+        num::NonZeroUsize,
         path::{
             Component::{self, CurDir, Normal},
             Path, PathBuf,
         },
-        sync::Arc,
+        sync::{
+            Arc,
+            atomic::{AtomicBool, AtomicUsize, Ordering},
+        },
+        thread,
     },
     tar::{
         Archive,
@@ -365,6 +374,120 @@ where
     )
 }
 
+/// Unpacks an indexed snapshot `archive`, decompressing files in parallel on
+/// `num_threads` threads, and passes each unpacked file to `file_complete`
+///
+/// Applies the same path, size and entry count protections as the tar based
+/// unpacking. The locations of all files are validated up front, before any
+/// file contents are written.
+#[allow(clippy::arithmetic_side_effects)]
+pub(super) fn unpack_indexed_snapshot<F>(
+    archive: &IndexedArchiveReader,
+    ledger_dir: &Path,
+    account_paths: &[PathBuf],
+    num_threads: NonZeroUsize,
+    file_complete: F,
+) -> Result<()>
+where
+    F: Fn(FileInfo) + Sync,
+{
+    assert!(!account_paths.is_empty());
+
+    let mut total_size: u64 = 0;
+    let mut total_count: u64 = 0;
+    let mut open_dirs = Vec::new();
+    let mut planned = Vec::with_capacity(archive.entries().len());
+    for entry in archive.entries() {
+        let parts: Vec<&str> = entry.path.split('/').collect();
+        if parts
+            .iter()
+            .any(|part| part.is_empty() || *part == "." || *part == "..")
+            || !is_valid_snapshot_archive_entry(&parts, Regular)
+        {
+            return Err(UnpackError::Archive(format!(
+                "extra entry found: {:?}",
+                entry.path,
+            )));
+        }
+
+        // Indexed archives have no holes, so the apparent and actual sizes match
+        total_size = checked_total_size_sum(
+            total_size,
+            entry.size,
+            MAX_SNAPSHOT_ARCHIVE_UNPACKED_APPARENT_SIZE
+                .min(MAX_SNAPSHOT_ARCHIVE_UNPACKED_ACTUAL_SIZE),
+        )?;
+        total_count =
+            checked_total_count_increment(total_count, MAX_SNAPSHOT_ARCHIVE_UNPACKED_COUNT)?;
+
+        let entry_path = match parts.as_slice() {
+            ["accounts", account_filename] => {
+                // Same as for tar archives, strip the accounts/ prefix and
+                // randomly distribute the storages across the account paths
+                let path_index = rng().random_range(0..account_paths.len());
+                sanitize_path_and_open_dir(
+                    Path::new(account_filename),
+                    &account_paths[path_index],
+                    &mut open_dirs,
+                )
+            }
+            _ => sanitize_path_and_open_dir(Path::new(&entry.path), ledger_dir, &mut open_dirs),
+        }?;
+        if let Some((entry_path, _open_dir)) = entry_path {
+            planned.push((entry, entry_path));
+        }
+    }
+
+    let next_entry = AtomicUsize::new(0);
+    // Set once any thread fails, so the others stop picking up new entries
+    let failed = AtomicBool::new(false);
+    let unpack_entry = |entry: &FileEntry, entry_path: &PathBuf| -> Result<()> {
+        let file = File::options()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(entry_path)?;
+        file.set_len(entry.size)?;
+        // Each chunk is verified against the archive manifest before it is
+        // written, so corruption fails the unpack at the offending entry
+        let unpack = archive.unpack_file(entry, &file);
+        check_unpack_result(unpack, entry.path.clone())?;
+        file_io::set_path_permissions(entry_path, 0o644)?;
+        file_complete(FileInfo::new_from_path_and_file(entry_path.clone(), file)?);
+        Ok(())
+    };
+    let unpack_entries = || -> Result<()> {
+        while !failed.load(Ordering::Relaxed) {
+            let index = next_entry.fetch_add(1, Ordering::Relaxed);
+            let Some((entry, entry_path)) = planned.get(index) else {
+                break;
+            };
+            if let Err(err) = unpack_entry(entry, entry_path) {
+                failed.store(true, Ordering::Relaxed);
+                return Err(err);
+            }
+        }
+        Ok(())
+    };
+    thread::scope(|scope| {
+        let handles: Vec<_> = (0..num_threads.get().min(planned.len().max(1)))
+            .map(|i| {
+                thread::Builder::new()
+                    .name(format!("solIdxUnpack{i:02}"))
+                    .spawn_scoped(scope, unpack_entries)
+                    .unwrap()
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("must join unpack thread"))
+            .collect::<Result<()>>()
+    })?;
+
+    info!("unpacked {} entries total", planned.len());
+    Ok(())
+}
+
 fn all_digits(v: &str) -> bool {
     if v.is_empty() {
         return false;
@@ -455,9 +578,10 @@ fn is_valid_genesis_archive_entry<'a>(
 mod tests {
     use {
         super::*,
+        crate::indexed_archive::IndexedArchiveWriter,
         agave_fs::{file_io::file_creator, io_setup::IoSetupState},
         assert_matches::assert_matches,
-        std::io::BufReader,
+        std::{collections::HashMap, io::BufReader, os::unix::fs::FileExt as _, sync::Mutex},
         tar::{Builder, Header},
     };
 
@@ -1013,4 +1137,147 @@ mod tests {
         });
         assert_matches!(result, Ok(()));
     }
+
+    fn indexed_archive_of(files: &[(&str, Vec<u8>)], compress: bool) -> IndexedArchiveReader {
+        let mut writer = IndexedArchiveWriter::new(tempfile::tempfile().unwrap(), 0).unwrap();
+        for (path, data) in files {
+            writer
+                .append_file(path, data.len() as u64, data.as_slice(), compress)
+                .unwrap();
+        }
+        IndexedArchiveReader::new(writer.finish().unwrap()).unwrap()
+    }
+
+    fn unpack_indexed_to_map(
+        archive: &IndexedArchiveReader,
+        ledger_dir: &Path,
+        account_paths: &[PathBuf],
+    ) -> Result<HashMap<PathBuf, Vec<u8>>> {
+        let unpacked = Mutex::new(HashMap::new());
+        unpack_indexed_snapshot(
+            archive,
+            ledger_dir,
+            account_paths,
+            NonZeroUsize::new(4).unwrap(),
+            |file_info| {
+                let contents = fs::read(&file_info.path).unwrap();
+                assert_eq!(contents.len() as u64, file_info.size);
+                unpacked.lock().unwrap().insert(file_info.path, contents);
+            },
+        )?;
+        Ok(unpacked.into_inner().unwrap())
+    }
+
+    #[test]
+    fn test_unpack_indexed_snapshot_matches_tar() {
+        let storage: Vec<u8> = (0..3 * 1024 * 1024 + 17).map(|i| i as u8).collect();
+        let files = [
+            ("version", b"1.2.0".to_vec()),
+            ("snapshots/42/42", vec![7; 1000]),
+            ("snapshots/status_cache", vec![]),
+            ("accounts/42.0", storage),
+            ("accounts/41.3", rand::random::<[u8; 64]>().to_vec()),
+        ];
+
+        let mut tar_archive = Builder::new(Vec::new());
+        for (path, data) in &files {
+            let mut header = Header::new_gnu();
+            header.set_path(path).unwrap();
+            header.set_size(data.len() as u64);
+            header.set_cksum();
+            tar_archive.append(&header, data.as_slice()).unwrap();
+        }
+        let tar_dir = tempfile::TempDir::new().unwrap();
+        let tar_accounts_dir = tar_dir.path().join("accounts_dest");
+        let tar_unpacked = Mutex::new(HashMap::new());
+        let file_creator = file_creator(256, &IoSetupState::default(), |file_info| {
+            let path = file_info
+                .path
+                .strip_prefix(tar_dir.path())
+                .unwrap()
+                .to_path_buf();
+            tar_unpacked.lock().unwrap().insert(path, file_info.size);
+            Some(file_info.file)
+        })
+        .unwrap();
+        unpack_snapshot_with_processors(
+            tar_archive.into_inner().unwrap().as_slice(),
+            file_creator,
+            tar_dir.path(),
+            &[tar_accounts_dir],
+            |_, _| {},
+        )
+        .unwrap();
+        let tar_unpacked = tar_unpacked.into_inner().unwrap();
+
+        for compress in [false, true] {
+            let archive = indexed_archive_of(&files, compress);
+            let indexed_dir = tempfile::TempDir::new().unwrap();
+            let indexed_unpacked = unpack_indexed_to_map(
+                &archive,
+                indexed_dir.path(),
+                &[indexed_dir.path().join("accounts_dest")],
+            )
+            .unwrap();
+
+            assert_eq!(indexed_unpacked.len(), tar_unpacked.len());
+            for (path, contents) in indexed_unpacked {
+                let relative_path = path.strip_prefix(indexed_dir.path()).unwrap();
+                assert_eq!(
+                    tar_unpacked.get(relative_path),
+                    Some(&(contents.len() as u64)),
+                );
+                assert_eq!(
+                    contents,
+                    fs::read(tar_dir.path().join(relative_path)).unwrap()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_unpack_indexed_snapshot_invalid_entry() {
+        for path in [
+            "../version",
+            "snapshots/../version",
+            "/version",
+            "snapshots//status_cache",
+            "accounts/not_a_storage",
+            "foo",
+        ] {
+            let archive = indexed_archive_of(&[(path, vec![1, 2, 3, 4])], false);
+            let temp_dir = tempfile::TempDir::new().unwrap();
+            let result = unpack_indexed_to_map(
+                &archive,
+                temp_dir.path(),
+                &[temp_dir.path().join("accounts_dest")],
+            );
+            assert_matches!(result, Err(UnpackError::Archive(_)), "{path}");
+            // Nothing may be written when any entry is invalid
+            assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 0, "{path}");
+        }
+    }
+
+    #[test]
+    fn test_unpack_indexed_snapshot_corrupt_entry() {
+        let mut writer = IndexedArchiveWriter::new(tempfile::tempfile().unwrap(), 0).unwrap();
+        writer
+            .append_file("version", 5, b"1.2.0".as_slice(), true)
+            .unwrap();
+        writer
+            .append_file("accounts/42.0", 64, [9u8; 64].as_slice(), false)
+            .unwrap();
+        let file = writer.finish().unwrap();
+        let archive = IndexedArchiveReader::new(file.try_clone().unwrap()).unwrap();
+        let chunk = &archive.entry("accounts/42.0").unwrap().chunks[0];
+        file.write_all_at(&[0], chunk.offset).unwrap();
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let result = unpack_indexed_to_map(
+            &archive,
+            temp_dir.path(),
+            &[temp_dir.path().join("accounts_dest")],
+        );
+        assert_matches!(result, Err(UnpackError::Archive(msg)) if msg.contains("accounts/42.0"));
+    }
 }
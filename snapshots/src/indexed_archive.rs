@@ -0,0 +1,873 @@
+//! The indexed snapshot archive format
+//!
+//! Unlike the tar based formats, an indexed archive can be read without
+//! streaming through the whole archive. Every file is split into chunks that
+//! are compressed independently, and an index at the end of the archive
+//! records where each chunk lives. This allows the chunks to be decompressed
+//! in parallel and in any order, and allows files whose chunks are all stored
+//! uncompressed to be mapped directly from the archive.
+//!
+//! The index doubles as the manifest of the archive. It holds a BLAKE3 hash
+//! of the stored bytes of every chunk and of the contents of every file, so
+//! corruption is detected at the exact chunk, before it is decompressed. The
+//! per-chunk hashes also allow a partially downloaded archive to be verified
+//! and resumed, see [`verified_prefix_len`].
+//!
+//! Layout (all integers are little endian):
+//!
+//! ```text
+//! +--------------------------------------------+
+//! | magic: [u8; 8] | version: u32 | flags: u32 |  header
+//! +--------------------------------------------+
+//! | chunk data, the first chunk of every file  |
+//! | starts at a DATA_ALIGNMENT aligned offset  |
+//! +--------------------------------------------+
+//! | index                                      |
+//! +--------------------------------------------+
+//! | index_offset: u64 | index_len: u64 | magic |  footer
+//! +--------------------------------------------+
+//! ```
+//!
+//! The index is a `u32` number of files followed by, for each file, its path
+//! (`u16` length and UTF-8 bytes), its size (`u64`), the hash of its contents
+//! (`[u8; 32]`), and its chunks (`u32` count followed by `offset: u64`,
+//! `stored_len: u32`, `raw_len: u32`, `compression: u8` and the hash of the
+//! stored bytes `[u8; 32]` for each chunk).
+
+use {
+    crate::hardened_unpack::{Result, UnpackError},
+    std::{
+        fs::File,
+        io::{self, Read, Write},
+        ops::Range,
+        os::unix::fs::FileExt,
+        path::Path,
+    },
+};
+
+pub(crate) const INDEXED_ARCHIVE_MAGIC: [u8; 8] = *b"AGVSIDX\0";
+const INDEXED_ARCHIVE_VERSION: u32 = 1;
+const HEADER_LEN: u64 = 16;
+pub const FOOTER_LEN: u64 = 24;
+
+/// Uncompressed bytes per chunk. Large enough to keep compression loss small,
+/// while still giving the parallel unpacker plenty of independent work.
+pub(crate) const CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
+/// Alignment of the first chunk of each file, which allows files stored
+/// without compression to be mapped directly from the archive.
+pub(crate) const DATA_ALIGNMENT: u64 = 4096;
+
+// The index of a snapshot with the maximum number of files, each with short
+// paths and a few chunks, fits well within this bound. Protect against
+// allocating insane amounts of memory for a corrupt or malicious footer.
+const MAX_INDEX_LEN: u64 = 2 * 1024 * 1024 * 1024;
+
+// Encoded lengths of the smallest possible file entry (an empty path and no
+// chunks) and of a chunk entry, used to bound the counts read from the index
+const MIN_FILE_ENTRY_LEN: usize = 2 + 8 + 32 + 4;
+const CHUNK_ENTRY_LEN: usize = 8 + 4 + 4 + 1 + 32;
+
+// Chunks are only stored compressed if that saves at least 1/16th of the
+// chunk, otherwise the decompression cost isn't worth it.
+const MIN_COMPRESSION_SAVINGS_RATIO: usize = 16;
+
+/// How the bytes of a chunk are stored in the archive
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ChunkCompression {
+    None = 0,
+    Zstd = 1,
+}
+
+impl TryFrom<u8> for ChunkCompression {
+    type Error = UnpackError;
+
+    fn try_from(value: u8) -> Result<Self> {
+        match value {
+            0 => Ok(Self::None),
+            1 => Ok(Self::Zstd),
+            _ => Err(UnpackError::Archive(format!(
+                "invalid chunk compression: {value}"
+            ))),
+        }
+    }
+}
+
+/// Location of one chunk of a file within the archive
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ChunkEntry {
+    /// Offset of the stored bytes from the start of the archive
+    pub offset: u64,
+    /// Number of bytes stored in the archive
+    pub stored_len: u32,
+    /// Number of bytes after decompression
+    pub raw_len: u32,
+    pub compression: ChunkCompression,
+    /// BLAKE3 hash of the stored bytes
+    pub hash: [u8; 32],
+}
+
+/// A file stored in the archive
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FileEntry {
+    /// Path of the file relative to the root of the archive, using `/` as separator
+    pub path: String,
+    pub size: u64,
+    /// BLAKE3 hash of the file contents
+    pub hash: [u8; 32],
+    pub chunks: Vec<ChunkEntry>,
+}
+
+impl FileEntry {
+    /// Returns the range of the archive that holds the contents of this file,
+    /// if the file is stored contiguously without compression.
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn raw_range(&self) -> Option<Range<u64>> {
+        if self
+            .chunks
+            .iter()
+            .any(|chunk| chunk.compression != ChunkCompression::None)
+        {
+            return None;
+        }
+        let start = self.chunks.first().map(|chunk| chunk.offset)?;
+        let mut end = start;
+        for chunk in &self.chunks {
+            if chunk.offset != end {
+                return None;
+            }
+            end += u64::from(chunk.stored_len);
+        }
+        Some(start..end)
+    }
+}
+
+/// Writes an indexed archive
+pub struct IndexedArchiveWriter<W: Write> {
+    writer: W,
+    position: u64,
+    entries: Vec<FileEntry>,
+    compressor: zstd::bulk::Compressor<'static>,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> IndexedArchiveWriter<W> {
+    pub fn new(mut writer: W, compression_level: i32) -> io::Result<Self> {
+        writer.write_all(&INDEXED_ARCHIVE_MAGIC)?;
+        writer.write_all(&INDEXED_ARCHIVE_VERSION.to_le_bytes())?;
+        writer.write_all(&0u32.to_le_bytes())?;
+        Ok(Self {
+            writer,
+            position: HEADER_LEN,
+            entries: Vec::new(),
+            compressor: zstd::bulk::Compressor::new(compression_level)?,
+            buffer: Vec::with_capacity(CHUNK_SIZE),
+        })
+    }
+
+    /// Appends `size` bytes read from `reader` as the file at `path`
+    ///
+    /// If `compress` is false, the file is stored uncompressed and can later
+    /// be mapped directly from the archive.
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn append_file(
+        &mut self,
+        path: &str,
+        size: u64,
+        mut reader: impl Read,
+        compress: bool,
+    ) -> io::Result<()> {
+        if path.len() > usize::from(u16::MAX) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("path too long: {path}"),
+            ));
+        }
+        self.pad_to_alignment()?;
+
+        let mut chunks = Vec::with_capacity(size.div_ceil(CHUNK_SIZE as u64) as usize);
+        let mut file_hasher = blake3::Hasher::new();
+        let mut remaining = size;
+        while remaining > 0 {
+            let raw_len = remaining.min(CHUNK_SIZE as u64) as usize;
+            self.buffer.resize(raw_len, 0);
+            reader.read_exact(&mut self.buffer)?;
+            remaining -= raw_len as u64;
+            file_hasher.update(&self.buffer);
+
+            let compressed = if compress {
+                let compressed = self.compressor.compress(&self.buffer)?;
+                (compressed.len() < raw_len - raw_len / MIN_COMPRESSION_SAVINGS_RATIO)
+                    .then_some(compressed)
+            } else {
+                None
+            };
+            let (stored, compression) = match &compressed {
+                Some(compressed) => (compressed.as_slice(), ChunkCompression::Zstd),
+                None => (self.buffer.as_slice(), ChunkCompression::None),
+            };
+            self.writer.write_all(stored)?;
+            chunks.push(ChunkEntry {
+                offset: self.position,
+                stored_len: stored.len() as u32,
+                raw_len: raw_len as u32,
+                compression,
+                hash: blake3::hash(stored).into(),
+            });
+            self.position += stored.len() as u64;
+        }
+
+        self.entries.push(FileEntry {
+            path: path.to_string(),
+            size,
+            hash: file_hasher.finalize().into(),
+            chunks,
+        });
+        Ok(())
+    }
+
+    /// Appends the file at `src` as the file at `path` in the archive
+    pub fn append_path(
+        &mut self,
+        path: &str,
+        src: impl AsRef<Path>,
+        compress: bool,
+    ) -> io::Result<()> {
+        let file = File::open(src)?;
+        let size = file.metadata()?.len();
+        self.append_file(path, size, file, compress)
+    }
+
+    /// Writes the index and footer and returns the inner writer
+    pub fn finish(mut self) -> io::Result<W> {
+        let index = encode_index(&self.entries);
+        let index_offset = self.position;
+        self.writer.write_all(&index)?;
+        self.writer.write_all(&index_offset.to_le_bytes())?;
+        self.writer.write_all(&(index.len() as u64).to_le_bytes())?;
+        self.writer.write_all(&INDEXED_ARCHIVE_MAGIC)?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    #[allow(clippy::arithmetic_side_effects)]
+    fn pad_to_alignment(&mut self) -> io::Result<()> {
+        let padding = self.position.next_multiple_of(DATA_ALIGNMENT) - self.position;
+        if padding > 0 {
+            self.writer.write_all(&vec![0; padding as usize])?;
+            self.position += padding;
+        }
+        Ok(())
+    }
+}
+
+/// Random access reader over an indexed archive
+///
+/// The index is fully validated when the archive is opened, so the offsets
+/// and lengths of all entries can be trusted to lie within the archive.
+#[derive(Debug)]
+pub struct IndexedArchiveReader {
+    file: File,
+    entries: Vec<FileEntry>,
+}
+
+impl IndexedArchiveReader {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::new(File::open(path)?)
+    }
+
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn new(file: File) -> Result<Self> {
+        let archive_len = file.metadata()?.len();
+        if archive_len < HEADER_LEN + FOOTER_LEN {
+            return Err(UnpackError::Archive(format!(
+                "archive too small: {archive_len} bytes"
+            )));
+        }
+
+        let mut header = [0u8; HEADER_LEN as usize];
+        file.read_exact_at(&mut header, 0)?;
+        check_header(&header)?;
+
+        let mut footer = [0u8; FOOTER_LEN as usize];
+        file.read_exact_at(&mut footer, archive_len - FOOTER_LEN)?;
+        let index_range = index_range(&footer, archive_len)?;
+
+        let mut index = vec![0u8; (index_range.end - index_range.start) as usize];
+        file.read_exact_at(&mut index, index_range.start)?;
+        let entries = decode_index(&index, index_range.start)?;
+        Ok(Self { file, entries })
+    }
+
+    pub fn entries(&self) -> &[FileEntry] {
+        &self.entries
+    }
+
+    pub fn entry(&self, path: &str) -> Option<&FileEntry> {
+        self.entries.iter().find(|entry| entry.path == path)
+    }
+
+    /// Reads, verifies and decompresses a single chunk
+    ///
+    /// The stored bytes are checked against the chunk's hash before they are
+    /// decompressed.
+    pub fn read_chunk(&self, chunk: &ChunkEntry) -> Result<Vec<u8>> {
+        let mut stored = vec![0u8; chunk.stored_len as usize];
+        self.file.read_exact_at(&mut stored, chunk.offset)?;
+        if blake3::hash(&stored) != chunk.hash {
+            return Err(UnpackError::Archive(format!(
+                "chunk at offset {} is corrupt",
+                chunk.offset,
+            )));
+        }
+        match chunk.compression {
+            ChunkCompression::None => Ok(stored),
+            ChunkCompression::Zstd => {
+                // Bound the decompressed size by the expected length, so a
+                // corrupt chunk cannot be used as a decompression bomb.
+                let raw = zstd::bulk::decompress(&stored, chunk.raw_len as usize)?;
+                if raw.len() != chunk.raw_len as usize {
+                    return Err(UnpackError::Archive(format!(
+                        "chunk at offset {} decompressed to {} bytes, expected {}",
+                        chunk.offset,
+                        raw.len(),
+                        chunk.raw_len,
+                    )));
+                }
+                Ok(raw)
+            }
+        }
+    }
+
+    /// Reads the full contents of a file, verifying them against the manifest
+    pub fn read_file(&self, entry: &FileEntry) -> Result<Vec<u8>> {
+        // Size the buffer from the chunks that will actually be read rather
+        // than from the file size recorded in the index
+        let raw_len = entry
+            .chunks
+            .iter()
+            .map(|chunk| u64::from(chunk.raw_len))
+            .sum::<u64>();
+        if raw_len != entry.size {
+            return Err(UnpackError::Archive(format!(
+                "{}: chunks hold {raw_len} bytes, expected {}",
+                entry.path, entry.size,
+            )));
+        }
+        let mut contents = Vec::with_capacity(raw_len as usize);
+        for chunk in &entry.chunks {
+            contents.extend_from_slice(&self.read_chunk(chunk)?);
+        }
+        check_file_hash(entry, blake3::hash(&contents))?;
+        Ok(contents)
+    }
+
+    /// Writes the contents of a file into `dst`, starting at offset 0
+    ///
+    /// The contents are verified against the manifest while they are written.
+    /// Files stored uncompressed are copied straight out of a mapping of the
+    /// archive instead of being read chunk by chunk.
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn unpack_file(&self, entry: &FileEntry, dst: &File) -> Result<()> {
+        if let Some(mmap) = self.map_file(entry)? {
+            dst.write_all_at(&mmap, 0)?;
+            return check_file_hash(entry, blake3::hash(&mmap));
+        }
+
+        let mut hasher = blake3::Hasher::new();
+        let mut position = 0;
+        for chunk in &entry.chunks {
+            let raw = self.read_chunk(chunk)?;
+            hasher.update(&raw);
+            dst.write_all_at(&raw, position)?;
+            position += raw.len() as u64;
+        }
+        check_file_hash(entry, hasher.finalize())
+    }
+
+    /// Verifies the stored bytes of every chunk in the archive
+    ///
+    /// Returns an error naming the first file with a corrupt chunk.
+    pub fn verify(&self) -> Result<()> {
+        for entry in &self.entries {
+            for chunk in &entry.chunks {
+                let mut stored = vec![0u8; chunk.stored_len as usize];
+                self.file.read_exact_at(&mut stored, chunk.offset)?;
+                if blake3::hash(&stored) != chunk.hash {
+                    return Err(UnpackError::Archive(format!(
+                        "{}: chunk at offset {} is corrupt",
+                        entry.path, chunk.offset,
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Maps the contents of a file directly from the archive
+    ///
+    /// Returns `None` if the file is stored compressed. The mapped contents
+    /// are *not* verified; call [`Self::verify`] first if required.
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn map_file(&self, entry: &FileEntry) -> Result<Option<memmap2::Mmap>> {
+        let Some(range) = entry.raw_range() else {
+            return Ok(None);
+        };
+        if range.is_empty() {
+            return Ok(None);
+        }
+        // SAFETY: The archive is not modified while mapped; the range was
+        // validated against the archive length when the index was decoded.
+        let mmap = unsafe {
+            memmap2::MmapOptions::new()
+                .offset(range.start)
+                .len((range.end - range.start) as usize)
+                .map(&self.file)?
+        };
+        Ok(Some(mmap))
+    }
+}
+
+/// Returns the location of the index, given the footer of an archive that is
+/// `archive_len` bytes long
+#[allow(clippy::arithmetic_side_effects)]
+pub fn index_range(footer: &[u8; FOOTER_LEN as usize], archive_len: u64) -> Result<Range<u64>> {
+    if footer[16..] != INDEXED_ARCHIVE_MAGIC {
+        return Err(UnpackError::Archive(
+            "invalid archive footer, archive may be truncated".to_string(),
+        ));
+    }
+    let index_offset = u64::from_le_bytes(footer[..8].try_into().unwrap());
+    let index_len = u64::from_le_bytes(footer[8..16].try_into().unwrap());
+    if archive_len < HEADER_LEN + FOOTER_LEN
+        || index_len > MAX_INDEX_LEN
+        || index_offset < HEADER_LEN
+        || index_offset.checked_add(index_len) != Some(archive_len - FOOTER_LEN)
+    {
+        return Err(UnpackError::Archive(format!(
+            "invalid archive index location: offset {index_offset}, length {index_len}"
+        )));
+    }
+    Ok(index_offset..index_offset + index_len)
+}
+
+/// Returns the length of the longest prefix of a partially written or
+/// downloaded archive that matches `entries`
+///
+/// Chunks are checked in the order they are stored, and the prefix ends
+/// before the first chunk that is missing, truncated or corrupt. Downloads can
+/// be resumed from the returned offset.
+#[allow(clippy::arithmetic_side_effects)]
+pub fn verified_prefix_len(partial: &File, entries: &[FileEntry]) -> io::Result<u64> {
+    let partial_len = partial.metadata()?.len();
+    if partial_len < HEADER_LEN {
+        return Ok(0);
+    }
+    let mut header = [0u8; HEADER_LEN as usize];
+    partial.read_exact_at(&mut header, 0)?;
+    if check_header(&header).is_err() {
+        return Ok(0);
+    }
+
+    let mut chunks: Vec<_> = entries.iter().flat_map(|entry| &entry.chunks).collect();
+    chunks.sort_unstable_by_key(|chunk| chunk.offset);
+    let mut verified_len = HEADER_LEN;
+    let mut stored = Vec::with_capacity(CHUNK_SIZE);
+    for chunk in chunks {
+        let chunk_end = chunk.offset + u64::from(chunk.stored_len);
+        if chunk_end > partial_len {
+            break;
+        }
+        stored.resize(chunk.stored_len as usize, 0);
+        partial.read_exact_at(&mut stored, chunk.offset)?;
+        if blake3::hash(&stored) != chunk.hash {
+            break;
+        }
+        verified_len = chunk_end;
+    }
+    Ok(verified_len)
+}
+
+fn check_header(header: &[u8; HEADER_LEN as usize]) -> Result<()> {
+    if header[..8] != INDEXED_ARCHIVE_MAGIC {
+        return Err(UnpackError::Archive("invalid archive header".to_string()));
+    }
+    let version = u32::from_le_bytes(header[8..12].try_into().unwrap());
+    if version != INDEXED_ARCHIVE_VERSION {
+        return Err(UnpackError::Archive(format!(
+            "unsupported archive version: {version}"
+        )));
+    }
+    Ok(())
+}
+
+fn check_file_hash(entry: &FileEntry, hash: blake3::Hash) -> Result<()> {
+    if hash != entry.hash {
+        return Err(UnpackError::Archive(format!(
+            "{}: contents do not match the archive manifest",
+            entry.path,
+        )));
+    }
+    Ok(())
+}
+
+fn encode_index(entries: &[FileEntry]) -> Vec<u8> {
+    let mut index = Vec::new();
+    index.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+    for entry in entries {
+        index.extend_from_slice(&(entry.path.len() as u16).to_le_bytes());
+        index.extend_from_slice(entry.path.as_bytes());
+        index.extend_from_slice(&entry.size.to_le_bytes());
+        index.extend_from_slice(&entry.hash);
+        index.extend_from_slice(&(entry.chunks.len() as u32).to_le_bytes());
+        for chunk in &entry.chunks {
+            index.extend_from_slice(&chunk.offset.to_le_bytes());
+            index.extend_from_slice(&chunk.stored_len.to_le_bytes());
+            index.extend_from_slice(&chunk.raw_len.to_le_bytes());
+            index.push(chunk.compression as u8);
+            index.extend_from_slice(&chunk.hash);
+        }
+    }
+    index
+}
+
+/// Decodes the index that starts at `data_end`
+///
+/// Checks that every chunk lies within the data region `[HEADER_LEN, data_end)`
+/// and that the chunks of each file add up to its size.
+#[allow(clippy::arithmetic_side_effects)]
+pub fn decode_index(index: &[u8], data_end: u64) -> Result<Vec<FileEntry>> {
+    let mut reader = IndexReader { index };
+    let num_entries = reader.read_u32()? as usize;
+    // Don't trust counts that could not possibly fit in the index
+    let mut entries = Vec::with_capacity(num_entries.min(index.len() / MIN_FILE_ENTRY_LEN));
+    for _ in 0..num_entries {
+        let path_len = usize::from(reader.read_u16()?);
+        let path = std::str::from_utf8(reader.read_bytes(path_len)?)
+            .map_err(|err| UnpackError::Archive(format!("invalid path in index: {err}")))?
+            .to_string();
+        let size = reader.read_u64()?;
+        let hash = reader.read_hash()?;
+        let num_chunks = reader.read_u32()? as usize;
+        if size.div_ceil(CHUNK_SIZE as u64) != num_chunks as u64 {
+            return Err(UnpackError::Archive(format!(
+                "{path}: {num_chunks} chunks for {size} bytes"
+            )));
+        }
+
+        let mut chunks = Vec::with_capacity(num_chunks.min(reader.index.len() / CHUNK_ENTRY_LEN));
+        let mut total_raw_len = 0u64;
+        for _ in 0..num_chunks {
+            let chunk = ChunkEntry {
+                offset: reader.read_u64()?,
+                stored_len: reader.read_u32()?,
+                raw_len: reader.read_u32()?,
+                compression: ChunkCompression::try_from(reader.read_u8()?)?,
+                hash: reader.read_hash()?,
+            };
+            let in_bounds = chunk.offset >= HEADER_LEN
+                && chunk
+                    .offset
+                    .checked_add(u64::from(chunk.stored_len))
+                    .is_some_and(|end| end <= data_end);
+            let valid_len = chunk.raw_len as usize <= CHUNK_SIZE
+                && (chunk.compression != ChunkCompression::None
+                    || chunk.stored_len == chunk.raw_len);
+            if !in_bounds || !valid_len {
+                return Err(UnpackError::Archive(format!(
+                    "{path}: invalid chunk {chunk:?}"
+                )));
+            }
+            total_raw_len += u64::from(chunk.raw_len);
+            chunks.push(chunk);
+        }
+        if total_raw_len != size {
+            return Err(UnpackError::Archive(format!(
+                "{path}: chunks hold {total_raw_len} bytes, expected {size}"
+            )));
+        }
+        entries.push(FileEntry {
+            path,
+            size,
+            hash,
+            chunks,
+        });
+    }
+    if !reader.index.is_empty() {
+        return Err(UnpackError::Archive(format!(
+            "{} trailing bytes in index",
+            reader.index.len()
+        )));
+    }
+    Ok(entries)
+}
+
+struct IndexReader<'a> {
+    index: &'a [u8],
+}
+
+impl<'a> IndexReader<'a> {
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.index.len() < len {
+            return Err(UnpackError::Archive("truncated index".to_string()));
+        }
+        let (bytes, rest) = self.index.split_at(len);
+        self.index = rest;
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> Result<u8> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.read_bytes(2)?.try_into().unwrap()))
+    }
+
+    fn read_u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.read_bytes(4)?.try_into().unwrap()))
+    }
+
+    fn read_u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.read_bytes(8)?.try_into().unwrap()))
+    }
+
+    fn read_hash(&mut self) -> Result<[u8; 32]> {
+        Ok(self.read_bytes(32)?.try_into().unwrap())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::arithmetic_side_effects)]
+mod tests {
+    use {super::*, assert_matches::assert_matches, std::io::Seek, tempfile::tempfile};
+
+    fn write_archive(files: &[(&str, Vec<u8>, bool)]) -> File {
+        let mut writer = IndexedArchiveWriter::new(tempfile().unwrap(), 0).unwrap();
+        for (path, contents, compress) in files {
+            writer
+                .append_file(path, contents.len() as u64, contents.as_slice(), *compress)
+                .unwrap();
+        }
+        let mut file = writer.finish().unwrap();
+        file.rewind().unwrap();
+        file
+    }
+
+    #[test]
+    fn test_indexed_archive_round_trip() {
+        let compressible = vec![7u8; CHUNK_SIZE * 2 + 123];
+        let incompressible: Vec<u8> = (0..CHUNK_SIZE + 5).map(|_| rand::random()).collect();
+        let files = [
+            ("version", b"1.2.0".to_vec(), true),
+            ("snapshots/42/42", compressible.clone(), true),
+            ("accounts/42.7", incompressible.clone(), true),
+            ("accounts/42.8", compressible.clone(), false),
+            ("snapshots/status_cache", Vec::new(), true),
+        ];
+        let archive = IndexedArchiveReader::new(write_archive(&files)).unwrap();
+
+        assert_eq!(archive.entries().len(), files.len());
+        for (path, contents, _) in &files {
+            let entry = archive.entry(path).unwrap();
+            assert_eq!(entry.size, contents.len() as u64);
+            assert_eq!(&archive.read_file(entry).unwrap(), contents);
+        }
+
+        let entry = archive.entry("snapshots/42/42").unwrap();
+        assert!(
+            entry
+                .chunks
+                .iter()
+                .all(|chunk| chunk.compression == ChunkCompression::Zstd)
+        );
+        assert_eq!(entry.raw_range(), None);
+        assert_matches!(archive.map_file(entry), Ok(None));
+
+        // Random data does not compress, so it must have been stored raw
+        let entry = archive.entry("accounts/42.7").unwrap();
+        assert!(entry.raw_range().is_some());
+
+        let entry = archive.entry("accounts/42.8").unwrap();
+        let range = entry.raw_range().unwrap();
+        assert_eq!(range.start % DATA_ALIGNMENT, 0);
+        let mmap = archive.map_file(entry).unwrap().unwrap();
+        assert_eq!(&mmap[..], compressible.as_slice());
+
+        // Unpacking goes through the mapping for raw files and through the
+        // chunks for compressed ones, both must produce the same contents
+        for path in ["snapshots/42/42", "accounts/42.8"] {
+            let dst = tempfile().unwrap();
+            archive
+                .unpack_file(archive.entry(path).unwrap(), &dst)
+                .unwrap();
+            let mut unpacked = Vec::new();
+            (&dst).read_to_end(&mut unpacked).unwrap();
+            assert_eq!(unpacked, compressible);
+        }
+    }
+
+    #[test]
+    fn test_indexed_archive_read_file_size() {
+        let files = [("version", b"1.2.0".to_vec(), true)];
+        let archive = IndexedArchiveReader::new(write_archive(&files)).unwrap();
+
+        // A size that does not match the chunks is rejected before anything
+        // is allocated for it
+        let mut entry = archive.entry("version").unwrap().clone();
+        entry.size = u64::MAX;
+        assert_matches!(archive.read_file(&entry), Err(UnpackError::Archive(_)));
+    }
+
+    #[test]
+    fn test_indexed_archive_truncated() {
+        let files = [("version", b"1.2.0".to_vec(), true)];
+        let file = write_archive(&files);
+        let len = file.metadata().unwrap().len();
+        file.set_len(len - 1).unwrap();
+        assert_matches!(
+            IndexedArchiveReader::new(file),
+            Err(UnpackError::Archive(_))
+        );
+    }
+
+    #[test]
+    fn test_indexed_archive_invalid_chunk() {
+        let entries = vec![FileEntry {
+            path: "version".to_string(),
+            size: 4,
+            hash: [1; 32],
+            chunks: vec![ChunkEntry {
+                offset: HEADER_LEN,
+                stored_len: 100,
+                raw_len: 4,
+                compression: ChunkCompression::Zstd,
+                hash: [2; 32],
+            }],
+        }];
+        let index = encode_index(&entries);
+        // Chunk extends past the end of the data region
+        assert_matches!(
+            decode_index(&index, HEADER_LEN + 50),
+            Err(UnpackError::Archive(_))
+        );
+        assert_eq!(decode_index(&index, HEADER_LEN + 100).unwrap(), entries);
+
+        // Raw chunks must store exactly their raw length
+        let mut entries = entries;
+        entries[0].chunks[0].compression = ChunkCompression::None;
+        let index = encode_index(&entries);
+        assert_matches!(
+            decode_index(&index, HEADER_LEN + 100),
+            Err(UnpackError::Archive(_))
+        );
+
+        // Chunks must add up to the file size
+        entries[0].size = 5;
+        entries[0].chunks[0].stored_len = 4;
+        let index = encode_index(&entries);
+        assert_matches!(
+            decode_index(&index, HEADER_LEN + 100),
+            Err(UnpackError::Archive(_))
+        );
+    }
+
+    #[test]
+    fn test_indexed_archive_truncated_index() {
+        let entries = vec![FileEntry {
+            path: "version".to_string(),
+            size: 0,
+            hash: [0; 32],
+            chunks: vec![],
+        }];
+        let index = encode_index(&entries);
+        assert_matches!(
+            decode_index(&index[..index.len() - 1], HEADER_LEN),
+            Err(UnpackError::Archive(_))
+        );
+
+        // A huge chunk count must not be trusted before the chunks are read
+        let num_chunks = u32::MAX;
+        let mut index = Vec::new();
+        index.extend_from_slice(&1u32.to_le_bytes());
+        index.extend_from_slice(&7u16.to_le_bytes());
+        index.extend_from_slice(b"version");
+        index.extend_from_slice(&(u64::from(num_chunks) * CHUNK_SIZE as u64).to_le_bytes());
+        index.extend_from_slice(&[0; 32]);
+        index.extend_from_slice(&num_chunks.to_le_bytes());
+        assert_matches!(
+            decode_index(&index, HEADER_LEN),
+            Err(UnpackError::Archive(_))
+        );
+    }
+
+    #[test]
+    fn test_indexed_archive_corrupt_chunk() {
+        let contents: Vec<u8> = (0..CHUNK_SIZE * 2).map(|i| i as u8).collect();
+        let files = [
+            ("version", b"1.2.0".to_vec(), true),
+            ("accounts/42.7", contents, false),
+        ];
+        let file = write_archive(&files);
+        let archive = IndexedArchiveReader::new(file.try_clone().unwrap()).unwrap();
+        assert_matches!(archive.verify(), Ok(()));
+
+        // Flip a byte in the second chunk of the storage
+        let entry = archive.entry("accounts/42.7").unwrap().clone();
+        let offset = entry.chunks[1].offset + 17;
+        let mut byte = [0u8];
+        file.read_exact_at(&mut byte, offset).unwrap();
+        file.write_all_at(&[!byte[0]], offset).unwrap();
+
+        let err = archive.verify().unwrap_err().to_string();
+        assert!(err.contains("accounts/42.7"), "{err}");
+        assert_matches!(archive.read_chunk(&entry.chunks[0]), Ok(_));
+        assert_matches!(
+            archive.read_chunk(&entry.chunks[1]),
+            Err(UnpackError::Archive(_))
+        );
+        assert_matches!(archive.read_file(&entry), Err(UnpackError::Archive(_)));
+        let dst = tempfile().unwrap();
+        assert_matches!(
+            archive.unpack_file(&entry, &dst),
+            Err(UnpackError::Archive(_))
+        );
+    }
+
+    #[test]
+    fn test_verified_prefix_len() {
+        let files = [
+            ("version", b"1.2.0".to_vec(), true),
+            ("accounts/42.7", vec![3u8; CHUNK_SIZE + 100], false),
+            ("accounts/42.8", vec![5u8; 100], false),
+        ];
+        let file = write_archive(&files);
+        let archive = IndexedArchiveReader::new(file.try_clone().unwrap()).unwrap();
+        let entries = archive.entries();
+        let storage = archive.entry("accounts/42.7").unwrap();
+        let index_start = archive.entry("accounts/42.8").unwrap().chunks[0].offset + 100;
+
+        // A complete archive is verified up to the end of the chunk data
+        assert_eq!(verified_prefix_len(&file, entries).unwrap(), index_start);
+
+        // A truncated archive is verified up to the last complete chunk
+        let first_chunk_end = storage.chunks[0].offset + u64::from(storage.chunks[0].stored_len);
+        file.set_len(first_chunk_end + 10).unwrap();
+        assert_eq!(
+            verified_prefix_len(&file, entries).unwrap(),
+            first_chunk_end
+        );
+
+        // A corrupt chunk ends the prefix
+        file.write_all_at(&[0], storage.chunks[0].offset).unwrap();
+        let version_chunk = &archive.entry("version").unwrap().chunks[0];
+        assert_eq!(
+            verified_prefix_len(&file, entries).unwrap(),
+            version_chunk.offset + u64::from(version_chunk.stored_len),
+        );
+
+        // An empty or foreign file has no verified prefix
+        file.set_len(0).unwrap();
+        assert_eq!(verified_prefix_len(&file, entries).unwrap(), 0);
+    }
+}
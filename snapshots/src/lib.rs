@@ -4,6 +4,7 @@ mod archive;
 mod archive_format;
 pub mod error;
 pub mod hardened_unpack;
+pub mod indexed_archive;
 mod kind;
 mod multiframe;
 pub mod paths;
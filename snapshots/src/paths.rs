@@ -25,14 +25,20 @@ pub const SNAPSHOT_ACCOUNTS_HARDLINKS: &str = "accounts_hardlinks";
 pub const SNAPSHOT_STORAGES_LIST_FILENAME: &str = "storages_list";
 pub const SNAPSHOT_ARCHIVE_DOWNLOAD_DIR: &str = "remote";
 pub const SNAPSHOT_OBSOLETE_ACCOUNTS_FILENAME: &str = "obsolete_accounts";
+/// The storage scan cache is shared by all bank snapshots, in
+/// `BANK_SNAPSHOTS_DIR/STORAGE_SCAN_CACHE_DIR/`.
+pub const STORAGE_SCAN_CACHE_DIR: &str = "storage_scan_cache";
+/// The progress of verifying the accounts of the snapshot archives being booted from, in
+/// `BANK_SNAPSHOTS_DIR/ACCOUNTS_VERIFICATION_PROGRESS_FILENAME`.
+pub const ACCOUNTS_VERIFICATION_PROGRESS_FILENAME: &str = "accounts_verification_progress";
 /// When a snapshot is taken of a bank, the state is serialized under this directory.
 /// Specifically in `BANK_SNAPSHOTS_DIR/SLOT/`.
 /// This is also where the bank state is located in the snapshot archive.
 pub const BANK_SNAPSHOTS_DIR: &str = "snapshots";
 pub const TMP_SNAPSHOT_ARCHIVE_PREFIX: &str = "tmp-snapshot-archive-";
 pub const FULL_SNAPSHOT_ARCHIVE_FILENAME_REGEX: &str =
-    r"^snapshot-(?P<slot>[[:digit:]]+)-(?P<hash>[[:alnum:]]+)\.(?P<ext>tar\.zst|tar\.lz4)$";
-pub const INCREMENTAL_SNAPSHOT_ARCHIVE_FILENAME_REGEX: &str = r"^incremental-snapshot-(?P<base>[[:digit:]]+)-(?P<slot>[[:digit:]]+)-(?P<hash>[[:alnum:]]+)\.(?P<ext>tar\.zst|tar\.lz4)$";
+    r"^snapshot-(?P<slot>[[:digit:]]+)-(?P<hash>[[:alnum:]]+)\.(?P<ext>tar\.zst|tar\.lz4|idx)$";
+pub const INCREMENTAL_SNAPSHOT_ARCHIVE_FILENAME_REGEX: &str = r"^incremental-snapshot-(?P<base>[[:digit:]]+)-(?P<slot>[[:digit:]]+)-(?P<hash>[[:alnum:]]+)\.(?P<ext>tar\.zst|tar\.lz4|idx)$";
 
 /// Get the `&str` from a `&Path`
 pub fn path_to_file_name_str(path: &Path) -> Result<&str> {
@@ -48,6 +54,20 @@ pub fn build_snapshot_archives_remote_dir(snapshot_archives_dir: impl AsRef<Path
         .join(SNAPSHOT_ARCHIVE_DOWNLOAD_DIR)
 }
 
+/// Build the path to the storage scan cache shared by the bank snapshots in
+/// `bank_snapshots_dir`
+pub fn build_storage_scan_cache_dir(bank_snapshots_dir: impl AsRef<Path>) -> PathBuf {
+    bank_snapshots_dir.as_ref().join(STORAGE_SCAN_CACHE_DIR)
+}
+
+/// Build the path to the progress of verifying the accounts of the snapshot archives booted from
+/// with `bank_snapshots_dir`
+pub fn build_accounts_verification_progress_path(bank_snapshots_dir: impl AsRef<Path>) -> PathBuf {
+    bank_snapshots_dir
+        .as_ref()
+        .join(ACCOUNTS_VERIFICATION_PROGRESS_FILENAME)
+}
+
 /// Build the full snapshot archive path from its components: the snapshot archives directory, the
 /// snapshot slot, the accounts hash, and the archive format.
 pub fn build_full_snapshot_archive_path(
@@ -260,7 +280,10 @@ pub fn get_highest_incremental_snapshot_archive_info(
 
 #[cfg(test)]
 mod tests {
-    use {super::*, crate::ZstdConfig};
+    use {
+        super::*,
+        crate::{IndexedConfig, ZstdConfig},
+    };
 
     #[test]
     fn test_parse_full_snapshot_archive_filename() {
@@ -286,6 +309,17 @@ mod tests {
             .unwrap(),
             (45, SnapshotHash(Hash::default()), ArchiveFormat::TarLz4)
         );
+        assert_eq!(
+            parse_full_snapshot_archive_filename(&format!("snapshot-47-{}.idx", Hash::default()))
+                .unwrap(),
+            (
+                47,
+                SnapshotHash(Hash::default()),
+                ArchiveFormat::Indexed {
+                    config: IndexedConfig::default(),
+                }
+            )
+        );
 
         assert!(parse_full_snapshot_archive_filename("invalid").is_err());
         assert!(
@@ -373,6 +407,21 @@ mod tests {
                 ArchiveFormat::TarLz4
             )
         );
+        assert_eq!(
+            parse_incremental_snapshot_archive_filename(&format!(
+                "incremental-snapshot-47-789-{}.idx",
+                Hash::default()
+            ))
+            .unwrap(),
+            (
+                47,
+                789,
+                SnapshotHash(Hash::default()),
+                ArchiveFormat::Indexed {
+                    config: IndexedConfig::default(),
+                }
+            )
+        );
 
         assert!(parse_incremental_snapshot_archive_filename("invalid").is_err());
         assert!(
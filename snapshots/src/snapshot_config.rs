@@ -56,6 +56,10 @@ pub struct SnapshotConfig {
     ///
     /// Requires memlock ulimit higher than sum of buffer sizes registered at the same time.
     pub use_registered_io_uring_buffers: bool,
+
+    /// Whether to keep a storage scan cache alongside the bank snapshots, so that
+    /// booting from local state does not need to scan every account storage.
+    pub use_storage_scan_cache: bool,
 }
 
 impl Default for SnapshotConfig {
@@ -80,6 +84,7 @@ impl Default for SnapshotConfig {
                 DEFAULT_MAX_INCREMENTAL_SNAPSHOT_ARCHIVES_TO_RETAIN,
             use_direct_io: true,
             use_registered_io_uring_buffers: true,
+            use_storage_scan_cache: false,
         }
     }
 }
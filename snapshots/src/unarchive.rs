@@ -3,6 +3,7 @@ use {
         ArchiveFormat, ArchiveFormatDecompressor,
         error::SnapshotError,
         hardened_unpack::{self, UnpackError},
+        indexed_archive::IndexedArchiveReader,
     },
     agave_fs::{FileInfo, buffered_reader, file_io::file_creator, io_setup::IoSetupState},
     bzip2::bufread::BzDecoder,
@@ -10,6 +11,7 @@ use {
     std::{
         fs,
         io::{self, BufRead, BufReader},
+        num::NonZeroUsize,
         path::{Path, PathBuf},
         sync::OnceLock,
         thread::{self, Scope, ScopedJoinHandle},
@@ -25,6 +27,9 @@ const MAX_SNAPSHOT_READER_BUF_SIZE: usize = 128 * 1024 * 1024;
 // - Large files: their data may accumulate in backlog buffers while waiting for file open
 //   operations to complete.
 const MAX_UNPACK_WRITE_BUF_SIZE: usize = 512 * 1024 * 1024;
+// Indexed archives are unpacked with positional writes from multiple threads;
+// beyond this many threads the unpacking is bound by disk bandwidth.
+const MAX_INDEXED_UNPACK_THREADS: NonZeroUsize = NonZeroUsize::new(16).unwrap();
 
 /// Streams unpacked files across channel
 pub fn streaming_unarchive_snapshot<'scope, 'env: 'scope>(
@@ -36,6 +41,16 @@ pub fn streaming_unarchive_snapshot<'scope, 'env: 'scope>(
     archive_format: ArchiveFormat,
     io_setup: &'env IoSetupState,
 ) -> ScopedJoinHandle<'scope, Result<(), SnapshotError>> {
+    if let ArchiveFormat::Indexed { .. } = archive_format {
+        return streaming_unarchive_indexed_snapshot(
+            scope,
+            file_sender,
+            account_paths,
+            ledger_dir,
+            snapshot_archive_path,
+        );
+    }
+
     let do_unpack = move |archive_path: &Path| {
         let first_failed_send = OnceLock::<PathBuf>::new();
         let first_failed_send_ref = &first_failed_send;
@@ -84,6 +99,47 @@ pub fn streaming_unarchive_snapshot<'scope, 'env: 'scope>(
         .unwrap()
 }
 
+/// Streams unpacked files from an indexed archive across channel
+///
+/// Files are decompressed in parallel, so they are sent in no particular order.
+fn streaming_unarchive_indexed_snapshot<'scope, 'env: 'scope>(
+    scope: &'scope Scope<'scope, 'env>,
+    file_sender: Sender<FileInfo>,
+    account_paths: Vec<PathBuf>,
+    ledger_dir: PathBuf,
+    snapshot_archive_path: PathBuf,
+) -> ScopedJoinHandle<'scope, Result<(), SnapshotError>> {
+    let do_unpack = move |archive_path: &Path| {
+        let first_failed_send = OnceLock::<PathBuf>::new();
+        let archive = IndexedArchiveReader::open(archive_path)?;
+        let num_threads = thread::available_parallelism()
+            .unwrap_or(NonZeroUsize::MIN)
+            .min(MAX_INDEXED_UNPACK_THREADS);
+        hardened_unpack::unpack_indexed_snapshot(
+            &archive,
+            ledger_dir.as_path(),
+            &account_paths,
+            num_threads,
+            |file_info| {
+                if let Err(SendError(FileInfo { path, .. })) = file_sender.send(file_info) {
+                    let _ = first_failed_send.set(path);
+                }
+            },
+        )
+        .map(|()| first_failed_send.into_inner())
+    };
+    thread::Builder::new()
+        .name("solIdxUnpack".to_string())
+        .spawn_scoped(scope, move || -> Result<(), SnapshotError> {
+            match do_unpack(&snapshot_archive_path) {
+                Err(err) => Err(UnpackError::Unpack(Box::new(err), snapshot_archive_path).into()),
+                Ok(Some(path)) => Err(SnapshotError::CrossbeamSend(SendError(path))),
+                Ok(None) => Ok(()),
+            }
+        })
+        .unwrap()
+}
+
 pub fn unpack_genesis_archive(
     archive_filename: &Path,
     destination_dir: &Path,
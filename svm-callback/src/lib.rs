@@ -36,6 +36,18 @@ pub trait InvokeContextCallback {
 pub trait TransactionProcessingCallback {
     fn get_account_shared_data(&self, pubkey: &Pubkey) -> Option<(AccountSharedData, Slot)>;
 
+    /// Loads the accounts of `pubkeys`, in order. Implementations may load them at once, which
+    /// is faster than loading them one at a time.
+    fn get_multiple_account_shared_data(
+        &self,
+        pubkeys: &[Pubkey],
+    ) -> Vec<Option<(AccountSharedData, Slot)>> {
+        pubkeys
+            .iter()
+            .map(|pubkey| self.get_account_shared_data(pubkey))
+            .collect()
+    }
+
     fn inspect_account(&self, _address: &Pubkey, _account_state: AccountState, _is_writable: bool) {
     }
 }
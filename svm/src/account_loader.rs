@@ -22,8 +22,7 @@ use {
     solana_pubkey::Pubkey,
     solana_rent::Rent,
     solana_sdk_ids::{
-        bpf_loader, bpf_loader_deprecated, bpf_loader_upgradeable, loader_v4, native_loader,
-        sysvar::{self, slot_history},
+        bpf_loader, bpf_loader_deprecated, bpf_loader_upgradeable, loader_v4, native_loader, sysvar,
     },
     solana_svm_callback::{AccountState, TransactionProcessingCallback},
     solana_svm_feature_set::SVMFeatureSet,
@@ -186,6 +185,9 @@ pub struct NoOpTransaction {
 #[cfg_attr(feature = "dev-context-only-utils", qualifiers(pub))]
 pub(crate) struct AccountLoader<'a, CB: TransactionProcessingCallback> {
     loaded_accounts: AHashMap<Pubkey, (AccountSharedData, Slot)>,
+    // Accounts loaded from accounts-db ahead of the transactions that use them, which are
+    // returned as if accounts-db had been asked for them when they are first loaded
+    prefetched_accounts: AHashMap<Pubkey, Option<(AccountSharedData, Slot)>>,
     callbacks: &'a CB,
     pub(crate) feature_set: &'a SVMFeatureSet,
 }
@@ -201,21 +203,40 @@ impl<'a, CB: TransactionProcessingCallback> AccountLoader<'a, CB> {
     ) -> AccountLoader<'a, CB> {
         let mut loaded_accounts = AHashMap::with_capacity(capacity);
 
-        // SlotHistory may be overridden for simulation.
-        // No other uses of AccountOverrides are expected.
-        if let Some(slot_history) =
-            account_overrides.and_then(|overrides| overrides.get(&slot_history::id()))
-        {
-            loaded_accounts.insert(slot_history::id(), (slot_history.clone(), 0));
+        // Accounts may be overridden for simulation, e.g. SlotHistory or the
+        // accounts written by previously simulated transactions. They are
+        // loaded as if an earlier transaction of the batch had written them.
+        if let Some(account_overrides) = account_overrides {
+            loaded_accounts.extend(
+                account_overrides
+                    .iter()
+                    .map(|(pubkey, account)| (*pubkey, (account.clone(), 0))),
+            );
         }
 
         Self {
             loaded_accounts,
+            prefetched_accounts: AHashMap::new(),
             callbacks,
             feature_set,
         }
     }
 
+    // Load the accounts that the transactions of a batch will load from accounts-db at once,
+    // which lets Bank batch their reads from storage rather than read them one at a time.
+    // Accounts already held by AccountLoader are skipped.
+    pub(crate) fn prefetch_accounts(&mut self, mut account_keys: Vec<Pubkey>) {
+        account_keys.retain(|account_key| !self.loaded_accounts.contains_key(account_key));
+        account_keys.sort_unstable();
+        account_keys.dedup();
+
+        let accounts = self
+            .callbacks
+            .get_multiple_account_shared_data(&account_keys);
+        self.prefetched_accounts
+            .extend(account_keys.into_iter().zip(accounts));
+    }
+
     // Load an account either from our own store or accounts-db and inspect it on behalf of Bank.
     // Inspection is required prior to any modifications to the account. This function is used
     // by load_transaction() and validate_transaction_fee_payer() for that purpose. It returns
@@ -289,6 +310,8 @@ impl<'a, CB: TransactionProcessingCallback> AccountLoader<'a, CB> {
             };
 
             (option_account, false)
+        } else if let Some(prefetched_account) = self.prefetched_accounts.get(account_key) {
+            (prefetched_account.clone(), true)
         } else if let Some((account, slot)) = self.callbacks.get_account_shared_data(account_key) {
             (Some((account, slot)), true)
         } else {
@@ -886,6 +909,48 @@ mod tests {
         assert!(!account_loader.loaded_accounts.contains_key(&program));
     }
 
+    #[test]
+    fn test_prefetch_accounts() {
+        let (stored_key, missing_key, written_key) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let stored_account = AccountSharedData::new(1, 0, &Pubkey::default());
+        let callbacks = TestCallbacks {
+            accounts_map: HashMap::from([
+                (stored_key, (stored_account.clone(), 1)),
+                (written_key, (stored_account.clone(), 1)),
+            ]),
+            ..Default::default()
+        };
+        let mut account_loader: AccountLoader<TestCallbacks> = (&callbacks).into();
+
+        // Accounts already written in the batch are not prefetched.
+        let written_account = AccountSharedData::new(2, 0, &Pubkey::default());
+        account_loader.update_accounts_for_failed_tx(
+            &RollbackAccounts::FeePayerOnly {
+                fee_payer: (written_key, written_account.clone()),
+            },
+            5,
+        );
+        account_loader.prefetch_accounts(vec![stored_key, missing_key, written_key, stored_key]);
+        assert_eq!(account_loader.prefetched_accounts.len(), 2);
+
+        assert_eq!(
+            account_loader.load_account(&stored_key),
+            Some(stored_account)
+        );
+        assert_eq!(account_loader.load_account(&missing_key), None);
+        assert_eq!(
+            account_loader.load_account(&written_key),
+            Some(written_account)
+        );
+        // Loaded accounts are held for later loads, like accounts loaded from accounts-db.
+        assert!(account_loader.loaded_accounts.contains_key(&stored_key));
+        assert!(account_loader.loaded_accounts.contains_key(&missing_key));
+    }
+
     #[test]
     fn test_load_accounts_unknown_program_id() {
         let mut accounts: Vec<KeyedAccountSharedData> = Vec::new();
@@ -1188,6 +1253,9 @@ mod tests {
         let slot_history_id = sysvar::slot_history::id();
         let account = AccountSharedData::new(42, 0, &Pubkey::default());
         account_overrides.set_slot_history(Some(account));
+        let overridden_id = Pubkey::new_unique();
+        let account = AccountSharedData::new(7, 0, &Pubkey::default());
+        account_overrides.set_account(&overridden_id, Some(account));
 
         let keypair = Keypair::new();
         let account = AccountSharedData::new(1_000_000, 0, &Pubkey::default());
@@ -1197,10 +1265,10 @@ mod tests {
         program_account.set_executable(true);
         program_account.set_owner(native_loader::id());
 
-        let instructions = vec![CompiledInstruction::new(2, &(), vec![0])];
+        let instructions = vec![CompiledInstruction::new(3, &(), vec![0])];
         let tx = Transaction::new_with_compiled_instructions(
             &[&keypair],
-            &[slot_history_id],
+            &[slot_history_id, overridden_id],
             Hash::default(),
             vec![bpf_loader::id()],
             instructions,
@@ -1219,6 +1287,8 @@ mod tests {
                 assert_eq!(loaded_transaction.accounts[0].0, keypair.pubkey());
                 assert_eq!(loaded_transaction.accounts[1].0, slot_history_id);
                 assert_eq!(loaded_transaction.accounts[1].1.lamports(), 42);
+                assert_eq!(loaded_transaction.accounts[2].0, overridden_id);
+                assert_eq!(loaded_transaction.accounts[2].1.lamports(), 7);
             }
             TransactionLoadResult::FeesOnly(fees_only_tx) => panic!("{}", fees_only_tx.load_error),
             TransactionLoadResult::NoOp(no_op_tx) => panic!("{}", no_op_tx.validation_error),
@@ -1,6 +1,7 @@
 //! Error-code mapping for VM execution results.
 
 use {
+    crate::conformance::error_code::serialized_error_code,
     solana_instruction::error::InstructionError,
     solana_poseidon::PoseidonSyscallError,
     solana_program_runtime::{
@@ -26,11 +27,6 @@ fn syscall_error_code(error: &SyscallError) -> i64 {
     (error.discriminant() as i64).saturating_add(1)
 }
 
-pub fn serialized_error_code<T: serde::Serialize>(error: &T) -> u32 {
-    let serialized = bincode::serialize(error).unwrap();
-    u32::from_le_bytes(serialized[0..4].try_into().unwrap()).saturating_add(1)
-}
-
 /// A VM `program_result` mapped into the fields a conformance fixture compares.
 pub struct UnpackedResult {
     /// Error number, or `0` on success.
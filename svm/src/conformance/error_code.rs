@@ -0,0 +1,8 @@
+//! Error numbers of serialized error enums.
+
+/// Error number of a serialized enum: its bincode discriminant `+ 1`, so that
+/// `0` is left for success.
+pub fn serialized_error_code<T: serde::Serialize>(error: &T) -> u32 {
+    let serialized = bincode::serialize(error).unwrap();
+    u32::from_le_bytes(serialized[0..4].try_into().unwrap()).saturating_add(1)
+}
@@ -2,7 +2,7 @@
 
 #[cfg(feature = "conformance")]
 use {
-    crate::conformance::{account_state::account_to_proto, err::serialized_error_code},
+    crate::conformance::{account_state::account_to_proto, error_code::serialized_error_code},
     protosol::protos::InstrEffects as ProtoInstrEffects,
 };
 use {solana_account::Account, solana_instruction::error::InstructionError, solana_pubkey::Pubkey};
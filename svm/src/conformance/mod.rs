@@ -1,24 +1,34 @@
 //! Solana SVM conformance.
 
-#[cfg(feature = "conformance")]
+#[cfg(feature = "conformance-proto")]
 pub mod account_state;
+#[cfg(feature = "dev-context-only-utils")]
 pub mod callback;
 #[cfg(feature = "conformance")]
 pub mod direct_mapping;
 #[cfg(feature = "conformance")]
 pub mod err;
+#[cfg(feature = "conformance-proto")]
+pub mod error_code;
 #[cfg(feature = "conformance")]
 pub mod fd_hash;
 #[cfg(feature = "conformance")]
 pub mod feature_set;
+#[cfg(feature = "dev-context-only-utils")]
 pub mod instr;
+#[cfg(feature = "dev-context-only-utils")]
 pub mod nonce_fields;
+#[cfg(feature = "dev-context-only-utils")]
 pub mod programs;
 #[cfg(feature = "conformance")]
 pub mod serialization;
+#[cfg(feature = "dev-context-only-utils")]
 pub mod setup;
+#[cfg(feature = "dev-context-only-utils")]
 pub mod transaction_address_loader;
+#[cfg(feature = "dev-context-only-utils")]
 pub mod transaction_meta;
+#[cfg(feature = "dev-context-only-utils")]
 pub mod txn;
 #[cfg(feature = "conformance")]
 pub mod versioned_message;
@@ -2,7 +2,7 @@
 
 #[cfg(feature = "conformance")]
 use {
-    crate::conformance::{account_state::account_to_proto, err::serialized_error_code},
+    crate::conformance::{account_state::account_to_proto, error_code::serialized_error_code},
     protosol::protos::{FeeDetails as ProtoFeeDetails, TxnResult as ProtoTxnResult},
     solana_instruction::error::InstructionError,
 };
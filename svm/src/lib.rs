@@ -4,7 +4,11 @@
 
 pub mod account_loader;
 pub mod account_overrides;
-#[cfg(any(feature = "conformance", feature = "dev-context-only-utils"))]
+#[cfg(any(
+    feature = "conformance",
+    feature = "conformance-proto",
+    feature = "dev-context-only-utils"
+))]
 pub mod conformance;
 pub mod nonce_info;
 pub mod program_loader;
@@ -188,6 +188,35 @@ pub fn load_program_with_pubkey<CB: TransactionProcessingCallback>(
     Some((Arc::new(loaded_program), last_modification_slot))
 }
 
+/// Returns the ELF of the program with the given pubkey, without the loader
+/// metadata preceding it in the account data.
+///
+/// Returns `None` if the account doesn't exist, is not owned by one of the
+/// program loaders or holds no deployed program.
+pub fn load_program_elf<CB: TransactionProcessingCallback>(
+    callbacks: &CB,
+    pubkey: &Pubkey,
+) -> Option<Vec<u8>> {
+    let (load_result, _last_modification_slot) = load_program_accounts(callbacks, pubkey)?;
+    match load_result {
+        ProgramAccountLoadResult::InvalidAccountData(_) => None,
+        ProgramAccountLoadResult::ProgramOfLoaderV1(program_account)
+        | ProgramAccountLoadResult::ProgramOfLoaderV2(program_account) => {
+            Some(program_account.data().to_vec())
+        }
+        ProgramAccountLoadResult::ProgramOfLoaderV3(_, programdata_account, _) => {
+            programdata_account
+                .data()
+                .get(UpgradeableLoaderState::size_of_programdata_metadata()..)
+                .map(<[u8]>::to_vec)
+        }
+        ProgramAccountLoadResult::ProgramOfLoaderV4(program_account, _) => program_account
+            .data()
+            .get(LoaderV4State::program_data_offset()..)
+            .map(<[u8]>::to_vec),
+    }
+}
+
 /// Find the slot in which the program was most recently re-/deployed.
 /// Returns slot 0 for programs deployed with v1/v2 loaders, since programs deployed
 /// with those loaders do not retain deployment slot information.
@@ -455,6 +484,43 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_load_program_elf() {
+        let program_key = Pubkey::new_unique();
+        let programdata_key = Pubkey::new_unique();
+        let mock_bank = MockBankCallback::default();
+        assert!(load_program_elf(&mock_bank, &program_key).is_none());
+
+        let mut program_account = AccountSharedData::default();
+        program_account.set_owner(bpf_loader_upgradeable::id());
+        let state = UpgradeableLoaderState::Program {
+            programdata_address: programdata_key,
+        };
+        program_account.set_data_from_slice(&bincode::serialize(&state).unwrap());
+        mock_bank
+            .account_shared_data
+            .borrow_mut()
+            .insert(program_key, (program_account, 0));
+        assert!(load_program_elf(&mock_bank, &program_key).is_none());
+
+        let elf = load_test_program();
+        let state = UpgradeableLoaderState::ProgramData {
+            slot: 0,
+            upgrade_authority_address: None,
+        };
+        let mut programdata = bincode::serialize(&state).unwrap();
+        programdata.resize(UpgradeableLoaderState::size_of_programdata_metadata(), 0);
+        programdata.extend_from_slice(&elf);
+        let mut programdata_account = AccountSharedData::default();
+        programdata_account.set_owner(bpf_loader_upgradeable::id());
+        programdata_account.set_data_from_slice(&programdata);
+        mock_bank
+            .account_shared_data
+            .borrow_mut()
+            .insert(programdata_key, (programdata_account, 0));
+        assert_eq!(load_program_elf(&mock_bank, &program_key), Some(elf));
+    }
+
     fn load_test_program() -> Vec<u8> {
         let mut dir = env::current_dir().unwrap();
         dir.push("tests");
@@ -1,7 +1,9 @@
 use {
     crate::account_loader::LoadedTransaction,
     solana_message::inner_instruction::InnerInstructionsList,
-    solana_program_runtime::program_cache_entry::ProgramCacheEntry,
+    solana_program_runtime::{
+        compute_profile::ComputeProfile, program_cache_entry::ProgramCacheEntry,
+    },
     solana_pubkey::Pubkey,
     solana_transaction_context::transaction::TransactionReturnData,
     solana_transaction_error::TransactionResult,
@@ -37,6 +39,9 @@ pub struct TransactionExecutionDetails {
     /// deltas related to total account data size changes for this transaction.
     /// NOTE: set to None IFF `status` is not `Ok`.
     pub accounts_deltas: Option<AccountsDeltas>,
+    /// Compute units consumed per instruction and per syscall.
+    /// NOTE: set to None unless compute profile recording is enabled.
+    pub compute_profile: Option<ComputeProfile>,
 }
 
 impl TransactionExecutionDetails {
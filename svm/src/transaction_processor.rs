@@ -36,10 +36,11 @@ use {
     },
     solana_nonce_account::verify_nonce_account,
     solana_program_runtime::{
+        debugger::DebuggerConfig,
         execution_budget::{
             SVMTransactionExecutionAndFeeBudgetLimits, SVMTransactionExecutionCost,
         },
-        invoke_context::{EnvironmentConfig, InvokeContext},
+        invoke_context::{EnvironmentConfig, InvokeContext, RegisterTraceInspector},
         loaded_programs::{
             EpochBoundaryPreparation, ForkGraph, Percent, ProgramCache, ProgramCacheForTxBatch,
             ProgramRuntimeEnvironment, ProgramRuntimeEnvironments, ProgramToLoad,
@@ -98,15 +99,22 @@ pub struct ExecutionRecordingConfig {
     pub enable_log_recording: bool,
     pub enable_return_data_recording: bool,
     pub enable_transaction_balance_recording: bool,
+    /// Record the compute units consumed per instruction and per syscall.
+    /// Adds overhead to every syscall, so it is intended for simulation and
+    /// tooling rather than replay.
+    pub enable_compute_profile_recording: bool,
 }
 
 impl ExecutionRecordingConfig {
+    /// Compute profile recording is left disabled, it has to be enabled
+    /// explicitly
     pub fn new_single_setting(option: bool) -> Self {
         ExecutionRecordingConfig {
             enable_return_data_recording: option,
             enable_log_recording: option,
             enable_cpi_recording: option,
             enable_transaction_balance_recording: option,
+            enable_compute_profile_recording: false,
         }
     }
 }
@@ -124,6 +132,13 @@ pub struct TransactionProcessingConfig<'a> {
     pub limit_to_load_programs: bool,
     /// Recording capabilities for transaction execution.
     pub recording_config: ExecutionRecordingConfig,
+    /// Program invocations to host a debugger session for. Debugged programs
+    /// are interpreted and wait for a debugger to connect before they run.
+    pub debugger_config: Option<&'a DebuggerConfig>,
+    /// Receives the VM register traces of the SBF programs run by the batch.
+    /// Traces are only recorded if the program runtime environments have
+    /// their debugging features enabled.
+    pub register_trace_inspector: Option<&'a dyn RegisterTraceInspector>,
     /// Should failing transactions within the batch be dropped (no fee charged
     /// & not committed).
     pub drop_on_failure: bool,
@@ -492,7 +507,19 @@ impl<FG: ForkGraph> TransactionBatchProcessor<FG> {
             };
         }
 
-        let (mut load_us, mut execution_us): (u64, u64) = (0, 0);
+        // Load the accounts of the transactions that passed the checks up front, in one batch,
+        // rather than one at a time as each transaction is loaded.
+        let ((), prefetch_us) = measure_us!({
+            let mut account_keys = Vec::with_capacity(account_keys_in_batch);
+            for (tx, check_result) in sanitized_txs.iter().zip(&check_results) {
+                if check_result.is_ok() {
+                    account_keys.extend(tx.account_keys().iter());
+                }
+            }
+            account_loader.prefetch_accounts(account_keys);
+        });
+
+        let (mut load_us, mut execution_us): (u64, u64) = (prefetch_us, 0);
         let sysvar_cache = self.sysvar_cache();
 
         // Validate, execute, and collect results from each transaction in order.
@@ -1137,6 +1164,11 @@ impl<FG: ForkGraph> TransactionBatchProcessor<FG> {
             compute_budget,
             self.execution_cost,
         );
+        if config.recording_config.enable_compute_profile_recording {
+            invoke_context.enable_compute_profile_recording();
+        }
+        invoke_context.debugger_config = config.debugger_config.cloned();
+        invoke_context.register_trace_inspector = config.register_trace_inspector;
 
         let mut process_message_time = Measure::start("process_message_time");
         let process_result = invoke_context
@@ -1144,6 +1176,7 @@ impl<FG: ForkGraph> TransactionBatchProcessor<FG> {
             .map_err(|(index, err)| TransactionError::InstructionError(index, err));
         process_message_time.stop();
 
+        let compute_profile = invoke_context.take_compute_profile();
         drop(invoke_context);
 
         execute_timings.execute_accessories.process_message_us += process_message_time.as_us();
@@ -1254,6 +1287,7 @@ impl<FG: ForkGraph> TransactionBatchProcessor<FG> {
                 return_data,
                 executed_units,
                 accounts_deltas,
+                compute_profile,
             },
             loaded_transaction,
             programs_modified_by_tx: program_cache_for_tx_batch.drain_modified_entries(),
@@ -7,7 +7,7 @@ use {
     },
 };
 
-declare_builtin_function!(
+declare_syscall!(
     /// Cross-program invocation called from Rust
     SyscallInvokeSignedRust,
     fn rust(
@@ -46,7 +46,7 @@ impl SyscallInvokeSigned for SyscallInvokeSignedRust {
     }
 }
 
-declare_builtin_function!(
+declare_syscall!(
     /// Cross-program invocation called from C
     SyscallInvokeSignedC,
     fn rust(
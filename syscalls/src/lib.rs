@@ -56,6 +56,34 @@ use {
     thiserror::Error as ThisError,
 };
 
+/// Declares a syscall via `declare_builtin_function!`, attributing the compute
+/// units it charges to the current instruction when compute profile recording
+/// is enabled
+macro_rules! declare_syscall {
+    (
+        $(#[$attr:meta])*
+        $name:ident $(<$($generic_ident:tt : $generic_type:tt),+>)?,
+        fn rust(
+            $invoke_context:ident: &mut $InvokeContext:ty,
+            $($arg:ident: u64),* $(,)?
+        ) -> Result<u64, $Error:ty> $body:block
+    ) => {
+        declare_builtin_function!(
+            $(#[$attr])*
+            $name $(<$($generic_ident : $generic_type),+>)?,
+            fn rust(
+                $invoke_context: &mut $InvokeContext,
+                $($arg: u64),*
+            ) -> Result<u64, $Error> {
+                $invoke_context.profile_syscall(
+                    std::any::type_name::<Self>(),
+                    |$invoke_context| -> Result<u64, $Error> $body,
+                )
+            }
+        );
+    };
+}
+
 mod cpi;
 mod logging;
 mod mem_ops;
@@ -730,7 +758,7 @@ declare_builtin_function!(
     }
 );
 
-declare_builtin_function!(
+declare_syscall!(
     /// Panic syscall function, called when the SBF program calls 'sol_panic_()`
     /// Causes the SBF program to be halted immediately
     SyscallPanic,
@@ -755,7 +783,7 @@ declare_builtin_function!(
     }
 );
 
-declare_builtin_function!(
+declare_syscall!(
     /// Dynamic memory allocation syscall called when the SBF program calls
     /// `sol_alloc_free_()`.  The allocator is expected to allocate/free
     /// from/to a given chunk of memory and enforce size restrictions.  The
@@ -817,7 +845,7 @@ fn translate_and_check_program_address_inputs(
     Ok((seeds, program_id))
 }
 
-declare_builtin_function!(
+declare_syscall!(
     /// Create a program address
     SyscallCreateProgramAddress,
     fn rust(
@@ -856,7 +884,7 @@ declare_builtin_function!(
     }
 );
 
-declare_builtin_function!(
+declare_syscall!(
     /// Create a program address
     SyscallTryFindProgramAddress,
     fn rust(
@@ -909,7 +937,7 @@ declare_builtin_function!(
     }
 );
 
-declare_builtin_function!(
+declare_syscall!(
     /// secp256k1_recover
     SyscallSecp256k1Recover,
     fn rust(
@@ -978,7 +1006,7 @@ declare_builtin_function!(
     }
 );
 
-declare_builtin_function!(
+declare_syscall!(
     // Elliptic Curve Point Validation
     //
     // Currently, the following curves are supported:
@@ -1114,7 +1142,7 @@ declare_builtin_function!(
     }
 );
 
-declare_builtin_function!(
+declare_syscall!(
     // Elliptic Curve Point Decompression
     //
     // Currently, the following curves are supported:
@@ -1213,7 +1241,7 @@ declare_builtin_function!(
     }
 );
 
-declare_builtin_function!(
+declare_syscall!(
     // Elliptic Curve Group Operations
     //
     // Currently, the following curves are supported:
@@ -1707,7 +1735,7 @@ declare_builtin_function!(
     }
 );
 
-declare_builtin_function!(
+declare_syscall!(
     // Elliptic Curve Multiscalar Multiplication
     //
     // Currently, the following curves are supported:
@@ -1829,7 +1857,7 @@ declare_builtin_function!(
     }
 );
 
-declare_builtin_function!(
+declare_syscall!(
     /// Elliptic Curve Pairing Map
     ///
     // Currently, the following curves are supported:
@@ -1909,7 +1937,7 @@ declare_builtin_function!(
     }
 );
 
-declare_builtin_function!(
+declare_syscall!(
     /// Set return data
     SyscallSetReturnData,
     fn rust(
@@ -1958,7 +1986,7 @@ declare_builtin_function!(
     }
 );
 
-declare_builtin_function!(
+declare_syscall!(
     /// Get return data
     SyscallGetReturnData,
     fn rust(
@@ -2005,7 +2033,7 @@ declare_builtin_function!(
     }
 );
 
-declare_builtin_function!(
+declare_syscall!(
     /// Get a processed sigling instruction
     SyscallGetProcessedSiblingInstruction,
     fn rust(
@@ -2105,7 +2133,7 @@ declare_builtin_function!(
     }
 );
 
-declare_builtin_function!(
+declare_syscall!(
     /// Get current call stack height
     SyscallGetStackHeight,
     fn rust(
@@ -2124,7 +2152,7 @@ declare_builtin_function!(
     }
 );
 
-declare_builtin_function!(
+declare_syscall!(
     /// alt_bn128 group operations
     SyscallAltBn128,
     fn rust(
@@ -2372,7 +2400,7 @@ fn big_mod_exp_operation_cost(
     u64::try_from(operation_cost).ok()
 }
 
-declare_builtin_function!(
+declare_syscall!(
     /// Big integer modular exponentiation
     SyscallBigModExp,
     fn rust(
@@ -2449,7 +2477,7 @@ declare_builtin_function!(
     }
 );
 
-declare_builtin_function!(
+declare_syscall!(
     // Poseidon
     SyscallPoseidon,
     fn rust(
@@ -2519,7 +2547,7 @@ declare_builtin_function!(
     }
 );
 
-declare_builtin_function!(
+declare_syscall!(
     /// Read remaining compute units
     SyscallRemainingComputeUnits,
     fn rust(
@@ -2538,7 +2566,7 @@ declare_builtin_function!(
     }
 );
 
-declare_builtin_function!(
+declare_syscall!(
     /// alt_bn128 g1 and g2 compression and decompression
     SyscallAltBn128Compression,
     fn rust(
@@ -2714,7 +2742,7 @@ declare_builtin_function!(
     }
 );
 
-declare_builtin_function!(
+declare_syscall!(
     // Generic Hashing Syscall
     SyscallHash<H: HasherImpl>,
     fn rust(
@@ -2787,7 +2815,7 @@ declare_builtin_function!(
     }
 );
 
-declare_builtin_function!(
+declare_syscall!(
     // Get Epoch Stake Syscall
     SyscallGetEpochStake,
     fn rust(
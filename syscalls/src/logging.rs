@@ -2,7 +2,7 @@ use {
     super::*, solana_program_runtime::memory::translate_vm_slice, solana_sbpf::vm::ContextObject,
 };
 
-declare_builtin_function!(
+declare_syscall!(
     /// Log a user's info message
     SyscallLog,
     fn rust(
@@ -35,7 +35,7 @@ declare_builtin_function!(
     }
 );
 
-declare_builtin_function!(
+declare_syscall!(
     /// Log 5 64-bit values
     SyscallLogU64,
     fn rust(
@@ -57,7 +57,7 @@ declare_builtin_function!(
     }
 );
 
-declare_builtin_function!(
+declare_syscall!(
     /// Log current compute consumption
     SyscallLogBpfComputeUnits,
     fn rust(
@@ -80,7 +80,7 @@ declare_builtin_function!(
     }
 );
 
-declare_builtin_function!(
+declare_syscall!(
     /// Log a [`Pubkey`] as a base58 string
     SyscallLogPubkey,
     fn rust(
@@ -106,7 +106,7 @@ declare_builtin_function!(
     }
 );
 
-declare_builtin_function!(
+declare_syscall!(
     /// Log data handling
     SyscallLogData,
     fn rust(
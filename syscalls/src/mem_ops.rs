@@ -23,7 +23,7 @@ where
     }
 }
 
-declare_builtin_function!(
+declare_syscall!(
     /// memcpy
     SyscallMemcpy,
     fn rust(
@@ -45,7 +45,7 @@ declare_builtin_function!(
     }
 );
 
-declare_builtin_function!(
+declare_syscall!(
     /// memmove
     SyscallMemmove,
     fn rust(
@@ -61,7 +61,7 @@ declare_builtin_function!(
     }
 );
 
-declare_builtin_function!(
+declare_syscall!(
     /// memcmp
     SyscallMemcmp,
     fn rust(
@@ -108,7 +108,7 @@ declare_builtin_function!(
     }
 );
 
-declare_builtin_function!(
+declare_syscall!(
     /// memset
     SyscallMemset,
     fn rust(
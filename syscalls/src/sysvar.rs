@@ -50,7 +50,7 @@ fn get_sysvar<T: SysvarId + Clone>(
     Ok(SUCCESS)
 }
 
-declare_builtin_function!(
+declare_syscall!(
     /// Get a Clock sysvar
     SyscallGetClockSysvar,
     fn rust(
@@ -69,7 +69,7 @@ declare_builtin_function!(
     }
 );
 
-declare_builtin_function!(
+declare_syscall!(
     /// Get a EpochSchedule sysvar
     SyscallGetEpochScheduleSysvar,
     fn rust(
@@ -88,7 +88,7 @@ declare_builtin_function!(
     }
 );
 
-declare_builtin_function!(
+declare_syscall!(
     /// Get a EpochRewards sysvar
     SyscallGetEpochRewardsSysvar,
     fn rust(
@@ -107,7 +107,7 @@ declare_builtin_function!(
     }
 );
 
-declare_builtin_function!(
+declare_syscall!(
     /// Get a Fees sysvar
     SyscallGetFeesSysvar,
     fn rust(
@@ -129,7 +129,7 @@ declare_builtin_function!(
     }
 );
 
-declare_builtin_function!(
+declare_syscall!(
     /// Get a Rent sysvar
     SyscallGetRentSysvar,
     fn rust(
@@ -148,7 +148,7 @@ declare_builtin_function!(
     }
 );
 
-declare_builtin_function!(
+declare_syscall!(
     /// Get a Last Restart Slot sysvar
     SyscallGetLastRestartSlotSysvar,
     fn rust(
@@ -172,7 +172,7 @@ const OFFSET_LENGTH_EXCEEDS_SYSVAR: u64 = 1;
 
 // quoted language from SIMD0127
 // because this syscall can both return error codes and abort, well-ordered error checking is crucial
-declare_builtin_function!(
+declare_syscall!(
     /// Get a slice of a Sysvar in-memory representation
     SyscallGetSysvar,
     fn rust(
@@ -11,6 +11,7 @@ use {
     },
     log::*,
     serde::{Deserialize, Serialize, de::Deserializer},
+    solana_accounts_db::write_telemetry::{HotAccount, HotOwner},
     solana_clock::{Epoch, Slot},
     solana_core::{
         admin_rpc_post_init::AdminRpcRequestMetadataPostInit,
@@ -29,6 +30,7 @@ use {
     solana_gossip::contact_info::{ContactInfo, Protocol, SOCKET_ADDR_UNSPECIFIED},
     solana_keypair::{Keypair, read_keypair_file},
     solana_metrics::{datapoint_info, datapoint_warn},
+    solana_program_runtime::loaded_programs::ProgramCacheEntrySummary,
     solana_pubkey::Pubkey,
     solana_runtime::{bank::VATHealthError, snapshot_controller::SnapshotController},
     solana_signer::Signer,
@@ -181,6 +183,74 @@ impl Display for AdminRpcRepairWhitelist {
 impl solana_cli_output::VerboseDisplay for AdminRpcRepairWhitelist {}
 impl solana_cli_output::QuietDisplay for AdminRpcRepairWhitelist {}
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AdminRpcProgramCache {
+    pub entries: Vec<ProgramCacheEntrySummary>,
+}
+
+impl Display for AdminRpcProgramCache {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        ProgramCacheEntrySummary::write_table(f, &self.entries)
+    }
+}
+impl solana_cli_output::VerboseDisplay for AdminRpcProgramCache {}
+impl solana_cli_output::QuietDisplay for AdminRpcProgramCache {}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AdminRpcHotAccounts {
+    /// Writes are sampled, one in `sample_rate` accounts stored
+    pub sample_rate: u64,
+    pub accounts: Vec<HotAccount>,
+    pub owners: Vec<HotOwner>,
+}
+
+impl Display for AdminRpcHotAccounts {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "Estimated writes since startup, sampling one in {} accounts stored",
+            self.sample_rate,
+        )?;
+        writeln!(f)?;
+        writeln!(
+            f,
+            "{:<44} {:<44} {:>12} {:>14} {:>12} {:>12}",
+            "Account", "Owner", "Writes", "Bytes", "First Slot", "Last Slot",
+        )?;
+        for account in &self.accounts {
+            writeln!(
+                f,
+                "{:<44} {:<44} {:>12} {:>14} {:>12} {:>12}",
+                account.pubkey.to_string(),
+                account.owner.to_string(),
+                account.writes,
+                account.bytes,
+                account.first_slot,
+                account.last_slot,
+            )?;
+        }
+        writeln!(f)?;
+        writeln!(
+            f,
+            "{:<44} {:>12} {:>14} {:>12}",
+            "Owner", "Writes", "Bytes", "Rewrites",
+        )?;
+        for owner in &self.owners {
+            writeln!(
+                f,
+                "{:<44} {:>12} {:>14} {:>12}",
+                owner.owner.to_string(),
+                owner.writes,
+                owner.bytes,
+                owner.rewrites,
+            )?;
+        }
+        Ok(())
+    }
+}
+impl solana_cli_output::VerboseDisplay for AdminRpcHotAccounts {}
+impl solana_cli_output::QuietDisplay for AdminRpcHotAccounts {}
+
 #[rpc]
 pub trait AdminRpc {
     type Metadata;
@@ -314,6 +384,18 @@ pub trait AdminRpc {
 
     #[rpc(meta, name = "blockstorePurge")]
     fn blockstore_purge(&self, meta: Self::Metadata, maximum_purge_slot: Slot) -> Result<()>;
+
+    #[rpc(meta, name = "programCache")]
+    fn program_cache(&self, meta: Self::Metadata) -> Result<AdminRpcProgramCache>;
+
+    #[rpc(meta, name = "pinPrograms")]
+    fn pin_programs(&self, meta: Self::Metadata, program_ids: Vec<Pubkey>) -> Result<()>;
+
+    #[rpc(meta, name = "unpinPrograms")]
+    fn unpin_programs(&self, meta: Self::Metadata, program_ids: Vec<Pubkey>) -> Result<()>;
+
+    #[rpc(meta, name = "hotAccounts")]
+    fn hot_accounts(&self, meta: Self::Metadata, limit: usize) -> Result<AdminRpcHotAccounts>;
 }
 
 pub struct AdminRpcImpl;
@@ -920,6 +1002,67 @@ impl AdminRpc for AdminRpcImpl {
                 })
         })
     }
+
+    fn program_cache(&self, meta: Self::Metadata) -> Result<AdminRpcProgramCache> {
+        debug!("program_cache request received");
+
+        meta.with_post_init(|post_init| {
+            let root_bank = post_init.bank_forks.read().unwrap().root_bank();
+            let entries = root_bank.program_cache_entries();
+            Ok(AdminRpcProgramCache { entries })
+        })
+    }
+
+    fn pin_programs(&self, meta: Self::Metadata, program_ids: Vec<Pubkey>) -> Result<()> {
+        debug!("pin_programs request received");
+
+        meta.with_post_init(|post_init| {
+            let root_bank = post_init.bank_forks.read().unwrap().root_bank();
+            for program_id in program_ids {
+                if root_bank.pin_cached_program(program_id) {
+                    info!("Pinned program {program_id} in the program cache");
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn unpin_programs(&self, meta: Self::Metadata, program_ids: Vec<Pubkey>) -> Result<()> {
+        debug!("unpin_programs request received");
+
+        meta.with_post_init(|post_init| {
+            let root_bank = post_init.bank_forks.read().unwrap().root_bank();
+            for program_id in &program_ids {
+                if root_bank.unpin_cached_program(program_id) {
+                    info!("Unpinned program {program_id} in the program cache");
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn hot_accounts(&self, meta: Self::Metadata, limit: usize) -> Result<AdminRpcHotAccounts> {
+        debug!("hot_accounts request received");
+
+        meta.with_post_init(|post_init| {
+            let root_bank = post_init.bank_forks.read().unwrap().root_bank();
+            let report = root_bank
+                .accounts()
+                .accounts_db
+                .write_telemetry_report(limit)
+                .ok_or_else(|| {
+                    jsonrpc_core::error::Error::invalid_params(
+                        "write telemetry is disabled, see \
+                         --accounts-db-write-telemetry-sample-rate",
+                    )
+                })?;
+            Ok(AdminRpcHotAccounts {
+                sample_rate: report.sample_rate,
+                accounts: report.accounts,
+                owners: report.owners,
+            })
+        })
+    }
 }
 
 impl AdminRpcImpl {
@@ -63,6 +63,8 @@ pub fn app<'a>(version: &'a str, default_args: &'a DefaultArgs) -> App<'a, 'a> {
         .subcommand(commands::exit::command())
         .subcommand(commands::authorized_voter::command())
         .subcommand(commands::contact_info::command())
+        .subcommand(commands::hot_accounts::command())
+        .subcommand(commands::program_cache::command())
         .subcommand(commands::repair_shred_from_peer::command())
         .subcommand(commands::repair_whitelist::command())
         .subcommand(
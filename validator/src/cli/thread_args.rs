@@ -14,6 +14,7 @@ pub struct DefaultThreadArgs {
     pub accounts_db_background_threads: String,
     pub accounts_db_foreground_threads: String,
     pub accounts_index_flush_threads: String,
+    pub accounts_index_generation_threads: String,
     pub block_production_num_workers: String,
     pub ip_echo_server_threads: String,
     pub rayon_global_threads: String,
@@ -38,6 +39,8 @@ impl Default for DefaultThreadArgs {
                 .to_string(),
             accounts_index_flush_threads: AccountsIndexFlushThreadsArg::bounded_default()
                 .to_string(),
+            accounts_index_generation_threads: AccountsIndexGenerationThreadsArg::bounded_default()
+                .to_string(),
             block_production_num_workers: BankingStage::default_num_workers().to_string(),
             ip_echo_server_threads: IpEchoServerThreadsArg::bounded_default().to_string(),
             rayon_global_threads: RayonGlobalThreadsArg::bounded_default().to_string(),
@@ -65,6 +68,9 @@ pub fn thread_args<'a>(defaults: &DefaultThreadArgs) -> Vec<Arg<'_, 'a>> {
         new_thread_arg::<AccountsDbBackgroundThreadsArg>(&defaults.accounts_db_background_threads),
         new_thread_arg::<AccountsDbForegroundThreadsArg>(&defaults.accounts_db_foreground_threads),
         new_thread_arg::<AccountsIndexFlushThreadsArg>(&defaults.accounts_index_flush_threads),
+        new_thread_arg::<AccountsIndexGenerationThreadsArg>(
+            &defaults.accounts_index_generation_threads,
+        ),
         new_thread_arg::<BlockProductionNumWorkersArg>(&defaults.block_production_num_workers),
         new_thread_arg::<IpEchoServerThreadsArg>(&defaults.ip_echo_server_threads),
         new_thread_arg::<RayonGlobalThreadsArg>(&defaults.rayon_global_threads),
@@ -100,6 +106,7 @@ pub struct NumThreadConfig {
     pub accounts_db_background_threads: NonZeroUsize,
     pub accounts_db_foreground_threads: NonZeroUsize,
     pub accounts_index_flush_threads: NonZeroUsize,
+    pub accounts_index_generation_threads: NonZeroUsize,
     pub block_production_num_workers: NonZeroUsize,
     pub ip_echo_server_threads: NonZeroUsize,
     pub rayon_global_threads: NonZeroUsize,
@@ -132,6 +139,11 @@ pub fn parse_num_threads_args(matches: &ArgMatches) -> NumThreadConfig {
             AccountsIndexFlushThreadsArg::NAME,
             NonZeroUsize
         ),
+        accounts_index_generation_threads: value_t_or_exit!(
+            matches,
+            AccountsIndexGenerationThreadsArg::NAME,
+            NonZeroUsize
+        ),
         block_production_num_workers: value_t_or_exit!(
             matches,
             BlockProductionNumWorkersArg::NAME,
@@ -254,6 +266,18 @@ impl ThreadArg for AccountsIndexFlushThreadsArg {
     }
 }
 
+struct AccountsIndexGenerationThreadsArg;
+impl ThreadArg for AccountsIndexGenerationThreadsArg {
+    const NAME: &'static str = "accounts_index_generation_threads";
+    const LONG_NAME: &'static str = "accounts-index-generation-threads";
+    const HELP: &'static str = "Number of threads to use for generating the accounts index at \
+                                startup, which also verifies the snapshot's accounts";
+
+    fn default() -> usize {
+        num_cpus::get()
+    }
+}
+
 struct BlockProductionNumWorkersArg;
 impl ThreadArg for BlockProductionNumWorkersArg {
     const NAME: &'static str = "block_production_num_workers";
@@ -0,0 +1,108 @@
+use {
+    crate::{
+        admin_rpc_service,
+        commands::{FromClapArgMatches, Result},
+    },
+    clap::{App, Arg, ArgMatches, SubCommand, value_t},
+    solana_clap_utils::input_validators::is_parsable,
+    solana_cli_output::OutputFormat,
+    std::path::Path,
+};
+
+const COMMAND: &str = "hot-accounts";
+
+const DEFAULT_LIMIT: &str = "20";
+
+#[derive(Debug, PartialEq)]
+pub struct HotAccountsArgs {
+    pub limit: usize,
+    pub output: OutputFormat,
+}
+
+impl FromClapArgMatches for HotAccountsArgs {
+    fn from_clap_arg_match(matches: &ArgMatches) -> Result<Self> {
+        Ok(HotAccountsArgs {
+            limit: value_t!(matches, "limit", usize)?,
+            output: OutputFormat::from_matches(matches, "output", false),
+        })
+    }
+}
+
+pub fn command<'a>() -> App<'a, 'a> {
+    SubCommand::with_name(COMMAND)
+        .about("Display the accounts and owner programs written the most")
+        .arg(
+            Arg::with_name("limit")
+                .long("limit")
+                .takes_value(true)
+                .value_name("NUM")
+                .validator(is_parsable::<usize>)
+                .default_value(DEFAULT_LIMIT)
+                .help("Number of accounts and owners to display"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .long("output")
+                .takes_value(true)
+                .value_name("MODE")
+                .possible_values(&["json", "json-compact"])
+                .help("Output display mode"),
+        )
+        .after_help(
+            "Note: requires the validator to run with --accounts-db-write-telemetry-sample-rate",
+        )
+}
+
+pub fn execute(matches: &ArgMatches, ledger_path: &Path) -> Result<()> {
+    let HotAccountsArgs { limit, output } = HotAccountsArgs::from_clap_arg_match(matches)?;
+
+    let admin_client = admin_rpc_service::connect(ledger_path);
+    let hot_accounts = admin_rpc_service::runtime()
+        .block_on(async move { admin_client.await?.hot_accounts(limit).await })?;
+
+    println!("{}", output.formatted_string(&hot_accounts));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::commands::tests::{
+            verify_args_struct_by_command, verify_args_struct_by_command_is_error,
+        },
+    };
+
+    #[test]
+    fn verify_args_struct_by_command_hot_accounts_default() {
+        verify_args_struct_by_command(
+            command(),
+            vec![COMMAND],
+            HotAccountsArgs {
+                limit: 20,
+                output: OutputFormat::Display,
+            },
+        );
+    }
+
+    #[test]
+    fn verify_args_struct_by_command_hot_accounts_with_args() {
+        verify_args_struct_by_command(
+            command(),
+            vec![COMMAND, "--limit", "5", "--output", "json"],
+            HotAccountsArgs {
+                limit: 5,
+                output: OutputFormat::Json,
+            },
+        );
+    }
+
+    #[test]
+    fn verify_args_struct_by_command_hot_accounts_invalid_limit() {
+        verify_args_struct_by_command_is_error::<HotAccountsArgs>(
+            command(),
+            vec![COMMAND, "--limit", "many"],
+        );
+    }
+}
@@ -2,9 +2,11 @@ pub mod authorized_voter;
 pub mod blockstore;
 pub mod contact_info;
 pub mod exit;
+pub mod hot_accounts;
 pub mod manage_block_production;
 pub mod monitor;
 pub mod plugin;
+pub mod program_cache;
 pub mod repair_shred_from_peer;
 pub mod repair_whitelist;
 pub mod run;
@@ -0,0 +1,163 @@
+use {
+    crate::{
+        admin_rpc_service,
+        commands::{FromClapArgMatches, Result},
+    },
+    clap::{App, AppSettings, Arg, ArgMatches, SubCommand, values_t},
+    itertools::Itertools,
+    solana_clap_utils::input_validators::is_pubkey,
+    solana_cli_output::OutputFormat,
+    solana_pubkey::Pubkey,
+    std::path::Path,
+};
+
+pub const COMMAND: &str = "program-cache";
+
+#[derive(Debug, PartialEq)]
+pub struct ProgramCacheListArgs {
+    pub output: OutputFormat,
+}
+
+impl FromClapArgMatches for ProgramCacheListArgs {
+    fn from_clap_arg_match(matches: &ArgMatches) -> Result<Self> {
+        Ok(ProgramCacheListArgs {
+            output: OutputFormat::from_matches(matches, "output", false),
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ProgramCachePinArgs {
+    pub program_ids: Vec<Pubkey>,
+}
+
+impl FromClapArgMatches for ProgramCachePinArgs {
+    fn from_clap_arg_match(matches: &ArgMatches) -> Result<Self> {
+        let program_ids = values_t!(matches, "program_id", Pubkey)?
+            .into_iter()
+            .unique()
+            .collect::<Vec<_>>();
+        Ok(ProgramCachePinArgs { program_ids })
+    }
+}
+
+fn program_id_arg<'a>() -> Arg<'a, 'a> {
+    Arg::with_name("program_id")
+        .index(1)
+        .validator(is_pubkey)
+        .value_name("PROGRAM_ID")
+        .multiple(true)
+        .takes_value(true)
+        .required(true)
+}
+
+pub fn command<'a>() -> App<'a, 'a> {
+    SubCommand::with_name(COMMAND)
+        .about("Inspect and control the validator's program cache")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .setting(AppSettings::InferSubcommands)
+        .subcommand(
+            SubCommand::with_name("list")
+                .about(
+                    "Display the cached programs with their deployment slot, tier, usage and \
+                     compilation cost",
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .long("output")
+                        .takes_value(true)
+                        .value_name("MODE")
+                        .possible_values(&["json", "json-compact"])
+                        .help("Output display mode"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("pin")
+                .about("Exempt the compiled executables of programs from eviction")
+                .setting(AppSettings::ArgRequiredElseHelp)
+                .arg(program_id_arg().help("Program to pin"))
+                .after_help(
+                    "Note: pinned programs only apply to the currently running validator instance",
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("unpin")
+                .about("Make pinned programs evictable again")
+                .setting(AppSettings::ArgRequiredElseHelp)
+                .arg(program_id_arg().help("Program to unpin")),
+        )
+}
+
+pub fn execute(matches: &ArgMatches, ledger_path: &Path) -> Result<()> {
+    match matches.subcommand() {
+        ("list", Some(subcommand_matches)) => {
+            let ProgramCacheListArgs { output } =
+                ProgramCacheListArgs::from_clap_arg_match(subcommand_matches)?;
+
+            let admin_client = admin_rpc_service::connect(ledger_path);
+            let program_cache = admin_rpc_service::runtime()
+                .block_on(async move { admin_client.await?.program_cache().await })?;
+
+            println!("{}", output.formatted_string(&program_cache));
+        }
+        ("pin", Some(subcommand_matches)) => {
+            let ProgramCachePinArgs { program_ids } =
+                ProgramCachePinArgs::from_clap_arg_match(subcommand_matches)?;
+
+            let admin_client = admin_rpc_service::connect(ledger_path);
+            admin_rpc_service::runtime()
+                .block_on(async move { admin_client.await?.pin_programs(program_ids).await })?;
+        }
+        ("unpin", Some(subcommand_matches)) => {
+            let ProgramCachePinArgs { program_ids } =
+                ProgramCachePinArgs::from_clap_arg_match(subcommand_matches)?;
+
+            let admin_client = admin_rpc_service::connect(ledger_path);
+            admin_rpc_service::runtime()
+                .block_on(async move { admin_client.await?.unpin_programs(program_ids).await })?;
+        }
+        _ => unreachable!(),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::str::FromStr};
+
+    #[test]
+    fn verify_args_struct_by_command_program_cache_list_default() {
+        let app = command();
+        let matches = app.get_matches_from(vec![COMMAND, "list"]);
+        let subcommand_matches = matches.subcommand_matches("list").unwrap();
+        let args = ProgramCacheListArgs::from_clap_arg_match(subcommand_matches).unwrap();
+        assert_eq!(
+            args,
+            ProgramCacheListArgs {
+                output: OutputFormat::Display
+            }
+        );
+    }
+
+    #[test]
+    fn verify_args_struct_by_command_program_cache_pin() {
+        let app = command();
+        let matches = app.get_matches_from(vec![
+            COMMAND,
+            "pin",
+            "ch1do11111111111111111111111111111111111111",
+            "ch1do11111111111111111111111111111111111111",
+        ]);
+        let subcommand_matches = matches.subcommand_matches("pin").unwrap();
+        let args = ProgramCachePinArgs::from_clap_arg_match(subcommand_matches).unwrap();
+        assert_eq!(
+            args,
+            ProgramCachePinArgs {
+                program_ids: vec![
+                    Pubkey::from_str("ch1do11111111111111111111111111111111111111").unwrap(),
+                ]
+            }
+        );
+    }
+}
@@ -9,6 +9,7 @@ use {
     clap::{App, Arg, ArgMatches, values_t},
     solana_accounts_db::utils::create_and_canonicalize_directory,
     solana_clap_utils::{
+        conformance_fixture::{conformance_fixture_args, conformance_fixture_filter_group},
         hidden_unless_forced,
         input_parsers::keypair_of,
         input_validators::{
@@ -31,7 +32,7 @@ use {
     solana_send_transaction_service::send_transaction_service::Config as SendTransactionServiceConfig,
     solana_signer::Signer,
     solana_unified_scheduler_pool::DefaultSchedulerPool,
-    std::{collections::HashSet, net::SocketAddr, path::PathBuf},
+    std::{collections::HashSet, net::SocketAddr, num::NonZeroU64, path::PathBuf},
 };
 
 const EXCLUDE_KEY: &str = "account-index-exclude-key";
@@ -624,6 +625,25 @@ pub fn add_args<'a>(app: App<'a, 'a>, default_args: &'a DefaultArgs) -> App<'a,
                  signing. Turn off to risk double signing a block.",
             ),
     )
+    .arg(
+        Arg::with_name("enable_blockstore_scrub")
+            .long("enable-blockstore-scrub")
+            .takes_value(false)
+            .help(
+                "Continuously check rooted slots in the blockstore for shreds that are missing, \
+                 corrupt or inconsistent with their metadata",
+            ),
+    )
+    .arg(
+        Arg::with_name("blockstore_scrub_repair")
+            .long("blockstore-scrub-repair")
+            .takes_value(false)
+            .requires("enable_blockstore_scrub")
+            .help(
+                "Re-fetch the shreds of rooted slots that fail the blockstore scrub through \
+                 repair, replacing the stored shreds of the slot",
+            ),
+    )
     .arg(
         Arg::with_name("hard_forks")
             .long("hard-fork")
@@ -656,6 +676,31 @@ pub fn add_args<'a>(app: App<'a, 'a>, default_args: &'a DefaultArgs) -> App<'a,
             .takes_value(true)
             .help("Log when transactions are processed which reference a given key."),
     )
+    .arg(
+        Arg::with_name("program_cache_prewarm")
+            .long("program-cache-prewarm")
+            .validator(is_pubkey)
+            .value_name("ADDRESS")
+            .multiple(true)
+            .takes_value(true)
+            .help(
+                "Compile this program into the program cache at startup, instead of on its first \
+                 invocation. May be specified multiple times.",
+            ),
+    )
+    .arg(
+        Arg::with_name("jit_cache_dir")
+            .long("jit-cache-dir")
+            .value_name("DIR")
+            .takes_value(true)
+            .help(
+                "Record the programs compiled into the program cache to DIR every minute and at \
+                 exit, and compile them again at startup, before replay, if neither the programs \
+                 nor the runtime environment and features they were compiled for have changed",
+            ),
+    )
+    .args(&conformance_fixture_args())
+    .group(conformance_fixture_filter_group())
     .arg(
         Arg::with_name("repair_validators")
             .long("repair-validator")
@@ -898,6 +943,18 @@ pub fn add_args<'a>(app: App<'a, 'a>, default_args: &'a DefaultArgs) -> App<'a,
                  See the zstd manpage for more information.",
             ),
     )
+    .arg(
+        Arg::with_name("snapshot_archive_uncompressed_storages")
+            .long("snapshot-archive-uncompressed-storages")
+            .takes_value(false)
+            .help("Store account storages uncompressed in indexed snapshot archives")
+            .long_help(
+                "Store account storages uncompressed in indexed snapshot archives. The archives \
+                 are larger, but their storages are copied out of a mapping of the archive \
+                 instead of being decompressed when the snapshot is unpacked. Only applies to the \
+                 indexed archive format.",
+            ),
+    )
     .arg(
         Arg::with_name("poh_pinned_cpu_core")
             .long("poh-pinned-cpu-core")
@@ -1013,6 +1070,19 @@ pub fn add_args<'a>(app: App<'a, 'a>, default_args: &'a DefaultArgs) -> App<'a,
             .help("The number of ancient storages the ancient slot combining should converge to.")
             .hidden(hidden_unless_forced()),
     )
+    .arg(
+        Arg::with_name("accounts_db_ancient_paths")
+            .long("accounts-db-ancient-path")
+            .value_name("PATH")
+            .takes_value(true)
+            .multiple(true)
+            .help(
+                "Persistent location for ancient account storages, typically on slower disks than \
+                 --accounts. Storages that become ancient are moved here. May be specified \
+                 multiple times.",
+            )
+            .hidden(hidden_unless_forced()),
+    )
     .arg(
         Arg::with_name("accounts_db_write_cache_limit")
             .long("accounts-db-write-cache-limit")
@@ -1045,6 +1115,84 @@ pub fn add_args<'a>(app: App<'a, 'a>, default_args: &'a DefaultArgs) -> App<'a,
             )
             .hidden(hidden_unless_forced()),
     )
+    .arg(
+        Arg::with_name("accounts_db_read_cache_eviction_policy")
+            .long("accounts-db-read-cache-eviction-policy")
+            .value_name("POLICY")
+            .takes_value(true)
+            .possible_values(&["sampled-lru", "s3-fifo", "tiny-lfu"])
+            .help("How the read cache for account data picks the entries to evict")
+            .long_help(
+                "How the read cache for account data picks the entries to evict. 'sampled-lru' \
+                 evicts the least recently used of a random sample of entries. 's3-fifo' evicts \
+                 new entries which are not loaded again soon, before frequently loaded ones. \
+                 'tiny-lfu' only caches accounts which are loaded more often than the entries \
+                 they would displace. [default: sampled-lru]",
+            )
+            .hidden(hidden_unless_forced()),
+    )
+    .arg(
+        Arg::with_name("accounts_db_read_cache_adaptive_min_size")
+            .long("accounts-db-read-cache-adaptive-min-size")
+            .value_name("BYTES")
+            .validator(is_parsable::<ByteSize>)
+            .takes_value(true)
+            .help("Adapt the size of the read cache for account data, down to this size")
+            .long_help(
+                "Adapt the size of the read cache for account data to its hit rate and the \
+                 available memory. The high watermark grows up to its configured value while the \
+                 cache misses, and shrinks down to this size while memory is low. The low \
+                 watermark is scaled along. Accepts SI and IEC prefixes, e.g. 1GB or 1Gi.",
+            )
+            .hidden(hidden_unless_forced()),
+    )
+    .arg(
+        Arg::with_name("accounts_db_file_format")
+            .long("accounts-db-file-format")
+            .value_name("FORMAT")
+            .takes_value(true)
+            .possible_values(&["append-vec", "cold-storage"])
+            .help("The file format of shrunk and ancient account storages")
+            .long_help(
+                "The file format of account storages written by shrink and ancient packing. \
+                 'cold-storage' compresses account data in blocks, trading read latency for disk \
+                 space. Newly flushed slots are always written as 'append-vec', and snapshot \
+                 archives always contain 'append-vec' storages. [default: append-vec]",
+            )
+            .hidden(hidden_unless_forced()),
+    )
+    .arg(
+        Arg::with_name("accounts_db_storage_read_io")
+            .long("accounts-db-storage-read-io")
+            .value_name("METHOD")
+            .takes_value(true)
+            .possible_values(&["sync", "io-uring", "io-uring-direct"])
+            .help("How account storages are read when many accounts are loaded at once")
+            .long_help(
+                "How account storages are read when many accounts are loaded at once, by scans of \
+                 the accounts index such as getProgramAccounts, by getMultipleAccounts and for \
+                 transaction batches. 'sync' reads one account at a time. 'io-uring' submits the \
+                 reads in batches. 'io-uring-direct' also bypasses the OS page cache, which \
+                 requires the file systems hosting the accounts directories to support O_DIRECT, \
+                 and keeps a second file open for every storage it reads. [default: sync]",
+            )
+            .hidden(hidden_unless_forced()),
+    )
+    .arg(
+        Arg::with_name("accounts_db_write_telemetry_sample_rate")
+            .long("accounts-db-write-telemetry-sample-rate")
+            .value_name("RATE")
+            .takes_value(true)
+            .validator(is_parsable::<NonZeroU64>)
+            .help("Track the accounts and programs written the most, sampling one in RATE writes")
+            .long_help(
+                "Track the accounts and owner programs written the most, sampling one in RATE \
+                 accounts stored. The estimated writes are reported to metrics and through the \
+                 hot-accounts command. Lower rates are more accurate, but add overhead to every \
+                 store. [default: disabled]",
+            )
+            .hidden(hidden_unless_forced()),
+    )
     .arg(
         Arg::with_name("no_accounts_db_snapshots_direct_io")
             .long("no-accounts-db-snapshots-direct-io")
@@ -1110,6 +1258,24 @@ pub fn add_args<'a>(app: App<'a, 'a>, default_args: &'a DefaultArgs) -> App<'a,
                  <LEDGER>/accounts_index]",
             ),
     )
+    .arg(
+        Arg::with_name("accounts_db_storage_scan_cache")
+            .long("accounts-db-storage-scan-cache")
+            .help("Cache the result of scanning each account storage alongside local snapshots")
+            .long_help(
+                "Keep a cache of the result of scanning each account storage alongside the bank \
+                 snapshots, updated in the background whenever a snapshot becomes loadable. \
+                 Booting from local state still generates the accounts index, but reads the \
+                 accounts, capitalization and lattice hash of each storage from the cache instead \
+                 of scanning the storage. Storages without a valid cached result are still \
+                 scanned. A cached result is matched to its storage by slot, id, length and a \
+                 hash of the first and last 4 KiB of the storage file, not of the whole file, so \
+                 that checking it does not read the storage. Storage files modified by anything \
+                 but the validator may go unnoticed; remove the storage_scan_cache directory \
+                 after doing so.",
+            )
+            .hidden(hidden_unless_forced()),
+    )
     .arg(
         Arg::with_name("accounts_shrink_optimize_total_space")
             .long("accounts-shrink-optimize-total-space")
@@ -20,6 +20,7 @@ use {
     log::*,
     rand::{rng, seq::SliceRandom},
     solana_accounts_db::{
+        ReadCacheEvictionPolicy, StorageReadIo,
         accounts_db::{AccountShrinkThreshold, AccountsDbConfig},
         accounts_file::AccountsFileProvider,
         accounts_index::{
@@ -33,7 +34,10 @@ use {
             create_and_canonicalize_directory,
         },
     },
-    solana_clap_utils::input_parsers::{keypair_of, keypairs_of, pubkey_of, value_of, values_of},
+    solana_clap_utils::{
+        conformance_fixture::{ConformanceFixtureConfig, parse_conformance_fixture_args},
+        input_parsers::{keypair_of, keypairs_of, pubkey_of, value_of, values_of},
+    },
     solana_clock::{DEFAULT_SLOTS_PER_EPOCH, Slot},
     solana_core::{
         banking_stage::transaction_scheduler::scheduler_controller::SchedulerConfig,
@@ -65,7 +69,11 @@ use {
     solana_net_utils::multihomed_sockets::BindIpAddrs,
     solana_poh::poh_service,
     solana_pubkey::Pubkey,
-    solana_runtime::{runtime_config::RuntimeConfig, snapshot_utils},
+    solana_runtime::{
+        fixture_capture::{FixtureCapture, FixtureCaptureFilter},
+        runtime_config::{RuntimeConfig, TransactionFixtureRecorder},
+        snapshot_utils,
+    },
     solana_signer::Signer,
     solana_streamer::{
         nonblocking::{simple_qos::SimpleQosConfig, swqos::SwQosConfig},
@@ -115,6 +123,7 @@ pub fn execute(
         accounts_db_background_threads,
         accounts_db_foreground_threads,
         accounts_index_flush_threads,
+        accounts_index_generation_threads,
         block_production_num_workers,
         ip_echo_server_threads,
         rayon_global_threads,
@@ -480,6 +489,8 @@ pub fn execute(
         None
     };
 
+    let transaction_fixture_recorder = conformance_fixture_capture(matches)?;
+
     let repair_validators = validators_set(
         &identity_keypair.pubkey(),
         matches,
@@ -701,6 +712,48 @@ pub fn execute(
         })
         .unwrap_or_default();
 
+    let read_cache_eviction_policy = matches
+        .value_of("accounts_db_read_cache_eviction_policy")
+        .map(|policy| match policy {
+            "sampled-lru" => ReadCacheEvictionPolicy::SampledLru,
+            "s3-fifo" => ReadCacheEvictionPolicy::S3Fifo,
+            "tiny-lfu" => ReadCacheEvictionPolicy::TinyLfu,
+            _ => {
+                // clap will enforce one of the above values is given
+                unreachable!("invalid value given to accounts_db_read_cache_eviction_policy")
+            }
+        })
+        .unwrap_or_default();
+    let read_cache_adaptive_min_bytes =
+        value_of::<ByteSize>(matches, "accounts_db_read_cache_adaptive_min_size")
+            .map(|size| usize::try_from(size.0))
+            .transpose()?;
+
+    let accounts_file_provider = matches
+        .value_of("accounts_db_file_format")
+        .map(|format| match format {
+            "append-vec" => AccountsFileProvider::AppendVec,
+            "cold-storage" => AccountsFileProvider::ColdStorage,
+            _ => {
+                // clap will enforce one of the above values is given
+                unreachable!("invalid value given to accounts_db_file_format")
+            }
+        })
+        .unwrap_or_default();
+
+    let storage_read_io = matches
+        .value_of("accounts_db_storage_read_io")
+        .map(|read_io| match read_io {
+            "sync" => StorageReadIo::Sync,
+            "io-uring" => StorageReadIo::IoUring,
+            "io-uring-direct" => StorageReadIo::IoUringDirect,
+            _ => {
+                // clap will enforce one of the above values is given
+                unreachable!("invalid value given to accounts_db_storage_read_io")
+            }
+        })
+        .unwrap_or_default();
+
     let accounts_db_config = AccountsDbConfig {
         index: Some(accounts_index_config),
         account_indexes: Some(account_indexes.clone()),
@@ -709,6 +762,8 @@ pub fn execute(
         read_cache_limit_bytes,
         read_cache_evict_sample_size: None,
         read_cache_num_shards: None,
+        read_cache_eviction_policy,
+        read_cache_adaptive_min_bytes,
         write_cache_limit_bytes,
         ancient_append_vec_offset: value_t!(matches, "accounts_db_ancient_append_vecs", i64).ok(),
         ancient_storage_ideal_size: value_t!(
@@ -718,13 +773,23 @@ pub fn execute(
         )
         .ok(),
         max_ancient_storages: value_t!(matches, "accounts_db_max_ancient_storages", usize).ok(),
+        ancient_storage_paths: Vec::new(),
         skip_initial_hash_calc: false,
         verify_index: matches.is_present("accounts_db_verify_index"),
         partitioned_epoch_rewards_config: PartitionedEpochRewardsConfig::default(),
         scan_filter_for_shrinking,
         num_background_threads: Some(accounts_db_background_threads),
         num_foreground_threads: Some(accounts_db_foreground_threads),
-        accounts_file_provider: AccountsFileProvider::AppendVec,
+        num_index_generation_threads: Some(accounts_index_generation_threads),
+        index_generation_progress_callback: None,
+        accounts_file_provider,
+        storage_read_io,
+        write_telemetry_sample_rate: value_t!(
+            matches,
+            "accounts_db_write_telemetry_sample_rate",
+            NonZeroU64
+        )
+        .ok(),
     };
 
     let on_start_geyser_plugin_config_files = if matches.is_present("geyser_plugin_config") {
@@ -753,10 +818,32 @@ pub fn execute(
     let account_paths = create_and_canonicalize_directories(account_paths)
         .map_err(|err| format!("unable to access account path: {err}"))?;
 
+    let account_ancient_paths: Vec<PathBuf> =
+        values_t!(matches, "accounts_db_ancient_paths", PathBuf).unwrap_or_default();
+    let account_ancient_paths = create_and_canonicalize_directories(account_ancient_paths)
+        .map_err(|err| format!("unable to access accounts ancient path: {err}"))?;
+    if account_ancient_paths
+        .iter()
+        .any(|ancient_path| account_paths.contains(ancient_path))
+    {
+        Err("the --accounts and --accounts-db-ancient-path paths must be unique".to_string())?;
+    }
+    // Ancient paths hold account storages too, so they are set up and walked at startup
+    // along with the account paths.
+    let num_hot_account_paths = account_paths.len();
+    let account_paths: Vec<PathBuf> = account_paths
+        .into_iter()
+        .chain(account_ancient_paths)
+        .collect();
+
     // From now on, use run/ paths in the same way as the previous account_paths.
     let (account_run_paths, account_snapshot_paths) =
         create_all_accounts_run_and_snapshot_dirs(&account_paths)
             .map_err(|err| format!("unable to create account directories: {err}"))?;
+    let accounts_db_config = AccountsDbConfig {
+        ancient_storage_paths: account_run_paths[num_hot_account_paths..].to_vec(),
+        ..accounts_db_config
+    };
 
     let snapshot_config = new_snapshot_config(
         matches,
@@ -799,6 +886,7 @@ pub fn execute(
         runtime_config: RuntimeConfig {
             log_messages_bytes_limit: value_of(matches, "log_messages_bytes_limit"),
             skip_transaction_signatures_in_status_cache,
+            transaction_fixture_recorder,
             ..RuntimeConfig::default()
         },
         rpc_config: run_args.json_rpc_config,
@@ -828,6 +916,9 @@ pub fn execute(
         run_verification: !matches.is_present("skip_startup_ledger_verification"),
         debug_keys,
         filter_keys: Arc::new(run_args.filter_keys),
+        program_cache_prewarm: values_t!(matches, "program_cache_prewarm", Pubkey)
+            .unwrap_or_default(),
+        jit_cache_dir: matches.value_of("jit_cache_dir").map(PathBuf::from),
         warp_slot: None,
         generator_config: None,
         contact_debug_interval,
@@ -853,6 +944,8 @@ pub fn execute(
         accounts_db_force_initial_clean: matches.is_present("no_skip_initial_accounts_db_clean"),
         snapshot_config,
         no_wait_for_vote_to_start_leader: matches.is_present("no_wait_for_vote_to_start_leader"),
+        enable_blockstore_scrub: matches.is_present("enable_blockstore_scrub"),
+        blockstore_scrub_repair: matches.is_present("blockstore_scrub_repair"),
         wait_to_vote_slot: value_t!(matches, "wait_to_vote_slot", Slot).ok(),
         staked_nodes_overrides: staked_nodes_overrides.clone(),
         use_snapshot_archives_at_startup,
@@ -1176,6 +1269,30 @@ fn hardforks_of(matches: &ArgMatches<'_>, name: &str) -> Option<Vec<Slot>> {
     }
 }
 
+fn conformance_fixture_capture(
+    matches: &ArgMatches<'_>,
+) -> Result<Option<Arc<dyn TransactionFixtureRecorder>>, String> {
+    let Some(ConformanceFixtureConfig {
+        output_dir,
+        program_ids,
+        signatures,
+    }) = parse_conformance_fixture_args(matches)
+    else {
+        return Ok(None);
+    };
+    let filter = FixtureCaptureFilter {
+        program_ids: program_ids.into_iter().collect(),
+        signatures: signatures.into_iter().collect(),
+    };
+    let fixture_capture = FixtureCapture::new(output_dir.clone(), filter).map_err(|err| {
+        format!(
+            "unable to create conformance fixture directory {}: {err}",
+            output_dir.display()
+        )
+    })?;
+    Ok(Some(Arc::new(fixture_capture)))
+}
+
 fn validators_set(
     identity_pubkey: &Pubkey,
     matches: &ArgMatches<'_>,
@@ -1340,9 +1457,15 @@ fn new_snapshot_config(
         let archive_format_str = value_t_or_exit!(matches, "snapshot_archive_format", String);
         let mut archive_format = ArchiveFormat::from_cli_arg(&archive_format_str)
             .unwrap_or_else(|| panic!("Archive format not recognized: {archive_format_str}"));
-        if let ArchiveFormat::TarZstd { config } = &mut archive_format {
-            config.compression_level =
-                value_t_or_exit!(matches, "snapshot_zstd_compression_level", i32);
+        let compression_level = value_t_or_exit!(matches, "snapshot_zstd_compression_level", i32);
+        match &mut archive_format {
+            ArchiveFormat::TarZstd { config } => config.compression_level = compression_level,
+            ArchiveFormat::Indexed { config } => {
+                config.compression_level = compression_level;
+                config.compress_storages =
+                    !matches.is_present("snapshot_archive_uncompressed_storages");
+            }
+            ArchiveFormat::TarLz4 => {}
         }
         archive_format
     };
@@ -1384,6 +1507,7 @@ fn new_snapshot_config(
             solana_accounts_db::accounts_db::TOTAL_IO_URING_BUFFERS_SIZE_LIMIT,
         ),
         use_direct_io: !matches.is_present("no_accounts_db_snapshots_direct_io"),
+        use_storage_scan_cache: matches.is_present("accounts_db_storage_scan_cache"),
     };
 
     if !is_snapshot_config_valid(&snapshot_config) {
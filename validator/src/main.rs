@@ -117,6 +117,12 @@ pub fn main() {
         ("repair-shred-from-peer", Some(subcommand_matches)) => {
             commands::repair_shred_from_peer::execute(subcommand_matches, &ledger_path)
         }
+        ("hot-accounts", Some(subcommand_matches)) => {
+            commands::hot_accounts::execute(subcommand_matches, &ledger_path)
+        }
+        ("program-cache", Some(subcommand_matches)) => {
+            commands::program_cache::execute(subcommand_matches, &ledger_path)
+        }
         ("repair-whitelist", Some(repair_whitelist_subcommand_matches)) => {
             commands::repair_whitelist::execute(repair_whitelist_subcommand_matches, &ledger_path)
         }